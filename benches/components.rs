@@ -0,0 +1,143 @@
+//! Criterion benchmarks for the generation and scan hot paths:
+//! `cargo bench --bench components`.
+//!
+//! - `synthesis`: rows of a mixed table through the [`Synthesizer`].
+//! - `copy_format`: numeric, timestamp and text cells written as COPY text.
+//! - `reservoir`: values offered to a full reservoir sample.
+//! - `copula`: correlated uniforms drawn from a Gaussian copula.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use replica_db::copula::{CorrelationMethod, CovarianceMatrix, GaussianCopula};
+use replica_db::math::{BinScale, Distribution, Histogram, Reservoir, DEFAULT_RESERVOIR_CAPACITY};
+use replica_db::schema::{Column, DataType, Table};
+use replica_db::synth::{write_value, Value};
+use replica_db::{DatabaseGenome, SynthesisConfig, Synthesizer};
+
+const ROWS: usize = 100_000;
+
+fn genome() -> DatabaseGenome {
+    let orders = Table::new(
+        "orders".to_string(),
+        vec![
+            Column::new("id".to_string(), DataType::Integer, false, true),
+            Column::new("amount".to_string(), DataType::Float, true, false),
+            Column::new("status".to_string(), DataType::Text, true, false),
+            Column::new("created_at".to_string(), DataType::Timestamp, true, false),
+        ],
+        vec![],
+    );
+
+    let numeric = |min: f64, step: f64| {
+        Distribution::new(Some(min), Some(min + 100.0 * step), 10, 1000, 1000, Histogram::Numeric {
+            bins: (0..=100).map(|i| min + i as f64 * step).collect(),
+            frequencies: vec![10; 100],
+            scale: BinScale::Linear,
+        })
+    };
+    let statuses: BTreeMap<String, u64> = ["new", "paid", "shipped", "cancelled"]
+        .iter()
+        .map(|s| (s.to_string(), 25))
+        .collect();
+
+    let mut distributions = HashMap::new();
+    distributions.insert("orders.amount".to_string(), numeric(0.0, 10.0));
+    distributions.insert(
+        "orders.status".to_string(),
+        Distribution::new(None, None, 0, 100, 4, Histogram::Categorical {
+            frequencies: statuses,
+            truncated: false,
+            long_tail: None,
+        }),
+    );
+    distributions.insert("orders.created_at".to_string(), numeric(1.6e9, 86_400.0));
+
+    DatabaseGenome::new(vec![orders], distributions)
+}
+
+fn synthesis(c: &mut Criterion) {
+    let config = SynthesisConfig {
+        rows_per_table: ROWS,
+        seed: Some(1),
+        ..Default::default()
+    };
+    let synthesizer = Synthesizer::new(genome(), config).expect("valid genome");
+
+    let mut group = c.benchmark_group("synthesis");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(ROWS as u64));
+    group.bench_function("mixed_table", |b| b.iter(|| black_box(synthesizer.generate().unwrap())));
+    group.finish();
+}
+
+fn copy_format(c: &mut Criterion) {
+    let cells = [
+        (Value::Number(1234.5678), DataType::Float),
+        (Value::Number(42.0), DataType::Integer),
+        (Value::Number(1.7e9 + 0.25), DataType::Timestamp),
+        (Value::Text(Cow::Borrowed("shipped")), DataType::Text),
+        (Value::Null, DataType::Text),
+    ];
+
+    let mut group = c.benchmark_group("copy_format");
+    group.throughput(Throughput::Elements(cells.len() as u64));
+    group.bench_function("cells", |b| {
+        let mut out = Vec::with_capacity(256);
+        b.iter(|| {
+            out.clear();
+            for (value, data_type) in &cells {
+                write_value(&mut out, value, data_type);
+                out.push(b'\t');
+            }
+            black_box(out.len())
+        })
+    });
+    group.finish();
+}
+
+fn reservoir(c: &mut Criterion) {
+    let values: Vec<f64> = (0..ROWS).map(|i| i as f64 * 0.5).collect();
+
+    let mut group = c.benchmark_group("reservoir");
+    group.throughput(Throughput::Elements(ROWS as u64));
+    group.bench_function("add_f64", |b| {
+        b.iter_batched(
+            || Reservoir::new(DEFAULT_RESERVOIR_CAPACITY),
+            |mut reservoir| {
+                for &value in &values {
+                    reservoir.add(value);
+                }
+                black_box(reservoir.sample_size())
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn copula(c: &mut Criterion) {
+    let dimension = 8;
+    let matrix_data = (0..dimension * dimension)
+        .map(|i| if i / dimension == i % dimension { 1.0 } else { 0.3 })
+        .collect();
+    let covariance = CovarianceMatrix {
+        columns: (0..dimension).map(|i| format!("c{}", i)).collect(),
+        matrix_data,
+        dimension,
+        method: CorrelationMethod::default(),
+        pseudo_observations: None,
+    };
+    let copula = GaussianCopula::new(&covariance).expect("positive definite");
+    let mut rng = StdRng::seed_from_u64(1);
+
+    let mut group = c.benchmark_group("copula");
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("uniforms_8d", |b| b.iter(|| black_box(copula.generate_correlated_uniforms(&mut rng))));
+    group.finish();
+}
+
+criterion_group!(benches, synthesis, copy_format, reservoir, copula);
+criterion_main!(benches);
@@ -0,0 +1,73 @@
+//! Row generation throughput: `cargo bench --bench generate`.
+//!
+//! One table with an integer key, numeric, categorical and timestamp columns, generated
+//! single-threaded so the figure reflects the row emitter rather than the thread count.
+
+use std::collections::{BTreeMap, HashMap};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use replica_db::math::{BinScale, Distribution, Histogram};
+use replica_db::schema::{Column, DataType, Table};
+use replica_db::{DatabaseGenome, SynthesisConfig, Synthesizer};
+
+const ROWS: usize = 1_000_000;
+
+fn numeric(min: f64, step: f64) -> Distribution {
+    let bins: Vec<f64> = (0..=100).map(|i| min + i as f64 * step).collect();
+    Distribution::new(Some(min), Some(min + 100.0 * step), 10, 1000, 1000, Histogram::Numeric {
+        bins,
+        frequencies: vec![10; 100],
+        scale: BinScale::Linear,
+    })
+}
+
+fn genome() -> DatabaseGenome {
+    let orders = Table::new(
+        "orders".to_string(),
+        vec![
+            Column::new("id".to_string(), DataType::Integer, false, true),
+            Column::new("amount".to_string(), DataType::Float, true, false),
+            Column::new("quantity".to_string(), DataType::Integer, true, false),
+            Column::new("status".to_string(), DataType::Text, true, false),
+            Column::new("created_at".to_string(), DataType::Timestamp, true, false),
+        ],
+        vec![],
+    );
+
+    let statuses: BTreeMap<String, u64> = ["new", "paid", "shipped", "cancelled"]
+        .iter()
+        .map(|s| (s.to_string(), 25))
+        .collect();
+
+    let mut distributions = HashMap::new();
+    distributions.insert("orders.amount".to_string(), numeric(0.0, 10.0));
+    distributions.insert("orders.quantity".to_string(), numeric(0.0, 1.0));
+    distributions.insert(
+        "orders.status".to_string(),
+        Distribution::new(None, None, 0, 100, 4, Histogram::Categorical {
+            frequencies: statuses,
+            truncated: false,
+            long_tail: None,
+        }),
+    );
+    distributions.insert("orders.created_at".to_string(), numeric(1.6e9, 86_400.0));
+
+    DatabaseGenome::new(vec![orders], distributions)
+}
+
+fn generate(c: &mut Criterion) {
+    let config = SynthesisConfig {
+        rows_per_table: ROWS,
+        seed: Some(1),
+        ..Default::default()
+    };
+    let synthesizer = Synthesizer::new(genome(), config).expect("valid genome");
+
+    let mut group = c.benchmark_group("generate");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(ROWS as u64));
+    group.bench_function("orders_1m_rows", |b| b.iter(|| black_box(synthesizer.generate().unwrap())));
+    group.finish();
+}
+
+criterion_group!(benches, generate);
+criterion_main!(benches);
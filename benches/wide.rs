@@ -0,0 +1,80 @@
+//! Wide-table generation throughput: `cargo bench --bench wide`.
+//!
+//! One table with many high-cardinality categorical and finely binned numeric columns,
+//! where picking a category or bin dominates the cost of a row.
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::Instant;
+use replica_db::math::{BinScale, Distribution, Histogram};
+use replica_db::schema::{Column, DataType, Table};
+use replica_db::{DatabaseGenome, SynthesisConfig, Synthesizer};
+
+const ROWS: usize = 100_000;
+const RUNS: usize = 5;
+const COLUMNS: usize = 100;
+const CATEGORIES: usize = 1000;
+const BINS: usize = 500;
+
+fn categorical() -> Distribution {
+    let frequencies: BTreeMap<String, u64> = (0..CATEGORIES)
+        .map(|i| (format!("value_{:04}", i), (i % 17 + 1) as u64))
+        .collect();
+    Distribution::new(None, None, 0, 10_000, CATEGORIES, Histogram::Categorical {
+        frequencies,
+        truncated: false,
+        long_tail: None,
+    })
+}
+
+fn numeric() -> Distribution {
+    let bins: Vec<f64> = (0..=BINS).map(|i| i as f64).collect();
+    Distribution::new(Some(0.0), Some(BINS as f64), 0, 10_000, 10_000, Histogram::Numeric {
+        bins,
+        frequencies: (0..BINS).map(|i| (i % 13 + 1) as u64).collect(),
+        scale: BinScale::Linear,
+    })
+}
+
+fn genome() -> DatabaseGenome {
+    let mut columns = vec![Column::new("id".to_string(), DataType::Integer, false, true)];
+    let mut distributions = HashMap::new();
+    for i in 0..COLUMNS {
+        let (name, data_type, dist) = if i % 2 == 0 {
+            (format!("label_{}", i), DataType::Text, categorical())
+        } else {
+            (format!("measure_{}", i), DataType::Float, numeric())
+        };
+        distributions.insert(DatabaseGenome::make_key("wide", &name), dist);
+        columns.push(Column::new(name, data_type, false, false));
+    }
+
+    DatabaseGenome::new(vec![Table::new("wide".to_string(), columns, vec![])], distributions)
+}
+
+fn main() -> anyhow::Result<()> {
+    let config = SynthesisConfig {
+        rows_per_table: ROWS,
+        seed: Some(1),
+        ..Default::default()
+    };
+    let synthesizer = Synthesizer::new(genome(), config)?;
+
+    let mut timings = Vec::with_capacity(RUNS);
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        let result = synthesizer.generate()?;
+        timings.push(start.elapsed());
+        std::hint::black_box(result);
+    }
+
+    timings.sort();
+    let median = timings[RUNS / 2];
+    println!(
+        "generate {} rows x {} columns: median {:?}, {:.0} cells/s",
+        ROWS,
+        COLUMNS,
+        median,
+        (ROWS * COLUMNS) as f64 / median.as_secs_f64()
+    );
+    Ok(())
+}
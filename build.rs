@@ -0,0 +1,17 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    compile_protos();
+}
+
+#[cfg(feature = "grpc")]
+fn compile_protos() {
+    println!("cargo:rerun-if-changed=proto/replica_db.proto");
+
+    let protoc = protoc_bin_vendored::protoc_bin_path().expect("No vendored protoc for this platform");
+    let mut config = tonic_prost_build::Config::new();
+    config.protoc_executable(protoc);
+
+    tonic_prost_build::configure()
+        .compile_with_config(config, &["proto/replica_db.proto"], &["proto"])
+        .expect("Failed to compile proto/replica_db.proto");
+}
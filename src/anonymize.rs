@@ -0,0 +1,244 @@
+//! Format-preserving anonymization for identifiers such as phone numbers or account IDs.
+//!
+//! After an optional kept prefix, every ASCII digit, uppercase and lowercase letter is
+//! replaced by a random character of the same class. Length, separators, case and the
+//! prefix survive, so generated values still pass format checks. The transform runs at
+//! scan time on the stored values, or at generation on every generated value.
+
+use std::collections::{BTreeMap, HashMap};
+use anyhow::{bail, Context, Result};
+use rand::Rng;
+use serde::Deserialize;
+use tracing::{debug, info};
+use crate::genome::DatabaseGenome;
+use crate::math::{Distribution, Histogram};
+use crate::schema::DataType;
+
+/// When a column's values are anonymized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnonymizeStage {
+    /// Before values are stored in the genome; the genome never holds the originals.
+    #[default]
+    Scan,
+    /// On each generated value; the genome keeps the originals.
+    Generate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FormatPreserving {
+    /// Leading characters kept as they are, e.g. a country code or a `ACC-` prefix.
+    #[serde(default)]
+    pub keep_prefix: usize,
+
+    #[serde(default)]
+    pub stage: AnonymizeStage,
+}
+
+impl FormatPreserving {
+    pub fn transform<R: Rng + ?Sized>(&self, value: &str, rng: &mut R) -> String {
+        value
+            .chars()
+            .enumerate()
+            .map(|(i, c)| match c {
+                _ if i < self.keep_prefix => c,
+                '0'..='9' => rng.gen_range(b'0'..=b'9') as char,
+                'A'..='Z' => rng.gen_range(b'A'..=b'Z') as char,
+                'a'..='z' => rng.gen_range(b'a'..=b'z') as char,
+                _ => c,
+            })
+            .collect()
+    }
+}
+
+/// Checks that every `table.column` in `columns` is a text column of `genome` that
+/// isn't a key.
+pub fn validate_columns<'a>(
+    genome: &DatabaseGenome,
+    columns: impl IntoIterator<Item = &'a String>,
+) -> Result<()> {
+    let mut keys: Vec<&String> = columns.into_iter().collect();
+    keys.sort();
+
+    for key in keys {
+        let (table_name, column_name) = key
+            .split_once('.')
+            .context(format!("Anonymized column '{}' must be table.column", key))?;
+        let table = genome
+            .get_table(table_name)
+            .context(format!("Anonymized column '{}': table not found in genome", key))?;
+        let column = table
+            .columns
+            .iter()
+            .find(|c| c.name == column_name)
+            .context(format!("Anonymized column '{}': column not found in genome", key))?;
+        if column.is_primary_key || table.foreign_keys.iter().any(|fk| fk.source_col == column.name) {
+            bail!("Anonymized column '{}' is a key column", key);
+        }
+        if column.data_type != DataType::Text {
+            bail!("Anonymized column '{}' is {}, only text columns can be anonymized", key, column.data_type);
+        }
+    }
+    Ok(())
+}
+
+/// Anonymizes the stored values of the scan-stage columns in `columns`, keyed
+/// `table.column`: categorical histograms, the column's cross-table conditionals, and
+/// the groups of conditionals keyed by it. Each distinct value gets one replacement,
+/// so counts and joins between those places are kept. Returns the number of
+/// distributions rewritten.
+pub fn anonymize_genome<R: Rng + ?Sized>(
+    genome: &mut DatabaseGenome,
+    columns: &HashMap<String, FormatPreserving>,
+    rng: &mut R,
+) -> Result<usize> {
+    let scan_columns: HashMap<&String, &FormatPreserving> = columns
+        .iter()
+        .filter(|(_, transform)| transform.stage == AnonymizeStage::Scan)
+        .collect();
+    if scan_columns.is_empty() {
+        return Ok(0);
+    }
+    validate_columns(genome, scan_columns.keys().copied())?;
+
+    let mut rewritten = 0;
+    for (key, transform) in scan_columns {
+        let mut replacements = HashMap::new();
+        let mut replace = |value: String| {
+            replacements
+                .entry(value)
+                .or_insert_with_key(|value| transform.transform(value, rng))
+                .clone()
+        };
+
+        if let Some(dist) = genome.distributions.get_mut(key) {
+            if anonymize_distribution(dist, &mut replace) {
+                rewritten += 1;
+            } else {
+                debug!(column = %key, "No stored values to anonymize");
+            }
+        }
+        for (child, conditional) in genome.conditionals.iter_mut() {
+            if child == key {
+                for dist in conditional.by_parent_value.values_mut() {
+                    anonymize_distribution(dist, &mut replace);
+                }
+            }
+            if DatabaseGenome::make_key(&conditional.parent_table, &conditional.parent_column) == *key {
+                conditional.by_parent_value = std::mem::take(&mut conditional.by_parent_value)
+                    .into_iter()
+                    .map(|(value, dist)| (replace(value), dist))
+                    .collect();
+            }
+        }
+    }
+
+    info!(columns = rewritten, "Anonymized identifier columns");
+    Ok(rewritten)
+}
+
+/// Replaces the values of a categorical histogram, summing counts when two values get
+/// the same replacement.
+fn anonymize_distribution(dist: &mut Distribution, replace: &mut impl FnMut(String) -> String) -> bool {
+    let Histogram::Categorical { frequencies, .. } = &mut dist.histogram else {
+        return false;
+    };
+    let mut replaced = BTreeMap::new();
+    for (value, count) in std::mem::take(frequencies) {
+        *replaced.entry(replace(value)).or_insert(0) += count;
+    }
+    *frequencies = replaced;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use crate::conditional::ConditionalDistribution;
+    use crate::schema::{Column, Table};
+
+    fn categorical(counts: &[(&str, u64)]) -> Distribution {
+        let frequencies = counts.iter().map(|(k, v)| (k.to_string(), *v)).collect();
+        let total = counts.iter().map(|(_, v)| v).sum();
+        Distribution::new(None, None, 0, total, counts.len(), Histogram::Categorical {
+            frequencies,
+            truncated: false,
+            long_tail: None,
+        })
+    }
+
+    fn values(dist: &Distribution) -> Vec<(String, u64)> {
+        let Histogram::Categorical { frequencies, .. } = &dist.histogram else {
+            panic!("expected categorical histogram");
+        };
+        frequencies.iter().map(|(k, v)| (k.clone(), *v)).collect()
+    }
+
+    #[test]
+    fn test_transform_preserves_format() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let transform = FormatPreserving { keep_prefix: 3, stage: AnonymizeStage::Generate };
+
+        for _ in 0..20 {
+            let value = transform.transform("+44 20-7946 abC9", &mut rng);
+            assert_eq!(value.len(), 16);
+            assert!(value.starts_with("+44 "));
+            let classes = |s: &str| -> Vec<u8> {
+                s.chars()
+                    .map(|c| match c {
+                        '0'..='9' => 0,
+                        'a'..='z' => 1,
+                        'A'..='Z' => 2,
+                        _ => c as u8,
+                    })
+                    .collect()
+            };
+            assert_eq!(classes(&value), classes("+44 20-7946 abC9"), "{}", value);
+        }
+        assert_ne!(transform.transform("ACC-123456789", &mut rng), "ACC-123456789");
+    }
+
+    #[test]
+    fn test_anonymize_genome_keeps_counts_and_groups() -> Result<()> {
+        let accounts = Table::new(
+            "accounts".to_string(),
+            vec![
+                Column::new("id".to_string(), DataType::Integer, false, true),
+                Column::new("number".to_string(), DataType::Text, false, false),
+            ],
+            vec![],
+        );
+        let mut distributions = HashMap::new();
+        distributions.insert("accounts.number".to_string(), categorical(&[("AB-1234", 3), ("CD-5678", 1)]));
+        let mut genome = DatabaseGenome::new(vec![accounts], distributions);
+        genome.conditionals.insert("payments.amount".to_string(), ConditionalDistribution {
+            foreign_key: "account_id".to_string(),
+            parent_table: "accounts".to_string(),
+            parent_column: "number".to_string(),
+            by_parent_value: HashMap::from([("AB-1234".to_string(), categorical(&[("10", 1)]))]),
+        });
+
+        let columns = HashMap::from([(
+            "accounts.number".to_string(),
+            FormatPreserving { keep_prefix: 3, stage: AnonymizeStage::Scan },
+        )]);
+        assert_eq!(anonymize_genome(&mut genome, &columns, &mut StdRng::seed_from_u64(1))?, 1);
+
+        let stored = values(&genome.distributions["accounts.number"]);
+        let counts: Vec<u64> = stored.iter().map(|(_, count)| *count).collect();
+        assert_eq!(counts.iter().sum::<u64>(), 4);
+        assert!(stored.iter().all(|(value, _)| value != "AB-1234" && value != "CD-5678"));
+        let (replacement, _) = stored.iter().find(|(_, count)| *count == 3).unwrap();
+        assert!(replacement.starts_with("AB-"));
+        assert!(genome.conditionals["payments.amount"].for_parent_value(replacement).is_some());
+
+        let key = HashMap::from([("accounts.id".to_string(), FormatPreserving::default())]);
+        assert!(anonymize_genome(&mut genome, &key, &mut StdRng::seed_from_u64(1)).is_err());
+        let missing = HashMap::from([("accounts.iban".to_string(), FormatPreserving::default())]);
+        assert!(anonymize_genome(&mut genome, &missing, &mut StdRng::seed_from_u64(1)).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,303 @@
+//! Generated tables as Arrow [`RecordBatch`]es (`arrow` feature).
+//!
+//! Columns are typed from the genome, so consumers such as Polars or Spark get
+//! integers, floats, booleans and timestamps without parsing any text:
+//!
+//! | genome type | Arrow type |
+//! |-------------|------------|
+//! | Integer     | Int64 |
+//! | Float       | Float64 |
+//! | Boolean     | Boolean |
+//! | Timestamp   | Timestamp(Microsecond, "UTC") |
+//! | Text, Uuid  | Utf8 |
+
+use std::sync::Arc;
+use anyhow::{bail, Context, Result};
+use arrow_array::builder::{BinaryBuilder, BooleanBuilder, Float64Builder, Int64Builder, StringBuilder, TimestampMicrosecondBuilder};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType as ArrowType, Field, Schema, SchemaRef, TimeUnit};
+use crate::output::parse_copy_line;
+use crate::schema::{DataType, Table};
+use crate::synth::{GenerationResult, Synthesizer};
+
+/// Rows per batch used by [`record_batches`].
+pub const DEFAULT_BATCH_ROWS: usize = 64 * 1024;
+
+/// Timezone of timestamp columns; generated timestamps are always UTC.
+const TIMEZONE: &str = "UTC";
+
+/// Arrow type a genome column is exported as.
+pub fn arrow_type(data_type: &DataType) -> ArrowType {
+    match data_type {
+        DataType::Integer => ArrowType::Int64,
+        DataType::Float => ArrowType::Float64,
+        DataType::Boolean => ArrowType::Boolean,
+        DataType::Timestamp => ArrowType::Timestamp(TimeUnit::Microsecond, Some(TIMEZONE.into())),
+        DataType::Text | DataType::Uuid | DataType::Inet | DataType::Cidr | DataType::MacAddr => ArrowType::Utf8,
+        // As the literals and EWKT generation writes
+        DataType::Interval | DataType::Geometry | DataType::Geography | DataType::Hstore => ArrowType::Utf8,
+        DataType::Bytea => ArrowType::Binary,
+    }
+}
+
+/// Schema of `table` as the synthesizer writes it, leaving out columns it doesn't
+/// output. Every field is nullable, since a genome edit can add NULLs to any column.
+pub fn table_schema(synthesizer: &Synthesizer, table: &Table) -> SchemaRef {
+    let fields: Vec<Field> = synthesizer
+        .output_columns(table)
+        .iter()
+        .map(|c| Field::new(&c.name, arrow_type(&c.data_type), true))
+        .collect();
+    Arc::new(Schema::new(fields))
+}
+
+/// The generated rows of `table_name` in batches of at most `batch_rows` rows.
+pub fn table_record_batches(
+    synthesizer: &Synthesizer,
+    result: &GenerationResult,
+    table_name: &str,
+    batch_rows: usize,
+) -> Result<Vec<RecordBatch>> {
+    if batch_rows == 0 {
+        bail!("Batch size must be at least 1");
+    }
+
+    let table = synthesizer
+        .genome()
+        .get_table(table_name)
+        .context(format!("Table '{}' not found in genome", table_name))?;
+    let data = result
+        .get_copy_data(table_name)
+        .context(format!("No generated data for table '{}'", table_name))?;
+
+    let schema = table_schema(synthesizer, table);
+    let columns = synthesizer.output_columns(table);
+    let lines: Vec<&str> = data.lines().collect();
+
+    lines
+        .chunks(batch_rows)
+        .map(|chunk| {
+            let mut builders: Vec<ColumnBuilder> =
+                columns.iter().map(|c| ColumnBuilder::new(&c.data_type, chunk.len())).collect();
+            for line in chunk {
+                let values = parse_copy_line(line);
+                if values.len() != builders.len() {
+                    bail!("Row of {} has {} values, expected {}", table_name, values.len(), builders.len());
+                }
+                for ((builder, column), value) in builders.iter_mut().zip(&columns).zip(values) {
+                    builder
+                        .append(value.as_deref())
+                        .context(format!("Bad value in {}.{}", table_name, column.name))?;
+                }
+            }
+            let arrays: Vec<ArrayRef> = builders.iter_mut().map(ColumnBuilder::finish).collect();
+            RecordBatch::try_new(schema.clone(), arrays).context(format!("Failed to build batch for {}", table_name))
+        })
+        .collect()
+}
+
+/// Every generated table in execution order, each in batches of
+/// [`DEFAULT_BATCH_ROWS`] rows.
+pub fn record_batches(synthesizer: &Synthesizer, result: &GenerationResult) -> Result<Vec<(String, Vec<RecordBatch>)>> {
+    synthesizer
+        .execution_order()
+        .iter()
+        .filter(|name| result.get_table_data(name).is_some())
+        .map(|name| Ok((name.clone(), table_record_batches(synthesizer, result, name, DEFAULT_BATCH_ROWS)?)))
+        .collect()
+}
+
+enum ColumnBuilder {
+    Integer(Int64Builder),
+    Float(Float64Builder),
+    Boolean(BooleanBuilder),
+    Timestamp(TimestampMicrosecondBuilder),
+    Text(StringBuilder),
+    Binary(BinaryBuilder),
+}
+
+impl ColumnBuilder {
+    fn new(data_type: &DataType, capacity: usize) -> Self {
+        match data_type {
+            DataType::Integer => ColumnBuilder::Integer(Int64Builder::with_capacity(capacity)),
+            DataType::Float => ColumnBuilder::Float(Float64Builder::with_capacity(capacity)),
+            DataType::Boolean => ColumnBuilder::Boolean(BooleanBuilder::with_capacity(capacity)),
+            DataType::Timestamp => ColumnBuilder::Timestamp(
+                TimestampMicrosecondBuilder::with_capacity(capacity).with_timezone(TIMEZONE),
+            ),
+            DataType::Text
+            | DataType::Uuid
+            | DataType::Inet
+            | DataType::Cidr
+            | DataType::MacAddr
+            | DataType::Interval
+            | DataType::Geometry
+            | DataType::Geography
+            | DataType::Hstore => {
+                ColumnBuilder::Text(StringBuilder::with_capacity(capacity, capacity * 16))
+            }
+            DataType::Bytea => ColumnBuilder::Binary(BinaryBuilder::with_capacity(capacity, capacity * 16)),
+        }
+    }
+
+    /// Appends a value in COPY text form; `None` is NULL.
+    fn append(&mut self, value: Option<&str>) -> Result<()> {
+        match self {
+            ColumnBuilder::Integer(builder) => builder.append_option(value.map(parse_integer).transpose()?),
+            ColumnBuilder::Float(builder) => builder.append_option(
+                value
+                    .map(|v| v.parse::<f64>().context(format!("Invalid float '{}'", v)))
+                    .transpose()?,
+            ),
+            ColumnBuilder::Boolean(builder) => builder.append_option(value.map(parse_boolean).transpose()?),
+            ColumnBuilder::Timestamp(builder) => builder.append_option(value.map(parse_timestamp_micros).transpose()?),
+            ColumnBuilder::Text(builder) => builder.append_option(value),
+            ColumnBuilder::Binary(builder) => builder.append_option(value.map(parse_bytea).transpose()?),
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            ColumnBuilder::Integer(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Float(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Boolean(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Timestamp(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Text(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Binary(builder) => Arc::new(builder.finish()),
+        }
+    }
+}
+
+fn parse_integer(raw: &str) -> Result<i64> {
+    raw.parse::<i64>().context(format!("Invalid integer '{}'", raw))
+}
+
+/// Bytes of a generated bytea value, which is always in hex form.
+fn parse_bytea(raw: &str) -> Result<Vec<u8>> {
+    let hex = raw.strip_prefix("\\x").context(format!("Invalid bytea '{}'", raw))?;
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .context(format!("Invalid bytea '{}'", raw))
+        })
+        .collect()
+}
+
+fn parse_boolean(raw: &str) -> Result<bool> {
+    match raw {
+        "t" | "true" => Ok(true),
+        "f" | "false" => Ok(false),
+        other => bail!("Invalid boolean '{}'", other),
+    }
+}
+
+/// Microseconds since the epoch of a generated timestamp literal, or of raw epoch
+/// seconds, which the generator falls back to outside chrono's range.
+fn parse_timestamp_micros(raw: &str) -> Result<i64> {
+    if let Ok(ts) = chrono::DateTime::parse_from_str(raw, "%Y-%m-%d %H:%M:%S%.f%:z") {
+        return Ok(ts.timestamp_micros());
+    }
+    let seconds: f64 = raw.parse().context(format!("Invalid timestamp '{}'", raw))?;
+    Ok((seconds * 1e6).round() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Float64Type, Int64Type, TimestampMicrosecondType};
+    use arrow_array::Array;
+    use crate::genome::DatabaseGenome;
+    use crate::math::{Bernoulli, BinScale, Distribution, Histogram};
+    use crate::schema::Column;
+    use crate::synth::SynthesisConfig;
+
+    fn synthesizer(rows: usize) -> Result<Synthesizer> {
+        let tables = vec![Table::new(
+            "events".to_string(),
+            vec![
+                Column::new("id".to_string(), DataType::Integer, false, true),
+                Column::new("score".to_string(), DataType::Float, false, false),
+                Column::new("active".to_string(), DataType::Boolean, false, false),
+                Column::new("at".to_string(), DataType::Timestamp, false, false),
+                Column::new("note".to_string(), DataType::Text, true, false),
+            ],
+            vec![],
+        )];
+
+        let mut distributions = HashMap::new();
+        distributions.insert(
+            DatabaseGenome::make_key("events", "score"),
+            Distribution::new(Some(1.0), Some(2.0), 0, 10, 10, Histogram::Numeric {
+                bins: vec![1.0, 2.0],
+                frequencies: vec![10],
+                scale: BinScale::Linear,
+            }),
+        );
+        distributions.insert(
+            DatabaseGenome::make_key("events", "active"),
+            Distribution::new(None, None, 0, 10, 1, Histogram::Boolean {
+                bernoulli: Bernoulli { true_count: 10, false_count: 0 },
+            }),
+        );
+        distributions.insert(
+            DatabaseGenome::make_key("events", "at"),
+            Distribution::new(Some(1.7e9), Some(1.7e9 + 60.0), 0, 10, 10, Histogram::Numeric {
+                bins: vec![1.7e9, 1.7e9 + 60.0],
+                frequencies: vec![10],
+                scale: BinScale::Linear,
+            }),
+        );
+        distributions.insert(
+            DatabaseGenome::make_key("events", "note"),
+            Distribution::new(None, None, 10, 10, 0, Histogram::Categorical {
+                frequencies: Default::default(),
+                truncated: false,
+                long_tail: None,
+            }),
+        );
+
+        let config = SynthesisConfig { rows_per_table: rows, seed: Some(1), ..SynthesisConfig::default() };
+        Synthesizer::new(DatabaseGenome::new(tables, distributions), config)
+    }
+
+    #[test]
+    fn test_table_record_batches() -> Result<()> {
+        let synthesizer = synthesizer(5)?;
+        let result = synthesizer.generate()?;
+        let batches = table_record_batches(&synthesizer, &result, "events", 2)?;
+
+        assert_eq!(batches.iter().map(RecordBatch::num_rows).collect::<Vec<_>>(), vec![2, 2, 1]);
+        let schema = batches[0].schema();
+        let types: Vec<&ArrowType> = schema.fields().iter().map(|f| f.data_type()).collect();
+        assert_eq!(types[0], &ArrowType::Int64);
+        assert_eq!(types[3], &ArrowType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())));
+
+        let batch = &batches[1];
+        assert_eq!(batch.column(0).as_primitive::<Int64Type>().values(), &[3, 4]);
+        assert!((1.0..=2.0).contains(&batch.column(1).as_primitive::<Float64Type>().value(0)));
+        assert!(batch.column(2).as_boolean().value(0));
+        let at = batch.column(3).as_primitive::<TimestampMicrosecondType>().value(0);
+        assert!((1_700_000_000_000_000..=1_700_000_060_000_000).contains(&at));
+        assert_eq!(batch.column(4).null_count(), 2);
+
+        assert!(table_record_batches(&synthesizer, &result, "events", 0).is_err());
+        assert_eq!(record_batches(&synthesizer, &result)?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_values() {
+        assert_eq!(parse_timestamp_micros("2023-11-14 22:13:20.5+00:00").unwrap(), 1_699_999_999_999_999 + 500_001);
+        assert_eq!(parse_timestamp_micros("1700000000").unwrap(), 1_700_000_000_000_000);
+        assert!(parse_timestamp_micros("yesterday").is_err());
+        assert!(parse_boolean("t").unwrap());
+        assert!(parse_integer("1.5").is_err());
+        assert_eq!(parse_bytea("\\xdead00").unwrap(), vec![0xde, 0xad, 0x00]);
+        assert!(parse_bytea("\\xabc").is_err());
+    }
+}
@@ -0,0 +1,422 @@
+//! k-anonymity audit of a genome (`replica_db audit`).
+//!
+//! For a set of quasi-identifiers (columns that could be linked to outside data, like a
+//! ZIP code or a birth year) the audit estimates from the stored histograms which rows
+//! fall into groups of fewer than `k` rows: rare categories of single columns, and rare
+//! combinations across the columns of one table. Histograms keep no joint counts, so
+//! combinations are estimated assuming the columns are independent.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use anyhow::{bail, Context, Result};
+use crate::genome::DatabaseGenome;
+use crate::math::{Distribution, Histogram};
+
+/// Default smallest acceptable group size.
+pub const DEFAULT_K: u64 = 5;
+
+/// Small groups listed per column in the text report.
+const LISTED_GROUPS: usize = 10;
+
+/// Below this share of rows, a column's small groups are suppressed rather than the
+/// whole column generalized.
+const SUPPRESS_MAX_SHARE: f64 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recommendation {
+    /// No group is smaller than k.
+    Keep,
+    /// Few rows are in small groups: drop or merge those categories.
+    Suppress,
+    /// Many rows are in small groups: coarsen the column (ranges, prefixes).
+    Generalize,
+    /// The histogram holds no source values (generator, pattern or text model).
+    NoStoredValues,
+}
+
+impl fmt::Display for Recommendation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Recommendation::Keep => write!(f, "ok"),
+            Recommendation::Suppress => write!(f, "suppress the small groups"),
+            Recommendation::Generalize => write!(f, "generalize"),
+            Recommendation::NoStoredValues => write!(f, "no stored values"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ColumnAudit {
+    pub column: String,
+    /// Groups (categories, bins or centroids, and NULL) stored for the column.
+    pub groups: usize,
+    /// Groups of fewer than k estimated rows, smallest first.
+    pub small_groups: Vec<(String, f64)>,
+    /// Estimated rows in small groups, including rows outside the tracked categories.
+    pub small_group_rows: f64,
+    pub recommendation: Recommendation,
+}
+
+#[derive(Debug, Clone)]
+pub struct TableAudit {
+    pub table: String,
+    pub rows: u64,
+    pub columns: Vec<ColumnAudit>,
+    /// Estimated share of rows whose combination of quasi-identifiers is shared by
+    /// fewer than k rows.
+    pub at_risk_share: f64,
+    /// The same estimate with each column left out, lowest first. The first column
+    /// is the one whose generalization helps most.
+    pub without_column: Vec<(String, f64)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditReport {
+    pub k: u64,
+    pub tables: Vec<TableAudit>,
+}
+
+impl AuditReport {
+    /// Some rows are estimated to be in groups smaller than k.
+    pub fn at_risk(&self) -> bool {
+        self.tables.iter().any(|t| t.at_risk_share > 0.0)
+    }
+}
+
+/// Share of rows per stored group of one column, plus the share outside them.
+struct Groups {
+    shares: Vec<(String, f64)>,
+    untracked: f64,
+}
+
+/// Audits the quasi-identifiers `columns`, given as `table.column`, against group size `k`.
+pub fn audit_genome(genome: &DatabaseGenome, columns: &[String], k: u64) -> Result<AuditReport> {
+    if k < 2 {
+        bail!("k must be at least 2, got {}", k);
+    }
+    if columns.is_empty() {
+        bail!("At least one quasi-identifier is required");
+    }
+
+    let mut by_table: BTreeMap<&str, Vec<(&str, &Distribution)>> = BTreeMap::new();
+    for key in columns {
+        let (table, column) = key
+            .split_once('.')
+            .context(format!("Quasi-identifier '{}' must be table.column", key))?;
+        let dist = genome
+            .get_distribution(table, column)
+            .context(format!("Quasi-identifier '{}' not found in genome", key))?;
+        by_table.entry(table).or_default().push((column, dist));
+    }
+
+    let tables = by_table
+        .into_iter()
+        .map(|(table, columns)| audit_table(table, &columns, k as f64))
+        .collect();
+    Ok(AuditReport { k, tables })
+}
+
+fn audit_table(table: &str, columns: &[(&str, &Distribution)], k: f64) -> TableAudit {
+    let rows = columns.iter().map(|(_, d)| d.total_count).max().unwrap_or(0);
+    let mut audits = Vec::new();
+    let mut groups = Vec::new();
+
+    for (column, dist) in columns {
+        let Some(column_groups) = stored_groups(dist) else {
+            audits.push(ColumnAudit {
+                column: column.to_string(),
+                groups: 0,
+                small_groups: Vec::new(),
+                small_group_rows: 0.0,
+                recommendation: Recommendation::NoStoredValues,
+            });
+            continue;
+        };
+
+        let mut small_groups: Vec<(String, f64)> = column_groups
+            .shares
+            .iter()
+            .map(|(label, share)| (label.clone(), share * rows as f64))
+            .filter(|(_, count)| *count < k)
+            .collect();
+        small_groups.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        let small_group_rows =
+            small_groups.iter().map(|(_, count)| count).sum::<f64>() + column_groups.untracked * rows as f64;
+
+        let recommendation = if small_group_rows == 0.0 {
+            Recommendation::Keep
+        } else if small_group_rows <= SUPPRESS_MAX_SHARE * rows as f64 {
+            Recommendation::Suppress
+        } else {
+            Recommendation::Generalize
+        };
+
+        audits.push(ColumnAudit {
+            column: column.to_string(),
+            groups: column_groups.shares.len(),
+            small_groups,
+            small_group_rows,
+            recommendation,
+        });
+        groups.push((column.to_string(), column_groups));
+    }
+
+    let all: Vec<&Groups> = groups.iter().map(|(_, g)| g).collect();
+    let at_risk_share = combination_risk(&all, rows as f64, k);
+    let mut without_column: Vec<(String, f64)> = if groups.len() > 1 {
+        groups
+            .iter()
+            .enumerate()
+            .map(|(skip, (column, _))| {
+                let rest: Vec<&Groups> = all
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| *i != skip)
+                    .map(|(_, g)| *g)
+                    .collect();
+                (column.clone(), combination_risk(&rest, rows as f64, k))
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    without_column.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+
+    TableAudit {
+        table: table.to_string(),
+        rows,
+        columns: audits,
+        at_risk_share,
+        without_column,
+    }
+}
+
+/// Row shares of the groups a histogram stores, NULL included, or `None` when it
+/// stores no source values. Counts are normalized so sampled histograms scale to the
+/// full table.
+fn stored_groups(dist: &Distribution) -> Option<Groups> {
+    let mut untracked = 0.0;
+    let mut counted: Vec<(String, f64)> = match &dist.histogram {
+        Histogram::Categorical { frequencies, long_tail, .. } => {
+            // Values outside the heavy hitters are individually rare
+            untracked = long_tail.as_ref().map_or(0.0, |tail| tail.count as f64);
+            frequencies.iter().map(|(value, count)| (format!("{:?}", value), *count as f64)).collect()
+        }
+        Histogram::Numeric { bins, frequencies, .. } => frequencies
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(i, count)| (format!("[{}, {})", bins[i], bins[i + 1]), *count as f64))
+            .collect(),
+        Histogram::Quantile { digest } => digest
+            .centroids
+            .iter()
+            .map(|c| (format!("~{}", c.mean), c.weight))
+            .collect(),
+        Histogram::Boolean { bernoulli } => vec![
+            ("true".to_string(), bernoulli.true_count as f64),
+            ("false".to_string(), bernoulli.false_count as f64),
+        ],
+        Histogram::Discrete { values, frequencies } => values
+            .iter()
+            .zip(frequencies)
+            .map(|(value, count)| (value.to_string(), *count as f64))
+            .collect(),
+        Histogram::Semantic { .. } | Histogram::Pattern { .. } | Histogram::Markov { .. } => return None,
+    };
+
+    // Point masses are shares of everything the histogram counts plus themselves
+    let point_share: f64 = dist.point_masses.iter().map(|m| m.share).sum();
+    if point_share > 0.0 && point_share < 1.0 {
+        let histogram_count: f64 = counted.iter().map(|(_, count)| count).sum::<f64>() + untracked;
+        let per_share = histogram_count / (1.0 - point_share);
+        counted.extend(dist.point_masses.iter().map(|m| (m.value.to_string(), m.share * per_share)));
+    }
+
+    let total = dist.total_count.max(1) as f64;
+    let non_null = dist.total_count.saturating_sub(dist.null_count) as f64 / total;
+    let stored = counted.iter().map(|(_, count)| count).sum::<f64>() + untracked;
+    let scale = if stored > 0.0 { non_null / stored } else { 0.0 };
+
+    let mut shares: Vec<(String, f64)> = counted
+        .into_iter()
+        .map(|(label, count)| (label, count * scale))
+        .collect();
+    if dist.null_count > 0 {
+        shares.push(("NULL".to_string(), dist.null_count as f64 / total));
+    }
+    Some(Groups { shares, untracked: untracked * scale })
+}
+
+/// Share of rows in combinations of fewer than `k` expected rows, with the columns
+/// treated as independent.
+fn combination_risk(columns: &[&Groups], rows: f64, k: f64) -> f64 {
+    let sorted: Vec<(Vec<f64>, f64)> = columns
+        .iter()
+        .map(|groups| {
+            let mut shares: Vec<f64> = groups.shares.iter().map(|(_, s)| *s).collect();
+            shares.sort_by(|a, b| b.total_cmp(a));
+            (shares, groups.untracked)
+        })
+        .collect();
+    risk_below(&sorted, 0, 1.0, rows, k)
+}
+
+/// At-risk share under a partial combination of probability `p`, fixed up to `depth`.
+fn risk_below(columns: &[(Vec<f64>, f64)], depth: usize, p: f64, rows: f64, k: f64) -> f64 {
+    if rows * p < k {
+        // Every extension is smaller still
+        return p;
+    }
+    let Some((shares, untracked)) = columns.get(depth) else {
+        return 0.0;
+    };
+
+    let mut risk = p * untracked;
+    for (i, share) in shares.iter().enumerate() {
+        if rows * p * share < k {
+            // Shares are sorted, so the remaining groups are all too small
+            risk += p * shares[i..].iter().sum::<f64>();
+            break;
+        }
+        risk += risk_below(columns, depth + 1, p * share, rows, k);
+    }
+    risk
+}
+
+impl fmt::Display for AuditReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for table in &self.tables {
+            writeln!(f, "table {} ({} rows), k = {}", table.table, table.rows, self.k)?;
+
+            for column in &table.columns {
+                if column.recommendation == Recommendation::NoStoredValues {
+                    writeln!(f, "    {}: no stored values", column.column)?;
+                    continue;
+                }
+                writeln!(
+                    f,
+                    "    {}: {} groups, {} below k covering ~{:.0} rows -> {}",
+                    column.column,
+                    column.groups,
+                    column.small_groups.len(),
+                    column.small_group_rows,
+                    column.recommendation
+                )?;
+                for (label, count) in column.small_groups.iter().take(LISTED_GROUPS) {
+                    writeln!(f, "        {}: ~{:.1}", label, count)?;
+                }
+                if column.small_groups.len() > LISTED_GROUPS {
+                    writeln!(f, "        ... {} more", column.small_groups.len() - LISTED_GROUPS)?;
+                }
+            }
+
+            writeln!(
+                f,
+                "    combinations: ~{:.2}% of rows in groups below k (columns assumed independent)",
+                table.at_risk_share * 100.0
+            )?;
+            for (column, share) in &table.without_column {
+                writeln!(f, "        without {}: ~{:.2}%", column, share * 100.0)?;
+            }
+            if let Some((column, share)) = table.without_column.first()
+                && *share < table.at_risk_share
+            {
+                writeln!(f, "    -> generalize {} first", column)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Column, DataType, Table};
+
+    fn categorical(pairs: &[(&str, u64)]) -> Distribution {
+        let total = pairs.iter().map(|(_, v)| v).sum();
+        Distribution::new(
+            None,
+            None,
+            0,
+            total,
+            pairs.len(),
+            Histogram::Categorical {
+                frequencies: pairs.iter().map(|(k, v)| (k.to_string(), *v)).collect(),
+                truncated: false,
+                long_tail: None,
+            },
+        )
+    }
+
+    fn genome(distributions: Vec<(&str, Distribution)>) -> DatabaseGenome {
+        let columns = distributions
+            .iter()
+            .map(|(name, _)| Column::new(name.to_string(), DataType::Text, true, false))
+            .collect();
+        let tables = vec![Table::new("users".to_string(), columns, vec![])];
+        let distributions = distributions
+            .into_iter()
+            .map(|(col, d)| (DatabaseGenome::make_key("users", col), d))
+            .collect();
+        DatabaseGenome::new(tables, distributions)
+    }
+
+    fn keys(columns: &[&str]) -> Vec<String> {
+        columns.iter().map(|c| format!("users.{}", c)).collect()
+    }
+
+    #[test]
+    fn test_small_categories_flagged() -> Result<()> {
+        let genome = genome(vec![
+            ("gender", categorical(&[("f", 500), ("m", 498), ("x", 2)])),
+            ("zip", categorical(&[("10001", 400), ("10002", 300), ("10003", 300)])),
+        ]);
+
+        let report = audit_genome(&genome, &keys(&["gender", "zip"]), 5)?;
+        let table = &report.tables[0];
+        assert_eq!(table.rows, 1000);
+        assert_eq!(table.columns[0].small_groups, vec![("\"x\"".to_string(), 2.0)]);
+        assert_eq!(table.columns[0].recommendation, Recommendation::Suppress);
+        assert_eq!(table.columns[1].recommendation, Recommendation::Keep);
+
+        // Only the x rows end up in combinations below k
+        assert!((table.at_risk_share - 0.002).abs() < 1e-9, "{}", table.at_risk_share);
+        assert!(report.at_risk());
+        assert!(report.to_string().contains("gender: 3 groups, 1 below k covering ~2 rows -> suppress"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_rare_combinations_name_the_column_to_generalize() -> Result<()> {
+        let years: Vec<(String, u64)> = (1950..2000).map(|y| (y.to_string(), 20)).collect();
+        let years: Vec<(&str, u64)> = years.iter().map(|(y, c)| (y.as_str(), *c)).collect();
+        let zips: Vec<(String, u64)> = (0..200).map(|z| (format!("{:05}", z), 5)).collect();
+        let zips: Vec<(&str, u64)> = zips.iter().map(|(z, c)| (z.as_str(), *c)).collect();
+        let genome = genome(vec![
+            ("birth_year", categorical(&years)),
+            ("zip", categorical(&zips)),
+            ("gender", categorical(&[("f", 500), ("m", 500)])),
+        ]);
+
+        let report = audit_genome(&genome, &keys(&["birth_year", "zip", "gender"]), 5)?;
+        let table = &report.tables[0];
+        // Every column alone is fine, but 1000 rows over 20,000 combinations are not
+        assert!(table.columns.iter().all(|c| c.recommendation == Recommendation::Keep));
+        assert!((table.at_risk_share - 1.0).abs() < 1e-9);
+        // Dropping zip leaves 100 combinations of 10 rows each; dropping the others doesn't help
+        assert_eq!(table.without_column[0].0, "zip");
+        assert!(table.without_column[0].1 < 1e-9);
+        assert!(report.to_string().contains("-> generalize zip first"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_unknown_columns() {
+        let genome = genome(vec![("zip", categorical(&[("10001", 10)]))]);
+        assert!(audit_genome(&genome, &keys(&["birth_year"]), 5).is_err());
+        assert!(audit_genome(&genome, &["zip".to_string()], 5).is_err());
+        assert!(audit_genome(&genome, &keys(&["zip"]), 1).is_err());
+    }
+}
@@ -0,0 +1,187 @@
+//! Generation throughput of a genome, for `replica_db bench`.
+//!
+//! Runs a full [`Synthesizer::generate`] and times each table from its first to its
+//! last row through [`GenerationProgress`]. Bytes are the COPY text generated, so
+//! the figures are for the generator alone, without output I/O.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use anyhow::{Context, Result};
+use crate::genome::DatabaseGenome;
+use crate::synth::{GenerationProgress, SynthesisConfig, Synthesizer};
+
+/// Throughput of one generated table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableThroughput {
+    pub table: String,
+    pub rows: usize,
+    pub bytes: usize,
+    pub elapsed: Duration,
+}
+
+impl TableThroughput {
+    pub fn rows_per_sec(&self) -> f64 {
+        per_second(self.rows, self.elapsed)
+    }
+
+    pub fn bytes_per_sec(&self) -> f64 {
+        per_second(self.bytes, self.elapsed)
+    }
+}
+
+/// Per-table throughput in generation order, and the whole run's wall time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchReport {
+    pub tables: Vec<TableThroughput>,
+    pub elapsed: Duration,
+}
+
+impl BenchReport {
+    pub fn total_rows(&self) -> usize {
+        self.tables.iter().map(|t| t.rows).sum()
+    }
+
+    pub fn total_bytes(&self) -> usize {
+        self.tables.iter().map(|t| t.bytes).sum()
+    }
+}
+
+/// Records when each table starts and finishes.
+#[derive(Default)]
+struct TableTimer {
+    started: Mutex<HashMap<String, Instant>>,
+    elapsed: Mutex<HashMap<String, Duration>>,
+}
+
+impl GenerationProgress for TableTimer {
+    fn table_started(&self, table: &str, _rows: usize) {
+        self.started.lock().unwrap().insert(table.to_string(), Instant::now());
+    }
+
+    fn table_finished(&self, table: &str) {
+        if let Some(start) = self.started.lock().unwrap().get(table) {
+            self.elapsed.lock().unwrap().insert(table.to_string(), start.elapsed());
+        }
+    }
+}
+
+/// Generates every table of `genome` under `config` and reports how fast each went.
+pub fn bench_generation(genome: DatabaseGenome, config: SynthesisConfig) -> Result<BenchReport> {
+    let timer = Arc::new(TableTimer::default());
+    let synthesizer = Synthesizer::new(genome, config)
+        .context("Failed to initialize synthesizer")?
+        .with_progress(timer.clone());
+
+    let start = Instant::now();
+    let result = synthesizer.generate().context("Failed to generate synthetic data")?;
+    let elapsed = start.elapsed();
+
+    let timings = timer.elapsed.lock().unwrap();
+    let tables = synthesizer
+        .execution_order()
+        .iter()
+        .filter_map(|table| {
+            let data = result.get_table_data(table)?;
+            Some(TableThroughput {
+                table: table.clone(),
+                rows: data.row_count,
+                bytes: data.size_bytes(),
+                elapsed: timings.get(table).copied().unwrap_or_default(),
+            })
+        })
+        .collect();
+
+    Ok(BenchReport { tables, elapsed })
+}
+
+fn per_second(count: usize, elapsed: Duration) -> f64 {
+    let seconds = elapsed.as_secs_f64();
+    if seconds > 0.0 { count as f64 / seconds } else { 0.0 }
+}
+
+const MIB: f64 = (1 << 20) as f64;
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.tables.iter().map(|t| t.table.len()).max().unwrap_or(0).max("total".len());
+        writeln!(
+            f,
+            "{:<width$}  {:>12}  {:>10}  {:>9}  {:>12}  {:>10}",
+            "table", "rows", "MiB", "seconds", "rows/s", "MiB/s"
+        )?;
+
+        let totals = TableThroughput {
+            table: "total".to_string(),
+            rows: self.total_rows(),
+            bytes: self.total_bytes(),
+            elapsed: self.elapsed,
+        };
+        for table in self.tables.iter().chain(std::iter::once(&totals)) {
+            writeln!(
+                f,
+                "{:<width$}  {:>12}  {:>10.1}  {:>9.3}  {:>12.0}  {:>10.1}",
+                table.table,
+                table.rows,
+                table.bytes as f64 / MIB,
+                table.elapsed.as_secs_f64(),
+                table.rows_per_sec(),
+                table.bytes_per_sec() / MIB
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{BinScale, Distribution, Histogram};
+    use crate::schema::{Column, DataType, ForeignKey, Table};
+
+    fn genome() -> DatabaseGenome {
+        let users = Table::new(
+            "users".to_string(),
+            vec![
+                Column::new("id".to_string(), DataType::Integer, false, true),
+                Column::new("score".to_string(), DataType::Float, false, false),
+            ],
+            vec![],
+        );
+        let orders = Table::new(
+            "orders".to_string(),
+            vec![
+                Column::new("id".to_string(), DataType::Integer, false, true),
+                Column::new("user_id".to_string(), DataType::Integer, false, false),
+            ],
+            vec![ForeignKey {
+                source_col: "user_id".to_string(),
+                target_table: "users".to_string(),
+                target_col: "id".to_string(),
+            }],
+        );
+        let score = Distribution::new(Some(0.0), Some(1.0), 0, 10, 10, Histogram::Numeric {
+            bins: vec![0.0, 1.0],
+            frequencies: vec![10],
+            scale: BinScale::Linear,
+        });
+        DatabaseGenome::new(vec![orders, users], HashMap::from([("users.score".to_string(), score)]))
+    }
+
+    #[test]
+    fn test_bench_reports_every_table() -> Result<()> {
+        let config = SynthesisConfig { rows_per_table: 500, seed: Some(1), ..SynthesisConfig::default() };
+        let report = bench_generation(genome(), config)?;
+
+        let tables: Vec<&str> = report.tables.iter().map(|t| t.table.as_str()).collect();
+        assert_eq!(tables, ["users", "orders"]);
+        assert!(report.tables.iter().all(|t| t.rows == 500 && t.bytes > 0));
+        assert_eq!(report.total_rows(), 1000);
+
+        let rendered = report.to_string();
+        assert!(rendered.lines().next().unwrap().contains("rows/s"));
+        assert!(rendered.lines().last().unwrap().starts_with("total"));
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+//! Incremental scan checkpoints so an interrupted scan can resume where it stopped.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+use crate::copula::CovarianceMatrix;
+use crate::math::Distribution;
+use crate::schema::{DataType, Table};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableCheckpoint {
+    /// Column layout the profile was computed for, used to detect schema changes.
+    pub columns: Vec<(String, DataType)>,
+    pub distributions: HashMap<String, Distribution>,
+
+    #[serde(default)]
+    pub covariance: Option<CovarianceMatrix>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScanCheckpoint {
+    #[serde(default)]
+    pub source_database: Option<String>,
+
+    #[serde(default)]
+    pub tables: HashMap<String, TableCheckpoint>,
+}
+
+impl ScanCheckpoint {
+    pub fn new(source_database: Option<String>) -> Self {
+        Self {
+            source_database,
+            tables: HashMap::new(),
+        }
+    }
+
+    /// Returns the checkpoint path that belongs to a genome output path (`genome.json.partial`).
+    pub fn partial_path(output: &Path) -> PathBuf {
+        let mut name = output.as_os_str().to_os_string();
+        name.push(".partial");
+        PathBuf::from(name)
+    }
+
+    /// Loads a checkpoint if one exists at `path`.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let json = std::fs::read_to_string(path)
+            .context("Failed to read scan checkpoint file")?;
+
+        let checkpoint: ScanCheckpoint = serde_json::from_str(&json)
+            .context("Failed to deserialize scan checkpoint")?;
+
+        info!(
+            path = ?path,
+            tables = checkpoint.tables.len(),
+            "Loaded scan checkpoint"
+        );
+
+        Ok(Some(checkpoint))
+    }
+
+    /// Writes the checkpoint atomically (temp file + rename) so a crash mid-write never
+    /// leaves a truncated checkpoint behind.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let dir = path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+
+        let json = serde_json::to_vec(self)
+            .context("Failed to serialize scan checkpoint")?;
+
+        let mut tmp = tempfile::NamedTempFile::new_in(dir)
+            .context("Failed to create temporary checkpoint file")?;
+        tmp.write_all(&json)
+            .context("Failed to write scan checkpoint")?;
+        tmp.persist(path)
+            .context("Failed to persist scan checkpoint")?;
+
+        debug!(path = ?path, tables = self.tables.len(), "Scan checkpoint saved");
+
+        Ok(())
+    }
+
+    pub fn remove(path: &Path) -> Result<()> {
+        if path.exists() {
+            std::fs::remove_file(path).context("Failed to remove scan checkpoint")?;
+        }
+        Ok(())
+    }
+
+    pub fn record(
+        &mut self,
+        table: &Table,
+        distributions: HashMap<String, Distribution>,
+        covariance: Option<CovarianceMatrix>,
+    ) {
+        self.tables.insert(
+            table.name.clone(),
+            TableCheckpoint {
+                columns: column_layout(table),
+                distributions,
+                covariance,
+            },
+        );
+    }
+
+    /// Returns the checkpointed profile for a table, provided its columns are unchanged.
+    pub fn completed(&self, table: &Table) -> Option<&TableCheckpoint> {
+        self.tables
+            .get(&table.name)
+            .filter(|entry| entry.columns == column_layout(table))
+    }
+}
+
+fn column_layout(table: &Table) -> Vec<(String, DataType)> {
+    table
+        .columns
+        .iter()
+        .map(|c| (c.name.clone(), c.data_type.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{BinScale, Histogram};
+    use crate::schema::Column;
+
+    fn users_table() -> Table {
+        Table::new(
+            "users".to_string(),
+            vec![
+                Column::new("id".to_string(), DataType::Integer, false, true),
+                Column::new("name".to_string(), DataType::Text, false, false),
+            ],
+            vec![],
+        )
+    }
+
+    fn sample_distributions() -> HashMap<String, Distribution> {
+        let mut distributions = HashMap::new();
+        distributions.insert(
+            "id".to_string(),
+            Distribution::new(Some(1.0), Some(10.0), 0, 10, 10, Histogram::Numeric { bins: vec![], frequencies: vec![], scale: BinScale::Linear }),
+        );
+        distributions
+    }
+
+    #[test]
+    fn test_partial_path() {
+        let path = ScanCheckpoint::partial_path(Path::new("out/genome.json"));
+        assert_eq!(path, PathBuf::from("out/genome.json.partial"));
+    }
+
+    #[test]
+    fn test_checkpoint_roundtrip() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("genome.json.partial");
+
+        let mut checkpoint = ScanCheckpoint::new(Some("db".to_string()));
+        checkpoint.record(&users_table(), sample_distributions(), None);
+        checkpoint.save(&path)?;
+
+        let loaded = ScanCheckpoint::load(&path)?.expect("checkpoint should exist");
+        assert_eq!(loaded.source_database.as_deref(), Some("db"));
+        assert!(loaded.completed(&users_table()).is_some());
+
+        ScanCheckpoint::remove(&path)?;
+        assert!(ScanCheckpoint::load(&path)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_schema_change_invalidates_entry() {
+        let mut checkpoint = ScanCheckpoint::new(None);
+        checkpoint.record(&users_table(), sample_distributions(), None);
+
+        let mut changed = users_table();
+        changed.columns.push(Column::new("email".to_string(), DataType::Text, true, false));
+
+        assert!(checkpoint.completed(&changed).is_none());
+    }
+}
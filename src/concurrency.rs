@@ -0,0 +1,157 @@
+//! Adaptive scan concurrency: how many tables are profiled at once, adjusted to how
+//! busy the database is.
+//!
+//! Every [`LOAD_SAMPLE_INTERVAL`] the scan counts the active sessions in
+//! `pg_stat_activity` and how many of them wait on I/O, locks or buffers. While few
+//! wait, one more table is started; once most wait, the number of tables is halved.
+//! Running tables are never interrupted, a lower limit only delays the next one.
+
+use std::time::Duration;
+use anyhow::{Context, Result};
+use sqlx::{PgPool, Row};
+use crate::schema::Table;
+
+/// Time between two samples of `pg_stat_activity`.
+pub const LOAD_SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Share of waiting sessions below which concurrency grows.
+const GROW_BELOW: f64 = 0.2;
+
+/// Share of waiting sessions above which concurrency is halved.
+const SHRINK_ABOVE: f64 = 0.5;
+
+/// Active client sessions of the server and how many of them are waiting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DatabaseLoad {
+    pub active: u64,
+
+    /// Active sessions waiting on I/O, a lock, a lightweight lock or a buffer pin.
+    pub waiting: u64,
+}
+
+impl DatabaseLoad {
+    /// Share of active sessions that are waiting; 0 when none is active.
+    pub fn waiting_share(&self) -> f64 {
+        if self.active == 0 {
+            0.0
+        } else {
+            self.waiting as f64 / self.active as f64
+        }
+    }
+}
+
+/// Reads the current [`DatabaseLoad`] from `pg_stat_activity`, server-wide, since
+/// other databases compete for the same disks. The sampling session is left out.
+pub async fn sample_load(pool: &PgPool) -> Result<DatabaseLoad> {
+    let row = sqlx::query(
+        "SELECT count(*) FILTER (WHERE state = 'active') AS active, \
+         count(*) FILTER (WHERE state = 'active' AND wait_event_type IN ('IO', 'Lock', 'LWLock', 'BufferPin')) AS waiting \
+         FROM pg_stat_activity WHERE backend_type = 'client backend' AND pid <> pg_backend_pid()",
+    )
+        .fetch_one(pool)
+        .await
+        .context("Failed to read pg_stat_activity")?;
+
+    Ok(DatabaseLoad {
+        active: row.try_get::<i64, _>("active")?.max(0) as u64,
+        waiting: row.try_get::<i64, _>("waiting")?.max(0) as u64,
+    })
+}
+
+/// Number of tables profiled at once, fixed or adjusted between 1 and a connection
+/// budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConcurrencyTuner {
+    limit: usize,
+    budget: usize,
+    adaptive: bool,
+}
+
+impl ConcurrencyTuner {
+    /// Always `jobs` tables; [`ConcurrencyTuner::adjust`] leaves it unchanged.
+    pub fn fixed(jobs: usize) -> Self {
+        let jobs = jobs.max(1);
+        Self { limit: jobs, budget: jobs, adaptive: false }
+    }
+
+    /// Starts at `initial` tables, never more than `budget`.
+    pub fn adaptive(initial: usize, budget: usize) -> Self {
+        let budget = budget.max(1);
+        Self { limit: initial.clamp(1, budget), budget, adaptive: true }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Whether the limit follows the database load.
+    pub fn is_adaptive(&self) -> bool {
+        self.adaptive
+    }
+
+    /// Adds a table while under [`GROW_BELOW`] of the sessions wait, and halves the
+    /// tables above [`SHRINK_ABOVE`]. Returns the new limit.
+    pub fn adjust(&mut self, load: DatabaseLoad) -> usize {
+        if !self.adaptive {
+            return self.limit;
+        }
+        let waiting = load.waiting_share();
+        if waiting > SHRINK_ABOVE {
+            self.limit = (self.limit / 2).max(1);
+        } else if waiting < GROW_BELOW {
+            self.limit = (self.limit + 1).min(self.budget);
+        }
+        self.limit
+    }
+}
+
+/// Sorts `tables` largest first by estimated size, tables without an estimate last,
+/// so the longest scans start early instead of holding up the end of the run.
+pub fn order_largest_first(tables: &mut [&Table]) {
+    tables.sort_by_key(|table| std::cmp::Reverse(table.estimate.map(|e| e.bytes)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::TableEstimate;
+
+    #[test]
+    fn test_tuner_follows_waiting_share() {
+        let mut tuner = ConcurrencyTuner::adaptive(4, 6);
+        let idle = DatabaseLoad { active: 10, waiting: 1 };
+        let busy = DatabaseLoad { active: 10, waiting: 8 };
+
+        assert_eq!(tuner.adjust(idle), 5);
+        assert_eq!(tuner.adjust(idle), 6);
+        // Never beyond the budget
+        assert_eq!(tuner.adjust(idle), 6);
+        // Between the thresholds the limit holds
+        assert_eq!(tuner.adjust(DatabaseLoad { active: 10, waiting: 3 }), 6);
+        assert_eq!(tuner.adjust(busy), 3);
+        assert_eq!(tuner.adjust(busy), 1);
+        assert_eq!(tuner.adjust(busy), 1);
+        assert_eq!(tuner.adjust(DatabaseLoad::default()), 2);
+
+        assert_eq!(ConcurrencyTuner::adaptive(10, 4).limit(), 4);
+        assert_eq!(ConcurrencyTuner::adaptive(0, 0).limit(), 1);
+        let mut fixed = ConcurrencyTuner::fixed(10);
+        assert_eq!(fixed.adjust(busy), 10);
+    }
+
+    #[test]
+    fn test_largest_tables_first() {
+        let sized = |name: &str, bytes: Option<u64>| {
+            let mut table = Table::new(name.to_string(), vec![], vec![]);
+            table.estimate = bytes.map(|bytes| TableEstimate { rows: None, bytes });
+            table
+        };
+        let (users, events, unknown, orders) =
+            (sized("users", Some(1 << 20)), sized("events", Some(1 << 34)), sized("unknown", None), sized("orders", Some(1 << 30)));
+        let mut tables = vec![&users, &unknown, &events, &orders];
+
+        order_largest_first(&mut tables);
+        let names: Vec<&str> = tables.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["events", "orders", "users", "unknown"]);
+    }
+}
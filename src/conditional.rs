@@ -0,0 +1,185 @@
+//! Child-column distributions conditioned on an attribute of the parent row.
+//!
+//! Marginals and per-table copulas lose dependence that crosses a foreign key, such as
+//! `orders.amount` depending on `customers.tier`. An optional scan pass joins each child
+//! to its parent, groups child values by one low-cardinality parent attribute and stores
+//! a distribution per group; generation then looks up the attribute of the parent row it
+//! picked for the foreign key.
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::genome::DatabaseGenome;
+use crate::math::{Distribution, Histogram};
+use crate::schema::{Column, ForeignKey, Table};
+
+/// Parent attributes with more categories than this are not used for conditioning.
+pub const MAX_CONDITIONING_CATEGORIES: usize = 20;
+
+/// Groups with fewer child rows than this fall back to the marginal distribution.
+pub const MIN_GROUP_ROWS: u64 = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConditionalDistribution {
+    /// Child column holding the foreign key.
+    pub foreign_key: String,
+    pub parent_table: String,
+    pub parent_column: String,
+
+    /// Parent attribute value -> distribution of the child column in that group.
+    pub by_parent_value: HashMap<String, Distribution>,
+}
+
+impl ConditionalDistribution {
+    pub fn for_parent_value(&self, value: &str) -> Option<&Distribution> {
+        self.by_parent_value.get(value)
+    }
+}
+
+/// The parent column to condition `fk` on: the categorical or boolean, non-key column
+/// of the parent with the fewest (but at least two) categories.
+pub fn conditioning_column<'a>(
+    parent: &'a Table,
+    distributions: &HashMap<String, Distribution>,
+) -> Option<&'a Column> {
+    parent
+        .columns
+        .iter()
+        .filter(|c| !c.is_primary_key && !parent.foreign_keys.iter().any(|fk| fk.source_col == c.name))
+        .filter_map(|c| {
+            let dist = distributions.get(&DatabaseGenome::make_key(&parent.name, &c.name))?;
+            match &dist.histogram {
+                Histogram::Categorical { frequencies, truncated: false, long_tail: None }
+                    if (2..=MAX_CONDITIONING_CATEGORIES).contains(&frequencies.len()) =>
+                {
+                    Some((c, frequencies.len()))
+                }
+                Histogram::Boolean { bernoulli } if bernoulli.true_count > 0 && bernoulli.false_count > 0 => {
+                    Some((c, 2))
+                }
+                Histogram::Discrete { values, .. } if (2..=MAX_CONDITIONING_CATEGORIES).contains(&values.len()) => {
+                    Some((c, values.len()))
+                }
+                _ => None,
+            }
+        })
+        .min_by_key(|(_, categories)| *categories)
+        .map(|(column, _)| column)
+}
+
+/// Child columns worth conditioning: non-key columns modelled by a histogram or
+/// digest. Generators (semantic, pattern, Markov) and PII columns are left alone.
+pub fn conditioned_columns<'a>(child: &'a Table, distributions: &HashMap<String, Distribution>) -> Vec<&'a Column> {
+    child
+        .columns
+        .iter()
+        .filter(|c| !c.is_primary_key && !child.foreign_keys.iter().any(|fk| fk.source_col == c.name))
+        .filter(|c| {
+            distributions
+                .get(&DatabaseGenome::make_key(&child.name, &c.name))
+                .is_some_and(|dist| {
+                    dist.pii.is_none()
+                        && matches!(
+                            dist.histogram,
+                            Histogram::Numeric { .. }
+                                | Histogram::Quantile { .. }
+                                | Histogram::Discrete { .. }
+                                | Histogram::Categorical { .. }
+                                | Histogram::Boolean { .. }
+                        )
+                })
+        })
+        .collect()
+}
+
+/// Foreign keys of `child` paired with the parent column to condition on.
+pub fn conditioning_plan<'a>(
+    child: &'a Table,
+    tables: &'a [Table],
+    distributions: &HashMap<String, Distribution>,
+) -> Vec<(&'a ForeignKey, &'a Column)> {
+    child
+        .foreign_keys
+        .iter()
+        .filter(|fk| fk.target_table != child.name)
+        .filter_map(|fk| {
+            let parent = tables.iter().find(|t| t.name == fk.target_table)?;
+            conditioning_column(parent, distributions).map(|column| (fk, column))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::DataType;
+
+    fn categorical(values: &[(&str, u64)]) -> Distribution {
+        let frequencies = values.iter().map(|(k, v)| (k.to_string(), *v)).collect();
+        let total = values.iter().map(|(_, v)| v).sum();
+        Distribution::new(None, None, 0, total, values.len(), Histogram::Categorical {
+            frequencies,
+            truncated: false,
+            long_tail: None,
+        })
+    }
+
+    #[test]
+    fn test_picks_lowest_cardinality_attribute() {
+        let customers = Table::new(
+            "customers".to_string(),
+            vec![
+                Column::new("id".to_string(), DataType::Integer, false, true),
+                Column::new("region".to_string(), DataType::Text, true, false),
+                Column::new("tier".to_string(), DataType::Text, true, false),
+                Column::new("status".to_string(), DataType::Text, true, false),
+            ],
+            vec![],
+        );
+
+        let mut distributions = HashMap::new();
+        distributions.insert("customers.region".to_string(), categorical(&[("eu", 5), ("us", 5), ("apac", 5)]));
+        distributions.insert("customers.tier".to_string(), categorical(&[("gold", 2), ("basic", 8)]));
+        // A single category carries no signal
+        distributions.insert("customers.status".to_string(), categorical(&[("active", 10)]));
+
+        assert_eq!(conditioning_column(&customers, &distributions).map(|c| c.name.as_str()), Some("tier"));
+    }
+
+    #[test]
+    fn test_boolean_attribute_preferred() {
+        let users = Table::new(
+            "users".to_string(),
+            vec![
+                Column::new("tier".to_string(), DataType::Text, true, false),
+                Column::new("premium".to_string(), DataType::Boolean, true, false),
+            ],
+            vec![],
+        );
+
+        let mut distributions = HashMap::new();
+        distributions.insert("users.tier".to_string(), categorical(&[("gold", 2), ("silver", 3), ("basic", 5)]));
+        let bernoulli = crate::math::Bernoulli { true_count: 3, false_count: 7 };
+        distributions.insert(
+            "users.premium".to_string(),
+            Distribution::new(None, None, 0, 10, 2, Histogram::Boolean { bernoulli }),
+        );
+
+        assert_eq!(conditioning_column(&users, &distributions).map(|c| c.name.as_str()), Some("premium"));
+    }
+
+    #[test]
+    fn test_high_cardinality_attribute_ignored() {
+        let users = Table::new(
+            "users".to_string(),
+            vec![Column::new("city".to_string(), DataType::Text, true, false)],
+            vec![],
+        );
+        let cities: Vec<(String, u64)> = (0..50).map(|i| (format!("city{}", i), 1)).collect();
+        let refs: Vec<(&str, u64)> = cities.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+
+        let mut distributions = HashMap::new();
+        distributions.insert("users.city".to_string(), categorical(&refs));
+
+        assert!(conditioning_column(&users, &distributions).is_none());
+    }
+}
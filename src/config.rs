@@ -0,0 +1,293 @@
+//! Settings read from a TOML file, for options that do not fit on a command line.
+//!
+//! ```toml
+//! # Only the last 90 days of events shape the twin
+//! [where]
+//! events = "created_at > now() - interval '90 days'"
+//!
+//! # Named generators for specific columns
+//! [generators]
+//! users.email = "email"
+//! payments.card = "luhn"
+//!
+//! # Identifiers replaced character by character, keeping their format
+//! [anonymize]
+//! users.phone = { keep_prefix = 3 }
+//! accounts.number = { stage = "generate" }
+//!
+//! # Larger samples for critical tables, or single columns
+//! [reservoir]
+//! orders = 100000
+//! "events.payload" = 2000
+//!
+//! # Recent orders shape the twin more than old ones
+//! [recency]
+//! orders = { column = "created_at", half_life = "30 days" }
+//!
+//! # Joint samples resampled as-is, keeping tail dependence
+//! [copula]
+//! claims = "empirical"
+//!
+//! # Rows counted per tenant, for tenant-aware generation
+//! [tenant]
+//! column = "tenant_id"
+//!
+//! # Copied rows for testing deduplication
+//! [duplicates]
+//! customers = { exact = 0.01, near = 0.03 }
+//! ```
+
+use std::collections::HashMap;
+use std::path::Path;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::{Token, Tokenizer, Whitespace};
+use crate::anonymize::FormatPreserving;
+use crate::copula::CopulaKind;
+use crate::genome::DatabaseGenome;
+use crate::math::Recency;
+use crate::synth::DuplicateRates;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReplicaConfig {
+    /// SQL predicate per table; only matching rows are profiled.
+    #[serde(default, rename = "where")]
+    pub row_filters: HashMap<String, String>,
+
+    /// Generator name per column, by table then column.
+    #[serde(default)]
+    pub generators: HashMap<String, HashMap<String, String>>,
+
+    /// Format-preserving anonymization per column, by table then column.
+    #[serde(default)]
+    pub anonymize: HashMap<String, HashMap<String, FormatPreserving>>,
+
+    /// Values sampled per column, keyed `table` or `table.column`; overrides
+    /// `scan --reservoir`.
+    #[serde(default)]
+    pub reservoir: HashMap<String, usize>,
+
+    /// Timestamp column and half-life per table; newer rows are more likely sampled.
+    #[serde(default)]
+    pub recency: HashMap<String, Recency>,
+
+    /// Copula per table; empirical tables keep their joint sample in the genome.
+    #[serde(default)]
+    pub copula: HashMap<String, CopulaKind>,
+
+    /// Shares of exact and near duplicate rows per table; overrides the `gen` flags.
+    #[serde(default)]
+    pub duplicates: HashMap<String, DuplicateRates>,
+
+    /// Tenant column of a multi-tenant schema.
+    #[serde(default)]
+    pub tenant: Option<TenantConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TenantConfig {
+    /// Column naming the tenant, the same in every tenant table.
+    pub column: String,
+}
+
+impl ReplicaConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .context(format!("Failed to read config file '{}'", path.display()))?;
+        Self::parse(&text).context(format!("Invalid config file '{}'", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let config: ReplicaConfig = toml::from_str(text)?;
+        for (table, predicate) in &config.row_filters {
+            validate_predicate(predicate).context(format!("Invalid row filter for table '{}'", table))?;
+        }
+        if let Some((name, _)) = config.reservoir.iter().find(|(_, capacity)| **capacity == 0) {
+            bail!("Reservoir capacity of '{}' must be at least 1", name);
+        }
+        for (table, rates) in &config.duplicates {
+            rates.validate().context(format!("Invalid duplicate rates for table '{}'", table))?;
+        }
+        Ok(config)
+    }
+
+    /// Generator names keyed `table.column`, as [`crate::synth::SynthesisConfig`] takes them.
+    pub fn column_generators(&self) -> HashMap<String, String> {
+        by_column(&self.generators)
+    }
+
+    /// Anonymized columns keyed `table.column`, for both scan and generation.
+    pub fn anonymized_columns(&self) -> HashMap<String, FormatPreserving> {
+        by_column(&self.anonymize)
+    }
+}
+
+/// Flattens a table -> column -> setting map to `table.column` keys.
+fn by_column<T: Clone>(tables: &HashMap<String, HashMap<String, T>>) -> HashMap<String, T> {
+    tables
+        .iter()
+        .flat_map(|(table, columns)| {
+            columns
+                .iter()
+                .map(move |(column, setting)| (DatabaseGenome::make_key(table, column), setting.clone()))
+        })
+        .collect()
+}
+
+/// Rejects predicates that could reach outside the `WHERE (...)` they are placed in.
+/// The predicate is tokenized as Postgres would (so `E'...'` escapes and dollar quoting
+/// can't hide anything) and must parse as a single expression, without comments or
+/// statement separators.
+pub fn validate_predicate(predicate: &str) -> Result<()> {
+    if predicate.trim().is_empty() {
+        bail!("Row filter is empty");
+    }
+
+    let dialect = PostgreSqlDialect {};
+    let tokens = Tokenizer::new(&dialect, predicate)
+        .tokenize()
+        .context(format!("Invalid row filter: {}", predicate))?;
+    for token in &tokens {
+        match token {
+            Token::SemiColon => bail!("Row filter may not contain ';': {}", predicate),
+            Token::Whitespace(Whitespace::SingleLineComment { .. } | Whitespace::MultiLineComment(_)) => {
+                bail!("Row filter may not contain comments: {}", predicate)
+            }
+            _ => {}
+        }
+    }
+
+    let mut parser = Parser::new(&dialect).with_tokens(tokens);
+    parser
+        .parse_expr()
+        .context(format!("Row filter is not an expression: {}", predicate))?;
+    if parser.peek_token().token != Token::EOF {
+        bail!("Row filter must be a single expression: {}", predicate);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anonymize::AnonymizeStage;
+
+    #[test]
+    fn test_parse_row_filters() -> Result<()> {
+        let config = ReplicaConfig::parse(
+            "[where]\nevents = \"created_at > now() - interval '90 days'\"\norders = \"status <> 'test'\"\n",
+        )?;
+        assert_eq!(config.row_filters.len(), 2);
+        assert_eq!(config.row_filters["orders"], "status <> 'test'");
+
+        assert!(ReplicaConfig::parse("").is_ok_and(|c| c.row_filters.is_empty()));
+        assert!(ReplicaConfig::parse("[where]\nusers = \"true; DROP TABLE users\"\n").is_err());
+        assert!(ReplicaConfig::parse("[filters]\nusers = \"true\"\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_reservoir() -> Result<()> {
+        let config = ReplicaConfig::parse("[reservoir]\norders = 100000\n\"events.payload\" = 2000\n")?;
+        assert_eq!(config.reservoir["orders"], 100_000);
+        assert_eq!(config.reservoir["events.payload"], 2_000);
+
+        assert!(ReplicaConfig::parse("[reservoir]\norders = 0\n").is_err());
+        assert!(ReplicaConfig::parse("[reservoir]\norders = -5\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_recency() -> Result<()> {
+        let config = ReplicaConfig::parse("[recency]\norders = { column = \"created_at\", half_life = \"30 days\" }\n")?;
+        assert_eq!(config.recency["orders"], Recency { column: "created_at".to_string(), half_life: 30.0 * 86_400.0 });
+
+        assert!(ReplicaConfig::parse("[recency]\norders = { column = \"created_at\", half_life = \"soon\" }\n").is_err());
+        assert!(ReplicaConfig::parse("[recency]\norders = { column = \"created_at\", half_life = \"-1 days\" }\n").is_err());
+        assert!(ReplicaConfig::parse("[recency]\norders = { column = \"created_at\" }\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_copula() -> Result<()> {
+        let config = ReplicaConfig::parse("[copula]\nclaims = \"empirical\"\nusers = \"gaussian\"\n")?;
+        assert_eq!(config.copula["claims"], CopulaKind::Empirical);
+        assert_eq!(config.copula["users"], CopulaKind::Gaussian);
+
+        assert!(ReplicaConfig::parse("[copula]\nclaims = \"vine\"\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_duplicates() -> Result<()> {
+        let config = ReplicaConfig::parse("[duplicates]\ncustomers = { exact = 0.01, near = 0.03 }\nevents = { near = 0.1 }\n")?;
+        assert_eq!(config.duplicates["customers"], DuplicateRates { exact: 0.01, near: 0.03 });
+        assert_eq!(config.duplicates["events"], DuplicateRates { exact: 0.0, near: 0.1 });
+
+        assert!(ReplicaConfig::parse("[duplicates]\ncustomers = { exact = 0.7, near = 0.7 }\n").is_err());
+        assert!(ReplicaConfig::parse("[duplicates]\ncustomers = { fuzzy = 0.1 }\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_tenant() -> Result<()> {
+        let config = ReplicaConfig::parse("[tenant]\ncolumn = \"tenant_id\"\n")?;
+        assert_eq!(config.tenant, Some(TenantConfig { column: "tenant_id".to_string() }));
+
+        assert!(ReplicaConfig::parse("").is_ok_and(|c| c.tenant.is_none()));
+        assert!(ReplicaConfig::parse("[tenant]\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_generators() -> Result<()> {
+        let config = ReplicaConfig::parse(
+            "[generators]\nusers.email = \"email\"\npayments.card = \"luhn\"\n\n[generators.hosts]\naddress = \"ipv4\"\n",
+        )?;
+        let generators = config.column_generators();
+        assert_eq!(generators.len(), 3);
+        assert_eq!(generators["users.email"], "email");
+        assert_eq!(generators["payments.card"], "luhn");
+        assert_eq!(generators["hosts.address"], "ipv4");
+
+        assert!(ReplicaConfig::parse("[generators]\nemail = \"email\"\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_anonymize() -> Result<()> {
+        let config = ReplicaConfig::parse(
+            "[anonymize]\nusers.phone = { keep_prefix = 3 }\naccounts.number = { stage = \"generate\" }\n",
+        )?;
+        let columns = config.anonymized_columns();
+        assert_eq!(columns["users.phone"], FormatPreserving { keep_prefix: 3, stage: AnonymizeStage::Scan });
+        assert_eq!(columns["accounts.number"].stage, AnonymizeStage::Generate);
+
+        assert!(ReplicaConfig::parse("[anonymize]\nusers.phone = { stage = \"load\" }\n").is_err());
+        assert!(ReplicaConfig::parse("[anonymize]\nusers.phone = { prefix = 3 }\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_predicate() {
+        assert!(validate_predicate("created_at > now() - interval '90 days'").is_ok());
+        assert!(validate_predicate("note = 'a;b -- c' AND \"odd;name\" IS NOT NULL").is_ok());
+        assert!(validate_predicate("name = 'O''Brien'").is_ok());
+        assert!(validate_predicate("note = E'it\\'s' AND body = $$a;b$$").is_ok());
+
+        assert!(validate_predicate("").is_err());
+        assert!(validate_predicate("true; DROP TABLE users").is_err());
+        assert!(validate_predicate("true) OR (1 = 1").is_err());
+        assert!(validate_predicate("(true").is_err());
+        assert!(validate_predicate("true -- ignore the rest").is_err());
+        assert!(validate_predicate("true /* x */").is_err());
+        assert!(validate_predicate("name = 'open").is_err());
+        assert!(validate_predicate("note = E'\\'' ; DROP TABLE t; --'").is_err());
+        assert!(validate_predicate("note = $$'$$ ; DROP TABLE t; --'").is_err());
+        assert!(validate_predicate("note = $tag$'$tag$ ; DROP TABLE t; --'").is_err());
+    }
+}
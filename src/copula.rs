@@ -16,6 +16,8 @@
 //! 1. **Training (Scan Phase):**
 //!    - Compute Pearson correlation matrix from numeric column samples
 //!    - R[i,j] = Cov(X_i, X_j) / (σ_i * σ_j)
+//!    - Or, with [`CorrelationMethod::Spearman`], the same on per-column ranks; the
+//!      copula then uses r = 2·sin(π·ρ/6), the normal correlation with rank correlation ρ
 //!
 //! 2. **Generation (Synthesis Phase):**
 //!    - Generate independent standard normals: Z ~ N(0, 1)
@@ -31,15 +33,54 @@
 //!
 //! For typical schemas (n < 100 columns), this adds ~10ms to scan,
 //! negligible overhead to generation (~0.1ms per row).
+//!
+//! Computing matrices and factoring them needs the `copula` feature (nalgebra).
+//! Without it, genomes still load with their matrices, and synthesis samples each
+//! column independently.
 
+use std::fmt;
+use std::str::FromStr;
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "copula")]
 use anyhow::{Context, Result};
+#[cfg(feature = "copula")]
 use nalgebra::{DMatrix, DVector};
-use rand::prelude::*;
-use statrs::distribution::{ContinuousCDF, Normal};
-use tracing::debug;
+#[cfg(feature = "copula")]
+use tracing::{debug, warn};
 use rand::Rng;
-use rand::rngs::ThreadRng;
+use crate::special::normal_cdf;
+
+/// How pairwise dependence between numeric columns is measured.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CorrelationMethod {
+    /// Linear correlation of the raw values.
+    #[default]
+    Pearson,
+    /// Correlation of ranks; robust to skew and monotone transforms.
+    Spearman,
+}
+
+impl fmt::Display for CorrelationMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CorrelationMethod::Pearson => write!(f, "pearson"),
+            CorrelationMethod::Spearman => write!(f, "spearman"),
+        }
+    }
+}
+
+impl FromStr for CorrelationMethod {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "pearson" => Ok(CorrelationMethod::Pearson),
+            "spearman" => Ok(CorrelationMethod::Spearman),
+            other => anyhow::bail!("Unknown correlation method '{}' (expected pearson or spearman)", other),
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CovarianceMatrix {
@@ -48,11 +89,61 @@ pub struct CovarianceMatrix {
     #[serde(rename = "correlation_matrix")]
     pub matrix_data: Vec<f64>,
     pub dimension: usize,
+
+    /// Genomes written before rank correlation existed hold Pearson matrices.
+    #[serde(default)]
+    pub method: CorrelationMethod,
+
+    /// Rows of the joint sample with every value replaced by its scaled rank
+    /// (rank − ½) / n within its column, NULLs kept. Only tables scanned for the
+    /// [`CopulaKind::Empirical`] copula store them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pseudo_observations: Option<Vec<Vec<Option<f64>>>>,
+}
+
+/// How generation draws the joint uniforms of a table's correlated columns.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CopulaKind {
+    /// Gaussian copula over the correlation matrix.
+    #[default]
+    Gaussian,
+    /// Resampled rank-transformed rows of the joint sample, which keeps tail
+    /// dependence the Gaussian copula smooths away.
+    Empirical,
+}
+
+impl fmt::Display for CopulaKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CopulaKind::Gaussian => write!(f, "gaussian"),
+            CopulaKind::Empirical => write!(f, "empirical"),
+        }
+    }
 }
 
+#[cfg(feature = "copula")]
 impl CovarianceMatrix {
 
     pub fn compute(column_names: Vec<String>, samples: &[Vec<f64>]) -> Result<Self> {
+        Self::compute_with_method(column_names, samples, CorrelationMethod::Pearson)
+    }
+
+    /// Like [`CovarianceMatrix::compute`]; Spearman replaces every column by its
+    /// ranks (ties averaged) before correlating.
+    pub fn compute_with_method(
+        column_names: Vec<String>,
+        samples: &[Vec<f64>],
+        method: CorrelationMethod,
+    ) -> Result<Self> {
+        let ranked;
+        let samples = match method {
+            CorrelationMethod::Pearson => samples,
+            CorrelationMethod::Spearman => {
+                ranked = rank_columns(samples);
+                &ranked
+            }
+        };
 
         let n_samples = samples.len();
         let n_cols = column_names.len();
@@ -81,7 +172,8 @@ impl CovarianceMatrix {
         }
 
         let sample_matrix = DMatrix::from_row_slice(n_samples, n_cols, &data);
-        let means: DVector<f64> = sample_matrix.column_mean();
+        // Per-column means (nalgebra's column_mean() averages across each row)
+        let means = sample_matrix.row_mean();
 
         // computing standard deviation
         let mut std_devs = DVector::zeros(n_cols);
@@ -112,7 +204,10 @@ impl CovarianceMatrix {
         }
 
         // Computing correlational matrix: R = (Z^T * Z) / (n-1)
-        let correlational_matrix = (standardized.transpose() * standardized) / (n_samples - 1) as f64;
+        let mut correlational_matrix = (standardized.transpose() * standardized) / (n_samples - 1) as f64;
+
+        // A constant column would leave a zero on the diagonal and break Cholesky
+        correlational_matrix.fill_diagonal(1.0);
 
         let matrix_data: Vec<f64> = correlational_matrix.iter().copied().collect();
 
@@ -126,46 +221,266 @@ impl CovarianceMatrix {
             columns: column_names,
             matrix_data,
             dimension: n_cols,
+            method,
+            pseudo_observations: None,
+        })
+    }
+
+    /// Like [`CovarianceMatrix::compute_with_method`] for rows with missing values
+    /// (`None`, i.e. NULL): every coefficient comes from the rows where both of its
+    /// columns are present (pairwise deletion), so one sparse column doesn't empty the
+    /// whole sample. Pairs seen together fewer than [`MIN_PAIRWISE_SAMPLES`] times are
+    /// stored as uncorrelated. The result may not be positive definite;
+    /// [`GaussianCopula::new`] repairs it.
+    pub fn compute_pairwise(
+        column_names: Vec<String>,
+        samples: &[Vec<Option<f64>>],
+        method: CorrelationMethod,
+    ) -> Result<Self> {
+        let n_cols = column_names.len();
+        if samples.is_empty() {
+            anyhow::bail!("Cannot compute covariance from zero samples");
+        }
+        if n_cols == 0 {
+            anyhow::bail!("Cannot compute covariance from zero columns");
+        }
+        if let Some((i, sample)) = samples.iter().enumerate().find(|(_, sample)| sample.len() != n_cols) {
+            anyhow::bail!("Sample {} has {} values, expected {}", i, sample.len(), n_cols);
+        }
+
+        let mut correlational_matrix = DMatrix::identity(n_cols, n_cols);
+        let mut sparse_pairs = 0;
+        for i in 0..n_cols {
+            for j in (i + 1)..n_cols {
+                let (xs, ys): (Vec<f64>, Vec<f64>) = samples
+                    .iter()
+                    .filter_map(|sample| Some((sample[i]?, sample[j]?)))
+                    .unzip();
+                if xs.len() < MIN_PAIRWISE_SAMPLES {
+                    sparse_pairs += 1;
+                    continue;
+                }
+                let r = match method {
+                    CorrelationMethod::Pearson => pearson(&xs, &ys),
+                    CorrelationMethod::Spearman => pearson(&ranks(&xs), &ranks(&ys)),
+                };
+                correlational_matrix[(i, j)] = r;
+                correlational_matrix[(j, i)] = r;
+            }
+        }
+
+        debug!(
+            columns = n_cols,
+            samples = samples.len(),
+            sparse_pairs = sparse_pairs,
+            "Computed pairwise correlational matrix"
+        );
+
+        Ok(Self {
+            columns: column_names,
+            matrix_data: correlational_matrix.iter().copied().collect(),
+            dimension: n_cols,
+            method,
+            pseudo_observations: None,
         })
     }
 
+    /// Stores `samples`, the rows the matrix was computed from, as
+    /// [`CovarianceMatrix::pseudo_observations`] for the empirical copula. Ranks are
+    /// taken among each column's non-NULL values, ties averaged.
+    pub fn with_pseudo_observations(mut self, samples: &[Vec<Option<f64>>]) -> Self {
+        let value = |sample: &Vec<Option<f64>>, col: usize| sample.get(col).copied().flatten();
+        let scaled: Vec<Vec<Option<f64>>> = (0..self.dimension)
+            .map(|col| {
+                let values: Vec<f64> = samples.iter().filter_map(|sample| value(sample, col)).collect();
+                let n = values.len() as f64;
+                let mut ranked = ranks(&values).into_iter().map(|rank| (rank - 0.5) / n);
+                samples.iter().map(|sample| value(sample, col).and_then(|_| ranked.next())).collect()
+            })
+            .collect();
+        let observations = (0..samples.len())
+            .map(|row| scaled.iter().map(|column| column[row]).collect())
+            .collect();
+        self.pseudo_observations = Some(observations);
+        self
+    }
+
+    /// Splits the matrix into blocks of columns linked by coefficients of at least
+    /// `threshold` in absolute value, directly or through other columns of the block
+    /// (connected components of the correlation graph). Columns linked to none are left
+    /// out. Blocks keep the matrix's column order and are ordered by their first column.
+    pub fn blocks(&self, threshold: f64) -> Vec<CovarianceMatrix> {
+        let n = self.dimension;
+        let mut block_of: Vec<Option<usize>> = vec![None; n];
+        let mut blocks: Vec<Vec<usize>> = Vec::new();
+
+        for start in 0..n {
+            if block_of[start].is_some() {
+                continue;
+            }
+            let id = blocks.len();
+            block_of[start] = Some(id);
+            let mut members = vec![start];
+            let mut next = 0;
+            while next < members.len() {
+                let i = members[next];
+                next += 1;
+                for (j, block) in block_of.iter_mut().enumerate() {
+                    if block.is_none() && self.matrix_data[i * n + j].abs() >= threshold {
+                        *block = Some(id);
+                        members.push(j);
+                    }
+                }
+            }
+            members.sort_unstable();
+            blocks.push(members);
+        }
+
+        blocks
+            .into_iter()
+            .filter(|members| members.len() >= 2)
+            .map(|members| CovarianceMatrix {
+                columns: members.iter().map(|&i| self.columns[i].clone()).collect(),
+                matrix_data: members
+                    .iter()
+                    .flat_map(|&i| members.iter().map(move |&j| self.matrix_data[i * n + j]))
+                    .collect(),
+                dimension: members.len(),
+                method: self.method,
+                pseudo_observations: None,
+            })
+            .collect()
+    }
+
     pub fn to_matrix(&self) -> DMatrix<f64> {
         DMatrix::from_row_slice(self.dimension, self.dimension, &self.matrix_data)
     }
+
+    /// Correlation of the latent normals that reproduces the stored coefficients.
+    pub fn copula_matrix(&self) -> DMatrix<f64> {
+        let mut matrix = self.to_matrix();
+        if self.method == CorrelationMethod::Spearman {
+            for i in 0..self.dimension {
+                for j in 0..self.dimension {
+                    if i != j {
+                        matrix[(i, j)] = 2.0 * (std::f64::consts::PI * matrix[(i, j)] / 6.0).sin();
+                    }
+                }
+            }
+        }
+        matrix
+    }
+}
+
+/// Fewest rows holding both values of a column pair that
+/// [`CovarianceMatrix::compute_pairwise`] computes a coefficient from.
+#[cfg(feature = "copula")]
+pub const MIN_PAIRWISE_SAMPLES: usize = 3;
+
+/// Replaces each column of `samples` by its 1-based ranks, averaging ties.
+#[cfg(feature = "copula")]
+fn rank_columns(samples: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let mut ranked: Vec<Vec<f64>> = samples.iter().map(|row| vec![0.0; row.len()]).collect();
+    let n_cols = samples.first().map_or(0, |row| row.len());
+
+    for col in 0..n_cols {
+        let values: Vec<f64> = samples.iter().map(|row| row[col]).collect();
+        for (row, rank) in ranks(&values).into_iter().enumerate() {
+            ranked[row][col] = rank;
+        }
+    }
+
+    ranked
+}
+
+/// 1-based ranks of `values`, averaging ties.
+#[cfg(feature = "copula")]
+fn ranks(values: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+
+    let mut ranked = vec![0.0; values.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+        let rank = (start + end + 1) as f64 / 2.0;
+        for &row in &order[start..end] {
+            ranked[row] = rank;
+        }
+        start = end;
+    }
+    ranked
+}
+
+/// Pearson correlation of two equally long series; 0 if either is constant.
+#[cfg(feature = "copula")]
+fn pearson(xs: &[f64], ys: &[f64]) -> f64 {
+    let n = xs.len() as f64;
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = ys.iter().sum::<f64>() / n;
+
+    let (mut covariance, mut variance_x, mut variance_y) = (0.0, 0.0, 0.0);
+    for (x, y) in xs.iter().zip(ys) {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance_x += (x - mean_x).powi(2);
+        variance_y += (y - mean_y).powi(2);
+    }
+
+    // Same cut-off as the standardization in `compute_with_method`
+    let threshold = 1e-20 * (n - 1.0);
+    if variance_x < threshold || variance_y < threshold {
+        return 0.0;
+    }
+    (covariance / (variance_x * variance_y).sqrt()).clamp(-1.0, 1.0)
 }
 
 #[derive(Debug, Clone)]
 pub struct GaussianCopula {
-    // Lower triangular Cholesky decomposition: L where R = L * L^T
-    cholesky_lower: DMatrix<f64>,
+    // Lower triangular Cholesky decomposition: L where R = L * L^T, row-major
+    cholesky_lower: Vec<f64>,
+    dimension: usize,
 
     columns: Vec<String>,
-    standard_normal: Normal
 }
 
 impl GaussianCopula {
 
+    #[cfg(feature = "copula")]
     pub fn new(covariance: &CovarianceMatrix) -> Result<Self> {
-        let correlation_matrix = covariance.to_matrix();
-
-        // Perform Cholesky decomposition
-        let cholesky = correlation_matrix
-            .clone()
-            .cholesky()
-            .context("Failed to compute Cholesky decomposition - correlation matrix not positive definite")?;
-
-        let standard_normal = Normal::new(0.0, 1.0)
-            .context("Failed to create standard normal distribution")?;
+        let correlation_matrix = covariance.copula_matrix();
+
+        // Perform Cholesky decomposition, repairing the matrix first if sampling noise
+        // (or pairwise deletion) left it indefinite
+        let cholesky = match correlation_matrix.clone().cholesky() {
+            Some(cholesky) => cholesky,
+            None => {
+                let repaired = nearest_correlation_matrix(&correlation_matrix);
+                let adjustment = (&repaired - &correlation_matrix).abs().max();
+                warn!(
+                    columns = ?covariance.columns,
+                    max_adjustment = adjustment,
+                    "Correlation matrix not positive definite, using nearest correlation matrix"
+                );
+                repaired
+                    .cholesky()
+                    .context("Failed to compute Cholesky decomposition - correlation matrix not positive definite")?
+            }
+        };
 
         debug!(
             dimension = covariance.dimension,
             "Initialized Gaussian copula"
         );
 
+        let lower = cholesky.l();
         Ok(Self {
-            cholesky_lower: cholesky.l(),
+            // nalgebra stores column-major, so the transpose's storage is L by rows
+            cholesky_lower: lower.transpose().iter().copied().collect(),
+            dimension: lower.nrows(),
             columns: covariance.columns.clone(),
-            standard_normal,
         })
     }
 
@@ -181,48 +496,286 @@ impl GaussianCopula {
     ///
     /// # Returns
     /// Vector of n uniform [0,1] values with correlation structure
-    pub fn generate_correlated_uniforms(&self, rng: &mut ThreadRng) -> Vec<f64> {
-        let dimension = self.cholesky_lower.nrows();
-
+    pub fn generate_correlated_uniforms<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<f64> {
         // Step 1: Generate independent standard normals
-        let mut independent_normals = DVector::zeros(dimension);
-        for i in 0..dimension {
-            // Box-Muller transform for standard normal
-            let u1: f64 = rng.r#gen();
-            let u2: f64 = rng.r#gen();
-            independent_normals[i] = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
-        }
+        let independent_normals: Vec<f64> = (0..self.dimension)
+            .map(|_| {
+                // Box-Muller transform for standard normal
+                // 1 - U keeps u1 in (0, 1] so ln(u1) stays finite
+                let u1: f64 = 1.0 - rng.r#gen::<f64>();
+                let u2: f64 = rng.r#gen();
+                (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+            })
+            .collect();
 
         // Step 2: Transform to correlated normals via Cholesky matrix
-        let correlated_normals = &self.cholesky_lower * independent_normals;
+        let correlated_normals: Vec<f64> = self
+            .cholesky_lower
+            .chunks_exact(self.dimension.max(1))
+            .map(|row| row.iter().zip(&independent_normals).map(|(l, z)| l * z).sum())
+            .collect();
 
         // Step 3: Convert to uniform [0,1] via standard normal CDF
-        let mut uniforms = Vec::with_capacity(dimension);
-        for i in 0..dimension {
-            let normal_value = correlated_normals[i];
-            let uniform = self.standard_normal.cdf(normal_value);
+        correlated_normals
+            .into_iter()
             // Clamp to [0,1] for numerical stability
-            uniforms.push(uniform.clamp(0.0, 1.0));
+            .map(|normal_value| normal_cdf(normal_value).clamp(0.0, 1.0))
+            .collect()
+    }
+
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    /// Returns the column names.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+}
+
+/// Independent Gaussian copulas over blocks of a table's columns, sampled side by side.
+/// Factoring a few small blocks is cheaper and sturdier than one matrix over a wide table.
+#[derive(Debug, Clone)]
+pub struct BlockCopula {
+    blocks: Vec<GaussianCopula>,
+
+    /// Columns of every block, in the order of the sampled uniforms.
+    columns: Vec<String>,
+}
+
+impl BlockCopula {
+    /// Without `threshold`, a single copula over the whole matrix, which fails if the
+    /// matrix can't be factored. With it, one copula per block of
+    /// [`CovarianceMatrix::blocks`]; a block that can't be factored is left out and
+    /// its columns are sampled independently.
+    #[cfg(feature = "copula")]
+    pub fn new(covariance: &CovarianceMatrix, threshold: Option<f64>) -> Result<Self> {
+        let blocks = match threshold {
+            None => vec![GaussianCopula::new(covariance)?],
+            Some(threshold) => covariance
+                .blocks(threshold)
+                .iter()
+                .filter_map(|block| match GaussianCopula::new(block) {
+                    Ok(copula) => Some(copula),
+                    Err(e) => {
+                        warn!(
+                            columns = ?block.columns,
+                            error = %e,
+                            "Correlation block can't be factored, sampling its columns independently"
+                        );
+                        None
+                    }
+                })
+                .collect(),
+        };
+
+        let columns = blocks.iter().flat_map(|block| block.columns.iter().cloned()).collect();
+        Ok(Self { blocks, columns })
+    }
+
+    /// Correlated uniforms of every block, concatenated in [`BlockCopula::columns`] order.
+    pub fn generate_correlated_uniforms<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<f64> {
+        self.blocks
+            .iter()
+            .flat_map(|block| block.generate_correlated_uniforms(rng))
+            .collect()
+    }
+
+    pub fn dimension(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn block_count(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+}
+
+/// Resamples the rows of [`CovarianceMatrix::pseudo_observations`]. Each drawn value
+/// is spread uniformly over its rank's cell of width 1 / n (a checkerboard copula), so
+/// generated uniforms don't repeat the stored ranks and every margin stays uniform.
+/// NULLs in the drawn row become independent uniforms.
+#[derive(Debug, Clone)]
+pub struct EmpiricalCopula {
+    rows: Vec<Vec<Option<f64>>>,
+
+    /// Cell width per column, one over its number of non-NULL values.
+    widths: Vec<f64>,
+
+    columns: Vec<String>,
+}
+
+impl EmpiricalCopula {
+    #[cfg(feature = "copula")]
+    pub fn new(covariance: &CovarianceMatrix) -> Result<Self> {
+        let rows = covariance
+            .pseudo_observations
+            .clone()
+            .context("Correlation matrix has no pseudo-observations for an empirical copula")?;
+        if rows.is_empty() {
+            anyhow::bail!("Cannot build an empirical copula from zero pseudo-observations");
         }
+        if let Some((i, row)) = rows.iter().enumerate().find(|(_, row)| row.len() != covariance.dimension) {
+            anyhow::bail!("Pseudo-observation {} has {} values, expected {}", i, row.len(), covariance.dimension);
+        }
+
+        let widths = (0..covariance.dimension)
+            .map(|col| 1.0 / rows.iter().filter(|row| row[col].is_some()).count().max(1) as f64)
+            .collect();
+
+        debug!(
+            dimension = covariance.dimension,
+            observations = rows.len(),
+            "Initialized empirical copula"
+        );
 
-        uniforms
+        Ok(Self {
+            rows,
+            widths,
+            columns: covariance.columns.clone(),
+        })
+    }
+
+    /// Uniforms of one stored row, picked at random and jittered within its rank cells.
+    pub fn generate_correlated_uniforms<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<f64> {
+        let row = &self.rows[rng.gen_range(0..self.rows.len())];
+        row.iter()
+            .zip(&self.widths)
+            .map(|(u, width)| match u {
+                Some(u) => (u + (rng.r#gen::<f64>() - 0.5) * width).clamp(0.0, 1.0),
+                None => rng.r#gen(),
+            })
+            .collect()
     }
 
     pub fn dimension(&self) -> usize {
-        self.cholesky_lower.nrows()
+        self.columns.len()
     }
 
-    /// Returns the column names.
     pub fn columns(&self) -> &[String] {
         &self.columns
     }
 }
 
+/// The copula a table's correlated columns are generated from.
+#[derive(Debug, Clone)]
+pub enum TableCopula {
+    Gaussian(BlockCopula),
+    Empirical(EmpiricalCopula),
+}
+
+impl TableCopula {
+    /// An [`EmpiricalCopula`] when the matrix carries pseudo-observations, otherwise a
+    /// [`BlockCopula`] split by `block_threshold`.
+    #[cfg(feature = "copula")]
+    pub fn new(covariance: &CovarianceMatrix, block_threshold: Option<f64>) -> Result<Self> {
+        match covariance.pseudo_observations {
+            Some(_) => EmpiricalCopula::new(covariance).map(TableCopula::Empirical),
+            None => BlockCopula::new(covariance, block_threshold).map(TableCopula::Gaussian),
+        }
+    }
+
+    pub fn kind(&self) -> CopulaKind {
+        match self {
+            TableCopula::Gaussian(_) => CopulaKind::Gaussian,
+            TableCopula::Empirical(_) => CopulaKind::Empirical,
+        }
+    }
+
+    /// Correlated uniforms in [`TableCopula::columns`] order.
+    pub fn generate_correlated_uniforms<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<f64> {
+        match self {
+            TableCopula::Gaussian(copula) => copula.generate_correlated_uniforms(rng),
+            TableCopula::Empirical(copula) => copula.generate_correlated_uniforms(rng),
+        }
+    }
+
+    pub fn dimension(&self) -> usize {
+        match self {
+            TableCopula::Gaussian(copula) => copula.dimension(),
+            TableCopula::Empirical(copula) => copula.dimension(),
+        }
+    }
+
+    /// Copulas sampled side by side; an empirical copula is a single block.
+    pub fn block_count(&self) -> usize {
+        match self {
+            TableCopula::Gaussian(copula) => copula.block_count(),
+            TableCopula::Empirical(_) => 1,
+        }
+    }
+
+    pub fn columns(&self) -> &[String] {
+        match self {
+            TableCopula::Gaussian(copula) => copula.columns(),
+            TableCopula::Empirical(copula) => copula.columns(),
+        }
+    }
+}
+
+/// Iterations of alternating projections before giving up on convergence.
+#[cfg(feature = "copula")]
+const NEAREST_CORRELATION_MAX_ITERATIONS: usize = 100;
+
+/// Frobenius change between iterations at which the projection has converged.
+#[cfg(feature = "copula")]
+const NEAREST_CORRELATION_TOLERANCE: f64 = 1e-9;
+
+/// Smallest eigenvalue kept, so the result is positive definite and not merely semidefinite.
+#[cfg(feature = "copula")]
+const MIN_EIGENVALUE: f64 = 1e-8;
+
+/// Nearest correlation matrix to `matrix` in the Frobenius norm (Higham, 2002):
+/// alternating projections onto the positive-definite cone and onto unit-diagonal
+/// matrices, with Dykstra's correction.
+#[cfg(feature = "copula")]
+pub fn nearest_correlation_matrix(matrix: &DMatrix<f64>) -> DMatrix<f64> {
+    let symmetric = (matrix + matrix.transpose()) / 2.0;
+    let mut y = symmetric.clone();
+    let mut correction = DMatrix::zeros(matrix.nrows(), matrix.ncols());
+
+    for _ in 0..NEAREST_CORRELATION_MAX_ITERATIONS {
+        let r = &y - &correction;
+        let x = project_positive_definite(&r);
+        correction = &x - &r;
+
+        let mut next = x.clone();
+        next.fill_diagonal(1.0);
+
+        let change = (&next - &y).norm();
+        y = next;
+        if change < NEAREST_CORRELATION_TOLERANCE {
+            break;
+        }
+    }
+
+    // The unit-diagonal step can reintroduce tiny negative eigenvalues; clip once more
+    // and rescale back to a correlation matrix
+    let clipped = project_positive_definite(&y);
+    let scale = clipped.diagonal().map(|d| 1.0 / d.sqrt());
+    let mut result = DMatrix::from_fn(clipped.nrows(), clipped.ncols(), |i, j| clipped[(i, j)] * scale[i] * scale[j]);
+    result.fill_diagonal(1.0);
+    result
+}
+
+/// Clips the eigenvalues of a symmetric matrix to at least [`MIN_EIGENVALUE`].
+#[cfg(feature = "copula")]
+fn project_positive_definite(matrix: &DMatrix<f64>) -> DMatrix<f64> {
+    let eigen = matrix.clone().symmetric_eigen();
+    let clipped = eigen.eigenvalues.map(|lambda| lambda.max(MIN_EIGENVALUE));
+    &eigen.eigenvectors * DMatrix::from_diagonal(&clipped) * eigen.eigenvectors.transpose()
+}
+
+#[cfg(feature = "copula")]
 pub struct CovarianceBuilder {
     columns: Vec<String>,
     samples: Vec<Vec<f64>>,
 }
 
+#[cfg(feature = "copula")]
 impl CovarianceBuilder {
     pub fn new(columns: Vec<String>) -> Self {
         Self {
@@ -252,3 +805,175 @@ impl CovarianceBuilder {
         self.samples.len()
     }
 }
+
+#[cfg(all(test, feature = "copula"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spearman_sees_monotone_dependence() -> Result<()> {
+        // y = e^x is perfectly monotone but far from linear
+        let samples: Vec<Vec<f64>> = (1..=50).map(|i| vec![i as f64, (i as f64).exp()]).collect();
+        let names = vec!["x".to_string(), "y".to_string()];
+
+        let pearson = CovarianceMatrix::compute(names.clone(), &samples)?;
+        let spearman = CovarianceMatrix::compute_with_method(names, &samples, CorrelationMethod::Spearman)?;
+
+        assert!(pearson.to_matrix()[(0, 1)] < 0.5);
+        assert!((spearman.to_matrix()[(0, 1)] - 1.0).abs() < 1e-9);
+        assert_eq!(spearman.method, CorrelationMethod::Spearman);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pairwise_deletion_keeps_sparse_columns() -> Result<()> {
+        // a and b are always present; c only where a is NULL, d only where a is present
+        let samples: Vec<Vec<Option<f64>>> = (0..100)
+            .map(|i| {
+                let x = i as f64;
+                let even = i % 2 == 0;
+                vec![even.then_some(x), Some(2.0 * x), (!even).then_some(-x), even.then_some(x * x)]
+            })
+            .collect();
+        let names: Vec<String> = ["a", "b", "c", "d"].map(String::from).to_vec();
+
+        let cov = CovarianceMatrix::compute_pairwise(names.clone(), &samples, CorrelationMethod::Spearman)?;
+        let matrix = cov.to_matrix();
+        assert!((matrix[(0, 1)] - 1.0).abs() < 1e-9);
+        assert!((matrix[(1, 2)] + 1.0).abs() < 1e-9);
+        assert!((matrix[(0, 3)] - 1.0).abs() < 1e-9);
+        // a and c are never present together
+        assert_eq!(matrix[(0, 2)], 0.0);
+        assert_eq!(matrix[(2, 0)], 0.0);
+
+        // Without missing values the coefficients match the listwise computation
+        let complete: Vec<Vec<f64>> = (0..50).map(|i| vec![i as f64, ((i * 7) % 11) as f64, (i as f64).sqrt()]).collect();
+        let listwise = CovarianceMatrix::compute(names[..3].to_vec(), &complete)?;
+        let optional: Vec<Vec<Option<f64>>> = complete.iter().map(|row| row.iter().copied().map(Some).collect()).collect();
+        let pairwise = CovarianceMatrix::compute_pairwise(names[..3].to_vec(), &optional, CorrelationMethod::Pearson)?;
+        for (l, p) in listwise.matrix_data.iter().zip(&pairwise.matrix_data) {
+            assert!((l - p).abs() < 1e-9, "listwise {} pairwise {}", l, p);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_blocks_follow_correlation_graph() -> Result<()> {
+        // a-b and b-c are linked, d only weakly to a, e to nothing
+        let names: Vec<String> = ["a", "b", "c", "d", "e"].map(String::from).to_vec();
+        #[rustfmt::skip]
+        let matrix_data = vec![
+            1.0,  0.8,  0.0,  0.1, 0.0,
+            0.8,  1.0, -0.5,  0.0, 0.0,
+            0.0, -0.5,  1.0,  0.0, 0.0,
+            0.1,  0.0,  0.0,  1.0, 0.6,
+            0.0,  0.0,  0.0,  0.6, 1.0,
+        ];
+        let cov = CovarianceMatrix { columns: names, matrix_data, dimension: 5, method: CorrelationMethod::Pearson, pseudo_observations: None };
+
+        let blocks = cov.blocks(0.3);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].columns, ["a", "b", "c"]);
+        assert_eq!(blocks[0].matrix_data, [1.0, 0.8, 0.0, 0.8, 1.0, -0.5, 0.0, -0.5, 1.0]);
+        assert_eq!(blocks[1].columns, ["d", "e"]);
+        assert_eq!(cov.blocks(0.05).len(), 1);
+        assert!(cov.blocks(0.9).is_empty());
+
+        let copula = BlockCopula::new(&cov, Some(0.3))?;
+        assert_eq!(copula.block_count(), 2);
+        assert_eq!(copula.columns(), ["a", "b", "c", "d", "e"]);
+        let uniforms = copula.generate_correlated_uniforms(&mut rand::thread_rng());
+        assert_eq!(uniforms.len(), 5);
+        assert!(uniforms.iter().all(|u| (0.0..=1.0).contains(u)));
+
+        let whole = BlockCopula::new(&cov, None)?;
+        assert_eq!((whole.block_count(), whole.dimension()), (1, 5));
+        Ok(())
+    }
+
+    #[test]
+    fn test_empirical_copula_resamples_ranks() -> Result<()> {
+        // b follows a only in the upper tail; c is NULL in every other row
+        let samples: Vec<Vec<Option<f64>>> = (0..200)
+            .map(|i| {
+                let x = i as f64;
+                let b = if i >= 180 { x } else { ((i * 37) % 180) as f64 };
+                vec![Some(x), Some(b), (i % 2 == 0).then_some(-x)]
+            })
+            .collect();
+        let names: Vec<String> = ["a", "b", "c"].map(String::from).to_vec();
+        let cov = CovarianceMatrix::compute_pairwise(names, &samples, CorrelationMethod::Spearman)?
+            .with_pseudo_observations(&samples);
+
+        let observations = cov.pseudo_observations.as_ref().unwrap();
+        assert_eq!(observations[0][0], Some(0.5 / 200.0));
+        assert_eq!(observations[199][0], Some(199.5 / 200.0));
+        assert_eq!(observations[1][2], None);
+        assert_eq!(observations[0][2], Some(99.5 / 100.0));
+
+        let copula = TableCopula::new(&cov, Some(0.3))?;
+        assert_eq!(copula.kind(), CopulaKind::Empirical);
+        assert_eq!(copula.columns(), ["a", "b", "c"]);
+
+        let mut rng = rand::thread_rng();
+        let draws: Vec<Vec<f64>> = (0..5_000).map(|_| copula.generate_correlated_uniforms(&mut rng)).collect();
+        assert!(draws.iter().flatten().all(|u| (0.0..=1.0).contains(u)));
+        // Jitter stays within a rank cell, so the upper tail of a still drags b along
+        for draw in draws.iter().filter(|draw| draw[0] > 0.9) {
+            assert!(draw[1] >= 0.9, "{:?}", draw);
+        }
+        // No stored value comes back verbatim
+        assert!(draws.iter().all(|draw| draw[0] != observations[0][0].unwrap()));
+
+        let gaussian = TableCopula::new(&CovarianceMatrix { pseudo_observations: None, ..cov }, None)?;
+        assert_eq!(gaussian.kind(), CopulaKind::Gaussian);
+        Ok(())
+    }
+
+    #[test]
+    fn test_ranks_average_ties() {
+        let ranked = rank_columns(&[vec![5.0], vec![1.0], vec![5.0], vec![3.0]]);
+        assert_eq!(ranked, vec![vec![3.5], vec![1.0], vec![3.5], vec![2.0]]);
+    }
+
+    #[test]
+    fn test_indefinite_matrix_repaired() -> Result<()> {
+        // Pairwise-inconsistent: a~b and b~c strongly positive, a~c strongly negative
+        let cov = CovarianceMatrix {
+            columns: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            matrix_data: vec![1.0, 0.9, -0.9, 0.9, 1.0, 0.9, -0.9, 0.9, 1.0],
+            dimension: 3,
+            method: CorrelationMethod::Pearson,
+            pseudo_observations: None,
+        };
+        assert!(cov.to_matrix().cholesky().is_none());
+
+        let repaired = nearest_correlation_matrix(&cov.to_matrix());
+        assert!(repaired.clone().cholesky().is_some());
+        for i in 0..3 {
+            assert!((repaired[(i, i)] - 1.0).abs() < 1e-12);
+        }
+        assert!((repaired[(0, 1)] - repaired[(1, 0)]).abs() < 1e-12);
+        // Signs of the dependence survive the repair
+        assert!(repaired[(0, 1)] > 0.0 && repaired[(0, 2)] < 0.0);
+
+        let copula = GaussianCopula::new(&cov)?;
+        assert_eq!(copula.dimension(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_spearman_converted_for_copula() {
+        let cov = CovarianceMatrix {
+            columns: vec!["a".to_string(), "b".to_string()],
+            matrix_data: vec![1.0, 0.5, 0.5, 1.0],
+            dimension: 2,
+            method: CorrelationMethod::Spearman,
+            pseudo_observations: None,
+        };
+
+        let latent = cov.copula_matrix();
+        assert!((latent[(0, 1)] - 2.0 * (std::f64::consts::PI / 12.0).sin()).abs() < 1e-12);
+        assert_eq!(latent[(0, 0)], 1.0);
+    }
+}
@@ -0,0 +1,204 @@
+//! Offline scan of CSV exports (`scan --from-csv`).
+//!
+//! The schema (types, keys) comes from a DDL file parsed by [`crate::ddl`]. Each table
+//! is then profiled from `<dir>/<table>.csv`, read by header name, with the same
+//! statistics a database scan computes. Tables or columns without CSV data keep the
+//! DDL placeholder distributions.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use anyhow::{Context, Result};
+use tracing::{info, warn};
+use crate::anonymize::anonymize_genome;
+use crate::copula::CovarianceMatrix;
+use crate::ddl::genome_from_ddl;
+use crate::genome::{DatabaseGenome, GenomeWarning};
+use crate::math::Distribution;
+use crate::privacy::apply_differential_privacy;
+use crate::scan::ScanOptions;
+use crate::scanner::{profile_text_rows, ProfileOptions};
+use crate::schema::Table;
+
+type TableProfile = (HashMap<String, Distribution>, Option<CovarianceMatrix>);
+
+/// Builds a genome from the schema in `ddl` and the CSV files in `dir`. Empty fields
+/// are read as NULL, as `COPY ... CSV` writes them.
+pub fn scan_csv(dir: &Path, ddl: &str, options: &ScanOptions) -> Result<DatabaseGenome> {
+    let mut genome = genome_from_ddl(ddl, options.source_database.clone()).context("Failed to import schema")?;
+
+    if !options.row_filters.is_empty() {
+        warn!("Row filters need a database and are ignored when scanning CSV files");
+    }
+    if options.fk_conditioning {
+        warn!("Foreign key conditioning needs a database and is skipped when scanning CSV files");
+    }
+
+    let profile_options = ProfileOptions {
+        redact_pii: options.redact_pii,
+        markov_text: options.markov_text,
+        fit_distributions: options.fit_distributions,
+        binning: options.binning,
+        bins: Some(options.bins),
+        winsorize: options.winsorize,
+        quantile_sketch: options.quantile_sketch,
+        correlation: options.correlation,
+        metrics: options.metrics.clone(),
+        min_category_count: options.min_category_count,
+        progress: options.progress.clone(),
+        reservoir_capacity: Some(options.reservoir_capacity),
+        reservoir_capacities: options.reservoir_capacities.clone(),
+        recency: options.recency.clone(),
+        copulas: options.copulas.clone(),
+        ..ProfileOptions::default()
+    };
+
+    let mut missing = Vec::new();
+    let files: Vec<(&Table, PathBuf)> = genome
+        .tables
+        .iter()
+        .filter_map(|table| {
+            let path = dir.join(format!("{}.csv", table.name));
+            if path.is_file() {
+                Some((table, path))
+            } else {
+                warn!(table = %table.name, path = %path.display(), "No CSV file for table, keeping placeholder distributions");
+                missing.push(table.name.clone());
+                None
+            }
+        })
+        .collect();
+
+    // Tables are independent, so profile up to `parallel_jobs` files at once
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<(String, Result<TableProfile>)>> = Mutex::new(Vec::new());
+    std::thread::scope(|scope| {
+        for _ in 0..options.parallel_jobs.clamp(1, files.len().max(1)) {
+            scope.spawn(|| {
+                while let Some((table, path)) = files.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let result = profile_csv_table(table, path, &profile_options);
+                    results.lock().unwrap_or_else(|e| e.into_inner()).push((table.name.clone(), result));
+                }
+            });
+        }
+    });
+
+    let profiled = results.into_inner().unwrap_or_else(|e| e.into_inner());
+    info!(tables = profiled.len(), "Profiled CSV files");
+    for (table, result) in profiled {
+        let (distributions, covariance) = result.context(format!("Failed to profile '{}.csv'", table))?;
+        for (column, distribution) in distributions {
+            genome.distributions.insert(DatabaseGenome::make_key(&table, &column), distribution);
+        }
+        if let Some(covariance) = covariance {
+            genome.correlations.insert(table, covariance);
+        }
+    }
+
+    anonymize_genome(&mut genome, &options.anonymized_columns, &mut rand::thread_rng())
+        .context("Failed to anonymize columns")?;
+
+    if let Some(pseudonymizer) = &options.pseudonymizer {
+        pseudonymizer.apply(&mut genome);
+    }
+
+    if let Some(dp) = &options.dp {
+        apply_differential_privacy(&mut genome, dp, &mut rand::thread_rng())
+            .context("Failed to apply differential privacy")?;
+    }
+
+    for table in &missing {
+        genome.warn(GenomeWarning::table(table, "no CSV file, its columns use placeholder distributions"));
+    }
+    genome.collect_warnings(|table| !missing.iter().any(|m| m == table));
+
+    genome
+        .validate()
+        .context("Genome validation failed")?;
+
+    Ok(genome)
+}
+
+/// Profiles the columns of `table` found in the header of `path`.
+fn profile_csv_table(table: &Table, path: &Path, options: &ProfileOptions) -> Result<TableProfile> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .from_path(path)
+        .context(format!("Failed to open '{}'", path.display()))?;
+    let headers = reader.headers().context("Failed to read CSV header")?.clone();
+
+    let mut positions = Vec::new();
+    let mut profiled = Table::new(table.name.clone(), Vec::new(), Vec::new());
+    for column in &table.columns {
+        match headers.iter().position(|h| h.trim() == column.name) {
+            Some(position) => {
+                positions.push(position);
+                profiled.columns.push(column.clone());
+            }
+            None => warn!(
+                table = %table.name,
+                column = %column.name,
+                "Column missing from CSV header, keeping placeholder distribution"
+            ),
+        }
+    }
+    for header in headers.iter().filter(|h| !table.columns.iter().any(|c| c.name == h.trim())) {
+        warn!(table = %table.name, column = %header, "CSV column not in schema, ignoring it");
+    }
+
+    let rows = reader.records().map(|record| {
+        let record = record.context("Failed to read CSV record")?;
+        Ok(positions
+            .iter()
+            .map(|&i| record.get(i).filter(|value| !value.is_empty()).map(str::to_string))
+            .collect())
+    });
+    profile_text_rows(&profiled, rows, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Histogram;
+
+    const DDL: &str = "
+        CREATE TABLE users (id integer PRIMARY KEY, tier text, score float8, visits integer, joined timestamptz);
+        CREATE TABLE orders (id integer PRIMARY KEY, user_id integer REFERENCES users (id), amount numeric);
+    ";
+
+    #[test]
+    fn test_scan_csv() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut users = String::from("id,tier,score,visits,joined,legacy\n");
+        for i in 1..=200 {
+            let tier = if i % 4 == 0 { "pro" } else { "free" };
+            let joined = if i % 10 == 0 { String::new() } else { format!("2024-01-{:02} 10:00:00+00", i % 28 + 1) };
+            users.push_str(&format!("{},{},{},{},{},x\n", i, tier, i as f64 * 1.5, i * 3, joined));
+        }
+        std::fs::write(dir.path().join("users.csv"), users)?;
+
+        let genome = scan_csv(dir.path(), DDL, &ScanOptions::default())?;
+
+        let tier = genome.get_distribution("users", "tier").unwrap();
+        match &tier.histogram {
+            Histogram::Categorical { frequencies, .. } => {
+                assert_eq!(frequencies["pro"], 50);
+                assert_eq!(frequencies["free"], 150);
+            }
+            other => panic!("Expected categorical histogram, got {:?}", other),
+        }
+        let joined = genome.get_distribution("users", "joined").unwrap();
+        assert_eq!((joined.null_count, joined.total_count), (20, 200));
+        assert_eq!(genome.get_distribution("users", "visits").unwrap().max, Some(600.0));
+
+        // score and visits move together
+        let correlation = &genome.correlations["users"];
+        assert!(correlation.columns.contains(&"visits".to_string()));
+
+        // orders.csv is missing, so orders keeps its DDL placeholders
+        let amount = genome.get_distribution("orders", "amount").unwrap();
+        assert_eq!(amount.total_count, crate::ddl::NOMINAL_ROWS);
+        Ok(())
+    }
+}
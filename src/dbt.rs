@@ -0,0 +1,614 @@
+//! Genome import from a dbt project (`scan --from-dbt`).
+//!
+//! Reads the `manifest.json` that `dbt compile` writes, and optionally the
+//! `catalog.json` of `dbt docs generate`:
+//!
+//! * models, seeds, snapshots and sources become tables, named by their alias or
+//!   identifier (ephemeral models are skipped);
+//! * columns and their types come from the catalog, falling back to the `data_type`
+//!   documented in the manifest, and to text without either;
+//! * `relationships` tests and `foreign_key` constraints become foreign keys;
+//! * `primary_key` constraints, or a column with both `unique` and `not_null` tests,
+//!   become the primary key; `not_null` tests make columns NOT NULL;
+//! * `accepted_values` tests become the categories of the column.
+//!
+//! Like [`crate::ddl`], every column gets a placeholder distribution.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use tracing::{debug, info, warn};
+use crate::ddl::{base_type_name, placeholder_distribution, type_length};
+use crate::genome::DatabaseGenome;
+use crate::schema::{map_sql_type_to_datatype, Column, DataType, ForeignKey, Table};
+
+#[derive(Debug, Default, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    nodes: BTreeMap<String, ManifestNode>,
+    #[serde(default)]
+    sources: BTreeMap<String, ManifestNode>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ManifestNode {
+    resource_type: String,
+    name: String,
+    alias: Option<String>,
+    identifier: Option<String>,
+    config: NodeConfig,
+    columns: BTreeMap<String, ManifestColumn>,
+    constraints: Vec<Constraint>,
+    test_metadata: Option<TestMetadata>,
+    attached_node: Option<String>,
+    column_name: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct NodeConfig {
+    materialized: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ManifestColumn {
+    name: String,
+    data_type: Option<String>,
+    constraints: Vec<Constraint>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Constraint {
+    #[serde(rename = "type")]
+    kind: String,
+    columns: Vec<String>,
+    /// `parent_table (parent_column)`, the pre-1.9 way to name the referenced column.
+    expression: Option<String>,
+    /// `ref('parent')` or `source('schema', 'parent')`.
+    to: Option<String>,
+    to_columns: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct TestMetadata {
+    name: String,
+    kwargs: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Catalog {
+    #[serde(default)]
+    nodes: BTreeMap<String, CatalogNode>,
+    #[serde(default)]
+    sources: BTreeMap<String, CatalogNode>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CatalogNode {
+    #[serde(default)]
+    columns: BTreeMap<String, CatalogColumn>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CatalogColumn {
+    name: String,
+    #[serde(rename = "type")]
+    data_type: String,
+    #[serde(default)]
+    index: usize,
+}
+
+/// A table under construction, with what the tests say about its columns.
+struct DbtTable {
+    /// dbt `name`, which `ref()` and `source()` use.
+    ref_name: String,
+    table: Table,
+    unique: HashSet<String>,
+    not_null: HashSet<String>,
+    accepted_values: HashMap<String, Vec<String>>,
+    text_lengths: HashMap<String, usize>,
+}
+
+impl DbtTable {
+    /// Name of the column called `name`, ignoring case, since warehouses like
+    /// Snowflake report columns in upper case.
+    fn column_name(&self, name: &str) -> Option<String> {
+        let name = name.trim_matches('"');
+        self.table
+            .columns
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(name))
+            .map(|c| c.name.clone())
+    }
+}
+
+/// A foreign key whose parent is resolved once every table is known.
+struct PendingForeignKey {
+    /// Index of the child table.
+    table: usize,
+    column: String,
+    /// `ref()` name of the parent model or source.
+    parent: String,
+    /// `None` for the parent's primary key.
+    parent_column: Option<String>,
+}
+
+/// Builds a genome from the contents of a dbt `manifest.json` and, when given, its
+/// `catalog.json`. Fails when the manifest holds no models, seeds or sources.
+pub fn genome_from_dbt(manifest: &str, catalog: Option<&str>, source: Option<String>) -> Result<DatabaseGenome> {
+    let manifest: Manifest = serde_json::from_str(manifest).context("Failed to parse dbt manifest")?;
+    let catalog: Catalog = match catalog {
+        Some(catalog) => serde_json::from_str(catalog).context("Failed to parse dbt catalog")?,
+        None => Catalog::default(),
+    };
+
+    let mut tables: Vec<DbtTable> = Vec::new();
+    let mut by_id: HashMap<&str, usize> = HashMap::new();
+    let relations = manifest.nodes.iter().chain(&manifest.sources).filter(|(_, node)| {
+        matches!(node.resource_type.as_str(), "model" | "seed" | "snapshot" | "source")
+            && node.config.materialized.as_deref() != Some("ephemeral")
+    });
+    for (unique_id, node) in relations {
+        let catalog_node = catalog.nodes.get(unique_id).or_else(|| catalog.sources.get(unique_id));
+        let table = dbt_table(node, catalog_node);
+        if tables.iter().any(|t| t.table.name == table.table.name) {
+            warn!(table = %table.table.name, node = %unique_id, "Two dbt nodes build the same table, keeping the first");
+            continue;
+        }
+        by_id.insert(unique_id, tables.len());
+        tables.push(table);
+    }
+    if tables.is_empty() {
+        bail!("No models, seeds or sources found in dbt manifest");
+    }
+
+    let mut foreign_keys = Vec::new();
+    for (unique_id, node) in manifest.nodes.iter().chain(&manifest.sources) {
+        if let Some(&index) = by_id.get(unique_id.as_str()) {
+            collect_constraints(&mut tables[index], node, index, &mut foreign_keys);
+        }
+    }
+    let tests = manifest
+        .nodes
+        .values()
+        .filter_map(|node| Some((node, node.test_metadata.as_ref()?)))
+        .filter(|(node, _)| node.resource_type == "test");
+    for (node, test) in tests {
+        apply_test(&mut tables, &by_id, node, test, &mut foreign_keys);
+    }
+
+    infer_primary_keys(&mut tables);
+    resolve_foreign_keys(&mut tables, foreign_keys);
+
+    let mut distributions = HashMap::new();
+    for dbt in &tables {
+        for column in &dbt.table.columns {
+            let labels = dbt.accepted_values.get(&column.name).map(Vec::as_slice);
+            let length = dbt.text_lengths.get(&column.name).copied();
+            distributions.insert(
+                DatabaseGenome::make_key(&dbt.table.name, &column.name),
+                placeholder_distribution(column, labels, length),
+            );
+        }
+    }
+
+    let tables: Vec<Table> = tables.into_iter().map(|t| t.table).collect();
+    info!(
+        tables = tables.len(),
+        foreign_keys = tables.iter().map(|t| t.foreign_keys.len()).sum::<usize>(),
+        "Built genome from dbt manifest"
+    );
+    Ok(DatabaseGenome::with_metadata(tables, distributions, source))
+}
+
+fn dbt_table(node: &ManifestNode, catalog: Option<&CatalogNode>) -> DbtTable {
+    let name = node
+        .alias
+        .clone()
+        .or_else(|| node.identifier.clone())
+        .unwrap_or_else(|| node.name.clone());
+
+    let declared: Vec<(String, Option<String>)> = match catalog {
+        Some(catalog) => {
+            let mut columns: Vec<&CatalogColumn> = catalog.columns.values().collect();
+            columns.sort_by_key(|c| c.index);
+            columns.iter().map(|c| (c.name.clone(), Some(c.data_type.clone()))).collect()
+        }
+        None => node.columns.values().map(|c| (c.name.clone(), c.data_type.clone())).collect(),
+    };
+
+    let mut text_lengths = HashMap::new();
+    let columns = declared
+        .into_iter()
+        .map(|(column, declared)| {
+            let data_type = match &declared {
+                Some(declared) => warehouse_type(declared, &name, &column),
+                None => {
+                    debug!(table = %name, column = %column, "Column has no documented type, treating it as text");
+                    DataType::Text
+                }
+            };
+            if data_type == DataType::Text
+                && let Some(length) = declared.as_deref().and_then(type_length)
+            {
+                text_lengths.insert(column.clone(), length);
+            }
+            Column::new(column, data_type, true, false)
+        })
+        .collect();
+
+    DbtTable {
+        ref_name: node.name.clone(),
+        table: Table::new(name, columns, Vec::new()),
+        unique: HashSet::new(),
+        not_null: HashSet::new(),
+        accepted_values: HashMap::new(),
+        text_lengths,
+    }
+}
+
+/// Maps a warehouse type name (Postgres, Snowflake, BigQuery, ...) to a genome type.
+fn warehouse_type(declared: &str, table: &str, column: &str) -> DataType {
+    let lowered = declared.to_lowercase();
+    let base = base_type_name(&lowered);
+    let scale = lowered
+        .split_once(',')
+        .and_then(|(_, rest)| rest.trim_end_matches(|c: char| c == ')' || c.is_whitespace()).trim().parse::<u32>().ok());
+
+    match base.as_str() {
+        "string" => DataType::Text,
+        "int64" | "int32" | "int16" | "tinyint" | "byteint" | "long" => DataType::Integer,
+        "float64" | "double" | "bignumeric" | "bigdecimal" => DataType::Float,
+        // Snowflake's NUMBER defaults to a scale of 0
+        "number" if scale.unwrap_or(0) == 0 => DataType::Integer,
+        "numeric" | "decimal" if scale == Some(0) => DataType::Integer,
+        "number" => DataType::Float,
+        "datetime" | "timestamp_ntz" | "timestamp_ltz" | "timestamp_tz" => DataType::Timestamp,
+        other => map_sql_type_to_datatype(other, other, table, column),
+    }
+}
+
+/// Applies the model and column constraints of `node`; foreign keys are queued until
+/// every table is known.
+fn collect_constraints(
+    dbt: &mut DbtTable,
+    node: &ManifestNode,
+    index: usize,
+    foreign_keys: &mut Vec<PendingForeignKey>,
+) {
+    let column_constraints = node
+        .columns
+        .values()
+        .flat_map(|column| column.constraints.iter().map(move |c| (Some(column.name.as_str()), c)));
+    let model_constraints = node.constraints.iter().map(|c| (None, c));
+
+    for (column, constraint) in column_constraints.chain(model_constraints) {
+        let columns: Vec<String> = match column {
+            Some(column) => vec![column.to_string()],
+            None => constraint.columns.clone(),
+        };
+        let columns: Vec<String> = columns.iter().filter_map(|c| dbt.column_name(c)).collect();
+
+        match constraint.kind.as_str() {
+            "primary_key" => {
+                for column in dbt.table.columns.iter_mut().filter(|c| columns.contains(&c.name)) {
+                    column.is_primary_key = true;
+                    column.is_nullable = false;
+                }
+            }
+            "not_null" => dbt.not_null.extend(columns),
+            "unique" => dbt.unique.extend(columns),
+            "foreign_key" => {
+                let parent = constraint
+                    .to
+                    .as_deref()
+                    .and_then(ref_target)
+                    .map(|target| (target, constraint.to_columns.clone()))
+                    .or_else(|| constraint.expression.as_deref().and_then(parse_fk_expression));
+                let Some((parent, parent_columns)) = parent else {
+                    debug!(table = %dbt.table.name, "Foreign key constraint without a parent, skipping it");
+                    continue;
+                };
+                for (i, column) in columns.into_iter().enumerate() {
+                    foreign_keys.push(PendingForeignKey {
+                        table: index,
+                        column,
+                        parent: parent.clone(),
+                        parent_column: parent_columns.get(i).cloned(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn apply_test(
+    tables: &mut [DbtTable],
+    by_id: &HashMap<&str, usize>,
+    node: &ManifestNode,
+    test: &TestMetadata,
+    foreign_keys: &mut Vec<PendingForeignKey>,
+) {
+    let kwarg = |name: &str| test.kwargs.get(name).and_then(|v| v.as_str());
+
+    let index = node
+        .attached_node
+        .as_deref()
+        .and_then(|id| by_id.get(id).copied())
+        .or_else(|| {
+            let model = ref_target(kwarg("model")?)?;
+            tables.iter().position(|t| t.ref_name == model)
+        });
+    let Some(index) = index else {
+        return;
+    };
+    let dbt = &mut tables[index];
+    let Some(column) = node.column_name.as_deref().or(kwarg("column_name")).and_then(|c| dbt.column_name(c)) else {
+        return;
+    };
+
+    match test.name.as_str() {
+        "not_null" => {
+            dbt.not_null.insert(column);
+        }
+        "unique" => {
+            dbt.unique.insert(column);
+        }
+        "accepted_values" => {
+            let values: Vec<String> = test
+                .kwargs
+                .get("values")
+                .and_then(|v| v.as_array())
+                .map(|values| {
+                    values
+                        .iter()
+                        .map(|v| v.as_str().map_or_else(|| v.to_string(), str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default();
+            if !values.is_empty() {
+                dbt.accepted_values.insert(column, values);
+            }
+        }
+        "relationships" => {
+            let Some(parent) = kwarg("to").and_then(ref_target) else {
+                debug!(table = %dbt.table.name, column = %column, "Relationships test without a parent, skipping it");
+                return;
+            };
+            foreign_keys.push(PendingForeignKey {
+                table: index,
+                column,
+                parent,
+                parent_column: kwarg("field").map(str::to_string),
+            });
+        }
+        _ => {}
+    }
+}
+
+/// Marks `not_null` columns and, in tables without a primary key constraint, makes
+/// the first column with both `unique` and `not_null` tests the primary key.
+fn infer_primary_keys(tables: &mut [DbtTable]) {
+    for dbt in tables {
+        let has_primary_key = dbt.table.columns.iter().any(|c| c.is_primary_key);
+        let mut inferred = has_primary_key;
+        for column in &mut dbt.table.columns {
+            if dbt.not_null.contains(&column.name) {
+                column.is_nullable = false;
+            }
+            if !inferred && dbt.unique.contains(&column.name) && dbt.not_null.contains(&column.name) {
+                column.is_primary_key = true;
+                inferred = true;
+            }
+        }
+    }
+}
+
+/// Attaches the queued foreign keys to their tables. A missing parent column means the
+/// parent's primary key, and a parent without one gets the referenced column as its
+/// key. Parents outside the manifest are dropped.
+fn resolve_foreign_keys(tables: &mut [DbtTable], foreign_keys: Vec<PendingForeignKey>) {
+    for PendingForeignKey { table: index, column, parent, parent_column } in foreign_keys {
+        let Some(parent_index) = tables.iter().position(|t| t.ref_name == parent || t.table.name == parent) else {
+            warn!(
+                table = %tables[index].table.name,
+                column = %column,
+                parent = %parent,
+                "Foreign key references a model that is not in the manifest, ignoring it"
+            );
+            continue;
+        };
+        let parent_table = &mut tables[parent_index];
+        let target_col = match parent_column {
+            Some(name) => parent_table.column_name(&name),
+            None => match parent_table.table.primary_keys().as_slice() {
+                [pk] => Some(pk.name.clone()),
+                _ => None,
+            },
+        };
+        let Some(target_col) = target_col else {
+            warn!(
+                table = %tables[index].table.name,
+                column = %column,
+                parent = %parent,
+                "Foreign key parent column not found, ignoring it"
+            );
+            continue;
+        };
+        // A relationship target is unique; it's the only key a parent without a
+        // primary key has, and generated children need parent keys to point at
+        if parent_table.table.primary_keys().is_empty()
+            && let Some(key) = parent_table.table.columns.iter_mut().find(|c| c.name == target_col)
+        {
+            key.is_primary_key = true;
+            key.is_nullable = false;
+        }
+        let target_table = parent_table.table.name.clone();
+
+        let table = &mut tables[index].table;
+        if !table.foreign_keys.iter().any(|fk| fk.source_col == column) {
+            table.foreign_keys.push(ForeignKey::new(column, target_table, target_col));
+        }
+    }
+}
+
+/// The model or source table named by `ref('model')`, `ref('package', 'model')` or
+/// `source('schema', 'table')`, also inside a Jinja expression.
+fn ref_target(expression: &str) -> Option<String> {
+    let start = ["ref(", "source("]
+        .iter()
+        .filter_map(|call| expression.find(call).map(|i| i + call.len()))
+        .min()?;
+    let arguments = &expression[start..start + expression[start..].find(')')?];
+
+    let mut quoted = Vec::new();
+    let mut rest = arguments;
+    while let Some(open) = rest.find(['\'', '"']) {
+        let quote = rest[open..].chars().next()?;
+        let close = rest[open + 1..].find(quote)? + open + 1;
+        quoted.push(&rest[open + 1..close]);
+        rest = &rest[close + 1..];
+    }
+    quoted.last().map(|name| name.to_string())
+}
+
+/// Parent table and columns of a `schema.parent (id)` foreign key expression.
+fn parse_fk_expression(expression: &str) -> Option<(String, Vec<String>)> {
+    let (table, columns) = expression.split_once('(')?;
+    let table = table.trim().rsplit('.').next()?.trim_matches('"').to_string();
+    let columns = columns
+        .trim_end()
+        .trim_end_matches(')')
+        .split(',')
+        .map(|c| c.trim().trim_matches('"').to_string())
+        .filter(|c| !c.is_empty())
+        .collect();
+    Some((table, columns))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Histogram;
+
+    const MANIFEST: &str = r#"{
+        "metadata": {"dbt_version": "1.8.0"},
+        "nodes": {
+            "model.shop.customers": {
+                "resource_type": "model", "name": "customers", "alias": "dim_customers",
+                "config": {"materialized": "table"},
+                "columns": {
+                    "customer_id": {"name": "customer_id", "data_type": "integer"},
+                    "status": {"name": "status", "data_type": "varchar(8)"}
+                }
+            },
+            "model.shop.orders": {
+                "resource_type": "model", "name": "orders",
+                "config": {"materialized": "table"},
+                "columns": {
+                    "order_id": {"name": "order_id", "constraints": [{"type": "primary_key"}]},
+                    "customer_id": {"name": "customer_id"},
+                    "product_sku": {"name": "product_sku"}
+                },
+                "constraints": [{"type": "foreign_key", "columns": ["product_sku"], "expression": "raw.products (sku)"}]
+            },
+            "model.shop.stg_tmp": {
+                "resource_type": "model", "name": "stg_tmp", "config": {"materialized": "ephemeral"}, "columns": {}
+            },
+            "test.shop.unique_customers_customer_id": {
+                "resource_type": "test", "name": "unique_customers_customer_id",
+                "attached_node": "model.shop.customers", "column_name": "customer_id",
+                "test_metadata": {"name": "unique", "kwargs": {"column_name": "customer_id"}}
+            },
+            "test.shop.not_null_customers_customer_id": {
+                "resource_type": "test", "name": "not_null_customers_customer_id",
+                "attached_node": "model.shop.customers", "column_name": "customer_id",
+                "test_metadata": {"name": "not_null", "kwargs": {"column_name": "customer_id"}}
+            },
+            "test.shop.accepted_values_customers_status": {
+                "resource_type": "test", "name": "accepted_values_customers_status",
+                "attached_node": "model.shop.customers", "column_name": "status",
+                "test_metadata": {"name": "accepted_values", "kwargs": {"column_name": "status", "values": ["active", "churned"]}}
+            },
+            "test.shop.relationships_orders_customer_id": {
+                "resource_type": "test", "name": "relationships_orders_customer_id",
+                "column_name": "customer_id",
+                "test_metadata": {"name": "relationships", "kwargs": {
+                    "column_name": "customer_id", "to": "ref('customers')", "field": "customer_id",
+                    "model": "{{ get_where_subquery(ref('orders')) }}"
+                }}
+            }
+        },
+        "sources": {
+            "source.shop.raw.products": {
+                "resource_type": "source", "name": "products", "identifier": "products",
+                "columns": {"sku": {"name": "sku", "data_type": "text"}}
+            }
+        }
+    }"#;
+
+    const CATALOG: &str = r#"{
+        "nodes": {
+            "model.shop.orders": {"columns": {
+                "ORDER_ID": {"name": "ORDER_ID", "type": "NUMBER(38,0)", "index": 1},
+                "PRODUCT_SKU": {"name": "PRODUCT_SKU", "type": "TEXT", "index": 3},
+                "CUSTOMER_ID": {"name": "CUSTOMER_ID", "type": "NUMBER(38,0)", "index": 2},
+                "AMOUNT": {"name": "AMOUNT", "type": "NUMBER(10,2)", "index": 4}
+            }}
+        }
+    }"#;
+
+    #[test]
+    fn test_genome_from_dbt() -> Result<()> {
+        let genome = genome_from_dbt(MANIFEST, Some(CATALOG), None)?;
+        let names: Vec<&str> = genome.tables.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["dim_customers", "orders", "products"]);
+        genome.validate()?;
+
+        let customers = genome.get_table("dim_customers").unwrap();
+        assert!(customers.columns[0].is_primary_key && !customers.columns[0].is_nullable);
+        match &genome.get_distribution("dim_customers", "status").unwrap().histogram {
+            Histogram::Categorical { frequencies, .. } => assert_eq!(frequencies.len(), 2),
+            other => panic!("Expected categorical histogram, got {:?}", other),
+        }
+
+        // Catalog columns keep their order and warehouse types
+        let orders = genome.get_table("orders").unwrap();
+        let columns: Vec<(&str, &DataType)> = orders.columns.iter().map(|c| (c.name.as_str(), &c.data_type)).collect();
+        assert_eq!(columns, vec![
+            ("ORDER_ID", &DataType::Integer),
+            ("CUSTOMER_ID", &DataType::Integer),
+            ("PRODUCT_SKU", &DataType::Text),
+            ("AMOUNT", &DataType::Float),
+        ]);
+        assert!(orders.columns[0].is_primary_key);
+
+        let fks: Vec<(&str, &str, &str)> = orders
+            .foreign_keys
+            .iter()
+            .map(|fk| (fk.source_col.as_str(), fk.target_table.as_str(), fk.target_col.as_str()))
+            .collect();
+        assert_eq!(fks, vec![("PRODUCT_SKU", "products", "sku"), ("CUSTOMER_ID", "dim_customers", "customer_id")]);
+        // The referenced column becomes the key of a parent without one
+        assert!(genome.get_table("products").unwrap().columns[0].is_primary_key);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dbt_helpers() {
+        assert_eq!(ref_target("ref('customers')").as_deref(), Some("customers"));
+        assert_eq!(ref_target("{{ ref(\"pkg\", \"orders\") }}").as_deref(), Some("orders"));
+        assert_eq!(ref_target("source('raw', 'products')").as_deref(), Some("products"));
+        assert_eq!(ref_target("customers"), None);
+        assert_eq!(parse_fk_expression("raw.products (sku)"), Some(("products".to_string(), vec!["sku".to_string()])));
+        assert_eq!(warehouse_type("INT64", "t", "c"), DataType::Integer);
+        assert_eq!(warehouse_type("TIMESTAMP_NTZ(9)", "t", "c"), DataType::Timestamp);
+        assert_eq!(warehouse_type("numeric(12, 2)", "t", "c"), DataType::Float);
+        assert!(genome_from_dbt(r#"{"nodes": {}}"#, None, None).is_err());
+    }
+}
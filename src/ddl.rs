@@ -0,0 +1,526 @@
+//! Offline genome import from SQL DDL (`scan --from-ddl`).
+//!
+//! Builds the schema half of a genome from `CREATE TABLE` statements, for example a
+//! `pg_dump --schema-only` file, when the source database can't be reached. Keys
+//! come from column and table constraints as well as `ALTER TABLE ... ADD CONSTRAINT`,
+//! and `CREATE TYPE ... AS ENUM` columns draw from their labels. Every other column
+//! gets a placeholder distribution (uniform over a default range, or faker values for
+//! text named like PII) that can be refined with `edit` or replaced by a real scan.
+//!
+//! Statements that aren't schema definitions, or that the parser doesn't understand,
+//! are skipped.
+
+use std::collections::{BTreeMap, HashMap};
+use anyhow::{bail, Context, Result};
+use sqlparser::ast::{
+    AlterColumnOperation, AlterTableOperation, ColumnDef, ColumnOption, CreateTable, Expr, ObjectName,
+    Statement, TableConstraint, UserDefinedTypeRepresentation,
+};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::{Token, Tokenizer};
+use tracing::{debug, info, warn};
+use crate::genome::DatabaseGenome;
+use crate::geometry::PLACEHOLDER_BOX;
+use crate::hstore::format_pair;
+use crate::math::{Bernoulli, BinScale, Distribution, Histogram};
+use crate::network::{PLACEHOLDER_NETWORKS, PLACEHOLDER_OUIS};
+use crate::pattern::{PatternSegment, TextPattern};
+use crate::schema::{map_sql_type_to_datatype, unmapped_sql_type, Column, DataType, ForeignKey, Table};
+use crate::semantic::classify_by_name;
+
+/// Row count the placeholder distributions pretend to have been profiled from, so
+/// `edit --null-rate` has counts to rescale.
+pub const NOMINAL_ROWS: u64 = 1000;
+
+/// Range of integer and float columns.
+const NUMERIC_RANGE: (f64, f64) = (0.0, 1000.0);
+
+/// Range of timestamp columns: 2020-01-01 to 2025-01-01 UTC, in epoch seconds.
+const TIMESTAMP_RANGE: (f64, f64) = (1_577_836_800.0, 1_735_689_600.0);
+
+/// Lengths in bytes of bytea values.
+const BYTEA_LENGTH: (f64, f64) = (16.0, 256.0);
+
+/// Range of interval columns: up to 30 days, in seconds.
+const INTERVAL_RANGE: (f64, f64) = (0.0, 2_592_000.0);
+
+/// Lengths of generated lowercase text, capped by `varchar(n)`.
+const TEXT_LENGTH: (usize, usize) = (4, 12);
+
+/// Builds a genome from the DDL in `sql`. Fails when no table is defined.
+pub fn genome_from_ddl(sql: &str, source: Option<String>) -> Result<DatabaseGenome> {
+    let mut schema = DdlSchema::default();
+    for statement in parse_statements(sql)? {
+        schema.apply(statement);
+    }
+    if schema.tables.is_empty() {
+        bail!("No CREATE TABLE statements found");
+    }
+    schema.resolve_foreign_keys();
+
+    let mut distributions = HashMap::new();
+    for table in &schema.tables {
+        for column in &table.columns {
+            let key = (table.name.clone(), column.name.clone());
+            let labels = schema.column_types.get(&key).and_then(|type_name| schema.enums.get(type_name)).map(Vec::as_slice);
+            let distribution = placeholder_distribution(column, labels, schema.text_lengths.get(&key).copied());
+            distributions.insert(DatabaseGenome::make_key(&table.name, &column.name), distribution);
+        }
+    }
+
+    info!(
+        tables = schema.tables.len(),
+        columns = distributions.len(),
+        enums = schema.enums.len(),
+        "Built genome from DDL"
+    );
+    let mut genome = DatabaseGenome::with_metadata(schema.tables, distributions, source);
+    genome.collect_warnings(|_| false);
+    Ok(genome)
+}
+
+/// Splits `sql` at top-level semicolons and parses each statement on its own, so one
+/// unsupported statement doesn't lose the rest of the file.
+fn parse_statements(sql: &str) -> Result<Vec<Statement>> {
+    let dialect = PostgreSqlDialect {};
+
+    // psql meta-commands such as `\connect` aren't SQL
+    let sql: String = sql
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('\\'))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let tokens = Tokenizer::new(&dialect, &sql)
+        .tokenize()
+        .context("Failed to tokenize DDL")?;
+
+    let mut statements = Vec::new();
+    let mut skipped = 0;
+    for chunk in tokens.split(|t| *t == Token::SemiColon) {
+        if chunk.iter().all(|t| matches!(t, Token::Whitespace(_))) {
+            continue;
+        }
+        match Parser::new(&dialect).with_tokens(chunk.to_vec()).parse_statement() {
+            Ok(statement) => statements.push(statement),
+            Err(e) => {
+                skipped += 1;
+                debug!(error = %e, "Skipping DDL statement that could not be parsed");
+            }
+        }
+    }
+    if skipped > 0 {
+        info!(skipped, "Skipped DDL statements that could not be parsed");
+    }
+    Ok(statements)
+}
+
+#[derive(Default)]
+struct DdlSchema {
+    tables: Vec<Table>,
+    /// Enum labels by type name.
+    enums: HashMap<String, Vec<String>>,
+    /// Declared type name of every column, keyed by (table, column).
+    column_types: HashMap<(String, String), String>,
+    /// `varchar(n)` limits, keyed by (table, column).
+    text_lengths: HashMap<(String, String), usize>,
+}
+
+impl DdlSchema {
+    fn apply(&mut self, statement: Statement) {
+        match statement {
+            Statement::CreateTable(create) => self.create_table(create),
+            Statement::CreateType {
+                name,
+                representation: UserDefinedTypeRepresentation::Enum { labels },
+            } => {
+                let labels = labels.into_iter().map(|label| label.value).collect();
+                self.enums.insert(object_name(&name).to_lowercase(), labels);
+            }
+            Statement::AlterTable { name, operations, .. } => {
+                let table_name = object_name(&name);
+                for operation in operations {
+                    self.alter_table(&table_name, operation);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn create_table(&mut self, create: CreateTable) {
+        let name = object_name(&create.name);
+        if self.table_mut(&name).is_some() {
+            warn!(table = %name, "Table defined twice in DDL, keeping the first definition");
+            return;
+        }
+
+        let mut table = Table::new(name, Vec::new(), Vec::new());
+        for def in &create.columns {
+            let column = self.column(&table.name, def);
+            for option in &def.options {
+                if let ColumnOption::ForeignKey { foreign_table, referred_columns, .. } = &option.option {
+                    table.foreign_keys.push(ForeignKey::new(
+                        column.name.clone(),
+                        object_name(foreign_table),
+                        referred_columns.first().map(|c| c.value.clone()).unwrap_or_default(),
+                    ));
+                }
+            }
+            table.columns.push(column);
+        }
+        for constraint in create.constraints {
+            add_constraint(&mut table, constraint);
+        }
+        table.set_aside_derived_columns();
+        self.tables.push(table);
+    }
+
+    fn column(&mut self, table: &str, def: &ColumnDef) -> Column {
+        let declared = def.data_type.to_string().to_lowercase();
+        let base = base_type_name(&declared);
+        let key = (table.to_string(), def.name.value.clone());
+
+        let data_type = if self.enums.contains_key(&base) {
+            DataType::Text
+        } else {
+            match base.as_str() {
+                serial if is_serial(serial) => DataType::Integer,
+                other => map_sql_type_to_datatype(other, other, table, &def.name.value),
+            }
+        };
+        if data_type == DataType::Text
+            && let Some(length) = type_length(&declared)
+        {
+            self.text_lengths.insert(key.clone(), length);
+        }
+        self.column_types.insert(key, base.clone());
+
+        let mut column = Column::new(def.name.value.clone(), data_type, true, false);
+        if !self.enums.contains_key(&base) && !is_serial(&base) {
+            column.unmapped_type = unmapped_sql_type(&base, &base);
+        }
+        column.money = base == "money";
+        if is_serial(&base) {
+            column.serial_sequence = Some(format!("{}_{}_seq", table, column.name));
+            column.has_default = true;
+            column.is_nullable = false;
+        }
+        for option in &def.options {
+            match &option.option {
+                ColumnOption::NotNull => column.is_nullable = false,
+                ColumnOption::Unique { is_primary: true, .. } => {
+                    column.is_primary_key = true;
+                    column.is_nullable = false;
+                }
+                ColumnOption::Default(expr) => {
+                    column.has_default = true;
+                    if let Some(sequence) = nextval_sequence(expr) {
+                        column.serial_sequence = Some(sequence);
+                    }
+                }
+                ColumnOption::Generated { .. } => column.has_default = true,
+                _ => {}
+            }
+        }
+        column
+    }
+
+    fn alter_table(&mut self, table_name: &str, operation: AlterTableOperation) {
+        let Some(table) = self.table_mut(table_name) else {
+            debug!(table = %table_name, "Skipping ALTER TABLE of a table not defined in the DDL");
+            return;
+        };
+        match operation {
+            AlterTableOperation::AddConstraint(constraint) => add_constraint(table, constraint),
+            AlterTableOperation::AlterColumn { column_name, op } => {
+                let Some(column) = table.columns.iter_mut().find(|c| c.name == column_name.value) else {
+                    return;
+                };
+                match op {
+                    AlterColumnOperation::SetDefault { value } => {
+                        column.has_default = true;
+                        if let Some(sequence) = nextval_sequence(&value) {
+                            column.serial_sequence = Some(sequence);
+                        }
+                    }
+                    AlterColumnOperation::SetNotNull => column.is_nullable = false,
+                    AlterColumnOperation::DropNotNull => column.is_nullable = true,
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn table_mut(&mut self, name: &str) -> Option<&mut Table> {
+        self.tables.iter_mut().find(|t| t.name == name)
+    }
+
+    /// Points `REFERENCES parent` without a column list at the parent's primary key,
+    /// and drops foreign keys to tables the DDL doesn't define.
+    fn resolve_foreign_keys(&mut self) {
+        let primary_keys: HashMap<String, String> = self
+            .tables
+            .iter()
+            .filter_map(|t| match t.primary_keys().as_slice() {
+                [pk] => Some((t.name.clone(), pk.name.clone())),
+                _ => None,
+            })
+            .collect();
+        let defined: Vec<String> = self.tables.iter().map(|t| t.name.clone()).collect();
+
+        for table in &mut self.tables {
+            table.foreign_keys.retain_mut(|fk| {
+                if !defined.contains(&fk.target_table) {
+                    warn!(
+                        table = %table.name,
+                        column = %fk.source_col,
+                        target = %fk.target_table,
+                        "Foreign key references a table not defined in the DDL, ignoring it"
+                    );
+                    return false;
+                }
+                if fk.target_col.is_empty() {
+                    match primary_keys.get(&fk.target_table) {
+                        Some(pk) => fk.target_col = pk.clone(),
+                        None => {
+                            warn!(
+                                table = %table.name,
+                                column = %fk.source_col,
+                                target = %fk.target_table,
+                                "Foreign key target has no single-column primary key, ignoring it"
+                            );
+                            return false;
+                        }
+                    }
+                }
+                true
+            });
+        }
+    }
+}
+
+fn add_constraint(table: &mut Table, constraint: TableConstraint) {
+    match constraint {
+        TableConstraint::PrimaryKey { columns, .. } => {
+            for name in columns {
+                if let Some(column) = table.columns.iter_mut().find(|c| c.name == name.value) {
+                    column.is_primary_key = true;
+                    column.is_nullable = false;
+                }
+            }
+        }
+        TableConstraint::ForeignKey { columns, foreign_table, referred_columns, .. } => {
+            let target_table = object_name(&foreign_table);
+            for (index, column) in columns.iter().enumerate() {
+                let target_col = referred_columns.get(index).map(|c| c.value.clone()).unwrap_or_default();
+                table
+                    .foreign_keys
+                    .push(ForeignKey::new(column.value.clone(), target_table.clone(), target_col));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Unqualified name, the way scanned genomes name tables and types.
+fn object_name(name: &ObjectName) -> String {
+    name.0.last().map(|ident| ident.value.clone()).unwrap_or_default()
+}
+
+/// Lowercase type name without schema, modifiers or array brackets, e.g.
+/// `character varying` for `CHARACTER VARYING(20)[]`.
+pub(crate) fn base_type_name(declared: &str) -> String {
+    let mut base = String::with_capacity(declared.len());
+    let mut depth = 0;
+    for c in declared.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            '[' | ']' => {}
+            _ if depth == 0 => base.push(c),
+            _ => {}
+        }
+    }
+    let base = base.split_whitespace().collect::<Vec<_>>().join(" ");
+    match base.rsplit_once('.') {
+        Some((_, name)) => name.trim_matches('"').to_string(),
+        None => base.trim_matches('"').to_string(),
+    }
+}
+
+fn is_serial(type_name: &str) -> bool {
+    matches!(type_name, "serial" | "serial4" | "bigserial" | "serial8" | "smallserial" | "serial2")
+}
+
+/// The `n` of `varchar(n)` or `char(n)`.
+pub(crate) fn type_length(declared: &str) -> Option<usize> {
+    let start = declared.find('(')?;
+    let end = declared[start..].find(')')? + start;
+    declared[start + 1..end].trim().parse().ok()
+}
+
+/// The sequence named in a `nextval('seq'::regclass)` default.
+fn nextval_sequence(expr: &Expr) -> Option<String> {
+    let text = expr.to_string();
+    let start = text.to_lowercase().find("nextval('")? + "nextval('".len();
+    let end = text[start..].find('\'')? + start;
+    Some(text[start..end].to_string())
+}
+
+/// Distribution of a column without profiled data: uniform over a default range, the
+/// given labels drawn evenly, or faker values for text named like PII.
+pub(crate) fn placeholder_distribution(column: &Column, labels: Option<&[String]>, text_length: Option<usize>) -> Distribution {
+    let numeric = |(min, max): (f64, f64)| {
+        Distribution::new(Some(min), Some(max), 0, NOMINAL_ROWS, NOMINAL_ROWS as usize, Histogram::Numeric {
+            bins: vec![min, max],
+            frequencies: vec![NOMINAL_ROWS],
+            scale: BinScale::Linear,
+        })
+    };
+    let text = |histogram: Histogram| Distribution::new(None, None, 0, NOMINAL_ROWS, NOMINAL_ROWS as usize, histogram);
+    let labeled = |labels: &[String]| {
+        let per_label = (NOMINAL_ROWS / labels.len() as u64).max(1);
+        let frequencies: BTreeMap<String, u64> = labels.iter().map(|l| (l.clone(), per_label)).collect();
+        let unique_count = frequencies.len();
+        Distribution::new(None, None, 0, per_label * unique_count as u64, unique_count, Histogram::Categorical {
+            frequencies,
+            truncated: false,
+            long_tail: None,
+        })
+    };
+
+    match column.data_type {
+        DataType::Integer | DataType::Float => numeric(NUMERIC_RANGE),
+        DataType::Timestamp => numeric(TIMESTAMP_RANGE),
+        DataType::Bytea => numeric(BYTEA_LENGTH),
+        DataType::Interval => numeric(INTERVAL_RANGE),
+        DataType::Inet | DataType::Cidr => labeled(&PLACEHOLDER_NETWORKS.map(String::from)),
+        DataType::MacAddr => labeled(&PLACEHOLDER_OUIS.map(String::from)),
+        DataType::Geometry | DataType::Geography => labeled(&[PLACEHOLDER_BOX.to_string()]),
+        DataType::Hstore => labeled(&[format_pair("key", Some("value"))]),
+        DataType::Boolean => Distribution::new(None, None, 0, NOMINAL_ROWS, 2, Histogram::Boolean {
+            bernoulli: Bernoulli {
+                true_count: NOMINAL_ROWS / 2,
+                false_count: NOMINAL_ROWS - NOMINAL_ROWS / 2,
+            },
+        }),
+        DataType::Uuid => text(Histogram::Pattern { pattern: uuid_pattern() }),
+        DataType::Text => {
+            if let Some(labels) = labels.filter(|l| !l.is_empty()) {
+                return labeled(labels);
+            }
+            if let Some(semantic_type) = classify_by_name(&column.name) {
+                let mut distribution = text(Histogram::Semantic { semantic_type });
+                distribution.pii = semantic_type.is_pii().then_some(semantic_type);
+                return distribution;
+            }
+            let max_len = text_length.map_or(TEXT_LENGTH.1, |n| n.min(TEXT_LENGTH.1)).max(1);
+            let min_len = TEXT_LENGTH.0.min(max_len);
+            text(Histogram::Pattern {
+                pattern: TextPattern {
+                    segments: vec![PatternSegment::Lower { min_len, max_len }],
+                },
+            })
+        }
+    }
+}
+
+/// Hex digits in UUID layout; digits alone are valid hex.
+fn uuid_pattern() -> TextPattern {
+    let mut segments = Vec::new();
+    for (i, len) in [8, 4, 4, 4, 12].into_iter().enumerate() {
+        if i > 0 {
+            segments.push(PatternSegment::Literal { text: "-".to_string() });
+        }
+        segments.push(PatternSegment::Digits { min_len: len, max_len: len });
+    }
+    TextPattern { segments }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synth::{SynthesisConfig, Synthesizer};
+
+    const DDL: &str = r#"
+        -- pg_dump style
+        SET statement_timeout = 0;
+        SELECT pg_catalog.set_config('search_path', '', false);
+        \connect shop
+
+        CREATE TYPE public.tier AS ENUM ('free', 'pro', 'enterprise');
+
+        CREATE TABLE public.users (
+            id integer NOT NULL,
+            email character varying(255) NOT NULL,
+            tier public.tier,
+            premium boolean DEFAULT false,
+            created_at timestamp with time zone NOT NULL
+        );
+
+        CREATE TABLE orders (
+            id bigserial PRIMARY KEY,
+            user_id integer NOT NULL REFERENCES users,
+            amount numeric(10, 2),
+            code varchar(6),
+            coupon_id integer REFERENCES coupons (id),
+            search tsvector
+        );
+
+        CREATE FUNCTION touch() RETURNS trigger AS $$ BEGIN NEW.x := 1; RETURN NEW; END; $$ LANGUAGE plpgsql;
+
+        ALTER TABLE ONLY public.users ALTER COLUMN id SET DEFAULT nextval('public.users_id_seq'::regclass);
+        ALTER TABLE ONLY public.users ADD CONSTRAINT users_pkey PRIMARY KEY (id);
+    "#;
+
+    #[test]
+    fn test_genome_from_ddl() -> Result<()> {
+        let genome = genome_from_ddl(DDL, Some("schema.sql".to_string()))?;
+        assert_eq!(genome.tables.len(), 2);
+        genome.validate()?;
+
+        let users = genome.get_table("users").unwrap();
+        let id = &users.columns[0];
+        assert!(id.is_primary_key && !id.is_nullable && id.has_default);
+        assert_eq!(id.serial_sequence.as_deref(), Some("public.users_id_seq"));
+        assert_eq!(users.columns[4].data_type, DataType::Timestamp);
+        assert!(users.columns[2].is_nullable);
+
+        let orders = genome.get_table("orders").unwrap();
+        assert_eq!(orders.primary_keys()[0].serial_sequence.as_deref(), Some("orders_id_seq"));
+        assert_eq!(orders.columns[2].data_type, DataType::Float);
+        // The foreign key to the undefined coupons table is dropped
+        assert_eq!(orders.foreign_keys.len(), 1);
+        assert_eq!(orders.foreign_keys[0].target_table, "users");
+        assert_eq!(orders.foreign_keys[0].target_col, "id");
+        // The full-text column is left to the target
+        assert_eq!(orders.columns.len(), 5);
+        assert_eq!(orders.derived_columns.get("search").map(String::as_str), Some("tsvector"));
+        assert!(genome.warnings.iter().any(|w| w.column.as_deref() == Some("search")));
+
+        let email = genome.get_distribution("users", "email").unwrap();
+        assert!(matches!(email.histogram, Histogram::Semantic { .. }));
+        match &genome.get_distribution("users", "tier").unwrap().histogram {
+            Histogram::Categorical { frequencies, .. } => assert_eq!(frequencies.len(), 3),
+            other => panic!("Expected categorical histogram, got {:?}", other),
+        }
+
+        let config = SynthesisConfig { rows_per_table: 20, seed: Some(1), ..SynthesisConfig::default() };
+        let synthesizer = Synthesizer::new(genome, config)?;
+        let result = synthesizer.generate()?;
+        for line in result.get_copy_data("orders").unwrap().lines() {
+            let code = line.split('\t').nth(3).unwrap();
+            assert!((1..=6).contains(&code.len()), "code '{}' longer than varchar(6)", code);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_genome_from_ddl_without_tables() {
+        assert!(genome_from_ddl("CREATE INDEX idx ON users (email);", None).is_err());
+        assert_eq!(base_type_name("public.\"tier\"[]"), "tier");
+        assert_eq!(base_type_name("timestamp(3) with time zone"), "timestamp with time zone");
+        assert_eq!(type_length("character varying(20)"), Some(20));
+    }
+}
@@ -0,0 +1,590 @@
+//! Drift detection between two genomes: schema changes and statistically
+//! significant distribution shifts.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use crate::genome::DatabaseGenome;
+use crate::math::{Distribution, Histogram};
+use crate::schema::{DataType, Table};
+use crate::special::chi_squared_sf;
+
+#[derive(Debug, Clone)]
+pub struct DiffOptions {
+    /// Significance level for the KS and chi-square tests.
+    pub alpha: f64,
+
+    /// Minimum effect size (KS statistic, total variation distance or absolute null-rate
+    /// change) for a shift to be reported. Keeps huge samples from flagging noise.
+    pub min_effect: f64,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            alpha: 0.01,
+            min_effect: 0.05,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GenomeDiff {
+    pub added_tables: Vec<String>,
+    pub removed_tables: Vec<String>,
+    pub tables: Vec<TableDiff>,
+}
+
+impl GenomeDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_tables.is_empty() && self.removed_tables.is_empty() && self.tables.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TableDiff {
+    pub table: String,
+    pub added_columns: Vec<String>,
+    pub removed_columns: Vec<String>,
+    pub type_changes: Vec<TypeChange>,
+    pub shifts: Vec<DistributionShift>,
+}
+
+impl TableDiff {
+    fn is_empty(&self) -> bool {
+        self.added_columns.is_empty()
+            && self.removed_columns.is_empty()
+            && self.type_changes.is_empty()
+            && self.shifts.is_empty()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeChange {
+    pub column: String,
+    pub from: DataType,
+    pub to: DataType,
+}
+
+#[derive(Debug, Clone)]
+pub struct DistributionShift {
+    pub column: String,
+    pub null_rate: (f64, f64),
+    pub detail: ShiftDetail,
+}
+
+#[derive(Debug, Clone)]
+pub enum ShiftDetail {
+    Numeric {
+        ks_statistic: f64,
+        critical_value: f64,
+        mean: (Option<f64>, Option<f64>),
+        p50: (Option<f64>, Option<f64>),
+        p95: (Option<f64>, Option<f64>),
+    },
+    Categorical {
+        total_variation: f64,
+        p_value: f64,
+        added_values: usize,
+        removed_values: usize,
+        /// Largest share changes: (value, share in a, share in b)
+        top_changes: Vec<(String, f64, f64)>,
+    },
+    /// The column is profiled as a different kind of histogram (or a different
+    /// semantic type or pattern), so the values aren't compared.
+    KindChanged {
+        from: String,
+        to: String,
+    },
+    /// Only the NULL rate moved.
+    NullRate,
+}
+
+/// Compares genome `a` (baseline) against genome `b`.
+pub fn diff_genomes(a: &DatabaseGenome, b: &DatabaseGenome, options: &DiffOptions) -> GenomeDiff {
+    let a_tables: HashMap<&str, &Table> = a.tables.iter().map(|t| (t.name.as_str(), t)).collect();
+    let b_tables: HashMap<&str, &Table> = b.tables.iter().map(|t| (t.name.as_str(), t)).collect();
+
+    let names: BTreeSet<&str> = a_tables.keys().chain(b_tables.keys()).copied().collect();
+
+    let mut diff = GenomeDiff::default();
+
+    for name in names {
+        match (a_tables.get(name), b_tables.get(name)) {
+            (Some(_), None) => diff.removed_tables.push(name.to_string()),
+            (None, Some(_)) => diff.added_tables.push(name.to_string()),
+            (Some(ta), Some(tb)) => {
+                let table_diff = diff_table(a, ta, b, tb, options);
+                if !table_diff.is_empty() {
+                    diff.tables.push(table_diff);
+                }
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+
+    diff
+}
+
+fn diff_table(
+    a: &DatabaseGenome,
+    ta: &Table,
+    b: &DatabaseGenome,
+    tb: &Table,
+    options: &DiffOptions,
+) -> TableDiff {
+    let mut diff = TableDiff {
+        table: ta.name.clone(),
+        ..TableDiff::default()
+    };
+
+    for col in &ta.columns {
+        if !tb.columns.iter().any(|c| c.name == col.name) {
+            diff.removed_columns.push(col.name.clone());
+        }
+    }
+
+    for col_b in &tb.columns {
+        let Some(col_a) = ta.columns.iter().find(|c| c.name == col_b.name) else {
+            diff.added_columns.push(col_b.name.clone());
+            continue;
+        };
+
+        if col_a.data_type != col_b.data_type {
+            diff.type_changes.push(TypeChange {
+                column: col_b.name.clone(),
+                from: col_a.data_type.clone(),
+                to: col_b.data_type.clone(),
+            });
+            continue;
+        }
+
+        let (Some(da), Some(db)) = (
+            a.get_distribution(&ta.name, &col_a.name),
+            b.get_distribution(&tb.name, &col_b.name),
+        ) else {
+            continue;
+        };
+
+        if let Some(shift) = compare_distributions(&col_b.name, da, db, options) {
+            diff.shifts.push(shift);
+        }
+    }
+
+    diff
+}
+
+/// Returns a shift when the two distributions differ significantly.
+pub fn compare_distributions(
+    column: &str,
+    a: &Distribution,
+    b: &Distribution,
+    options: &DiffOptions,
+) -> Option<DistributionShift> {
+    let null_rate = (a.null_rate(), b.null_rate());
+    let null_shifted = (null_rate.0 - null_rate.1).abs() >= options.min_effect;
+
+    let detail = match (&a.histogram, &b.histogram) {
+        (
+            Histogram::Numeric { .. } | Histogram::Quantile { .. } | Histogram::Discrete { .. },
+            Histogram::Numeric { .. } | Histogram::Quantile { .. } | Histogram::Discrete { .. },
+        ) => numeric_shift(a, b, options),
+        (
+            Histogram::Categorical { frequencies: fa, .. },
+            Histogram::Categorical { frequencies: fb, .. },
+        ) => categorical_shift(fa, fb, options),
+        (Histogram::Boolean { bernoulli: ba }, Histogram::Boolean { bernoulli: bb }) => {
+            categorical_shift(&ba.frequencies(), &bb.frequencies(), options)
+        }
+        (
+            Histogram::Semantic { semantic_type: sa },
+            Histogram::Semantic { semantic_type: sb },
+        ) if sa == sb => None,
+        (Histogram::Pattern { pattern: pa }, Histogram::Pattern { pattern: pb }) if pa == pb => None,
+        (Histogram::Markov { .. }, Histogram::Markov { .. }) => None,
+        (ha, hb) => Some(ShiftDetail::KindChanged {
+            from: histogram_kind(ha),
+            to: histogram_kind(hb),
+        }),
+    };
+
+    match detail {
+        Some(detail) => Some(DistributionShift {
+            column: column.to_string(),
+            null_rate,
+            detail,
+        }),
+        None if null_shifted => Some(DistributionShift {
+            column: column.to_string(),
+            null_rate,
+            detail: ShiftDetail::NullRate,
+        }),
+        None => None,
+    }
+}
+
+/// Short name of a histogram's kind for reports, with the semantic type or pattern.
+fn histogram_kind(histogram: &Histogram) -> String {
+    match histogram {
+        Histogram::Numeric { .. } => "numeric".to_string(),
+        Histogram::Categorical { .. } => "categorical".to_string(),
+        Histogram::Semantic { semantic_type } => format!("semantic ({})", semantic_type),
+        Histogram::Pattern { pattern } => format!("pattern ({})", pattern),
+        Histogram::Markov { .. } => "markov".to_string(),
+        Histogram::Quantile { .. } => "quantile".to_string(),
+        Histogram::Boolean { .. } => "boolean".to_string(),
+        Histogram::Discrete { .. } => "discrete".to_string(),
+    }
+}
+
+fn numeric_shift(a: &Distribution, b: &Distribution, options: &DiffOptions) -> Option<ShiftDetail> {
+    let ((points_a, n), (points_b, m)) = (a.numeric_support()?, b.numeric_support()?);
+    if n == 0 || m == 0 {
+        return None;
+    }
+
+    let ks_statistic = ks_statistic(a, b, points_a.into_iter().chain(points_b));
+    let critical_value = ks_critical_value(n, m, options.alpha);
+
+    if ks_statistic <= critical_value || ks_statistic < options.min_effect {
+        return None;
+    }
+
+    Some(ShiftDetail::Numeric {
+        ks_statistic,
+        critical_value,
+        mean: (a.mean(), b.mean()),
+        p50: (a.quantile(0.5), b.quantile(0.5)),
+        p95: (a.quantile(0.95), b.quantile(0.95)),
+    })
+}
+
+/// Two-sample Kolmogorov–Smirnov statistic evaluated at the given points.
+pub fn ks_statistic(a: &Distribution, b: &Distribution, points: impl Iterator<Item = f64>) -> f64 {
+    points
+        .filter_map(|x| Some((a.cdf(x)? - b.cdf(x)?).abs()))
+        .fold(0.0, f64::max)
+}
+
+/// Large-sample critical value of the two-sample KS test:
+/// c(α) · sqrt((n + m) / (n · m)) with c(α) = sqrt(-ln(α / 2) / 2).
+pub fn ks_critical_value(n: u64, m: u64, alpha: f64) -> f64 {
+    let (n, m) = (n as f64, m as f64);
+    let c_alpha = (-(alpha / 2.0).ln() / 2.0).sqrt();
+    c_alpha * ((n + m) / (n * m)).sqrt()
+}
+
+/// Asymptotic p-value of the two-sample KS test (Kolmogorov distribution with the
+/// Stephens small-sample correction).
+pub fn ks_p_value(statistic: f64, n: u64, m: u64) -> f64 {
+    if n == 0 || m == 0 {
+        return 1.0;
+    }
+
+    let effective_n = (n as f64 * m as f64) / (n as f64 + m as f64);
+    kolmogorov_p_value(statistic, effective_n)
+}
+
+/// Tail probability of the Kolmogorov distribution for a KS statistic computed over
+/// `effective_n` observations. Shared by the one- and two-sample tests.
+pub fn kolmogorov_p_value(statistic: f64, effective_n: f64) -> f64 {
+    let sqrt_n = effective_n.sqrt();
+    let lambda = (sqrt_n + 0.12 + 0.11 / sqrt_n) * statistic;
+
+    if lambda < 1e-3 {
+        return 1.0;
+    }
+
+    let mut sum = 0.0;
+    for k in 1..=100 {
+        let k = k as f64;
+        let term = 2.0 * (-1.0f64).powf(k - 1.0) * (-2.0 * k * k * lambda * lambda).exp();
+        sum += term;
+        if term.abs() < 1e-12 {
+            break;
+        }
+    }
+
+    sum.clamp(0.0, 1.0)
+}
+
+fn categorical_shift(
+    fa: &BTreeMap<String, u64>,
+    fb: &BTreeMap<String, u64>,
+    options: &DiffOptions,
+) -> Option<ShiftDetail> {
+    let total_a: u64 = fa.values().sum();
+    let total_b: u64 = fb.values().sum();
+    if total_a == 0 || total_b == 0 {
+        return None;
+    }
+
+    let values: BTreeSet<&String> = fa.keys().chain(fb.keys()).collect();
+
+    let mut total_variation = 0.0;
+    let mut changes = Vec::with_capacity(values.len());
+
+    for value in &values {
+        let share_a = fa.get(*value).copied().unwrap_or(0) as f64 / total_a as f64;
+        let share_b = fb.get(*value).copied().unwrap_or(0) as f64 / total_b as f64;
+        total_variation += (share_a - share_b).abs();
+        changes.push(((*value).clone(), share_a, share_b));
+    }
+    total_variation /= 2.0;
+
+    let p_value = chi_square_homogeneity_p_value(fa, fb);
+
+    if p_value >= options.alpha || total_variation < options.min_effect {
+        return None;
+    }
+
+    changes.sort_by(|x, y| {
+        (y.1 - y.2).abs().partial_cmp(&(x.1 - x.2).abs()).unwrap_or(std::cmp::Ordering::Equal)
+    });
+    changes.truncate(5);
+
+    Some(ShiftDetail::Categorical {
+        total_variation,
+        p_value,
+        added_values: fb.keys().filter(|k| !fa.contains_key(*k)).count(),
+        removed_values: fa.keys().filter(|k| !fb.contains_key(*k)).count(),
+        top_changes: changes,
+    })
+}
+
+/// p-value of Pearson's chi-square test that two frequency tables come from the
+/// same categorical distribution.
+pub fn chi_square_homogeneity_p_value(fa: &BTreeMap<String, u64>, fb: &BTreeMap<String, u64>) -> f64 {
+    let total_a: f64 = fa.values().sum::<u64>() as f64;
+    let total_b: f64 = fb.values().sum::<u64>() as f64;
+    let grand_total = total_a + total_b;
+
+    let values: BTreeSet<&String> = fa.keys().chain(fb.keys()).collect();
+    if values.len() < 2 || total_a == 0.0 || total_b == 0.0 {
+        return 1.0;
+    }
+
+    let mut statistic = 0.0;
+    for value in &values {
+        let oa = fa.get(*value).copied().unwrap_or(0) as f64;
+        let ob = fb.get(*value).copied().unwrap_or(0) as f64;
+        let row_total = oa + ob;
+
+        let ea = row_total * total_a / grand_total;
+        let eb = row_total * total_b / grand_total;
+
+        statistic += (oa - ea).powi(2) / ea + (ob - eb).powi(2) / eb;
+    }
+
+    let degrees_of_freedom = (values.len() - 1) as f64;
+    if degrees_of_freedom <= 0.0 {
+        return 1.0;
+    }
+    chi_squared_sf(statistic, degrees_of_freedom)
+}
+
+fn fmt_opt(value: Option<f64>) -> String {
+    value.map_or_else(|| "-".to_string(), |v| format!("{:.4}", v))
+}
+
+impl fmt::Display for GenomeDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No drift detected");
+        }
+
+        for table in &self.added_tables {
+            writeln!(f, "+ table {}", table)?;
+        }
+        for table in &self.removed_tables {
+            writeln!(f, "- table {}", table)?;
+        }
+
+        for table in &self.tables {
+            writeln!(f, "~ table {}", table.table)?;
+
+            for col in &table.added_columns {
+                writeln!(f, "    + column {}", col)?;
+            }
+            for col in &table.removed_columns {
+                writeln!(f, "    - column {}", col)?;
+            }
+            for change in &table.type_changes {
+                writeln!(f, "    ~ column {}: type {} -> {}", change.column, change.from, change.to)?;
+            }
+
+            for shift in &table.shifts {
+                writeln!(
+                    f,
+                    "    ~ column {}: null rate {:.2}% -> {:.2}%",
+                    shift.column,
+                    shift.null_rate.0 * 100.0,
+                    shift.null_rate.1 * 100.0
+                )?;
+
+                match &shift.detail {
+                    ShiftDetail::Numeric { ks_statistic, critical_value, mean, p50, p95 } => {
+                        writeln!(f, "        KS D = {:.4} (critical {:.4})", ks_statistic, critical_value)?;
+                        writeln!(f, "        mean {} -> {}", fmt_opt(mean.0), fmt_opt(mean.1))?;
+                        writeln!(f, "        p50  {} -> {}", fmt_opt(p50.0), fmt_opt(p50.1))?;
+                        writeln!(f, "        p95  {} -> {}", fmt_opt(p95.0), fmt_opt(p95.1))?;
+                    }
+                    ShiftDetail::Categorical { total_variation, p_value, added_values, removed_values, top_changes } => {
+                        writeln!(
+                            f,
+                            "        TVD = {:.4}, chi-square p = {:.2e}, {} new / {} vanished values",
+                            total_variation, p_value, added_values, removed_values
+                        )?;
+                        for (value, share_a, share_b) in top_changes {
+                            writeln!(
+                                f,
+                                "        {:?}: {:.2}% -> {:.2}%",
+                                value,
+                                share_a * 100.0,
+                                share_b * 100.0
+                            )?;
+                        }
+                    }
+                    ShiftDetail::KindChanged { from, to } => {
+                        writeln!(f, "        kind {} -> {}", from, to)?;
+                    }
+                    ShiftDetail::NullRate => {}
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::BinScale;
+    use crate::schema::Column;
+
+    fn numeric(frequencies: Vec<u64>) -> Distribution {
+        let bins = (0..=frequencies.len()).map(|i| i as f64 * 10.0).collect();
+        Distribution::new(Some(0.0), Some(100.0), 0, 1000, 100, Histogram::Numeric { bins, frequencies, scale: BinScale::Linear })
+    }
+
+    fn categorical(pairs: &[(&str, u64)]) -> Distribution {
+        Distribution::new(
+            None,
+            None,
+            0,
+            1000,
+            pairs.len(),
+            Histogram::Categorical {
+                frequencies: pairs.iter().map(|(k, v)| (k.to_string(), *v)).collect(),
+                truncated: false,
+                long_tail: None,
+            },
+        )
+    }
+
+    fn genome(columns: Vec<Column>, distributions: Vec<(&str, Distribution)>) -> DatabaseGenome {
+        let tables = vec![Table::new("users".to_string(), columns, vec![])];
+        let distributions = distributions
+            .into_iter()
+            .map(|(col, d)| (DatabaseGenome::make_key("users", col), d))
+            .collect();
+        DatabaseGenome::new(tables, distributions)
+    }
+
+    #[test]
+    fn test_identical_genomes_have_no_drift() {
+        let cols = vec![Column::new("age".to_string(), DataType::Integer, false, false)];
+        let a = genome(cols.clone(), vec![("age", numeric(vec![100; 10]))]);
+        let b = genome(cols, vec![("age", numeric(vec![100; 10]))]);
+
+        assert!(diff_genomes(&a, &b, &DiffOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn test_schema_changes_detected() {
+        let a = genome(
+            vec![
+                Column::new("age".to_string(), DataType::Integer, false, false),
+                Column::new("zip".to_string(), DataType::Integer, false, false),
+            ],
+            vec![],
+        );
+        let b = genome(
+            vec![
+                Column::new("age".to_string(), DataType::Integer, false, false),
+                Column::new("zip".to_string(), DataType::Text, false, false),
+                Column::new("email".to_string(), DataType::Text, false, false),
+            ],
+            vec![],
+        );
+
+        let diff = diff_genomes(&a, &b, &DiffOptions::default());
+        assert_eq!(diff.tables.len(), 1);
+        assert_eq!(diff.tables[0].added_columns, vec!["email".to_string()]);
+        assert_eq!(diff.tables[0].type_changes.len(), 1);
+        assert_eq!(diff.tables[0].type_changes[0].column, "zip");
+    }
+
+    #[test]
+    fn test_numeric_shift_detected() {
+        let cols = vec![Column::new("age".to_string(), DataType::Integer, false, false)];
+        let a = genome(cols.clone(), vec![("age", numeric(vec![500, 500, 0, 0, 0, 0, 0, 0, 0, 0]))]);
+        let b = genome(cols, vec![("age", numeric(vec![0, 0, 0, 0, 0, 0, 0, 0, 500, 500]))]);
+
+        let diff = diff_genomes(&a, &b, &DiffOptions::default());
+        let shift = &diff.tables[0].shifts[0];
+        match &shift.detail {
+            ShiftDetail::Numeric { ks_statistic, .. } => assert!((ks_statistic - 1.0).abs() < 1e-9),
+            other => panic!("Expected numeric shift, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_categorical_shift_detected() {
+        let cols = vec![Column::new("status".to_string(), DataType::Text, false, false)];
+        let a = genome(cols.clone(), vec![("status", categorical(&[("active", 900), ("closed", 100)]))]);
+        let b = genome(cols, vec![("status", categorical(&[("active", 500), ("closed", 400), ("frozen", 100)]))]);
+
+        let diff = diff_genomes(&a, &b, &DiffOptions::default());
+        match &diff.tables[0].shifts[0].detail {
+            ShiftDetail::Categorical { added_values, total_variation, .. } => {
+                assert_eq!(*added_values, 1);
+                assert!(*total_variation > 0.3);
+            }
+            other => panic!("Expected categorical shift, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_kind_change_is_not_a_null_rate_shift() {
+        let a = categorical(&[("a", 500), ("b", 500)]);
+        let b = numeric(vec![100; 10]);
+        assert_eq!(a.null_rate(), b.null_rate());
+
+        let shift = compare_distributions("age", &a, &b, &DiffOptions::default()).expect("kind change reported");
+        match &shift.detail {
+            ShiftDetail::KindChanged { from, to } => assert_eq!((from.as_str(), to.as_str()), ("categorical", "numeric")),
+            other => panic!("expected a kind change, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_ks_p_value_bounds() {
+        assert!(ks_p_value(0.0, 1000, 1000) > 0.99);
+        assert!(ks_p_value(0.5, 1000, 1000) < 1e-6);
+
+        // D at the alpha=0.05 critical value should land near p=0.05
+        let critical = ks_critical_value(1000, 1000, 0.05);
+        let p = ks_p_value(critical, 1000, 1000);
+        assert!((0.03..0.07).contains(&p), "p = {}", p);
+    }
+
+    #[test]
+    fn test_small_categorical_noise_ignored() {
+        let fa: BTreeMap<String, u64> = [("a".to_string(), 500), ("b".to_string(), 500)].into_iter().collect();
+        let fb: BTreeMap<String, u64> = [("a".to_string(), 505), ("b".to_string(), 495)].into_iter().collect();
+
+        assert!(chi_square_homogeneity_p_value(&fa, &fb) > 0.5);
+        assert!(categorical_shift(&fa, &fb, &DiffOptions::default()).is_none());
+    }
+}
@@ -0,0 +1,408 @@
+//! Offline edits to a genome: tune null rates and value ranges, drop columns and rename
+//! tables without going back to the source database.
+//!
+//! Every edit keeps the dependent parts of the genome consistent (foreign keys,
+//! distribution keys, correlation matrices, conditionals) and the result is
+//! re-validated before it is returned.
+
+use std::collections::BTreeMap;
+use anyhow::{bail, Context, Result};
+use tracing::info;
+use crate::copula::CovarianceMatrix;
+use crate::genome::DatabaseGenome;
+use crate::math::{Distribution, Histogram};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GenomeEdit {
+    /// Fraction of generated values that are NULL, in `[0, 1]`.
+    SetNullRate { column: String, rate: f64 },
+    /// Remove a column along with its distribution, correlations and foreign key.
+    DropColumn { column: String },
+    /// Rename a table and every reference to it.
+    RenameTable { from: String, to: String },
+    /// Linearly map a numeric column's values onto `[min, max]`.
+    SetRange { column: String, min: f64, max: f64 },
+}
+
+/// Applies `edit` to `genome` and validates the result.
+pub fn apply_edit(genome: &mut DatabaseGenome, edit: &GenomeEdit) -> Result<()> {
+    match edit {
+        GenomeEdit::SetNullRate { column, rate } => set_null_rate(genome, column, *rate)?,
+        GenomeEdit::DropColumn { column } => drop_column(genome, column)?,
+        GenomeEdit::RenameTable { from, to } => rename_table(genome, from, to)?,
+        GenomeEdit::SetRange { column, min, max } => set_range(genome, column, *min, *max)?,
+    }
+
+    genome.validate().context("Edited genome is invalid")?;
+    info!(?edit, "Applied genome edit");
+    Ok(())
+}
+
+/// Splits `table.column`.
+pub fn split_column_ref(column: &str) -> Result<(&str, &str)> {
+    column
+        .split_once('.')
+        .filter(|(table, name)| !table.is_empty() && !name.is_empty())
+        .context(format!("Expected table.column, got '{}'", column))
+}
+
+fn distribution_mut<'a>(genome: &'a mut DatabaseGenome, column: &str) -> Result<&'a mut Distribution> {
+    genome
+        .distributions
+        .get_mut(column)
+        .context(format!("No distribution for column '{}'", column))
+}
+
+fn set_null_rate(genome: &mut DatabaseGenome, column: &str, rate: f64) -> Result<()> {
+    if !(0.0..=1.0).contains(&rate) {
+        bail!("Null rate must be between 0 and 1, got {}", rate);
+    }
+
+    let (table_name, column_name) = split_column_ref(column)?;
+    let schema_column = genome
+        .get_table(table_name)
+        .and_then(|t| t.columns.iter().find(|c| c.name == column_name))
+        .context(format!("Column '{}' not found in genome", column))?;
+    if rate > 0.0 && !schema_column.is_nullable {
+        bail!("Column '{}' is NOT NULL", column);
+    }
+
+    let dist = distribution_mut(genome, column)?;
+    if dist.total_count == 0 {
+        bail!("Column '{}' has no profiled rows to rescale", column);
+    }
+    dist.null_count = (rate * dist.total_count as f64).round() as u64;
+    Ok(())
+}
+
+fn drop_column(genome: &mut DatabaseGenome, column: &str) -> Result<()> {
+    let (table_name, column_name) = split_column_ref(column)?;
+
+    let referenced_by: Vec<&str> = genome
+        .tables
+        .iter()
+        .filter(|t| {
+            t.foreign_keys
+                .iter()
+                .any(|fk| fk.target_table == table_name && fk.target_col == column_name)
+        })
+        .map(|t| t.name.as_str())
+        .collect();
+    if !referenced_by.is_empty() {
+        bail!("Column '{}' is referenced by foreign keys of {}", column, referenced_by.join(", "));
+    }
+
+    let table = genome
+        .tables
+        .iter_mut()
+        .find(|t| t.name == table_name)
+        .context(format!("Table '{}' not found in genome", table_name))?;
+    let before = table.columns.len();
+    table.columns.retain(|c| c.name != column_name);
+    if table.columns.len() == before {
+        bail!("Column '{}' not found in genome", column);
+    }
+    table.foreign_keys.retain(|fk| fk.source_col != column_name);
+
+    genome.distributions.remove(column);
+    genome.conditionals.remove(column);
+    genome.fanouts.remove(column);
+    if let Some(profile) = &mut genome.tenants
+        && profile.column == column_name
+    {
+        profile.remove_table(table_name);
+    }
+    genome
+        .conditionals
+        .retain(|_, c| !(c.parent_table == table_name && c.parent_column == column_name));
+
+    if let Some(matrix) = genome.correlations.get(table_name) {
+        match without_column(matrix, column_name) {
+            Some(reduced) if reduced.dimension < 2 => {
+                genome.correlations.remove(table_name);
+            }
+            Some(reduced) => {
+                genome.correlations.insert(table_name.to_string(), reduced);
+            }
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+/// `matrix` with the row and column of `column` removed, or `None` if it does not
+/// include `column`.
+fn without_column(matrix: &CovarianceMatrix, column: &str) -> Option<CovarianceMatrix> {
+    let index = matrix.columns.iter().position(|c| c == column)?;
+    let n = matrix.dimension;
+    let matrix_data = (0..n)
+        .filter(|&i| i != index)
+        .flat_map(|i| (0..n).filter(|&j| j != index).map(move |j| matrix.matrix_data[i * n + j]))
+        .collect();
+
+    let mut columns = matrix.columns.clone();
+    columns.remove(index);
+    let pseudo_observations = matrix.pseudo_observations.as_ref().map(|rows| {
+        rows.iter()
+            .map(|row| row.iter().enumerate().filter(|&(i, _)| i != index).map(|(_, u)| *u).collect())
+            .collect()
+    });
+    Some(CovarianceMatrix {
+        dimension: columns.len(),
+        columns,
+        matrix_data,
+        method: matrix.method,
+        pseudo_observations,
+    })
+}
+
+fn rename_table(genome: &mut DatabaseGenome, from: &str, to: &str) -> Result<()> {
+    if to.is_empty() || to.contains('.') {
+        bail!("Invalid table name '{}'", to);
+    }
+    if genome.get_table(to).is_some() {
+        bail!("Table '{}' already exists", to);
+    }
+
+    let table = genome
+        .tables
+        .iter_mut()
+        .find(|t| t.name == from)
+        .context(format!("Table '{}' not found in genome", from))?;
+    table.name = to.to_string();
+
+    for fk in genome.tables.iter_mut().flat_map(|t| t.foreign_keys.iter_mut()) {
+        if fk.target_table == from {
+            fk.target_table = to.to_string();
+        }
+    }
+
+    let prefix = format!("{}.", from);
+    let rekey = |key: String| match key.strip_prefix(&prefix) {
+        Some(column) => DatabaseGenome::make_key(to, column),
+        None => key,
+    };
+    genome.distributions = std::mem::take(&mut genome.distributions)
+        .into_iter()
+        .map(|(key, dist)| (rekey(key), dist))
+        .collect();
+    genome.conditionals = std::mem::take(&mut genome.conditionals)
+        .into_iter()
+        .map(|(key, mut conditional)| {
+            if conditional.parent_table == from {
+                conditional.parent_table = to.to_string();
+            }
+            (rekey(key), conditional)
+        })
+        .collect();
+    genome.fanouts = std::mem::take(&mut genome.fanouts)
+        .into_iter()
+        .map(|(key, fanout)| (rekey(key), fanout))
+        .collect();
+
+    if let Some(matrix) = genome.correlations.remove(from) {
+        genome.correlations.insert(to.to_string(), matrix);
+    }
+    for table in genome.tenants.iter_mut().flat_map(|profile| profile.tables.iter_mut()) {
+        if table == from {
+            *table = to.to_string();
+        }
+    }
+    Ok(())
+}
+
+/// Maps every stored value `x` of a numeric column to `min + (x - old_min) * scale`,
+/// so the shape of the distribution is kept. A fitted parametric form no longer
+/// matches and is dropped in favour of the rescaled histogram.
+fn set_range(genome: &mut DatabaseGenome, column: &str, min: f64, max: f64) -> Result<()> {
+    if !min.is_finite() || !max.is_finite() || min > max {
+        bail!("Invalid range [{}, {}]", min, max);
+    }
+
+    let dist = distribution_mut(genome, column)?;
+    let (old_min, old_max) = match (dist.min, dist.max) {
+        (Some(old_min), Some(old_max)) => (old_min, old_max),
+        _ => bail!("Column '{}' has no numeric range", column),
+    };
+    let scale = if old_max > old_min { (max - min) / (old_max - old_min) } else { 0.0 };
+    let map = |x: f64| (min + (x - old_min) * scale).clamp(min, max);
+
+    match &mut dist.histogram {
+        Histogram::Numeric { bins, .. } => {
+            if scale == 0.0 && bins.len() >= 2 {
+                // A constant column: spread the bins evenly over the new range
+                let last = (bins.len() - 1) as f64;
+                for (i, bin) in bins.iter_mut().enumerate() {
+                    *bin = min + (max - min) * i as f64 / last;
+                }
+            } else {
+                bins.iter_mut().for_each(|bin| *bin = map(*bin));
+            }
+        }
+        Histogram::Quantile { digest } => {
+            digest.centroids.iter_mut().for_each(|c| c.mean = map(c.mean));
+            digest.min = min;
+            digest.max = max;
+        }
+        Histogram::Discrete { values, frequencies } => {
+            // Values stay whole; those that round together are merged
+            let mut merged: BTreeMap<i64, u64> = BTreeMap::new();
+            for (value, count) in values.iter().zip(frequencies.iter()) {
+                *merged.entry(map(*value as f64).round() as i64).or_insert(0) += count;
+            }
+            (*values, *frequencies) = merged.into_iter().unzip();
+        }
+        _ => bail!("Column '{}' is not numeric", column),
+    }
+
+    if let Some(sequence) = &mut dist.sequence {
+        sequence.start = map(sequence.start);
+        sequence.gap_quantiles.iter_mut().for_each(|gap| *gap *= scale);
+    }
+    dist.point_masses.iter_mut().for_each(|mass| mass.value = map(mass.value));
+    if let Some(outliers) = &mut dist.outliers {
+        outliers.lower = map(outliers.lower);
+        outliers.upper = map(outliers.upper);
+    }
+    dist.fit = None;
+    dist.min = Some(min);
+    dist.max = Some(max);
+    dist.non_negative = min >= 0.0;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use crate::conditional::ConditionalDistribution;
+    use crate::copula::CorrelationMethod;
+    use crate::math::BinScale;
+    use crate::schema::{Column, DataType, ForeignKey, Table};
+
+    fn numeric(min: f64, max: f64) -> Distribution {
+        Distribution::new(Some(min), Some(max), 10, 100, 50, Histogram::Numeric {
+            bins: vec![min, (min + max) / 2.0, max],
+            frequencies: vec![60, 30],
+            scale: BinScale::Linear,
+        })
+    }
+
+    fn genome() -> DatabaseGenome {
+        let users = Table::new(
+            "users".to_string(),
+            vec![
+                Column::new("id".to_string(), DataType::Integer, false, true),
+                Column::new("age".to_string(), DataType::Integer, true, false),
+                Column::new("score".to_string(), DataType::Float, true, false),
+                Column::new("phone".to_string(), DataType::Text, true, false),
+            ],
+            vec![],
+        );
+        let orders = Table::new(
+            "orders".to_string(),
+            vec![
+                Column::new("id".to_string(), DataType::Integer, false, true),
+                Column::new("user_id".to_string(), DataType::Integer, false, false),
+                Column::new("amount".to_string(), DataType::Float, true, false),
+            ],
+            vec![ForeignKey::new("user_id".to_string(), "users".to_string(), "id".to_string())],
+        );
+
+        let mut distributions = HashMap::new();
+        for key in ["users.id", "users.age", "users.score", "orders.id", "orders.user_id", "orders.amount"] {
+            distributions.insert(key.to_string(), numeric(0.0, 100.0));
+        }
+        distributions.insert("users.phone".to_string(), Distribution::new(None, None, 0, 100, 0, Histogram::Categorical {
+            frequencies: [("555".to_string(), 100)].into_iter().collect(),
+            truncated: false,
+            long_tail: None,
+        }));
+
+        let mut genome = DatabaseGenome::new(vec![users, orders], distributions);
+        genome.correlations.insert("users".to_string(), CovarianceMatrix {
+            columns: vec!["age".to_string(), "score".to_string(), "id".to_string()],
+            matrix_data: vec![1.0, 0.5, 0.1, 0.5, 1.0, 0.2, 0.1, 0.2, 1.0],
+            dimension: 3,
+            method: CorrelationMethod::Pearson,
+            pseudo_observations: None,
+        });
+        genome.conditionals.insert("orders.amount".to_string(), ConditionalDistribution {
+            foreign_key: "user_id".to_string(),
+            parent_table: "users".to_string(),
+            parent_column: "phone".to_string(),
+            by_parent_value: HashMap::new(),
+        });
+        genome
+    }
+
+    #[test]
+    fn test_set_null_rate() -> Result<()> {
+        let mut genome = genome();
+        apply_edit(&mut genome, &GenomeEdit::SetNullRate { column: "users.phone".to_string(), rate: 0.3 })?;
+        assert_eq!(genome.distributions["users.phone"].null_count, 30);
+
+        assert!(apply_edit(&mut genome, &GenomeEdit::SetNullRate { column: "users.id".to_string(), rate: 0.1 }).is_err());
+        assert!(apply_edit(&mut genome, &GenomeEdit::SetNullRate { column: "users.age".to_string(), rate: 1.5 }).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_drop_column() -> Result<()> {
+        let mut genome = genome();
+        apply_edit(&mut genome, &GenomeEdit::DropColumn { column: "users.score".to_string() })?;
+
+        let users = genome.get_table("users").unwrap();
+        assert!(users.columns.iter().all(|c| c.name != "score"));
+        assert!(!genome.distributions.contains_key("users.score"));
+        let matrix = &genome.correlations["users"];
+        assert_eq!(matrix.columns, vec!["age", "id"]);
+        assert_eq!(matrix.matrix_data, vec![1.0, 0.1, 0.1, 1.0]);
+
+        // Conditionals on a dropped parent attribute go too
+        apply_edit(&mut genome, &GenomeEdit::DropColumn { column: "users.phone".to_string() })?;
+        assert!(genome.conditionals.is_empty());
+
+        // Referenced keys stay; dropping the foreign key column removes the constraint
+        assert!(apply_edit(&mut genome, &GenomeEdit::DropColumn { column: "users.id".to_string() }).is_err());
+        apply_edit(&mut genome, &GenomeEdit::DropColumn { column: "orders.user_id".to_string() })?;
+        assert!(genome.get_table("orders").unwrap().foreign_keys.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_table() -> Result<()> {
+        let mut genome = genome();
+        apply_edit(&mut genome, &GenomeEdit::RenameTable { from: "users".to_string(), to: "customers".to_string() })?;
+
+        assert!(genome.get_table("users").is_none());
+        assert!(genome.distributions.contains_key("customers.age"));
+        assert!(!genome.distributions.keys().any(|k| k.starts_with("users.")));
+        assert!(genome.correlations.contains_key("customers"));
+        assert_eq!(genome.conditionals["orders.amount"].parent_table, "customers");
+        assert_eq!(genome.get_table("orders").unwrap().foreign_keys[0].target_table, "customers");
+
+        assert!(apply_edit(&mut genome, &GenomeEdit::RenameTable { from: "orders".to_string(), to: "customers".to_string() }).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_range() -> Result<()> {
+        let mut genome = genome();
+        apply_edit(&mut genome, &GenomeEdit::SetRange { column: "orders.amount".to_string(), min: 0.0, max: 5000.0 })?;
+
+        let dist = &genome.distributions["orders.amount"];
+        assert_eq!((dist.min, dist.max), (Some(0.0), Some(5000.0)));
+        match &dist.histogram {
+            Histogram::Numeric { bins, frequencies, .. } => {
+                assert_eq!(bins, &vec![0.0, 2500.0, 5000.0]);
+                assert_eq!(frequencies, &vec![60, 30]);
+            }
+            other => panic!("Expected numeric histogram, got {:?}", other),
+        }
+
+        assert!(apply_edit(&mut genome, &GenomeEdit::SetRange { column: "users.phone".to_string(), min: 0.0, max: 1.0 }).is_err());
+        assert!(apply_edit(&mut genome, &GenomeEdit::SetRange { column: "users.age".to_string(), min: 5.0, max: 1.0 }).is_err());
+        Ok(())
+    }
+}
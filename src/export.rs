@@ -0,0 +1,269 @@
+//! Genome exports for other synthetic data tools.
+//!
+//! `sdv-metadata` writes the tables, column types and foreign keys of a genome as
+//! [SDV](https://docs.sdv.dev) multi-table metadata, which `Metadata.load_from_json`
+//! reads directly. Distributions are not exported; SDV fits its own models.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use anyhow::{bail, Result};
+use serde::Serialize;
+use crate::genome::DatabaseGenome;
+use crate::math::Histogram;
+use crate::schema::{Column, DataType, Table};
+use crate::semantic::SemanticType;
+
+/// Metadata layout version written by SDV 1.x for multi-table datasets.
+const SDV_SPEC_VERSION: &str = "MULTI_TABLE_V1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// SDV multi-table metadata JSON.
+    SdvMetadata,
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportFormat::SdvMetadata => write!(f, "sdv-metadata"),
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "sdv-metadata" | "sdv" => Ok(ExportFormat::SdvMetadata),
+            other => bail!("Unknown export format '{}' (expected sdv-metadata)", other),
+        }
+    }
+}
+
+/// Renders `genome` in `format`.
+pub fn export_genome(genome: &DatabaseGenome, format: ExportFormat) -> Result<String> {
+    match format {
+        ExportFormat::SdvMetadata => Ok(serde_json::to_string_pretty(&sdv_metadata(genome))?),
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SdvMetadata {
+    #[serde(rename = "METADATA_SPEC_VERSION")]
+    pub spec_version: String,
+    pub tables: BTreeMap<String, SdvTable>,
+    pub relationships: Vec<SdvRelationship>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SdvTable {
+    /// Unset for tables without a primary key or with a composite one, which SDV
+    /// can't represent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub primary_key: Option<String>,
+    pub columns: BTreeMap<String, SdvColumn>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SdvColumn {
+    pub sdtype: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub computer_representation: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pii: Option<bool>,
+}
+
+impl SdvColumn {
+    fn new(sdtype: &str) -> Self {
+        Self {
+            sdtype: sdtype.to_string(),
+            computer_representation: None,
+            pii: None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SdvRelationship {
+    pub parent_table_name: String,
+    pub parent_primary_key: String,
+    pub child_table_name: String,
+    pub child_foreign_key: String,
+}
+
+/// SDV metadata for the tables and foreign keys of `genome`. Foreign keys to tables
+/// missing from the genome are left out, since SDV rejects them.
+pub fn sdv_metadata(genome: &DatabaseGenome) -> SdvMetadata {
+    let tables = genome
+        .tables
+        .iter()
+        .map(|table| (table.name.clone(), sdv_table(genome, table)))
+        .collect();
+
+    let relationships = genome
+        .tables
+        .iter()
+        .flat_map(|table| table.foreign_keys.iter().map(move |fk| (table, fk)))
+        .filter(|(_, fk)| genome.get_table(&fk.target_table).is_some())
+        .map(|(table, fk)| SdvRelationship {
+            parent_table_name: fk.target_table.clone(),
+            parent_primary_key: fk.target_col.clone(),
+            child_table_name: table.name.clone(),
+            child_foreign_key: fk.source_col.clone(),
+        })
+        .collect();
+
+    SdvMetadata {
+        spec_version: SDV_SPEC_VERSION.to_string(),
+        tables,
+        relationships,
+    }
+}
+
+fn sdv_table(genome: &DatabaseGenome, table: &Table) -> SdvTable {
+    let primary_key = match table.primary_keys().as_slice() {
+        [pk] => Some(pk.name.clone()),
+        _ => None,
+    };
+    let columns = table
+        .columns
+        .iter()
+        .map(|column| (column.name.clone(), sdv_column(genome, table, column)))
+        .collect();
+    SdvTable { primary_key, columns }
+}
+
+fn sdv_column(genome: &DatabaseGenome, table: &Table, column: &Column) -> SdvColumn {
+    // SDV requires key columns to be ids, whatever their storage type
+    let is_foreign_key = table.foreign_keys.iter().any(|fk| fk.source_col == column.name);
+    if column.is_primary_key || is_foreign_key || column.data_type == DataType::Uuid {
+        return SdvColumn::new("id");
+    }
+
+    let distribution = genome.get_distribution(&table.name, &column.name);
+    let semantic = distribution.and_then(|d| match &d.histogram {
+        Histogram::Semantic { semantic_type } => Some(*semantic_type),
+        _ => d.pii,
+    });
+    if let Some(semantic_type) = semantic {
+        return SdvColumn {
+            pii: semantic_type.is_pii().then_some(true),
+            ..SdvColumn::new(sdv_semantic_type(semantic_type))
+        };
+    }
+
+    match column.data_type {
+        DataType::Integer => SdvColumn {
+            computer_representation: Some("Int64".to_string()),
+            ..SdvColumn::new("numerical")
+        },
+        DataType::Float => SdvColumn {
+            computer_representation: Some("Float".to_string()),
+            ..SdvColumn::new("numerical")
+        },
+        DataType::Boolean => SdvColumn::new("boolean"),
+        DataType::Timestamp => SdvColumn::new("datetime"),
+        // SDV has no binary sdtype
+        DataType::Bytea => SdvColumn::new("unknown"),
+        // Nor a duration one; interval literals aren't numbers to it
+        DataType::Interval => SdvColumn::new("unknown"),
+        DataType::Inet | DataType::Cidr => SdvColumn::new("ipv4_address"),
+        DataType::MacAddr => SdvColumn::new("mac_address"),
+        DataType::Geometry | DataType::Geography | DataType::Hstore => SdvColumn::new("unknown"),
+        DataType::Text | DataType::Uuid => match distribution.map(|d| &d.histogram) {
+            Some(Histogram::Pattern { .. }) => SdvColumn::new("id"),
+            Some(Histogram::Markov { .. }) => SdvColumn::new("unknown"),
+            _ => SdvColumn::new("categorical"),
+        },
+    }
+}
+
+/// The SDV sdtype (a Faker provider) of a semantic column.
+fn sdv_semantic_type(semantic_type: SemanticType) -> &'static str {
+    match semantic_type {
+        SemanticType::PersonName => "name",
+        SemanticType::Email => "email",
+        SemanticType::Phone => "phone_number",
+        SemanticType::Ssn => "ssn",
+        SemanticType::CreditCard => "credit_card_number",
+        SemanticType::Address => "address",
+        SemanticType::Company => "company",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use crate::math::Distribution;
+    use crate::schema::ForeignKey;
+
+    #[test]
+    fn test_sdv_metadata() {
+        let tables = vec![
+            Table::new(
+                "users".to_string(),
+                vec![
+                    Column::new("id".to_string(), DataType::Integer, false, true),
+                    Column::new("email".to_string(), DataType::Text, false, false),
+                    Column::new("tier".to_string(), DataType::Text, true, false),
+                    Column::new("created_at".to_string(), DataType::Timestamp, false, false),
+                ],
+                vec![],
+            ),
+            Table::new(
+                "orders".to_string(),
+                vec![
+                    Column::new("id".to_string(), DataType::Uuid, false, true),
+                    Column::new("user_id".to_string(), DataType::Integer, false, false),
+                    Column::new("amount".to_string(), DataType::Float, false, false),
+                    Column::new("coupon_id".to_string(), DataType::Integer, true, false),
+                ],
+                vec![
+                    ForeignKey::new("user_id".to_string(), "users".to_string(), "id".to_string()),
+                    ForeignKey::new("coupon_id".to_string(), "coupons".to_string(), "id".to_string()),
+                ],
+            ),
+        ];
+        let mut distributions = HashMap::new();
+        distributions.insert(
+            DatabaseGenome::make_key("users", "email"),
+            Distribution::new(None, None, 0, 10, 10, Histogram::Semantic {
+                semantic_type: SemanticType::Email,
+            }),
+        );
+        let genome = DatabaseGenome::new(tables, distributions);
+
+        let metadata = sdv_metadata(&genome);
+        assert_eq!(metadata.spec_version, "MULTI_TABLE_V1");
+
+        let users = &metadata.tables["users"];
+        assert_eq!(users.primary_key.as_deref(), Some("id"));
+        assert_eq!(users.columns["id"], SdvColumn::new("id"));
+        assert_eq!(users.columns["email"], SdvColumn { pii: Some(true), ..SdvColumn::new("email") });
+        assert_eq!(users.columns["tier"], SdvColumn::new("categorical"));
+        assert_eq!(users.columns["created_at"], SdvColumn::new("datetime"));
+
+        let orders = &metadata.tables["orders"];
+        assert_eq!(orders.columns["user_id"], SdvColumn::new("id"));
+        assert_eq!(orders.columns["amount"].computer_representation.as_deref(), Some("Float"));
+
+        // The foreign key to the missing coupons table is dropped
+        assert_eq!(
+            metadata.relationships,
+            vec![SdvRelationship {
+                parent_table_name: "users".to_string(),
+                parent_primary_key: "id".to_string(),
+                child_table_name: "orders".to_string(),
+                child_foreign_key: "user_id".to_string(),
+            }]
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&export_genome(&genome, ExportFormat::SdvMetadata).unwrap()).unwrap();
+        assert_eq!(json["METADATA_SPEC_VERSION"], "MULTI_TABLE_V1");
+        assert_eq!(json["tables"]["users"]["columns"]["id"], serde_json::json!({"sdtype": "id"}));
+    }
+}
@@ -0,0 +1,194 @@
+//! Parametric fits (normal, lognormal, exponential, uniform) for numeric columns.
+//!
+//! Parameters are estimated from the reservoir sample and scored with a one-sample
+//! Kolmogorov–Smirnov test. A fit is only kept when the test cannot reject it, in which
+//! case synthesis samples from the closed form instead of the binned histogram.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::diff::kolmogorov_p_value;
+use crate::special::{normal_cdf, normal_quantile};
+
+/// Fits with a KS p-value below this are discarded.
+pub const FIT_MIN_P_VALUE: f64 = 0.05;
+
+/// Fewest samples worth fitting.
+pub const MIN_FIT_SAMPLES: usize = 30;
+
+/// Quantiles are clamped away from 0 and 1 so unbounded families stay finite.
+const QUANTILE_EPSILON: f64 = 1e-9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "family", rename_all = "snake_case")]
+pub enum ParametricFamily {
+    Normal { mean: f64, std_dev: f64 },
+    LogNormal { mu: f64, sigma: f64 },
+    Exponential { rate: f64 },
+    Uniform { low: f64, high: f64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ParametricFit {
+    #[serde(flatten)]
+    pub family: ParametricFamily,
+    pub ks_statistic: f64,
+    pub p_value: f64,
+}
+
+impl ParametricFamily {
+    pub fn cdf(&self, x: f64) -> f64 {
+        match *self {
+            ParametricFamily::Normal { mean, std_dev } => normal_cdf((x - mean) / std_dev),
+            ParametricFamily::LogNormal { mu, sigma } => {
+                if x <= 0.0 {
+                    0.0
+                } else {
+                    normal_cdf((x.ln() - mu) / sigma)
+                }
+            }
+            ParametricFamily::Exponential { rate } => {
+                if x < 0.0 {
+                    0.0
+                } else {
+                    1.0 - (-rate * x).exp()
+                }
+            }
+            ParametricFamily::Uniform { low, high } => ((x - low) / (high - low)).clamp(0.0, 1.0),
+        }
+    }
+
+    pub fn inverse_cdf(&self, q: f64) -> f64 {
+        let q = q.clamp(QUANTILE_EPSILON, 1.0 - QUANTILE_EPSILON);
+        match *self {
+            ParametricFamily::Normal { mean, std_dev } => mean + std_dev * normal_quantile(q),
+            ParametricFamily::LogNormal { mu, sigma } => (mu + sigma * normal_quantile(q)).exp(),
+            ParametricFamily::Exponential { rate } => -(1.0 - q).ln() / rate,
+            ParametricFamily::Uniform { low, high } => low + q * (high - low),
+        }
+    }
+
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        self.inverse_cdf(rng.gen_range(0.0..1.0))
+    }
+}
+
+/// Fits every applicable family and returns the one with the smallest KS statistic,
+/// provided it passes the goodness-of-fit test.
+pub fn fit_best(samples: &[f64]) -> Option<ParametricFit> {
+    if samples.len() < MIN_FIT_SAMPLES {
+        return None;
+    }
+
+    let mut sorted: Vec<f64> = samples.iter().copied().filter(|x| x.is_finite()).collect();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    if sorted.len() < MIN_FIT_SAMPLES {
+        return None;
+    }
+
+    candidate_families(&sorted)
+        .into_iter()
+        .map(|family| {
+            let ks_statistic = one_sample_ks(&sorted, &family);
+            ParametricFit {
+                family,
+                ks_statistic,
+                p_value: kolmogorov_p_value(ks_statistic, sorted.len() as f64),
+            }
+        })
+        .min_by(|a, b| a.ks_statistic.total_cmp(&b.ks_statistic))
+        .filter(|fit| fit.p_value >= FIT_MIN_P_VALUE)
+}
+
+fn candidate_families(sorted: &[f64]) -> Vec<ParametricFamily> {
+    let n = sorted.len() as f64;
+    let (min, max) = (sorted[0], sorted[sorted.len() - 1]);
+    let mut families = Vec::with_capacity(4);
+
+    let mean = sorted.iter().sum::<f64>() / n;
+    let variance = sorted.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    if variance > 0.0 {
+        families.push(ParametricFamily::Normal { mean, std_dev: variance.sqrt() });
+    }
+
+    if min > 0.0 {
+        let logs: Vec<f64> = sorted.iter().map(|x| x.ln()).collect();
+        let mu = logs.iter().sum::<f64>() / n;
+        let log_variance = logs.iter().map(|x| (x - mu).powi(2)).sum::<f64>() / (n - 1.0);
+        if log_variance > 0.0 {
+            families.push(ParametricFamily::LogNormal { mu, sigma: log_variance.sqrt() });
+        }
+    }
+
+    if min >= 0.0 && mean > 0.0 {
+        families.push(ParametricFamily::Exponential { rate: 1.0 / mean });
+    }
+
+    if max > min {
+        families.push(ParametricFamily::Uniform { low: min, high: max });
+    }
+
+    families
+}
+
+/// sup |F_n(x) - F(x)| over a sorted sample.
+fn one_sample_ks(sorted: &[f64], family: &ParametricFamily) -> f64 {
+    let n = sorted.len() as f64;
+    sorted
+        .iter()
+        .enumerate()
+        .map(|(i, &x)| {
+            let f = family.cdf(x);
+            let above = (i + 1) as f64 / n - f;
+            let below = f - i as f64 / n;
+            above.max(below)
+        })
+        .fold(0.0, f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn draw(family: ParametricFamily, n: usize) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(42);
+        (0..n).map(|_| family.sample(&mut rng)).collect()
+    }
+
+    #[test]
+    fn test_recovers_normal() {
+        let samples = draw(ParametricFamily::Normal { mean: 50.0, std_dev: 10.0 }, 5_000);
+        let fit = fit_best(&samples).expect("normal fit");
+
+        match fit.family {
+            ParametricFamily::Normal { mean, std_dev } => {
+                assert!((mean - 50.0).abs() < 1.0);
+                assert!((std_dev - 10.0).abs() < 1.0);
+            }
+            other => panic!("expected normal, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_recovers_exponential() {
+        let samples = draw(ParametricFamily::Exponential { rate: 0.5 }, 5_000);
+        let fit = fit_best(&samples).expect("exponential fit");
+        assert!(matches!(fit.family, ParametricFamily::Exponential { .. }), "{:?}", fit);
+    }
+
+    #[test]
+    fn test_bimodal_data_is_not_fitted() {
+        let mut samples = draw(ParametricFamily::Normal { mean: 0.0, std_dev: 1.0 }, 2_000);
+        samples.extend(draw(ParametricFamily::Normal { mean: 20.0, std_dev: 1.0 }, 2_000));
+        assert!(fit_best(&samples).is_none());
+    }
+
+    #[test]
+    fn test_inverse_cdf_round_trip() {
+        let family = ParametricFamily::LogNormal { mu: 1.0, sigma: 0.5 };
+        for q in [0.1, 0.5, 0.9] {
+            assert!((family.cdf(family.inverse_cdf(q)) - q).abs() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! Arrow Flight service over one generated dataset (`flight` feature).
+//!
+//! Each generated table is a flight: `DoGet` with the table name as the ticket streams
+//! its [`RecordBatch`](arrow_array::RecordBatch)es as typed by [`crate::arrow`], and
+//! `ListFlights` / `GetFlightInfo` (with a one-element path descriptor) describe every
+//! table with its schema and row count.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use anyhow::{Context, Result};
+use arrow_flight::encode::FlightDataEncoderBuilder;
+use arrow_flight::error::FlightError;
+use arrow_flight::flight_service_server::{FlightService, FlightServiceServer};
+use arrow_flight::{
+    Action, ActionType, Criteria, Empty, FlightData, FlightDescriptor, FlightEndpoint, FlightInfo, HandshakeRequest,
+    HandshakeResponse, PollInfo, PutResult, SchemaResult, Ticket,
+};
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use tonic::{Request, Response, Status, Streaming};
+use tracing::info;
+use crate::arrow::{table_record_batches, table_schema, DEFAULT_BATCH_ROWS};
+use crate::synth::{GenerationResult, Synthesizer};
+
+/// Serves the tables of one generation result.
+pub struct FlightGenerator {
+    synthesizer: Arc<Synthesizer>,
+    result: Arc<GenerationResult>,
+}
+
+impl FlightGenerator {
+    pub fn new(synthesizer: Arc<Synthesizer>, result: Arc<GenerationResult>) -> Self {
+        Self { synthesizer, result }
+    }
+
+    /// Flight description of `table`: its schema, row count and the ticket to fetch it.
+    fn flight_info(&self, table: &str) -> Result<FlightInfo, Status> {
+        let schema = self.schema(table)?;
+        let rows = self.result.get_table_data(table).map_or(0, |data| data.row_count);
+        FlightInfo::new()
+            .try_with_schema(&schema)
+            .map_err(|e| Status::internal(format!("Failed to encode schema of '{}': {}", table, e)))
+            .map(|info| {
+                info.with_descriptor(FlightDescriptor::new_path(vec![table.to_string()]))
+                    .with_endpoint(FlightEndpoint::new().with_ticket(Ticket::new(table.to_string())))
+                    .with_total_records(rows as i64)
+            })
+    }
+
+    fn schema(&self, table: &str) -> Result<arrow_schema::SchemaRef, Status> {
+        let schema = self
+            .synthesizer
+            .genome()
+            .get_table(table)
+            .filter(|_| self.result.get_table_data(table).is_some())
+            .ok_or_else(|| Status::not_found(format!("No generated table '{}'", table)))?;
+        Ok(table_schema(&self.synthesizer, schema))
+    }
+}
+
+/// The table named by a path descriptor.
+fn descriptor_table(descriptor: &FlightDescriptor) -> Result<&str, Status> {
+    match descriptor.path.as_slice() {
+        [table] => Ok(table),
+        _ => Err(Status::invalid_argument("Descriptor path must be a single table name")),
+    }
+}
+
+#[tonic::async_trait]
+impl FlightService for FlightGenerator {
+    type HandshakeStream = BoxStream<'static, Result<HandshakeResponse, Status>>;
+    type ListFlightsStream = BoxStream<'static, Result<FlightInfo, Status>>;
+    type DoGetStream = BoxStream<'static, Result<FlightData, Status>>;
+    type DoPutStream = BoxStream<'static, Result<PutResult, Status>>;
+    type DoActionStream = BoxStream<'static, Result<arrow_flight::Result, Status>>;
+    type ListActionsStream = BoxStream<'static, Result<ActionType, Status>>;
+    type DoExchangeStream = BoxStream<'static, Result<FlightData, Status>>;
+
+    async fn handshake(
+        &self,
+        _request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<Response<Self::HandshakeStream>, Status> {
+        Err(Status::unimplemented("No authentication; call DoGet directly"))
+    }
+
+    /// Every generated table, in execution order.
+    async fn list_flights(&self, _request: Request<Criteria>) -> Result<Response<Self::ListFlightsStream>, Status> {
+        let flights: Vec<Result<FlightInfo, Status>> = self
+            .synthesizer
+            .execution_order()
+            .iter()
+            .filter(|name| self.result.get_table_data(name).is_some())
+            .map(|name| self.flight_info(name))
+            .collect();
+        Ok(Response::new(futures::stream::iter(flights).boxed()))
+    }
+
+    async fn get_flight_info(&self, request: Request<FlightDescriptor>) -> Result<Response<FlightInfo>, Status> {
+        let descriptor = request.into_inner();
+        Ok(Response::new(self.flight_info(descriptor_table(&descriptor)?)?))
+    }
+
+    async fn poll_flight_info(&self, _request: Request<FlightDescriptor>) -> Result<Response<PollInfo>, Status> {
+        Err(Status::unimplemented("Tables are generated up front; use GetFlightInfo"))
+    }
+
+    async fn get_schema(&self, request: Request<FlightDescriptor>) -> Result<Response<SchemaResult>, Status> {
+        let descriptor = request.into_inner();
+        let schema = self.schema(descriptor_table(&descriptor)?)?;
+        let options = arrow_ipc::writer::IpcWriteOptions::default();
+        let result = arrow_flight::SchemaAsIpc::new(&schema, &options)
+            .try_into()
+            .map_err(|e: arrow_schema::ArrowError| Status::internal(e.to_string()))?;
+        Ok(Response::new(result))
+    }
+
+    /// Streams the batches of the table named by the ticket.
+    async fn do_get(&self, request: Request<Ticket>) -> Result<Response<Self::DoGetStream>, Status> {
+        let ticket = request.into_inner().ticket;
+        let table = std::str::from_utf8(&ticket)
+            .map_err(|_| Status::invalid_argument("Ticket must be a UTF-8 table name"))?
+            .to_string();
+        let schema = self.schema(&table)?;
+
+        let (synthesizer, result) = (self.synthesizer.clone(), self.result.clone());
+        let name = table.clone();
+        let batches = tokio::task::spawn_blocking(move || {
+            table_record_batches(&synthesizer, &result, &name, DEFAULT_BATCH_ROWS)
+        })
+        .await
+        .map_err(|e| Status::internal(format!("Conversion task failed: {}", e)))?
+        .map_err(|e| Status::internal(format!("{:#}", e)))?;
+        info!(table = %table, batches = batches.len(), "Streaming generated table over Arrow Flight");
+
+        let stream = FlightDataEncoderBuilder::new()
+            .with_schema(schema)
+            .build(futures::stream::iter(batches.into_iter().map(Ok::<_, FlightError>)))
+            .map_err(Status::from);
+        Ok(Response::new(stream.boxed()))
+    }
+
+    async fn do_put(&self, _request: Request<Streaming<FlightData>>) -> Result<Response<Self::DoPutStream>, Status> {
+        Err(Status::unimplemented("The generated tables are read-only"))
+    }
+
+    async fn do_action(&self, _request: Request<Action>) -> Result<Response<Self::DoActionStream>, Status> {
+        Err(Status::unimplemented("No actions"))
+    }
+
+    async fn list_actions(&self, _request: Request<Empty>) -> Result<Response<Self::ListActionsStream>, Status> {
+        Ok(Response::new(futures::stream::empty().boxed()))
+    }
+
+    async fn do_exchange(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoExchangeStream>, Status> {
+        Err(Status::unimplemented("The generated tables are read-only"))
+    }
+}
+
+/// Serves the generated tables over Arrow Flight on `listen` until the process is
+/// stopped.
+pub async fn serve_flight(listen: SocketAddr, synthesizer: Arc<Synthesizer>, result: Arc<GenerationResult>) -> Result<()> {
+    let service = FlightServiceServer::new(FlightGenerator::new(synthesizer, result));
+    info!(address = %listen, "Serving generated tables over Arrow Flight");
+    tonic::transport::Server::builder()
+        .add_service(service)
+        .serve(listen)
+        .await
+        .context("Arrow Flight server failed")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::Int64Type;
+    use arrow_array::RecordBatch;
+    use arrow_flight::FlightClient;
+    use tonic::transport::server::TcpIncoming;
+    use tonic::transport::Channel;
+    use crate::genome::DatabaseGenome;
+    use crate::schema::{Column, DataType, ForeignKey, Table};
+    use crate::synth::SynthesisConfig;
+
+    fn generated(rows: usize) -> Result<(Arc<Synthesizer>, Arc<GenerationResult>)> {
+        let tables = vec![
+            Table::new("users".to_string(), vec![Column::new("id".to_string(), DataType::Integer, false, true)], vec![]),
+            Table::new(
+                "orders".to_string(),
+                vec![
+                    Column::new("id".to_string(), DataType::Integer, false, true),
+                    Column::new("user_id".to_string(), DataType::Integer, false, false),
+                ],
+                vec![ForeignKey::new("user_id".to_string(), "users".to_string(), "id".to_string())],
+            ),
+        ];
+        let config = SynthesisConfig { rows_per_table: rows, seed: Some(1), ..SynthesisConfig::default() };
+        let synthesizer = Synthesizer::new(DatabaseGenome::new(tables, HashMap::new()), config)?;
+        let result = synthesizer.generate()?;
+        Ok((Arc::new(synthesizer), Arc::new(result)))
+    }
+
+    #[tokio::test]
+    async fn test_do_get_round_trips_a_table() -> Result<()> {
+        let (synthesizer, result) = generated(5)?;
+        let incoming = TcpIncoming::bind("127.0.0.1:0".parse()?)?;
+        let address = incoming.local_addr()?;
+        let service = FlightServiceServer::new(FlightGenerator::new(synthesizer, result));
+        tokio::spawn(tonic::transport::Server::builder().add_service(service).serve_with_incoming(incoming));
+
+        let channel = Channel::from_shared(format!("http://{}", address))?.connect().await?;
+        let mut client = FlightClient::new(channel);
+
+        let batches: Vec<RecordBatch> = client.do_get(Ticket::new("orders")).await?.try_collect().await?;
+        assert_eq!(batches.iter().map(RecordBatch::num_rows).sum::<usize>(), 5);
+        let batch = &batches[0];
+        assert_eq!(batch.schema().field(1).name(), "user_id");
+        assert_eq!(batch.column(0).as_primitive::<Int64Type>().values(), &[1, 2, 3, 4, 5]);
+        assert!(batch.column(1).as_primitive::<Int64Type>().values().iter().all(|id| (1..=5).contains(id)));
+
+        let info = client.get_flight_info(FlightDescriptor::new_path(vec!["users".to_string()])).await?;
+        assert_eq!(info.total_records, 5);
+        assert_eq!(info.endpoint[0].ticket, Some(Ticket::new("users")));
+
+        let missing = client.do_get(Ticket::new("missing")).await;
+        assert!(matches!(missing, Err(FlightError::Tonic(status)) if status.code() == tonic::Code::NotFound));
+        Ok(())
+    }
+}
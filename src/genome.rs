@@ -1,15 +1,70 @@
 //! The portable DNA of database schema
 
 use std::collections::HashMap;
+use std::fmt;
 use std::path::Path;
+use std::str::FromStr;
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use tracing::{debug, info};
+use crate::conditional::ConditionalDistribution;
 use crate::copula::CovarianceMatrix;
-use crate::math::Distribution;
-use crate::schema::{DataType, Table};
+use crate::ddl::placeholder_distribution;
+use crate::math::{Distribution, Histogram};
+use crate::privacy::PrivacyBudget;
+use crate::schema::Table;
+use crate::subset::FanOut;
+use crate::tenant::TenantProfile;
+
+/// Leading bytes of every zstd frame.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// zstd level used for binary genomes.
+#[cfg(feature = "zstd")]
+const ZSTD_LEVEL: i32 = 19;
+
+/// On-disk encoding of a genome. Loading detects the encoding from the file contents.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GenomeFormat {
+    /// Pretty-printed JSON, readable and diffable.
+    #[default]
+    Json,
+    /// zstd-compressed MessagePack, far smaller and faster to parse for large schemas.
+    /// Without the `zstd` feature it is written uncompressed.
+    Bin,
+}
 
-type TableColumn = (String, String);
+impl GenomeFormat {
+    /// The format `bytes` were written in. A genome is a JSON object or a MessagePack
+    /// map, and a MessagePack map never starts with `{`.
+    pub fn detect(bytes: &[u8]) -> Self {
+        match bytes.iter().find(|b| !b.is_ascii_whitespace()) {
+            Some(b'{') => GenomeFormat::Json,
+            _ => GenomeFormat::Bin,
+        }
+    }
+}
+
+impl fmt::Display for GenomeFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenomeFormat::Json => write!(f, "json"),
+            GenomeFormat::Bin => write!(f, "bin"),
+        }
+    }
+}
+
+impl FromStr for GenomeFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "json" => Ok(GenomeFormat::Json),
+            "bin" => Ok(GenomeFormat::Bin),
+            other => anyhow::bail!("Unknown genome format '{}' (expected json or bin)", other),
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DatabaseGenome {
@@ -29,6 +84,56 @@ pub struct DatabaseGenome {
     #[serde(default)]
     pub correlations: HashMap<String, CovarianceMatrix>,
 
+    /// Child columns conditioned on a parent attribute, keyed `table.column`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub conditionals: HashMap<String, ConditionalDistribution>,
+
+    /// Present when histograms were released under differential privacy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub privacy: Option<PrivacyBudget>,
+
+    /// Fidelity caveats found while scanning, shown by `inspect` and before `gen`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<GenomeWarning>,
+
+    /// Per-tenant row counts, when the scan was told the tenant column.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenants: Option<TenantProfile>,
+
+    /// Children per parent through each foreign key, keyed `child_table.fk_column`,
+    /// when the scan profiled fan-out.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub fanouts: HashMap<String, FanOut>,
+
+}
+
+/// Something the genome does not capture about its source, e.g. a column of an
+/// unsupported type that is generated as text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenomeWarning {
+    pub table: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<String>,
+    pub message: String,
+}
+
+impl GenomeWarning {
+    pub fn table(table: &str, message: impl Into<String>) -> Self {
+        Self { table: table.to_string(), column: None, message: message.into() }
+    }
+
+    pub fn column(table: &str, column: &str, message: impl Into<String>) -> Self {
+        Self { table: table.to_string(), column: Some(column.to_string()), message: message.into() }
+    }
+}
+
+impl fmt::Display for GenomeWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.column {
+            Some(column) => write!(f, "{}.{}: {}", self.table, column, self.message),
+            None => write!(f, "{}: {}", self.table, self.message),
+        }
+    }
 }
 
 fn default_version() -> String {
@@ -44,6 +149,11 @@ impl DatabaseGenome {
             tables,
             distributions,
             correlations: HashMap::new(),
+            conditionals: HashMap::new(),
+            privacy: None,
+            warnings: Vec::new(),
+            tenants: None,
+            fanouts: HashMap::new(),
         }
     }
 
@@ -59,6 +169,11 @@ impl DatabaseGenome {
             tables,
             distributions,
             correlations: HashMap::new(),
+            conditionals: HashMap::new(),
+            privacy: None,
+            warnings: Vec::new(),
+            tenants: None,
+            fanouts: HashMap::new(),
         }
     }
 
@@ -75,6 +190,11 @@ impl DatabaseGenome {
             tables,
             distributions,
             correlations,
+            conditionals: HashMap::new(),
+            privacy: None,
+            warnings: Vec::new(),
+            tenants: None,
+            fanouts: HashMap::new(),
         }
     }
 
@@ -83,12 +203,13 @@ impl DatabaseGenome {
     }
 
     pub fn save_to_file(&self, path: &Path) -> Result<()> {
-        info!(path = ?path, "Saving database genome to file");
+        self.save_as(path, GenomeFormat::Json)
+    }
 
-        let json = serde_json::to_string_pretty(self)
-            .context("Failed to serialize databasegenome to JSON")?;
+    pub fn save_as(&self, path: &Path, format: GenomeFormat) -> Result<()> {
+        info!(path = ?path, %format, "Saving database genome to file");
 
-        std::fs::write(path, json)
+        std::fs::write(path, self.to_bytes(format)?)
             .context("Failed to write DatabaseGenome to file")?;
 
         let file_size = std::fs::metadata(path)
@@ -106,14 +227,50 @@ impl DatabaseGenome {
         Ok(())
     }
 
+    pub fn to_bytes(&self, format: GenomeFormat) -> Result<Vec<u8>> {
+        match format {
+            GenomeFormat::Json => serde_json::to_vec_pretty(self)
+                .context("Failed to serialize databasegenome to JSON"),
+            GenomeFormat::Bin => {
+                // Named fields keep `#[serde(default)]` working when fields are added later
+                let packed = rmp_serde::to_vec_named(self)
+                    .context("Failed to serialize DatabaseGenome to MessagePack")?;
+                #[cfg(feature = "zstd")]
+                let packed = zstd::encode_all(packed.as_slice(), ZSTD_LEVEL)
+                    .context("Failed to compress DatabaseGenome")?;
+                Ok(packed)
+            }
+        }
+    }
+
+    /// Decodes JSON or MessagePack, either of them optionally zstd-compressed.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.starts_with(&ZSTD_MAGIC) {
+            #[cfg(not(feature = "zstd"))]
+            anyhow::bail!("DatabaseGenome is zstd-compressed, which needs the `zstd` feature");
+            #[cfg(feature = "zstd")]
+            {
+                let decompressed = zstd::decode_all(bytes)
+                    .context("Failed to decompress DatabaseGenome")?;
+                return Self::from_bytes(&decompressed);
+            }
+        }
+
+        match GenomeFormat::detect(bytes) {
+            GenomeFormat::Json => serde_json::from_slice(bytes)
+                .context("Failed to deserialize DatabaseGenome from JSON"),
+            GenomeFormat::Bin => rmp_serde::from_slice(bytes)
+                .context("Failed to deserialize DatabaseGenome from MessagePack"),
+        }
+    }
+
     pub fn load_from_file(path: &Path) -> Result<Self> {
         info!(path = ?path, "Loading DatabaseGenome from file");
 
-        let json = std::fs::read_to_string(path)
+        let bytes = std::fs::read(path)
             .context("Failed to read DatabaseGenome file")?;
 
-        let genome: DatabaseGenome = serde_json::from_str(&json)
-            .context("Failed to deserialize DatabaseGenome from JSON")?;
+        let genome = Self::from_bytes(&bytes)?;
 
         debug!(
             version = %genome.version,
@@ -153,27 +310,117 @@ impl DatabaseGenome {
         self.tables.iter().map(|t| t.foreign_keys.len()).sum()
     }
 
-    /// Validates that distributions exist for all columns in all tables.
-    pub fn validate(&self) -> Result<()> {
-        let mut missing_distributions = Vec::new();
-        let mut correlation_errors = Vec::new();
+    /// `table.column` of every column without a distribution, in table order.
+    pub fn missing_distributions(&self) -> Vec<String> {
+        self.tables
+            .iter()
+            .flat_map(|table| table.columns.iter().map(|column| Self::make_key(&table.name, &column.name)))
+            .filter(|key| !self.distributions.contains_key(key))
+            .collect()
+    }
 
+    /// Lenient loading (`gen --allow-missing-distributions`): gives every column without
+    /// a distribution the type-based placeholder that `scan --from-ddl` uses, and
+    /// returns the columns filled.
+    pub fn fill_missing_distributions(&mut self) -> Vec<String> {
+        let missing = self.missing_distributions();
         for table in &self.tables {
-            // Validate distributions for all columns
             for column in &table.columns {
                 let key = Self::make_key(&table.name, &column.name);
-                if !self.distributions.contains_key(&key) {
-                    missing_distributions.push(format!("{}.{}", table.name, column.name));
+                if missing.contains(&key) {
+                    self.distributions.insert(key, placeholder_distribution(column, None, None));
+                }
+            }
+        }
+        missing
+    }
+
+    /// Adds a warning unless the genome already has it.
+    pub fn warn(&mut self, warning: GenomeWarning) {
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+
+    /// Records the caveats that can be read off the finished genome: columns of
+    /// derived types left out, columns of unsupported types, categorical columns that kept only their most frequent
+    /// values, and tables that were empty. `profiled` tells the tables whose
+    /// distributions come from scanned rows; for those, numeric columns without a
+    /// correlation matrix are a caveat too.
+    pub fn collect_warnings(&mut self, profiled: impl Fn(&str) -> bool) {
+        let mut warnings = Vec::new();
+        for table in &self.tables {
+            let distributions: Vec<(&str, Option<&Distribution>)> = table
+                .columns
+                .iter()
+                .map(|column| (column.name.as_str(), self.get_distribution(&table.name, &column.name)))
+                .collect();
+
+            for (column, source_type) in &table.derived_columns {
+                warnings.push(GenomeWarning::column(
+                    &table.name,
+                    column,
+                    format!("type {} is derived, the column is not profiled or generated", source_type),
+                ));
+            }
+            if !distributions.is_empty() && distributions.iter().all(|(_, d)| d.is_some_and(|d| d.synthetic_default)) {
+                warnings.push(GenomeWarning::table(&table.name, "table was empty, its columns use defaults for their types"));
+                continue;
+            }
+
+            for column in &table.columns {
+                if let Some(source_type) = &column.unmapped_type {
+                    warnings.push(GenomeWarning::column(
+                        &table.name,
+                        &column.name,
+                        format!("type {} is not supported, values are generated as text", source_type),
+                    ));
                 }
             }
+            for (column, distribution) in &distributions {
+                if let Some(Distribution {
+                    histogram: Histogram::Categorical { frequencies, truncated: true, long_tail: None },
+                    ..
+                }) = distribution
+                {
+                    warnings.push(GenomeWarning::column(
+                        &table.name,
+                        column,
+                        format!("too many distinct values, only the {} most frequent are generated", frequencies.len()),
+                    ));
+                }
+            }
+
+            let numeric = table
+                .columns
+                .iter()
+                .filter(|c| c.data_type.is_correlated())
+                .count();
+            if numeric >= 2 && profiled(&table.name) && !self.correlations.contains_key(&table.name) {
+                warnings.push(GenomeWarning::table(
+                    &table.name,
+                    format!("no correlation matrix, its {} numeric and timestamp columns are generated independently", numeric),
+                ));
+            }
+        }
+        for warning in warnings {
+            self.warn(warning);
+        }
+    }
 
+    /// Validates that distributions exist for all columns in all tables.
+    pub fn validate(&self) -> Result<()> {
+        let missing_distributions = self.missing_distributions();
+        let mut correlation_errors = Vec::new();
+
+        for table in &self.tables {
             // Validate correlation matrix if present
             if let Some(corr_matrix) = self.correlations.get(&table.name) {
                 // Get numeric columns from table
                 let numeric_columns: Vec<&str> = table
                     .columns
                     .iter()
-                    .filter(|c| matches!(c.data_type, DataType::Integer | DataType::Float))
+                    .filter(|c| c.data_type.is_correlated())
                     .map(|c| c.name.as_str())
                     .collect();
 
@@ -191,7 +438,7 @@ impl DatabaseGenome {
                 for corr_col in &corr_matrix.columns {
                     if !numeric_columns.contains(&corr_col.as_str()) {
                         correlation_errors.push(format!(
-                            "Table '{}': correlation matrix references non-numeric or non-existent column '{}'",
+                            "Table '{}': correlation matrix references an uncorrelated or non-existent column '{}'",
                             table.name,
                             corr_col
                         ));
@@ -229,6 +476,24 @@ impl DatabaseGenome {
             ));
         }
 
+        if let Some(profile) = &self.tenants {
+            for table in &profile.tables {
+                if !self.get_table(table).is_some_and(|t| t.columns.iter().any(|c| c.name == profile.column)) {
+                    errors.push(format!("Tenant profile names table '{}' without a '{}' column", table, profile.column));
+                }
+            }
+            if profile.row_counts.iter().any(|rows| rows.len() != profile.tables.len()) {
+                errors.push(format!("Tenant profile rows don't have one count per table ({})", profile.tables.len()));
+            }
+        }
+
+        for key in self.fanouts.keys() {
+            let (table, column) = key.split_once('.').unwrap_or((key, ""));
+            if !self.get_table(table).is_some_and(|t| t.foreign_keys.iter().any(|fk| fk.source_col == column)) {
+                errors.push(format!("Fan-out '{}' names no foreign key", key));
+            }
+        }
+
         if !errors.is_empty() {
             anyhow::bail!(
                 "DatabaseGenome validation failed:\n{}",
@@ -244,6 +509,7 @@ impl DatabaseGenome {
 mod tests {
     use super::*;
     use crate::schema::{Column, DataType};
+    use crate::copula::CorrelationMethod;
 
     #[test]
     fn test_genome_with_correlations() {
@@ -262,15 +528,15 @@ mod tests {
         let mut distributions = HashMap::new();
         distributions.insert(
             DatabaseGenome::make_key("users", "id"),
-            crate::math::Distribution::new(Some(1.0), Some(100.0), 0, 100, 100, crate::math::Histogram::Numeric { bins: vec![], frequencies: vec![] }),
+            crate::math::Distribution::new(Some(1.0), Some(100.0), 0, 100, 100, crate::math::Histogram::Numeric { bins: vec![], frequencies: vec![], scale: crate::math::BinScale::Linear }),
         );
         distributions.insert(
             DatabaseGenome::make_key("users", "age"),
-            crate::math::Distribution::new(Some(18.0), Some(65.0), 0, 100, 48, crate::math::Histogram::Numeric { bins: vec![], frequencies: vec![] }),
+            crate::math::Distribution::new(Some(18.0), Some(65.0), 0, 100, 48, crate::math::Histogram::Numeric { bins: vec![], frequencies: vec![], scale: crate::math::BinScale::Linear }),
         );
         distributions.insert(
             DatabaseGenome::make_key("users", "salary"),
-            crate::math::Distribution::new(Some(30000.0), Some(200000.0), 0, 100, 95, crate::math::Histogram::Numeric { bins: vec![], frequencies: vec![] }),
+            crate::math::Distribution::new(Some(30000.0), Some(200000.0), 0, 100, 95, crate::math::Histogram::Numeric { bins: vec![], frequencies: vec![], scale: crate::math::BinScale::Linear }),
         );
 
         let mut correlations = HashMap::new();
@@ -278,6 +544,8 @@ mod tests {
             columns: vec!["age".to_string(), "salary".to_string()],
             matrix_data: vec![1.0, 0.8, 0.8, 1.0],
             dimension: 2,
+            method: CorrelationMethod::Pearson,
+            pseudo_observations: None,
         };
         correlations.insert("users".to_string(), cov);
 
@@ -311,11 +579,11 @@ mod tests {
         let mut distributions = HashMap::new();
         distributions.insert(
             DatabaseGenome::make_key("users", "id"),
-            crate::math::Distribution::new(Some(1.0), Some(100.0), 0, 100, 100, crate::math::Histogram::Numeric { bins: vec![], frequencies: vec![] }),
+            crate::math::Distribution::new(Some(1.0), Some(100.0), 0, 100, 100, crate::math::Histogram::Numeric { bins: vec![], frequencies: vec![], scale: crate::math::BinScale::Linear }),
         );
         distributions.insert(
             DatabaseGenome::make_key("users", "age"),
-            crate::math::Distribution::new(Some(18.0), Some(65.0), 0, 100, 48, crate::math::Histogram::Numeric { bins: vec![], frequencies: vec![] }),
+            crate::math::Distribution::new(Some(18.0), Some(65.0), 0, 100, 48, crate::math::Histogram::Numeric { bins: vec![], frequencies: vec![], scale: crate::math::BinScale::Linear }),
         );
 
         let mut correlations = HashMap::new();
@@ -324,6 +592,8 @@ mod tests {
             columns: vec!["age".to_string(), "salary".to_string()],
             matrix_data: vec![1.0, 0.8, 0.8, 1.0],
             dimension: 2,
+            method: CorrelationMethod::Pearson,
+            pseudo_observations: None,
         };
         correlations.insert("users".to_string(), cov);
 
@@ -349,6 +619,8 @@ mod tests {
             columns: vec!["a".to_string(), "b".to_string()],
             matrix_data: vec![1.0, 0.5, 0.5, 1.0],
             dimension: 2,
+            method: CorrelationMethod::Pearson,
+            pseudo_observations: None,
         };
         correlations.insert("test".to_string(), cov);
 
@@ -367,4 +639,120 @@ mod tests {
         let corr = genome.get_correlation("test").unwrap();
         assert_eq!(corr.matrix_data[1], 0.9);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_binary_round_trip() -> Result<()> {
+        let tables = vec![Table::new(
+            "users".to_string(),
+            vec![
+                Column::new("age".to_string(), DataType::Float, true, false),
+                Column::new("tier".to_string(), DataType::Text, true, false),
+            ],
+            vec![],
+        )];
+
+        let mut age = crate::math::Distribution::new(Some(18.0), Some(65.0), 3, 100, 48, crate::math::Histogram::Numeric {
+            bins: vec![18.0, 40.0, 65.0],
+            frequencies: vec![60, 37],
+            scale: crate::math::BinScale::Linear,
+        });
+        age.fit = Some(crate::fit::ParametricFit {
+            family: crate::fit::ParametricFamily::Normal { mean: 40.0, std_dev: 10.0 },
+            ks_statistic: 0.02,
+            p_value: 0.9,
+        });
+        let tier = crate::math::Distribution::new(None, None, 0, 100, 2, crate::math::Histogram::Categorical {
+            frequencies: [("gold".to_string(), 20), ("basic".to_string(), 80)].into_iter().collect(),
+            truncated: false,
+            long_tail: None,
+        });
+
+        let mut distributions = HashMap::new();
+        distributions.insert(DatabaseGenome::make_key("users", "age"), age);
+        distributions.insert(DatabaseGenome::make_key("users", "tier"), tier);
+        let genome = DatabaseGenome::new(tables, distributions);
+
+        let json = genome.to_bytes(GenomeFormat::Json)?;
+        let bin = genome.to_bytes(GenomeFormat::Bin)?;
+        assert!(bin.len() < json.len());
+
+        // Both encodings load back to the same genome, and plain MessagePack is accepted too
+        let expected = serde_json::to_value(&genome)?;
+        for bytes in [json, bin, rmp_serde::to_vec_named(&genome)?] {
+            assert_eq!(serde_json::to_value(DatabaseGenome::from_bytes(&bytes)?)?, expected);
+        }
+        assert!(DatabaseGenome::from_bytes(b"not a genome").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fill_missing_distributions() {
+        let tables = vec![Table::new(
+            "events".to_string(),
+            vec![
+                Column::new("id".to_string(), DataType::Integer, false, true),
+                Column::new("at".to_string(), DataType::Timestamp, true, false),
+                Column::new("kind".to_string(), DataType::Text, true, false),
+            ],
+            vec![],
+        )];
+        let mut distributions = HashMap::new();
+        distributions.insert(
+            DatabaseGenome::make_key("events", "id"),
+            crate::math::Distribution::new(Some(1.0), Some(100.0), 0, 100, 100, crate::math::Histogram::Numeric { bins: vec![1.0, 100.0], frequencies: vec![100], scale: crate::math::BinScale::Linear }),
+        );
+        let mut genome = DatabaseGenome::new(tables, distributions);
+
+        assert_eq!(genome.missing_distributions(), ["events.at", "events.kind"]);
+        assert!(genome.validate().is_err());
+
+        assert_eq!(genome.fill_missing_distributions(), ["events.at", "events.kind"]);
+        assert!(genome.missing_distributions().is_empty());
+        genome.validate().expect("Filled genome should validate");
+        let at = genome.get_distribution("events", "at").unwrap();
+        assert!(at.min.is_some_and(|min| min > 0.0));
+    }
+
+    #[test]
+    fn test_collect_warnings() {
+        let mut location = Column::new("location".to_string(), DataType::Text, true, false);
+        location.unmapped_type = Some("point".to_string());
+        let mut docs = Table::new(
+            "docs".to_string(),
+            vec![
+                Column::new("id".to_string(), DataType::Integer, false, true),
+                Column::new("score".to_string(), DataType::Float, true, false),
+                Column::new("tag".to_string(), DataType::Text, true, false),
+                location,
+            ],
+            vec![],
+        );
+        docs.derived_columns.insert("search".to_string(), "tsvector".to_string());
+        let tables = vec![docs];
+        let mut genome = DatabaseGenome::new(tables, HashMap::new());
+        genome.fill_missing_distributions();
+        genome.distributions.insert(
+            DatabaseGenome::make_key("docs", "tag"),
+            crate::math::Distribution::new(None, None, 0, 100, 50, crate::math::Histogram::Categorical {
+                frequencies: [("a".to_string(), 60), ("b".to_string(), 40)].into_iter().collect(),
+                truncated: true,
+                long_tail: None,
+            }),
+        );
+
+        genome.collect_warnings(|_| false);
+        assert_eq!(genome.warnings.len(), 3);
+        assert_eq!(genome.warnings[0].to_string(), "docs.search: type tsvector is derived, the column is not profiled or generated");
+        assert_eq!(genome.warnings[1].to_string(), "docs.location: type point is not supported, values are generated as text");
+        assert_eq!(genome.warnings[2].column.as_deref(), Some("tag"));
+
+        // Collecting again adds only what is new
+        genome.collect_warnings(|_| true);
+        assert_eq!(genome.warnings.len(), 4);
+        assert_eq!(genome.warnings[3], GenomeWarning::table("docs", "no correlation matrix, its 2 numeric and timestamp columns are generated independently"));
+
+        let bytes = serde_json::to_vec(&genome).unwrap();
+        let loaded: DatabaseGenome = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(loaded.warnings, genome.warnings);
+    }
+}
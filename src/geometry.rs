@@ -0,0 +1,165 @@
+//! Bounding-box profiling and point generation for PostGIS `geometry` and `geography`
+//! columns.
+//!
+//! Scanning keeps, per SRID, the number of rows and the bounding box of their
+//! coordinates, never the geometries themselves. The genome stores each box as a
+//! category in PostGIS' `box2d` text form with an SRID prefix
+//! (`SRID=4326;BOX(-74.3 40.5,-73.7 40.9)`); generation draws a box by its row count
+//! and writes a uniformly random point inside it as EWKT.
+
+use std::collections::BTreeMap;
+use anyhow::{Context, Result};
+use rand::Rng;
+
+/// Box of columns without profiled data: the whole WGS 84 range, which any SRID-less
+/// or 4326 column accepts.
+pub const PLACEHOLDER_BOX: &str = "SRID=4326;BOX(-180 -90,180 90)";
+
+/// Smallest rectangle holding a set of points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+impl BoundingBox {
+    fn point(x: f64, y: f64) -> Self {
+        Self { min_x: x, min_y: y, max_x: x, max_y: y }
+    }
+
+    fn extend(&mut self, x: f64, y: f64) {
+        self.min_x = self.min_x.min(x);
+        self.min_y = self.min_y.min(y);
+        self.max_x = self.max_x.max(x);
+        self.max_y = self.max_y.max(y);
+    }
+}
+
+/// Row counts and bounding boxes of a column's geometries, per SRID (0 when unset).
+#[derive(Debug, Default)]
+pub struct Extents {
+    boxes: BTreeMap<i32, (u64, BoundingBox)>,
+}
+
+impl Extents {
+    /// Adds a geometry in WKT or EWKT form. Returns `false` if it can't be parsed;
+    /// empty geometries are accepted but widen no box.
+    pub fn add(&mut self, ewkt: &str) -> bool {
+        let Some((srid, coordinates)) = parse_ewkt(ewkt) else {
+            return false;
+        };
+        let mut points = coordinates.into_iter();
+        let Some((x, y)) = points.next() else {
+            return true;
+        };
+        let (count, bounds) = self.boxes.entry(srid).or_insert((0, BoundingBox::point(x, y)));
+        *count += 1;
+        bounds.extend(x, y);
+        for (x, y) in points {
+            bounds.extend(x, y);
+        }
+        true
+    }
+
+    /// Row counts keyed by box, the form the genome stores them in.
+    pub fn frequencies(&self) -> BTreeMap<String, u64> {
+        self.boxes.iter().map(|(&srid, &(count, bounds))| (box_key(srid, &bounds), count)).collect()
+    }
+}
+
+/// `SRID=<srid>;BOX(<min_x> <min_y>,<max_x> <max_y>)`, without the prefix for SRID 0.
+pub fn box_key(srid: i32, bounds: &BoundingBox) -> String {
+    let srid = if srid == 0 { String::new() } else { format!("SRID={};", srid) };
+    format!("{}BOX({} {},{} {})", srid, bounds.min_x, bounds.min_y, bounds.max_x, bounds.max_y)
+}
+
+/// A uniformly random point within `key`, a box as written by [`box_key`], as EWKT.
+pub fn point_in_box<R: Rng + ?Sized>(key: &str, rng: &mut R) -> Result<String> {
+    let (srid, corners) = parse_ewkt(key).context(format!("Invalid bounding box '{}'", key))?;
+    let mut corners = corners.into_iter();
+    let (x, y) = corners.next().context(format!("Empty bounding box '{}'", key))?;
+    let mut bounds = BoundingBox::point(x, y);
+    for (x, y) in corners {
+        bounds.extend(x, y);
+    }
+
+    let x = rng.gen_range(bounds.min_x..=bounds.max_x);
+    let y = rng.gen_range(bounds.min_y..=bounds.max_y);
+    let srid = if srid == 0 { String::new() } else { format!("SRID={};", srid) };
+    Ok(format!("{}POINT({} {})", srid, x, y))
+}
+
+/// SRID (0 when absent) and the x/y of every coordinate of a WKT or EWKT geometry.
+/// Z and M values are ignored.
+fn parse_ewkt(text: &str) -> Option<(i32, Vec<(f64, f64)>)> {
+    let text = text.trim();
+    let (srid, wkt) = match text.strip_prefix("SRID=").and_then(|rest| rest.split_once(';')) {
+        Some((srid, wkt)) => (srid.trim().parse().ok()?, wkt.trim()),
+        None => (0, text),
+    };
+    let tag_end = wkt.find('(').unwrap_or(wkt.len());
+    if !is_tag(&wkt[..tag_end]) {
+        return None;
+    }
+
+    // Every run of text between parentheses and commas is a coordinate, or the tag of
+    // a collection member (`POINT`, `LINESTRING Z`, `EMPTY`)
+    let mut coordinates = Vec::new();
+    for chunk in wkt[tag_end..].split(['(', ')', ',']).map(str::trim).filter(|c| !c.is_empty()) {
+        if is_tag(chunk) {
+            continue;
+        }
+        let values = chunk.split_whitespace().map(|v| v.parse::<f64>().ok()).collect::<Option<Vec<_>>>()?;
+        match values[..] {
+            [x, y, ..] if x.is_finite() && y.is_finite() => coordinates.push((x, y)),
+            _ => return None,
+        }
+    }
+    Some((srid, coordinates))
+}
+
+fn is_tag(text: &str) -> bool {
+    let mut words = text.split_whitespace().peekable();
+    words.peek().is_some() && words.all(|word| word.bytes().all(|b| b.is_ascii_alphabetic()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_extents() {
+        let mut extents = Extents::default();
+        assert!(extents.add("SRID=4326;POINT(-73.9 40.7)"));
+        assert!(extents.add("SRID=4326;POLYGON((-74 40.6,-73.8 40.6,-73.8 40.8,-74 40.6))"));
+        assert!(extents.add("POINT Z (1 2 3)"));
+        assert!(extents.add("GEOMETRYCOLLECTION(POINT(5 6),LINESTRING EMPTY)"));
+        assert!(extents.add("SRID=4326;POINT EMPTY"));
+        assert!(!extents.add("0101000020E6100000"));
+        assert!(!extents.add("POINT(1)"));
+
+        let frequencies = extents.frequencies();
+        assert_eq!(frequencies.get("SRID=4326;BOX(-74 40.6,-73.8 40.8)"), Some(&2));
+        assert_eq!(frequencies.get("BOX(1 2,5 6)"), Some(&2));
+        assert_eq!(frequencies.len(), 2);
+    }
+
+    #[test]
+    fn test_point_in_box() -> Result<()> {
+        let mut rng = StdRng::seed_from_u64(5);
+        for _ in 0..100 {
+            let point = point_in_box("SRID=4326;BOX(-74 40.6,-73.8 40.8)", &mut rng)?;
+            let (srid, coordinates) = parse_ewkt(&point).unwrap();
+            let (x, y) = coordinates[0];
+            assert_eq!(srid, 4326);
+            assert!((-74.0..=-73.8).contains(&x) && (40.6..=40.8).contains(&y), "{}", point);
+        }
+        assert_eq!(point_in_box("BOX(3 4,3 4)", &mut rng)?, "POINT(3 4)");
+        assert!(point_in_box("BOX EMPTY", &mut rng).is_err());
+        Ok(())
+    }
+}
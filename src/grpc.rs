@@ -0,0 +1,276 @@
+//! gRPC generation service (`grpc` feature).
+//!
+//! Implements the `replica_db.v1.Generator` service from `proto/replica_db.proto`
+//! over the same [`GenomeStore`] as the HTTP server: `LoadGenome` stores a genome and
+//! `GenerateTable` streams one table's rows as [`RowBatch`]es, each value in Postgres
+//! text form.
+
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use anyhow::{Context, Result};
+use futures::Stream;
+use tokio::sync::mpsc;
+use tonic::{Request, Response, Status};
+use tracing::info;
+use crate::output::parse_copy_line;
+use crate::serve::{GenomeStore, ServeOptions};
+use crate::synth::{SynthesisConfig, Synthesizer};
+
+pub mod proto {
+    tonic::include_proto!("replica_db.v1");
+}
+
+use proto::generator_server::{Generator, GeneratorServer};
+use proto::{GenerateTableRequest, LoadGenomeRequest, Row, RowBatch, Value};
+
+/// Rows per batch when the request leaves `batch_size` at 0.
+pub const DEFAULT_BATCH_SIZE: usize = 1000;
+
+/// Batches buffered between the generating thread and the response stream.
+const BATCHES_IN_FLIGHT: usize = 16;
+
+pub struct GeneratorService {
+    store: Arc<GenomeStore>,
+    options: ServeOptions,
+}
+
+impl GeneratorService {
+    pub fn new(store: Arc<GenomeStore>, options: ServeOptions) -> Self {
+        Self { store, options }
+    }
+
+    fn synthesis_config(&self, request: &GenerateTableRequest) -> Result<SynthesisConfig, Status> {
+        let max_rows = self.options.max_rows as u64;
+        let too_many = std::iter::once(request.rows)
+            .chain(request.table_rows.values().copied())
+            .any(|rows| rows > max_rows);
+        if too_many {
+            return Err(Status::invalid_argument(format!(
+                "This server generates at most {} rows per table",
+                max_rows
+            )));
+        }
+
+        Ok(SynthesisConfig {
+            rows_per_table: request.rows as usize,
+            table_rows: request
+                .table_rows
+                .iter()
+                .map(|(table, rows)| (table.clone(), *rows as usize))
+                .collect(),
+            seed: request.seed,
+            jobs: self.options.jobs,
+            ..SynthesisConfig::default()
+        })
+    }
+}
+
+type RowBatchStream = Pin<Box<dyn Stream<Item = Result<RowBatch, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Generator for GeneratorService {
+    async fn load_genome(
+        &self,
+        request: Request<LoadGenomeRequest>,
+    ) -> Result<Response<proto::GenomeSummary>, Status> {
+        let summary = self
+            .store
+            .insert_bytes(&request.into_inner().genome)
+            .map_err(|e| Status::invalid_argument(format!("{:#}", e)))?;
+        Ok(Response::new(proto::GenomeSummary {
+            id: summary.id,
+            tables: summary.tables,
+            columns: summary.columns as u64,
+        }))
+    }
+
+    type GenerateTableStream = RowBatchStream;
+
+    /// Generates the whole genome, so the table's foreign keys resolve, then streams
+    /// only the requested table.
+    async fn generate_table(
+        &self,
+        request: Request<GenerateTableRequest>,
+    ) -> Result<Response<Self::GenerateTableStream>, Status> {
+        let request = request.into_inner();
+        let genome = self
+            .store
+            .get(&request.genome_id)
+            .ok_or_else(|| Status::not_found(format!("No genome with id '{}'", request.genome_id)))?;
+        if genome.get_table(&request.table).is_none() {
+            return Err(Status::not_found(format!("Table '{}' not found in genome", request.table)));
+        }
+        let config = self.synthesis_config(&request)?;
+        let batch_size = match request.batch_size {
+            0 => DEFAULT_BATCH_SIZE,
+            n => n as usize,
+        };
+
+        let (synthesizer, result) = tokio::task::spawn_blocking(move || -> Result<_> {
+            let synthesizer = Synthesizer::new((*genome).clone(), config)?;
+            let result = synthesizer.generate()?;
+            Ok((synthesizer, result))
+        })
+        .await
+        .map_err(|e| Status::internal(format!("Generation task failed: {}", e)))?
+        .map_err(|e| Status::failed_precondition(format!("{:#}", e)))?;
+
+        let table = request.table;
+        info!(genome = %request.genome_id, table = %table, "Streaming generated rows over gRPC");
+
+        let (sender, receiver) = mpsc::channel(BATCHES_IN_FLIGHT);
+        tokio::task::spawn_blocking(move || {
+            let Some(schema) = synthesizer.genome().get_table(&table) else {
+                return;
+            };
+            let columns: Vec<String> = synthesizer
+                .output_columns(schema)
+                .iter()
+                .map(|c| c.name.clone())
+                .collect();
+            let lines = result.get_copy_data(&table).unwrap_or_default().lines();
+
+            let mut rows = Vec::with_capacity(batch_size);
+            for line in lines {
+                let values = parse_copy_line(line).into_iter().map(|text| Value { text }).collect();
+                rows.push(Row { values });
+                if rows.len() == batch_size {
+                    let batch = RowBatch { columns: columns.clone(), rows: std::mem::take(&mut rows) };
+                    if sender.blocking_send(Ok(batch)).is_err() {
+                        // Client went away
+                        return;
+                    }
+                }
+            }
+            if !rows.is_empty() {
+                let _ = sender.blocking_send(Ok(RowBatch { columns, rows }));
+            }
+        });
+
+        let stream = futures::stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|batch| (batch, receiver))
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+/// Serves the gRPC generator on `listen` until the process is stopped.
+pub async fn serve_grpc(listen: SocketAddr, store: Arc<GenomeStore>, options: ServeOptions) -> Result<()> {
+    let max_message = options.max_genome_bytes;
+    let service = GeneratorServer::new(GeneratorService::new(store, options)).max_decoding_message_size(max_message);
+    info!(address = %listen, "Serving replica_db gRPC API");
+    tonic::transport::Server::builder()
+        .add_service(service)
+        .serve(listen)
+        .await
+        .context("gRPC server failed")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use futures::StreamExt;
+    use crate::genome::DatabaseGenome;
+    use crate::math::{Distribution, Histogram};
+    use crate::schema::{Column, DataType, ForeignKey, Table};
+
+    fn genome_bytes() -> Vec<u8> {
+        let tables = vec![
+            Table::new(
+                "users".to_string(),
+                vec![
+                    Column::new("id".to_string(), DataType::Integer, false, true),
+                    Column::new("name".to_string(), DataType::Text, true, false),
+                ],
+                vec![],
+            ),
+            Table::new(
+                "orders".to_string(),
+                vec![
+                    Column::new("id".to_string(), DataType::Integer, false, true),
+                    Column::new("user_id".to_string(), DataType::Integer, false, false),
+                ],
+                vec![ForeignKey::new("user_id".to_string(), "users".to_string(), "id".to_string())],
+            ),
+        ];
+        let mut distributions = HashMap::new();
+        distributions.insert(
+            DatabaseGenome::make_key("users", "name"),
+            Distribution::new(None, None, 10, 10, 0, Histogram::Categorical {
+                frequencies: Default::default(),
+                truncated: false,
+                long_tail: None,
+            }),
+        );
+        serde_json::to_vec(&DatabaseGenome::new(tables, distributions)).unwrap()
+    }
+
+    async fn collect(service: &GeneratorService, request: GenerateTableRequest) -> Result<Vec<RowBatch>, Status> {
+        let stream = service.generate_table(Request::new(request)).await?.into_inner();
+        stream.collect::<Vec<_>>().await.into_iter().collect()
+    }
+
+    #[tokio::test]
+    async fn test_generate_table_batches() {
+        let service = GeneratorService::new(Arc::default(), ServeOptions::default());
+        let summary = service
+            .load_genome(Request::new(LoadGenomeRequest { genome: genome_bytes() }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let request = GenerateTableRequest {
+            genome_id: summary.id.clone(),
+            table: "orders".to_string(),
+            rows: 3,
+            table_rows: [("orders".to_string(), 25)].into_iter().collect(),
+            seed: Some(1),
+            batch_size: 10,
+        };
+        let batches = collect(&service, request).await.unwrap();
+        assert_eq!(batches.iter().map(|b| b.rows.len()).collect::<Vec<_>>(), vec![10, 10, 5]);
+        assert_eq!(batches[0].columns, vec!["id", "user_id"]);
+        for row in batches.iter().flat_map(|b| &b.rows) {
+            let user_id: u64 = row.values[1].text.as_deref().unwrap().parse().unwrap();
+            assert!((1..=3).contains(&user_id));
+        }
+
+        let request = GenerateTableRequest {
+            genome_id: summary.id,
+            table: "users".to_string(),
+            rows: 2,
+            ..Default::default()
+        };
+        let batches = collect(&service, request).await.unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].rows[1].values, vec![Value { text: Some("2".to_string()) }, Value { text: None }]);
+    }
+
+    #[tokio::test]
+    async fn test_generate_table_errors() {
+        let options = ServeOptions { max_rows: 10, ..ServeOptions::default() };
+        let service = GeneratorService::new(Arc::default(), options);
+        let id = service
+            .load_genome(Request::new(LoadGenomeRequest { genome: genome_bytes() }))
+            .await
+            .unwrap()
+            .into_inner()
+            .id;
+
+        let request = |table: &str, rows: u64| GenerateTableRequest {
+            genome_id: id.clone(),
+            table: table.to_string(),
+            rows,
+            ..Default::default()
+        };
+        let code = |r: Result<Vec<RowBatch>, Status>| r.unwrap_err().code();
+        assert_eq!(code(collect(&service, request("missing", 1)).await), tonic::Code::NotFound);
+        assert_eq!(code(collect(&service, request("users", 11)).await), tonic::Code::InvalidArgument);
+
+        let invalid = service.load_genome(Request::new(LoadGenomeRequest { genome: b"{}".to_vec() })).await;
+        assert_eq!(invalid.unwrap_err().code(), tonic::Code::InvalidArgument);
+    }
+}
@@ -0,0 +1,131 @@
+//! Space-Saving heavy-hitter sketch (Metwally et al.) for categorical columns with
+//! more distinct values than can be tracked exactly.
+//!
+//! With `capacity` counters, every value whose true frequency exceeds `n / capacity`
+//! is guaranteed to be tracked, and each count overestimates by at most its `error`.
+
+use std::collections::{BTreeSet, HashMap};
+
+pub const DEFAULT_HEAVY_HITTER_CAPACITY: usize = 1_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Counter {
+    pub count: u64,
+    pub error: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SpaceSaving {
+    capacity: usize,
+    counters: HashMap<String, Counter>,
+    /// (count, value) ordered so the minimum counter is found in O(log k).
+    by_count: BTreeSet<(u64, String)>,
+    total: u64,
+    evictions: u64,
+}
+
+impl Default for SpaceSaving {
+    fn default() -> Self {
+        Self::new(DEFAULT_HEAVY_HITTER_CAPACITY)
+    }
+}
+
+impl SpaceSaving {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            counters: HashMap::with_capacity(capacity),
+            by_count: BTreeSet::new(),
+            total: 0,
+            evictions: 0,
+        }
+    }
+
+    pub fn add(&mut self, value: &str) {
+        self.total += 1;
+
+        if let Some(counter) = self.counters.get_mut(value) {
+            self.by_count.remove(&(counter.count, value.to_string()));
+            counter.count += 1;
+            self.by_count.insert((counter.count, value.to_string()));
+            return;
+        }
+
+        if self.counters.len() < self.capacity {
+            self.counters.insert(value.to_string(), Counter { count: 1, error: 0 });
+            self.by_count.insert((1, value.to_string()));
+            return;
+        }
+
+        // Evict the smallest counter; the newcomer inherits its count as error
+        if let Some((min_count, evicted)) = self.by_count.pop_first() {
+            self.counters.remove(&evicted);
+            self.evictions += 1;
+            let counter = Counter { count: min_count + 1, error: min_count };
+            self.counters.insert(value.to_string(), counter);
+            self.by_count.insert((counter.count, value.to_string()));
+        }
+    }
+
+    /// Values seen so far, including those no longer tracked.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Whether the stream held more distinct values than there are counters.
+    pub fn overflowed(&self) -> bool {
+        self.evictions > 0
+    }
+
+    /// Up to `k` values by estimated count, keeping only those guaranteed to occur
+    /// more than `total / capacity` times (`count - error` above that bound).
+    pub fn top_k(&self, k: usize) -> Vec<(String, Counter)> {
+        let threshold = self.total / self.capacity as u64;
+        self.by_count
+            .iter()
+            .rev()
+            .filter_map(|(_, value)| {
+                let counter = self.counters[value];
+                (counter.count - counter.error > threshold).then(|| (value.clone(), counter))
+            })
+            .take(k)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_when_under_capacity() {
+        let mut sketch = SpaceSaving::new(10);
+        for value in ["a", "b", "a", "c", "a", "b"] {
+            sketch.add(value);
+        }
+
+        let top = sketch.top_k(2);
+        assert_eq!(top[0], ("a".to_string(), Counter { count: 3, error: 0 }));
+        assert_eq!(top[1], ("b".to_string(), Counter { count: 2, error: 0 }));
+        assert!(!sketch.overflowed());
+    }
+
+    #[test]
+    fn test_heavy_hitters_survive_long_tail() {
+        let mut sketch = SpaceSaving::new(50);
+        for i in 0..100_000u64 {
+            match i % 10 {
+                0..=3 => sketch.add("popular"),
+                4 => sketch.add("common"),
+                _ => sketch.add(&format!("rare-{}", i)),
+            }
+        }
+
+        assert!(sketch.overflowed());
+        let top = sketch.top_k(2);
+        assert_eq!(top[0].0, "popular");
+        assert_eq!(top[1].0, "common");
+        // Overestimate is bounded by n / capacity
+        assert!(top[0].1.count >= 40_000 && top[0].1.count <= 40_000 + 100_000 / 50);
+    }
+}
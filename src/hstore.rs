@@ -0,0 +1,107 @@
+//! Key/value profiling for `hstore` columns.
+//!
+//! Each pair of a value is profiled as one category, written in hstore's own text form
+//! (`"color"=>"red"`), and the number of pairs per row is counted alongside. Generation
+//! draws a pair count and that many pairs with distinct keys, and joins them into an
+//! hstore literal.
+
+/// Keys and values (`None` for NULL) of hstore text such as `"a"=>"1", b=>NULL`.
+/// `None` if the text is malformed.
+pub fn parse_hstore(text: &str) -> Option<Vec<(String, Option<String>)>> {
+    let mut chars = text.chars().peekable();
+    let mut pairs = Vec::new();
+    loop {
+        skip_whitespace(&mut chars);
+        if chars.peek().is_none() {
+            return Some(pairs);
+        }
+        if !pairs.is_empty() {
+            if chars.next() != Some(',') {
+                return None;
+            }
+            skip_whitespace(&mut chars);
+        }
+
+        let (key, _) = read_item(&mut chars)?;
+        skip_whitespace(&mut chars);
+        if (chars.next(), chars.next()) != (Some('='), Some('>')) {
+            return None;
+        }
+        skip_whitespace(&mut chars);
+        let (value, quoted) = read_item(&mut chars)?;
+        let value = (quoted || !value.eq_ignore_ascii_case("null")).then_some(value);
+        pairs.push((key, value));
+    }
+}
+
+/// One pair in hstore text form, quoted and escaped.
+pub fn format_pair(key: &str, value: Option<&str>) -> String {
+    let quote = |text: &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+    match value {
+        Some(value) => format!("{}=>{}", quote(key), quote(value)),
+        None => format!("{}=>NULL", quote(key)),
+    }
+}
+
+/// Key of a single pair as written by [`format_pair`]; `None` for anything else.
+pub fn pair_key(pair: &str) -> Option<String> {
+    match parse_hstore(pair)?.as_slice() {
+        [(key, _)] => Some(key.clone()),
+        _ => None,
+    }
+}
+
+fn skip_whitespace(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+/// A double-quoted item with backslash escapes, or a bare one running up to
+/// whitespace, `,` or `=>`. Also returns whether it was quoted.
+fn read_item(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Option<(String, bool)> {
+    let mut item = String::new();
+    if chars.next_if_eq(&'"').is_some() {
+        loop {
+            match chars.next()? {
+                '"' => return Some((item, true)),
+                '\\' => item.push(chars.next()?),
+                c => item.push(c),
+            }
+        }
+    }
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() || c == ',' || c == '=' {
+            break;
+        }
+        item.push(c);
+        chars.next();
+    }
+    (!item.is_empty()).then_some((item, false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hstore() {
+        let pairs = parse_hstore(r#""color"=>"red", size=>10, "note"=>NULL, "q\"t"=>"NULL""#).unwrap();
+        assert_eq!(pairs, vec![
+            ("color".to_string(), Some("red".to_string())),
+            ("size".to_string(), Some("10".to_string())),
+            ("note".to_string(), None),
+            ("q\"t".to_string(), Some("NULL".to_string())),
+        ]);
+        assert_eq!(parse_hstore(""), Some(vec![]));
+        assert_eq!(parse_hstore("a=>"), None);
+        assert_eq!(parse_hstore(r#""a"=>"1" "b"=>"2""#), None);
+    }
+
+    #[test]
+    fn test_format_pair() {
+        let pair = format_pair("sa\"id", Some("c:\\tmp"));
+        assert_eq!(pair, r#""sa\"id"=>"c:\\tmp""#);
+        assert_eq!(parse_hstore(&pair).unwrap(), vec![("sa\"id".to_string(), Some("c:\\tmp".to_string()))]);
+        assert_eq!(pair_key(&format_pair("k", None)).as_deref(), Some("k"));
+        assert_eq!(pair_key("unknown"), None);
+    }
+}
@@ -0,0 +1,127 @@
+//! `interval` values as seconds. Scanning profiles intervals as a number of seconds,
+//! the way `extract(epoch from ...)` counts them, and generation writes the seconds
+//! back as interval literals.
+
+use std::io::Write;
+
+/// Seconds in a day, hour and minute.
+const DAY: f64 = 86_400.0;
+const HOUR: f64 = 3_600.0;
+const MINUTE: f64 = 60.0;
+
+/// Postgres counts a month as 30 days and a year as 365.25 days when it turns an
+/// interval into seconds.
+const MONTH: f64 = 30.0 * DAY;
+const YEAR: f64 = 365.25 * DAY;
+
+/// Seconds in an interval in Postgres' output form (`1 year 2 mons 3 days 04:05:06.5`,
+/// `-00:00:01`) or its verbose form (`@ 3 days 4 hours ago`). `None` if unparsable.
+pub fn parse_interval(raw: &str) -> Option<f64> {
+    let mut text = raw.trim().strip_prefix('@').unwrap_or(raw.trim()).trim();
+    let ago = text.strip_suffix("ago").map(str::trim_end);
+    if let Some(rest) = ago {
+        text = rest;
+    }
+
+    let mut seconds = 0.0;
+    let mut tokens = text.split_whitespace();
+    let mut any = false;
+    while let Some(token) = tokens.next() {
+        any = true;
+        if token.contains(':') {
+            seconds += parse_clock(token)?;
+            continue;
+        }
+        let amount: f64 = token.parse().ok()?;
+        let unit = tokens.next()?;
+        seconds += amount * unit_seconds(unit)?;
+    }
+    if !any {
+        return None;
+    }
+    Some(if ago.is_some() { -seconds } else { seconds })
+}
+
+/// Seconds in a `[-]HH:MM[:SS[.ffffff]]` time part.
+fn parse_clock(token: &str) -> Option<f64> {
+    let (sign, clock) = match token.strip_prefix('-') {
+        Some(clock) => (-1.0, clock),
+        None => (1.0, token.strip_prefix('+').unwrap_or(token)),
+    };
+    let mut parts = clock.split(':');
+    let hours: f64 = parts.next()?.parse().ok()?;
+    let minutes: f64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next().map_or(Some(0.0), |s| s.parse().ok())?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(sign * (hours * HOUR + minutes * MINUTE + seconds))
+}
+
+fn unit_seconds(unit: &str) -> Option<f64> {
+    Some(match unit.to_lowercase().trim_end_matches(',') {
+        "year" | "years" | "yr" | "yrs" | "y" => YEAR,
+        "mon" | "mons" | "month" | "months" => MONTH,
+        "week" | "weeks" | "w" => 7.0 * DAY,
+        "day" | "days" | "d" => DAY,
+        "hour" | "hours" | "hr" | "hrs" | "h" => HOUR,
+        "min" | "mins" | "minute" | "minutes" | "m" => MINUTE,
+        "sec" | "secs" | "second" | "seconds" | "s" => 1.0,
+        _ => return None,
+    })
+}
+
+/// Writes `seconds` as `[D days ]HH:MM:SS[.ffffff]`, which Postgres reads back as the
+/// same interval. Negative intervals have both parts negated (`-3 days -04:00:00`).
+pub fn write_interval(out: &mut Vec<u8>, seconds: f64) {
+    if !seconds.is_finite() {
+        out.extend_from_slice(b"00:00:00");
+        return;
+    }
+
+    let micros = (seconds.abs() * 1e6).round() as u64;
+    let sign = if seconds < 0.0 && micros > 0 { "-" } else { "" };
+    let days = micros / (DAY as u64 * 1_000_000);
+    let rest = micros % (DAY as u64 * 1_000_000);
+    let (whole, fraction) = (rest / 1_000_000, rest % 1_000_000);
+    if days > 0 {
+        let _ = write!(out, "{sign}{} days ", days);
+    }
+    let _ = write!(out, "{sign}{:02}:{:02}:{:02}", whole / 3_600, whole / 60 % 60, whole % 60);
+    if fraction > 0 {
+        let digits = format!("{:06}", fraction);
+        let _ = write!(out, ".{}", digits.trim_end_matches('0'));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(seconds: f64) -> String {
+        let mut out = Vec::new();
+        write_interval(&mut out, seconds);
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval("3 days 04:00:00"), Some(3.0 * DAY + 4.0 * HOUR));
+        assert_eq!(parse_interval("1 year 2 mons"), Some(YEAR + 2.0 * MONTH));
+        assert_eq!(parse_interval("-00:00:01.5"), Some(-1.5));
+        assert_eq!(parse_interval("1 day -01:00:00"), Some(DAY - HOUR));
+        assert_eq!(parse_interval("@ 2 hours 30 mins ago"), Some(-2.5 * HOUR));
+        assert_eq!(parse_interval("soon"), None);
+        assert_eq!(parse_interval(""), None);
+    }
+
+    #[test]
+    fn test_write_interval() {
+        assert_eq!(render(3.0 * DAY + 4.0 * HOUR), "3 days 04:00:00");
+        assert_eq!(render(90.25), "00:01:30.25");
+        assert_eq!(render(-(DAY + 61.0)), "-1 days -00:01:01");
+        for seconds in [0.0, 59.5, 12.0 * DAY + 7.0, -HOUR] {
+            assert_eq!(parse_interval(&render(seconds)), Some(seconds));
+        }
+    }
+}
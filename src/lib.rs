@@ -0,0 +1,106 @@
+//! replica_db: fast statistical database twin generator.
+//!
+//! The crate works in two phases:
+//!
+//! 1. **Scan** a Postgres database into a [`DatabaseGenome`], a compact statistical
+//!    snapshot of the schema (tables, keys, per-column distributions, correlations).
+//! 2. **Synthesize** any number of rows from a genome with a [`Synthesizer`] and write
+//!    them out with one of the [`output`] writers.
+//!
+//! Scanning and loading need the `postgres` feature, the HTTP service the `server`
+//! feature, and correlated sampling the `copula` feature; the default `cli` feature
+//! turns on all three. Without them, only genome parsing, synthesis and output are
+//! left, which also build for `wasm32-unknown-unknown`:
+//! `cargo check --target wasm32-unknown-unknown --no-default-features`.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # #[cfg(feature = "postgres")]
+//! use replica_db::{scan, write_copy_sql, ScanOptions, SynthesisConfig, Synthesizer};
+//!
+//! # #[cfg(feature = "postgres")]
+//! # async fn run() -> anyhow::Result<()> {
+//! let pool = sqlx::PgPool::connect("postgresql://localhost/production").await?;
+//! let genome = scan(&pool, &ScanOptions::default()).await?;
+//!
+//! let config = SynthesisConfig { rows_per_table: 10_000, ..SynthesisConfig::default() };
+//! let synthesizer = Synthesizer::new(genome, config)?;
+//! let result = synthesizer.generate()?;
+//!
+//! write_copy_sql(&mut std::io::stdout().lock(), &synthesizer, &result)?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod anonymize;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod audit;
+pub mod bench;
+#[cfg(feature = "postgres")]
+pub mod checkpoint;
+#[cfg(feature = "postgres")]
+pub mod concurrency;
+pub mod conditional;
+pub mod config;
+pub mod copula;
+#[cfg(feature = "postgres")]
+pub mod csv_scan;
+pub mod dbt;
+pub mod ddl;
+pub mod diff;
+pub mod edit;
+pub mod export;
+pub mod fit;
+#[cfg(feature = "flight")]
+pub mod flight;
+pub mod genome;
+pub mod geometry;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod heavy_hitters;
+pub mod hstore;
+pub mod interval;
+#[cfg(feature = "postgres")]
+pub mod load;
+pub mod markov;
+pub mod math;
+pub mod metrics;
+pub mod network;
+pub mod order;
+pub mod pattern;
+pub mod output;
+#[cfg(feature = "postgres")]
+pub mod pg_stats;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+pub mod privacy;
+#[cfg(feature = "postgres")]
+pub mod pseudonym;
+pub mod report;
+#[cfg(feature = "postgres")]
+pub mod scan;
+#[cfg(feature = "postgres")]
+pub mod scanner;
+pub mod schema;
+pub mod seasonality;
+pub mod sequence;
+pub mod semantic;
+#[cfg(feature = "server")]
+pub mod serve;
+pub mod special;
+pub mod subset;
+pub mod synth;
+pub mod tdigest;
+pub mod tenant;
+#[cfg(feature = "tui")]
+pub mod tui;
+
+pub use genome::DatabaseGenome;
+pub use output::write_copy_sql;
+#[cfg(feature = "postgres")]
+pub use scan::{scan, ScanOptions};
+#[cfg(feature = "postgres")]
+pub use scanner::RetryPolicy;
+pub use synth::{GenerationResult, SynthesisConfig, Synthesizer};
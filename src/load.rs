@@ -0,0 +1,459 @@
+//! Direct loading of generated rows into a target database with `COPY ... FROM STDIN`,
+//! without writing SQL to disk first.
+//!
+//! Tables are loaded in execution order so parents exist before the children that
+//! reference them, and serial/identity sequences are moved past the loaded keys. The
+//! whole load runs in one transaction, so a failure leaves the target untouched.
+//!
+//! Plain COPY fails on duplicate keys. To refresh a target that already holds data,
+//! either truncate it first or copy each table into a staging table and merge it with
+//! `INSERT ... ON CONFLICT`. To add rows next to the existing ones instead, generate
+//! with the keys offset by [`existing_key_offsets`] and load with `append`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use anyhow::{bail, Context, Result};
+use sqlx::{PgConnection, PgPool};
+use tracing::info;
+use crate::output::{copy_columns, copy_statement, sequence_fixups, DEFAULT_WRITE_BUFFER};
+use crate::schema::{Column, DataType, Table};
+use crate::synth::{GenerationResult, KeyKind, Synthesizer};
+
+/// Temporary table each table is copied into before an `ON CONFLICT` merge.
+const STAGING_TABLE: &str = "replica_db_staging";
+
+/// What a direct load does with rows whose key already exists in the target.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnConflict {
+    /// Plain COPY; a duplicate key fails the load.
+    #[default]
+    Error,
+    /// Keep the existing row.
+    Skip,
+    /// Overwrite the existing row with the generated one. Needs a primary key.
+    Replace,
+}
+
+impl fmt::Display for OnConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OnConflict::Error => write!(f, "error"),
+            OnConflict::Skip => write!(f, "skip"),
+            OnConflict::Replace => write!(f, "replace"),
+        }
+    }
+}
+
+impl FromStr for OnConflict {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "error" => Ok(OnConflict::Error),
+            "skip" => Ok(OnConflict::Skip),
+            "replace" => Ok(OnConflict::Replace),
+            other => bail!("Unknown conflict mode '{}' (expected error, skip or replace)", other),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LoadOptions {
+    /// Run `CREATE TABLE IF NOT EXISTS` for every table before loading it.
+    pub create_tables: bool,
+
+    /// Empty every generated table on the target before loading.
+    pub truncate_target: bool,
+
+    pub on_conflict: OnConflict,
+
+    /// Keep the target's rows and add the generated ones after them; generation must
+    /// continue the target's keys (see [`existing_key_offsets`]).
+    pub append: bool,
+
+    /// Bytes sent to the server per COPY message.
+    pub chunk_size: usize,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self {
+            create_tables: false,
+            truncate_target: false,
+            on_conflict: OnConflict::Error,
+            append: false,
+            chunk_size: DEFAULT_WRITE_BUFFER,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadSummary {
+    pub tables: usize,
+    pub rows: u64,
+}
+
+/// Loads every generated table into the database behind `pool` in one transaction.
+pub async fn load(
+    pool: &PgPool,
+    synthesizer: &Synthesizer,
+    result: &GenerationResult,
+    options: &LoadOptions,
+) -> Result<LoadSummary> {
+    if options.append && options.truncate_target {
+        bail!("Appending can't be combined with truncating the target");
+    }
+    let mut tx = pool.begin().await.context("Failed to start the load transaction")?;
+    let genome = synthesizer.genome();
+    let mut summary = LoadSummary::default();
+
+    let tables: Vec<&Table> = synthesizer
+        .execution_order()
+        .iter()
+        .filter(|name| result.get_table_data(name).is_some())
+        .map(|name| genome.get_table(name).context(format!("Table '{}' not found in genome", name)))
+        .collect::<Result<_>>()?;
+
+    if options.create_tables {
+        for table in &tables {
+            sqlx::raw_sql(&create_table_sql(table, &genome.tables))
+                .execute(&mut *tx)
+                .await
+                .context(format!("Failed to create table '{}'", table.name))?;
+        }
+    }
+
+    if options.truncate_target && !tables.is_empty() {
+        sqlx::raw_sql(&truncate_sql(&tables))
+            .execute(&mut *tx)
+            .await
+            .context("Failed to truncate target tables")?;
+        info!(tables = tables.len(), "Truncated target tables");
+    }
+
+    for table in tables {
+        let copy_data = result
+            .get_table_data(&table.name)
+            .context(format!("No generated data for table '{}'", table.name))?
+            .as_copy_data();
+
+        let rows = match options.on_conflict {
+            OnConflict::Error => {
+                copy_rows(&mut tx, &copy_statement(synthesizer, table), copy_data, options.chunk_size).await?
+            }
+            mode => merge_rows(&mut tx, synthesizer, table, copy_data, mode, options.chunk_size).await?,
+        };
+
+        for statement in sequence_fixups(table) {
+            sqlx::raw_sql(&statement)
+                .execute(&mut *tx)
+                .await
+                .context(format!("Failed to advance sequences of '{}'", table.name))?;
+        }
+
+        info!(table = %table.name, rows, "Loaded table");
+        summary.tables += 1;
+        summary.rows += rows;
+    }
+
+    tx.commit().await.context("Failed to commit the load")?;
+    Ok(summary)
+}
+
+/// Streams COPY text through `statement`; returns the rows copied.
+async fn copy_rows(conn: &mut PgConnection, statement: &str, copy_data: &str, chunk_size: usize) -> Result<u64> {
+    let mut copy = conn
+        .copy_in_raw(statement)
+        .await
+        .context(format!("Failed to start {}", statement))?;
+    for chunk in copy_data.as_bytes().chunks(chunk_size.max(1)) {
+        copy.send(chunk).await.context("Failed to send COPY data")?;
+    }
+    copy.finish().await.context(format!("{} failed", statement))
+}
+
+/// Copies into a staging table shaped like `table` and merges it with `ON CONFLICT`;
+/// returns the rows inserted or updated.
+async fn merge_rows(
+    conn: &mut PgConnection,
+    synthesizer: &Synthesizer,
+    table: &Table,
+    copy_data: &str,
+    mode: OnConflict,
+    chunk_size: usize,
+) -> Result<u64> {
+    let insert = merge_sql(table, &synthesizer.output_columns(table), mode)?;
+    let columns = copy_columns(synthesizer, table);
+
+    sqlx::raw_sql(&format!(
+        "CREATE TEMP TABLE {} (LIKE {} INCLUDING DEFAULTS)",
+        STAGING_TABLE, table.name
+    ))
+    .execute(&mut *conn)
+    .await
+    .context(format!("Failed to create staging table for '{}'", table.name))?;
+
+    let copy = format!("COPY {} ({}) FROM stdin", STAGING_TABLE, columns);
+    copy_rows(conn, &copy, copy_data, chunk_size).await?;
+
+    let merged = sqlx::raw_sql(&insert)
+        .execute(&mut *conn)
+        .await
+        .context(format!("Failed to merge rows into '{}'", table.name))?
+        .rows_affected();
+
+    sqlx::raw_sql(&format!("DROP TABLE {}", STAGING_TABLE))
+        .execute(&mut *conn)
+        .await
+        .context("Failed to drop staging table")?;
+    Ok(merged)
+}
+
+/// `INSERT ... SELECT` from the staging table with the conflict clause for `mode`.
+fn merge_sql(table: &Table, columns: &[&Column], mode: OnConflict) -> Result<String> {
+    let names: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
+    let insert = format!(
+        "INSERT INTO {table} ({columns}) SELECT {columns} FROM {STAGING_TABLE}",
+        table = table.name,
+        columns = names.join(", "),
+    );
+
+    let keys: Vec<&str> = table.primary_keys().iter().map(|c| c.name.as_str()).collect();
+    let updates: Vec<String> = columns
+        .iter()
+        .filter(|c| !c.is_primary_key)
+        .map(|c| format!("{0} = EXCLUDED.{0}", c.name))
+        .collect();
+
+    Ok(match mode {
+        OnConflict::Error => insert,
+        OnConflict::Skip => format!("{} ON CONFLICT DO NOTHING", insert),
+        OnConflict::Replace if keys.is_empty() => {
+            bail!("Table '{}' has no primary key to replace rows on", table.name)
+        }
+        OnConflict::Replace if updates.is_empty() => {
+            format!("{} ON CONFLICT ({}) DO NOTHING", insert, keys.join(", "))
+        }
+        OnConflict::Replace => format!(
+            "{} ON CONFLICT ({}) DO UPDATE SET {}",
+            insert,
+            keys.join(", "),
+            updates.join(", ")
+        ),
+    })
+}
+
+/// Largest counted primary key of every table in `tables` that exists on the target
+/// behind `pool`, for [`SynthesisConfig::table_key_offsets`](crate::synth::SynthesisConfig::table_key_offsets)
+/// when appending. Text keys count when they are numbers. Tables without counted keys
+/// or rows are left out.
+pub async fn existing_key_offsets(pool: &PgPool, tables: &[Table]) -> Result<HashMap<String, i64>> {
+    let mut offsets = HashMap::new();
+    for table in tables {
+        let Some(query) = max_key_sql(table) else {
+            continue;
+        };
+        let exists: bool = sqlx::query_scalar("SELECT to_regclass($1) IS NOT NULL")
+            .bind(&table.name)
+            .fetch_one(pool)
+            .await
+            .context(format!("Failed to look up '{}' on the target", table.name))?;
+        if !exists {
+            continue;
+        }
+        let largest: Option<i64> = sqlx::query_scalar(&query)
+            .fetch_one(pool)
+            .await
+            .context(format!("Failed to read the largest key of '{}'", table.name))?;
+        if let Some(largest) = largest.filter(|&largest| largest > 0) {
+            offsets.insert(table.name.clone(), largest);
+        }
+    }
+    info!(tables = offsets.len(), "Read existing primary keys to append after");
+    Ok(offsets)
+}
+
+/// `SELECT` of the largest counted key of `table` over all its counted key columns,
+/// or `None` when it has none (only UUID keys, or no primary key).
+fn max_key_sql(table: &Table) -> Option<String> {
+    let maxima: Vec<String> = table
+        .primary_keys()
+        .iter()
+        .filter_map(|column| match KeyKind::for_column(&column.data_type) {
+            KeyKind::Uuid => None,
+            KeyKind::Text => Some(format!("max(CASE WHEN {0} ~ '^[0-9]{{1,18}}$' THEN {0}::int8 END)", column.name)),
+            KeyKind::Int => Some(format!("max({})::int8", column.name)),
+        })
+        .collect();
+    match maxima.as_slice() {
+        [] => None,
+        [max] => Some(format!("SELECT {} FROM {}", max, table.name)),
+        _ => Some(format!("SELECT greatest({}) FROM {}", maxima.join(", "), table.name)),
+    }
+}
+
+/// One `TRUNCATE` for all `tables`, children first. Tables referenced by a foreign key
+/// can only be truncated in the same statement as the tables referencing them.
+fn truncate_sql(tables: &[&Table]) -> String {
+    let names: Vec<&str> = tables.iter().rev().map(|t| t.name.as_str()).collect();
+    format!("TRUNCATE {}", names.join(", "))
+}
+
+/// `CREATE TABLE IF NOT EXISTS` for `table` with its primary key and the foreign keys
+/// whose parent is among `tables`. Serial and identity columns get their sequence back
+/// as a `nextval` default; other defaults are not part of the genome and are left out.
+pub fn create_table_sql(table: &Table, tables: &[Table]) -> String {
+    let sequences: String = table
+        .columns
+        .iter()
+        .filter_map(|c| c.serial_sequence.as_ref())
+        .map(|sequence| format!("CREATE SEQUENCE IF NOT EXISTS {};\n", sequence))
+        .collect();
+
+    let mut definitions: Vec<String> = table.columns.iter().map(column_definition).collect();
+
+    let primary_keys: Vec<&str> = table.primary_keys().iter().map(|c| c.name.as_str()).collect();
+    if !primary_keys.is_empty() {
+        definitions.push(format!("PRIMARY KEY ({})", primary_keys.join(", ")));
+    }
+
+    for fk in &table.foreign_keys {
+        if tables.iter().any(|t| t.name == fk.target_table) {
+            definitions.push(format!(
+                "FOREIGN KEY ({}) REFERENCES {} ({})",
+                fk.source_col, fk.target_table, fk.target_col
+            ));
+        }
+    }
+
+    format!(
+        "{}CREATE TABLE IF NOT EXISTS {} (\n    {}\n);",
+        sequences,
+        table.name,
+        definitions.join(",\n    ")
+    )
+}
+
+fn column_definition(column: &Column) -> String {
+    let default = column
+        .serial_sequence
+        .as_ref()
+        .map(|sequence| format!(" DEFAULT nextval('{}')", sequence.replace('\'', "''")))
+        .unwrap_or_default();
+    let not_null = if column.is_nullable { "" } else { " NOT NULL" };
+    format!("{} {}{}{}", column.name, postgres_type(&column.data_type), default, not_null)
+}
+
+fn postgres_type(data_type: &DataType) -> &'static str {
+    match data_type {
+        DataType::Integer => "bigint",
+        DataType::Float => "double precision",
+        DataType::Text => "text",
+        DataType::Timestamp => "timestamptz",
+        DataType::Boolean => "boolean",
+        DataType::Uuid => "uuid",
+        DataType::Bytea => "bytea",
+        DataType::Inet => "inet",
+        DataType::Cidr => "cidr",
+        DataType::MacAddr => "macaddr",
+        DataType::Interval => "interval",
+        DataType::Geometry => "geometry",
+        DataType::Geography => "geography",
+        DataType::Hstore => "hstore",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::ForeignKey;
+
+    #[test]
+    fn test_merge_sql() -> Result<()> {
+        let table = Table::new(
+            "users".to_string(),
+            vec![
+                Column::new("id".to_string(), DataType::Integer, false, true),
+                Column::new("name".to_string(), DataType::Text, false, false),
+            ],
+            vec![],
+        );
+        let columns: Vec<&Column> = table.columns.iter().collect();
+
+        assert_eq!(
+            merge_sql(&table, &columns, OnConflict::Skip)?,
+            "INSERT INTO users (id, name) SELECT id, name FROM replica_db_staging ON CONFLICT DO NOTHING"
+        );
+        assert_eq!(
+            merge_sql(&table, &columns, OnConflict::Replace)?,
+            "INSERT INTO users (id, name) SELECT id, name FROM replica_db_staging \
+             ON CONFLICT (id) DO UPDATE SET name = EXCLUDED.name"
+        );
+
+        let keyless = Table::new("events".to_string(), table.columns[1..].to_vec(), vec![]);
+        assert!(merge_sql(&keyless, &[&keyless.columns[0]], OnConflict::Replace).is_err());
+        assert_eq!("Replace".parse::<OnConflict>()?, OnConflict::Replace);
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncate_children_first() {
+        let users = Table::new("users".to_string(), vec![], vec![]);
+        let orders = Table::new("orders".to_string(), vec![], vec![]);
+        assert_eq!(truncate_sql(&[&users, &orders]), "TRUNCATE orders, users");
+    }
+
+    #[test]
+    fn test_max_key_sql_covers_counted_keys() {
+        let column = |name: &str, data_type: DataType| Column::new(name.to_string(), data_type, false, true);
+        let users = Table::new("users".to_string(), vec![column("id", DataType::Integer)], vec![]);
+        assert_eq!(max_key_sql(&users).as_deref(), Some("SELECT max(id)::int8 FROM users"));
+
+        let tags = Table::new("tags".to_string(), vec![column("a", DataType::Integer), column("b", DataType::Text)], vec![]);
+        assert_eq!(
+            max_key_sql(&tags).as_deref(),
+            Some("SELECT greatest(max(a)::int8, max(CASE WHEN b ~ '^[0-9]{1,18}$' THEN b::int8 END)) FROM tags")
+        );
+
+        let events = Table::new("events".to_string(), vec![column("id", DataType::Uuid)], vec![]);
+        assert_eq!(max_key_sql(&events), None);
+    }
+
+    #[test]
+    fn test_create_table_sql() {
+        let users = Table::new(
+            "users".to_string(),
+            vec![Column::new("id".to_string(), DataType::Uuid, false, true)],
+            vec![],
+        );
+        let mut id = Column::new("id".to_string(), DataType::Integer, false, true);
+        id.serial_sequence = Some("public.orders_id_seq".to_string());
+        let orders = Table::new(
+            "orders".to_string(),
+            vec![
+                id,
+                Column::new("user_id".to_string(), DataType::Uuid, true, false),
+                Column::new("placed_at".to_string(), DataType::Timestamp, false, false),
+                Column::new("coupon_id".to_string(), DataType::Integer, true, false),
+            ],
+            vec![
+                ForeignKey::new("user_id".to_string(), "users".to_string(), "id".to_string()),
+                // Parent not in the genome
+                ForeignKey::new("coupon_id".to_string(), "coupons".to_string(), "id".to_string()),
+            ],
+        );
+
+        assert_eq!(
+            create_table_sql(&orders, &[users, orders.clone()]),
+            "CREATE SEQUENCE IF NOT EXISTS public.orders_id_seq;\n\
+             CREATE TABLE IF NOT EXISTS orders (\n    \
+             id bigint DEFAULT nextval('public.orders_id_seq') NOT NULL,\n    \
+             user_id uuid,\n    \
+             placed_at timestamptz NOT NULL,\n    \
+             coupon_id bigint,\n    \
+             PRIMARY KEY (id),\n    \
+             FOREIGN KEY (user_id) REFERENCES users (id)\n);"
+        );
+    }
+}
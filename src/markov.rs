@@ -0,0 +1,170 @@
+//! Word-level Markov chains for free-form text (descriptions, comments, notes).
+//!
+//! Only words that occur in at least [`MIN_WORD_DOCUMENTS`] sampled values enter the
+//! vocabulary, so one-off tokens such as names or ticket numbers are never stored.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Fewest text samples a model is trained on.
+pub const MIN_MARKOV_SAMPLES: usize = 50;
+
+/// Distinct values a word must appear in to be kept.
+pub const MIN_WORD_DOCUMENTS: usize = 2;
+
+/// Average words per value below which a column is not considered free text.
+pub const MIN_AVERAGE_WORDS: f64 = 3.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarkovModel {
+    /// Frequencies of the first kept word of each value.
+    pub starts: BTreeMap<String, u64>,
+
+    /// Word -> following word -> frequency.
+    pub transitions: BTreeMap<String, BTreeMap<String, u64>>,
+
+    /// Words per value -> frequency.
+    pub word_counts: BTreeMap<usize, u64>,
+}
+
+/// Whether `samples` look like prose rather than short labels.
+pub fn is_free_text(samples: &[String]) -> bool {
+    if samples.len() < MIN_MARKOV_SAMPLES {
+        return false;
+    }
+    let words: usize = samples.iter().map(|s| s.split_whitespace().count()).sum();
+    words as f64 / samples.len() as f64 >= MIN_AVERAGE_WORDS
+}
+
+impl MarkovModel {
+    /// Trains a first-order model, or `None` when too few words survive pruning.
+    pub fn train(samples: &[String]) -> Option<Self> {
+        let mut documents: HashMap<&str, usize> = HashMap::new();
+        for sample in samples {
+            let unique: HashSet<&str> = sample.split_whitespace().collect();
+            for word in unique {
+                *documents.entry(word).or_insert(0) += 1;
+            }
+        }
+
+        let mut model = MarkovModel {
+            starts: BTreeMap::new(),
+            transitions: BTreeMap::new(),
+            word_counts: BTreeMap::new(),
+        };
+
+        for sample in samples {
+            let words: Vec<&str> = sample.split_whitespace().collect();
+            if words.is_empty() {
+                continue;
+            }
+            *model.word_counts.entry(words.len()).or_insert(0) += 1;
+
+            let kept: Vec<&str> = words
+                .into_iter()
+                .filter(|w| documents.get(w).copied().unwrap_or(0) >= MIN_WORD_DOCUMENTS)
+                .collect();
+
+            if let Some(first) = kept.first() {
+                *model.starts.entry(first.to_string()).or_insert(0) += 1;
+            }
+            for pair in kept.windows(2) {
+                *model
+                    .transitions
+                    .entry(pair[0].to_string())
+                    .or_default()
+                    .entry(pair[1].to_string())
+                    .or_insert(0) += 1;
+            }
+        }
+
+        if model.starts.is_empty() || model.word_counts.is_empty() {
+            return None;
+        }
+
+        Some(model)
+    }
+
+    pub fn generate<R: Rng + ?Sized>(&self, rng: &mut R) -> String {
+        let length = weighted_choice(self.word_counts.iter().map(|(len, f)| (*len, *f)), rng).unwrap_or(1);
+
+        let mut words: Vec<&str> = Vec::with_capacity(length);
+        while words.len() < length {
+            let next = words
+                .last()
+                .and_then(|current| self.transitions.get(*current))
+                .and_then(|followers| weighted_choice(followers.iter().map(|(w, f)| (w.as_str(), *f)), rng))
+                // Dead end: restart from a sentence opener
+                .or_else(|| weighted_choice(self.starts.iter().map(|(w, f)| (w.as_str(), *f)), rng));
+
+            match next {
+                Some(word) => words.push(word),
+                None => break,
+            }
+        }
+
+        words.join(" ")
+    }
+}
+
+fn weighted_choice<T, R: Rng + ?Sized>(items: impl Iterator<Item = (T, u64)> + Clone, rng: &mut R) -> Option<T> {
+    let total: u64 = items.clone().map(|(_, f)| f).sum();
+    if total == 0 {
+        return None;
+    }
+
+    let mut target = rng.gen_range(0..total);
+    for (item, weight) in items {
+        if target < weight {
+            return Some(item);
+        }
+        target -= weight;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reviews() -> Vec<String> {
+        (0..60)
+            .map(|i| match i % 3 {
+                0 => format!("the delivery was fast and the box was fine {}", i),
+                1 => "the product was great and fast".to_string(),
+                _ => "box arrived late but the product was fine".to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rare_words_pruned() {
+        let model = MarkovModel::train(&reviews()).expect("model");
+
+        assert!(model.transitions.contains_key("product"));
+        // Trailing numbers are unique per value and must not be memorized
+        assert!(!model.transitions.values().any(|next| next.contains_key("3")));
+        assert!(!model.starts.contains_key("3"));
+    }
+
+    #[test]
+    fn test_generated_lengths_follow_source() {
+        let model = MarkovModel::train(&reviews()).expect("model");
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..50 {
+            let text = model.generate(&mut rng);
+            let words = text.split_whitespace().count();
+            assert!(model.word_counts.contains_key(&words), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_is_free_text() {
+        assert!(is_free_text(&reviews()));
+
+        let labels: Vec<String> = (0..60).map(|i| format!("status_{}", i % 4)).collect();
+        assert!(!is_free_text(&labels));
+    }
+}
@@ -1,10 +1,21 @@
 // Implements Reservoir algorithm for constant memory statistical analysis of large datasets
 
-use std::collections::{HashMap, HashSet};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
 use rand::Rng;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use crate::fit::{fit_best, ParametricFit};
+use crate::interval::parse_interval;
+use crate::markov::MarkovModel;
+use crate::pattern::TextPattern;
+use crate::seasonality::Seasonality;
+use crate::sequence::SequenceModel;
+use crate::semantic::SemanticType;
+use crate::tdigest::TDigest;
 
 const MAX_UNIQUE_TRACKING: usize = 10_000;
 
@@ -12,6 +23,66 @@ pub const DEFAULT_RESERVOIR_CAPACITY: usize = 10_000;
 
 pub const NUMERIC_HISTOGRAM_BINS: usize = 100;
 
+/// Equal-width histograms switch to log-scale edges when the fullest bin holds more
+/// than this share of the samples and the skewness exceeds `LOG_SCALE_MIN_SKEWNESS`.
+const LOG_SCALE_MIN_BIN_SHARE: f64 = 0.5;
+const LOG_SCALE_MIN_SKEWNESS: f64 = 2.0;
+
+/// Fewest samples the skew check trusts.
+const LOG_SCALE_MIN_SAMPLES: usize = 30;
+
+/// Integer columns with at most this many distinct values get a discrete histogram.
+pub const MAX_DISCRETE_VALUES: usize = 50;
+
+/// Numeric values holding at least this share of the samples become point masses.
+pub const POINT_MASS_MIN_SHARE: f64 = 0.1;
+
+/// Fewest samples left after splitting off point masses.
+const MIN_CONTINUOUS_SAMPLES: usize = 2;
+
+/// Largest magnitude an f64 holds every integer up to.
+const MAX_EXACT_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+/// How the edges of numeric histograms are placed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BinningStrategy {
+    /// `bins` bins of equal width between min and max.
+    #[default]
+    EqualWidth,
+    /// `bins` bins holding about the same number of samples; keeps detail where
+    /// skewed columns are dense.
+    Quantile,
+    /// Equal-width bins sized by the Freedman-Diaconis rule (2 IQR / n^(1/3)), at
+    /// most `bins` of them.
+    FreedmanDiaconis,
+}
+
+impl fmt::Display for BinningStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinningStrategy::EqualWidth => write!(f, "equal-width"),
+            BinningStrategy::Quantile => write!(f, "quantile"),
+            BinningStrategy::FreedmanDiaconis => write!(f, "freedman-diaconis"),
+        }
+    }
+}
+
+impl FromStr for BinningStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "equal-width" => Ok(BinningStrategy::EqualWidth),
+            "quantile" | "equal-frequency" => Ok(BinningStrategy::Quantile),
+            "freedman-diaconis" | "fd" => Ok(BinningStrategy::FreedmanDiaconis),
+            other => anyhow::bail!(
+                "Unknown binning strategy '{}' (expected equal-width, quantile or freedman-diaconis)",
+                other
+            ),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Distribution {
     pub min: Option<f64>,
@@ -20,6 +91,50 @@ pub struct Distribution {
     pub total_count: u64,
     pub unique_count: usize,
     pub histogram: Histogram,
+
+    /// Set when the scanner classified the column as personally identifiable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pii: Option<SemanticType>,
+
+    /// Character lengths of sampled text values.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lengths: Option<LengthDistribution>,
+
+    /// Key/value pairs per row of an hstore column, whose categories are single pairs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pair_counts: Option<LengthDistribution>,
+
+    /// Closed-form distribution that passed a goodness-of-fit test; preferred over
+    /// the histogram when sampling.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fit: Option<ParametricFit>,
+
+    /// Hour-of-day, weekday and monthly counts of a timestamp column.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seasonality: Option<Seasonality>,
+
+    /// Set for columns whose values increase in scan order; generated as a sequence.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<SequenceModel>,
+
+    /// Set when the scan winsorized the column: the histogram and fit cover only the
+    /// clipped range, while `min` and `max` stay the true extremes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outliers: Option<Outliers>,
+
+    /// Values far more frequent than the rest (zero discounts), by ascending value. The
+    /// histogram and fit describe only the remaining values.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub point_masses: Vec<PointMass>,
+
+    /// No sampled value was negative; generated values are kept at zero or above.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub non_negative: bool,
+
+    /// The source table was empty, so this is a default for the column's type rather
+    /// than a profile of its values.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub synthetic_default: bool,
 }
 
 impl Distribution {
@@ -38,6 +153,16 @@ impl Distribution {
             total_count,
             unique_count,
             histogram,
+            pii: None,
+            lengths: None,
+            pair_counts: None,
+            fit: None,
+            seasonality: None,
+            sequence: None,
+            outliers: None,
+            point_masses: Vec::new(),
+            non_negative: false,
+            synthetic_default: false,
         }
     }
 
@@ -47,6 +172,179 @@ impl Distribution {
         }
         ((self.total_count - self.null_count) as f64 / self.total_count as f64) * 100.0
     }
+
+    /// Fraction of NULLs in [0, 1].
+    pub fn null_rate(&self) -> f64 {
+        if self.total_count == 0 {
+            return 0.0;
+        }
+        self.null_count as f64 / self.total_count as f64
+    }
+
+    /// Approximate mean of a numeric column, point masses included.
+    pub fn mean(&self) -> Option<f64> {
+        if self.point_masses.is_empty() {
+            return self.histogram_mean();
+        }
+        let point_share: f64 = self.point_masses.iter().map(|m| m.share).sum();
+        let points: f64 = self.point_masses.iter().map(|m| m.value * m.share).sum();
+        Some(points + (1.0 - point_share) * self.histogram_mean().unwrap_or(0.0))
+    }
+
+    /// Approximate q-quantile of a numeric column, point masses included.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        if self.point_masses.is_empty() {
+            return self.histogram_quantile(q);
+        }
+        match locate_in_mixture(&self.point_masses, q, |x| self.histogram_cdf(x).unwrap_or(0.0)) {
+            MixtureDraw::PointMass(value) => Some(value),
+            MixtureDraw::Continuous(q) => self.histogram_quantile(q),
+        }
+    }
+
+    /// Approximate empirical CDF of a numeric column at `x`, point masses included.
+    pub fn cdf(&self, x: f64) -> Option<f64> {
+        if self.point_masses.is_empty() {
+            return self.histogram_cdf(x);
+        }
+        let point_share: f64 = self.point_masses.iter().map(|m| m.share).sum();
+        let below: f64 = self.point_masses.iter().filter(|m| m.value <= x).map(|m| m.share).sum();
+        Some(below + (1.0 - point_share) * self.histogram_cdf(x).unwrap_or(0.0))
+    }
+
+    /// Mean of the histogram alone (bin midpoints weighted by frequency).
+    fn histogram_mean(&self) -> Option<f64> {
+        if let Histogram::Quantile { digest } = &self.histogram {
+            return digest.mean();
+        }
+        if let Histogram::Discrete { values, frequencies } = &self.histogram {
+            let total: u64 = frequencies.iter().sum();
+            if total == 0 {
+                return None;
+            }
+            let weighted: f64 = values.iter().zip(frequencies).map(|(&v, &f)| v as f64 * f as f64).sum();
+            return Some(weighted / total as f64);
+        }
+
+        let Histogram::Numeric { bins, frequencies, scale } = &self.histogram else {
+            return None;
+        };
+
+        let total: u64 = frequencies.iter().sum();
+        if total == 0 || bins.len() < 2 {
+            return None;
+        }
+
+        let weighted: f64 = frequencies
+            .iter()
+            .enumerate()
+            .map(|(i, &f)| f as f64 * scale.interpolate(bins[i], bins[i + 1], 0.5, bins[0]))
+            .sum();
+
+        Some(weighted / total as f64)
+    }
+
+    /// q-quantile of the histogram alone, interpolating inside bins.
+    fn histogram_quantile(&self, q: f64) -> Option<f64> {
+        if let Histogram::Quantile { digest } = &self.histogram {
+            return digest.quantile(q);
+        }
+        if let Histogram::Discrete { values, frequencies } = &self.histogram {
+            return discrete_quantile(values, frequencies, q).map(|v| v as f64);
+        }
+
+        let Histogram::Numeric { bins, frequencies, scale } = &self.histogram else {
+            return None;
+        };
+
+        let total: u64 = frequencies.iter().sum();
+        if total == 0 || bins.len() < 2 {
+            return None;
+        }
+
+        let target = q.clamp(0.0, 1.0) * total as f64;
+        let mut cumulative = 0.0;
+
+        for (i, &f) in frequencies.iter().enumerate() {
+            let next = cumulative + f as f64;
+            if f > 0 && next >= target {
+                let position = (target - cumulative) / f as f64;
+                return Some(scale.interpolate(bins[i], bins[i + 1], position, bins[0]));
+            }
+            cumulative = next;
+        }
+
+        bins.last().copied()
+    }
+
+    /// Points where the numeric CDF changes slope (bin edges, centroid means or point
+    /// masses) and the number of sampled values behind it. `None` for non-numeric
+    /// histograms.
+    pub fn numeric_support(&self) -> Option<(Vec<f64>, u64)> {
+        let (mut points, count) = self.histogram_support()?;
+        if self.point_masses.is_empty() {
+            return Some((points, count));
+        }
+        let point_share: f64 = self.point_masses.iter().map(|m| m.share).sum();
+        points.extend(self.point_masses.iter().map(|m| m.value));
+        Some((points, (count as f64 / (1.0 - point_share)).round() as u64))
+    }
+
+    fn histogram_support(&self) -> Option<(Vec<f64>, u64)> {
+        match &self.histogram {
+            Histogram::Numeric { bins, frequencies, .. } => Some((bins.clone(), frequencies.iter().sum())),
+            Histogram::Discrete { values, frequencies } => {
+                Some((values.iter().map(|&v| v as f64).collect(), frequencies.iter().sum()))
+            }
+            Histogram::Quantile { digest } => {
+                let mut points: Vec<f64> = digest.centroids.iter().map(|c| c.mean).collect();
+                points.push(digest.min);
+                points.push(digest.max);
+                Some((points, digest.count))
+            }
+            _ => None,
+        }
+    }
+
+    /// CDF of the histogram alone at `x`, without point masses.
+    pub fn histogram_cdf(&self, x: f64) -> Option<f64> {
+        if let Histogram::Quantile { digest } = &self.histogram {
+            return digest.cdf(x);
+        }
+        if let Histogram::Discrete { values, frequencies } = &self.histogram {
+            let total: u64 = frequencies.iter().sum();
+            if total == 0 {
+                return None;
+            }
+            let below: u64 = values.iter().zip(frequencies).filter(|(v, _)| **v as f64 <= x).map(|(_, f)| f).sum();
+            return Some(below as f64 / total as f64);
+        }
+
+        let Histogram::Numeric { bins, frequencies, scale } = &self.histogram else {
+            return None;
+        };
+
+        let total: u64 = frequencies.iter().sum();
+        if total == 0 || bins.len() < 2 {
+            return None;
+        }
+
+        let mut cumulative = 0.0;
+        for (i, &f) in frequencies.iter().enumerate() {
+            let (lo, hi) = (bins[i], bins[i + 1]);
+            if x < lo {
+                break;
+            }
+            if x >= hi {
+                cumulative += f as f64;
+            } else {
+                cumulative += f as f64 * scale.position(lo, hi, x, bins[0]);
+                break;
+            }
+        }
+
+        Some(cumulative / total as f64)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,10 +353,252 @@ pub enum Histogram {
     Numeric {
         bins: Vec<f64>,
         frequencies: Vec<u64>,
+        /// Spacing of the edges, and how values are spread inside a bin.
+        #[serde(default, skip_serializing_if = "BinScale::is_linear")]
+        scale: BinScale,
     },
     Categorical {
-        frequencies: HashMap<String, u64>,
+        /// Ordered so seeded generation draws the same values in every run.
+        frequencies: BTreeMap<String, u64>,
         truncated: bool,
+        /// Mass outside `frequencies` when they hold only the heavy hitters of a
+        /// column with too many distinct values to track.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        long_tail: Option<LongTail>,
+    },
+    /// Values are produced by a faker-style generator; no source values are stored.
+    Semantic {
+        semantic_type: SemanticType,
+    },
+    /// High-cardinality identifiers generated from an inferred character-class pattern.
+    Pattern {
+        pattern: TextPattern,
+    },
+    /// Free-form text generated from a word-level Markov chain.
+    Markov {
+        model: MarkovModel,
+    },
+    /// Numeric column summarized by a t-digest quantile sketch.
+    Quantile {
+        digest: TDigest,
+    },
+    /// Boolean column: counts of true and false over every non-null row.
+    Boolean {
+        bernoulli: Bernoulli,
+    },
+    /// Integer column with few distinct values (ratings, quantities): exact counts per
+    /// value, so only observed integers are generated.
+    Discrete {
+        /// Distinct values, ascending.
+        values: Vec<i64>,
+        frequencies: Vec<u64>,
+    },
+}
+
+/// Smallest value of a discrete histogram whose cumulative share reaches `q`.
+pub fn discrete_quantile(values: &[i64], frequencies: &[u64], q: f64) -> Option<i64> {
+    let total: u64 = frequencies.iter().sum();
+    if total == 0 {
+        return None;
+    }
+    let target = q.clamp(0.0, 1.0) * total as f64;
+    let mut cumulative = 0.0;
+    for (&value, &f) in values.iter().zip(frequencies) {
+        cumulative += f as f64;
+        if f > 0 && cumulative >= target {
+            return Some(value);
+        }
+    }
+    values.last().copied()
+}
+
+/// Spacing of numeric histogram edges.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BinScale {
+    #[default]
+    Linear,
+    /// Edges evenly spaced in ln(1 + x - first edge), for heavily right-skewed
+    /// columns; values are spread the same way inside a bin.
+    Log,
+}
+
+impl BinScale {
+    fn is_linear(&self) -> bool {
+        *self == BinScale::Linear
+    }
+
+    /// Value `position` (0 to 1) of the way through the bin `[lo, hi]` of a histogram
+    /// whose first edge is `origin`.
+    pub fn interpolate(self, lo: f64, hi: f64, position: f64, origin: f64) -> f64 {
+        match self {
+            BinScale::Linear => lo + position * (hi - lo),
+            BinScale::Log => {
+                let (u_lo, u_hi) = ((lo - origin).ln_1p(), (hi - origin).ln_1p());
+                origin + (u_lo + position * (u_hi - u_lo)).exp_m1()
+            }
+        }
+    }
+
+    /// Inverse of [`BinScale::interpolate`]: how far `x` lies through `[lo, hi]`.
+    pub fn position(self, lo: f64, hi: f64, x: f64, origin: f64) -> f64 {
+        match self {
+            BinScale::Linear => (x - lo) / (hi - lo),
+            BinScale::Log => {
+                let (u_lo, u_hi) = ((lo - origin).ln_1p(), (hi - origin).ln_1p());
+                ((x - origin).ln_1p() - u_lo) / (u_hi - u_lo)
+            }
+        }
+    }
+}
+
+/// A single value holding `share` of a column's non-null values.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PointMass {
+    pub value: f64,
+    pub share: f64,
+}
+
+/// Where a quantile of a point-mass mixture falls.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MixtureDraw {
+    PointMass(f64),
+    /// Quantile within the continuous remainder.
+    Continuous(f64),
+}
+
+/// Finds quantile `q` of the mixture of `point_masses` and a continuous remainder
+/// whose CDF is `cdf`.
+pub fn locate_in_mixture(point_masses: &[PointMass], q: f64, cdf: impl Fn(f64) -> f64) -> MixtureDraw {
+    let continuous = 1.0 - point_masses.iter().map(|m| m.share).sum::<f64>();
+    let remainder = |q: f64, before: f64| {
+        MixtureDraw::Continuous(((q - before) / continuous).clamp(0.0, 1.0))
+    };
+
+    let mut before = 0.0;
+    for mass in point_masses {
+        let start = before + continuous * cdf(mass.value);
+        if q < start {
+            return remainder(q, before);
+        }
+        if q < start + mass.share {
+            return MixtureDraw::PointMass(mass.value);
+        }
+        before += mass.share;
+    }
+    remainder(q, before)
+}
+
+/// Values a winsorized column had beyond its clipping bounds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Outliers {
+    pub lower: f64,
+    pub upper: f64,
+    /// Shares of the sampled values below `lower` and above `upper`.
+    pub below_share: f64,
+    pub above_share: f64,
+}
+
+/// Rows not covered by the tracked heavy hitters, generated synthetically rather than
+/// replayed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LongTail {
+    /// Rows whose value is outside the heavy hitters, in the same units as the frequencies.
+    pub count: u64,
+
+    /// Shape of the tail values, when one could be inferred from the sample.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<TextPattern>,
+
+    /// Faker generator for tail values of a column that looks like personal data;
+    /// preferred over the pattern.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub semantic_type: Option<SemanticType>,
+}
+
+/// True/false counts of a boolean column. NULLs are counted by the enclosing
+/// [`Distribution`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Bernoulli {
+    pub true_count: u64,
+    pub false_count: u64,
+}
+
+impl Bernoulli {
+    pub fn add(&mut self, value: bool) {
+        if value {
+            self.true_count += 1;
+        } else {
+            self.false_count += 1;
+        }
+    }
+
+    /// Probability of `true` among non-null values.
+    pub fn p_true(&self) -> Option<f64> {
+        let total = self.true_count + self.false_count;
+        (total > 0).then(|| self.true_count as f64 / total as f64)
+    }
+
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> bool {
+        self.p_true().is_some_and(|p| rng.gen_bool(p))
+    }
+
+    /// Counts keyed by COPY text form (`t`/`f`), so reports and diffs can compare
+    /// boolean columns like categorical ones.
+    pub fn frequencies(&self) -> BTreeMap<String, u64> {
+        [("t".to_string(), self.true_count), ("f".to_string(), self.false_count)]
+            .into_iter()
+            .collect()
+    }
+}
+
+/// Frequencies of string lengths (in characters).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LengthDistribution {
+    pub counts: BTreeMap<usize, u64>,
+}
+
+impl LengthDistribution {
+    pub fn from_values<'a>(values: impl IntoIterator<Item = &'a String>) -> Self {
+        let mut lengths = Self::default();
+        for value in values {
+            lengths.add(value.chars().count());
+        }
+        lengths
+    }
+
+    pub fn add(&mut self, length: usize) {
+        *self.counts.entry(length).or_insert(0) += 1;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        let total: u64 = self.counts.values().sum();
+        if total == 0 {
+            return None;
+        }
+        let weighted: f64 = self.counts.iter().map(|(len, f)| *len as f64 * *f as f64).sum();
+        Some(weighted / total as f64)
+    }
+
+    /// Draws a length with probability proportional to its frequency.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<usize> {
+        let total: u64 = self.counts.values().sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut target = rng.gen_range(0..total);
+        for (&length, &frequency) in &self.counts {
+            if target < frequency {
+                return Some(length);
+            }
+            target -= frequency;
+        }
+        self.counts.keys().next_back().copied()
     }
 }
 
@@ -66,6 +606,40 @@ pub struct Reservoir<T: Clone> {
     capacity: usize,
     items: Vec<T>,
     total_seen: Arc<AtomicU64>,
+    weighted: Option<WeightedKeys>,
+}
+
+/// Keys of a weighted reservoir's items, smallest on top, and the weight of the
+/// items added next.
+struct WeightedKeys {
+    heap: BinaryHeap<Reverse<SampleKey>>,
+    log_weight: f64,
+}
+
+/// Key of the item in `slot`, compared by key only.
+struct SampleKey {
+    key: f64,
+    slot: usize,
+}
+
+impl PartialEq for SampleKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for SampleKey {}
+
+impl PartialOrd for SampleKey {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SampleKey {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.key.total_cmp(&other.key)
+    }
 }
 
 impl<T: Clone> Reservoir<T> {
@@ -75,24 +649,65 @@ impl<T: Clone> Reservoir<T> {
             capacity,
             items: Vec::with_capacity(capacity),
             total_seen: Arc::new(AtomicU64::new(0)),
+            weighted: None,
         }
     }
 
+    /// A reservoir that samples items in proportion to the weight set with
+    /// [`Reservoir::set_log_weight`] before adding them, instead of uniformly.
+    pub fn weighted(capacity: usize) -> Self {
+        Self {
+            weighted: Some(WeightedKeys { heap: BinaryHeap::with_capacity(capacity), log_weight: 0.0 }),
+            ..Self::new(capacity)
+        }
+    }
+
+    /// Natural log of the weight of the items added next; ignored by uniform reservoirs.
+    /// Taking the log keeps weights that grow exponentially, like recency, finite.
+    pub fn set_log_weight(&mut self, log_weight: f64) {
+        if let Some(weighted) = &mut self.weighted {
+            weighted.log_weight = log_weight;
+        }
+    }
+
+    pub fn is_weighted(&self) -> bool {
+        self.weighted.is_some()
+    }
+
     /*
     Algorithm R:
         1. fill the reservoir until the capacity is reached
         2. For item i > capacity:
             Generate random j in [0, i)
             If j < capacity, replace items[j] with item i
+
+    Weighted reservoirs use Algorithm A-Res instead: every item gets the key u^(1/w)
+    for a uniform u and its weight w, and the items with the largest keys are kept.
+    Keys are compared as ln(w) - ln(-ln u), which orders the same way.
      */
     pub fn add(&mut self, item: T) {
         let current_count = self.total_seen.fetch_add(1, Ordering::Relaxed);
         let index = current_count as usize;
+        let mut rng = rand::thread_rng();
+
+        if let Some(weighted) = &mut self.weighted {
+            let u: f64 = rng.r#gen();
+            let key = weighted.log_weight - (-u.ln()).ln();
+            if self.items.len() < self.capacity {
+                weighted.heap.push(Reverse(SampleKey { key, slot: self.items.len() }));
+                self.items.push(item);
+            } else if let Some(mut smallest) = weighted.heap.peek_mut()
+                && key > smallest.0.key
+            {
+                smallest.0.key = key;
+                self.items[smallest.0.slot] = item;
+            }
+            return;
+        }
 
         if index < self.capacity {
             self.items.push(item);
         } else {
-            let mut rng = rand::thread_rng();
             let j = rng.gen_range(0..=index);
 
             if j < self.capacity {
@@ -105,6 +720,10 @@ impl<T: Clone> Reservoir<T> {
         &self.items
     }
 
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
     pub fn total_seen(&self) -> u64 {
         self.total_seen.load(Ordering::Relaxed)
     }
@@ -118,6 +737,39 @@ impl<T: Clone> Reservoir<T> {
     }
 }
 
+/// Exponentially decayed sampling keyed on a timestamp column: a row's chance of
+/// entering the reservoirs halves with every `half_life` it is older than another row.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Recency {
+    /// Timestamp column that dates each row.
+    pub column: String,
+
+    /// Age, in seconds, over which a row's weight halves. Written as an interval,
+    /// e.g. `"30 days"`, in config files.
+    #[serde(deserialize_with = "deserialize_half_life")]
+    pub half_life: f64,
+}
+
+impl Recency {
+    /// Natural log of the weight of a row dated `epoch_seconds`. Only differences
+    /// between weights matter, so the epoch serves as the reference point.
+    pub fn log_weight(&self, epoch_seconds: f64) -> f64 {
+        std::f64::consts::LN_2 * epoch_seconds / self.half_life
+    }
+}
+
+fn deserialize_half_life<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<f64, D::Error> {
+    let raw = String::deserialize(deserializer)?;
+    match parse_interval(&raw) {
+        Some(seconds) if seconds > 0.0 => Ok(seconds),
+        _ => Err(serde::de::Error::custom(format!(
+            "invalid half-life '{}', expected a positive interval like '30 days'",
+            raw
+        ))),
+    }
+}
+
 pub struct DistributionBuilder {
     min: Option<f64>,
     max: Option<f64>,
@@ -126,6 +778,13 @@ pub struct DistributionBuilder {
     unique_values: HashSet<String>,
     numeric_samples: Vec<f64>,
     categorical_samples: Vec<String>,
+    lengths: LengthDistribution,
+    fit_parametric: bool,
+    binning: BinningStrategy,
+    bins: usize,
+    winsorize: Option<f64>,
+    discrete_integers: bool,
+    point_masses: bool,
 }
 
 impl DistributionBuilder {
@@ -138,9 +797,51 @@ impl DistributionBuilder {
             unique_values: HashSet::new(),
             numeric_samples: Vec::new(),
             categorical_samples: Vec::new(),
+            lengths: LengthDistribution::default(),
+            fit_parametric: false,
+            binning: BinningStrategy::EqualWidth,
+            bins: NUMERIC_HISTOGRAM_BINS,
+            winsorize: None,
+            discrete_integers: false,
+            point_masses: false,
         }
     }
 
+    /// Also try normal/lognormal/exponential/uniform fits for numeric samples.
+    pub fn with_parametric_fit(mut self, enabled: bool) -> Self {
+        self.fit_parametric = enabled;
+        self
+    }
+
+    /// Place numeric bin edges by `strategy`, with `bins` bins (at most, for
+    /// Freedman-Diaconis).
+    pub fn with_binning(mut self, strategy: BinningStrategy, bins: usize) -> Self {
+        self.binning = strategy;
+        self.bins = bins.max(1);
+        self
+    }
+
+    /// Clamp numeric samples to the `tail` and `1 - tail` quantiles before binning
+    /// and fitting, so a few extreme values don't stretch the histogram.
+    pub fn with_winsorization(mut self, tail: Option<f64>) -> Self {
+        self.winsorize = tail;
+        self
+    }
+
+    /// Store exact value counts when every numeric sample is a whole number and there
+    /// are at most [`MAX_DISCRETE_VALUES`] distinct ones.
+    pub fn with_discrete_integers(mut self, enabled: bool) -> Self {
+        self.discrete_integers = enabled;
+        self
+    }
+
+    /// Split values holding at least [`POINT_MASS_MIN_SHARE`] of the numeric samples
+    /// (a zero discount on most rows) off the histogram as point masses.
+    pub fn with_point_masses(mut self, enabled: bool) -> Self {
+        self.point_masses = enabled;
+        self
+    }
+
     pub fn add_numeric(&mut self, value: f64) {
         self.numeric_samples.push(value);
 
@@ -156,64 +857,229 @@ impl DistributionBuilder {
         if self.unique_values.len() < MAX_UNIQUE_TRACKING {
             self.unique_values.insert(value.clone());
         }
+        self.lengths.add(value.chars().count());
         self.categorical_samples.push(value);
     }
 
-    pub fn build(self) -> Distribution {
+    pub fn build(mut self) -> Distribution {
         let unique_count = self.unique_values.len();
+        let discrete = self.discrete_histogram();
+        let point_masses = if discrete.is_none() && self.point_masses {
+            self.split_point_masses()
+        } else {
+            Vec::new()
+        };
+        // A small domain has no tail worth clipping
+        let outliers = match (&discrete, self.winsorize) {
+            (None, Some(tail)) => self.winsorize_samples(tail),
+            _ => None,
+        };
 
-        let histogram = if !self.numeric_samples.is_empty() {
+        let histogram = if let Some(discrete) = discrete {
+            discrete
+        } else if !self.numeric_samples.is_empty() {
             self.build_numeric_histogram()
         } else {
             self.build_categorical_histogram()
         };
 
-        Distribution::new(
+        let mut distribution = Distribution::new(
             self.min,
             self.max,
             self.null_count,
             self.total_count,
             unique_count,
             histogram,
-        )
+        );
+        let continuous = matches!(distribution.histogram, Histogram::Numeric { .. });
+        if self.fit_parametric && continuous {
+            distribution.fit = fit_best(&self.numeric_samples);
+        }
+        if !self.lengths.is_empty() {
+            distribution.lengths = Some(self.lengths);
+        }
+        distribution.outliers = outliers;
+        distribution.point_masses = point_masses;
+        distribution.non_negative = !self.numeric_samples.is_empty() && self.min.is_some_and(|min| min >= 0.0);
+        distribution
     }
 
+    /// Exact counts per value when discrete integers are enabled and the samples hold
+    /// few distinct whole numbers.
+    fn discrete_histogram(&self) -> Option<Histogram> {
+        if !self.discrete_integers || self.numeric_samples.is_empty() {
+            return None;
+        }
+        let mut counts: BTreeMap<i64, u64> = BTreeMap::new();
+        for &value in &self.numeric_samples {
+            if value.fract() != 0.0 || value.abs() > MAX_EXACT_INTEGER {
+                return None;
+            }
+            *counts.entry(value as i64).or_insert(0) += 1;
+            if counts.len() > MAX_DISCRETE_VALUES {
+                return None;
+            }
+        }
+        let (values, frequencies) = counts.into_iter().unzip();
+        Some(Histogram::Discrete { values, frequencies })
+    }
+
+    /// Removes values holding at least [`POINT_MASS_MIN_SHARE`] of the numeric samples,
+    /// leaving the continuous remainder to bin and fit. Nothing is split off unless
+    /// some remainder is left.
+    fn split_point_masses(&mut self) -> Vec<PointMass> {
+        let n = self.numeric_samples.len();
+        let mut counts: HashMap<u64, usize> = HashMap::new();
+        for &value in &self.numeric_samples {
+            // -0.0 and 0.0 are one value
+            *counts.entry((value + 0.0).to_bits()).or_insert(0) += 1;
+        }
+
+        let mut masses: Vec<PointMass> = counts
+            .into_iter()
+            .filter(|(_, count)| *count as f64 >= POINT_MASS_MIN_SHARE * n as f64)
+            .map(|(bits, count)| PointMass { value: f64::from_bits(bits), share: count as f64 / n as f64 })
+            .collect();
+        let remainder: Vec<f64> = self
+            .numeric_samples
+            .iter()
+            .copied()
+            .filter(|v| !masses.iter().any(|m| m.value == *v))
+            .collect();
+        if masses.is_empty() || remainder.len() < MIN_CONTINUOUS_SAMPLES {
+            return Vec::new();
+        }
+
+        masses.sort_by(|a, b| a.value.total_cmp(&b.value));
+        self.numeric_samples = remainder;
+        masses
+    }
+
+    /// Clamps the numeric samples to their `tail` and `1 - tail` quantiles. `None`
+    /// when nothing lies outside them.
+    fn winsorize_samples(&mut self, tail: f64) -> Option<Outliers> {
+        let sorted = self.sorted_samples();
+        let lower = sample_quantile(&sorted, tail)?;
+        let upper = sample_quantile(&sorted, 1.0 - tail)?;
+        if lower >= upper {
+            return None;
+        }
+
+        let below = sorted.partition_point(|&v| v < lower);
+        let above = sorted.len() - sorted.partition_point(|&v| v <= upper);
+        if below + above == 0 {
+            return None;
+        }
+        for value in &mut self.numeric_samples {
+            *value = value.clamp(lower, upper);
+        }
+
+        let n = sorted.len() as f64;
+        Some(Outliers { lower, upper, below_share: below as f64 / n, above_share: above as f64 / n })
+    }
+
+    /// Histogram over the range of the samples: the clipping bounds of a winsorized
+    /// column, and without split-off point masses.
     fn build_numeric_histogram(&self) -> Histogram {
-        let (min, max) = match (self.min, self.max) {
+        let range = self.numeric_samples.iter().fold((None, None), |(min, max): (Option<f64>, Option<f64>), &v| {
+            (Some(min.map_or(v, |m| m.min(v))), Some(max.map_or(v, |m| m.max(v))))
+        });
+        let (min, max) = match range {
             (Some(min), Some(max)) if min < max => (min, max),
             (Some(val), Some(_)) => (val, val + 1.0), // Handle constant values
             _ => return Histogram::Numeric {
                 bins: vec![],
                 frequencies: vec![],
+                scale: BinScale::Linear,
             },
         };
 
-        let bin_count = NUMERIC_HISTOGRAM_BINS;
-        let bin_width = (max - min) / bin_count as f64;
+        let bins = match self.binning {
+            BinningStrategy::EqualWidth => equal_width_edges(min, max, self.bins),
+            BinningStrategy::Quantile => self.quantile_edges(min, max),
+            BinningStrategy::FreedmanDiaconis => equal_width_edges(min, max, self.freedman_diaconis_bins(min, max)),
+        };
+
+        let frequencies = self.count_into(&bins);
 
-        // Generate bin edges
-        let mut bins = Vec::with_capacity(bin_count + 1);
-        for i in 0..=bin_count {
-            bins.push(min + (i as f64 * bin_width));
+        if self.binning == BinningStrategy::EqualWidth && self.is_heavily_right_skewed(&frequencies) {
+            let bins = log_edges(min, max, self.bins);
+            let frequencies = self.count_into(&bins);
+            return Histogram::Numeric { bins, frequencies, scale: BinScale::Log };
         }
 
-        let mut frequencies = vec![0u64; bin_count];
+        Histogram::Numeric { bins, frequencies, scale: BinScale::Linear }
+    }
 
+    /// Sample counts per bin of `bins`.
+    fn count_into(&self, bins: &[f64]) -> Vec<u64> {
+        let bin_count = bins.len() - 1;
+        let mut frequencies = vec![0u64; bin_count];
         for &value in &self.numeric_samples {
-            let bin_idx = if value >= max {
-                bin_count - 1 // Edge case: assign max value to last bin
-            } else {
-                let idx = ((value - min) / bin_width) as usize;
-                idx.min(bin_count - 1)
-            };
-            frequencies[bin_idx] += 1;
+            // Bins are [edge, next edge); the max value goes to the last bin
+            let bin_idx = bins.partition_point(|&edge| edge <= value).saturating_sub(1);
+            frequencies[bin_idx.min(bin_count - 1)] += 1;
+        }
+        frequencies
+    }
+
+    /// Most samples crowd into one equal-width bin under a long right tail (amounts,
+    /// latencies), so linear bins would keep almost no detail.
+    fn is_heavily_right_skewed(&self, frequencies: &[u64]) -> bool {
+        let n = self.numeric_samples.len() as f64;
+        if self.numeric_samples.len() < LOG_SCALE_MIN_SAMPLES {
+            return false;
+        }
+        let fullest = frequencies.iter().max().copied().unwrap_or(0) as f64;
+        if fullest / n <= LOG_SCALE_MIN_BIN_SHARE {
+            return false;
         }
 
-        Histogram::Numeric { bins, frequencies }
+        let mean = self.numeric_samples.iter().sum::<f64>() / n;
+        let (m2, m3) = self.numeric_samples.iter().fold((0.0, 0.0), |(m2, m3), &x| {
+            let d = x - mean;
+            (m2 + d * d / n, m3 + d * d * d / n)
+        });
+        m2 > 0.0 && m3 / m2.powf(1.5) > LOG_SCALE_MIN_SKEWNESS
+    }
+
+    fn sorted_samples(&self) -> Vec<f64> {
+        let mut sorted = self.numeric_samples.clone();
+        sorted.sort_by(f64::total_cmp);
+        sorted
+    }
+
+    /// Edges at evenly spaced sample quantiles. Repeated values can merge edges, so
+    /// heavily tied columns get fewer bins.
+    fn quantile_edges(&self, min: f64, max: f64) -> Vec<f64> {
+        let sorted = self.sorted_samples();
+        let last = sorted.len() - 1;
+        let mut edges: Vec<f64> = (0..=self.bins)
+            .map(|i| sorted[(i * last + self.bins / 2) / self.bins])
+            .collect();
+        edges[0] = min;
+        edges[self.bins] = max;
+        edges.dedup();
+        if edges.len() < 2 {
+            return equal_width_edges(min, max, 1);
+        }
+        edges
+    }
+
+    /// Freedman-Diaconis bin count, capped at `bins`; `bins` when the IQR is zero.
+    fn freedman_diaconis_bins(&self, min: f64, max: f64) -> usize {
+        let sorted = self.sorted_samples();
+        let at = |q: f64| sorted[((sorted.len() - 1) as f64 * q).round() as usize];
+        let iqr = at(0.75) - at(0.25);
+        if iqr <= 0.0 {
+            return self.bins;
+        }
+        let width = 2.0 * iqr / (sorted.len() as f64).cbrt();
+        (((max - min) / width).ceil() as usize).clamp(1, self.bins)
     }
 
     fn build_categorical_histogram(&self) -> Histogram {
-        let mut frequencies: HashMap<String, u64> = HashMap::new();
+        let mut frequencies: BTreeMap<String, u64> = BTreeMap::new();
 
         for value in &self.categorical_samples {
             *frequencies.entry(value.clone()).or_insert(0) += 1;
@@ -224,14 +1090,50 @@ impl DistributionBuilder {
         Histogram::Categorical {
             frequencies,
             truncated,
+            long_tail: None,
         }
     }
 }
 
+/// Nearest-rank `q`-quantile of `sorted`; `None` when it is empty.
+fn sample_quantile(sorted: &[f64], q: f64) -> Option<f64> {
+    let last = sorted.len().checked_sub(1)?;
+    sorted.get((last as f64 * q).round() as usize).copied()
+}
+
+/// `count + 1` edges from `min` to `max`, evenly spaced in ln(1 + x - min).
+fn log_edges(min: f64, max: f64, count: usize) -> Vec<f64> {
+    let span = (max - min).ln_1p();
+    let mut edges: Vec<f64> = (0..=count).map(|i| min + (span * i as f64 / count as f64).exp_m1()).collect();
+    edges[count] = max;
+    edges
+}
+
+/// `count + 1` evenly spaced edges from `min` to `max`.
+fn equal_width_edges(min: f64, max: f64, count: usize) -> Vec<f64> {
+    let width = (max - min) / count as f64;
+    (0..=count).map(|i| min + i as f64 * width).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_bernoulli_sampling() {
+        let mut bernoulli = Bernoulli::default();
+        for i in 0..100 {
+            bernoulli.add(i % 4 == 0);
+        }
+        assert_eq!(bernoulli.p_true(), Some(0.25));
+
+        let mut rng = rand::thread_rng();
+        let trues = (0..10_000).filter(|_| bernoulli.sample(&mut rng)).count();
+        assert!((2_200..2_800).contains(&trues), "trues {}", trues);
+
+        assert!(!Bernoulli::default().sample(&mut rng));
+    }
+
     #[test]
     fn test_reservoir_fill_phase() {
         let mut reservoir = Reservoir::new(5);
@@ -256,6 +1158,32 @@ mod tests {
         assert_eq!(reservoir.total_seen(), 100);
     }
 
+    #[test]
+    fn test_weighted_reservoir_prefers_heavy_items() {
+        let mut reservoir = Reservoir::weighted(100);
+        assert!(reservoir.is_weighted());
+
+        // The second half weighs e^3 (about 20) times as much as the first
+        for i in 0..10_000 {
+            reservoir.set_log_weight(if i < 5_000 { 0.0 } else { 3.0 });
+            reservoir.add(i);
+        }
+
+        assert_eq!(reservoir.sample_size(), 100);
+        assert_eq!(reservoir.total_seen(), 10_000);
+        let recent = reservoir.sample().iter().filter(|&&i| i >= 5_000).count();
+        assert!(recent > 85, "recent {}", recent);
+
+        // Equal weights sample uniformly
+        let mut reservoir = Reservoir::weighted(1_000);
+        for i in 0..10_000 {
+            reservoir.set_log_weight(1_000.0);
+            reservoir.add(i);
+        }
+        let recent = reservoir.sample().iter().filter(|&&i| i >= 5_000).count();
+        assert!((400..600).contains(&recent), "recent {}", recent);
+    }
+
     #[test]
     fn test_distribution_builder_numeric() {
         let mut builder = DistributionBuilder::new(100, 5);
@@ -272,7 +1200,7 @@ mod tests {
         assert_eq!(dist.total_count, 100);
 
         match dist.histogram {
-            Histogram::Numeric { bins, frequencies } => {
+            Histogram::Numeric { bins, frequencies, .. } => {
                 assert_eq!(bins.len(), NUMERIC_HISTOGRAM_BINS + 1);
                 assert_eq!(frequencies.len(), NUMERIC_HISTOGRAM_BINS);
             }
@@ -280,6 +1208,181 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_binning_strategies() {
+        // Exponential-like skew: most samples near zero, a long right tail
+        let samples: Vec<f64> = (1..=1000).map(|i| (1000.0 / i as f64).ln() * 10.0).collect();
+        let histogram = |strategy: BinningStrategy, bins: usize| {
+            let mut builder = DistributionBuilder::new(1000, 0).with_binning(strategy, bins);
+            samples.iter().for_each(|&v| builder.add_numeric(v));
+            match builder.build().histogram {
+                Histogram::Numeric { bins, frequencies, .. } => (bins, frequencies),
+                _ => panic!("Expected numeric histogram"),
+            }
+        };
+
+        let (edges, frequencies) = histogram(BinningStrategy::EqualWidth, 20);
+        assert_eq!(edges.len(), 21);
+        assert_eq!(frequencies.iter().sum::<u64>(), 1000);
+
+        let (edges, frequencies) = histogram(BinningStrategy::Quantile, 10);
+        assert_eq!(edges.len(), 11);
+        assert!(frequencies.iter().all(|&f| (99..=101).contains(&f)), "{:?}", frequencies);
+        assert!(edges.windows(2).all(|w| w[0] < w[1]));
+
+        // 2 IQR / cbrt(1000) over a range of about 69 gives 32 bins, capped by `bins`
+        let (edges, _) = histogram(BinningStrategy::FreedmanDiaconis, 100);
+        assert_eq!(edges.len(), 33);
+        let (edges, _) = histogram(BinningStrategy::FreedmanDiaconis, 16);
+        assert_eq!(edges.len(), 17);
+
+        assert_eq!("Equal-Frequency".parse::<BinningStrategy>().unwrap(), BinningStrategy::Quantile);
+        assert_eq!("fd".parse::<BinningStrategy>().unwrap().to_string(), "freedman-diaconis");
+        assert!("sturges".parse::<BinningStrategy>().is_err());
+    }
+
+    #[test]
+    fn test_log_scale_for_skewed_columns() {
+        let build = |samples: &[f64]| {
+            let mut builder = DistributionBuilder::new(1000, 0);
+            samples.iter().for_each(|&v| builder.add_numeric(v));
+            builder.build()
+        };
+
+        // Exponential-like amounts: most are small, a few are huge
+        let amounts: Vec<f64> = (1..1000).map(|i| -(1.0 - i as f64 / 1000.0).ln() * 20.0).chain([50_000.0]).collect();
+        let dist = build(&amounts);
+        let Histogram::Numeric { bins, frequencies, scale } = &dist.histogram else {
+            panic!("Expected numeric histogram");
+        };
+        assert_eq!(*scale, BinScale::Log);
+        assert_eq!(bins.len(), NUMERIC_HISTOGRAM_BINS + 1);
+        assert_eq!(*bins.last().unwrap(), 50_000.0);
+        // The bulk of the data is spread over many bins instead of one
+        assert!(frequencies.iter().filter(|&&f| f > 0).count() > 30);
+
+        let median = dist.quantile(0.5).unwrap();
+        assert!((median - 20.0 * 2f64.ln()).abs() < 2.0, "median {}", median);
+        assert!((dist.cdf(median).unwrap() - 0.5).abs() < 1e-9);
+
+        let uniform: Vec<f64> = (0..1000).map(|i| i as f64).collect();
+        let Histogram::Numeric { scale, .. } = build(&uniform).histogram else {
+            panic!("Expected numeric histogram");
+        };
+        assert_eq!(scale, BinScale::Linear);
+    }
+
+    #[test]
+    fn test_winsorization_keeps_true_range() {
+        let mut builder = DistributionBuilder::new(1001, 0).with_winsorization(Some(0.001));
+        (0..1000).for_each(|i| builder.add_numeric(i as f64));
+        builder.add_numeric(1e6);
+        let dist = builder.build();
+
+        assert_eq!((dist.min, dist.max), (Some(0.0), Some(1e6)));
+        assert!(dist.non_negative);
+        let outliers = dist.outliers.unwrap();
+        assert_eq!((outliers.lower, outliers.upper), (1.0, 999.0));
+        assert!((outliers.below_share - 1.0 / 1001.0).abs() < 1e-12);
+        assert!((outliers.above_share - 1.0 / 1001.0).abs() < 1e-12);
+
+        let Histogram::Numeric { bins, frequencies, scale } = &dist.histogram else {
+            panic!("Expected numeric histogram");
+        };
+        assert_eq!((bins[0], *bins.last().unwrap()), (1.0, 999.0));
+        assert_eq!(*scale, BinScale::Linear);
+        assert_eq!(frequencies.iter().sum::<u64>(), 1001);
+
+        // Nothing to clip
+        let mut builder = DistributionBuilder::new(10, 0).with_winsorization(Some(0.001));
+        (0..10).for_each(|i| builder.add_numeric(i as f64));
+        assert!(builder.build().outliers.is_none());
+    }
+
+    #[test]
+    fn test_discrete_integers() {
+        let build = |samples: &[f64], discrete: bool| {
+            let mut builder = DistributionBuilder::new(samples.len() as u64, 0)
+                .with_discrete_integers(discrete)
+                .with_parametric_fit(true);
+            samples.iter().for_each(|&v| builder.add_numeric(v));
+            builder.build()
+        };
+
+        // Ratings 1-5
+        let ratings: Vec<f64> = (0..100).map(|i| [1.0, 2.0, 3.0, 4.0, 4.0, 5.0, 5.0, 5.0, 5.0, 5.0][i % 10]).collect();
+        let dist = build(&ratings, true);
+        let Histogram::Discrete { values, frequencies } = &dist.histogram else {
+            panic!("Expected discrete histogram, got {:?}", dist.histogram);
+        };
+        assert_eq!(values, &vec![1, 2, 3, 4, 5]);
+        assert_eq!(frequencies, &vec![10, 10, 10, 20, 50]);
+        assert!(dist.fit.is_none());
+        assert_eq!(dist.quantile(0.5), Some(4.0));
+        assert_eq!(dist.quantile(0.51), Some(5.0));
+        assert_eq!(dist.cdf(3.5), Some(0.3));
+        assert!((dist.mean().unwrap() - 3.9).abs() < 1e-12);
+
+        // Continuous when disabled, fractional, or too many distinct values
+        assert!(matches!(build(&ratings, false).histogram, Histogram::Numeric { .. }));
+        assert!(matches!(build(&[1.0, 2.5, 3.0], true).histogram, Histogram::Numeric { .. }));
+        let ids: Vec<f64> = (0..=MAX_DISCRETE_VALUES).map(|i| i as f64).collect();
+        assert!(matches!(build(&ids, true).histogram, Histogram::Numeric { .. }));
+    }
+
+    #[test]
+    fn test_point_masses_split_off() {
+        // Discounts: zero on 90% of rows, otherwise spread over 5-25
+        let discounts: Vec<f64> = (0..1000).map(|i| if i % 10 == 0 { 5.0 + (i / 10) as f64 * 0.2 } else { 0.0 }).collect();
+        let mut builder = DistributionBuilder::new(1000, 0).with_point_masses(true);
+        discounts.iter().for_each(|&v| builder.add_numeric(v));
+        let dist = builder.build();
+
+        assert_eq!(dist.point_masses, vec![PointMass { value: 0.0, share: 0.9 }]);
+        assert_eq!(dist.min, Some(0.0));
+        let Histogram::Numeric { bins, frequencies, .. } = &dist.histogram else {
+            panic!("Expected numeric histogram");
+        };
+        assert_eq!(bins[0], 5.0);
+        assert_eq!(frequencies.iter().sum::<u64>(), 100);
+
+        assert!((dist.cdf(0.0).unwrap() - 0.9).abs() < 1e-12);
+        assert!((dist.cdf(4.9).unwrap() - 0.9).abs() < 1e-12);
+        assert_eq!(dist.quantile(0.5), Some(0.0));
+        assert!(dist.quantile(0.95).unwrap() > 5.0);
+        assert!((dist.mean().unwrap() - discounts.iter().sum::<f64>() / 1000.0).abs() < 0.1);
+        assert_eq!(dist.numeric_support().unwrap().1, 1000);
+
+        // Below the share threshold nothing is split off
+        let mut builder = DistributionBuilder::new(100, 0).with_point_masses(true);
+        (0..100).for_each(|i| builder.add_numeric(i as f64 * 1.5));
+        assert!(builder.build().point_masses.is_empty());
+    }
+
+    #[test]
+    fn test_numeric_summary_statistics() {
+        let dist = Distribution::new(
+            Some(0.0),
+            Some(100.0),
+            10,
+            110,
+            100,
+            Histogram::Numeric {
+                bins: vec![0.0, 50.0, 100.0],
+                frequencies: vec![50, 50],
+                scale: BinScale::Linear,
+            },
+        );
+
+        assert_eq!(dist.mean(), Some(50.0));
+        assert_eq!(dist.quantile(0.5), Some(50.0));
+        assert_eq!(dist.quantile(0.75), Some(75.0));
+        assert_eq!(dist.cdf(25.0), Some(0.25));
+        assert_eq!(dist.cdf(-1.0), Some(0.0));
+        assert_eq!(dist.cdf(150.0), Some(1.0));
+        assert!((dist.null_rate() - 10.0 / 110.0).abs() < 1e-12);
+    }
+
     #[test]
     fn test_distribution_builder_categorical() {
         let mut builder = DistributionBuilder::new(50, 2);
@@ -293,7 +1396,7 @@ mod tests {
         assert_eq!(dist.unique_count, 2);
 
         match dist.histogram {
-            Histogram::Categorical { frequencies, truncated } => {
+            Histogram::Categorical { frequencies, truncated, .. } => {
                 assert_eq!(frequencies.get("apple"), Some(&2));
                 assert_eq!(frequencies.get("banana"), Some(&1));
                 assert!(!truncated);
@@ -301,4 +1404,21 @@ mod tests {
             _ => panic!("Expected categorical histogram"),
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_distribution_builder_profiles_lengths() {
+        let mut builder = DistributionBuilder::new(3, 0);
+        builder.add_categorical("ab".to_string());
+        builder.add_categorical("cd".to_string());
+        builder.add_categorical("héllo".to_string());
+
+        let lengths = builder.build().lengths.expect("text lengths");
+        assert_eq!(lengths.counts.get(&2), Some(&2));
+        assert_eq!(lengths.counts.get(&5), Some(&1));
+        assert!((lengths.mean().unwrap() - 3.0).abs() < 1e-9);
+
+        let mut numeric = DistributionBuilder::new(1, 0);
+        numeric.add_numeric(1.0);
+        assert!(numeric.build().lengths.is_none());
+    }
+}
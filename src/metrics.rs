@@ -0,0 +1,441 @@
+//! Run metrics for pipelines.
+//!
+//! A [`RunMetrics`] collects per-table row counts, durations and reservoir fill
+//! ratios while a scan or generation runs, and renders them in the Prometheus text
+//! exposition format at the end, ready for a node_exporter textfile collector or a
+//! Pushgateway. It also keeps the run's warnings and the files it wrote, which the
+//! JSON [`RunSummary`] reports for orchestration tools to assert on.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use crate::synth::GenerationProgress;
+
+/// Shape of the log lines written to stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// Human-readable lines.
+    #[default]
+    Text,
+    /// One JSON object per event, for log shippers.
+    Json,
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogFormat::Text => write!(f, "text"),
+            LogFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => bail!("Unknown log format '{}' (expected text or json)", other),
+        }
+    }
+}
+
+/// Part of a run a table metric belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    Scan,
+    Gen,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Phase::Scan => write!(f, "scan"),
+            Phase::Gen => write!(f, "gen"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableMetrics {
+    /// Rows read (scan) or written (gen).
+    pub rows: u64,
+
+    pub duration: Duration,
+
+    /// Mean share of the column reservoirs' capacity that was filled; scan only.
+    pub reservoir_fill: Option<f64>,
+}
+
+/// Thread-safe collector shared by every table task of a run.
+#[derive(Debug, Default)]
+pub struct RunMetrics {
+    tables: Mutex<BTreeMap<(Phase, String), TableMetrics>>,
+
+    /// First start and last finish seen per phase.
+    spans: Mutex<HashMap<Phase, (Instant, Instant)>>,
+
+    /// Tables being generated: start time and rows so far.
+    generating: Mutex<HashMap<String, (Instant, u64)>>,
+
+    /// Warnings logged during the run, in order.
+    warnings: Mutex<Vec<String>>,
+
+    /// Files the run wrote, in order.
+    artifacts: Mutex<Vec<PathBuf>>,
+}
+
+impl RunMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a finished table that started at `started`.
+    pub fn record_table(&self, phase: Phase, table: &str, started: Instant, metrics: TableMetrics) {
+        let finished = started + metrics.duration;
+        if let Ok(mut spans) = self.spans.lock() {
+            let span = spans.entry(phase).or_insert((started, finished));
+            span.0 = span.0.min(started);
+            span.1 = span.1.max(finished);
+        }
+        if let Ok(mut tables) = self.tables.lock() {
+            tables.insert((phase, table.to_string()), metrics);
+        }
+    }
+
+    pub fn record_warning(&self, warning: String) {
+        if let Ok(mut warnings) = self.warnings.lock() {
+            warnings.push(warning);
+        }
+    }
+
+    /// Records a file the run wrote, such as a genome or a SQL file.
+    pub fn record_artifact(&self, path: impl Into<PathBuf>) {
+        if let Ok(mut artifacts) = self.artifacts.lock() {
+            artifacts.push(path.into());
+        }
+    }
+
+    /// Total rows and wall-clock time of `phase`, from its first table's start to its
+    /// last table's finish. `None` if no table of that phase was recorded.
+    pub fn phase_summary(&self, phase: Phase) -> Option<(u64, Duration)> {
+        let (start, end) = *self.spans.lock().ok()?.get(&phase)?;
+        let rows = self
+            .tables
+            .lock()
+            .ok()?
+            .iter()
+            .filter(|((p, _), _)| *p == phase)
+            .map(|(_, m)| m.rows)
+            .sum();
+        Some((rows, end - start))
+    }
+
+    /// Prometheus text exposition of everything recorded so far.
+    pub fn render_prometheus(&self) -> String {
+        let mut out = String::new();
+        let tables = match self.tables.lock() {
+            Ok(tables) => tables.clone(),
+            Err(_) => return out,
+        };
+
+        let phases: Vec<Phase> = [Phase::Scan, Phase::Gen]
+            .into_iter()
+            .filter_map(|phase| self.phase_summary(phase).map(|_| phase))
+            .collect();
+
+        write_header(&mut out, "replica_db_rows_total", "counter", "Rows scanned or generated.");
+        for &phase in &phases {
+            if let Some((rows, _)) = self.phase_summary(phase) {
+                let _ = writeln!(out, "replica_db_rows_total{{phase=\"{}\"}} {}", phase, rows);
+            }
+        }
+
+        write_header(&mut out, "replica_db_phase_duration_seconds", "gauge", "Wall-clock time of the phase.");
+        for &phase in &phases {
+            if let Some((_, duration)) = self.phase_summary(phase) {
+                let _ = writeln!(
+                    out,
+                    "replica_db_phase_duration_seconds{{phase=\"{}\"}} {}",
+                    phase,
+                    duration.as_secs_f64()
+                );
+            }
+        }
+
+        write_header(&mut out, "replica_db_rows_per_second", "gauge", "Rows per second over the phase.");
+        for &phase in &phases {
+            if let Some((rows, duration)) = self.phase_summary(phase) {
+                let _ = writeln!(
+                    out,
+                    "replica_db_rows_per_second{{phase=\"{}\"}} {}",
+                    phase,
+                    rate(rows, duration)
+                );
+            }
+        }
+
+        write_header(&mut out, "replica_db_table_rows", "gauge", "Rows scanned or generated per table.");
+        for ((phase, table), metrics) in &tables {
+            let _ = writeln!(out, "replica_db_table_rows{} {}", table_labels(*phase, table), metrics.rows);
+        }
+
+        write_header(&mut out, "replica_db_table_duration_seconds", "gauge", "Time spent on each table.");
+        for ((phase, table), metrics) in &tables {
+            let _ = writeln!(
+                out,
+                "replica_db_table_duration_seconds{} {}",
+                table_labels(*phase, table),
+                metrics.duration.as_secs_f64()
+            );
+        }
+
+        write_header(
+            &mut out,
+            "replica_db_reservoir_fill_ratio",
+            "gauge",
+            "Mean share of the column reservoirs' capacity filled by the scan.",
+        );
+        for ((phase, table), metrics) in &tables {
+            if let Some(fill) = metrics.reservoir_fill {
+                let _ = writeln!(out, "replica_db_reservoir_fill_ratio{} {}", table_labels(*phase, table), fill);
+            }
+        }
+
+        out
+    }
+
+    /// Writes [`RunMetrics::render_prometheus`] to `path`, through a temporary file so
+    /// a collector never reads a half-written file.
+    pub fn write_to_file(&self, path: &Path) -> Result<()> {
+        let temp = path.with_extension("tmp");
+        std::fs::write(&temp, self.render_prometheus())
+            .context(format!("Failed to write metrics to {}", temp.display()))?;
+        std::fs::rename(&temp, path).context(format!("Failed to move metrics into {}", path.display()))?;
+        Ok(())
+    }
+}
+
+/// Final report of a run for `--summary-json`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunSummary {
+    /// Subcommand that ran, e.g. `scan` or `gen`.
+    pub command: String,
+    pub success: bool,
+    /// Error chain of a failed run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub phases: BTreeMap<Phase, PhaseSummary>,
+    pub tables: Vec<TableSummary>,
+    pub warnings: Vec<String>,
+    pub artifacts: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PhaseSummary {
+    pub rows: u64,
+    pub duration_secs: f64,
+    pub rows_per_second: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TableSummary {
+    pub phase: Phase,
+    pub table: String,
+    pub rows: u64,
+    pub duration_secs: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reservoir_fill: Option<f64>,
+}
+
+impl RunMetrics {
+    /// Summary of everything recorded so far, for a run of `command` that failed with
+    /// `error`, if it did.
+    pub fn summary(&self, command: &str, error: Option<&anyhow::Error>) -> RunSummary {
+        let phases = [Phase::Scan, Phase::Gen]
+            .into_iter()
+            .filter_map(|phase| {
+                let (rows, duration) = self.phase_summary(phase)?;
+                Some((phase, PhaseSummary {
+                    rows,
+                    duration_secs: duration.as_secs_f64(),
+                    rows_per_second: rate(rows, duration),
+                }))
+            })
+            .collect();
+        let tables = self
+            .tables
+            .lock()
+            .map(|tables| {
+                tables
+                    .iter()
+                    .map(|((phase, table), metrics)| TableSummary {
+                        phase: *phase,
+                        table: table.clone(),
+                        rows: metrics.rows,
+                        duration_secs: metrics.duration.as_secs_f64(),
+                        reservoir_fill: metrics.reservoir_fill,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        RunSummary {
+            command: command.to_string(),
+            success: error.is_none(),
+            error: error.map(|e| format!("{:#}", e)),
+            phases,
+            tables,
+            warnings: self.warnings.lock().map(|w| w.clone()).unwrap_or_default(),
+            artifacts: self.artifacts.lock().map(|a| a.clone()).unwrap_or_default(),
+        }
+    }
+}
+
+impl RunSummary {
+    /// Writes the summary as pretty JSON to `path`, through a temporary file like
+    /// [`RunMetrics::write_to_file`].
+    pub fn write_to_file(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize run summary")?;
+        let temp = path.with_extension("tmp");
+        std::fs::write(&temp, json + "\n")
+            .context(format!("Failed to write run summary to {}", temp.display()))?;
+        std::fs::rename(&temp, path).context(format!("Failed to move run summary into {}", path.display()))?;
+        Ok(())
+    }
+}
+
+/// Records generation timings; combine with the progress bars through the
+/// `Vec<Arc<dyn GenerationProgress>>` implementation.
+impl GenerationProgress for RunMetrics {
+    fn table_started(&self, table: &str, _rows: usize) {
+        if let Ok(mut generating) = self.generating.lock() {
+            generating.insert(table.to_string(), (Instant::now(), 0));
+        }
+    }
+
+    fn rows_generated(&self, table: &str, rows: usize) {
+        if let Ok(mut generating) = self.generating.lock()
+            && let Some((_, done)) = generating.get_mut(table)
+        {
+            *done += rows as u64;
+        }
+    }
+
+    fn table_finished(&self, table: &str) {
+        let entry = self.generating.lock().ok().and_then(|mut g| g.remove(table));
+        if let Some((started, rows)) = entry {
+            let metrics = TableMetrics {
+                rows,
+                duration: started.elapsed(),
+                reservoir_fill: None,
+            };
+            self.record_table(Phase::Gen, table, started, metrics);
+        }
+    }
+}
+
+fn write_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn table_labels(phase: Phase, table: &str) -> String {
+    let escaped = table.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("{{phase=\"{}\",table=\"{}\"}}", phase, escaped)
+}
+
+fn rate(rows: u64, duration: Duration) -> f64 {
+    let seconds = duration.as_secs_f64();
+    if seconds > 0.0 { rows as f64 / seconds } else { 0.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_format_parse() {
+        assert_eq!("json".parse::<LogFormat>().unwrap(), LogFormat::Json);
+        assert_eq!(LogFormat::Text.to_string(), "text");
+        assert!("xml".parse::<LogFormat>().is_err());
+    }
+
+    #[test]
+    fn test_render_prometheus() {
+        let metrics = RunMetrics::new();
+        let start = Instant::now();
+        metrics.record_table(Phase::Scan, "users", start, TableMetrics {
+            rows: 500,
+            duration: Duration::from_secs(1),
+            reservoir_fill: Some(0.05),
+        });
+        metrics.record_table(Phase::Scan, "orders", start + Duration::from_secs(1), TableMetrics {
+            rows: 1500,
+            duration: Duration::from_secs(1),
+            reservoir_fill: Some(0.15),
+        });
+
+        assert_eq!(metrics.phase_summary(Phase::Scan), Some((2000, Duration::from_secs(2))));
+        assert_eq!(metrics.phase_summary(Phase::Gen), None);
+
+        let text = metrics.render_prometheus();
+        assert!(text.contains("# TYPE replica_db_rows_total counter\n"));
+        assert!(text.contains("replica_db_rows_total{phase=\"scan\"} 2000\n"));
+        assert!(text.contains("replica_db_rows_per_second{phase=\"scan\"} 1000\n"));
+        assert!(text.contains("replica_db_table_duration_seconds{phase=\"scan\",table=\"orders\"} 1\n"));
+        assert!(text.contains("replica_db_reservoir_fill_ratio{phase=\"scan\",table=\"users\"} 0.05\n"));
+        assert!(!text.contains("phase=\"gen\""));
+    }
+
+    #[test]
+    fn test_generation_progress_records_rows() {
+        let metrics = RunMetrics::new();
+        metrics.table_started("users", 30);
+        metrics.rows_generated("users", 10);
+        metrics.rows_generated("users", 20);
+        metrics.table_finished("users");
+
+        let (rows, _) = metrics.phase_summary(Phase::Gen).unwrap();
+        assert_eq!(rows, 30);
+        assert!(metrics.render_prometheus().contains("replica_db_table_rows{phase=\"gen\",table=\"users\"} 30\n"));
+    }
+
+    #[test]
+    fn test_run_summary() {
+        let metrics = RunMetrics::new();
+        metrics.record_table(Phase::Gen, "users", Instant::now(), TableMetrics {
+            rows: 100,
+            duration: Duration::from_secs(2),
+            reservoir_fill: None,
+        });
+        metrics.record_warning("Table is empty (table=refunds)".to_string());
+        metrics.record_artifact("out.sql");
+
+        let summary = metrics.summary("gen", None);
+        assert!(summary.success);
+        assert_eq!(summary.phases[&Phase::Gen].rows_per_second, 50.0);
+
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["command"], "gen");
+        assert_eq!(json["tables"][0]["phase"], "gen");
+        assert_eq!(json["tables"][0]["rows"], 100);
+        assert!(json["tables"][0].get("reservoir_fill").is_none());
+        assert_eq!(json["phases"]["gen"]["rows"], 100);
+        assert_eq!(json["warnings"][0], "Table is empty (table=refunds)");
+        assert_eq!(json["artifacts"][0], "out.sql");
+        assert!(json.get("error").is_none());
+
+        let failed = metrics.summary("gen", Some(&anyhow::anyhow!("disk full").context("Failed to write SQL output")));
+        assert!(!failed.success);
+        assert_eq!(failed.error.as_deref(), Some("Failed to write SQL output: disk full"));
+    }
+}
@@ -0,0 +1,178 @@
+//! Prefix profiling and address generation for `inet`, `cidr` and `macaddr` columns.
+//!
+//! The genome never stores whole addresses. Scanning reduces each value to a prefix
+//! (the /24 of an IPv4 address, the /48 of an IPv6 one, the vendor OUI of a MAC
+//! address) and keeps the frequencies of those prefixes; generation draws a prefix and
+//! fills in random host bits.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use anyhow::{bail, Context, Result};
+use rand::Rng;
+use crate::schema::DataType;
+
+/// Prefix length IPv4 addresses are bucketed by.
+pub const IPV4_PREFIX: u8 = 24;
+
+/// Prefix length IPv6 addresses are bucketed by.
+pub const IPV6_PREFIX: u8 = 48;
+
+/// Prefixes of columns without profiled data: private ranges, and the OUIs of common
+/// virtual NICs.
+pub const PLACEHOLDER_NETWORKS: [&str; 3] = ["10.0.0.0/24", "192.168.1.0/24", "172.16.0.0/24"];
+pub const PLACEHOLDER_OUIS: [&str; 3] = ["52:54:00", "02:42:ac", "00:16:3e"];
+
+/// The prefix `value` is profiled as: its network at [`IPV4_PREFIX`] or
+/// [`IPV6_PREFIX`] bits (a `cidr` value keeps its own prefix when that is shorter),
+/// or the first three bytes of a MAC address. `None` for unparsable values and other
+/// types.
+pub fn prefix_bucket(data_type: &DataType, value: &str) -> Option<String> {
+    match data_type {
+        DataType::Inet | DataType::Cidr => {
+            let (address, length) = parse_network(value)?;
+            let bucket = match address {
+                IpAddr::V4(_) => IPV4_PREFIX,
+                IpAddr::V6(_) => IPV6_PREFIX,
+            };
+            // An inet's netmask describes its network, not the host; only cidr values are networks
+            let length = match data_type {
+                DataType::Cidr => length.min(bucket),
+                _ => bucket,
+            };
+            Some(format!("{}/{}", mask(address, length), length))
+        }
+        DataType::MacAddr => {
+            let bytes = parse_mac(value)?;
+            Some(format!("{:02x}:{:02x}:{:02x}", bytes[0], bytes[1], bytes[2]))
+        }
+        _ => None,
+    }
+}
+
+/// A value of `data_type` within `prefix`: a random host of the network for `inet`,
+/// the network itself for `cidr`, and random device bytes after the OUI for `macaddr`.
+pub fn address_in_prefix<R: Rng + ?Sized>(data_type: &DataType, prefix: &str, rng: &mut R) -> Result<String> {
+    match data_type {
+        DataType::Inet => {
+            let (network, length) = parse_network(prefix).context(format!("Invalid network prefix '{}'", prefix))?;
+            let address = match network {
+                IpAddr::V4(network) => {
+                    let host_bits = 32 - u32::from(length);
+                    let host_mask = u32::MAX.checked_shr(u32::from(length)).unwrap_or(0);
+                    // Skip the network and broadcast addresses where there are others
+                    let host = match host_bits {
+                        0 => 0,
+                        1 => rng.gen_range(0..=1),
+                        _ => rng.gen_range(1..host_mask),
+                    };
+                    IpAddr::V4(Ipv4Addr::from(u32::from(network) | host))
+                }
+                IpAddr::V6(network) => {
+                    let host_mask = u128::MAX.checked_shr(u32::from(length)).unwrap_or(0);
+                    IpAddr::V6(Ipv6Addr::from(u128::from(network) | (rng.r#gen::<u128>() & host_mask)))
+                }
+            };
+            Ok(address.to_string())
+        }
+        DataType::Cidr => {
+            let (network, length) = parse_network(prefix).context(format!("Invalid network prefix '{}'", prefix))?;
+            Ok(format!("{}/{}", mask(network, length), length))
+        }
+        DataType::MacAddr => {
+            let oui = prefix.split(':').map(|byte| u8::from_str_radix(byte, 16)).collect::<Result<Vec<u8>, _>>();
+            match oui {
+                Ok(oui) if oui.len() == 3 => {
+                    let device: [u8; 3] = rng.r#gen();
+                    Ok(oui
+                        .iter()
+                        .chain(&device)
+                        .map(|byte| format!("{:02x}", byte))
+                        .collect::<Vec<_>>()
+                        .join(":"))
+                }
+                _ => bail!("Invalid MAC address prefix '{}'", prefix),
+            }
+        }
+        other => bail!("{} columns have no address prefixes", other),
+    }
+}
+
+/// Address and prefix length of `addr[/len]`; without a length, the whole address.
+fn parse_network(value: &str) -> Option<(IpAddr, u8)> {
+    let (address, length) = match value.trim().split_once('/') {
+        Some((address, length)) => (address, Some(length)),
+        None => (value.trim(), None),
+    };
+    let address: IpAddr = address.parse().ok()?;
+    let max = match address {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    };
+    let length = match length {
+        Some(length) => length.parse::<u8>().ok().filter(|&l| l <= max)?,
+        None => max,
+    };
+    Some((address, length))
+}
+
+/// `address` with all but the first `length` bits cleared.
+fn mask(address: IpAddr, length: u8) -> IpAddr {
+    match address {
+        IpAddr::V4(address) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(length)).unwrap_or(0);
+            IpAddr::V4(Ipv4Addr::from(u32::from(address) & mask))
+        }
+        IpAddr::V6(address) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(length)).unwrap_or(0);
+            IpAddr::V6(Ipv6Addr::from(u128::from(address) & mask))
+        }
+    }
+}
+
+/// The six bytes of a MAC address in any of the forms Postgres accepts for output
+/// (`08:00:2b:01:02:03`, `08-00-2b-01-02-03`).
+fn parse_mac(value: &str) -> Option<[u8; 6]> {
+    let bytes: Vec<u8> = value
+        .trim()
+        .split([':', '-'])
+        .map(|byte| u8::from_str_radix(byte, 16).ok())
+        .collect::<Option<_>>()?;
+    bytes.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_prefix_bucket() {
+        assert_eq!(prefix_bucket(&DataType::Inet, "192.168.7.42").as_deref(), Some("192.168.7.0/24"));
+        assert_eq!(prefix_bucket(&DataType::Inet, "192.168.7.42/16").as_deref(), Some("192.168.7.0/24"));
+        assert_eq!(prefix_bucket(&DataType::Inet, "2001:db8:1:2::5").as_deref(), Some("2001:db8:1::/48"));
+        assert_eq!(prefix_bucket(&DataType::Cidr, "10.0.0.0/8").as_deref(), Some("10.0.0.0/8"));
+        assert_eq!(prefix_bucket(&DataType::Cidr, "10.1.2.128/25").as_deref(), Some("10.1.2.0/24"));
+        assert_eq!(prefix_bucket(&DataType::MacAddr, "08:00:2B:01:02:03").as_deref(), Some("08:00:2b"));
+        assert_eq!(prefix_bucket(&DataType::Inet, "not an address"), None);
+        assert_eq!(prefix_bucket(&DataType::Text, "10.0.0.1"), None);
+    }
+
+    #[test]
+    fn test_address_in_prefix() -> Result<()> {
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..100 {
+            let address: Ipv4Addr = address_in_prefix(&DataType::Inet, "192.168.7.0/24", &mut rng)?.parse()?;
+            assert_eq!(address.octets()[..3], [192, 168, 7]);
+            assert!(!matches!(address.octets()[3], 0 | 255));
+        }
+        let address: Ipv6Addr = address_in_prefix(&DataType::Inet, "2001:db8:1::/48", &mut rng)?.parse()?;
+        assert_eq!(address.segments()[..3], [0x2001, 0xdb8, 1]);
+
+        assert_eq!(address_in_prefix(&DataType::Cidr, "10.0.0.0/8", &mut rng)?, "10.0.0.0/8");
+        let mac = address_in_prefix(&DataType::MacAddr, "08:00:2b", &mut rng)?;
+        assert!(mac.starts_with("08:00:2b:") && parse_mac(&mac).is_some());
+
+        assert!(address_in_prefix(&DataType::Inet, "10.0.0.0/40", &mut rng).is_err());
+        Ok(())
+    }
+}
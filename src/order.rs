@@ -1,6 +1,8 @@
 //! Topological ordering of tables based on foreign keys dependencies.
 
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{self, Write};
+use std::str::FromStr;
 use crate::schema::Table;
 use anyhow::{bail, Result};
 use tracing::debug;
@@ -13,7 +15,39 @@ pub fn calculate_execution_order(tables: &[Table]) -> Result<Vec<String>> {
     }
 
     let graph = build_dependency_graph(tables);
-    let mut in_degree = calculate_in_degree(&graph, tables);
+    let execution_order = topological_order(tables, &graph);
+
+    if execution_order.len() != tables.len() {
+        let missing: Vec<_> = tables
+            .iter()
+            .filter(|t| !execution_order.contains(&t.name))
+            .map(|t| t.name.as_str())
+            .collect();
+
+        let cycle_info = detect_cycle(&graph, tables)?;
+
+        bail!(
+            "Circular dependency detected in foreign keys. \
+             Unable to process {} tables: [{}]. \
+             Cycle: {}",
+            missing.len(),
+            missing.join(", "),
+            cycle_info
+        );
+    }
+
+    debug!(
+        "Topological sort complete: {} tables ordered",
+        execution_order.len()
+    );
+
+    Ok(execution_order)
+}
+
+/// Kahn's algorithm over `graph`. Tables on or below a cycle, or referencing a table
+/// outside `tables`, are left out of the returned order.
+fn topological_order(tables: &[Table], graph: &HashMap<String, HashSet<String>>) -> Vec<String> {
+    let mut in_degree = calculate_in_degree(tables);
 
     let mut queue: VecDeque<String> = tables
         .iter()
@@ -40,45 +74,175 @@ pub fn calculate_execution_order(tables: &[Table]) -> Result<Vec<String>> {
         }
     }
 
-    if execution_order.len() != tables.len() {
-        let missing: Vec<_> = tables
+    execution_order
+}
+
+/// Groups `execution_order` into levels: every table's parents sit in earlier levels,
+/// so the tables of one level can be generated concurrently.
+pub fn execution_levels(tables: &[Table], execution_order: &[String]) -> Vec<Vec<String>> {
+    let mut level_of: HashMap<&str, usize> = HashMap::with_capacity(execution_order.len());
+    let mut levels: Vec<Vec<String>> = Vec::new();
+
+    for name in execution_order {
+        let level = tables
             .iter()
-            .filter(|t| !execution_order.contains(&t.name))
-            .map(|t| t.name.as_str())
-            .collect();
+            .find(|t| &t.name == name)
+            .into_iter()
+            .flat_map(|t| &t.foreign_keys)
+            .filter(|fk| &fk.target_table != name)
+            .filter_map(|fk| level_of.get(fk.target_table.as_str()).map(|l| l + 1))
+            .max()
+            .unwrap_or(0);
+
+        level_of.insert(name, level);
+        if levels.len() <= level {
+            levels.resize_with(level + 1, Vec::new);
+        }
+        levels[level].push(name.clone());
+    }
 
-        let cycle_info = detect_cycle(&graph, tables)?;
+    levels
+}
 
-        bail!(
-            "Circular dependency detected in foreign keys. \
-             Unable to process {} tables: [{}]. \
-             Cycle: {}",
-            missing.len(),
-            missing.join(", "),
-            cycle_info
-        );
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// Graphviz, e.g. `dot -Tsvg`.
+    Dot,
+    /// Mermaid flowchart, rendered inline by GitHub and most wikis.
+    Mermaid,
+}
+
+impl fmt::Display for GraphFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphFormat::Dot => write!(f, "dot"),
+            GraphFormat::Mermaid => write!(f, "mermaid"),
+        }
     }
+}
 
-    debug!(
-        "Topological sort complete: {} tables ordered",
-        execution_order.len()
-    );
+impl FromStr for GraphFormat {
+    type Err = anyhow::Error;
 
-    Ok(execution_order)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "dot" | "graphviz" => Ok(GraphFormat::Dot),
+            "mermaid" => Ok(GraphFormat::Mermaid),
+            other => bail!("Unknown graph format '{}' (expected dot or mermaid)", other),
+        }
+    }
+}
+
+/// Renders the foreign key dependency graph with an edge from every parent to each
+/// child referencing it, labelled with the foreign key column. Tables are listed in
+/// execution order. Tables that cannot be ordered are highlighted, and so are the
+/// edges of the cycle that [`calculate_execution_order`] would report. Parents
+/// missing from `tables` are drawn dashed.
+pub fn render_dependency_graph(tables: &[Table], format: GraphFormat) -> String {
+    let graph = build_dependency_graph(tables);
+    let ordered = topological_order(tables, &graph);
+
+    let mut nodes: Vec<&str> = ordered.iter().map(String::as_str).collect();
+    nodes.extend(tables.iter().map(|t| t.name.as_str()).filter(|name| !ordered.iter().any(|o| o == name)));
+    let blocked: HashSet<&str> = nodes[ordered.len()..].iter().copied().collect();
+    for fk in tables.iter().flat_map(|t| &t.foreign_keys) {
+        if !nodes.contains(&fk.target_table.as_str()) {
+            nodes.push(&fk.target_table);
+        }
+    }
+    let external: HashSet<&str> = nodes[tables.len()..].iter().copied().collect();
+
+    let cycle = find_cycle(&graph, tables).unwrap_or_default();
+    let cycle_edges: HashSet<(&str, &str)> = cycle.windows(2).map(|w| (w[0].as_str(), w[1].as_str())).collect();
+
+    // (parent, child, column), children in execution order
+    let edges: Vec<(&str, &str, &str)> = nodes
+        .iter()
+        .filter_map(|name| tables.iter().find(|t| t.name == *name))
+        .flat_map(|t| {
+            t.foreign_keys
+                .iter()
+                .map(move |fk| (fk.target_table.as_str(), t.name.as_str(), fk.source_col.as_str()))
+        })
+        .collect();
+
+    let mut out = String::new();
+    match format {
+        GraphFormat::Dot => {
+            let quote = |name: &str| format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""));
+            let _ = writeln!(out, "digraph dependencies {{");
+            let _ = writeln!(out, "    rankdir=LR;");
+            let _ = writeln!(out, "    node [shape=box];");
+            for node in &nodes {
+                let style = if external.contains(node) {
+                    " [style=dashed]"
+                } else if blocked.contains(node) {
+                    " [color=red, fontcolor=red]"
+                } else {
+                    ""
+                };
+                let _ = writeln!(out, "    {}{};", quote(node), style);
+            }
+            for (parent, child, column) in &edges {
+                let style = if cycle_edges.contains(&(*parent, *child)) { ", color=red, penwidth=2" } else { "" };
+                let _ = writeln!(out, "    {} -> {} [label={}{}];", quote(parent), quote(child), quote(column), style);
+            }
+            let _ = writeln!(out, "}}");
+        }
+        GraphFormat::Mermaid => {
+            let label = |name: &str| format!("\"{}\"", name.replace('"', "#quot;"));
+            let id = |name: &str| nodes.iter().position(|n| *n == name).unwrap_or(0);
+            let _ = writeln!(out, "flowchart LR");
+            for (i, node) in nodes.iter().enumerate() {
+                let _ = writeln!(out, "    n{}[{}]", i, label(node));
+            }
+            for (parent, child, column) in &edges {
+                let _ = writeln!(out, "    n{} -->|{}| n{}", id(parent), label(column), id(child));
+            }
+
+            let class_members = |set: &HashSet<&str>| {
+                nodes
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, n)| set.contains(*n))
+                    .map(|(i, _)| format!("n{}", i))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            };
+            if !blocked.is_empty() {
+                let _ = writeln!(out, "    classDef blocked stroke:#d33,stroke-width:2px,color:#d33");
+                let _ = writeln!(out, "    class {} blocked", class_members(&blocked));
+            }
+            if !external.is_empty() {
+                let _ = writeln!(out, "    classDef external stroke-dasharray:5 5");
+                let _ = writeln!(out, "    class {} external", class_members(&external));
+            }
+            let cycle_links: Vec<String> = edges
+                .iter()
+                .enumerate()
+                .filter(|(_, (parent, child, _))| cycle_edges.contains(&(*parent, *child)))
+                .map(|(i, _)| i.to_string())
+                .collect();
+            if !cycle_links.is_empty() {
+                let _ = writeln!(out, "    linkStyle {} stroke:#d33,stroke-width:2px", cycle_links.join(","));
+            }
+        }
+    }
+    out
 }
 
 fn build_dependency_graph(tables: &[Table]) -> HashMap<String, HashSet<String>> {
     let mut graph: HashMap<String, HashSet<String>> = HashMap::new();
 
     for table in tables {
-        graph.entry(table.name.clone()).or_insert_with(HashSet::new);
+        graph.entry(table.name.clone()).or_default();
     }
 
     for table in tables {
         for fk in &table.foreign_keys {
             graph
                 .entry(fk.target_table.clone())
-                .or_insert_with(HashSet::new)
+                .or_default()
                 .insert(table.name.clone());
         }
     }
@@ -86,10 +250,7 @@ fn build_dependency_graph(tables: &[Table]) -> HashMap<String, HashSet<String>>
     graph
 }
 
-fn calculate_in_degree(
-    graph: &HashMap<String, HashSet<String>>,
-    tables: &[Table],
-) -> HashMap<String, usize> {
+fn calculate_in_degree(tables: &[Table]) -> HashMap<String, usize> {
     let mut in_degree: HashMap<String, usize> = HashMap::new();
 
     // Initialize all tables with 0 in-degree
@@ -99,34 +260,41 @@ fn calculate_in_degree(
 
     // Count incoming edges for each table
     for table in tables {
-        for fk in &table.foreign_keys {
-            *in_degree.entry(table.name.clone()).or_insert(0) += 1;
-        }
+        *in_degree.entry(table.name.clone()).or_insert(0) += table.foreign_keys.len();
     }
 
     in_degree
 }
 
 fn detect_cycle(graph: &HashMap<String, HashSet<String>>, tables: &[Table]) -> Result<String> {
+    Ok(find_cycle(graph, tables)
+        .map(|cycle_path| cycle_path.join("->"))
+        .unwrap_or_else(|| "Unknown cycle".to_string()))
+}
+
+/// The first cycle found, as a path that starts and ends on the same table.
+fn find_cycle(graph: &HashMap<String, HashSet<String>>, tables: &[Table]) -> Option<Vec<String>> {
     let mut visited = HashSet::new();
     let mut rec_stack = HashSet::new();
     let mut path = Vec::new();
 
     for table in tables {
-        if !visited.contains(&table.name) {
-            if let Some(cycle_path) = dfs_cycle_detection(
-                &table.name,
-                graph,
-                &mut visited,
-                &mut rec_stack,
-                &mut path,
-            ) {
-                return Ok(cycle_path.join("->"));
-            }
+        if visited.contains(&table.name) {
+            continue;
+        }
+
+        if let Some(cycle_path) = dfs_cycle_detection(
+            &table.name,
+            graph,
+            &mut visited,
+            &mut rec_stack,
+            &mut path,
+        ) {
+            return Some(cycle_path);
         }
     }
 
-    Ok("Unknown cycle".to_string())
+    None
 }
 
 fn dfs_cycle_detection(
@@ -165,7 +333,7 @@ fn dfs_cycle_detection(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::schema::{Column, DataType, ForeignKey};
+    use crate::schema::ForeignKey;
 
     #[test]
     fn test_simple_linear_order() -> Result<()> {
@@ -303,6 +471,75 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_execution_levels() -> Result<()> {
+        let fk = |target: &str| ForeignKey::new(format!("{}_id", target), target.to_string(), "id".to_string());
+        let tables = vec![
+            Table::new("users".to_string(), vec![], vec![]),
+            Table::new("products".to_string(), vec![], vec![]),
+            Table::new("orders".to_string(), vec![], vec![fk("users")]),
+            Table::new("line_items".to_string(), vec![], vec![fk("orders"), fk("products")]),
+            Table::new("reviews".to_string(), vec![], vec![fk("products")]),
+        ];
+
+        let order = calculate_execution_order(&tables)?;
+        let mut levels = execution_levels(&tables, &order);
+        levels.iter_mut().for_each(|level| level.sort());
+
+        assert_eq!(
+            levels,
+            vec![
+                vec!["products".to_string(), "users".to_string()],
+                vec!["orders".to_string(), "reviews".to_string()],
+                vec!["line_items".to_string()],
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_dot() {
+        let fk = |target: &str| ForeignKey::new(format!("{}_id", target), target.to_string(), "id".to_string());
+        let tables = vec![
+            Table::new("orders".to_string(), vec![], vec![fk("users")]),
+            Table::new("users".to_string(), vec![], vec![]),
+        ];
+
+        assert_eq!(
+            render_dependency_graph(&tables, GraphFormat::Dot),
+            "digraph dependencies {\n    rankdir=LR;\n    node [shape=box];\n    \"users\";\n    \"orders\";\n    \
+             \"users\" -> \"orders\" [label=\"users_id\"];\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_render_highlights_cycle() {
+        let fk = |target: &str| ForeignKey::new(format!("{}_id", target), target.to_string(), "id".to_string());
+        let tables = vec![
+            Table::new("users".to_string(), vec![], vec![]),
+            Table::new("table_a".to_string(), vec![], vec![fk("users"), fk("table_b")]),
+            Table::new("table_b".to_string(), vec![], vec![fk("table_a")]),
+            Table::new("audit".to_string(), vec![], vec![fk("archive")]),
+        ];
+
+        let dot = render_dependency_graph(&tables, GraphFormat::Dot);
+        assert!(dot.contains("\"table_a\" [color=red, fontcolor=red];"));
+        assert!(dot.contains("\"audit\" [color=red, fontcolor=red];"));
+        assert!(dot.contains("\"archive\" [style=dashed];"));
+        assert!(dot.contains("\"table_b\" -> \"table_a\" [label=\"table_b_id\", color=red, penwidth=2];"));
+        assert!(dot.contains("\"table_a\" -> \"table_b\" [label=\"table_a_id\", color=red, penwidth=2];"));
+        assert!(dot.contains("\"users\" -> \"table_a\" [label=\"users_id\"];"));
+
+        let mermaid = render_dependency_graph(&tables, GraphFormat::Mermaid);
+        assert_eq!(
+            mermaid,
+            "flowchart LR\n    n0[\"users\"]\n    n1[\"table_a\"]\n    n2[\"table_b\"]\n    n3[\"audit\"]\n    n4[\"archive\"]\n    \
+             n0 -->|\"users_id\"| n1\n    n2 -->|\"table_b_id\"| n1\n    n1 -->|\"table_a_id\"| n2\n    n4 -->|\"archive_id\"| n3\n    \
+             classDef blocked stroke:#d33,stroke-width:2px,color:#d33\n    class n1,n2,n3 blocked\n    \
+             classDef external stroke-dasharray:5 5\n    class n4 external\n    linkStyle 1,2 stroke:#d33,stroke-width:2px\n"
+        );
+    }
+
     #[test]
     fn test_empty_tables() -> Result<()> {
         let tables: Vec<Table> = vec![];
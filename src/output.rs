@@ -0,0 +1,509 @@
+//! Writers that turn a [`GenerationResult`] into loadable SQL.
+
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use crate::schema::Table;
+use crate::synth::{GenerationResult, Synthesizer};
+
+/// Default capacity of the buffered writer wrapped around output files and stdout.
+pub const DEFAULT_WRITE_BUFFER: usize = 1 << 20;
+
+/// Statements wrapped around the COPY blocks to make loading into a live schema safer.
+#[derive(Debug, Clone, Default)]
+pub struct SqlOptions {
+    /// Wrap the output in `BEGIN` / `COMMIT`.
+    pub transaction: bool,
+
+    /// `SET session_replication_role = replica`, which skips triggers and foreign key
+    /// checks while loading. Needs superuser on the target.
+    pub disable_triggers: bool,
+
+    /// `SET CONSTRAINTS ALL DEFERRED`, checking deferrable constraints at commit.
+    /// Implies `transaction`.
+    pub defer_constraints: bool,
+
+    /// `TRUNCATE ... CASCADE` each table before its COPY block.
+    pub truncate: bool,
+}
+
+impl SqlOptions {
+    fn in_transaction(&self) -> bool {
+        self.transaction || self.defer_constraints
+    }
+
+    fn write_preamble<W: Write>(&self, writer: &mut W) -> Result<()> {
+        if self.in_transaction() {
+            writeln!(writer, "BEGIN;")?;
+        }
+        if self.disable_triggers {
+            // LOCAL reverts at COMMIT
+            let scope = if self.in_transaction() { "LOCAL " } else { "" };
+            writeln!(writer, "SET {}session_replication_role = replica;", scope)?;
+        }
+        if self.defer_constraints {
+            writeln!(writer, "SET CONSTRAINTS ALL DEFERRED;")?;
+        }
+        if self.in_transaction() || self.disable_triggers {
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    fn write_epilogue<W: Write>(&self, writer: &mut W) -> Result<()> {
+        if self.in_transaction() {
+            writeln!(writer, "COMMIT;")?;
+        } else if self.disable_triggers {
+            writeln!(writer, "SET session_replication_role = DEFAULT;")?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes every generated table as a Postgres `COPY ... FROM stdin` block, in
+/// execution order so parents are loaded before the children referencing them.
+/// The writer is flushed after each table so a reader sees whole blocks.
+pub fn write_copy_sql<W: Write>(
+    writer: &mut W,
+    synthesizer: &Synthesizer,
+    result: &GenerationResult,
+) -> Result<()> {
+    write_copy_sql_with_options(writer, synthesizer, result, &SqlOptions::default())
+}
+
+/// [`write_copy_sql`] with the statements chosen in `options` around the blocks.
+pub fn write_copy_sql_with_options<W: Write>(
+    writer: &mut W,
+    synthesizer: &Synthesizer,
+    result: &GenerationResult,
+    options: &SqlOptions,
+) -> Result<()> {
+    options.write_preamble(writer)?;
+    for table_name in synthesizer.execution_order() {
+        if result.get_table_data(table_name).is_some() {
+            write_table_copy(writer, synthesizer, result, table_name, options)?;
+            writer.flush().context("Failed to flush SQL output")?;
+        }
+    }
+    options.write_epilogue(writer)?;
+    writer.flush().context("Failed to flush SQL output")?;
+
+    Ok(())
+}
+
+/// Writes each generated table to its own file in `dir`, named with its position in
+/// the execution order (`001_users.sql`) so loading the files in name order respects
+/// foreign keys. Each file gets its own `options` preamble, so a transaction covers
+/// one table. Returns the paths written.
+pub fn write_copy_files(
+    dir: &Path,
+    synthesizer: &Synthesizer,
+    result: &GenerationResult,
+    options: &SqlOptions,
+    buffer_size: usize,
+) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir).context(format!("Failed to create output directory {}", dir.display()))?;
+
+    let mut paths = Vec::new();
+    let tables = synthesizer
+        .execution_order()
+        .iter()
+        .filter(|name| result.get_table_data(name).is_some());
+    for (index, table_name) in tables.enumerate() {
+        let path = dir.join(format!("{:03}_{}.sql", index + 1, table_name));
+        let file = File::create(&path).context(format!("Failed to create {}", path.display()))?;
+        let mut writer = BufWriter::with_capacity(buffer_size, file);
+        options.write_preamble(&mut writer)?;
+        write_table_copy(&mut writer, synthesizer, result, table_name, options)?;
+        options.write_epilogue(&mut writer)?;
+        writer.flush().context(format!("Failed to flush {}", path.display()))?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+/// One table's COPY block followed by its sequence fix-ups.
+fn write_table_copy<W: Write>(
+    writer: &mut W,
+    synthesizer: &Synthesizer,
+    result: &GenerationResult,
+    table_name: &str,
+    options: &SqlOptions,
+) -> Result<()> {
+    let table_data = result
+        .get_table_data(table_name)
+        .context(format!("No generated data for table '{}'", table_name))?;
+
+    let table = synthesizer
+        .genome()
+        .get_table(table_name)
+        .context(format!("Table '{}' not found in genome", table_name))?;
+
+    if options.truncate {
+        // Children come later in the execution order, so CASCADE only empties tables
+        // that are still to be loaded
+        writeln!(writer, "TRUNCATE {} CASCADE;", table.name)?;
+    }
+    writeln!(writer, "{};", copy_statement(synthesizer, table))?;
+
+    // Output data
+    writer.write_all(table_data.as_copy_data().as_bytes())?;
+
+    // End of data marker
+    writeln!(writer, "\\.")?;
+    writeln!(writer)?;
+
+    let setvals = sequence_fixups(table);
+    if !setvals.is_empty() {
+        for statement in setvals {
+            writeln!(writer, "{}", statement)?;
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+/// Writes one generated table as CSV: a header row with the COPY columns, then one
+/// record per row. NULLs become unquoted empty fields and empty strings `""`, which is
+/// what `COPY ... FROM ... WITH (FORMAT csv)` expects.
+pub fn write_table_csv<W: Write>(
+    writer: &mut W,
+    synthesizer: &Synthesizer,
+    result: &GenerationResult,
+    table_name: &str,
+) -> Result<()> {
+    let table_data = result
+        .get_table_data(table_name)
+        .context(format!("No generated data for table '{}'", table_name))?;
+
+    let table = synthesizer
+        .genome()
+        .get_table(table_name)
+        .context(format!("Table '{}' not found in genome", table_name))?;
+
+    let header: Vec<String> = synthesizer
+        .output_columns(table)
+        .iter()
+        .map(|c| csv_field(&c.name))
+        .collect();
+    writeln!(writer, "{}", header.join(","))?;
+
+    for line in table_data.as_copy_data().lines() {
+        let fields: Vec<String> = parse_copy_line(line)
+            .iter()
+            .map(|value| value.as_deref().map(csv_field).unwrap_or_default())
+            .collect();
+        writeln!(writer, "{}", fields.join(","))?;
+    }
+    writer.flush().context("Failed to flush CSV output")?;
+
+    Ok(())
+}
+
+/// Quotes `value` when CSV requires it, and always when empty so it reads back as an
+/// empty string rather than NULL.
+fn csv_field(value: &str) -> String {
+    if value.is_empty() || value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Splits one line of COPY text data into its values, `None` for NULL.
+pub fn parse_copy_line(line: &str) -> Vec<Option<String>> {
+    line.split('\t')
+        .map(|raw| (raw != "\\N").then(|| unescape_copy_text(raw)))
+        .collect()
+}
+
+/// Undoes the backslash escapes of Postgres' COPY text format.
+pub(crate) fn unescape_copy_text(raw: &str) -> String {
+    if !raw.contains('\\') {
+        return raw.to_string();
+    }
+
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Backslash-escapes `text` for Postgres' COPY text format.
+pub(crate) fn escape_copy_text(text: &str) -> Cow<'_, str> {
+    if !text.contains(['\\', '\t', '\n', '\r']) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut out = String::with_capacity(text.len() + 8);
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// `COPY table (columns) FROM stdin` for the columns the synthesizer writes.
+pub fn copy_statement(synthesizer: &Synthesizer, table: &Table) -> String {
+    format!("COPY {} ({}) FROM stdin", table.name, copy_columns(synthesizer, table))
+}
+
+/// The columns the synthesizer writes for `table`, comma separated in COPY order.
+pub fn copy_columns(synthesizer: &Synthesizer, table: &Table) -> String {
+    let column_names: Vec<_> = synthesizer
+        .output_columns(table)
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect();
+
+    column_names.join(", ")
+}
+
+/// `setval` statements moving every serial/identity sequence of `table` past the
+/// largest loaded value, so later inserts that use the default don't collide.
+pub fn sequence_fixups(table: &Table) -> Vec<String> {
+    table
+        .columns
+        .iter()
+        .filter_map(|column| {
+            let sequence = column.serial_sequence.as_ref()?;
+            Some(format!(
+                "SELECT setval('{}', COALESCE(MAX({col}), 1), MAX({col}) IS NOT NULL) FROM {};",
+                sequence.replace('\'', "''"),
+                table.name,
+                col = column.name,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use crate::genome::DatabaseGenome;
+    use crate::math::{Distribution, Histogram};
+    use crate::schema::{Column, DataType, ForeignKey, Table};
+    use crate::synth::SynthesisConfig;
+
+    #[test]
+    fn test_write_copy_sql() -> Result<()> {
+        let tables = vec![Table::new(
+            "users".to_string(),
+            vec![
+                Column::new("id".to_string(), DataType::Integer, false, true),
+                Column::new("name".to_string(), DataType::Text, false, false),
+            ],
+            vec![],
+        )];
+
+        let mut distributions = HashMap::new();
+        distributions.insert(
+            DatabaseGenome::make_key("users", "name"),
+            Distribution::new(
+                None,
+                None,
+                0,
+                10,
+                1,
+                Histogram::Categorical {
+                    frequencies: [("Alice".to_string(), 10)].into_iter().collect(),
+                    truncated: false,
+                    long_tail: None,
+                },
+            ),
+        );
+
+        let config = SynthesisConfig {
+            rows_per_table: 2,
+            ..SynthesisConfig::default()
+        };
+        let synthesizer = Synthesizer::new(DatabaseGenome::new(tables, distributions), config)?;
+        let result = synthesizer.generate()?;
+
+        let mut out = Vec::new();
+        write_copy_sql(&mut out, &synthesizer, &result)?;
+        let sql = String::from_utf8(out)?;
+
+        assert_eq!(sql, "COPY users (id, name) FROM stdin;\n1\tAlice\n2\tAlice\n\\.\n\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_table_csv() -> Result<()> {
+        let tables = vec![Table::new(
+            "users".to_string(),
+            vec![
+                Column::new("id".to_string(), DataType::Integer, false, true),
+                Column::new("name".to_string(), DataType::Text, false, false),
+                Column::new("note".to_string(), DataType::Text, true, false),
+            ],
+            vec![],
+        )];
+
+        let mut distributions = HashMap::new();
+        distributions.insert(
+            DatabaseGenome::make_key("users", "name"),
+            Distribution::new(None, None, 0, 10, 1, Histogram::Categorical {
+                frequencies: [("Smith, \"J\"".to_string(), 10)].into_iter().collect(),
+                truncated: false,
+                long_tail: None,
+            }),
+        );
+        distributions.insert(
+            DatabaseGenome::make_key("users", "note"),
+            Distribution::new(None, None, 10, 10, 0, Histogram::Categorical {
+                frequencies: Default::default(),
+                truncated: false,
+                long_tail: None,
+            }),
+        );
+
+        let config = SynthesisConfig { rows_per_table: 2, ..SynthesisConfig::default() };
+        let synthesizer = Synthesizer::new(DatabaseGenome::new(tables, distributions), config)?;
+        let result = synthesizer.generate()?;
+
+        let mut out = Vec::new();
+        write_table_csv(&mut out, &synthesizer, &result, "users")?;
+        assert_eq!(
+            String::from_utf8(out)?,
+            "id,name,note\n1,\"Smith, \"\"J\"\"\",\n2,\"Smith, \"\"J\"\"\",\n"
+        );
+
+        assert_eq!(csv_field(""), "\"\"");
+        assert_eq!(
+            parse_copy_line("1\t\\N\ta\\tb"),
+            vec![Some("1".to_string()), None, Some("a\tb".to_string())]
+        );
+        assert_eq!(unescape_copy_text("a\\tb\\\\c"), "a\tb\\c");
+        assert_eq!(escape_copy_text("a\tb\\c"), "a\\tb\\\\c");
+        assert_eq!(unescape_copy_text(&escape_copy_text("x\n\\y")), "x\n\\y");
+        Ok(())
+    }
+
+    #[test]
+    fn test_use_db_defaults_omits_columns() -> Result<()> {
+        let mut id = Column::new("id".to_string(), DataType::Integer, false, true);
+        id.has_default = true;
+        let mut created = Column::new("created_at".to_string(), DataType::Timestamp, false, false);
+        created.has_default = true;
+        let tables = vec![Table::new(
+            "events".to_string(),
+            vec![id, created, Column::new("kind".to_string(), DataType::Text, false, false)],
+            vec![],
+        )];
+
+        let mut distributions = HashMap::new();
+        distributions.insert(
+            DatabaseGenome::make_key("events", "kind"),
+            Distribution::new(None, None, 0, 5, 1, Histogram::Categorical {
+                frequencies: [("click".to_string(), 5)].into_iter().collect(),
+                truncated: false,
+                long_tail: None,
+            }),
+        );
+
+        let config = SynthesisConfig { rows_per_table: 1, use_db_defaults: true, ..SynthesisConfig::default() };
+        let synthesizer = Synthesizer::new(DatabaseGenome::new(tables, distributions), config)?;
+        let result = synthesizer.generate()?;
+
+        let mut out = Vec::new();
+        write_copy_sql(&mut out, &synthesizer, &result)?;
+        assert_eq!(String::from_utf8(out)?, "COPY events (kind) FROM stdin;\nclick\n\\.\n\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_copy_files_in_load_order() -> Result<()> {
+        let tables = vec![
+            Table::new(
+                "users".to_string(),
+                vec![Column::new("id".to_string(), DataType::Integer, false, true)],
+                vec![],
+            ),
+            Table::new(
+                "orders".to_string(),
+                vec![
+                    Column::new("id".to_string(), DataType::Integer, false, true),
+                    Column::new("user_id".to_string(), DataType::Integer, false, false),
+                ],
+                vec![ForeignKey::new("user_id".to_string(), "users".to_string(), "id".to_string())],
+            ),
+        ];
+        let config = SynthesisConfig { rows_per_table: 1, ..SynthesisConfig::default() };
+        let synthesizer = Synthesizer::new(DatabaseGenome::new(tables, HashMap::new()), config)?;
+        let result = synthesizer.generate()?;
+
+        let dir = tempfile::tempdir()?;
+        let paths = write_copy_files(dir.path(), &synthesizer, &result, &SqlOptions::default(), 16)?;
+
+        let names: Vec<_> = paths.iter().filter_map(|p| p.file_name()?.to_str()).collect();
+        assert_eq!(names, ["001_users.sql", "002_orders.sql"]);
+        assert_eq!(fs::read_to_string(&paths[1])?, "COPY orders (id, user_id) FROM stdin;\n1\t1\n\\.\n\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_transaction_preamble() -> Result<()> {
+        let tables = vec![Table::new(
+            "users".to_string(),
+            vec![Column::new("id".to_string(), DataType::Integer, false, true)],
+            vec![],
+        )];
+        let config = SynthesisConfig { rows_per_table: 1, ..SynthesisConfig::default() };
+        let synthesizer = Synthesizer::new(DatabaseGenome::new(tables, HashMap::new()), config)?;
+        let result = synthesizer.generate()?;
+
+        let options = SqlOptions { disable_triggers: true, defer_constraints: true, truncate: true, ..SqlOptions::default() };
+        let mut out = Vec::new();
+        write_copy_sql_with_options(&mut out, &synthesizer, &result, &options)?;
+        assert_eq!(
+            String::from_utf8(out)?,
+            "BEGIN;\nSET LOCAL session_replication_role = replica;\nSET CONSTRAINTS ALL DEFERRED;\n\n\
+             TRUNCATE users CASCADE;\nCOPY users (id) FROM stdin;\n1\n\\.\n\nCOMMIT;\n"
+        );
+
+        let mut out = Vec::new();
+        let options = SqlOptions { disable_triggers: true, ..SqlOptions::default() };
+        write_copy_sql_with_options(&mut out, &synthesizer, &result, &options)?;
+        assert!(String::from_utf8(out)?.ends_with("\\.\n\nSET session_replication_role = DEFAULT;\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sequence_fixups() {
+        let mut id = Column::new("id".to_string(), DataType::Integer, false, true);
+        id.serial_sequence = Some("public.users_id_seq".to_string());
+        let table = Table::new(
+            "users".to_string(),
+            vec![id, Column::new("name".to_string(), DataType::Text, false, false)],
+            vec![],
+        );
+
+        assert_eq!(
+            sequence_fixups(&table),
+            vec!["SELECT setval('public.users_id_seq', COALESCE(MAX(id), 1), MAX(id) IS NOT NULL) FROM users;"]
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! Character-class patterns for structured identifiers (SKUs, order numbers, ...).
+//!
+//! A pattern like `ORD-[0-9]{5}` is inferred from the reservoir sample at scan time and
+//! used to generate fresh values instead of replaying the sampled strings.
+
+use std::collections::HashMap;
+use std::fmt;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Fewest samples a pattern is inferred from.
+pub const MIN_PATTERN_SAMPLES: usize = 20;
+
+/// Share of samples that must share the dominant shape.
+pub const PATTERN_MIN_COVERAGE: f64 = 0.9;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PatternSegment {
+    Literal { text: String },
+    Digits { min_len: usize, max_len: usize },
+    Upper { min_len: usize, max_len: usize },
+    Lower { min_len: usize, max_len: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextPattern {
+    pub segments: Vec<PatternSegment>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum CharClass {
+    Digit,
+    Upper,
+    Lower,
+    Other(char),
+}
+
+impl CharClass {
+    fn of(c: char) -> Self {
+        if c.is_ascii_digit() {
+            CharClass::Digit
+        } else if c.is_ascii_uppercase() {
+            CharClass::Upper
+        } else if c.is_ascii_lowercase() {
+            CharClass::Lower
+        } else {
+            CharClass::Other(c)
+        }
+    }
+}
+
+/// Splits a value into runs of one character class. Every non-alphanumeric
+/// character is its own run so separators stay aligned across samples.
+fn runs(value: &str) -> Vec<(CharClass, &str)> {
+    let mut runs: Vec<(CharClass, &str)> = Vec::new();
+    let mut start = 0;
+    let mut current: Option<CharClass> = None;
+
+    for (i, c) in value.char_indices() {
+        let class = CharClass::of(c);
+        match current {
+            Some(prev) if prev == class && !matches!(class, CharClass::Other(_)) => {}
+            Some(prev) => {
+                runs.push((prev, &value[start..i]));
+                start = i;
+                current = Some(class);
+            }
+            None => current = Some(class),
+        }
+    }
+    if let Some(class) = current {
+        runs.push((class, &value[start..]));
+    }
+
+    runs
+}
+
+/// Infers a pattern shared by most of `samples`, or `None` when the values are
+/// free text or too irregular.
+pub fn infer_pattern(samples: &[String]) -> Option<TextPattern> {
+    if samples.len() < MIN_PATTERN_SAMPLES || samples.iter().any(|s| s.contains(char::is_whitespace)) {
+        return None;
+    }
+
+    let tokenized: Vec<Vec<(CharClass, &str)>> = samples.iter().map(|s| runs(s)).collect();
+
+    let mut shapes: HashMap<Vec<CharClass>, Vec<usize>> = HashMap::new();
+    for (i, value_runs) in tokenized.iter().enumerate() {
+        let shape = value_runs.iter().map(|(class, _)| *class).collect();
+        shapes.entry(shape).or_default().push(i);
+    }
+
+    let (shape, members) = shapes.into_iter().max_by_key(|(_, members)| members.len())?;
+    if (members.len() as f64) < PATTERN_MIN_COVERAGE * samples.len() as f64 || shape.is_empty() {
+        return None;
+    }
+
+    let mut segments: Vec<PatternSegment> = Vec::with_capacity(shape.len());
+    for (position, class) in shape.iter().enumerate() {
+        let texts: Vec<&str> = members.iter().map(|&i| tokenized[i][position].1).collect();
+
+        let segment = if matches!(class, CharClass::Other(_)) || texts.iter().all(|t| *t == texts[0]) {
+            PatternSegment::Literal { text: texts[0].to_string() }
+        } else {
+            let min_len = texts.iter().map(|t| t.len()).min().unwrap_or(1);
+            let max_len = texts.iter().map(|t| t.len()).max().unwrap_or(1);
+            match class {
+                CharClass::Digit => PatternSegment::Digits { min_len, max_len },
+                CharClass::Upper => PatternSegment::Upper { min_len, max_len },
+                _ => PatternSegment::Lower { min_len, max_len },
+            }
+        };
+
+        // Merge adjacent literals ("ORD" + "-" => "ORD-")
+        match (segments.last_mut(), segment) {
+            (Some(PatternSegment::Literal { text }), PatternSegment::Literal { text: next }) => {
+                text.push_str(&next);
+            }
+            (_, segment) => segments.push(segment),
+        }
+    }
+
+    // A constant string is better served by the categorical histogram
+    if segments.iter().all(|s| matches!(s, PatternSegment::Literal { .. })) {
+        return None;
+    }
+
+    Some(TextPattern { segments })
+}
+
+impl TextPattern {
+    pub fn generate<R: Rng + ?Sized>(&self, rng: &mut R) -> String {
+        let mut out = String::new();
+
+        for segment in &self.segments {
+            let (alphabet, min_len, max_len): (&[u8], usize, usize) = match segment {
+                PatternSegment::Literal { text } => {
+                    out.push_str(text);
+                    continue;
+                }
+                PatternSegment::Digits { min_len, max_len } => (b"0123456789", *min_len, *max_len),
+                PatternSegment::Upper { min_len, max_len } => (b"ABCDEFGHIJKLMNOPQRSTUVWXYZ", *min_len, *max_len),
+                PatternSegment::Lower { min_len, max_len } => (b"abcdefghijklmnopqrstuvwxyz", *min_len, *max_len),
+            };
+
+            let len = rng.gen_range(min_len..=max_len.max(min_len));
+            for _ in 0..len {
+                out.push(alphabet[rng.gen_range(0..alphabet.len())] as char);
+            }
+        }
+
+        out
+    }
+}
+
+/// Renders the pattern as a regular expression.
+impl fmt::Display for TextPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for segment in &self.segments {
+            let (class, min_len, max_len) = match segment {
+                PatternSegment::Literal { text } => {
+                    for c in text.chars() {
+                        if "\\.+*?()|[]{}^$".contains(c) {
+                            write!(f, "\\")?;
+                        }
+                        write!(f, "{}", c)?;
+                    }
+                    continue;
+                }
+                PatternSegment::Digits { min_len, max_len } => ("[0-9]", min_len, max_len),
+                PatternSegment::Upper { min_len, max_len } => ("[A-Z]", min_len, max_len),
+                PatternSegment::Lower { min_len, max_len } => ("[a-z]", min_len, max_len),
+            };
+
+            if min_len == max_len {
+                write!(f, "{}{{{}}}", class, min_len)?;
+            } else {
+                write!(f, "{}{{{},{}}}", class, min_len, max_len)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn skus() -> Vec<String> {
+        (0..50).map(|i| format!("ORD-{:05}", i * 7919 % 100_000)).collect()
+    }
+
+    #[test]
+    fn test_infer_sku_pattern() {
+        let pattern = infer_pattern(&skus()).expect("pattern");
+        assert_eq!(pattern.to_string(), "ORD-[0-9]{5}");
+    }
+
+    #[test]
+    fn test_generated_values_match_shape() {
+        let samples: Vec<String> = (0..40)
+            .map(|i| format!("{}{}-{}", ["AB", "XY", "QR"][i % 3], i % 10, 1000 + i * 37))
+            .collect();
+        let pattern = infer_pattern(&samples).expect("pattern");
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..20 {
+            let value = pattern.generate(&mut rng);
+            let parts: Vec<&str> = value.split('-').collect();
+            assert_eq!(parts.len(), 2, "{}", value);
+            assert!(parts[0][..2].chars().all(|c| c.is_ascii_uppercase()), "{}", value);
+            assert!(parts[1].chars().all(|c| c.is_ascii_digit()), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_free_text_has_no_pattern() {
+        let sentences: Vec<String> = (0..30).map(|i| format!("note number {}", i)).collect();
+        assert!(infer_pattern(&sentences).is_none());
+
+        let constants: Vec<String> = (0..30).map(|_| "N/A".to_string()).collect();
+        assert!(infer_pattern(&constants).is_none());
+    }
+}
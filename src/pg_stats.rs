@@ -0,0 +1,312 @@
+//! Fast scan (`scan --fast`): distributions built from the planner statistics in
+//! `pg_stats` instead of from the rows.
+//!
+//! `ANALYZE` keeps, per column, the share of NULLs, an estimate of the distinct count,
+//! the most common values with their frequencies, and equal-frequency histogram bounds
+//! over the remaining values. For each table a pseudo-sample is rebuilt from those:
+//! common values in proportion to their frequencies, the rest spread evenly through the
+//! histogram buckets. The sample is profiled like CSV rows. Columns are rebuilt
+//! independently, so no correlations are learned, and the genome is only as fresh as
+//! the last `ANALYZE`.
+
+use std::collections::HashMap;
+use anyhow::{Context, Result};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use sqlx::{PgPool, Row};
+use tracing::{debug, info};
+use crate::genome::DatabaseGenome;
+use crate::interval::{parse_interval, write_interval};
+use crate::math::Distribution;
+use crate::scanner::{parse_timestamp, profile_text_rows, ProfileOptions};
+use crate::schema::{parse_currency, Column, DataType, Table};
+
+/// Rows of the pseudo-sample rebuilt for each table. Enough to resolve the default
+/// statistics target of 100 common values and 100 histogram buckets.
+const SAMPLE_ROWS: usize = 2_000;
+
+/// One column's row of `pg_stats`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColumnStats {
+    pub null_frac: f64,
+
+    /// Distinct non-null values; negative when it is minus a share of the row count.
+    pub n_distinct: f64,
+
+    pub most_common_vals: Vec<String>,
+    pub most_common_freqs: Vec<f64>,
+
+    /// Bounds of equal-frequency buckets over the values not in `most_common_vals`.
+    pub histogram_bounds: Vec<String>,
+}
+
+impl ColumnStats {
+    /// Distinct values among `rows` rows.
+    pub fn distinct_count(&self, rows: u64) -> u64 {
+        if self.n_distinct >= 0.0 {
+            self.n_distinct.round() as u64
+        } else {
+            (-self.n_distinct * rows as f64).round() as u64
+        }
+    }
+}
+
+/// Distributions of the columns of `tables` that have statistics, keyed `table.column`.
+/// Also returns the columns without usable statistics, as `(table, column)`; they are
+/// left to placeholders.
+pub async fn profile_from_stats(
+    pool: &PgPool,
+    tables: &[Table],
+    options: &ProfileOptions,
+) -> Result<(HashMap<String, Distribution>, Vec<(String, String)>)> {
+    let stats = fetch_column_stats(pool, tables).await?;
+    info!(columns = stats.len(), "Read planner statistics");
+
+    // Seeded so the same statistics give the same genome
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut distributions = HashMap::new();
+    let mut missing = Vec::new();
+    for table in tables {
+        let mut profiled = Table::new(table.name.clone(), Vec::new(), Vec::new());
+        let mut samples = Vec::new();
+        for column in &table.columns {
+            let column_stats = stats.get(&(table.name.clone(), column.name.clone()));
+            match column_stats.and_then(|s| column_sample(column, s, SAMPLE_ROWS, &mut rng)) {
+                Some(sample) => {
+                    profiled.columns.push(column.clone());
+                    samples.push(sample);
+                }
+                None => missing.push((table.name.clone(), column.name.clone())),
+            }
+        }
+        if profiled.columns.is_empty() {
+            continue;
+        }
+
+        let rows = (0..SAMPLE_ROWS).map(|i| Ok(samples.iter().map(|sample| sample[i].clone()).collect()));
+        // The columns were rebuilt independently, so their correlation is noise
+        let (profile, _) = profile_text_rows(&profiled, rows, options)
+            .context(format!("Failed to profile statistics of '{}'", table.name))?;
+
+        let row_count = table.estimate.and_then(|e| e.rows).unwrap_or(SAMPLE_ROWS as u64);
+        for (column, mut distribution) in profile {
+            if let Some(column_stats) = stats.get(&(table.name.clone(), column.clone())) {
+                scale_counts(&mut distribution, column_stats, row_count);
+            }
+            distributions.insert(DatabaseGenome::make_key(&table.name, &column), distribution);
+        }
+    }
+    Ok((distributions, missing))
+}
+
+/// The `pg_stats` rows of `tables`, keyed by table and column. Parents profiled with
+/// their children take the statistics that include the children's rows.
+async fn fetch_column_stats(pool: &PgPool, tables: &[Table]) -> Result<HashMap<(String, String), ColumnStats>> {
+    let query = r#"
+        SELECT
+            tablename::text AS table_name,
+            attname::text AS column_name,
+            inherited,
+            null_frac::float8 AS null_frac,
+            n_distinct::float8 AS n_distinct,
+            most_common_vals::text::text[] AS most_common_vals,
+            most_common_freqs::float8[] AS most_common_freqs,
+            histogram_bounds::text::text[] AS histogram_bounds
+        FROM pg_stats
+        WHERE schemaname NOT IN ('pg_catalog', 'information_schema')
+          AND tablename = ANY($1)
+    "#;
+
+    let table_names: Vec<String> = tables.iter().map(|t| t.name.clone()).collect();
+    let rows = sqlx::query(query)
+        .bind(&table_names)
+        .fetch_all(pool)
+        .await
+        .context("Failed to read pg_stats")?;
+
+    let mut stats = HashMap::new();
+    for row in rows {
+        let table_name: String = row.try_get("table_name")?;
+        let column_name: String = row.try_get("column_name")?;
+        let inherited: bool = row.try_get("inherited")?;
+        let Some(table) = tables.iter().find(|t| t.name == table_name) else {
+            continue;
+        };
+
+        // Tables with children have both rows; partitioned tables only the inherited one
+        let key = (table_name, column_name);
+        if stats.contains_key(&key) && inherited == table.own_rows_only {
+            continue;
+        }
+        stats.insert(key, ColumnStats {
+            null_frac: row.try_get("null_frac")?,
+            n_distinct: row.try_get("n_distinct")?,
+            most_common_vals: row.try_get::<Option<Vec<String>>, _>("most_common_vals")?.unwrap_or_default(),
+            most_common_freqs: row.try_get::<Option<Vec<f64>>, _>("most_common_freqs")?.unwrap_or_default(),
+            histogram_bounds: row.try_get::<Option<Vec<String>>, _>("histogram_bounds")?.unwrap_or_default(),
+        });
+    }
+    Ok(stats)
+}
+
+/// `rows` values of `column` in random order: NULLs at the null share, common values
+/// at their frequencies and the rest from the histogram. `None` when the statistics
+/// hold no values or values that can't be read back.
+fn column_sample<R: Rng + ?Sized>(column: &Column, stats: &ColumnStats, rows: usize, rng: &mut R) -> Option<Vec<Option<String>>> {
+    // PostGIS keeps its own statistics; pg_stats only has hex EWKB bounds, if anything
+    if matches!(column.data_type, DataType::Geometry | DataType::Geography) {
+        return None;
+    }
+
+    let nulls = ((stats.null_frac * rows as f64).round() as usize).min(rows);
+    let non_null = rows - nulls;
+    let has_histogram = stats.histogram_bounds.len() >= 2;
+    if non_null > 0 && stats.most_common_vals.is_empty() && !has_histogram {
+        return None;
+    }
+
+    // Common-value frequencies are shares of all rows; without a histogram they are
+    // the whole non-null part
+    let common_share: f64 = stats.most_common_freqs.iter().sum();
+    let common_rows = if has_histogram && stats.null_frac < 1.0 {
+        ((non_null as f64 * common_share / (1.0 - stats.null_frac)).round() as usize).min(non_null)
+    } else {
+        non_null
+    };
+
+    let mut values: Vec<Option<String>> = vec![None; nulls];
+    if common_share > 0.0 {
+        for (value, frequency) in stats.most_common_vals.iter().zip(&stats.most_common_freqs) {
+            let count = (common_rows as f64 * frequency / common_share).round() as usize;
+            values.extend(std::iter::repeat_n(Some(value.clone()), count));
+        }
+    }
+    if has_histogram {
+        let count = rows.saturating_sub(values.len());
+        values.extend(histogram_values(&column.data_type, &stats.histogram_bounds, count)?.into_iter().map(Some));
+    }
+    values.resize(rows, None);
+    values.shuffle(rng);
+    Some(values)
+}
+
+/// `count` values spread evenly through the buckets between consecutive `bounds`.
+/// Numbers, timestamps and intervals are interpolated inside each bucket; values of
+/// other types can't be, so the bounds themselves are repeated.
+fn histogram_values(data_type: &DataType, bounds: &[String], count: usize) -> Option<Vec<String>> {
+    let parse = |bound: &str| match data_type {
+        DataType::Integer | DataType::Float => bound.trim().parse::<f64>().ok().or_else(|| parse_currency(bound)),
+        DataType::Timestamp => parse_timestamp(bound),
+        DataType::Interval => parse_interval(bound),
+        _ => None,
+    };
+    if !matches!(data_type, DataType::Integer | DataType::Float | DataType::Timestamp | DataType::Interval) {
+        return Some((0..count).map(|i| bounds[i * bounds.len() / count].clone()).collect());
+    }
+
+    let edges = bounds.iter().map(|bound| parse(bound)).collect::<Option<Vec<f64>>>()?;
+    let buckets = edges.len() - 1;
+    (0..count)
+        .map(|i| {
+            let position = (i as f64 + 0.5) / count as f64 * buckets as f64;
+            let bucket = (position as usize).min(buckets - 1);
+            let (lo, hi) = (edges[bucket], edges[bucket + 1]);
+            format_value(data_type, lo + (position - bucket as f64) * (hi - lo))
+        })
+        .collect()
+}
+
+/// `value` written the way [`profile_text_rows`] reads the type.
+fn format_value(data_type: &DataType, value: f64) -> Option<String> {
+    match data_type {
+        DataType::Integer => Some((value.round() as i64).to_string()),
+        DataType::Timestamp => {
+            chrono::DateTime::from_timestamp_micros((value * 1e6).round() as i64).map(|ts| ts.to_rfc3339())
+        }
+        DataType::Interval => {
+            let mut out = Vec::new();
+            write_interval(&mut out, value);
+            String::from_utf8(out).ok()
+        }
+        _ => Some(value.to_string()),
+    }
+}
+
+/// Replaces the counts of the pseudo-sample with the table's row estimate and the
+/// statistics' null share and distinct count.
+fn scale_counts(distribution: &mut Distribution, stats: &ColumnStats, rows: u64) {
+    let nulls = (stats.null_frac * rows as f64).round() as u64;
+    distribution.total_count = rows;
+    distribution.null_count = nulls.min(rows);
+    distribution.unique_count = stats.distinct_count(rows).min(rows - distribution.null_count) as usize;
+    debug!(rows, nulls, distinct = distribution.unique_count, "Scaled pseudo-sample counts");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(null_frac: f64, common: &[(&str, f64)], bounds: &[&str]) -> ColumnStats {
+        ColumnStats {
+            null_frac,
+            n_distinct: -0.5,
+            most_common_vals: common.iter().map(|(v, _)| v.to_string()).collect(),
+            most_common_freqs: common.iter().map(|&(_, f)| f).collect(),
+            histogram_bounds: bounds.iter().map(|b| b.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_column_sample_mixes_common_values_and_histogram() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let amount = Column::new("amount".to_string(), DataType::Float, true, false);
+        let sample = column_sample(&amount, &stats(0.1, &[("0", 0.3)], &["10", "20", "100"]), 1000, &mut rng).unwrap();
+
+        assert_eq!(sample.len(), 1000);
+        assert_eq!(sample.iter().filter(|v| v.is_none()).count(), 100);
+        assert_eq!(sample.iter().filter(|v| v.as_deref() == Some("0")).count(), 300);
+        let rest: Vec<f64> = sample.iter().flatten().filter(|v| *v != "0").map(|v| v.parse().unwrap()).collect();
+        assert_eq!(rest.len(), 600);
+        assert!(rest.iter().all(|v| (10.0..=100.0).contains(v)));
+        // Equal-frequency buckets: half the values in each
+        assert_eq!(rest.iter().filter(|&&v| v < 20.0).count(), 300);
+    }
+
+    #[test]
+    fn test_column_sample_without_histogram() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let status = Column::new("status".to_string(), DataType::Text, false, false);
+        let sample = column_sample(&status, &stats(0.0, &[("open", 0.6), ("closed", 0.4)], &[]), 100, &mut rng).unwrap();
+        assert_eq!(sample.iter().filter(|v| v.as_deref() == Some("open")).count(), 60);
+        assert_eq!(sample.iter().filter(|v| v.as_deref() == Some("closed")).count(), 40);
+
+        assert!(column_sample(&status, &stats(0.2, &[], &[]), 100, &mut rng).is_none());
+        let location = Column::new("location".to_string(), DataType::Geometry, true, false);
+        assert!(column_sample(&location, &stats(0.0, &[], &["0101", "0102"]), 100, &mut rng).is_none());
+    }
+
+    #[test]
+    fn test_histogram_values_by_type() {
+        let at = histogram_values(&DataType::Timestamp, &["2024-01-01 00:00:00+00".to_string(), "2024-01-02 00:00:00+00".to_string()], 2).unwrap();
+        assert_eq!(at, ["2024-01-01T06:00:00+00:00", "2024-01-01T18:00:00+00:00"]);
+        assert_eq!(histogram_values(&DataType::Interval, &["00:00:00".to_string(), "02:00:00".to_string()], 1).unwrap(), ["01:00:00"]);
+        assert_eq!(histogram_values(&DataType::Text, &["a".to_string(), "m".to_string(), "z".to_string()], 3).unwrap(), ["a", "m", "z"]);
+        assert!(histogram_values(&DataType::Float, &["x".to_string(), "1".to_string()], 3).is_none());
+    }
+
+    #[test]
+    fn test_scale_counts() {
+        let mut distribution = Distribution::new(Some(0.0), Some(1.0), 10, 100, 40, crate::math::Histogram::Categorical {
+            frequencies: Default::default(),
+            truncated: false,
+            long_tail: None,
+        });
+        scale_counts(&mut distribution, &stats(0.1, &[], &[]), 1_000_000);
+        assert_eq!((distribution.total_count, distribution.null_count, distribution.unique_count), (1_000_000, 100_000, 500_000));
+
+        let unique = ColumnStats { n_distinct: -1.0, ..ColumnStats::default() };
+        assert_eq!(unique.distinct_count(42), 42);
+        assert_eq!(ColumnStats { n_distinct: 3.0, ..ColumnStats::default() }.distinct_count(42), 3);
+    }
+}
@@ -1,14 +1,73 @@
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
 use anyhow::{Result, Context};
-use sqlx::{PgPool, Row};
-use tracing::{debug, warn};
-use crate::schema::{Column, DataType, ForeignKey, Table};
+use sqlx::{PgConnection, PgPool, Postgres, Row};
+use sqlx::pool::PoolConnection;
+use tracing::{debug, info, warn};
+use crate::schema::{
+    map_sql_type_to_datatype, unmapped_sql_type, Column, DataType, ForeignKey, Table, TableEstimate, TypeBounds,
+};
+
+/// What to do with tables that take part in (non-partition) inheritance.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InheritanceMode {
+    /// Profile parents and children as separate tables, parents with `ONLY`.
+    #[default]
+    Separate,
+    /// Profile parents together with their children's rows and skip the children.
+    Merge,
+}
+
+impl fmt::Display for InheritanceMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InheritanceMode::Separate => write!(f, "separate"),
+            InheritanceMode::Merge => write!(f, "merge"),
+        }
+    }
+}
+
+impl FromStr for InheritanceMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "separate" => Ok(InheritanceMode::Separate),
+            "merge" => Ok(InheritanceMode::Merge),
+            other => anyhow::bail!("Unknown inheritance mode '{}' (expected separate or merge)", other),
+        }
+    }
+}
+
+/// Which relations introspection turns into tables.
+#[derive(Debug, Clone, Default)]
+pub struct IntrospectOptions {
+    pub inheritance: InheritanceMode,
+
+    /// Profile and generate foreign tables (only useful when the FDW accepts inserts).
+    pub foreign_tables: bool,
+}
+
+/// A relation returned by the catalog query, before filtering.
+#[derive(Debug, Clone, PartialEq)]
+struct Relation {
+    name: String,
+    /// `pg_class.relkind`: `r` table, `p` partitioned table, `f` foreign table, `m` materialized view.
+    kind: String,
+    is_partition: bool,
+    inherits: bool,
+    has_children: bool,
+}
 
-pub async fn introspect(pool: &PgPool) -> Result<Vec<Table>> {
+pub async fn introspect(pool: &PgPool, options: &IntrospectOptions) -> Result<Vec<Table>> {
     debug!("Starting schema introspection");
 
-    let table_names = fetch_table_names(pool).await?;
-    debug!("Discovered {} tables", table_names.len());
+    let relations = select_relations(fetch_relations(pool).await?, options);
+    debug!("Discovered {} tables", relations.len());
+
+    let table_names: Vec<String> = relations.iter().map(|(name, _)| name.clone()).collect();
 
     let columns_map = fetch_columns(pool, &table_names).await?;
 
@@ -18,7 +77,7 @@ pub async fn introspect(pool: &PgPool) -> Result<Vec<Table>> {
 
     let mut tables = Vec::with_capacity(table_names.len());
 
-    for table_name in table_names {
+    for (table_name, own_rows_only) in relations {
         let mut columns = columns_map
             .get(&table_name)
             .cloned()
@@ -36,34 +95,218 @@ pub async fn introspect(pool: &PgPool) -> Result<Vec<Table>> {
             .get(&table_name)
             .cloned()
             .unwrap_or_default();
-        tables.push(Table::new(table_name, columns, foreign_keys));
+        let mut table = Table::new(table_name, columns, foreign_keys);
+        table.own_rows_only = own_rows_only;
+        table.set_aside_derived_columns();
+        tables.push(table);
+    }
+
+    // Estimates are advisory; a failure here should not stop the scan
+    match fetch_table_estimates(pool, &table_names).await {
+        Ok(estimates) => {
+            for table in &mut tables {
+                table.estimate = estimates.get(&table.name).copied();
+            }
+        }
+        Err(e) => warn!(error = %e, "Failed to read table size estimates"),
     }
 
     debug!("Introspection complete: {} table processed", tables.len());
     Ok(tables)
 }
 
-async fn fetch_table_names(pool: &PgPool) -> Result<Vec<String>> {
+/// A `REPEATABLE READ` transaction whose snapshot has been exported for other sessions.
+/// The snapshot stays importable only while this transaction is open.
+pub struct ExportedSnapshot {
+    conn: PoolConnection<Postgres>,
+    id: String,
+}
+
+impl ExportedSnapshot {
+    pub async fn export(pool: &PgPool) -> Result<Self> {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Failed to acquire snapshot connection")?;
+
+        sqlx::query("BEGIN ISOLATION LEVEL REPEATABLE READ READ ONLY")
+            .execute(&mut *conn)
+            .await
+            .context("Failed to begin snapshot transaction")?;
+
+        let id: String = sqlx::query_scalar("SELECT pg_export_snapshot()")
+            .fetch_one(&mut *conn)
+            .await
+            .context("Failed to export snapshot")?;
+
+        debug!(snapshot = %id, "Exported snapshot for consistent scanning");
+
+        Ok(Self { conn, id })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub async fn release(mut self) -> Result<()> {
+        sqlx::query("COMMIT")
+            .execute(&mut *self.conn)
+            .await
+            .context("Failed to close snapshot transaction")?;
+        Ok(())
+    }
+}
+
+/// Session settings that keep a scan from writing or hogging resources, for scans
+/// pointed at production replicas. Apply them to every connection the scan uses, for
+/// example from `PgPoolOptions::after_connect`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadOnlySession {
+    /// Longest a single statement may run. Profiling streams a whole table in one
+    /// statement, so this bounds the time spent on the biggest table.
+    pub statement_timeout: Duration,
+
+    /// `work_mem` in kilobytes for sorts and hashes (the conditional joins).
+    pub work_mem_kb: u64,
+}
+
+impl Default for ReadOnlySession {
+    fn default() -> Self {
+        Self {
+            statement_timeout: Duration::from_secs(30 * 60),
+            work_mem_kb: 4096,
+        }
+    }
+}
+
+impl ReadOnlySession {
+    pub fn statements(&self) -> Vec<String> {
+        vec![
+            "SET default_transaction_read_only = on".to_string(),
+            format!("SET statement_timeout = {}", self.statement_timeout.as_millis()),
+            format!("SET work_mem = '{}kB'", self.work_mem_kb),
+        ]
+    }
+
+    pub async fn apply(&self, conn: &mut PgConnection) -> Result<()> {
+        for statement in self.statements() {
+            sqlx::query(&statement)
+                .execute(&mut *conn)
+                .await
+                .context(format!("Failed to run '{}'", statement))?;
+        }
+        Ok(())
+    }
+}
+
+/// Row and size estimates from the planner statistics, keyed by table name.
+/// Partitioned tables add up their partitions.
+async fn fetch_table_estimates(pool: &PgPool, table_names: &[String]) -> Result<HashMap<String, TableEstimate>> {
     let query = r#"
-        SELECT table_name
-        FROM information_schema.tables
-        WHERE table_schema NOT IN ('pg_catalog', 'information_schema')
-          AND table_type = 'BASE TABLE'
-        ORDER BY table_name
+        SELECT
+            c.relname AS table_name,
+            COALESCE(t.rows, CASE WHEN c.reltuples >= 0 THEN c.reltuples::bigint END) AS rows,
+            COALESCE(t.bytes, pg_table_size(c.oid)) AS bytes
+        FROM pg_class c
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        LEFT JOIN LATERAL (
+            SELECT
+                (sum(p.reltuples) FILTER (WHERE p.reltuples >= 0))::bigint AS rows,
+                sum(pg_table_size(p.oid))::bigint AS bytes
+            FROM pg_partition_tree(c.oid) tree
+            JOIN pg_class p ON p.oid = tree.relid
+            WHERE c.relkind = 'p'
+        ) t ON true
+        WHERE c.relname = ANY($1)
+          AND n.nspname NOT IN ('pg_catalog', 'information_schema')
+          AND n.nspname NOT LIKE 'pg_toast%'
     "#;
 
     let rows = sqlx::query(query)
+        .bind(table_names)
         .fetch_all(pool)
         .await
-        .context("Failed to fetch table names from information_schema")?;
+        .context("Failed to fetch table estimates from pg_class")?;
+
+    rows.into_iter()
+        .map(|row| {
+            let rows: Option<i64> = row.try_get("rows")?;
+            let bytes: i64 = row.try_get("bytes")?;
+            Ok((row.try_get("table_name")?, TableEstimate {
+                rows: rows.map(|r| r.max(0) as u64),
+                bytes: bytes.max(0) as u64,
+            }))
+        })
+        .collect::<Result<HashMap<_, _>, sqlx::Error>>()
+        .context("Failed to parse table estimates")
+}
 
-    let tables: Vec<String> = rows
-        .into_iter()
-        .map(|row| row.try_get::<String, _>("table_name"))
-        .collect::<Result<Vec<_>, _>>()
-        .context("Failed to parse table names")?;
+async fn fetch_relations(pool: &PgPool) -> Result<Vec<Relation>> {
+    let query = r#"
+        SELECT
+            c.relname AS table_name,
+            c.relkind::text AS kind,
+            c.relispartition AS is_partition,
+            EXISTS (SELECT 1 FROM pg_inherits i WHERE i.inhrelid = c.oid) AS inherits,
+            EXISTS (SELECT 1 FROM pg_inherits i WHERE i.inhparent = c.oid) AS has_children
+        FROM pg_class c
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        WHERE n.nspname NOT IN ('pg_catalog', 'information_schema')
+          AND n.nspname NOT LIKE 'pg_toast%'
+          AND c.relkind IN ('r', 'p', 'f', 'm')
+          AND has_table_privilege(c.oid, 'SELECT')
+        ORDER BY c.relname
+    "#;
 
-    Ok(tables)
+    let rows = sqlx::query(query)
+        .fetch_all(pool)
+        .await
+        .context("Failed to fetch table names from pg_class")?;
+
+    rows.into_iter()
+        .map(|row| {
+            Ok(Relation {
+                name: row.try_get("table_name")?,
+                kind: row.try_get("kind")?,
+                is_partition: row.try_get("is_partition")?,
+                inherits: row.try_get("inherits")?,
+                has_children: row.try_get("has_children")?,
+            })
+        })
+        .collect::<Result<Vec<_>, sqlx::Error>>()
+        .context("Failed to parse table names")
+}
+
+/// Tables to profile, each paired with whether it must be read with `ONLY`.
+///
+/// Partitions are always folded into their partitioned parent, which holds no rows of
+/// its own. Materialized views are skipped: COPY cannot load them.
+fn select_relations(relations: Vec<Relation>, options: &IntrospectOptions) -> Vec<(String, bool)> {
+    let mut selected = Vec::with_capacity(relations.len());
+
+    for relation in relations {
+        let skip_reason = match relation.kind.as_str() {
+            "m" => Some("materialized view"),
+            "f" if !options.foreign_tables => Some("foreign table"),
+            _ if relation.is_partition => Some("partition, profiled through its parent"),
+            _ if relation.inherits && options.inheritance == InheritanceMode::Merge => {
+                Some("inherited table, merged into its parent")
+            }
+            _ => None,
+        };
+
+        if let Some(reason) = skip_reason {
+            info!(table = %relation.name, reason, "Skipping relation");
+            continue;
+        }
+
+        let own_rows_only = relation.kind == "r"
+            && relation.has_children
+            && options.inheritance == InheritanceMode::Separate;
+        selected.push((relation.name, own_rows_only));
+    }
+
+    selected
 }
 
 async fn fetch_columns(pool: &PgPool, table_names: &[String]) -> Result<HashMap<String, Vec<Column>>> {
@@ -77,8 +320,36 @@ async fn fetch_columns(pool: &PgPool, table_names: &[String]) -> Result<HashMap<
             column_name,
             data_type,
             udt_name,
-            is_nullable
+            numeric_precision::int AS numeric_precision,
+            numeric_scale::int AS numeric_scale,
+            is_nullable,
+            pg_get_serial_sequence(format('%I.%I', table_schema, table_name), column_name) AS serial_sequence,
+            (column_default IS NOT NULL OR is_identity = 'YES') AS has_default,
+            (is_generated = 'ALWAYS') AS is_generated,
+            domain_name,
+            domain.base_type AS domain_base_type,
+            domain.checks AS domain_checks
         FROM information_schema.columns
+        -- A domain over another domain reports the inner domain as its udt_name; follow
+        -- typbasetype down to the base type, collecting the checks on the way
+        LEFT JOIN LATERAL (
+            WITH RECURSIVE chain AS (
+                SELECT t.oid, t.typname, t.typtype, t.typbasetype
+                FROM pg_type t
+                JOIN pg_namespace n ON n.oid = t.typnamespace
+                WHERE t.typname = domain_name AND n.nspname = domain_schema
+                UNION ALL
+                SELECT t.oid, t.typname, t.typtype, t.typbasetype
+                FROM pg_type t
+                JOIN chain ON t.oid = chain.typbasetype
+                WHERE chain.typtype = 'd'
+            )
+            SELECT
+                (SELECT typname::text FROM chain WHERE typtype <> 'd') AS base_type,
+                (SELECT array_agg(pg_get_constraintdef(con.oid))
+                 FROM pg_constraint con
+                 WHERE con.contype = 'c' AND con.contypid IN (SELECT oid FROM chain)) AS checks
+        ) domain ON domain_name IS NOT NULL
         WHERE table_schema NOT IN ('pg_catalog', 'information_schema')
         ORDER BY table_name, ordinal_position
     "#;
@@ -94,93 +365,64 @@ async fn fetch_columns(pool: &PgPool, table_names: &[String]) -> Result<HashMap<
         let table_name: String = row.try_get("table_name")?;
         let column_name: String = row.try_get("column_name")?;
         let sql_type: String = row.try_get("data_type")?;
-        let udt_name: String = row.try_get("udt_name")?;
+        let domain_name: Option<String> = row.try_get("domain_name")?;
+        let domain_base_type: Option<String> = row.try_get("domain_base_type")?;
+        let domain_checks: Option<Vec<String>> = row.try_get("domain_checks")?;
+        // Domains take the mapping of their base type
+        let udt_name: String = match domain_base_type {
+            Some(base_type) => base_type,
+            None => row.try_get("udt_name")?,
+        };
+        let numeric_precision: Option<i32> = row.try_get("numeric_precision")?;
+        let numeric_scale: Option<i32> = row.try_get("numeric_scale")?;
         let is_nullable: String = row.try_get("is_nullable")?;
+        let serial_sequence: Option<String> = row.try_get("serial_sequence")?;
+        let has_default: bool = row.try_get("has_default")?;
+        let is_generated: bool = row.try_get("is_generated")?;
+
+        // Computed columns reject COPY; the target derives them from the other columns
+        if is_generated {
+            debug!(table = %table_name, column = %column_name, "Skipping generated column");
+            continue;
+        }
 
         let data_type = map_sql_type_to_datatype(&sql_type, &udt_name, &table_name, &column_name);
         let is_nullable = is_nullable.eq_ignore_ascii_case("YES");
 
-        let column = Column::new(
+        if let Some(sequence) = &serial_sequence {
+            debug!(table = %table_name, column = %column_name, sequence = %sequence, "Column is backed by a sequence");
+        }
+
+        let mut column = Column::new(
             column_name,
             data_type,
             is_nullable,
             false,
         );
+        column.serial_sequence = serial_sequence;
+        column.has_default = has_default;
+        column.bounds = TypeBounds::for_pg_type(&udt_name, numeric_precision, numeric_scale);
+        column.unmapped_type = unmapped_sql_type(&sql_type, &udt_name);
+        column.money = udt_name == "money";
+        if matches!(column.data_type, DataType::Integer | DataType::Float) {
+            let integer = column.data_type == DataType::Integer;
+            for check in domain_checks.iter().flatten() {
+                if let Some(bounds) = TypeBounds::from_check(check, integer) {
+                    column.bounds = Some(column.bounds.map_or(bounds, |type_bounds| type_bounds.intersect(&bounds)));
+                }
+            }
+        }
+        column.domain = domain_name;
 
         columns_map
             .entry(table_name)
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(column);
     }
 
     Ok(columns_map)
 }
 
-fn map_sql_type_to_datatype(sql_type: &str, udt_name: &str, table_name: &str, column_name: &str) -> DataType {
-
-    let normalized = sql_type.to_lowercase();
-    let udt_normalized = udt_name.to_lowercase();
-
-    match normalized.as_str() {
-        "integer" | "int" | "smallint" | "bigint" => DataType::Integer,
-
-        "real" | "double precision" | "numeric" | "decimal" | "float" => DataType::Float,
-
-        "character varying" | "varchar" | "character" | "char" | "text" => DataType::Text,
-
-        "timestamp" | "timestamp without time zone" | "timestamp with time zone"
-        | "timestamptz" | "date" | "time" => DataType::Timestamp,
-
-        "boolean" | "bool" => DataType::Boolean,
-
-        "uuid" => DataType::Uuid,
-
-        "user-defined" => map_udt_type(&udt_normalized, table_name, column_name),
-
-        "array" => {
-            if udt_normalized.starts_with('_') {
-                let base_type = &udt_normalized[1..];
-                map_udt_type(base_type, table_name, column_name)
-            } else {
-                warn_unknown_type(sql_type, udt_name, table_name, column_name)
-            }
-        }
-
-        _ => {
-            map_udt_type(&udt_normalized, table_name, column_name)
-        }
-    }
-}
-
-fn map_udt_type(udt_name: &str, table_name: &str, column_name: &str) -> DataType {
-    match udt_name {
-        "int2" | "int4" | "int8" | "smallint" | "integer" | "bigint" => DataType::Integer,
-
-        "float4" | "float8" | "numeric" => DataType::Float,
-
-        "varchar" | "text" | "bpchar" | "char" => DataType::Text,
-
-        "timestamp" | "timestamptz" | "date" | "time" | "timetz" => DataType::Timestamp,
-
-        "bool" => DataType::Boolean,
-
-        "uuid" => DataType::Uuid,
-
-        _ => warn_unknown_type(udt_name, udt_name, table_name, column_name),
-    }
-}
-
-fn warn_unknown_type(sql_type: &str, udt_name: &str, table_name: &str, column_name: &str) -> DataType {
-    warn!(
-        table = %table_name,
-        column_name = %column_name,
-        sql_type = %sql_type,
-        udt_name = %udt_name,
-        "Unknown data type encountered, defaulting to Text"
-    );
-    DataType::Text
-}
-
 async fn fetch_primary_keys(pool: &PgPool) -> Result<HashMap<String, HashSet<String>>> {
     let query = r#"
         SELECT
@@ -207,7 +449,7 @@ async fn fetch_primary_keys(pool: &PgPool) -> Result<HashMap<String, HashSet<Str
 
         pk_map
             .entry(table_name)
-            .or_insert_with(HashSet::new)
+            .or_default()
             .insert(column_name);
     }
     Ok(pk_map)
@@ -248,7 +490,7 @@ async fn fetch_foreign_keys(pool: &PgPool) -> Result<HashMap<String, Vec<Foreign
 
         fk_map
             .entry(source_table)
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(fk);
     }
 
@@ -262,34 +504,50 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_type_mapping_integers() {
-        let dt = map_sql_type_to_datatype("integer", "int4", "test", "id");
-        assert_eq!(dt, DataType::Integer);
-
-        let dt = map_sql_type_to_datatype("bigint", "int8", "test", "id");
-        assert_eq!(dt, DataType::Integer);
-    }
-
-    #[test]
-    fn test_type_mapping_text() {
-        let dt = map_sql_type_to_datatype("character varying", "varchar", "test", "name");
-        assert_eq!(dt, DataType::Text);
-
-        let dt = map_sql_type_to_datatype("text", "text", "test", "desc");
-        assert_eq!(dt, DataType::Text);
+    fn test_read_only_session_statements() {
+        let session = ReadOnlySession {
+            statement_timeout: Duration::from_secs(90),
+            work_mem_kb: 1024,
+        };
+        assert_eq!(
+            session.statements(),
+            vec![
+                "SET default_transaction_read_only = on",
+                "SET statement_timeout = 90000",
+                "SET work_mem = '1024kB'",
+            ]
+        );
     }
 
     #[test]
-    fn test_type_mapping_timestamp() {
-        let dt = map_sql_type_to_datatype("timestamp without time zone", "timestamp", "test", "created");
-        assert_eq!(dt, DataType::Timestamp);
-    }
+    fn test_select_relations() {
+        let relation = |name: &str, kind: &str, is_partition, inherits, has_children| Relation {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            is_partition,
+            inherits,
+            has_children,
+        };
+        let relations = vec![
+            relation("events", "p", false, false, true),
+            relation("events_2024", "r", true, true, false),
+            relation("people", "r", false, false, true),
+            relation("staff", "r", false, true, false),
+            relation("remote", "f", false, false, false),
+            relation("daily_totals", "m", false, false, false),
+        ];
+
+        let separate = select_relations(relations.clone(), &IntrospectOptions::default());
+        assert_eq!(
+            separate,
+            vec![("events".to_string(), false), ("people".to_string(), true), ("staff".to_string(), false)]
+        );
 
-    #[test]
-    fn test_type_mapping_unknown_fallback() {
-        let dt = map_sql_type_to_datatype("exotic_type", "custom", "test", "col");
-        assert_eq!(dt, DataType::Text);
+        let options = IntrospectOptions { inheritance: InheritanceMode::Merge, foreign_tables: true };
+        let merged = select_relations(relations, &options);
+        assert_eq!(
+            merged,
+            vec![("events".to_string(), false), ("people".to_string(), false), ("remote".to_string(), false)]
+        );
     }
 }
-
-
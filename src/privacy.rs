@@ -0,0 +1,279 @@
+//! Differentially private release of histogram counts using the Laplace mechanism.
+//!
+//! Every row lands in exactly one bucket of a column histogram (NULL counts as its own
+//! bucket), so one column is a single query with sensitivity 1 and costs `epsilon`.
+//! Columns compose sequentially, which is what [`PrivacyBudget::total_epsilon`] reports.
+//! Min/max, bin edges, t-digest centroid means, row totals and Markov text models are not privatized; string
+//! length profiles, parametric fits, timestamp seasonality and sequence gaps are dropped.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use crate::genome::DatabaseGenome;
+use crate::math::{Distribution, Histogram};
+
+/// Default rare-category cut-off, in multiples of the Laplace scale (1 / epsilon).
+pub const DEFAULT_RARE_THRESHOLD_SCALES: f64 = 3.0;
+
+#[derive(Debug, Clone)]
+pub struct DpOptions {
+    /// Budget spent on each column histogram.
+    pub epsilon: f64,
+
+    /// Categories whose noisy count falls below this are dropped.
+    /// `None` uses `1 + DEFAULT_RARE_THRESHOLD_SCALES / epsilon`.
+    pub rare_threshold: Option<f64>,
+}
+
+impl DpOptions {
+    pub fn new(epsilon: f64) -> Self {
+        Self {
+            epsilon,
+            rare_threshold: None,
+        }
+    }
+
+    pub fn threshold(&self) -> f64 {
+        self.rare_threshold
+            .unwrap_or(1.0 + DEFAULT_RARE_THRESHOLD_SCALES / self.epsilon)
+    }
+}
+
+/// Privacy accounting recorded in the genome metadata.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrivacyBudget {
+    pub mechanism: String,
+    pub epsilon_per_column: f64,
+    pub total_epsilon: f64,
+    pub noised_columns: usize,
+    pub rare_threshold: f64,
+}
+
+/// Adds Laplace noise to every stored histogram in `genome` and records the budget spent.
+///
+/// Correlation matrices and cross-table conditionals are computed from raw rows and are
+/// dropped, since they would otherwise void the guarantee.
+pub fn apply_differential_privacy<R: Rng + ?Sized>(
+    genome: &mut DatabaseGenome,
+    options: &DpOptions,
+    rng: &mut R,
+) -> anyhow::Result<PrivacyBudget> {
+    if !(options.epsilon.is_finite() && options.epsilon > 0.0) {
+        anyhow::bail!("Privacy budget epsilon must be positive, got {}", options.epsilon);
+    }
+
+    let threshold = options.threshold();
+    let mut noised_columns = 0;
+    let mut dropped_categories = 0;
+
+    for dist in genome.distributions.values_mut() {
+        if let Some(dropped) = privatize_distribution(dist, options.epsilon, threshold, rng) {
+            noised_columns += 1;
+            dropped_categories += dropped;
+        }
+    }
+
+    if !genome.correlations.is_empty() {
+        info!(
+            tables = genome.correlations.len(),
+            "Dropping correlation matrices, they are not covered by differential privacy"
+        );
+        genome.correlations.clear();
+    }
+
+    if !genome.conditionals.is_empty() {
+        info!(
+            columns = genome.conditionals.len(),
+            "Dropping cross-table conditionals, they are not covered by differential privacy"
+        );
+        genome.conditionals.clear();
+    }
+
+    if genome.tenants.take().is_some() {
+        info!("Dropping per-tenant row counts, they are not covered by differential privacy");
+    }
+
+    if !genome.fanouts.is_empty() {
+        info!(
+            foreign_keys = genome.fanouts.len(),
+            "Dropping foreign key fan-out, it is not covered by differential privacy"
+        );
+        genome.fanouts.clear();
+    }
+
+    let budget = PrivacyBudget {
+        mechanism: "laplace".to_string(),
+        epsilon_per_column: options.epsilon,
+        total_epsilon: options.epsilon * noised_columns as f64,
+        noised_columns,
+        rare_threshold: threshold,
+    };
+
+    info!(
+        epsilon = options.epsilon,
+        total_epsilon = budget.total_epsilon,
+        columns = noised_columns,
+        dropped_categories,
+        "Applied differential privacy to histograms"
+    );
+
+    genome.privacy = Some(budget.clone());
+    Ok(budget)
+}
+
+/// Noises one column in place. Returns the number of dropped categories, or `None`
+/// when the column stores no counts (semantic generators and patterns).
+fn privatize_distribution<R: Rng + ?Sized>(
+    dist: &mut Distribution,
+    epsilon: f64,
+    threshold: f64,
+    rng: &mut R,
+) -> Option<usize> {
+    let scale = 1.0 / epsilon;
+
+    let dropped = match &mut dist.histogram {
+        Histogram::Numeric { frequencies, .. } => {
+            for count in frequencies.iter_mut() {
+                *count = noisy_count(*count, scale, rng);
+            }
+            0
+        }
+        Histogram::Quantile { digest } => {
+            for centroid in digest.centroids.iter_mut() {
+                centroid.weight = (centroid.weight + laplace_noise(scale, rng)).max(0.0);
+            }
+            digest.centroids.retain(|c| c.weight > 0.0);
+            0
+        }
+        Histogram::Categorical { frequencies, long_tail, .. } => {
+            if let Some(tail) = long_tail {
+                tail.count = noisy_count(tail.count, scale, rng);
+            }
+            let before = frequencies.len();
+            frequencies.retain(|_, count| {
+                let noisy = *count as f64 + laplace_noise(scale, rng);
+                *count = noisy.round().max(0.0) as u64;
+                noisy >= threshold
+            });
+            before - frequencies.len()
+        }
+        Histogram::Boolean { bernoulli } => {
+            bernoulli.true_count = noisy_count(bernoulli.true_count, scale, rng);
+            bernoulli.false_count = noisy_count(bernoulli.false_count, scale, rng);
+            0
+        }
+        Histogram::Discrete { values, frequencies } => {
+            // Values are source data like categories: rare ones are dropped
+            let before = values.len();
+            let kept: Vec<(i64, u64)> = values
+                .iter()
+                .zip(frequencies.iter())
+                .filter_map(|(&value, &count)| {
+                    let noisy = count as f64 + laplace_noise(scale, rng);
+                    (noisy >= threshold).then(|| (value, noisy.round().max(0.0) as u64))
+                })
+                .collect();
+            (*values, *frequencies) = kept.into_iter().unzip();
+            before - values.len()
+        }
+        Histogram::Semantic { .. } | Histogram::Pattern { .. } | Histogram::Markov { .. } => return None,
+    };
+
+    // Point-mass values are source values too, released like categories
+    let rows = dist.total_count.saturating_sub(dist.null_count) as f64;
+    let masses = dist.point_masses.len();
+    dist.point_masses.retain_mut(|mass| {
+        let noisy = mass.share * rows + laplace_noise(scale, rng);
+        mass.share = if rows > 0.0 { noisy.max(0.0) / rows } else { 0.0 };
+        noisy >= threshold
+    });
+    let dropped = dropped + masses - dist.point_masses.len();
+
+    dist.null_count = noisy_count(dist.null_count, scale, rng).min(dist.total_count);
+    // Length counts and fitted parameters would be further queries against the same rows
+    dist.lengths = None;
+    dist.pair_counts = None;
+    dist.fit = None;
+    dist.seasonality = None;
+    dist.sequence = None;
+    Some(dropped)
+}
+
+fn noisy_count<R: Rng + ?Sized>(count: u64, scale: f64, rng: &mut R) -> u64 {
+    (count as f64 + laplace_noise(scale, rng)).round().max(0.0) as u64
+}
+
+/// Samples Laplace(0, scale) by inverse transform.
+pub fn laplace_noise<R: Rng + ?Sized>(scale: f64, rng: &mut R) -> f64 {
+    let u: f64 = rng.gen_range(-0.5..0.5);
+    -scale * u.signum() * (1.0 - 2.0 * u.abs()).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn categorical(counts: &[(&str, u64)]) -> Distribution {
+        let frequencies = counts.iter().map(|(k, v)| (k.to_string(), *v)).collect();
+        let total = counts.iter().map(|(_, v)| v).sum();
+        Distribution::new(None, None, 0, total, counts.len(), Histogram::Categorical {
+            frequencies,
+            truncated: false,
+            long_tail: None,
+        })
+    }
+
+    #[test]
+    fn test_laplace_noise_is_centered() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let n = 20_000;
+        let samples: Vec<f64> = (0..n).map(|_| laplace_noise(2.0, &mut rng)).collect();
+
+        let mean = samples.iter().sum::<f64>() / n as f64;
+        let mean_abs = samples.iter().map(|x| x.abs()).sum::<f64>() / n as f64;
+
+        assert!(mean.abs() < 0.1, "mean {}", mean);
+        // E|X| equals the scale for a Laplace distribution
+        assert!((mean_abs - 2.0).abs() < 0.1, "mean |x| {}", mean_abs);
+    }
+
+    #[test]
+    fn test_rare_categories_dropped() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut dist = categorical(&[("common", 5_000), ("once", 1)]);
+
+        let dropped = privatize_distribution(&mut dist, 1.0, 100.0, &mut rng);
+
+        assert_eq!(dropped, Some(1));
+        let Histogram::Categorical { frequencies, .. } = &dist.histogram else {
+            panic!("expected categorical histogram");
+        };
+        assert!(frequencies.contains_key("common"));
+        assert!(!frequencies.contains_key("once"));
+    }
+
+    #[test]
+    fn test_budget_recorded_in_genome() -> anyhow::Result<()> {
+        let mut distributions = HashMap::new();
+        distributions.insert("t.a".to_string(), categorical(&[("x", 10)]));
+        distributions.insert("t.b".to_string(), categorical(&[("y", 10)]));
+        let mut genome = DatabaseGenome::new(vec![], distributions);
+
+        let budget = apply_differential_privacy(&mut genome, &DpOptions::new(0.5), &mut StdRng::seed_from_u64(3))?;
+
+        assert_eq!(budget.noised_columns, 2);
+        assert!((budget.total_epsilon - 1.0).abs() < 1e-12);
+        assert_eq!(genome.privacy, Some(budget));
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_non_positive_epsilon() {
+        let mut genome = DatabaseGenome::new(vec![], HashMap::new());
+        let result = apply_differential_privacy(&mut genome, &DpOptions::new(0.0), &mut rand::thread_rng());
+        assert!(result.is_err());
+    }
+}
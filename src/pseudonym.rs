@@ -0,0 +1,205 @@
+//! Deterministic pseudonyms for categorical text values (`scan --pseudonymize`).
+//!
+//! Each value is replaced by a truncated HMAC-SHA256 of the value under a secret key.
+//! The token depends only on the value and the key, so a value shared by two tables
+//! gets the same token in both, and in every later scan with the same key: joins and
+//! parent-conditioned distributions keep lining up while the genome holds no raw
+//! strings. Without the key the tokens can't be reversed or recomputed.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use anyhow::Result;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tracing::{info, warn};
+use crate::genome::DatabaseGenome;
+use crate::math::{Distribution, Histogram};
+use crate::schema::DataType;
+
+/// Environment variable the command line reads the key from, so it stays out of
+/// shell history and process listings.
+pub const KEY_ENV: &str = "REPLICA_DB_PSEUDONYM_KEY";
+
+/// Bytes of the HMAC kept in a token; 64 bits make collisions negligible for any
+/// realistic number of distinct values.
+const TOKEN_BYTES: usize = 8;
+
+#[derive(Clone)]
+pub struct Pseudonymizer {
+    mac: Hmac<Sha256>,
+}
+
+impl Pseudonymizer {
+    /// Keyed by the contents of [`KEY_ENV`].
+    pub fn from_env() -> Result<Self> {
+        let key = std::env::var(KEY_ENV)
+            .map_err(|_| anyhow::anyhow!("Pseudonymization needs a secret key in {}", KEY_ENV))?;
+        Self::new(key.as_bytes())
+    }
+
+    pub fn new(key: &[u8]) -> Result<Self> {
+        if key.is_empty() {
+            anyhow::bail!("Pseudonymization key must not be empty");
+        }
+        let mac = Hmac::<Sha256>::new_from_slice(key)
+            .map_err(|e| anyhow::anyhow!("Invalid pseudonymization key: {}", e))?;
+        Ok(Self { mac })
+    }
+
+    /// Token for `value`: 16 lowercase hex characters.
+    pub fn token(&self, value: &str) -> String {
+        let mut mac = self.mac.clone();
+        mac.update(value.as_bytes());
+        mac.finalize().into_bytes()[..TOKEN_BYTES]
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// Replaces the categorical values of every text column in `genome` with their
+    /// tokens, including the parent values that key cross-table conditionals.
+    /// Returns the number of distributions rewritten.
+    pub fn apply(&self, genome: &mut DatabaseGenome) -> usize {
+        let text_columns: HashSet<String> = genome
+            .tables
+            .iter()
+            .flat_map(|t| {
+                t.columns
+                    .iter()
+                    .filter(|c| c.data_type == DataType::Text)
+                    .map(|c| DatabaseGenome::make_key(&t.name, &c.name))
+            })
+            .collect();
+
+        let mut rewritten = 0;
+        for (key, dist) in genome.distributions.iter_mut() {
+            if text_columns.contains(key) && self.pseudonymize_distribution(dist) {
+                rewritten += 1;
+            }
+        }
+
+        for (key, conditional) in genome.conditionals.iter_mut() {
+            if text_columns.contains(key) {
+                for dist in conditional.by_parent_value.values_mut() {
+                    self.pseudonymize_distribution(dist);
+                }
+            }
+            let parent = DatabaseGenome::make_key(&conditional.parent_table, &conditional.parent_column);
+            if !text_columns.contains(&parent) {
+                continue;
+            }
+            let groups = std::mem::take(&mut conditional.by_parent_value);
+            let mut by_token = HashMap::with_capacity(groups.len());
+            for (value, dist) in groups {
+                if by_token.insert(self.token(&value), dist).is_some() {
+                    warn!(column = %key, "Two parent values share a pseudonym, keeping one group");
+                }
+            }
+            conditional.by_parent_value = by_token;
+        }
+
+        info!(columns = rewritten, "Pseudonymized categorical values");
+        rewritten
+    }
+
+    /// Rewrites the keys of a categorical histogram, summing counts if two values
+    /// ever share a token. Other histograms hold no raw values and are left alone.
+    fn pseudonymize_distribution(&self, dist: &mut Distribution) -> bool {
+        let Histogram::Categorical { frequencies, .. } = &mut dist.histogram else {
+            return false;
+        };
+        let mut tokens = BTreeMap::new();
+        for (value, count) in std::mem::take(frequencies) {
+            *tokens.entry(self.token(&value)).or_insert(0) += count;
+        }
+        *frequencies = tokens;
+        true
+    }
+}
+
+impl fmt::Debug for Pseudonymizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the key
+        f.write_str("Pseudonymizer { .. }")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conditional::ConditionalDistribution;
+    use crate::schema::{Column, Table};
+
+    fn categorical(counts: &[(&str, u64)]) -> Distribution {
+        let frequencies = counts.iter().map(|(k, v)| (k.to_string(), *v)).collect();
+        let total = counts.iter().map(|(_, v)| v).sum();
+        Distribution::new(None, None, 0, total, counts.len(), Histogram::Categorical {
+            frequencies,
+            truncated: false,
+            long_tail: None,
+        })
+    }
+
+    fn values(dist: &Distribution) -> Vec<&str> {
+        let Histogram::Categorical { frequencies, .. } = &dist.histogram else {
+            panic!("expected categorical histogram");
+        };
+        frequencies.keys().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn test_tokens_are_deterministic_and_keyed() -> Result<()> {
+        let a = Pseudonymizer::new(b"secret")?;
+        let token = a.token("gold");
+        assert_eq!(token.len(), 16);
+        assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(Pseudonymizer::new(b"secret")?.token("gold"), token);
+        assert_ne!(a.token("silver"), token);
+        assert_ne!(Pseudonymizer::new(b"other")?.token("gold"), token);
+        assert!(Pseudonymizer::new(b"").is_err());
+        assert_eq!(format!("{:?}", a), "Pseudonymizer { .. }");
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_keeps_shared_values_joinable() -> Result<()> {
+        let users = Table::new(
+            "users".to_string(),
+            vec![
+                Column::new("tier".to_string(), DataType::Text, false, false),
+                Column::new("age".to_string(), DataType::Integer, false, false),
+            ],
+            vec![],
+        );
+        let orders = Table::new(
+            "orders".to_string(),
+            vec![Column::new("tier".to_string(), DataType::Text, false, false)],
+            vec![],
+        );
+        let mut distributions = HashMap::new();
+        distributions.insert("users.tier".to_string(), categorical(&[("gold", 3), ("silver", 7)]));
+        distributions.insert("users.age".to_string(), categorical(&[("42", 10)]));
+        distributions.insert("orders.tier".to_string(), categorical(&[("gold", 5)]));
+        let mut genome = DatabaseGenome::new(vec![users, orders], distributions);
+        genome.conditionals.insert("orders.tier".to_string(), ConditionalDistribution {
+            foreign_key: "user_id".to_string(),
+            parent_table: "users".to_string(),
+            parent_column: "tier".to_string(),
+            by_parent_value: HashMap::from([("gold".to_string(), categorical(&[("gold", 5)]))]),
+        });
+
+        let pseudonymizer = Pseudonymizer::new(b"secret")?;
+        assert_eq!(pseudonymizer.apply(&mut genome), 2);
+
+        let gold = pseudonymizer.token("gold");
+        assert!(values(&genome.distributions["users.tier"]).contains(&gold.as_str()));
+        assert_eq!(values(&genome.distributions["orders.tier"]), vec![gold.as_str()]);
+        // Only text columns are rewritten
+        assert_eq!(values(&genome.distributions["users.age"]), vec!["42"]);
+
+        let conditional = &genome.conditionals["orders.tier"];
+        assert_eq!(values(conditional.for_parent_value(&gold).unwrap()), vec![gold.as_str()]);
+        assert!(conditional.for_parent_value("gold").is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,473 @@
+//! Fidelity report: re-profile generated data and score it against the genome it
+//! was synthesized from.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io::BufRead;
+use anyhow::{Context, Result};
+use crate::diff::{chi_square_homogeneity_p_value, ks_p_value, ks_statistic};
+use crate::genome::DatabaseGenome;
+use crate::hstore::{format_pair, parse_hstore};
+use crate::interval::parse_interval;
+use crate::math::{Bernoulli, Distribution, DistributionBuilder, Histogram, Reservoir, DEFAULT_RESERVOIR_CAPACITY};
+use crate::network::prefix_bucket;
+use crate::output::unescape_copy_text;
+use crate::schema::{bytea_length, parse_currency, DataType, Table};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FidelityTest {
+    KolmogorovSmirnov,
+    ChiSquare,
+}
+
+impl fmt::Display for FidelityTest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FidelityTest::KolmogorovSmirnov => f.pad("KS"),
+            FidelityTest::ChiSquare => f.pad("chi2"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ColumnScore {
+    pub table: String,
+    pub column: String,
+    pub test: FidelityTest,
+    /// KS D for numeric columns, total variation distance for categoricals.
+    pub statistic: f64,
+    pub p_value: f64,
+    /// 1 - statistic, in [0, 1]; 1 means the generated marginal matches the genome.
+    pub score: f64,
+    pub null_rate: (f64, f64),
+    pub passed: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FidelityReport {
+    pub alpha: f64,
+    pub columns: Vec<ColumnScore>,
+    /// Columns present in the genome but missing from the generated data.
+    pub missing: Vec<String>,
+}
+
+impl FidelityReport {
+    /// Mean per-column score.
+    pub fn overall_score(&self) -> f64 {
+        if self.columns.is_empty() {
+            return 0.0;
+        }
+        self.columns.iter().map(|c| c.score).sum::<f64>() / self.columns.len() as f64
+    }
+
+    pub fn passed(&self) -> usize {
+        self.columns.iter().filter(|c| c.passed).count()
+    }
+}
+
+/// Scores `generated` (keyed `table.column`, like genome distributions) against the
+/// reference genome. Primary and foreign key columns are skipped: they are not sampled
+/// from distributions.
+pub fn fidelity_report(
+    reference: &DatabaseGenome,
+    generated: &HashMap<String, Distribution>,
+    alpha: f64,
+) -> FidelityReport {
+    let mut report = FidelityReport {
+        alpha,
+        ..FidelityReport::default()
+    };
+
+    for table in &reference.tables {
+        for column in &table.columns {
+            if column.is_primary_key || table.foreign_keys.iter().any(|fk| fk.source_col == column.name) {
+                continue;
+            }
+
+            let Some(expected) = reference.get_distribution(&table.name, &column.name) else {
+                continue;
+            };
+
+            let key = DatabaseGenome::make_key(&table.name, &column.name);
+            let Some(actual) = generated.get(&key) else {
+                report.missing.push(key);
+                continue;
+            };
+
+            if let Some(score) = score_column(&table.name, &column.name, expected, actual, alpha) {
+                report.columns.push(score);
+            }
+        }
+    }
+
+    report
+}
+
+fn score_column(
+    table: &str,
+    column: &str,
+    expected: &Distribution,
+    actual: &Distribution,
+    alpha: f64,
+) -> Option<ColumnScore> {
+    let (test, statistic, p_value) = match (&expected.histogram, &actual.histogram) {
+        (Histogram::Categorical { frequencies: fe, .. }, Histogram::Categorical { frequencies: fa, .. }) => {
+            categorical_score(fe, fa)?
+        }
+        (Histogram::Boolean { bernoulli: be }, Histogram::Boolean { bernoulli: ba }) => {
+            categorical_score(&be.frequencies(), &ba.frequencies())?
+        }
+        _ => {
+            // Numeric histograms and quantile sketches
+            let ((points_e, n), (points_a, m)) = (expected.numeric_support()?, actual.numeric_support()?);
+            if n == 0 || m == 0 {
+                return None;
+            }
+            let d = ks_statistic(expected, actual, points_e.into_iter().chain(points_a));
+            (FidelityTest::KolmogorovSmirnov, d, ks_p_value(d, n, m))
+        }
+    };
+
+    Some(ColumnScore {
+        table: table.to_string(),
+        column: column.to_string(),
+        test,
+        statistic,
+        p_value,
+        score: (1.0 - statistic).clamp(0.0, 1.0),
+        null_rate: (expected.null_rate(), actual.null_rate()),
+        passed: p_value >= alpha,
+    })
+}
+
+/// Total variation distance and chi-square p-value between two sets of category counts.
+fn categorical_score(fe: &BTreeMap<String, u64>, fa: &BTreeMap<String, u64>) -> Option<(FidelityTest, f64, f64)> {
+    let total_e: u64 = fe.values().sum();
+    let total_a: u64 = fa.values().sum();
+    if total_e == 0 || total_a == 0 {
+        return None;
+    }
+    let tvd = fe
+        .keys()
+        .chain(fa.keys().filter(|k| !fe.contains_key(*k)))
+        .map(|k| {
+            let pe = fe.get(k).copied().unwrap_or(0) as f64 / total_e as f64;
+            let pa = fa.get(k).copied().unwrap_or(0) as f64 / total_a as f64;
+            (pe - pa).abs()
+        })
+        .sum::<f64>()
+        / 2.0;
+    Some((FidelityTest::ChiSquare, tvd, chi_square_homogeneity_p_value(fe, fa)))
+}
+
+/// Re-profiles `COPY ... FROM stdin` blocks (as written by `gen`) into distributions
+/// keyed `table.column`. Column types come from the genome.
+pub fn profile_copy_output<R: BufRead>(
+    reader: R,
+    genome: &DatabaseGenome,
+) -> Result<HashMap<String, Distribution>> {
+    let mut profiles: HashMap<String, ColumnProfile> = HashMap::new();
+    let mut current: Option<(&Table, Vec<String>)> = None;
+
+    for (line_no, line) in reader.lines().enumerate() {
+        let line = line.context("Failed to read COPY input")?;
+
+        match current.as_ref() {
+            None => {
+                if let Some((table_name, columns)) = parse_copy_header(&line) {
+                    let table = genome
+                        .get_table(&table_name)
+                        .context(format!("Table '{}' in COPY input not found in genome", table_name))?;
+                    current = Some((table, columns));
+                }
+            }
+            Some(_) if line == "\\." => current = None,
+            Some((table, columns)) => {
+                let fields: Vec<&str> = line.split('\t').collect();
+                if fields.len() != columns.len() {
+                    anyhow::bail!(
+                        "Line {}: expected {} fields for table '{}', found {}",
+                        line_no + 1,
+                        columns.len(),
+                        table.name,
+                        fields.len()
+                    );
+                }
+
+                for (col_name, raw) in columns.iter().zip(fields) {
+                    let Some(column) = table.columns.iter().find(|c| &c.name == col_name) else {
+                        continue;
+                    };
+                    profiles
+                        .entry(DatabaseGenome::make_key(&table.name, col_name))
+                        .or_insert_with(|| ColumnProfile::new(column.data_type.clone()))
+                        .add(raw);
+                }
+            }
+        }
+    }
+
+    Ok(profiles
+        .into_iter()
+        .map(|(key, profile)| (key, profile.build()))
+        .collect())
+}
+
+fn parse_copy_header(line: &str) -> Option<(String, Vec<String>)> {
+    let rest = line.strip_prefix("COPY ")?.strip_suffix(" FROM stdin;")?;
+    let open = rest.find('(')?;
+    let table = rest[..open].trim().to_string();
+    let columns = rest[open + 1..]
+        .trim_end_matches(')')
+        .split(',')
+        .map(|c| c.trim().to_string())
+        .collect();
+    Some((table, columns))
+}
+
+struct ColumnProfile {
+    data_type: DataType,
+    total: u64,
+    nulls: u64,
+    numeric: Reservoir<f64>,
+    text: Reservoir<String>,
+    bernoulli: Bernoulli,
+}
+
+impl ColumnProfile {
+    fn new(data_type: DataType) -> Self {
+        Self {
+            data_type,
+            total: 0,
+            nulls: 0,
+            numeric: Reservoir::new(DEFAULT_RESERVOIR_CAPACITY),
+            text: Reservoir::new(DEFAULT_RESERVOIR_CAPACITY),
+            bernoulli: Bernoulli::default(),
+        }
+    }
+
+    fn add(&mut self, raw: &str) {
+        self.total += 1;
+        if raw == "\\N" {
+            self.nulls += 1;
+            return;
+        }
+
+        match self.data_type {
+            DataType::Integer => match raw.parse::<f64>() {
+                Ok(v) => self.numeric.add(v),
+                Err(_) => self.nulls += 1,
+            },
+            DataType::Float => match raw.parse::<f64>().ok().or_else(|| parse_currency(raw)) {
+                Some(v) => self.numeric.add(v),
+                None => self.nulls += 1,
+            },
+            DataType::Timestamp => match parse_timestamp_epoch(raw) {
+                Some(v) => self.numeric.add(v),
+                None => self.nulls += 1,
+            },
+            // The genome holds lengths, not bytes
+            DataType::Bytea => self.numeric.add(bytea_length(&unescape_copy_text(raw)) as f64),
+            DataType::Interval => match parse_interval(raw) {
+                Some(v) => self.numeric.add(v),
+                None => self.nulls += 1,
+            },
+            // Nor addresses, only their prefixes
+            DataType::Inet | DataType::Cidr | DataType::MacAddr => match prefix_bucket(&self.data_type, raw) {
+                Some(prefix) => self.text.add(prefix),
+                None => self.nulls += 1,
+            },
+            // Profiled pair by pair, like the scan
+            DataType::Hstore => match parse_hstore(&unescape_copy_text(raw)) {
+                Some(pairs) => {
+                    for (key, value) in pairs {
+                        self.text.add(format_pair(&key, value.as_deref()));
+                    }
+                }
+                None => self.nulls += 1,
+            },
+            // Generated points only fill the boxes; there are no boxes to compare
+            DataType::Geometry | DataType::Geography => {}
+            DataType::Boolean => match raw.to_ascii_lowercase().as_str() {
+                "t" | "true" => self.bernoulli.add(true),
+                "f" | "false" => self.bernoulli.add(false),
+                _ => self.nulls += 1,
+            },
+            DataType::Text | DataType::Uuid => {
+                self.text.add(unescape_copy_text(raw));
+            }
+        }
+    }
+
+    fn build(self) -> Distribution {
+        if self.data_type == DataType::Boolean {
+            let unique_count = (self.bernoulli.true_count > 0) as usize + (self.bernoulli.false_count > 0) as usize;
+            return Distribution::new(None, None, self.nulls, self.total, unique_count, Histogram::Boolean {
+                bernoulli: self.bernoulli,
+            });
+        }
+
+        let mut builder = DistributionBuilder::new(self.total, self.nulls);
+        for &v in self.numeric.sample() {
+            builder.add_numeric(v);
+        }
+        for v in self.text.sample() {
+            builder.add_categorical(v.clone());
+        }
+        builder.build()
+    }
+}
+
+/// Accepts raw epoch seconds or common Postgres timestamp/date text forms.
+fn parse_timestamp_epoch(raw: &str) -> Option<f64> {
+    if let Ok(v) = raw.parse::<f64>() {
+        return Some(v);
+    }
+    if let Ok(ts) = chrono::DateTime::parse_from_rfc3339(raw) {
+        return Some(ts.timestamp() as f64);
+    }
+    for format in ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"] {
+        if let Ok(ts) = chrono::NaiveDateTime::parse_from_str(raw, format) {
+            return Some(ts.and_utc().timestamp() as f64);
+        }
+    }
+    chrono::NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|ts| ts.and_utc().timestamp() as f64)
+}
+
+impl fmt::Display for FidelityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<40} {:>5} {:>8} {:>10} {:>6} {:>15}  result",
+            "column", "test", "stat", "p-value", "score", "null% (g -> s)"
+        )?;
+
+        for col in &self.columns {
+            writeln!(
+                f,
+                "{:<40} {:>5} {:>8.4} {:>10.2e} {:>6.3} {:>6.2} -> {:>6.2}  {}",
+                format!("{}.{}", col.table, col.column),
+                col.test,
+                col.statistic,
+                col.p_value,
+                col.score,
+                col.null_rate.0 * 100.0,
+                col.null_rate.1 * 100.0,
+                if col.passed { "PASS" } else { "FAIL" }
+            )?;
+        }
+
+        for key in &self.missing {
+            writeln!(f, "{:<40} missing from generated data", key)?;
+        }
+
+        writeln!(f)?;
+        writeln!(
+            f,
+            "Overall fidelity: {:.3} ({}/{} columns pass at alpha = {})",
+            self.overall_score(),
+            self.passed(),
+            self.columns.len(),
+            self.alpha
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::BinScale;
+    use crate::schema::Column;
+
+    fn genome() -> DatabaseGenome {
+        let tables = vec![Table::new(
+            "users".to_string(),
+            vec![
+                Column::new("id".to_string(), DataType::Integer, false, true),
+                Column::new("age".to_string(), DataType::Integer, true, false),
+                Column::new("status".to_string(), DataType::Text, false, false),
+            ],
+            vec![],
+        )];
+
+        let mut distributions = HashMap::new();
+        distributions.insert(
+            DatabaseGenome::make_key("users", "age"),
+            Distribution::new(
+                Some(0.0),
+                Some(100.0),
+                0,
+                1000,
+                100,
+                Histogram::Numeric { bins: vec![0.0, 50.0, 100.0], frequencies: vec![500, 500], scale: BinScale::Linear },
+            ),
+        );
+        distributions.insert(
+            DatabaseGenome::make_key("users", "status"),
+            Distribution::new(
+                None,
+                None,
+                0,
+                1000,
+                2,
+                Histogram::Categorical {
+                    frequencies: [("active".to_string(), 500), ("closed".to_string(), 500)].into_iter().collect(),
+                    truncated: false,
+                    long_tail: None,
+                },
+            ),
+        );
+        DatabaseGenome::new(tables, distributions)
+    }
+
+    #[test]
+    fn test_parse_copy_header() {
+        let (table, columns) = parse_copy_header("COPY users (id, age, status) FROM stdin;").unwrap();
+        assert_eq!(table, "users");
+        assert_eq!(columns, vec!["id", "age", "status"]);
+        assert!(parse_copy_header("1\t2\t3").is_none());
+    }
+
+    #[test]
+    fn test_profile_copy_output() -> Result<()> {
+        let input = "COPY users (id, age, status) FROM stdin;\n1\t10\tactive\n2\t\\N\tclosed\n\\.\n\n";
+        let profiles = profile_copy_output(input.as_bytes(), &genome())?;
+
+        let age = &profiles["users.age"];
+        assert_eq!(age.total_count, 2);
+        assert_eq!(age.null_count, 1);
+        assert_eq!(profiles["users.status"].unique_count, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_matching_data_scores_high() -> Result<()> {
+        let mut input = String::from("COPY users (id, age, status) FROM stdin;\n");
+        for i in 0..1000 {
+            let status = if i % 2 == 0 { "active" } else { "closed" };
+            input.push_str(&format!("{}\t{}\t{}\n", i, i % 100, status));
+        }
+        input.push_str("\\.\n");
+
+        let reference = genome();
+        let profiles = profile_copy_output(input.as_bytes(), &reference)?;
+        let report = fidelity_report(&reference, &profiles, 0.01);
+
+        assert_eq!(report.columns.len(), 2);
+        assert_eq!(report.passed(), 2);
+        assert!(report.overall_score() > 0.9);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_timestamp_epoch() {
+        assert_eq!(parse_timestamp_epoch("0"), Some(0.0));
+        assert_eq!(parse_timestamp_epoch("1970-01-02 00:00:00"), Some(86400.0));
+        assert_eq!(parse_timestamp_epoch("1970-01-02"), Some(86400.0));
+        assert_eq!(parse_timestamp_epoch("1970-01-02 00:00:00+00:00"), Some(86400.0));
+        assert_eq!(parse_timestamp_epoch("garbage"), None);
+    }
+}
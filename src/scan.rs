@@ -0,0 +1,1008 @@
+//! Scan orchestration: introspect the schema, profile every table in parallel and
+//! assemble the result into a [`DatabaseGenome`].
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::{Context, Result};
+use futures_util::stream::FuturesUnordered;
+use futures_util::StreamExt;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use sqlx::PgPool;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, info, warn};
+use crate::anonymize::{anonymize_genome, FormatPreserving};
+use crate::checkpoint::ScanCheckpoint;
+use crate::config::validate_predicate;
+use crate::concurrency::{order_largest_first, sample_load, ConcurrencyTuner, LOAD_SAMPLE_INTERVAL};
+use crate::conditional::{conditioned_columns, conditioning_plan, ConditionalDistribution};
+use crate::copula::{CopulaKind, CorrelationMethod, CovarianceMatrix};
+use crate::genome::{DatabaseGenome, GenomeWarning};
+use crate::math::{BinningStrategy, Distribution, Recency, DEFAULT_RESERVOIR_CAPACITY, NUMERIC_HISTOGRAM_BINS};
+use crate::metrics::{RunMetrics, TableMetrics};
+use crate::pg_stats::profile_from_stats;
+use crate::postgres::{introspect, ExportedSnapshot, InheritanceMode, IntrospectOptions};
+use crate::privacy::{apply_differential_privacy, DpOptions};
+use crate::pseudonym::Pseudonymizer;
+use crate::scanner::{profile_columns, profile_conditionals, profile_fanout, profile_tenant_counts, ProfileOptions, RetryPolicy, ScanProgress};
+use crate::schema::Table;
+use crate::subset::FanOut;
+use crate::tenant::TenantProfile;
+
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// Concurrent table profiling tasks, or the starting point when `max_connections`
+    /// adapts them.
+    pub parallel_jobs: usize,
+
+    /// Profile every table against one exported snapshot.
+    pub consistent_snapshot: bool,
+
+    pub retry: RetryPolicy,
+
+    /// Where finished tables are checkpointed. `None` disables checkpointing.
+    pub checkpoint_path: Option<PathBuf>,
+
+    /// Reuse tables found in an existing checkpoint instead of profiling them again.
+    pub resume: bool,
+
+    /// Recorded in the genome metadata.
+    pub source_database: Option<String>,
+
+    /// Draw indicatif progress bars on stderr.
+    pub show_progress: bool,
+
+    /// Store faker-style generators instead of real values for PII columns.
+    pub redact_pii: bool,
+
+    /// Model free-form text columns with Markov chains instead of categorical replay.
+    pub markov_text: bool,
+
+    /// Fit normal/lognormal/exponential/uniform distributions to numeric columns.
+    pub fit_distributions: bool,
+
+    /// Use t-digest quantile sketches for numeric columns.
+    pub quantile_sketch: bool,
+
+    /// Pearson or Spearman (rank) correlation for the copula.
+    pub correlation: CorrelationMethod,
+
+    /// Join children to parents and learn child distributions per parent attribute value.
+    pub fk_conditioning: bool,
+
+    /// Profile inheriting tables separately or merged into their parents.
+    pub inheritance: InheritanceMode,
+
+    /// Profile foreign tables alongside regular ones.
+    pub foreign_tables: bool,
+
+    /// Placement of numeric bin edges.
+    pub binning: BinningStrategy,
+
+    /// Bins per numeric histogram (the most, for Freedman-Diaconis).
+    pub bins: usize,
+
+    /// Share of numeric values clipped in each tail before binning (0.001 clamps to
+    /// p0.1-p99.9).
+    pub winsorize: Option<f64>,
+
+    /// Fold text categories seen fewer than this many times into the generated tail.
+    pub min_category_count: Option<u64>,
+
+    /// Release histograms under differential privacy.
+    pub dp: Option<DpOptions>,
+
+    /// Format-preserving anonymization, keyed `table.column`; scan-stage entries are
+    /// applied before the genome is built.
+    pub anonymized_columns: HashMap<String, FormatPreserving>,
+
+    /// Replace categorical text values with keyed pseudonyms before the genome is built.
+    pub pseudonymizer: Option<Pseudonymizer>,
+
+    /// Per-table SQL predicates; only matching rows are profiled.
+    pub row_filters: HashMap<String, String>,
+
+    /// Server-side pause after every [`THROTTLE_INTERVAL`](crate::scanner::THROTTLE_INTERVAL)
+    /// profiled rows.
+    pub throttle: Option<Duration>,
+
+    /// Leave out tables whose estimated size exceeds this many bytes.
+    pub skip_larger_than: Option<u64>,
+
+    /// Read tables through a server-side cursor in batches of this many rows.
+    pub fetch_size: Option<usize>,
+
+    /// Collects per-table scan metrics.
+    pub metrics: Option<Arc<RunMetrics>>,
+
+    /// Receives per-table progress, e.g. the `--tui` dashboard.
+    pub progress: Option<Arc<dyn ScanProgress>>,
+
+    /// Build distributions from the planner statistics in `pg_stats` instead of
+    /// reading rows: seconds instead of hours, without correlations.
+    pub fast: bool,
+
+    /// Tables a fast scan still streams and profiles in full, correlations included.
+    pub deep_tables: HashSet<String>,
+
+    /// Values sampled per column.
+    pub reservoir_capacity: usize,
+
+    /// Per-table (`table`) and per-column (`table.column`) overrides of
+    /// `reservoir_capacity`.
+    pub reservoir_capacities: HashMap<String, usize>,
+
+    /// Tables whose samples favor recent rows, keyed by table name.
+    pub recency: HashMap<String, Recency>,
+
+    /// Copula per table, keyed by table name.
+    pub copulas: HashMap<String, CopulaKind>,
+
+    /// Blocking tasks the columns of a wide table are profiled on.
+    pub column_workers: Option<usize>,
+
+    /// Adjust the number of tables profiled at once to database load, starting from
+    /// `parallel_jobs` and never above this many connections. `None` keeps
+    /// `parallel_jobs` fixed.
+    pub max_connections: Option<usize>,
+
+    /// Column naming the tenant in multi-tenant schemas; every table holding it gets
+    /// its rows counted per tenant into the genome's [`TenantProfile`].
+    pub tenant_column: Option<String>,
+
+    /// Count children per parent row through every foreign key into the genome's
+    /// fan-outs, which `gen --root` sizes referential slices from.
+    pub fanout: bool,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            parallel_jobs: 10,
+            consistent_snapshot: false,
+            retry: RetryPolicy::default(),
+            checkpoint_path: None,
+            resume: false,
+            source_database: None,
+            show_progress: false,
+            redact_pii: false,
+            markov_text: false,
+            fit_distributions: false,
+            quantile_sketch: false,
+            correlation: CorrelationMethod::Pearson,
+            fk_conditioning: false,
+            inheritance: InheritanceMode::Separate,
+            foreign_tables: false,
+            binning: BinningStrategy::EqualWidth,
+            bins: NUMERIC_HISTOGRAM_BINS,
+            winsorize: None,
+            min_category_count: None,
+            dp: None,
+            anonymized_columns: HashMap::new(),
+            pseudonymizer: None,
+            row_filters: HashMap::new(),
+            throttle: None,
+            skip_larger_than: None,
+            fetch_size: None,
+            metrics: None,
+            progress: None,
+            fast: false,
+            deep_tables: HashSet::new(),
+            reservoir_capacity: DEFAULT_RESERVOIR_CAPACITY,
+            reservoir_capacities: HashMap::new(),
+            recency: HashMap::new(),
+            copulas: HashMap::new(),
+            column_workers: None,
+            max_connections: None,
+            tenant_column: None,
+            fanout: false,
+        }
+    }
+}
+
+/// Scans the database behind `pool` and returns a validated genome.
+///
+/// The checkpoint file (if any) is left in place; remove it with
+/// [`ScanCheckpoint::remove`] once the genome has been persisted.
+pub async fn scan(pool: &PgPool, options: &ScanOptions) -> Result<DatabaseGenome> {
+    for (table, predicate) in &options.row_filters {
+        validate_predicate(predicate).context(format!("Invalid row filter for table '{}'", table))?;
+    }
+
+    let multi_progress = if options.show_progress {
+        MultiProgress::new()
+    } else {
+        MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+    };
+
+    let introspect_spinner = multi_progress.add(ProgressBar::new_spinner());
+    introspect_spinner.set_style(
+        ProgressStyle::default_spinner()
+            .template("{spinner:.green} {msg}")
+            .context("Invalid spinner template")?,
+    );
+    introspect_spinner.set_message("Introspecting schema...");
+    introspect_spinner.enable_steady_tick(Duration::from_millis(100));
+
+    let introspect_options = IntrospectOptions {
+        inheritance: options.inheritance,
+        foreign_tables: options.foreign_tables,
+    };
+    let mut tables = introspect(pool, &introspect_options)
+        .await
+        .context("Failed to introspect database schema")?;
+
+    let skipped = match options.skip_larger_than {
+        Some(limit) => skip_large_tables(&mut tables, limit),
+        None => Vec::new(),
+    };
+
+    introspect_spinner.finish_with_message(format!(
+        "✓ Discovered {} tables{}",
+        tables.len(),
+        describe_estimates(&tables, options.parallel_jobs)
+    ));
+
+    for table in options.row_filters.keys() {
+        if !tables.iter().any(|t| &t.name == table) {
+            warn!(table = %table, "Row filter names a table that was not found");
+        }
+    }
+
+    // A fast scan streams only the deep tables and reads the rest from pg_stats
+    let (deep, shallow): (Vec<Table>, Vec<Table>) = if options.fast {
+        for table in &options.deep_tables {
+            if !tables.iter().any(|t| &t.name == table) {
+                warn!(table = %table, "Deep-profiled table was not found");
+            }
+        }
+        tables.iter().cloned().partition(|t| options.deep_tables.contains(&t.name))
+    } else {
+        (tables.clone(), Vec::new())
+    };
+    let (statistics, statistics_warnings) = if shallow.is_empty() {
+        (HashMap::new(), Vec::new())
+    } else {
+        profile_statistics(pool, &shallow, options).await?
+    };
+
+    let checkpoint = load_checkpoint(options)?;
+
+    let snapshot = if options.consistent_snapshot {
+        let snapshot = ExportedSnapshot::export(pool)
+            .await
+            .context("Failed to export snapshot for consistent scanning")?;
+        info!(snapshot = %snapshot.id(), "Pinned all profiling queries to snapshot");
+        if !checkpoint.tables.is_empty() {
+            info!("Tables restored from checkpoint were read under an earlier snapshot");
+        }
+        Some(snapshot)
+    } else {
+        None
+    };
+
+    let profile_options = ProfileOptions {
+        snapshot: snapshot.as_ref().map(|s| s.id().to_string()),
+        retry: options.retry.clone(),
+        redact_pii: options.redact_pii,
+        markov_text: options.markov_text,
+        fit_distributions: options.fit_distributions,
+        binning: options.binning,
+        bins: Some(options.bins),
+        winsorize: options.winsorize,
+        quantile_sketch: options.quantile_sketch,
+        correlation: options.correlation,
+        row_filters: options.row_filters.clone(),
+        throttle: options.throttle,
+        fetch_size: options.fetch_size,
+        metrics: options.metrics.clone(),
+        min_category_count: options.min_category_count,
+        progress: options.progress.clone(),
+        reservoir_capacity: Some(options.reservoir_capacity),
+        reservoir_capacities: options.reservoir_capacities.clone(),
+        recency: options.recency.clone(),
+        copulas: options.copulas.clone(),
+        column_workers: options.column_workers,
+    };
+
+    let profile_result = profile_tables_parallel(
+        pool,
+        &deep,
+        match options.max_connections {
+            Some(budget) => ConcurrencyTuner::adaptive(options.parallel_jobs, budget),
+            None => ConcurrencyTuner::fixed(options.parallel_jobs),
+        },
+        &multi_progress,
+        &profile_options,
+        checkpoint,
+        options.checkpoint_path.as_ref(),
+    )
+        .await
+        .map(|(mut distributions, correlations)| {
+            distributions.extend(statistics);
+            (distributions, correlations)
+        });
+
+    // Runs before the snapshot is released so the joins see the same data
+    let conditional_result = match &profile_result {
+        Ok((distributions, _)) if options.fk_conditioning => {
+            Some(profile_fk_conditionals(pool, &deep, &tables, distributions, &profile_options).await)
+        }
+        _ => None,
+    };
+
+    let tenant_result = match (&profile_result, &options.tenant_column) {
+        (Ok(_), Some(column)) => Some(profile_tenants(pool, &deep, &shallow, column, &profile_options).await),
+        _ => None,
+    };
+
+    let fanout_result = match &profile_result {
+        Ok(_) if options.fanout => Some(profile_fanouts(pool, &deep, &tables, &profile_options).await),
+        _ => None,
+    };
+
+    if let Some(snapshot) = snapshot {
+        snapshot.release().await?;
+    }
+
+    let (all_distributions, all_correlations) = profile_result
+        .context("Failed to profile tables")?;
+    let conditionals = conditional_result
+        .transpose()
+        .context("Failed to profile cross-table conditionals")?
+        .unwrap_or_default();
+
+    let mut genome = DatabaseGenome::with_correlations(
+        tables,
+        all_distributions,
+        all_correlations,
+        options.source_database.clone(),
+    );
+    genome.conditionals = conditionals;
+    genome.tenants = tenant_result
+        .transpose()
+        .context("Failed to count rows per tenant")?
+        .flatten();
+    genome.fanouts = fanout_result
+        .transpose()
+        .context("Failed to profile foreign key fan-out")?
+        .unwrap_or_default();
+    if options.fast {
+        genome.fill_missing_distributions();
+    }
+
+    for warning in statistics_warnings {
+        genome.warn(warning);
+    }
+    for table in &skipped {
+        genome.warn(GenomeWarning::table(table, "not scanned, larger than the --skip-tables-larger-than limit; foreign keys to it were dropped"));
+    }
+
+    anonymize_genome(&mut genome, &options.anonymized_columns, &mut rand::thread_rng())
+        .context("Failed to anonymize columns")?;
+
+    if let Some(pseudonymizer) = &options.pseudonymizer {
+        pseudonymizer.apply(&mut genome);
+    }
+
+    if let Some(dp) = &options.dp {
+        apply_differential_privacy(&mut genome, dp, &mut rand::thread_rng())
+            .context("Failed to apply differential privacy")?;
+    }
+
+    genome.collect_warnings(|_| true);
+
+    genome
+        .validate()
+        .context("Genome validation failed")?;
+
+    Ok(genome)
+}
+
+/// Distributions of `tables` built from `pg_stats`, and warnings for the columns
+/// without statistics.
+async fn profile_statistics(
+    pool: &PgPool,
+    tables: &[Table],
+    options: &ScanOptions,
+) -> Result<(HashMap<String, Distribution>, Vec<GenomeWarning>)> {
+    for table in options.row_filters.keys() {
+        if tables.iter().any(|t| &t.name == table) {
+            warn!(table = %table, "Row filters need the rows and are ignored for tables profiled from pg_stats");
+        }
+    }
+    for table in options.recency.keys() {
+        if tables.iter().any(|t| &t.name == table) {
+            warn!(table = %table, "Recency weighting needs the rows and is ignored for tables profiled from pg_stats");
+        }
+    }
+    for (table, _) in options.copulas.iter().filter(|(_, kind)| **kind == CopulaKind::Empirical) {
+        if tables.iter().any(|t| &t.name == table) {
+            warn!(table = %table, "The empirical copula needs the rows and is ignored for tables profiled from pg_stats");
+        }
+    }
+    if options.fk_conditioning {
+        warn!("Foreign key conditioning needs the rows; tables profiled from pg_stats are not conditioned");
+    }
+
+    let profile_options = ProfileOptions {
+        redact_pii: options.redact_pii,
+        markov_text: options.markov_text,
+        fit_distributions: options.fit_distributions,
+        binning: options.binning,
+        bins: Some(options.bins),
+        winsorize: options.winsorize,
+        quantile_sketch: options.quantile_sketch,
+        min_category_count: options.min_category_count,
+        ..ProfileOptions::default()
+    };
+    let (distributions, missing) = profile_from_stats(pool, tables, &profile_options)
+        .await
+        .context("Failed to profile planner statistics")?;
+
+    let mut warnings = Vec::new();
+    for table in tables {
+        let unprofiled: Vec<&str> = missing.iter().filter(|(t, _)| *t == table.name).map(|(_, c)| c.as_str()).collect();
+        if !unprofiled.is_empty() && unprofiled.len() == table.columns.len() {
+            warnings.push(GenomeWarning::table(&table.name, "no planner statistics (run ANALYZE), its columns use placeholder distributions"));
+        } else {
+            for column in unprofiled {
+                warnings.push(GenomeWarning::column(&table.name, column, "no planner statistics, uses a placeholder distribution"));
+            }
+        }
+    }
+    Ok((distributions, warnings))
+}
+
+/// Profiles, for every foreign key of `children` with a usable parent attribute among
+/// `tables`, the child's columns grouped by that attribute. Keyed `table.column`; a column conditioned through two
+/// foreign keys keeps the first.
+async fn profile_fk_conditionals(
+    pool: &PgPool,
+    children: &[Table],
+    tables: &[Table],
+    distributions: &HashMap<String, Distribution>,
+    options: &ProfileOptions,
+) -> Result<HashMap<String, ConditionalDistribution>> {
+    let mut conditionals = HashMap::new();
+
+    for child in children {
+        let columns = conditioned_columns(child, distributions);
+        for (fk, parent_column) in conditioning_plan(child, tables, distributions) {
+            let profiled = profile_conditionals(pool, child, &columns, fk, parent_column, options)
+                .await
+                .context(format!("Failed to profile '{}' by '{}.{}'", child.name, fk.target_table, parent_column.name))?;
+
+            for (column, conditional) in profiled {
+                conditionals
+                    .entry(DatabaseGenome::make_key(&child.name, &column))
+                    .or_insert(conditional);
+            }
+        }
+    }
+
+    Ok(conditionals)
+}
+
+/// Counts the rows per tenant of every table in `tables` holding the tenant `column`
+/// outside its primary key. `None` when no table holds it. Tables read from
+/// `pg_stats` have no rows to count and are left out.
+async fn profile_tenants(
+    pool: &PgPool,
+    tables: &[Table],
+    shallow: &[Table],
+    column: &str,
+    options: &ProfileOptions,
+) -> Result<Option<TenantProfile>> {
+    let holds_column = |table: &&Table| table.columns.iter().any(|c| c.name == column && !c.is_primary_key);
+    for table in shallow.iter().filter(holds_column) {
+        warn!(table = %table.name, "Tenant counts need the rows and are skipped for tables profiled from pg_stats");
+    }
+
+    let mut counts = Vec::new();
+    for table in tables.iter().filter(holds_column) {
+        let per_tenant = profile_tenant_counts(pool, table, column, options).await?;
+        counts.push((table.name.clone(), per_tenant));
+    }
+    if counts.is_empty() {
+        warn!(column = %column, "No scanned table holds the tenant column");
+        return Ok(None);
+    }
+
+    let profile = TenantProfile::from_counts(column, counts, &mut rand::thread_rng());
+    info!(
+        column = %column,
+        tables = profile.tables.len(),
+        tenants = profile.source_tenants,
+        "Counted rows per tenant"
+    );
+    Ok(Some(profile))
+}
+
+/// Children per parent row through every foreign key of `children` whose parent is
+/// among `tables`, keyed `table.column`. Self-references are left out, as are tables
+/// read from `pg_stats`, which aren't passed in.
+async fn profile_fanouts(
+    pool: &PgPool,
+    children: &[Table],
+    tables: &[Table],
+    options: &ProfileOptions,
+) -> Result<HashMap<String, FanOut>> {
+    let mut fanouts = HashMap::new();
+    for child in children {
+        for fk in child.foreign_keys.iter().filter(|fk| fk.target_table != child.name) {
+            let Some(parent) = tables.iter().find(|t| t.name == fk.target_table) else {
+                continue;
+            };
+            let fanout = profile_fanout(pool, child, fk, parent, options)
+                .await
+                .context(format!("Failed to profile fan-out of '{}.{}'", child.name, fk.source_col))?;
+            debug!(table = %child.name, column = %fk.source_col, mean = fanout.mean(), "Profiled fan-out");
+            fanouts.insert(DatabaseGenome::make_key(&child.name, &fk.source_col), fanout);
+        }
+    }
+    info!(foreign_keys = fanouts.len(), "Profiled foreign key fan-out");
+    Ok(fanouts)
+}
+
+/// Rough profiling throughput of one table task, for the up-front time estimate.
+const ESTIMATED_ROWS_PER_SECOND: f64 = 250_000.0;
+
+/// A table's spinner while it waits and once it is done.
+const TABLE_TEMPLATE: &str = "{spinner:.cyan} {prefix:>20} {msg}";
+
+fn table_style(template: &str) -> ProgressStyle {
+    ProgressStyle::default_spinner().template(template).unwrap_or_else(|_| ProgressStyle::default_spinner())
+}
+
+/// Turns each table's spinner into a row counter while it is read: a bar against the
+/// planner's row estimate when there is one, a plain count otherwise. Both show
+/// throughput.
+#[derive(Debug)]
+struct TableBars {
+    bars: HashMap<String, (ProgressBar, Option<u64>)>,
+    forward: Option<Arc<dyn ScanProgress>>,
+}
+
+impl ScanProgress for TableBars {
+    fn table_queued(&self, table: &str, estimated_rows: Option<u64>) {
+        if let Some(forward) = &self.forward {
+            forward.table_queued(table, estimated_rows);
+        }
+    }
+
+    fn table_started(&self, table: &str) {
+        if let Some((pb, estimate)) = self.bars.get(table) {
+            let template = match estimate {
+                Some(rows) => {
+                    pb.set_length(*rows);
+                    "{spinner:.cyan} {prefix:>20} [{bar:25.cyan/blue}] {human_pos}/~{human_len} rows ({per_sec}, eta {eta})"
+                }
+                None => "{spinner:.cyan} {prefix:>20} {human_pos} rows ({per_sec})",
+            };
+            pb.set_style(table_style(template));
+            pb.set_position(0);
+            pb.reset_eta();
+        }
+        if let Some(forward) = &self.forward {
+            forward.table_started(table);
+        }
+    }
+
+    fn rows_scanned(&self, table: &str, rows: u64) {
+        if let Some((pb, _)) = self.bars.get(table) {
+            // The estimate is only the planner's guess
+            if pb.length().is_some_and(|length| rows > length) {
+                pb.set_length(rows);
+            }
+            pb.set_position(rows);
+        }
+        if let Some(forward) = &self.forward {
+            forward.rows_scanned(table, rows);
+        }
+    }
+
+    fn table_finished(&self, table: &str, metrics: &TableMetrics) {
+        if let Some((pb, _)) = self.bars.get(table) {
+            pb.set_style(table_style(TABLE_TEMPLATE));
+            pb.set_position(metrics.rows);
+        }
+        if let Some(forward) = &self.forward {
+            forward.table_finished(table, metrics);
+        }
+    }
+
+    fn table_failed(&self, table: &str, error: &str) {
+        if let Some((pb, _)) = self.bars.get(table) {
+            pb.set_style(table_style(TABLE_TEMPLATE));
+        }
+        if let Some(forward) = &self.forward {
+            forward.table_failed(table, error);
+        }
+    }
+}
+
+/// Removes tables estimated larger than `limit` bytes, along with the foreign keys
+/// pointing at them; those columns are then generated from their own distributions.
+/// Returns the names of the removed tables.
+fn skip_large_tables(tables: &mut Vec<Table>, limit: u64) -> Vec<String> {
+    let skipped: Vec<String> = tables
+        .iter()
+        .filter(|t| t.estimate.is_some_and(|e| e.bytes > limit))
+        .map(|t| t.name.clone())
+        .collect();
+    if skipped.is_empty() {
+        return skipped;
+    }
+
+    for table in tables.iter().filter(|t| skipped.contains(&t.name)) {
+        let bytes = table.estimate.map(|e| e.bytes).unwrap_or_default();
+        warn!(table = %table.name, size = %format_bytes(bytes), limit = %format_bytes(limit), "Skipping table larger than the size limit");
+    }
+    tables.retain(|t| !skipped.contains(&t.name));
+
+    for table in tables.iter_mut() {
+        for fk in table.foreign_keys.iter().filter(|fk| skipped.contains(&fk.target_table)) {
+            warn!(
+                table = %table.name,
+                column = %fk.source_col,
+                parent = %fk.target_table,
+                "Dropping foreign key to a skipped table"
+            );
+        }
+        table.foreign_keys.retain(|fk| !skipped.contains(&fk.target_table));
+    }
+
+    skipped
+}
+
+/// Time to profile `tables` with `parallel_jobs` tasks: the work spread over the
+/// tasks, but never less than the largest table alone.
+fn estimate_scan_time(tables: &[Table], parallel_jobs: usize) -> Option<Duration> {
+    let rows: Vec<u64> = tables.iter().filter_map(|t| t.estimate.and_then(|e| e.rows)).collect();
+    if rows.is_empty() {
+        return None;
+    }
+
+    let workers = parallel_jobs.clamp(1, rows.len()) as f64;
+    let total = rows.iter().sum::<u64>() as f64 / workers;
+    let largest = rows.iter().copied().max().unwrap_or(0) as f64;
+    Some(Duration::from_secs_f64(total.max(largest) / ESTIMATED_ROWS_PER_SECOND))
+}
+
+/// `", ~N rows, SIZE, scan ~T"` for the discovered tables, or nothing without estimates.
+fn describe_estimates(tables: &[Table], parallel_jobs: usize) -> String {
+    let estimates: Vec<_> = tables.iter().filter_map(|t| t.estimate).collect();
+    if estimates.is_empty() {
+        return String::new();
+    }
+
+    let rows: u64 = estimates.iter().filter_map(|e| e.rows).sum();
+    let bytes: u64 = estimates.iter().map(|e| e.bytes).sum();
+    let mut description = format!(", ~{} rows, {}", rows, format_bytes(bytes));
+    if let Some(time) = estimate_scan_time(tables, parallel_jobs) {
+        description.push_str(&format!(", scan ~{}", format_duration(time)));
+    }
+    description
+}
+
+fn format_estimate(estimate: &crate::schema::TableEstimate) -> String {
+    match estimate.rows {
+        Some(rows) => format!("~{} rows, {}", rows, format_bytes(estimate.bytes)),
+        None => format_bytes(estimate.bytes),
+    }
+}
+
+/// Binary units, as `pg_size_pretty` prints them.
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["bytes", "kB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} bytes", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs().max(1);
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+fn load_checkpoint(options: &ScanOptions) -> Result<ScanCheckpoint> {
+    let fresh = || ScanCheckpoint::new(options.source_database.clone());
+
+    let Some(path) = options.checkpoint_path.as_ref().filter(|_| options.resume) else {
+        return Ok(fresh());
+    };
+
+    match ScanCheckpoint::load(path).context("Failed to load scan checkpoint")? {
+        Some(cp) if cp.source_database == options.source_database => {
+            info!(
+                path = ?path,
+                tables = cp.tables.len(),
+                "Resuming from checkpoint"
+            );
+            Ok(cp)
+        }
+        Some(_) => {
+            info!(path = ?path, "Checkpoint belongs to a different database, starting fresh");
+            Ok(fresh())
+        }
+        None => {
+            info!(path = ?path, "No checkpoint found, starting fresh");
+            Ok(fresh())
+        }
+    }
+}
+
+async fn profile_tables_parallel(
+    pool: &PgPool,
+    tables: &[Table],
+    mut tuner: ConcurrencyTuner,
+    multi_progress: &MultiProgress,
+    options: &ProfileOptions,
+    mut checkpoint: ScanCheckpoint,
+    checkpoint_path: Option<&PathBuf>,
+) -> Result<(
+    HashMap<String, Distribution>,
+    HashMap<String, CovarianceMatrix>,
+)> {
+    let pool = Arc::new(pool.clone());
+
+    let mut all_distributions = HashMap::new();
+    let mut all_correlations = HashMap::new();
+
+    // Restore tables already profiled by a previous run
+    let mut pending = Vec::with_capacity(tables.len());
+    for table in tables {
+        match checkpoint.completed(table) {
+            Some(done) => {
+                for (col_name, dist) in &done.distributions {
+                    let key = DatabaseGenome::make_key(&table.name, col_name);
+                    all_distributions.insert(key, dist.clone());
+                }
+                if let Some(cov) = &done.covariance {
+                    all_correlations.insert(table.name.clone(), cov.clone());
+                }
+            }
+            None => pending.push(table),
+        }
+    }
+
+    // Largest tables first, so the longest scans don't start last
+    order_largest_first(&mut pending);
+
+    if pending.len() < tables.len() {
+        info!(
+            restored = tables.len() - pending.len(),
+            "Skipping tables restored from checkpoint"
+        );
+    }
+
+    // Create progress bars for each table
+    let progress_bars: Vec<_> = pending
+        .iter()
+        .map(|table| {
+            let pb = multi_progress.add(ProgressBar::new_spinner());
+            pb.set_style(table_style(TABLE_TEMPLATE));
+            pb.set_prefix(table.name.clone());
+            if let Some(progress) = &options.progress {
+                progress.table_queued(&table.name, table.estimate.and_then(|e| e.rows));
+            }
+            match table.estimate {
+                Some(estimate) => pb.set_message(format!("waiting... ({})", format_estimate(&estimate))),
+                None => pb.set_message("waiting..."),
+            }
+            pb
+        })
+        .collect();
+
+    // Row counters on the bars, with everything passed on to the caller's receiver
+    let options = &ProfileOptions {
+        progress: Some(Arc::new(TableBars {
+            bars: pending
+                .iter()
+                .zip(&progress_bars)
+                .map(|(table, pb)| (table.name.clone(), (pb.clone(), table.estimate.and_then(|e| e.rows))))
+                .collect(),
+            forward: options.progress.clone(),
+        })),
+        ..options.clone()
+    };
+
+    let mut queue: VecDeque<(&Table, &ProgressBar)> = pending.iter().copied().zip(progress_bars.iter()).collect();
+
+    let spawn_table = |table: &Table, pb: &ProgressBar| {
+        let table = table.clone();
+        let pb = pb.clone();
+        let pool = Arc::clone(&pool);
+        let options = options.clone();
+
+        tokio::spawn(async move {
+            if let Some(progress) = &options.progress {
+                progress.table_started(&table.name);
+            }
+
+            //Now returns tuple (distributions, covariance)
+            let (distributions, covariance) = profile_columns(&pool, &table, &options).await.map_err(|e| {
+                pb.finish_with_message(format!("✗ failed: {}", e));
+                if let Some(progress) = &options.progress {
+                    progress.table_failed(&table.name, &format!("{:#}", e));
+                }
+                e
+            })?;
+
+            //Update progress message to show correlation status
+            let msg = if covariance.is_some() {
+                format!("{} rows, {} columns + correlations", pb.position(), distributions.len())
+            } else {
+                format!("{} rows, {} columns", pb.position(), distributions.len())
+            };
+            pb.finish_with_message(msg);
+
+            Ok::<_, anyhow::Error>((table, distributions, covariance))
+        })
+    };
+
+    let mut limit = tuner.limit();
+    let mut load_samples = tokio::time::interval(LOAD_SAMPLE_INTERVAL);
+    load_samples.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut running = FuturesUnordered::new();
+
+    loop {
+        while running.len() < limit
+            && let Some((table, pb)) = queue.pop_front()
+        {
+            running.push(spawn_table(table, pb));
+        }
+
+        // A lower limit lets running tables finish and only holds back the next ones
+        let result = if tuner.is_adaptive() {
+            tokio::select! {
+                result = running.next() => result,
+                _ = load_samples.tick() => {
+                    match sample_load(&pool).await {
+                        Ok(load) => {
+                            limit = tuner.adjust(load);
+                            debug!(active = load.active, waiting = load.waiting, limit = limit, "Adjusted scan concurrency");
+                        }
+                        Err(e) => warn!(error = %e, "Failed to sample database load, keeping scan concurrency"),
+                    }
+                    continue;
+                }
+            }
+        } else {
+            running.next().await
+        };
+        let Some(result) = result else {
+            break;
+        };
+
+        let (table, distributions, covariance) = result
+            .context("Task panicked")?
+            .context("Profiling failed")?;
+
+        // Persist progress before merging so a later failure doesn't lose this table
+        if let Some(path) = checkpoint_path {
+            checkpoint.record(&table, distributions.clone(), covariance.clone());
+            checkpoint
+                .save(path)
+                .context("Failed to write scan checkpoint")?;
+        }
+
+        for (col_name, dist) in distributions {
+            // Use the new key format: "table_name.column_name"
+            let key = DatabaseGenome::make_key(&table.name, &col_name);
+            all_distributions.insert(key, dist);
+        }
+
+        //Collect correlation matrix if computed
+        if let Some(cov) = covariance {
+            all_correlations.insert(table.name.clone(), cov);
+        }
+    }
+
+    Ok((all_distributions, all_correlations))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_options_defaults() {
+        let options = ScanOptions::default();
+        assert_eq!(options.parallel_jobs, 10);
+        assert!(!options.consistent_snapshot);
+        assert!(options.checkpoint_path.is_none());
+        assert!(!options.show_progress);
+    }
+
+    fn sized(name: &str, rows: u64, bytes: u64, foreign_keys: Vec<crate::schema::ForeignKey>) -> Table {
+        let mut table = Table::new(name.to_string(), vec![], foreign_keys);
+        table.estimate = Some(crate::schema::TableEstimate { rows: Some(rows), bytes });
+        table
+    }
+
+    #[test]
+    fn test_table_bars_count_rows() {
+        let bars = TableBars {
+            bars: HashMap::from([
+                ("events".to_string(), (ProgressBar::hidden(), Some(1000))),
+                ("users".to_string(), (ProgressBar::hidden(), None)),
+            ]),
+            forward: None,
+        };
+
+        bars.table_started("events");
+        bars.rows_scanned("events", 400);
+        let (events, _) = &bars.bars["events"];
+        assert_eq!((events.position(), events.length()), (400, Some(1000)));
+        // The planner underestimated
+        bars.rows_scanned("events", 1500);
+        assert_eq!(events.length(), Some(1500));
+
+        bars.table_started("users");
+        bars.rows_scanned("users", 20_000);
+        bars.table_finished("users", &TableMetrics { rows: 20_250, duration: Duration::from_secs(1), reservoir_fill: None });
+        assert_eq!(bars.bars["users"].0.position(), 20_250);
+    }
+
+    #[test]
+    fn test_skip_large_tables() {
+        let fk = crate::schema::ForeignKey::new("event_id".to_string(), "events".to_string(), "id".to_string());
+        let mut tables = vec![
+            sized("users", 1_000, 1 << 20, vec![]),
+            sized("events", 500_000_000, 60 << 30, vec![]),
+            sized("alerts", 10, 8192, vec![fk]),
+        ];
+
+        assert_eq!(skip_large_tables(&mut tables, 50 << 30), vec!["events"]);
+        assert_eq!(tables.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(), vec!["users", "alerts"]);
+        assert!(tables[1].foreign_keys.is_empty());
+    }
+
+    #[test]
+    fn test_scan_time_estimate() {
+        let tables = vec![
+            sized("users", 500_000, 1 << 20, vec![]),
+            sized("orders", 2_000_000, 1 << 30, vec![]),
+            Table::new("unknown".to_string(), vec![], vec![]),
+        ];
+
+        // Parallel tasks: bounded below by the largest table
+        assert_eq!(estimate_scan_time(&tables, 10), Some(Duration::from_secs(8)));
+        // One task: everything in sequence
+        assert_eq!(estimate_scan_time(&tables, 1), Some(Duration::from_secs(10)));
+        assert_eq!(estimate_scan_time(&tables[2..], 4), None);
+
+        assert_eq!(describe_estimates(&tables, 10), ", ~2500000 rows, 1.0 GB, scan ~8s");
+        assert_eq!(format_bytes(512), "512 bytes");
+        assert_eq!(format_duration(Duration::from_secs(3725)), "1h 2m");
+    }
+
+    #[test]
+    fn test_resume_without_checkpoint_path_starts_fresh() -> Result<()> {
+        let options = ScanOptions {
+            resume: true,
+            source_database: Some("db".to_string()),
+            ..ScanOptions::default()
+        };
+
+        let checkpoint = load_checkpoint(&options)?;
+        assert!(checkpoint.tables.is_empty());
+        assert_eq!(checkpoint.source_database.as_deref(), Some("db"));
+        Ok(())
+    }
+}
@@ -1,38 +1,442 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 use anyhow::{Context, Result};
-use sqlx::{Row, ValueRef};
+use futures::stream::BoxStream;
+use sqlx::{PgConnection, Row, ValueRef};
 use sqlx::postgres::{PgPool, PgRow};
-use sqlx::query::Query;
 use tracing::{debug, info, warn};
-use crate::copula::CovarianceMatrix;
-use crate::math::{Distribution, DistributionBuilder, Reservoir, DEFAULT_RESERVOIR_CAPACITY};
-use crate::schema::{Column, DataType, Table};
+use crate::conditional::{ConditionalDistribution, MIN_GROUP_ROWS};
+use crate::copula::{CopulaKind, CorrelationMethod, CovarianceMatrix};
+use crate::ddl::placeholder_distribution;
+use crate::geometry::Extents;
+use crate::heavy_hitters::SpaceSaving;
+use crate::hstore::{format_pair, parse_hstore};
+use crate::interval::parse_interval;
+use crate::math::{
+    Bernoulli, BinningStrategy, Distribution, DistributionBuilder, Histogram, LengthDistribution, LongTail, Recency, Reservoir,
+    DEFAULT_RESERVOIR_CAPACITY, NUMERIC_HISTOGRAM_BINS,
+};
+use crate::markov::{is_free_text, MarkovModel};
+use crate::metrics::{Phase, RunMetrics, TableMetrics};
+use crate::pattern::infer_pattern;
+use crate::network::prefix_bucket;
+use crate::schema::{bytea_length, parse_currency, Column, DataType, ForeignKey, Table};
+use crate::seasonality::SeasonalityProfile;
+use crate::sequence::SequenceTracker;
+use crate::semantic::classify_pii;
+use crate::subset::FanOut;
+use crate::tdigest::TDigest;
+
+/// Share of distinct values in a text sample above which a column is treated as an
+/// identifier and considered for pattern inference.
+const PATTERN_MIN_UNIQUE_RATIO: f64 = 0.5;
+
+/// Heavy hitters kept in the histogram once a column overflows the sketch.
+const HEAVY_HITTER_TOP_K: usize = 100;
 
 struct ColumnState {
     data_type: DataType,
     null_count: u64,
     numeric_reservoir: Option<Reservoir<f64>>,
     text_reservoir: Option<Reservoir<String>>,
+    digest: Option<TDigest>,
+    heavy_hitters: Option<SpaceSaving>,
+    seasonality: Option<SeasonalityProfile>,
+    sequence: Option<SequenceTracker>,
+    bernoulli: Option<Bernoulli>,
+    extents: Option<Extents>,
+    pair_counts: Option<LengthDistribution>,
 }
 
 impl ColumnState {
-    fn new(data_type: DataType) -> Self {
+    fn new(data_type: DataType, capacity: usize) -> Self {
         let (numeric_reservoir, text_reservoir) = match data_type {
-            DataType::Integer | DataType::Float | DataType::Timestamp => {
-                (Some(Reservoir::new(DEFAULT_RESERVOIR_CAPACITY)), None)
+            // Bytea columns are sampled as value lengths, intervals as seconds
+            DataType::Integer | DataType::Float | DataType::Timestamp | DataType::Bytea | DataType::Interval => {
+                (Some(Reservoir::new(capacity)), None)
             }
-            DataType::Text | DataType::Boolean | DataType::Uuid => {
-                (None, Some(Reservoir::new(DEFAULT_RESERVOIR_CAPACITY)))
+            // Network columns are sampled as address prefixes, hstore ones as single pairs
+            DataType::Text
+            | DataType::Uuid
+            | DataType::Inet
+            | DataType::Cidr
+            | DataType::MacAddr
+            | DataType::Hstore => {
+                (None, Some(Reservoir::new(capacity)))
             }
+            // Counted exactly, no sample needed
+            DataType::Boolean | DataType::Geometry | DataType::Geography => (None, None),
         };
 
+        let heavy_hitters = (data_type == DataType::Text).then(SpaceSaving::default);
+        let seasonality = (data_type == DataType::Timestamp).then(SeasonalityProfile::default);
+        let bernoulli = (data_type == DataType::Boolean).then(Bernoulli::default);
+        let extents = matches!(data_type, DataType::Geometry | DataType::Geography).then(Extents::default);
+        let pair_counts = (data_type == DataType::Hstore).then(LengthDistribution::default);
+
         Self {
             data_type,
             null_count: 0,
             numeric_reservoir,
             text_reservoir,
+            digest: None,
+            heavy_hitters,
+            seasonality,
+            sequence: None,
+            bernoulli,
+            extents,
+            pair_counts,
+        }
+    }
+
+    /// Also feed every numeric value (not just the reservoir sample) into a t-digest.
+    fn with_digest(mut self) -> Self {
+        if self.numeric_reservoir.is_some() {
+            self.digest = Some(TDigest::default());
+        }
+        self
+    }
+
+    /// Sample values in proportion to the weight set with [`ColumnState::set_log_weight`].
+    fn with_weighted_sampling(mut self) -> Self {
+        self.numeric_reservoir = self.numeric_reservoir.map(|r| Reservoir::weighted(r.capacity()));
+        self.text_reservoir = self.text_reservoir.map(|r| Reservoir::weighted(r.capacity()));
+        self
+    }
+
+    /// Weight of the row whose values are added next, see [`Reservoir::set_log_weight`].
+    fn set_log_weight(&mut self, log_weight: f64) {
+        if let Some(ref mut reservoir) = self.numeric_reservoir {
+            reservoir.set_log_weight(log_weight);
+        }
+        if let Some(ref mut reservoir) = self.text_reservoir {
+            reservoir.set_log_weight(log_weight);
+        }
+    }
+
+    /// Also check whether values arrive in increasing order (see [`SequenceTracker`]).
+    fn with_sequence_tracking(mut self) -> Self {
+        self.sequence = Some(SequenceTracker::default());
+        self
+    }
+
+    /// Counts the pairs of one hstore value and samples each as a category.
+    fn add_pairs(&mut self, pairs: Vec<(String, Option<String>)>) {
+        if let Some(ref mut counts) = self.pair_counts {
+            counts.add(pairs.len());
+        }
+        if let Some(ref mut reservoir) = self.text_reservoir {
+            for (key, value) in pairs {
+                reservoir.add(format_pair(&key, value.as_deref()));
+            }
+        }
+    }
+
+    fn add_numeric(&mut self, value: f64) {
+        if let Some(ref mut reservoir) = self.numeric_reservoir {
+            reservoir.add(value);
+        }
+        if let Some(ref mut sequence) = self.sequence {
+            sequence.add(value);
+        }
+        if let Some(ref mut digest) = self.digest {
+            digest.add(value);
+        }
+    }
+
+    fn add_timestamp(&mut self, epoch_seconds: f64) {
+        self.add_numeric(epoch_seconds);
+        if let Some(ref mut profile) = self.seasonality {
+            profile.add(epoch_seconds);
+        }
+    }
+
+    /// Adds a value in text form, as read from a CSV export; `None` is NULL. Returns
+    /// the parsed value of integer, float and timestamp columns for correlation tracking.
+    fn add_text(&mut self, value: Option<&str>) -> Result<Option<f64>> {
+        let Some(value) = value else {
+            self.null_count += 1;
+            return Ok(None);
+        };
+
+        match self.data_type {
+            DataType::Integer => {
+                let value = value.trim().parse::<i64>().context(format!("Invalid integer '{}'", value))? as f64;
+                self.add_numeric(value);
+                Ok(Some(value))
+            }
+            DataType::Float => {
+                let parsed = value.trim().parse::<f64>().ok().or_else(|| parse_currency(value));
+                let parsed = parsed.context(format!("Invalid float '{}'", value))?;
+                self.add_numeric(parsed);
+                Ok(Some(parsed))
+            }
+            DataType::Timestamp => {
+                let epoch_seconds = parse_timestamp(value).context(format!("Invalid timestamp '{}'", value))?;
+                self.add_timestamp(epoch_seconds);
+                Ok(Some(epoch_seconds))
+            }
+            DataType::Bytea => {
+                self.add_numeric(bytea_length(value) as f64);
+                Ok(None)
+            }
+            DataType::Interval => {
+                let seconds = parse_interval(value).context(format!("Invalid interval '{}'", value))?;
+                self.add_numeric(seconds);
+                Ok(None)
+            }
+            DataType::Hstore => {
+                let pairs = parse_hstore(value).context(format!("Invalid hstore '{}'", value))?;
+                self.add_pairs(pairs);
+                Ok(None)
+            }
+            DataType::Geometry | DataType::Geography => {
+                if let Some(ref mut extents) = self.extents
+                    && !extents.add(value)
+                {
+                    anyhow::bail!("Invalid {} '{}', expected WKT or EWKT", self.data_type, value);
+                }
+                Ok(None)
+            }
+            DataType::Inet | DataType::Cidr | DataType::MacAddr => {
+                let prefix = prefix_bucket(&self.data_type, value).context(format!("Invalid {} '{}'", self.data_type, value))?;
+                if let Some(ref mut reservoir) = self.text_reservoir {
+                    reservoir.add(prefix);
+                }
+                Ok(None)
+            }
+            DataType::Boolean => {
+                let value = match value.trim().to_lowercase().as_str() {
+                    "t" | "true" | "1" | "yes" | "y" | "on" => true,
+                    "f" | "false" | "0" | "no" | "n" | "off" => false,
+                    _ => anyhow::bail!("Invalid boolean '{}'", value),
+                };
+                if let Some(ref mut bernoulli) = self.bernoulli {
+                    bernoulli.add(value);
+                }
+                Ok(None)
+            }
+            DataType::Text | DataType::Uuid => {
+                if let Some(ref mut sketch) = self.heavy_hitters {
+                    sketch.add(value);
+                }
+                if let Some(ref mut reservoir) = self.text_reservoir {
+                    reservoir.add(value.to_string());
+                }
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Epoch seconds of a timestamp or date literal, with or without a UTC offset. Values
+/// without an offset are taken as UTC, like `timestamp without time zone` columns.
+pub(crate) fn parse_timestamp(raw: &str) -> Option<f64> {
+    let raw = raw.trim();
+    if let Ok(ts) = chrono::DateTime::parse_from_rfc3339(raw) {
+        return Some(ts.timestamp() as f64);
+    }
+    if let Ok(ts) = chrono::DateTime::parse_from_str(raw, "%Y-%m-%d %H:%M:%S%.f%#z") {
+        return Some(ts.timestamp() as f64);
+    }
+    for format in ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"] {
+        if let Ok(ts) = chrono::NaiveDateTime::parse_from_str(raw, format) {
+            return Some(ts.and_utc().timestamp() as f64);
+        }
+    }
+    chrono::NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|ts| ts.and_utc().timestamp() as f64)
+}
+
+/// Per-table profiling settings shared by every scan task.
+#[derive(Debug, Clone, Default)]
+pub struct ProfileOptions {
+    /// Snapshot exported by `pg_export_snapshot()`; when set, the table is read inside a
+    /// `REPEATABLE READ` transaction pinned to it so all tables see the same point in time.
+    pub snapshot: Option<String>,
+
+    pub retry: RetryPolicy,
+
+    /// Replace the histograms of columns classified as PII with faker-style generators
+    /// so no source values end up in the genome.
+    pub redact_pii: bool,
+
+    /// Learn word-level Markov models for free-form text columns.
+    pub markov_text: bool,
+
+    /// Try parametric fits for numeric columns.
+    pub fit_distributions: bool,
+
+    /// Placement of numeric bin edges.
+    pub binning: BinningStrategy,
+
+    /// Numeric histogram bins; [`NUMERIC_HISTOGRAM_BINS`] when unset.
+    pub bins: Option<usize>,
+
+    /// Share of numeric samples clipped in each tail before binning; the true min and
+    /// max and the clipped shares are recorded in [`Distribution::outliers`].
+    pub winsorize: Option<f64>,
+
+    /// Summarize numeric columns with t-digests over every row instead of fixed bins.
+    pub quantile_sketch: bool,
+
+    /// Coefficient stored in the per-table correlation matrix.
+    pub correlation: CorrelationMethod,
+
+    /// SQL predicates restricting which rows of a table are profiled, keyed by table
+    /// name. Checked with [`crate::config::validate_predicate`] before the scan starts.
+    pub row_filters: HashMap<String, String>,
+
+    /// Have the server `pg_sleep` this long after every [`THROTTLE_INTERVAL`] rows of a
+    /// profiling query, capping the read rate a scan imposes.
+    pub throttle: Option<Duration>,
+
+    /// Read profiling queries through a server-side cursor `fetch_size` rows at a time
+    /// instead of one streaming result, so client memory stays flat however wide rows are.
+    pub fetch_size: Option<usize>,
+
+    /// Where each profiled table's rows, duration and reservoir fill are recorded.
+    pub metrics: Option<Arc<RunMetrics>>,
+
+    /// Text categories counted fewer times than this are folded into the long tail and
+    /// generated instead of replayed.
+    pub min_category_count: Option<u64>,
+
+    /// Receives each table's row count while it is read, and its metrics once profiled.
+    pub progress: Option<Arc<dyn ScanProgress>>,
+
+    /// Values sampled per column; [`DEFAULT_RESERVOIR_CAPACITY`] when unset.
+    pub reservoir_capacity: Option<usize>,
+
+    /// Overrides of `reservoir_capacity`, keyed `table.column` or `table`. A table's
+    /// capacity also sizes the row sample its correlation matrix is computed from.
+    pub reservoir_capacities: HashMap<String, usize>,
+
+    /// Tables whose samples favor recent rows, keyed by table name. Exact counts
+    /// (booleans, heavy hitters, seasonality, t-digests) still weigh every row the same.
+    pub recency: HashMap<String, Recency>,
+
+    /// Copula per table, keyed by table name. Tables with the empirical copula keep
+    /// their correlation sample as [`CovarianceMatrix::pseudo_observations`].
+    pub copulas: HashMap<String, CopulaKind>,
+
+    /// Profile the columns of wide tables on up to this many blocking tasks, each
+    /// owning a share of the columns, instead of on the task reading the rows.
+    pub column_workers: Option<usize>,
+}
+
+impl ProfileOptions {
+    /// Reservoir capacity of `column` of `table`, or of the table's correlation sample
+    /// when `column` is `None`.
+    pub fn reservoir_capacity(&self, table: &str, column: Option<&str>) -> usize {
+        column
+            .and_then(|column| self.reservoir_capacities.get(&format!("{}.{}", table, column)))
+            .or_else(|| self.reservoir_capacities.get(table))
+            .copied()
+            .or(self.reservoir_capacity)
+            .unwrap_or(DEFAULT_RESERVOIR_CAPACITY)
+    }
+
+    /// Recency weighting of `table`, if configured for a timestamp column it has.
+    fn recency(&self, table: &Table) -> Option<&Recency> {
+        let recency = self.recency.get(&table.name)?;
+        match table.columns.iter().find(|c| c.name == recency.column) {
+            Some(column) if column.data_type == DataType::Timestamp => Some(recency),
+            Some(column) => {
+                warn!(table = %table.name, column = %column.name, data_type = %column.data_type, "Recency column is not a timestamp, sampling uniformly");
+                None
+            }
+            None => {
+                warn!(table = %table.name, column = %recency.column, "Recency column was not found, sampling uniformly");
+                None
+            }
+        }
+    }
+}
+
+/// Rows read between two [`ScanProgress::rows_scanned`] calls for a table.
+pub const SCAN_PROGRESS_INTERVAL: u64 = 10_000;
+
+/// Receives scan progress. Tables are profiled concurrently, so calls for different
+/// tables may interleave.
+pub trait ScanProgress: Send + Sync + fmt::Debug {
+    /// `table` is waiting for a profiling slot; `estimated_rows` comes from the planner.
+    fn table_queued(&self, _table: &str, _estimated_rows: Option<u64>) {}
+
+    /// `table` is being read.
+    fn table_started(&self, _table: &str) {}
+
+    /// `rows` rows of `table` have been read so far. A retried table starts again from zero.
+    fn rows_scanned(&self, _table: &str, _rows: u64) {}
+
+    /// `table` is profiled.
+    fn table_finished(&self, _table: &str, _metrics: &TableMetrics) {}
+
+    /// Profiling `table` failed with `error`.
+    fn table_failed(&self, _table: &str, _error: &str) {}
+}
+
+/// Rows a profiling query returns between two throttling pauses.
+pub const THROTTLE_INTERVAL: u64 = 10_000;
+
+/// Column alias of the throttling expression; its value is never read.
+const THROTTLE_ALIAS: &str = "replica_throttle";
+
+/// Name of the server-side cursor used when [`ProfileOptions::fetch_size`] is set.
+const CURSOR_NAME: &str = "replica_scan";
+
+/// Column alias under which the conditional query returns the parent attribute.
+const PARENT_VALUE_ALIAS: &str = "replica_parent_value";
+
+/// Exponential backoff for transient failures while streaming a table.
+/// A retry restarts profiling of that table only; finished tables are unaffected.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `attempt` (0-based): initial * 2^attempt, capped.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Accumulated state of a single pass over a table.
+struct TableScan {
+    column_states: HashMap<String, ColumnState>,
+    numeric_row_reservoir: Option<Reservoir<Vec<Option<f64>>>>,
+    total_rows: Arc<AtomicU64>,
+}
+
+impl TableScan {
+    /// Sets the weight of the next row on every reservoir of the scan.
+    fn set_log_weight(&mut self, log_weight: f64) {
+        for state in self.column_states.values_mut() {
+            state.set_log_weight(log_weight);
+        }
+        if let Some(reservoir) = &mut self.numeric_row_reservoir {
+            reservoir.set_log_weight(log_weight);
         }
     }
 }
@@ -40,15 +444,24 @@ impl ColumnState {
 pub async fn profile_columns(
     pool: &PgPool,
     table: &Table,
+    options: &ProfileOptions,
 ) -> Result<(HashMap<String, Distribution>, Option<CovarianceMatrix>)> {
     info!(table = %table.name, "Starting column profiling");
+    let started = Instant::now();
 
     if table.columns.is_empty() {
         return Ok((HashMap::new(), None));
     }
 
-    let column_names: Vec<&str> = table.columns.iter().map(|c| c.name.as_str()).collect();
-    let query = build_select_query(&table.name, &column_names);
+    let numeric_only = is_numeric_only(table);
+    let select_items: Vec<String> = table
+        .columns
+        .iter()
+        .map(|c| if numeric_only { float8_select_item(c) } else { select_item(c, "") })
+        .collect();
+    let column_names: Vec<&str> = select_items.iter().map(String::as_str).collect();
+    let filter = options.row_filters.get(&table.name).map(String::as_str);
+    let query = build_select_query(&relation_name(table), &column_names, filter, options.throttle);
 
     debug!(
         table = %table.name,
@@ -60,42 +473,88 @@ pub async fn profile_columns(
     let numeric_columns: Vec<&Column> = table
         .columns
         .iter()
-        .filter(|c| matches!(c.data_type, DataType::Integer | DataType::Float))
+        .filter(|c| c.data_type.is_correlated())
         .collect();
 
-    let has_numeric_columns = !numeric_columns.is_empty();
-
     debug!(
         table = %table.name,
         numeric_columns = numeric_columns.len(),
+        numeric_only = numeric_only,
         "Identified numeric columns for correlation tracking"
     );
 
-    let mut column_states = initialize_column_states(&table.columns);
-    let total_rows = Arc::new(AtomicU64::new(0));
-
-    // Joint numeric reservoir for correlation
-    let mut numeric_row_reservoir: Option<Reservoir<Vec<f64>>> = if has_numeric_columns {
-        Some(Reservoir::new(DEFAULT_RESERVOIR_CAPACITY))
-    } else {
-        None
+    let mut attempt = 0;
+    let TableScan {
+        column_states,
+        numeric_row_reservoir,
+        total_rows,
+    } = loop {
+        match scan_table_once(pool, table, &query, &numeric_columns, numeric_only, options).await {
+            Ok(scan) => break scan,
+            Err(e) if attempt < options.retry.max_retries && is_transient_error(&e) => {
+                let delay = options.retry.backoff(attempt);
+                attempt += 1;
+                warn!(
+                    table = %table.name,
+                    error = %e,
+                    attempt = attempt,
+                    max_retries = options.retry.max_retries,
+                    delay_ms = delay.as_millis() as u64,
+                    "Transient failure while profiling, restarting table"
+                );
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) => {
+                return Err(e).context(format!(
+                    "Failed during streaming profiling (after {} retries)",
+                    attempt
+                ));
+            }
+        }
     };
 
-    //Stream and process rows
-    stream_and_profile(
-        pool,
-        &query,
-        &table.columns,
+    Ok(finish_profile(
+        table,
+        TableScan { column_states, numeric_row_reservoir, total_rows },
         &numeric_columns,
-        &mut column_states,
-        &mut numeric_row_reservoir,
-        &total_rows,
-    )
-        .await
-        .context("Failed during streaming profiling")?;
+        options,
+        started,
+    ))
+}
+
+/// Turns the state of a finished table scan into column distributions and, with two
+/// or more numeric columns, a correlation matrix.
+fn finish_profile(
+    table: &Table,
+    scan: TableScan,
+    numeric_columns: &[&Column],
+    options: &ProfileOptions,
+    started: Instant,
+) -> (HashMap<String, Distribution>, Option<CovarianceMatrix>) {
+    let TableScan {
+        column_states,
+        numeric_row_reservoir,
+        total_rows,
+    } = scan;
+
+    let table_metrics = TableMetrics {
+        rows: total_rows.load(Ordering::Relaxed),
+        duration: started.elapsed(),
+        reservoir_fill: reservoir_fill(&column_states),
+    };
+    if let Some(progress) = &options.progress {
+        progress.table_finished(&table.name, &table_metrics);
+    }
+    if let Some(metrics) = &options.metrics {
+        metrics.record_table(Phase::Scan, &table.name, started, table_metrics);
+    }
 
     //Convert reservoir samples to distributions
-    let distributions = build_distributions(&table.columns, column_states, &total_rows);
+    let distributions = if total_rows.load(Ordering::Relaxed) == 0 {
+        default_distributions(table)
+    } else {
+        build_distributions(&table.name, column_states, &total_rows, options)
+    };
 
     //Compute covariance matrix if applicable
     let covariance = if numeric_columns.len() >= 2 {
@@ -106,14 +565,24 @@ pub async fn profile_columns(
                     .map(|c| c.name.clone())
                     .collect();
 
-                let samples: Vec<Vec<f64>> = reservoir.sample().to_vec();
+                let samples = reservoir.sample();
 
-                match CovarianceMatrix::compute(ordered_names, &samples) {
+                match CovarianceMatrix::compute_pairwise(ordered_names, samples, options.correlation) {
+                    Ok(cov) if options.copulas.get(&table.name) == Some(&CopulaKind::Empirical) => {
+                        info!(
+                            table = %table.name,
+                            numeric_cols = numeric_columns.len(),
+                            samples = samples.len(),
+                            "Computed correlation matrix and pseudo-observations for the empirical copula"
+                        );
+                        Some(cov.with_pseudo_observations(samples))
+                    }
                     Ok(cov) => {
                         info!(
                             table = %table.name,
                             numeric_cols = numeric_columns.len(),
                             samples = samples.len(),
+                            method = %options.correlation,
                             "Computed correlation matrix"
                         );
                         Some(cov)
@@ -155,202 +624,1024 @@ pub async fn profile_columns(
         "Profiling complete"
     );
 
-    Ok((distributions, covariance))
+    (distributions, covariance)
 }
 
-fn build_select_query(table_name: &str, column_names: &[&str]) -> String {
-    let columns_clause = column_names.join(", ");
-    format!("SELECT {} FROM {}", columns_clause, table_name)
-}
+/// Profiles rows that were read elsewhere, e.g. from a CSV export, with the same
+/// statistics as [`profile_columns`]. Every row holds the values of `table.columns` in
+/// order, in text form, with `None` for NULL.
+pub fn profile_text_rows<I>(
+    table: &Table,
+    rows: I,
+    options: &ProfileOptions,
+) -> Result<(HashMap<String, Distribution>, Option<CovarianceMatrix>)>
+where
+    I: IntoIterator<Item = Result<Vec<Option<String>>>>,
+{
+    info!(table = %table.name, "Starting column profiling");
+    let started = Instant::now();
 
-fn initialize_column_states(columns: &[Column]) -> HashMap<String, ColumnState> {
-    columns
+    let numeric_columns: Vec<&Column> = table
+        .columns
         .iter()
-        .map(|col| {
-            let state = ColumnState::new(col.data_type.clone());
-            (col.name.clone(), state)
-        })
-        .collect()
+        .filter(|c| c.data_type.is_correlated())
+        .collect();
+    let is_numeric = numeric_mask(&table.columns, &numeric_columns);
+
+    let recency = options.recency(table);
+    let recency_index = recency.and_then(|r| table.columns.iter().position(|c| c.name == r.column));
+    let capacity = |column: &str| options.reservoir_capacity(&table.name, Some(column));
+    let column_states = initialize_column_states(&table.columns, capacity, options.quantile_sketch, true, recency.is_some());
+    let numeric_row_reservoir = (!numeric_columns.is_empty())
+        .then(|| row_reservoir(options.reservoir_capacity(&table.name, None), recency.is_some()));
+    let total_rows = Arc::new(AtomicU64::new(0));
+    let mut scan = TableScan { column_states, numeric_row_reservoir, total_rows };
+
+    if let Some(progress) = &options.progress {
+        progress.table_started(&table.name);
+    }
+    for row in rows {
+        let row = row?;
+        if row.len() != table.columns.len() {
+            anyhow::bail!("Row of {} has {} values, expected {}", table.name, row.len(), table.columns.len());
+        }
+        let scanned = scan.total_rows.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(progress) = &options.progress
+            && scanned.is_multiple_of(SCAN_PROGRESS_INTERVAL)
+        {
+            progress.rows_scanned(&table.name, scanned);
+        }
+
+        if let Some(recency) = recency
+            && let Some(epoch_seconds) = recency_index.and_then(|i| row[i].as_deref()).and_then(parse_timestamp)
+        {
+            scan.set_log_weight(recency.log_weight(epoch_seconds));
+        }
+
+        let mut numeric_row = Vec::with_capacity(numeric_columns.len());
+        for (col_idx, (col, value)) in table.columns.iter().zip(&row).enumerate() {
+            let Some(state) = scan.column_states.get_mut(&col.name) else {
+                continue;
+            };
+            let number = state.add_text(value.as_deref()).unwrap_or_else(|e| {
+                warn!(column = %col.name, error = %e, "Failed to process column value");
+                None
+            });
+            if is_numeric[col_idx] {
+                numeric_row.push(number);
+            }
+        }
+
+        add_numeric_row(&mut scan.numeric_row_reservoir, numeric_row);
+    }
+
+    Ok(finish_profile(
+        table,
+        scan,
+        &numeric_columns,
+        options,
+        started,
+    ))
 }
 
-async fn stream_and_profile(
+/// Streams the whole table once on a fresh connection, starting from empty state.
+/// `numeric_only` tables were selected with [`float8_select_item`] and are decoded in
+/// batches by [`stream_and_profile_numeric`].
+async fn scan_table_once(
     pool: &PgPool,
+    table: &Table,
     query: &str,
-    columns: &[Column],
     numeric_columns: &[&Column],
-    column_states: &mut HashMap<String, ColumnState>,
-    numeric_row_reservoir: &mut Option<Reservoir<Vec<f64>>>,
-    total_rows: &Arc<AtomicU64>,
-) -> Result<()> {
-    use futures::TryStreamExt;
+    numeric_only: bool,
+    options: &ProfileOptions,
+) -> Result<TableScan> {
+    let recency = options.recency(table);
+    let capacity = |column: &str| options.reservoir_capacity(&table.name, Some(column));
+    let column_states = initialize_column_states(&table.columns, capacity, options.quantile_sketch, true, recency.is_some());
+    let total_rows = Arc::new(AtomicU64::new(0));
 
-    // Build index map for numeric columns
-    let numeric_indices: Vec<usize> = numeric_columns
-        .iter()
-        .filter_map(|nc| columns.iter().position(|c| c.name == nc.name))
-        .collect();
+    // Joint numeric reservoir for correlation
+    let numeric_row_reservoir: Option<Reservoir<Vec<Option<f64>>>> = if !numeric_columns.is_empty() {
+        Some(row_reservoir(options.reservoir_capacity(&table.name, None), recency.is_some()))
+    } else {
+        None
+    };
+    let mut scan = TableScan { column_states, numeric_row_reservoir, total_rows };
 
-    // Execute query and get a stream
-    let mut stream = sqlx::query(query).fetch(pool);
+    let mut conn = pool
+        .acquire()
+        .await
+        .context("Failed to acquire profiling connection")?;
 
-    // Process each row from the stream
-    while let Some(row) = stream.try_next().await? {
-        total_rows.fetch_add(1, Ordering::Relaxed);
+    let in_transaction = begin_profiling_transaction(&mut conn, options).await?;
 
-        //Track numeric values for correlation (pairwise deletion)
-        let mut numeric_row: Option<Vec<f64>> = if !numeric_indices.is_empty() {
-            Some(Vec::with_capacity(numeric_indices.len()))
-        } else {
-            None
-        };
+    //Stream and process rows
+    let progress = options.progress.as_deref().map(|progress| (table.name.as_str(), progress));
+    let workers = column_worker_count(options.column_workers, table.columns.len());
+    let stream_result = if numeric_only {
+        stream_and_profile_numeric(fetch_rows(&mut conn, query, options.fetch_size), &table.columns, &mut scan, progress).await
+    } else if workers > 1 {
+        debug!(table = %table.name, workers = workers, "Profiling columns on worker tasks");
+        stream_and_profile_sharded(
+            fetch_rows(&mut conn, query, options.fetch_size),
+            &table.columns,
+            numeric_columns,
+            &mut scan,
+            recency,
+            progress,
+            workers,
+        )
+            .await
+    } else {
+        stream_and_profile(
+            fetch_rows(&mut conn, query, options.fetch_size),
+            &table.columns,
+            numeric_columns,
+            &mut scan,
+            recency,
+            progress,
+        )
+            .await
+    };
 
-        let mut row_has_null_numeric = false;
+    if let Err(e) = stream_result {
+        // The connection may be broken or stuck in an aborted transaction; don't reuse it
+        let _ = conn.close().await;
+        return Err(e);
+    }
 
-        for (col_idx, col) in columns.iter().enumerate() {
-            if let Some(state) = column_states.get_mut(&col.name) {
-                // Process for individual column distribution
-                let process_result = process_row_value(&row, &col.name, state);
+    if in_transaction {
+        end_profiling_transaction(&mut conn).await?;
+    }
 
-                // Extract numeric value for correlation tracking
-                if let Some(ref mut num_row) = numeric_row {
-                    if numeric_indices.contains(&col_idx) {
-                        match extract_numeric_value(&row, &col.name, &col.data_type) {
-                            Ok(Some(value)) => {
-                                num_row.push(value);
-                            }
-                            Ok(None) => {
-                                row_has_null_numeric = true;
-                            }
-                            Err(_) => {
-                                row_has_null_numeric = true;
-                            }
-                        }
-                    }
-                }
+    Ok(scan)
+}
 
-                if let Err(e) = process_result {
-                    warn!(
-                        column = %col.name,
-                        error = %e,
-                        "Failed to process column value"
-                    );
-                }
-            }
-        }
+/// Returns true for failures worth retrying: dropped connections, pool timeouts and
+/// SQLSTATEs that signal a temporary server-side condition.
+fn is_transient_error(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|cause| cause.downcast_ref::<sqlx::Error>())
+        .any(|e| match e {
+            sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut | sqlx::Error::WorkerCrashed => true,
+            sqlx::Error::Database(db) => db
+                .code()
+                .is_some_and(|code| is_transient_sqlstate(&code)),
+            _ => false,
+        })
+}
 
-        //Add to numeric row reservoir if no NULLs in numeric columns
-        if let Some(num_row) = numeric_row {
-            if !row_has_null_numeric && num_row.len() == numeric_indices.len() {
-                if let Some(reservoir) = numeric_row_reservoir {
-                    reservoir.add(num_row);
-                }
-            }
-        }
+fn is_transient_sqlstate(code: &str) -> bool {
+    // 08xxx connection exceptions, 40001 serialization failure, 40P01 deadlock,
+    // 53300 too many connections, 57P01-57P03 shutdown / cannot connect now
+    code.starts_with("08")
+        || matches!(code, "40001" | "40P01" | "53300" | "57P01" | "57P02" | "57P03")
+}
+
+/// Opens the transaction a profiling query runs in, if it needs one: a snapshot
+/// transaction when a snapshot is shared, otherwise a plain read-only one when a cursor
+/// is used, since cursors only live inside a transaction. Returns whether one was opened.
+async fn begin_profiling_transaction(conn: &mut PgConnection, options: &ProfileOptions) -> Result<bool> {
+    if let Some(snapshot) = &options.snapshot {
+        begin_snapshot_transaction(conn, snapshot).await?;
+        return Ok(true);
+    }
+    if options.fetch_size.is_some() {
+        sqlx::query("BEGIN READ ONLY")
+            .execute(&mut *conn)
+            .await
+            .context("Failed to begin cursor transaction")?;
+        return Ok(true);
     }
+    Ok(false)
+}
 
+async fn end_profiling_transaction(conn: &mut PgConnection) -> Result<()> {
+    // Read-only transaction, nothing to keep; this also drops the cursor
+    sqlx::query("ROLLBACK")
+        .execute(&mut *conn)
+        .await
+        .context("Failed to end profiling transaction")?;
     Ok(())
 }
 
-fn extract_numeric_value(row: &PgRow, column_name: &str, data_type: &DataType) -> Result<Option<f64>> {
-    let value_ref = row.try_get_raw(column_name);
-    if value_ref?.is_null() {
-        return Ok(None);
-    }
+/// Streams the rows of `query`. With a `fetch_size` the query is declared as a
+/// server-side cursor and read `fetch_size` rows per round trip; this needs an open
+/// transaction (see [`begin_profiling_transaction`]).
+fn fetch_rows<'c>(
+    conn: &'c mut PgConnection,
+    query: &'c str,
+    fetch_size: Option<usize>,
+) -> BoxStream<'c, Result<PgRow, sqlx::Error>> {
+    let Some(fetch_size) = fetch_size else {
+        return sqlx::query(query).fetch(conn);
+    };
 
-    match data_type {
-        DataType::Integer => {
-            let value = row.try_get::<i64, _>(column_name)
-                .or_else(|_| row.try_get::<i32, _>(column_name).map(|v| v as i64))
-                .or_else(|_| row.try_get::<i16, _>(column_name).map(|v| v as i64))
-                .context("Failed to extract integer value")?;
+    let (declare, fetch) = cursor_statements(query, fetch_size);
+    let state = (conn, Some(declare), fetch, VecDeque::new(), false);
+    Box::pin(futures::stream::try_unfold(
+        state,
+        move |(conn, declare, fetch, mut batch, mut exhausted)| async move {
+            // Not cached: the FETCH text is the same for every table, but its columns are not
+            if let Some(declare) = declare {
+                sqlx::query(&declare).persistent(false).execute(&mut *conn).await?;
+            }
+            if batch.is_empty() && !exhausted {
+                let rows = sqlx::query(&fetch).persistent(false).fetch_all(&mut *conn).await?;
+                exhausted = rows.len() < fetch_size;
+                batch.extend(rows);
+            }
+            Ok(batch
+                .pop_front()
+                .map(|row| (row, (conn, None, fetch, batch, exhausted))))
+        },
+    ))
+}
 
-            Ok(Some(value as f64))
-        }
+/// `DECLARE` and `FETCH` statements reading `query` through [`CURSOR_NAME`].
+fn cursor_statements(query: &str, fetch_size: usize) -> (String, String) {
+    (
+        format!("DECLARE {} NO SCROLL CURSOR FOR {}", CURSOR_NAME, query),
+        format!("FETCH FORWARD {} FROM {}", fetch_size.max(1), CURSOR_NAME),
+    )
+}
 
-        DataType::Float => {
-            let value = row.try_get::<f64, _>(column_name)
-                .or_else(|_| row.try_get::<f32, _>(column_name).map(|v| v as f64))
-                .context("Failed to extract float value")?;
+async fn begin_snapshot_transaction(conn: &mut PgConnection, snapshot: &str) -> Result<()> {
+    sqlx::query("BEGIN ISOLATION LEVEL REPEATABLE READ READ ONLY")
+        .execute(&mut *conn)
+        .await
+        .context("Failed to begin snapshot transaction")?;
 
-            Ok(Some(value))
-        }
+    // SET TRANSACTION SNAPSHOT does not accept bind parameters
+    let set_snapshot = format!("SET TRANSACTION SNAPSHOT '{}'", snapshot.replace('\'', "''"));
+    sqlx::query(&set_snapshot)
+        .execute(&mut *conn)
+        .await
+        .context(format!("Failed to import snapshot '{}'", snapshot))?;
 
-        _ => {
-            anyhow::bail!("Non-numeric data type")
-        }
-    }
+    Ok(())
 }
 
-fn process_row_value(row: &PgRow, column_name: &str, state: &mut ColumnState) -> Result<()> {
-    // Check if value is NULL
-    let value_ref = row.try_get_raw(column_name)?;
-
-    if value_ref.is_null() {
-        state.null_count += 1;
-        return Ok(());
+/// Joins `child` to its parent over `fk` and profiles `columns` separately for every
+/// value of `parent_column`. Groups smaller than [`MIN_GROUP_ROWS`] are dropped, so
+/// generation falls back to the marginal for them.
+pub async fn profile_conditionals(
+    pool: &PgPool,
+    child: &Table,
+    columns: &[&Column],
+    fk: &ForeignKey,
+    parent_column: &Column,
+    options: &ProfileOptions,
+) -> Result<HashMap<String, ConditionalDistribution>> {
+    if columns.is_empty() {
+        return Ok(HashMap::new());
     }
 
-    // Extract value based on data type
-    match &state.data_type {
+    let filter = options.row_filters.get(&child.name).map(String::as_str);
+    let query = build_conditional_query(&relation_name(child), columns, fk, parent_column, filter, options.throttle);
+    debug!(table = %child.name, query = %query, "Constructed conditional profiling query");
+
+    let mut conn = pool
+        .acquire()
+        .await
+        .context("Failed to acquire profiling connection")?;
+
+    let in_transaction = begin_profiling_transaction(&mut conn, options).await?;
+
+    let owned_columns: Vec<Column> = columns.iter().map(|c| (*c).clone()).collect();
+    let mut groups: HashMap<String, (u64, HashMap<String, ColumnState>)> = HashMap::new();
+
+    {
+        use futures::TryStreamExt;
+
+        let mut stream = fetch_rows(&mut conn, &query, options.fetch_size);
+        while let Some(row) = stream.try_next().await? {
+            let Some(parent_value) = row.try_get::<Option<String>, _>(PARENT_VALUE_ALIAS)? else {
+                continue;
+            };
+
+            // Groups are many and each holds a slice of the rows, so they keep the default size
+            let (rows, states) = groups
+                .entry(parent_value)
+                .or_insert_with(|| (0, initialize_column_states(&owned_columns, |_| DEFAULT_RESERVOIR_CAPACITY, false, false, false)));
+            *rows += 1;
+
+            // The parent value comes first in the select list
+            for (index, column) in owned_columns.iter().enumerate() {
+                if let Some(state) = states.get_mut(&column.name)
+                    && let Err(e) = process_row_value(&row, index + 1, state)
+                {
+                    warn!(column = %column.name, error = %e, "Failed to process column value");
+                }
+            }
+        }
+    }
+
+    if in_transaction {
+        end_profiling_transaction(&mut conn).await?;
+    }
+
+    // Pattern, Markov and PII handling were already decided on the marginal
+    let group_options = ProfileOptions {
+        redact_pii: false,
+        markov_text: false,
+        ..options.clone()
+    };
+
+    let mut conditionals: HashMap<String, ConditionalDistribution> = columns
+        .iter()
+        .map(|c| {
+            (c.name.clone(), ConditionalDistribution {
+                foreign_key: fk.source_col.clone(),
+                parent_table: fk.target_table.clone(),
+                parent_column: parent_column.name.clone(),
+                by_parent_value: HashMap::new(),
+            })
+        })
+        .collect();
+
+    let group_count = groups.len();
+    for (parent_value, (rows, states)) in groups {
+        if rows < MIN_GROUP_ROWS {
+            continue;
+        }
+        for (column, state) in states {
+            let distribution = build_single_distribution(&child.name, &column, state, rows, &group_options);
+            if let Some(conditional) = conditionals.get_mut(&column) {
+                conditional.by_parent_value.insert(parent_value.clone(), distribution);
+            }
+        }
+    }
+
+    conditionals.retain(|_, c| !c.by_parent_value.is_empty());
+
+    info!(
+        table = %child.name,
+        parent = %fk.target_table,
+        parent_column = %parent_column.name,
+        groups = group_count,
+        columns = conditionals.len(),
+        "Profiled child columns conditioned on parent attribute"
+    );
+
+    Ok(conditionals)
+}
+
+/// Rows of `table` per value of its tenant `column`, under the table's row filter.
+/// Rows without a tenant are left out.
+pub async fn profile_tenant_counts(
+    pool: &PgPool,
+    table: &Table,
+    column: &str,
+    options: &ProfileOptions,
+) -> Result<HashMap<String, u64>> {
+    let filter = options.row_filters.get(&table.name).map(String::as_str);
+    let query = build_tenant_query(&relation_name(table), column, filter);
+    debug!(table = %table.name, query = %query, "Constructed tenant count query");
+
+    let mut conn = pool
+        .acquire()
+        .await
+        .context("Failed to acquire profiling connection")?;
+    let in_transaction = begin_profiling_transaction(&mut conn, options).await?;
+    let rows = sqlx::query(&query)
+        .fetch_all(&mut *conn)
+        .await
+        .context(format!("Failed to count rows of '{}' per tenant", table.name))?;
+    if in_transaction {
+        end_profiling_transaction(&mut conn).await?;
+    }
+
+    let mut counts = HashMap::with_capacity(rows.len());
+    for row in rows {
+        let tenant: String = row.try_get(0)?;
+        let count: i64 = row.try_get(1)?;
+        counts.insert(tenant, count.max(0) as u64);
+    }
+    Ok(counts)
+}
+
+fn build_tenant_query(relation: &str, column: &str, filter: Option<&str>) -> String {
+    let filter = filter.map(|predicate| format!(" AND ({})", predicate)).unwrap_or_default();
+    format!(
+        "SELECT {0}::text, count(*) FROM {1} WHERE {0} IS NOT NULL{2} GROUP BY 1",
+        column, relation, filter
+    )
+}
+
+/// How many parent rows of `fk` have each number of `child` rows, under both tables'
+/// row filters. Parents without children count as 0.
+pub async fn profile_fanout(
+    pool: &PgPool,
+    child: &Table,
+    fk: &ForeignKey,
+    parent: &Table,
+    options: &ProfileOptions,
+) -> Result<FanOut> {
+    let filtered = |table: &Table| {
+        let relation = relation_name(table);
+        match options.row_filters.get(&table.name) {
+            Some(predicate) => format!("(SELECT * FROM {} WHERE ({}))", relation, predicate),
+            None => relation,
+        }
+    };
+    let query = build_fanout_query(&filtered(child), fk, &filtered(parent));
+    debug!(table = %child.name, query = %query, "Constructed fan-out query");
+
+    let mut conn = pool
+        .acquire()
+        .await
+        .context("Failed to acquire profiling connection")?;
+    let in_transaction = begin_profiling_transaction(&mut conn, options).await?;
+    let rows = sqlx::query(&query)
+        .fetch_all(&mut *conn)
+        .await
+        .context(format!("Failed to count children of '{}' per '{}' row", child.name, parent.name))?;
+    if in_transaction {
+        end_profiling_transaction(&mut conn).await?;
+    }
+
+    let mut counts = Vec::with_capacity(rows.len());
+    for row in rows {
+        let children: i64 = row.try_get(0)?;
+        let parents: i64 = row.try_get(1)?;
+        counts.push((children.max(0) as u64, parents.max(0) as u64));
+    }
+    Ok(FanOut::from_counts(counts))
+}
+
+fn build_fanout_query(child: &str, fk: &ForeignKey, parent: &str) -> String {
+    format!(
+        "SELECT n, count(*) FROM (SELECT count(c.{0}) AS n FROM {1} p LEFT JOIN {2} c ON c.{0} = p.{3} \
+         GROUP BY p.{3}) g GROUP BY n",
+        fk.source_col, parent, child, fk.target_col
+    )
+}
+
+/// Only the child rows are filtered; every kept child row still finds its parent.
+fn build_conditional_query(
+    child: &str,
+    columns: &[&Column],
+    fk: &ForeignKey,
+    parent_column: &Column,
+    filter: Option<&str>,
+    throttle: Option<Duration>,
+) -> String {
+    let mut child_columns: Vec<String> = columns.iter().map(|c| select_item(c, "c.")).collect();
+    child_columns.extend(throttle_column(throttle));
+    // Group keys must match the values generation writes, which for booleans is t/f
+    let parent_value = match parent_column.data_type {
+        DataType::Boolean => format!("CASE WHEN p.{0} THEN 't' WHEN NOT p.{0} THEN 'f' END", parent_column.name),
+        _ => format!("p.{}::text", parent_column.name),
+    };
+    format!(
+        "SELECT {} AS {}, {} FROM {} c JOIN {} p ON c.{} = p.{}",
+        parent_value,
+        PARENT_VALUE_ALIAS,
+        child_columns.join(", "),
+        match filter {
+            Some(predicate) => format!("(SELECT * FROM {} WHERE ({}))", child, predicate),
+            None => child.to_string(),
+        },
+        fk.target_table,
+        fk.source_col,
+        fk.target_col,
+    )
+}
+
+/// `table`'s name as it appears in a FROM clause.
+fn relation_name(table: &Table) -> String {
+    if table.own_rows_only {
+        format!("ONLY {}", table.name)
+    } else {
+        table.name.clone()
+    }
+}
+
+/// Select-list item reading `column`, prefixed with `qualifier`. Bytea columns are
+/// read as their length, so the bytes never leave the server, intervals as seconds,
+/// float columns as `float8` (`numeric` has no f64 decoding; money goes through it for
+/// want of a float cast), geometries as their envelope, and network and hstore
+/// columns in text form.
+fn select_item(column: &Column, qualifier: &str) -> String {
+    match column.data_type {
+        DataType::Float if column.money => format!("{}{1}::numeric::float8 AS {1}", qualifier, column.name),
+        DataType::Float => format!("{}{1}::float8 AS {1}", qualifier, column.name),
+        DataType::Bytea => format!("octet_length({}{1}) AS {1}", qualifier, column.name),
+        DataType::Interval => format!("extract(epoch from {}{1})::float8 AS {1}", qualifier, column.name),
+        DataType::Geometry | DataType::Geography => {
+            format!("ST_AsEWKT(ST_Envelope({}{1}::geometry)) AS {1}", qualifier, column.name)
+        }
+        DataType::Inet | DataType::Cidr | DataType::MacAddr | DataType::Hstore => {
+            format!("{}{1}::text AS {1}", qualifier, column.name)
+        }
+        _ => format!("{}{}", qualifier, column.name),
+    }
+}
+
+fn build_select_query(table_name: &str, column_names: &[&str], filter: Option<&str>, throttle: Option<Duration>) -> String {
+    let mut columns: Vec<String> = column_names.iter().map(|c| c.to_string()).collect();
+    columns.extend(throttle_column(throttle));
+    let columns_clause = columns.join(", ");
+    match filter {
+        Some(predicate) => format!("SELECT {} FROM {} WHERE ({})", columns_clause, table_name, predicate),
+        None => format!("SELECT {} FROM {}", columns_clause, table_name),
+    }
+}
+
+/// Select-list item that makes the server sleep for `throttle` on every
+/// [`THROTTLE_INTERVAL`]th row it returns.
+fn throttle_column(throttle: Option<Duration>) -> Option<String> {
+    throttle.map(|pause| {
+        format!(
+            "CASE WHEN row_number() OVER () % {} = 0 THEN pg_sleep({}) IS NULL END AS {}",
+            THROTTLE_INTERVAL,
+            pause.as_secs_f64(),
+            THROTTLE_ALIAS
+        )
+    })
+}
+
+/// Mean share of the reservoirs' capacity that the scan filled, over the columns that
+/// keep one. `None` if no column does.
+fn reservoir_fill(column_states: &HashMap<String, ColumnState>) -> Option<f64> {
+    let fills: Vec<f64> = column_states
+        .values()
+        .filter_map(|state| {
+            let (size, capacity) = match (&state.numeric_reservoir, &state.text_reservoir) {
+                (Some(reservoir), _) => (reservoir.sample_size(), reservoir.capacity()),
+                (_, Some(reservoir)) => (reservoir.sample_size(), reservoir.capacity()),
+                (None, None) => return None,
+            };
+            Some(size as f64 / capacity as f64)
+        })
+        .collect();
+    (!fills.is_empty()).then(|| fills.iter().sum::<f64>() / fills.len() as f64)
+}
+
+fn initialize_column_states(
+    columns: &[Column],
+    capacity: impl Fn(&str) -> usize,
+    quantile_sketch: bool,
+    track_sequences: bool,
+    weighted: bool,
+) -> HashMap<String, ColumnState> {
+    columns
+        .iter()
+        .map(|col| {
+            let state = ColumnState::new(col.data_type.clone(), capacity(&col.name));
+            let state = if quantile_sketch { state.with_digest() } else { state };
+            let state = if weighted { state.with_weighted_sampling() } else { state };
+            // Primary keys are regenerated as 1..N anyway
+            let sequence_like = !col.is_primary_key && matches!(col.data_type, DataType::Integer | DataType::Timestamp);
+            let state = if track_sequences && sequence_like { state.with_sequence_tracking() } else { state };
+            (col.name.clone(), state)
+        })
+        .collect()
+}
+
+/// Whether each of `columns` is one of `numeric_columns`, by position, so the per-row
+/// loops test a flag instead of searching the list for every value.
+fn numeric_mask(columns: &[Column], numeric_columns: &[&Column]) -> Vec<bool> {
+    columns
+        .iter()
+        .map(|c| numeric_columns.iter().any(|nc| nc.name == c.name))
+        .collect()
+}
+
+/// Row sample a table's correlation matrix is computed from.
+fn row_reservoir(capacity: usize, weighted: bool) -> Reservoir<Vec<Option<f64>>> {
+    if weighted { Reservoir::weighted(capacity) } else { Reservoir::new(capacity) }
+}
+
+async fn stream_and_profile(
+    mut stream: BoxStream<'_, Result<PgRow, sqlx::Error>>,
+    columns: &[Column],
+    numeric_columns: &[&Column],
+    scan: &mut TableScan,
+    recency: Option<&Recency>,
+    progress: Option<(&str, &dyn ScanProgress)>,
+) -> Result<()> {
+    use futures::TryStreamExt;
+
+    // Values are read by their position in the select list, which follows `columns`
+    let is_numeric = numeric_mask(columns, numeric_columns);
+    let recency_index = recency.and_then(|r| columns.iter().position(|c| c.name == r.column));
+
+    // Process each row from the stream
+    while let Some(row) = stream.try_next().await? {
+        let rows = scan.total_rows.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some((table, progress)) = progress
+            && rows.is_multiple_of(SCAN_PROGRESS_INTERVAL)
+        {
+            progress.rows_scanned(table, rows);
+        }
+
+        // Rows with a NULL date keep the weight of the row before them
+        if let Some(recency) = recency
+            && let Some(epoch_seconds) = recency_index.and_then(|i| extract_timestamp(&row, i))
+        {
+            scan.set_log_weight(recency.log_weight(epoch_seconds));
+        }
+
+        //Track numeric values for correlation, NULLs included (pairwise deletion)
+        let mut numeric_row: Vec<Option<f64>> = Vec::with_capacity(numeric_columns.len());
+
+        for (col_idx, col) in columns.iter().enumerate() {
+            if let Some(state) = scan.column_states.get_mut(&col.name) {
+                // Process for individual column distribution
+                let process_result = process_row_value(&row, col_idx, state);
+
+                // Extract numeric value for correlation tracking
+                if is_numeric[col_idx] {
+                    numeric_row.push(extract_numeric_value(&row, col_idx, &col.data_type).ok().flatten());
+                }
+
+                if let Err(e) = process_result {
+                    warn!(
+                        column = %col.name,
+                        error = %e,
+                        "Failed to process column value"
+                    );
+                }
+            }
+        }
+
+        add_numeric_row(&mut scan.numeric_row_reservoir, numeric_row);
+    }
+
+    Ok(())
+}
+
+/// Rows sent to the column workers of [`stream_and_profile_sharded`] at a time.
+const COLUMN_BATCH_ROWS: usize = 256;
+
+/// Batches queued per column worker before reading the next rows waits for it.
+const COLUMN_BATCHES_QUEUED: usize = 4;
+
+/// Fewest columns a column worker is given; narrower tables use fewer workers.
+const MIN_COLUMNS_PER_WORKER: usize = 16;
+
+/// Column workers for a table of `columns` columns: `requested`, but no more than
+/// leave each worker [`MIN_COLUMNS_PER_WORKER`] columns. 1 profiles on the reading task.
+fn column_worker_count(requested: Option<usize>, columns: usize) -> usize {
+    requested.unwrap_or(1).min(columns / MIN_COLUMNS_PER_WORKER).max(1)
+}
+
+/// Rows shared by every column worker, with the recency weight of each (`None` keeps
+/// the weight of the row before).
+struct RowBatch {
+    rows: Vec<PgRow>,
+    log_weights: Vec<Option<f64>>,
+}
+
+/// A column worker's columns: position in the select list, name and state.
+type ColumnShard = Vec<(usize, String, ColumnState)>;
+
+/// [`stream_and_profile`] for wide tables: columns are dealt round-robin to `workers`
+/// blocking tasks, and every task receives the same batches of rows and decodes only
+/// its own columns. The reading task keeps the recency weights and the correlation
+/// sample, which need whole rows.
+async fn stream_and_profile_sharded(
+    mut stream: BoxStream<'_, Result<PgRow, sqlx::Error>>,
+    columns: &[Column],
+    numeric_columns: &[&Column],
+    scan: &mut TableScan,
+    recency: Option<&Recency>,
+    progress: Option<(&str, &dyn ScanProgress)>,
+    workers: usize,
+) -> Result<()> {
+    use futures::TryStreamExt;
+
+    let mut shards: Vec<ColumnShard> = (0..workers).map(|_| Vec::new()).collect();
+    for (index, column) in columns.iter().enumerate() {
+        if let Some(state) = scan.column_states.remove(&column.name) {
+            shards[index % workers].push((index, column.name.clone(), state));
+        }
+    }
+
+    let mut senders = Vec::with_capacity(workers);
+    let mut handles = Vec::with_capacity(workers);
+    for mut shard in shards {
+        let (sender, mut receiver) = tokio::sync::mpsc::channel::<Arc<RowBatch>>(COLUMN_BATCHES_QUEUED);
+        senders.push(sender);
+        handles.push(tokio::task::spawn_blocking(move || {
+            while let Some(batch) = receiver.blocking_recv() {
+                for (row, log_weight) in batch.rows.iter().zip(&batch.log_weights) {
+                    for (index, name, state) in shard.iter_mut() {
+                        if let Some(log_weight) = log_weight {
+                            state.set_log_weight(*log_weight);
+                        }
+                        if let Err(e) = process_row_value(row, *index, state) {
+                            warn!(column = %name, error = %e, "Failed to process column value");
+                        }
+                    }
+                }
+            }
+            shard
+        }));
+    }
+
+    let is_numeric = numeric_mask(columns, numeric_columns);
+    let recency_index = recency.and_then(|r| columns.iter().position(|c| c.name == r.column));
+    let mut batch = RowBatch { rows: Vec::with_capacity(COLUMN_BATCH_ROWS), log_weights: Vec::with_capacity(COLUMN_BATCH_ROWS) };
+
+    loop {
+        let row = stream.try_next().await?;
+        let batch_done = row.is_none() || batch.rows.len() == COLUMN_BATCH_ROWS;
+        if batch_done && !batch.rows.is_empty() {
+            let full = Arc::new(std::mem::replace(&mut batch, RowBatch {
+                rows: Vec::with_capacity(COLUMN_BATCH_ROWS),
+                log_weights: Vec::with_capacity(COLUMN_BATCH_ROWS),
+            }));
+            for sender in &senders {
+                if sender.send(Arc::clone(&full)).await.is_err() {
+                    anyhow::bail!("Column worker stopped before the table was read");
+                }
+            }
+        }
+        let Some(row) = row else {
+            break;
+        };
+
+        let rows = scan.total_rows.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some((table, progress)) = progress
+            && rows.is_multiple_of(SCAN_PROGRESS_INTERVAL)
+        {
+            progress.rows_scanned(table, rows);
+        }
+
+        // Rows with a NULL date keep the weight of the row before them
+        let log_weight = recency
+            .zip(recency_index.and_then(|i| extract_timestamp(&row, i)))
+            .map(|(recency, epoch_seconds)| recency.log_weight(epoch_seconds));
+        if let Some(log_weight) = log_weight
+            && let Some(reservoir) = &mut scan.numeric_row_reservoir
+        {
+            reservoir.set_log_weight(log_weight);
+        }
+
+        let numeric_row: Vec<Option<f64>> = columns
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| is_numeric[*index])
+            .map(|(index, column)| extract_numeric_value(&row, index, &column.data_type).ok().flatten())
+            .collect();
+        add_numeric_row(&mut scan.numeric_row_reservoir, numeric_row);
+
+        batch.rows.push(row);
+        batch.log_weights.push(log_weight);
+    }
+
+    drop(senders);
+    for handle in handles {
+        let shard = handle.await.context("Column worker failed")?;
+        scan.column_states.extend(shard.into_iter().map(|(_, name, state)| (name, state)));
+    }
+
+    Ok(())
+}
+
+/// Rows decoded into the buffer of [`stream_and_profile_numeric`] before it is profiled.
+const NUMERIC_BATCH_ROWS: usize = 1024;
+
+/// Whether every column of `table` is an integer or float, so its rows can be read as
+/// float8 only and profiled by [`stream_and_profile_numeric`].
+fn is_numeric_only(table: &Table) -> bool {
+    !table.columns.is_empty()
+        && table
+            .columns
+            .iter()
+            .all(|c| matches!(c.data_type, DataType::Integer | DataType::Float))
+}
+
+/// Select-list item reading an integer or float column as float8.
+fn float8_select_item(column: &Column) -> String {
+    match column.data_type {
+        DataType::Integer => format!("{0}::float8 AS {0}", column.name),
+        _ => select_item(column, ""),
+    }
+}
+
+/// [`stream_and_profile`] for numeric-only tables: rows are decoded into one flat
+/// buffer of float8 values, NULL as NaN, and each full batch is profiled column by
+/// column without looking at types. A NaN stored in a float column counts as NULL.
+/// Recency weighting needs a timestamp column, so these tables never have it.
+async fn stream_and_profile_numeric(
+    mut stream: BoxStream<'_, Result<PgRow, sqlx::Error>>,
+    columns: &[Column],
+    scan: &mut TableScan,
+    progress: Option<(&str, &dyn ScanProgress)>,
+) -> Result<()> {
+    use futures::TryStreamExt;
+
+    let width = columns.len();
+    let mut buffer: Vec<f64> = Vec::with_capacity(NUMERIC_BATCH_ROWS * width);
+
+    while let Some(row) = stream.try_next().await? {
+        let rows = scan.total_rows.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some((table, progress)) = progress
+            && rows.is_multiple_of(SCAN_PROGRESS_INTERVAL)
+        {
+            progress.rows_scanned(table, rows);
+        }
+
+        for index in 0..width {
+            buffer.push(row.try_get::<Option<f64>, _>(index)?.unwrap_or(f64::NAN));
+        }
+        if buffer.len() == buffer.capacity() {
+            profile_numeric_batch(&buffer, columns, scan);
+            buffer.clear();
+        }
+    }
+    profile_numeric_batch(&buffer, columns, scan);
+
+    Ok(())
+}
+
+/// Adds a batch of row-major float8 values, NaN for NULL, to the column states and
+/// the correlation sample.
+fn profile_numeric_batch(buffer: &[f64], columns: &[Column], scan: &mut TableScan) {
+    let width = columns.len();
+    if buffer.is_empty() || width == 0 {
+        return;
+    }
+
+    for (index, column) in columns.iter().enumerate() {
+        let Some(state) = scan.column_states.get_mut(&column.name) else {
+            continue;
+        };
+        for &value in buffer[index..].iter().step_by(width) {
+            if value.is_nan() {
+                state.null_count += 1;
+            } else {
+                state.add_numeric(value);
+            }
+        }
+    }
+
+    for row in buffer.chunks_exact(width) {
+        let numeric_row = row.iter().map(|&value| (!value.is_nan()).then_some(value)).collect();
+        add_numeric_row(&mut scan.numeric_row_reservoir, numeric_row);
+    }
+}
+
+/// Samples a row's numeric values for correlation. NULL and unparsable values stay in
+/// as `None` and only leave out the pairs they are part of; rows with fewer than two
+/// values take part in no pair and are skipped.
+fn add_numeric_row(reservoir: &mut Option<Reservoir<Vec<Option<f64>>>>, numeric_row: Vec<Option<f64>>) {
+    if numeric_row.iter().flatten().nth(1).is_some()
+        && let Some(reservoir) = reservoir
+    {
+        reservoir.add(numeric_row);
+    }
+}
+
+/// Value at `index` of `row` as it enters the correlation sample.
+fn extract_numeric_value(row: &PgRow, index: usize, data_type: &DataType) -> Result<Option<f64>> {
+    let value_ref = row.try_get_raw(index);
+    if value_ref?.is_null() {
+        return Ok(None);
+    }
+
+    match data_type {
         DataType::Integer => {
-            // Try i64 first, then i32, then i16
-            let value = row.try_get::<i64, _>(column_name)
-                .or_else(|_| row.try_get::<i32, _>(column_name).map(|v| v as i64))
-                .or_else(|_| row.try_get::<i16, _>(column_name).map(|v| v as i64))
+            let value = row.try_get::<i64, _>(index)
+                .or_else(|_| row.try_get::<i32, _>(index).map(|v| v as i64))
+                .or_else(|_| row.try_get::<i16, _>(index).map(|v| v as i64))
                 .context("Failed to extract integer value")?;
 
-            if let Some(ref mut reservoir) = state.numeric_reservoir {
-                reservoir.add(value as f64);
-            }
+            Ok(Some(value as f64))
         }
 
         DataType::Float => {
+            let value = row.try_get::<f64, _>(index)
+                .or_else(|_| row.try_get::<f32, _>(index).map(|v| v as f64))
+                .context("Failed to extract float value")?;
+
+            Ok(Some(value))
+        }
+
+        DataType::Timestamp => {
+            let value = extract_timestamp(row, index).context("Failed to extract timestamp value")?;
+            Ok(Some(value))
+        }
+
+        _ => {
+            anyhow::bail!("Non-numeric data type")
+        }
+    }
+}
+
+/// Epoch seconds of the timestamp at `index` of `row`, `None` if NULL or not read as
+/// a timestamp.
+fn extract_timestamp(row: &PgRow, index: usize) -> Option<f64> {
+    if let Ok(ts) = row.try_get::<chrono::NaiveDateTime, _>(index) {
+        Some(ts.and_utc().timestamp() as f64)
+    } else {
+        row.try_get::<chrono::DateTime<chrono::Utc>, _>(index)
+            .ok()
+            .map(|ts| ts.timestamp() as f64)
+    }
+}
+
+/// Adds the value at `index` of `row`, its position in the select list, to `state`.
+fn process_row_value(row: &PgRow, index: usize, state: &mut ColumnState) -> Result<()> {
+    // Check if value is NULL
+    let value_ref = row.try_get_raw(index)?;
+
+    if value_ref.is_null() {
+        state.null_count += 1;
+        return Ok(());
+    }
+
+    // Extract value based on data type
+    match &state.data_type {
+        // Read as `octet_length`, see `select_item`
+        DataType::Integer | DataType::Bytea => {
+            // Try i64 first, then i32, then i16
+            let value = row.try_get::<i64, _>(index)
+                .or_else(|_| row.try_get::<i32, _>(index).map(|v| v as i64))
+                .or_else(|_| row.try_get::<i16, _>(index).map(|v| v as i64))
+                .context("Failed to extract integer value")?;
+
+            state.add_numeric(value as f64);
+        }
+
+        // Intervals are read as seconds, see `select_item`
+        DataType::Float | DataType::Interval => {
             // Try f64 first, then f32
-            let value = row.try_get::<f64, _>(column_name)
-                .or_else(|_| row.try_get::<f32, _>(column_name).map(|v| v as f64))
+            let value = row.try_get::<f64, _>(index)
+                .or_else(|_| row.try_get::<f32, _>(index).map(|v| v as f64))
                 .context("Failed to extract float value")?;
 
-            if let Some(ref mut reservoir) = state.numeric_reservoir {
-                reservoir.add(value);
-            }
+            state.add_numeric(value);
         }
 
         DataType::Timestamp => {
             // Extract timestamp and convert to Unix epoch for numeric analysis
-            if let Ok(ts) = row.try_get::<chrono::NaiveDateTime, _>(column_name) {
-                let epoch_seconds = ts.and_utc().timestamp() as f64;
-                if let Some(ref mut reservoir) = state.numeric_reservoir {
-                    reservoir.add(epoch_seconds);
-                }
-            } else if let Ok(ts) = row.try_get::<chrono::DateTime<chrono::Utc>, _>(column_name) {
-                let epoch_seconds = ts.timestamp() as f64;
-                if let Some(ref mut reservoir) = state.numeric_reservoir {
-                    reservoir.add(epoch_seconds);
-                }
+            if let Some(epoch_seconds) = extract_timestamp(row, index) {
+                state.add_timestamp(epoch_seconds);
             } else {
                 // Fallback: treat as text
-                let value: String = row.try_get(column_name)?;
+                let value: String = row.try_get(index)?;
                 if let Some(ref mut reservoir) = state.text_reservoir {
                     reservoir.add(value);
                 }
             }
         }
 
-        DataType::Text | DataType::Uuid => {
-            let value: String = row.try_get(column_name)
-                .context("Failed to extract text value")?;
+        DataType::Text | DataType::Uuid => {
+            let value: String = row.try_get(index)
+                .context("Failed to extract text value")?;
+
+            if let Some(ref mut sketch) = state.heavy_hitters {
+                sketch.add(&value);
+            }
+
+            if let Some(ref mut reservoir) = state.text_reservoir {
+                reservoir.add(value);
+            }
+        }
+
+        // Read as text, see `select_item`
+        DataType::Hstore => {
+            let value: String = row.try_get(index)
+                .context("Failed to extract hstore value")?;
+            let pairs = parse_hstore(&value).context(format!("Invalid hstore '{}'", value))?;
+            state.add_pairs(pairs);
+        }
+
+        // Read as the EWKT of their envelope, see `select_item`
+        DataType::Geometry | DataType::Geography => {
+            let value: String = row.try_get(index)
+                .context("Failed to extract geometry value")?;
+
+            if let Some(ref mut extents) = state.extents
+                && !extents.add(&value)
+            {
+                anyhow::bail!("Invalid {} '{}'", state.data_type, value);
+            }
+        }
+
+        // Read as text, see `select_item`
+        DataType::Inet | DataType::Cidr | DataType::MacAddr => {
+            let value: String = row.try_get(index)
+                .context("Failed to extract network value")?;
+            let prefix = prefix_bucket(&state.data_type, &value)
+                .context(format!("Invalid {} '{}'", state.data_type, value))?;
 
             if let Some(ref mut reservoir) = state.text_reservoir {
-                reservoir.add(value);
+                reservoir.add(prefix);
             }
         }
 
         DataType::Boolean => {
-            let value: bool = row.try_get(column_name)
+            let value: bool = row.try_get(index)
                 .context("Failed to extract boolean value")?;
 
-            if let Some(ref mut reservoir) = state.text_reservoir {
-                reservoir.add(value.to_string());
+            if let Some(ref mut bernoulli) = state.bernoulli {
+                bernoulli.add(value);
             }
         }
     }
@@ -358,24 +1649,153 @@ fn process_row_value(row: &PgRow, column_name: &str, state: &mut ColumnState) ->
     Ok(())
 }
 
+/// Distributions for a table without rows: an empty histogram would make every
+/// generated value the same, so each column gets the type-based placeholder that
+/// `scan --from-ddl` uses, flagged as `synthetic_default`.
+fn default_distributions(table: &Table) -> HashMap<String, Distribution> {
+    warn!(table = %table.name, "Table is empty, using default distributions for its column types");
+
+    table
+        .columns
+        .iter()
+        .map(|column| {
+            let mut distribution = placeholder_distribution(column, None, None);
+            distribution.synthetic_default = true;
+            (column.name.clone(), distribution)
+        })
+        .collect()
+}
+
 fn build_distributions(
-    columns: &[Column],
+    table_name: &str,
     column_states: HashMap<String, ColumnState>,
     total_rows: &Arc<AtomicU64>,
+    options: &ProfileOptions,
 ) -> HashMap<String, Distribution> {
     let total_count = total_rows.load(Ordering::Relaxed);
 
     column_states
         .into_iter()
         .map(|(col_name, state)| {
-            let distribution = build_single_distribution(state, total_count);
+            let distribution = build_single_distribution(table_name, &col_name, state, total_count, options);
             (col_name, distribution)
         })
         .collect()
 }
 
-fn build_single_distribution(state: ColumnState, total_count: u64) -> Distribution {
-    let mut builder = DistributionBuilder::new(total_count, state.null_count);
+fn build_single_distribution(
+    table_name: &str,
+    column_name: &str,
+    state: ColumnState,
+    total_count: u64,
+    options: &ProfileOptions,
+) -> Distribution {
+    if let Some(bernoulli) = state.bernoulli {
+        let unique_count = (bernoulli.true_count > 0) as usize + (bernoulli.false_count > 0) as usize;
+        return Distribution::new(
+            None,
+            None,
+            state.null_count,
+            total_count,
+            unique_count,
+            Histogram::Boolean { bernoulli },
+        );
+    }
+
+    if let Some(extents) = state.extents {
+        let frequencies = extents.frequencies();
+        return Distribution::new(
+            None,
+            None,
+            state.null_count,
+            total_count,
+            frequencies.len(),
+            Histogram::Categorical { frequencies, truncated: false, long_tail: None },
+        );
+    }
+
+    let pii = match (&state.data_type, &state.text_reservoir) {
+        (DataType::Text, Some(reservoir)) => classify_pii(column_name, reservoir.sample()),
+        _ => None,
+    };
+
+    if let Some(kind) = pii {
+        if options.redact_pii {
+            info!(
+                table = %table_name,
+                column = %column_name,
+                pii = %kind,
+                "Redacting PII column, values replaced by synthetic generator"
+            );
+
+            let unique_count = state.text_reservoir.as_ref().map_or(0, |r| r.sample_size());
+            let mut distribution = Distribution::new(
+                None,
+                None,
+                state.null_count,
+                total_count,
+                unique_count,
+                Histogram::Semantic { semantic_type: kind },
+            );
+            distribution.pii = Some(kind);
+            distribution.lengths = state.text_reservoir.as_ref().map(|r| LengthDistribution::from_values(r.sample()));
+            return distribution;
+        }
+
+        warn!(
+            table = %table_name,
+            column = %column_name,
+            pii = %kind,
+            "Column looks like PII and its values are stored in the genome (use --redact-pii)"
+        );
+    }
+
+    // Prefixes stay categorical, so generated addresses fall in real networks
+    let replays_text = matches!(state.data_type, DataType::Text | DataType::Uuid);
+
+    if pii.is_none()
+        && replays_text
+        && let Some(reservoir) = &state.text_reservoir
+        && let Some(distribution) = build_pattern_distribution(table_name, column_name, reservoir, state.null_count, total_count)
+    {
+        return distribution;
+    }
+
+    if pii.is_none()
+        && replays_text
+        && options.markov_text
+        && let Some(reservoir) = &state.text_reservoir
+        && is_free_text(reservoir.sample())
+        && let Some(model) = MarkovModel::train(reservoir.sample())
+    {
+        debug!(
+            table = %table_name,
+            column = %column_name,
+            vocabulary = model.transitions.len(),
+            "Trained Markov text model for free-form column"
+        );
+        let mut distribution = Distribution::new(
+            None,
+            None,
+            state.null_count,
+            total_count,
+            reservoir.sample().iter().collect::<HashSet<_>>().len(),
+            Histogram::Markov { model },
+        );
+        distribution.lengths = Some(LengthDistribution::from_values(reservoir.sample()));
+        return distribution;
+    }
+
+    let mut builder = DistributionBuilder::new(total_count, state.null_count)
+        .with_parametric_fit(options.fit_distributions)
+        .with_binning(options.binning, options.bins.unwrap_or(NUMERIC_HISTOGRAM_BINS))
+        .with_winsorization(options.winsorize)
+        .with_discrete_integers(matches!(state.data_type, DataType::Integer | DataType::Bytea))
+        // A quantile sketch keeps point masses as steps in its CDF
+        .with_point_masses(
+            !options.quantile_sketch
+                && matches!(state.data_type, DataType::Integer | DataType::Float | DataType::Interval),
+        );
 
     // Process numeric reservoir
     if let Some(reservoir) = state.numeric_reservoir {
@@ -391,34 +1811,740 @@ fn build_single_distribution(state: ColumnState, total_count: u64) -> Distributi
         }
     }
 
-    builder.build()
+    let mut distribution = builder.build();
+    distribution.pii = pii;
+    distribution.pair_counts = state.pair_counts.filter(|counts| !counts.is_empty());
+
+    if let (Some(sketch), Histogram::Categorical { frequencies, long_tail, .. }) =
+        (&state.heavy_hitters, &mut distribution.histogram)
+        && sketch.overflowed()
+    {
+        let top = sketch.top_k(HEAVY_HITTER_TOP_K);
+        let tracked: u64 = top.iter().map(|(_, c)| c.count).sum();
+        let tail_sample: Vec<String> = frequencies
+            .keys()
+            .filter(|value| !top.iter().any(|(v, _)| v == *value))
+            .cloned()
+            .collect();
+
+        debug!(
+            table = %table_name,
+            column = %column_name,
+            heavy_hitters = top.len(),
+            tail_rows = sketch.total().saturating_sub(tracked),
+            "Modeling high-cardinality column as heavy hitters plus long tail"
+        );
+
+        *long_tail = Some(LongTail {
+            count: sketch.total().saturating_sub(tracked),
+            pattern: infer_pattern(&tail_sample),
+            semantic_type: None,
+        });
+        *frequencies = top.into_iter().map(|(value, counter)| (value, counter.count)).collect();
+    }
+
+    if let Some(min_count) = options.min_category_count
+        && state.data_type == DataType::Text
+    {
+        let folded = fold_rare_categories(&mut distribution, min_count);
+        if folded > 0 {
+            debug!(
+                table = %table_name,
+                column = %column_name,
+                folded,
+                "Folded rare categories into the generated tail"
+            );
+        }
+    }
+
+    // A small integer domain is already stored exactly
+    if let Some(mut digest) = state.digest.filter(|d| !d.is_empty())
+        && !matches!(distribution.histogram, Histogram::Discrete { .. })
+    {
+        digest.compress();
+        distribution.min = Some(digest.min);
+        distribution.max = Some(digest.max);
+        distribution.non_negative = digest.min >= 0.0;
+        distribution.histogram = Histogram::Quantile { digest };
+        // The digest saw every row unclipped
+        distribution.outliers = None;
+    }
+
+    if let (Some(profile), Some(min), Some(max)) = (state.seasonality, distribution.min, distribution.max) {
+        distribution.seasonality = profile.finish(min, max);
+    }
+
+    if let Some(sequence) = state.sequence.and_then(|t| t.finish(state.data_type == DataType::Integer)) {
+        debug!(
+            table = %table_name,
+            column = %column_name,
+            start = sequence.start,
+            "Column values increase in scan order, generating as a sequence"
+        );
+        distribution.sequence = Some(sequence);
+    }
+
+    distribution
+}
+
+/// Moves categories counted fewer than `min_count` times into the long tail, so one-off
+/// values (a single customer's name) are generated by faker or a pattern instead of
+/// replayed. Returns the number of categories folded.
+fn fold_rare_categories(distribution: &mut Distribution, min_count: u64) -> usize {
+    let pii = distribution.pii;
+    let Histogram::Categorical { frequencies, long_tail, .. } = &mut distribution.histogram else {
+        return 0;
+    };
+    let rare: Vec<String> = frequencies
+        .iter()
+        .filter(|(_, count)| **count < min_count)
+        .map(|(value, _)| value.clone())
+        .collect();
+    if rare.is_empty() {
+        return 0;
+    }
+
+    let rare_rows: u64 = rare.iter().filter_map(|value| frequencies.remove(value)).sum();
+    let tail = long_tail.get_or_insert(LongTail { count: 0, pattern: None, semantic_type: None });
+    tail.count += rare_rows;
+    tail.pattern = infer_pattern(&rare).or(tail.pattern.take());
+    tail.semantic_type = tail.semantic_type.or(pii);
+    rare.len()
+}
+
+/// Replaces categorical replay with a generated pattern for high-cardinality text
+/// columns whose values share one shape (SKUs, order numbers, ...).
+fn build_pattern_distribution(
+    table_name: &str,
+    column_name: &str,
+    reservoir: &Reservoir<String>,
+    null_count: u64,
+    total_count: u64,
+) -> Option<Distribution> {
+    let sample = reservoir.sample();
+    let unique_count = sample.iter().collect::<HashSet<_>>().len();
+
+    if (unique_count as f64) < PATTERN_MIN_UNIQUE_RATIO * sample.len() as f64 {
+        return None;
+    }
+
+    let pattern = infer_pattern(sample)?;
+
+    debug!(
+        table = %table_name,
+        column = %column_name,
+        pattern = %pattern,
+        "Inferred text pattern for high-cardinality column"
+    );
+
+    let mut distribution = Distribution::new(
+        None,
+        None,
+        null_count,
+        total_count,
+        unique_count,
+        Histogram::Pattern { pattern },
+    );
+    distribution.lengths = Some(LengthDistribution::from_values(sample));
+    Some(distribution)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::schema::Column;
+    use crate::semantic::SemanticType;
+
+    #[test]
+    fn test_cursor_statements() {
+        let (declare, fetch) = cursor_statements("SELECT id FROM users", 500);
+        assert_eq!(declare, "DECLARE replica_scan NO SCROLL CURSOR FOR SELECT id FROM users");
+        assert_eq!(fetch, "FETCH FORWARD 500 FROM replica_scan");
+
+        let (_, fetch) = cursor_statements("SELECT id FROM users", 0);
+        assert_eq!(fetch, "FETCH FORWARD 1 FROM replica_scan");
+    }
 
     #[test]
     fn test_build_select_query() {
-        let query = build_select_query("users", &["id", "name", "email"]);
+        let query = build_select_query("users", &["id", "name", "email"], None, None);
         assert_eq!(query, "SELECT id, name, email FROM users");
+
+        let mut parent = Table::new("people".to_string(), vec![], vec![]);
+        parent.own_rows_only = true;
+        assert_eq!(build_select_query(&relation_name(&parent), &["id"], None, None), "SELECT id FROM ONLY people");
+
+        assert_eq!(
+            build_select_query("events", &["id"], Some("created_at > now() - interval '90 days'"), None),
+            "SELECT id FROM events WHERE (created_at > now() - interval '90 days')"
+        );
+        assert_eq!(
+            build_select_query("events", &["id"], None, Some(Duration::from_millis(50))),
+            "SELECT id, CASE WHEN row_number() OVER () % 10000 = 0 THEN pg_sleep(0.05) IS NULL END AS replica_throttle \
+             FROM events"
+        );
+    }
+
+    #[test]
+    fn test_numeric_only_tables_decode_float8_batches() {
+        let id = Column::new("id".to_string(), DataType::Integer, false, true);
+        let amount = Column::new("amount".to_string(), DataType::Float, true, false);
+        let note = Column::new("note".to_string(), DataType::Text, true, false);
+        let table = Table::new("metrics".to_string(), vec![id.clone(), amount.clone()], vec![]);
+        assert!(is_numeric_only(&table));
+        assert!(!is_numeric_only(&Table::new("notes".to_string(), vec![id.clone(), note], vec![])));
+        assert_eq!(float8_select_item(&id), "id::float8 AS id");
+        assert_eq!(float8_select_item(&amount), "amount::float8 AS amount");
+
+        let column_states = initialize_column_states(&table.columns, |_| 100, false, true, false);
+        let mut scan = TableScan {
+            column_states,
+            numeric_row_reservoir: Some(row_reservoir(100, false)),
+            total_rows: Arc::new(AtomicU64::new(3)),
+        };
+        profile_numeric_batch(&[1.0, 2.5, 2.0, f64::NAN, 3.0, 7.5], &table.columns, &mut scan);
+
+        assert_eq!(scan.column_states["id"].numeric_reservoir.as_ref().unwrap().sample(), [1.0, 2.0, 3.0]);
+        assert_eq!(scan.column_states["amount"].null_count, 1);
+        assert_eq!(scan.column_states["amount"].numeric_reservoir.as_ref().unwrap().sample(), [2.5, 7.5]);
+        // The row with a NULL amount holds a single value and takes part in no pair
+        let rows = scan.numeric_row_reservoir.unwrap();
+        assert_eq!(rows.sample(), [vec![Some(1.0), Some(2.5)], vec![Some(3.0), Some(7.5)]]);
+    }
+
+    #[test]
+    fn test_column_workers_keep_enough_columns() {
+        assert_eq!(column_worker_count(None, 500), 1);
+        assert_eq!(column_worker_count(Some(8), 300), 8);
+        assert_eq!(column_worker_count(Some(8), 40), 2);
+        assert_eq!(column_worker_count(Some(8), 20), 1);
+        assert_eq!(column_worker_count(Some(8), 0), 1);
+    }
+
+    #[test]
+    fn test_conditional_query_filters_child() {
+        let amount = Column::new("amount".to_string(), DataType::Float, true, false);
+        let tier = Column::new("tier".to_string(), DataType::Text, true, false);
+        let fk = ForeignKey::new("user_id".to_string(), "users".to_string(), "id".to_string());
+
+        assert_eq!(
+            build_conditional_query("orders", &[&amount], &fk, &tier, Some("amount > 0"), None),
+            "SELECT p.tier::text AS replica_parent_value, c.amount::float8 AS amount \
+             FROM (SELECT * FROM orders WHERE (amount > 0)) c JOIN users p ON c.user_id = p.id"
+        );
+    }
+
+    #[test]
+    fn test_tenant_query_counts_per_tenant() {
+        assert_eq!(
+            build_tenant_query("ONLY orders", "tenant_id", Some("status <> 'test'")),
+            "SELECT tenant_id::text, count(*) FROM ONLY orders WHERE tenant_id IS NOT NULL AND (status <> 'test') GROUP BY 1"
+        );
+    }
+
+    #[test]
+    fn test_fanout_query_counts_childless_parents() {
+        let fk = ForeignKey::new("user_id".to_string(), "users".to_string(), "id".to_string());
+        assert_eq!(
+            build_fanout_query("(SELECT * FROM orders WHERE (amount > 0))", &fk, "users"),
+            "SELECT n, count(*) FROM (SELECT count(c.user_id) AS n FROM users p \
+             LEFT JOIN (SELECT * FROM orders WHERE (amount > 0)) c ON c.user_id = p.id GROUP BY p.id) g GROUP BY n"
+        );
+    }
+
+    #[test]
+    fn test_retry_backoff_is_capped() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+        };
+
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(400));
+        assert_eq!(policy.backoff(8), Duration::from_secs(1));
+        assert_eq!(policy.backoff(64), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_transient_error_classification() {
+        let io = sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset"));
+        let err = anyhow::Error::new(io).context("Failed during streaming profiling");
+        assert!(is_transient_error(&err));
+
+        let decode = anyhow::Error::new(sqlx::Error::RowNotFound);
+        assert!(!is_transient_error(&decode));
+
+        assert!(is_transient_sqlstate("08006"));
+        assert!(is_transient_sqlstate("40001"));
+        assert!(!is_transient_sqlstate("42P01"));
+    }
+
+    #[test]
+    fn test_pii_column_redacted() {
+        let mut state = ColumnState::new(DataType::Text, DEFAULT_RESERVOIR_CAPACITY);
+        if let Some(reservoir) = state.text_reservoir.as_mut() {
+            reservoir.add("alice@example.com".to_string());
+            reservoir.add("bob@example.org".to_string());
+        }
+
+        let options = ProfileOptions {
+            redact_pii: true,
+            ..ProfileOptions::default()
+        };
+        let dist = build_single_distribution("users", "contact", state, 2, &options);
+
+        assert_eq!(dist.pii, Some(SemanticType::Email));
+        assert!(matches!(
+            dist.histogram,
+            Histogram::Semantic { semantic_type: SemanticType::Email }
+        ));
+    }
+
+    #[test]
+    fn test_pii_column_tagged_without_redaction() {
+        let mut state = ColumnState::new(DataType::Text, DEFAULT_RESERVOIR_CAPACITY);
+        if let Some(reservoir) = state.text_reservoir.as_mut() {
+            reservoir.add("123-45-6789".to_string());
+        }
+
+        let dist = build_single_distribution("users", "tax_id", state, 1, &ProfileOptions::default());
+
+        assert_eq!(dist.pii, Some(SemanticType::Ssn));
+        assert!(matches!(dist.histogram, Histogram::Categorical { .. }));
+    }
+
+    #[test]
+    fn test_high_cardinality_text_becomes_pattern() {
+        let mut state = ColumnState::new(DataType::Text, DEFAULT_RESERVOIR_CAPACITY);
+        if let Some(reservoir) = state.text_reservoir.as_mut() {
+            for i in 0..100 {
+                reservoir.add(format!("SKU-{:04}", i * 37));
+            }
+        }
+
+        let dist = build_single_distribution("items", "sku", state, 100, &ProfileOptions::default());
+
+        match &dist.histogram {
+            Histogram::Pattern { pattern } => assert_eq!(pattern.to_string(), "SKU-[0-9]{4}"),
+            other => panic!("expected pattern, got {:?}", other),
+        }
+        let lengths = dist.lengths.expect("lengths profiled");
+        assert_eq!(lengths.counts.get(&8), Some(&100));
+    }
+
+    #[test]
+    fn test_free_text_uses_markov_model_when_enabled() {
+        let fill = |state: &mut ColumnState| {
+            if let Some(reservoir) = state.text_reservoir.as_mut() {
+                for i in 0..60 {
+                    reservoir.add(format!("customer asked about order status again {}", i % 7));
+                }
+            }
+        };
+
+        let options = ProfileOptions {
+            markov_text: true,
+            ..ProfileOptions::default()
+        };
+        let mut state = ColumnState::new(DataType::Text, DEFAULT_RESERVOIR_CAPACITY);
+        fill(&mut state);
+        let dist = build_single_distribution("tickets", "body", state, 60, &options);
+        assert!(matches!(dist.histogram, Histogram::Markov { .. }));
+
+        let mut state = ColumnState::new(DataType::Text, DEFAULT_RESERVOIR_CAPACITY);
+        fill(&mut state);
+        let dist = build_single_distribution("tickets", "body", state, 60, &ProfileOptions::default());
+        assert!(matches!(dist.histogram, Histogram::Categorical { .. }));
+    }
+
+    #[test]
+    fn test_quantile_sketch_replaces_bins() {
+        let mut state = ColumnState::new(DataType::Float, DEFAULT_RESERVOIR_CAPACITY).with_digest();
+        for i in 1..=1_000 {
+            state.add_numeric(i as f64);
+        }
+
+        let dist = build_single_distribution("orders", "amount", state, 1_000, &ProfileOptions::default());
+
+        assert!(matches!(dist.histogram, Histogram::Quantile { .. }));
+        assert_eq!(dist.max, Some(1_000.0));
+        let median = dist.quantile(0.5).unwrap();
+        assert!((median - 500.0).abs() < 10.0, "median {}", median);
+    }
+
+    #[test]
+    fn test_overflowing_column_keeps_heavy_hitters_and_tail() {
+        let mut state = ColumnState::new(DataType::Text, DEFAULT_RESERVOIR_CAPACITY);
+        state.heavy_hitters = Some(SpaceSaving::new(5));
+
+        let values = (0..100).map(|i| match i % 10 {
+            0..=4 => "red".to_string(),
+            5..=7 => "blue".to_string(),
+            _ => format!("one off {}", i),
+        });
+        for value in values {
+            if let Some(sketch) = state.heavy_hitters.as_mut() {
+                sketch.add(&value);
+            }
+            if let Some(reservoir) = state.text_reservoir.as_mut() {
+                reservoir.add(value);
+            }
+        }
+
+        let dist = build_single_distribution("cars", "color", state, 100, &ProfileOptions::default());
+
+        let Histogram::Categorical { frequencies, long_tail, .. } = &dist.histogram else {
+            panic!("expected categorical histogram");
+        };
+        assert_eq!(frequencies.get("red"), Some(&50));
+        assert_eq!(frequencies.get("blue"), Some(&30));
+        assert!(!frequencies.keys().any(|v| v.starts_with("one off")));
+        assert_eq!(long_tail.as_ref().map(|t| t.count), Some(20));
+    }
+
+    #[test]
+    fn test_rare_categories_folded_into_tail() {
+        let mut state = ColumnState::new(DataType::Text, DEFAULT_RESERVOIR_CAPACITY);
+        let names = ["Jane Smith", "Jane Smith", "Jane Smith", "John Doe", "Ada Lovelace"];
+        if let Some(reservoir) = state.text_reservoir.as_mut() {
+            for name in names {
+                reservoir.add(name.to_string());
+            }
+        }
+        let options = ProfileOptions { min_category_count: Some(2), ..ProfileOptions::default() };
+
+        let dist = build_single_distribution("customers", "full_name", state, 5, &options);
+
+        let Histogram::Categorical { frequencies, long_tail, .. } = &dist.histogram else {
+            panic!("expected categorical histogram");
+        };
+        assert_eq!(frequencies.keys().collect::<Vec<_>>(), vec!["Jane Smith"]);
+        let tail = long_tail.as_ref().unwrap();
+        assert_eq!(tail.count, 2);
+        assert_eq!(tail.semantic_type, Some(SemanticType::PersonName));
+    }
+
+    #[test]
+    fn test_empty_table_gets_default_distributions() -> Result<()> {
+        let table = Table::new(
+            "refunds".to_string(),
+            vec![
+                Column::new("amount".to_string(), DataType::Float, false, false),
+                Column::new("reason".to_string(), DataType::Text, true, false),
+            ],
+            vec![],
+        );
+
+        let (distributions, covariance) = profile_text_rows(&table, std::iter::empty(), &ProfileOptions::default())?;
+
+        assert!(covariance.is_none());
+        let amount = &distributions["amount"];
+        assert!(amount.synthetic_default);
+        assert!(amount.max.unwrap() > amount.min.unwrap());
+        assert!(matches!(distributions["reason"].histogram, Histogram::Pattern { .. }));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sparse_numeric_columns_keep_correlations() -> Result<()> {
+        let columns = ["price", "total", "discount", "refund"]
+            .map(|name| Column::new(name.to_string(), DataType::Float, true, false))
+            .to_vec();
+        let table = Table::new("orders".to_string(), columns, vec![]);
+        // Discounts and refunds are never set on the same row, so no row is complete
+        let rows = (0..200).map(|i| {
+            let price = i as f64;
+            let discount = (i % 2 == 0).then(|| (price / 10.0).to_string());
+            let refund = (i % 2 == 1).then(|| (1_000.0 - price).to_string());
+            Ok(vec![Some(price.to_string()), Some((price * 1.2).to_string()), discount, refund])
+        });
+
+        let (_, covariance) = profile_text_rows(&table, rows, &ProfileOptions::default())?;
+
+        let matrix = covariance.expect("pairwise correlations").to_matrix();
+        assert!((matrix[(0, 1)] - 1.0).abs() < 1e-9);
+        assert!((matrix[(0, 2)] - 1.0).abs() < 1e-9);
+        assert!((matrix[(0, 3)] + 1.0).abs() < 1e-9);
+        assert_eq!(matrix[(2, 3)], 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_timestamps_join_correlations() -> Result<()> {
+        let columns = vec![
+            Column::new("signed_up_at".to_string(), DataType::Timestamp, false, false),
+            Column::new("first_order_at".to_string(), DataType::Timestamp, true, false),
+            Column::new("note".to_string(), DataType::Text, true, false),
+        ];
+        let table = Table::new("users".to_string(), columns, vec![]);
+        // First orders follow signups by a day
+        let rows = (0..100).map(|day: i64| {
+            let signed_up_at = chrono::DateTime::from_timestamp(1_700_000_000 + day * 86_400, 0).unwrap();
+            let first_order_at = signed_up_at + chrono::Duration::days(1);
+            Ok(vec![Some(signed_up_at.to_rfc3339()), Some(first_order_at.to_rfc3339()), None])
+        });
+
+        let (_, covariance) = profile_text_rows(&table, rows, &ProfileOptions::default())?;
+
+        let covariance = covariance.expect("timestamp correlations");
+        assert_eq!(covariance.columns, ["signed_up_at", "first_order_at"]);
+        assert!((covariance.to_matrix()[(0, 1)] - 1.0).abs() < 1e-9);
+        Ok(())
+    }
+
+    #[test]
+    fn test_empirical_copula_keeps_pseudo_observations() -> Result<()> {
+        let columns = ["amount", "fee", "tax"]
+            .map(|name| Column::new(name.to_string(), DataType::Float, true, false))
+            .to_vec();
+        let table = Table::new("claims".to_string(), columns, vec![]);
+        let rows = || {
+            (0..50).map(|i| Ok(vec![Some(i.to_string()), (i % 5 != 0).then(|| (i * i).to_string()), Some((i % 7).to_string())]))
+        };
+
+        let (_, gaussian) = profile_text_rows(&table, rows(), &ProfileOptions::default())?;
+        assert!(gaussian.expect("correlations").pseudo_observations.is_none());
+
+        let mut options = ProfileOptions::default();
+        options.copulas.insert("claims".to_string(), CopulaKind::Empirical);
+        let (_, empirical) = profile_text_rows(&table, rows(), &options)?;
+        let observations = empirical.expect("correlations").pseudo_observations.expect("pseudo-observations");
+        assert_eq!(observations.len(), 50);
+        assert!(observations.iter().flatten().flatten().all(|u| *u > 0.0 && *u < 1.0));
+        assert_eq!(observations.iter().filter(|row| row[1].is_none()).count(), 10);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bytea_profiles_lengths_only() -> Result<()> {
+        let payload = Column::new("payload".to_string(), DataType::Bytea, true, false);
+        assert_eq!(select_item(&payload, "c."), "octet_length(c.payload) AS payload");
+
+        let table = Table::new("blobs".to_string(), vec![payload], vec![]);
+        let rows = [Some("\\xdeadbeef"), Some("\\x00"), Some("\\xcafe"), None]
+            .map(|value| Ok(vec![value.map(str::to_string)]));
+        let (distributions, _) = profile_text_rows(&table, rows, &ProfileOptions::default())?;
+
+        let payload = &distributions["payload"];
+        assert_eq!((payload.min, payload.max), (Some(1.0), Some(4.0)));
+        assert_eq!(payload.null_count, 1);
+        assert!(!matches!(payload.histogram, Histogram::Categorical { .. }));
+        Ok(())
+    }
+
+    #[test]
+    fn test_interval_profiles_seconds() -> Result<()> {
+        let ttl = Column::new("ttl".to_string(), DataType::Interval, true, false);
+        assert_eq!(select_item(&ttl, "c."), "extract(epoch from c.ttl)::float8 AS ttl");
+
+        let table = Table::new("sessions".to_string(), vec![ttl], vec![]);
+        let rows = ["00:30:00", "1 day", "1 day 01:00:00"].map(|value| Ok(vec![Some(value.to_string())]));
+        let (distributions, _) = profile_text_rows(&table, rows, &ProfileOptions::default())?;
+
+        let ttl = &distributions["ttl"];
+        assert_eq!((ttl.min, ttl.max), (Some(1_800.0), Some(90_000.0)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_money_profiles_amounts() -> Result<()> {
+        let mut price = Column::new("price".to_string(), DataType::Float, true, false);
+        price.money = true;
+        assert_eq!(select_item(&price, "c."), "c.price::numeric::float8 AS price");
+        price.money = false;
+        assert_eq!(select_item(&price, ""), "price::float8 AS price");
+        price.money = true;
+
+        let table = Table::new("orders".to_string(), vec![price], vec![]);
+        let rows = ["$1,234.50", "-$2.00", "7.25"].map(|value| Ok(vec![Some(value.to_string())]));
+        let (distributions, _) = profile_text_rows(&table, rows, &ProfileOptions::default())?;
+
+        let price = &distributions["price"];
+        assert_eq!((price.min, price.max), (Some(-2.0), Some(1234.5)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_geometry_profiles_bounding_boxes() -> Result<()> {
+        let location = Column::new("location".to_string(), DataType::Geography, true, false);
+        assert_eq!(select_item(&location, "c."), "ST_AsEWKT(ST_Envelope(c.location::geometry)) AS location");
+
+        let table = Table::new("stores".to_string(), vec![location], vec![]);
+        let rows = ["SRID=4326;POINT(-73.9 40.7)", "SRID=4326;POINT(-74 40.6)", "SRID=3857;POINT(10 20)"]
+            .map(|value| Ok(vec![Some(value.to_string())]));
+        let (distributions, _) = profile_text_rows(&table, rows, &ProfileOptions::default())?;
+
+        match &distributions["location"].histogram {
+            Histogram::Categorical { frequencies, .. } => {
+                assert_eq!(frequencies.get("SRID=4326;BOX(-74 40.6,-73.9 40.7)"), Some(&2));
+                assert_eq!(frequencies.get("SRID=3857;BOX(10 20,10 20)"), Some(&1));
+            }
+            other => panic!("Expected categorical boxes, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_hstore_profiles_pairs() -> Result<()> {
+        let tags = Column::new("tags".to_string(), DataType::Hstore, true, false);
+        assert_eq!(select_item(&tags, ""), "tags::text AS tags");
+
+        let table = Table::new("items".to_string(), vec![tags], vec![]);
+        let rows = [r#""color"=>"red", "size"=>"L""#, r#""color"=>"red""#, ""]
+            .map(|value| Ok(vec![Some(value.to_string())]));
+        let (distributions, _) = profile_text_rows(&table, rows, &ProfileOptions::default())?;
+
+        let tags = &distributions["tags"];
+        match &tags.histogram {
+            Histogram::Categorical { frequencies, .. } => {
+                assert_eq!(frequencies.get(r#""color"=>"red""#), Some(&2));
+                assert_eq!(frequencies.get(r#""size"=>"L""#), Some(&1));
+            }
+            other => panic!("Expected categorical pairs, got {:?}", other),
+        }
+        let counts = tags.pair_counts.as_ref().unwrap();
+        assert_eq!(counts.counts.values().sum::<u64>(), 3);
+        assert_eq!(counts.counts.get(&0), Some(&1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_network_columns_profile_prefixes() -> Result<()> {
+        let ip = Column::new("ip".to_string(), DataType::Inet, true, false);
+        assert_eq!(select_item(&ip, ""), "ip::text AS ip");
+
+        let table = Table::new("logins".to_string(), vec![ip], vec![]);
+        let rows = ["10.1.2.3", "10.1.2.200", "192.168.0.7/16"].map(|value| Ok(vec![Some(value.to_string())]));
+        let (distributions, _) = profile_text_rows(&table, rows, &ProfileOptions::default())?;
+
+        match &distributions["ip"].histogram {
+            Histogram::Categorical { frequencies, .. } => {
+                assert_eq!(frequencies.get("10.1.2.0/24"), Some(&2));
+                assert_eq!(frequencies.get("192.168.0.0/24"), Some(&1));
+            }
+            other => panic!("Expected categorical prefixes, got {:?}", other),
+        }
+        Ok(())
     }
 
     #[test]
     fn test_column_state_numeric() {
-        let state = ColumnState::new(DataType::Integer);
+        let state = ColumnState::new(DataType::Integer, DEFAULT_RESERVOIR_CAPACITY);
         assert!(state.numeric_reservoir.is_some());
         assert!(state.text_reservoir.is_none());
     }
 
     #[test]
     fn test_column_state_text() {
-        let state = ColumnState::new(DataType::Text);
+        let state = ColumnState::new(DataType::Text, DEFAULT_RESERVOIR_CAPACITY);
         assert!(state.numeric_reservoir.is_none());
         assert!(state.text_reservoir.is_some());
     }
 
+    #[test]
+    fn test_column_state_add_text() {
+        let mut state = ColumnState::new(DataType::Integer, DEFAULT_RESERVOIR_CAPACITY);
+        assert_eq!(state.add_text(Some(" 42")).unwrap(), Some(42.0));
+        assert_eq!(state.add_text(None).unwrap(), None);
+        assert!(state.add_text(Some("4.2")).is_err());
+        assert_eq!(state.null_count, 1);
+
+        let mut state = ColumnState::new(DataType::Boolean, DEFAULT_RESERVOIR_CAPACITY);
+        state.add_text(Some("TRUE")).unwrap();
+        state.add_text(Some("f")).unwrap();
+        assert!(state.add_text(Some("maybe")).is_err());
+        let bernoulli = state.bernoulli.unwrap();
+        assert_eq!((bernoulli.true_count, bernoulli.false_count), (1, 1));
+
+        assert_eq!(parse_timestamp("2024-01-01 00:00:00+00"), Some(1_704_067_200.0));
+        assert_eq!(parse_timestamp("2024-01-01T01:00:00+01:00"), Some(1_704_067_200.0));
+        assert_eq!(parse_timestamp("2024-01-01 00:00:00.25"), Some(1_704_067_200.0));
+        assert_eq!(parse_timestamp("2024-01-01"), Some(1_704_067_200.0));
+        assert_eq!(parse_timestamp("soon"), None);
+    }
+
+    #[test]
+    fn test_boolean_column_counts_bernoulli() {
+        let mut state = ColumnState::new(DataType::Boolean, DEFAULT_RESERVOIR_CAPACITY);
+        assert!(state.text_reservoir.is_none());
+        for i in 0..90 {
+            state.bernoulli.as_mut().unwrap().add(i % 3 == 0);
+        }
+        state.null_count = 10;
+
+        let dist = build_single_distribution("users", "active", state, 100, &ProfileOptions::default());
+
+        let Histogram::Boolean { bernoulli } = dist.histogram else {
+            panic!("expected boolean histogram");
+        };
+        assert_eq!((bernoulli.true_count, bernoulli.false_count), (30, 60));
+        assert_eq!(dist.unique_count, 2);
+        assert_eq!(dist.null_count, 10);
+    }
+
+    #[test]
+    fn test_reservoir_capacity_overrides() {
+        let options = ProfileOptions {
+            reservoir_capacity: Some(50_000),
+            reservoir_capacities: HashMap::from([("orders".to_string(), 100_000), ("orders.note".to_string(), 500)]),
+            ..ProfileOptions::default()
+        };
+        assert_eq!(options.reservoir_capacity("orders", Some("note")), 500);
+        assert_eq!(options.reservoir_capacity("orders", Some("amount")), 100_000);
+        assert_eq!(options.reservoir_capacity("orders", None), 100_000);
+        assert_eq!(options.reservoir_capacity("users", Some("name")), 50_000);
+        assert_eq!(ProfileOptions::default().reservoir_capacity("users", None), DEFAULT_RESERVOIR_CAPACITY);
+
+        let columns = vec![Column::new("note".to_string(), DataType::Text, true, false)];
+        let capacity = |column: &str| options.reservoir_capacity("orders", Some(column));
+        let mut states = initialize_column_states(&columns, capacity, false, true, false);
+        let state = states.get_mut("note").unwrap();
+        for i in 0..1000 {
+            state.add_text(Some(&i.to_string())).unwrap();
+        }
+        assert_eq!(state.text_reservoir.as_ref().unwrap().sample_size(), 500);
+        assert_eq!(reservoir_fill(&states), Some(1.0));
+    }
+
+    #[test]
+    fn test_recency_weighting_favors_recent_rows() -> Result<()> {
+        let columns = vec![
+            Column::new("created_at".to_string(), DataType::Timestamp, true, false),
+            Column::new("status".to_string(), DataType::Text, true, false),
+        ];
+        let table = Table::new("orders".to_string(), columns, vec![]);
+        // One order an hour for 100 days; the last 10 days are "new"
+        let rows = || {
+            (0..2_400).map(|hour: i64| {
+                let created_at = chrono::DateTime::from_timestamp(1_700_000_000 + hour * 3_600, 0).unwrap();
+                let status = if hour < 2_160 { "old" } else { "new" };
+                Ok(vec![Some(created_at.to_rfc3339()), Some(status.to_string())])
+            })
+        };
+        let new_share = |distributions: &HashMap<String, Distribution>| match &distributions["status"].histogram {
+            Histogram::Categorical { frequencies, .. } => {
+                frequencies.get("new").copied().unwrap_or(0) as f64 / frequencies.values().sum::<u64>() as f64
+            }
+            other => panic!("Expected categorical status, got {:?}", other),
+        };
+
+        let mut options = ProfileOptions { reservoir_capacity: Some(100), ..ProfileOptions::default() };
+        let (uniform, _) = profile_text_rows(&table, rows(), &options)?;
+        assert!(new_share(&uniform) < 0.25, "uniform new share {}", new_share(&uniform));
+
+        let recency = Recency { column: "created_at".to_string(), half_life: 86_400.0 };
+        options.recency.insert("orders".to_string(), recency);
+        let (weighted, _) = profile_text_rows(&table, rows(), &options)?;
+        assert!(new_share(&weighted) > 0.9, "weighted new share {}", new_share(&weighted));
+        assert_eq!(weighted["status"].total_count, 2_400);
+
+        // A recency column that isn't a timestamp falls back to uniform sampling
+        options.recency.insert("orders".to_string(), Recency { column: "status".to_string(), half_life: 1.0 });
+        assert!(options.recency(&table).is_none());
+        Ok(())
+    }
+
     #[test]
     fn test_initialize_column_states() {
         let columns = vec![
@@ -426,7 +2552,7 @@ mod tests {
             Column::new("name".to_string(), DataType::Text, false, false),
         ];
 
-        let states = initialize_column_states(&columns);
+        let states = initialize_column_states(&columns, |_| DEFAULT_RESERVOIR_CAPACITY, false, true, false);
 
         assert_eq!(states.len(), 2);
         assert!(states.contains_key("id"));
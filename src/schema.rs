@@ -1,6 +1,8 @@
+use std::collections::BTreeMap;
 use std::fmt;
 use std::fmt::Formatter;
 use serde::{Deserialize, Serialize};
+use tracing::warn;
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -11,6 +13,19 @@ pub enum DataType {
     Timestamp,
     Boolean,
     Uuid,
+    /// Binary data; only value lengths are profiled, never the bytes.
+    Bytea,
+    /// Network types, profiled by address prefix (see [`crate::network`]).
+    Inet,
+    Cidr,
+    MacAddr,
+    /// Durations, profiled as seconds (see [`crate::interval`]).
+    Interval,
+    /// PostGIS types, profiled by bounding box (see [`crate::geometry`]).
+    Geometry,
+    Geography,
+    /// Key/value maps, profiled as pairs (see [`crate::hstore`]).
+    Hstore,
 }
 
 impl fmt::Display for DataType {
@@ -22,16 +37,77 @@ impl fmt::Display for DataType {
             DataType::Timestamp => write!(f, "timestamp"),
             DataType::Boolean => write!(f, "boolean"),
             DataType::Uuid => write!(f, "uuid"),
+            DataType::Bytea => write!(f, "bytea"),
+            DataType::Inet => write!(f, "inet"),
+            DataType::Cidr => write!(f, "cidr"),
+            DataType::MacAddr => write!(f, "macaddr"),
+            DataType::Interval => write!(f, "interval"),
+            DataType::Geometry => write!(f, "geometry"),
+            DataType::Geography => write!(f, "geography"),
+            DataType::Hstore => write!(f, "hstore"),
         }
     }
 }
 
+impl DataType {
+    /// Types that take part in a table's correlation matrix: numbers, and timestamps
+    /// as epoch seconds.
+    pub fn is_correlated(&self) -> bool {
+        matches!(self, DataType::Integer | DataType::Float | DataType::Timestamp)
+    }
+}
+
+/// Length in bytes of a bytea value in its text form: hex (`\x0a0b`) or the older
+/// escape format, where `\\` is a backslash and `\ooo` an octal byte.
+pub fn bytea_length(text: &str) -> usize {
+    if let Some(hex) = text.strip_prefix("\\x") {
+        return hex.len() / 2;
+    }
+    let bytes = text.as_bytes();
+    let (mut length, mut i) = (0, 0);
+    while i < bytes.len() {
+        i += match bytes.get(i..i + 2) {
+            Some(b"\\\\") => 2,
+            Some([b'\\', _]) if bytes.len() >= i + 4 => 4,
+            _ => 1,
+        };
+        length += 1;
+    }
+    length
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Column {
     pub name: String,
     pub data_type: DataType,
     pub is_nullable: bool,
     pub is_primary_key: bool,
+
+    /// Sequence backing a serial or identity column, as returned by `pg_get_serial_sequence`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial_sequence: Option<String>,
+
+    /// The column has a `DEFAULT` expression or is an identity column.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub has_default: bool,
+
+    /// Range the physical type can hold; generated numbers are clamped to it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bounds: Option<TypeBounds>,
+
+    /// Source type with no genome equivalent; the column is profiled and generated as text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unmapped_type: Option<String>,
+
+    /// The source column is `money`, a float column read through `numeric` and generated
+    /// in whole cents.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub money: bool,
+
+    /// Domain the column is declared with; `data_type` is that of the domain's base type,
+    /// and simple range checks of the domain are folded into `bounds`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
 }
 
 impl Column {
@@ -41,10 +117,138 @@ impl Column {
             data_type,
             is_nullable,
             is_primary_key,
+            serial_sequence: None,
+            has_default: false,
+            bounds: None,
+            unmapped_type: None,
+            money: false,
+            domain: None,
         }
     }
 }
 
+/// Smallest and largest value of a bounded numeric type.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TypeBounds {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl TypeBounds {
+    /// Bounds of the Postgres type `udt_name`; `precision` and `scale` are the declared
+    /// ones of a `numeric`. `None` for unbounded types such as plain `numeric`.
+    pub fn for_pg_type(udt_name: &str, precision: Option<i32>, scale: Option<i32>) -> Option<Self> {
+        let symmetric = |max: f64| Some(Self { min: -max, max });
+        match udt_name {
+            "int2" => Some(Self { min: i16::MIN as f64, max: i16::MAX as f64 }),
+            "int4" => Some(Self { min: i32::MIN as f64, max: i32::MAX as f64 }),
+            // i64::MAX rounds up to 2^63 as an f64; the next float down still fits
+            "int8" => Some(Self { min: i64::MIN as f64, max: 9_223_372_036_854_774_784.0 }),
+            "float4" => symmetric(f32::MAX as f64),
+            // An int8 count of cents; as with int8, the top rounds up, so step a float down
+            "money" => Some(Self { min: i64::MIN as f64 / 100.0, max: 92_233_720_368_547_744.0 }),
+            "numeric" => {
+                let (precision, scale) = (precision?, scale.unwrap_or(0));
+                let step = 10f64.powi(-scale);
+                symmetric(10f64.powi(precision - scale) - step)
+            }
+            _ => None,
+        }
+    }
+
+    /// Bounds implied by a `CHECK` constraint of a domain, as `pg_get_constraintdef`
+    /// prints it: comparisons of `VALUE` with numbers, joined by `AND`
+    /// (`CHECK (((VALUE >= 1) AND (VALUE <= 5)))`). Other conjuncts add no bound; a check
+    /// with `OR` or `NOT` gives `None`. Strict comparisons step to the next integer, or the
+    /// next float when `integer` is false.
+    pub fn from_check(check: &str, integer: bool) -> Option<Self> {
+        let upper = check.to_uppercase();
+        if upper.contains(" OR ") || upper.contains("NOT ") {
+            return None;
+        }
+        let body = upper.trim().strip_prefix("CHECK").unwrap_or(&upper);
+        let step = |value: f64, up: bool| match (integer, up) {
+            (true, true) => value.floor() + 1.0,
+            (true, false) => value.ceil() - 1.0,
+            (false, true) => value.next_up(),
+            (false, false) => value.next_down(),
+        };
+
+        let mut bounds = Self { min: f64::MIN, max: f64::MAX };
+        for conjunct in body.split(" AND ") {
+            let Some((value, op, literal)) = value_comparison(conjunct) else {
+                continue;
+            };
+            let (min, max) = match op {
+                ">=" => (Some(literal), None),
+                ">" => (Some(step(literal, true)), None),
+                "<=" => (None, Some(literal)),
+                "<" => (None, Some(step(literal, false))),
+                _ => (Some(literal), Some(literal)),
+            };
+            // `5 > VALUE` bounds VALUE from above
+            let (min, max) = if value { (min, max) } else { (max, min) };
+            bounds.min = bounds.min.max(min.unwrap_or(f64::MIN));
+            bounds.max = bounds.max.min(max.unwrap_or(f64::MAX));
+        }
+        (bounds.min > f64::MIN || bounds.max < f64::MAX).then_some(bounds)
+    }
+
+    /// Values within both `self` and `other`.
+    pub fn intersect(&self, other: &Self) -> Self {
+        Self { min: self.min.max(other.min), max: self.max.min(other.max) }
+    }
+
+    pub fn clamp(&self, value: f64) -> f64 {
+        value.clamp(self.min, self.max)
+    }
+}
+
+/// A `VALUE <op> number` or `number <op> VALUE` comparison, with parentheses, quotes
+/// and casts around either side; the flag is whether `VALUE` is on the left.
+fn value_comparison(conjunct: &str) -> Option<(bool, &'static str, f64)> {
+    let (position, op) = [">=", "<=", "<>", "!=", ">", "<", "="]
+        .iter()
+        .find_map(|op| conjunct.find(op).map(|position| (position, *op)))?;
+    if matches!(op, "<>" | "!=") {
+        return None;
+    }
+    // Parentheses, quotes and a trailing `::type` cast around an operand
+    let operand = |text: &str| {
+        let text: String = text.chars().filter(|c| !matches!(c, '(' | ')' | '\'')).collect();
+        text.split("::").next().unwrap_or_default().trim().to_string()
+    };
+    let (left, right) = (operand(&conjunct[..position]), operand(&conjunct[position + op.len()..]));
+    match (left.as_str(), right.as_str()) {
+        ("VALUE", number) => Some((true, op, number.parse().ok()?)),
+        (number, "VALUE") => Some((false, op, number.parse().ok()?)),
+        _ => None,
+    }
+}
+
+/// A number written with currency formatting, as `money` columns print and
+/// spreadsheets export them: `$1,234.56`, `-€12.00`, `(4.50)` or `12.00 USD`. Only `.`
+/// is taken as the decimal point. `None` if anything but a number is left after
+/// stripping symbols, group separators and signs.
+pub fn parse_currency(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let (mut negative, text) = match text.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+        Some(inner) => (true, inner),
+        None => (false, text),
+    };
+    // The sign goes before the symbol (`-$3.00`) or after it (`€-3.00`)
+    let mut text = text.strip_prefix('-').inspect(|_| negative = !negative).unwrap_or(text);
+    text = text.trim_matches(|c: char| !(c.is_ascii_digit() || matches!(c, '-' | '.')));
+    text = text.strip_prefix('-').inspect(|_| negative = !negative).unwrap_or(text);
+
+    let digits: String = text.chars().filter(|&c| c != ',').collect();
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit() || b == b'.') {
+        return None;
+    }
+    let value: f64 = digits.parse().ok()?;
+    Some(if negative { -value } else { value })
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ForeignKey {
     pub source_col: String,
@@ -62,11 +266,37 @@ impl ForeignKey {
     }
 }
 
+/// Planner statistics read before profiling. Approximate: they are as fresh as the
+/// last `VACUUM`/`ANALYZE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableEstimate {
+    /// `pg_class.reltuples`; `None` for tables never vacuumed or analyzed.
+    pub rows: Option<u64>,
+
+    /// `pg_table_size`: heap and TOAST without indexes, summed over partitions.
+    pub bytes: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Table {
     pub name: String,
     pub columns: Vec<Column>,
     pub foreign_keys: Vec<ForeignKey>,
+
+    /// Has inheriting child tables that are profiled on their own; read with `ONLY`
+    /// so their rows are not counted twice.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub own_rows_only: bool,
+
+    /// Size of the source table when it was scanned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<TableEstimate>,
+
+    /// Columns of derived types such as `tsvector`, by name with their source type.
+    /// They are neither profiled nor part of COPY column lists; the target fills them,
+    /// usually from a trigger or a generated column.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub derived_columns: BTreeMap<String, String>,
 }
 
 impl Table {
@@ -75,6 +305,27 @@ impl Table {
             name,
             columns,
             foreign_keys,
+            own_rows_only: false,
+            estimate: None,
+            derived_columns: BTreeMap::new(),
+        }
+    }
+
+    /// Moves columns of derived types out of `columns` into `derived_columns`.
+    pub fn set_aside_derived_columns(&mut self) {
+        let (derived, columns): (Vec<Column>, Vec<Column>) = std::mem::take(&mut self.columns)
+            .into_iter()
+            .partition(|column| column.unmapped_type.as_deref().is_some_and(is_derived_type));
+        self.columns = columns;
+        for column in derived {
+            let source_type = column.unmapped_type.unwrap_or_default();
+            warn!(
+                table = %self.name,
+                column = %column.name,
+                source_type = %source_type,
+                "Skipping column of a derived type, the target must fill it"
+            );
+            self.derived_columns.insert(column.name, source_type);
         }
     }
 
@@ -88,4 +339,235 @@ impl Table {
     pub fn has_foreign_keys(&self) -> bool {
         !self.foreign_keys.is_empty()
     }
-}
\ No newline at end of file
+}
+
+/// Maps an `information_schema` type (or a DDL type name) and its `udt_name` to a
+/// genome type. Unknown types become text.
+pub(crate) fn map_sql_type_to_datatype(sql_type: &str, udt_name: &str, table_name: &str, column_name: &str) -> DataType {
+    lookup_sql_type(sql_type, udt_name).unwrap_or_else(|| warn_unknown_type(sql_type, udt_name, table_name, column_name))
+}
+
+/// The type to record as [`Column::unmapped_type`] when `sql_type` has no genome
+/// type, e.g. `tsvector`.
+pub(crate) fn unmapped_sql_type(sql_type: &str, udt_name: &str) -> Option<String> {
+    match lookup_sql_type(sql_type, udt_name) {
+        Some(_) => None,
+        None if sql_type.eq_ignore_ascii_case("user-defined") || sql_type.eq_ignore_ascii_case("array") => {
+            Some(udt_name.to_string())
+        }
+        None => Some(sql_type.to_string()),
+    }
+}
+
+/// Whether values of the type are computed from other columns rather than stored data:
+/// full-text documents and queries.
+pub(crate) fn is_derived_type(type_name: &str) -> bool {
+    matches!(type_name.to_lowercase().as_str(), "tsvector" | "tsquery")
+}
+
+fn lookup_sql_type(sql_type: &str, udt_name: &str) -> Option<DataType> {
+    let normalized = sql_type.to_lowercase();
+    let udt_normalized = udt_name.to_lowercase();
+
+    match normalized.as_str() {
+        "integer" | "int" | "smallint" | "bigint" => Some(DataType::Integer),
+
+        "real" | "double precision" | "numeric" | "decimal" | "float" | "money" => Some(DataType::Float),
+
+        "character varying" | "varchar" | "character" | "char" | "text" => Some(DataType::Text),
+
+        "timestamp" | "timestamp without time zone" | "timestamp with time zone"
+        | "timestamptz" | "date" | "time" => Some(DataType::Timestamp),
+
+        "boolean" | "bool" => Some(DataType::Boolean),
+
+        "uuid" => Some(DataType::Uuid),
+
+        "bytea" => Some(DataType::Bytea),
+
+        "inet" => Some(DataType::Inet),
+
+        "cidr" => Some(DataType::Cidr),
+
+        "macaddr" => Some(DataType::MacAddr),
+
+        "interval" => Some(DataType::Interval),
+
+        "user-defined" => map_udt_type(&udt_normalized),
+
+        "array" => udt_normalized.strip_prefix('_').and_then(map_udt_type),
+
+        _ => {
+            map_udt_type(&udt_normalized)
+        }
+    }
+}
+
+fn map_udt_type(udt_name: &str) -> Option<DataType> {
+    match udt_name {
+        "int2" | "int4" | "int8" | "smallint" | "integer" | "bigint" => Some(DataType::Integer),
+
+        "float4" | "float8" | "numeric" | "money" => Some(DataType::Float),
+
+        "varchar" | "text" | "bpchar" | "char" => Some(DataType::Text),
+
+        "timestamp" | "timestamptz" | "date" | "time" | "timetz" => Some(DataType::Timestamp),
+
+        "bool" => Some(DataType::Boolean),
+
+        "uuid" => Some(DataType::Uuid),
+
+        "bytea" => Some(DataType::Bytea),
+
+        "inet" => Some(DataType::Inet),
+
+        "cidr" => Some(DataType::Cidr),
+
+        "macaddr" => Some(DataType::MacAddr),
+
+        "interval" => Some(DataType::Interval),
+
+        // Installed by the hstore extension as a user-defined type
+        "hstore" => Some(DataType::Hstore),
+
+        // PostGIS installs these as user-defined types
+        #[cfg(feature = "postgis")]
+        "geometry" => Some(DataType::Geometry),
+
+        #[cfg(feature = "postgis")]
+        "geography" => Some(DataType::Geography),
+
+        _ => None,
+    }
+}
+
+fn warn_unknown_type(sql_type: &str, udt_name: &str, table_name: &str, column_name: &str) -> DataType {
+    // Derived columns are set aside with a warning of their own
+    if is_derived_type(udt_name) {
+        return DataType::Text;
+    }
+    warn!(
+        table = %table_name,
+        column_name = %column_name,
+        sql_type = %sql_type,
+        udt_name = %udt_name,
+        "Unknown data type encountered, defaulting to Text"
+    );
+    DataType::Text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_type_mapping_integers() {
+        let dt = map_sql_type_to_datatype("integer", "int4", "test", "id");
+        assert_eq!(dt, DataType::Integer);
+
+        let dt = map_sql_type_to_datatype("bigint", "int8", "test", "id");
+        assert_eq!(dt, DataType::Integer);
+    }
+
+    #[test]
+    fn test_type_mapping_text() {
+        let dt = map_sql_type_to_datatype("character varying", "varchar", "test", "name");
+        assert_eq!(dt, DataType::Text);
+
+        let dt = map_sql_type_to_datatype("text", "text", "test", "desc");
+        assert_eq!(dt, DataType::Text);
+    }
+
+    #[test]
+    fn test_type_mapping_timestamp() {
+        let dt = map_sql_type_to_datatype("timestamp without time zone", "timestamp", "test", "created");
+        assert_eq!(dt, DataType::Timestamp);
+    }
+
+    #[test]
+    fn test_type_bounds() {
+        let smallint = TypeBounds::for_pg_type("int2", Some(16), Some(0)).unwrap();
+        assert_eq!((smallint.min, smallint.max), (-32768.0, 32767.0));
+        assert_eq!(smallint.clamp(1e6), 32767.0);
+
+        let price = TypeBounds::for_pg_type("numeric", Some(5), Some(2)).unwrap();
+        assert!((price.max - 999.99).abs() < 1e-9);
+        assert!((price.min + 999.99).abs() < 1e-9);
+        assert_eq!(TypeBounds::for_pg_type("numeric", None, None), None);
+
+        let bigint = TypeBounds::for_pg_type("int8", None, None).unwrap();
+        assert!(bigint.max < 2f64.powi(63));
+        assert_eq!(TypeBounds::for_pg_type("float8", None, None), None);
+        assert_eq!(TypeBounds::for_pg_type("text", None, None), None);
+    }
+
+    #[test]
+    fn test_postgis_types() {
+        let (geometry, geography) = if cfg!(feature = "postgis") {
+            (DataType::Geometry, DataType::Geography)
+        } else {
+            (DataType::Text, DataType::Text)
+        };
+        assert_eq!(map_sql_type_to_datatype("USER-DEFINED", "geometry", "test", "location"), geometry);
+        assert_eq!(map_sql_type_to_datatype("USER-DEFINED", "geography", "test", "location"), geography);
+    }
+
+    #[test]
+    fn test_domain_check_bounds() {
+        let rating = TypeBounds::from_check("CHECK (((VALUE >= 1) AND (VALUE <= 5)))", true).unwrap();
+        assert_eq!((rating.min, rating.max), (1.0, 5.0));
+        let stars = TypeBounds::from_check("CHECK (((VALUE)::integer > 2))", true).unwrap();
+        assert_eq!(stars.min, 3.0);
+        let price = TypeBounds::from_check("CHECK ((VALUE > (0)::numeric))", false).unwrap();
+        assert!(price.min > 0.0 && price.min < 1e-300);
+        let capped = TypeBounds::from_check("CHECK ((100 >= VALUE))", false).unwrap();
+        assert_eq!((capped.min, capped.max), (f64::MIN, 100.0));
+        assert_eq!(capped.intersect(&rating).max, 5.0);
+
+        assert_eq!(TypeBounds::from_check("CHECK ((VALUE ~ '@'::text))", false), None);
+        assert_eq!(TypeBounds::from_check("CHECK (((VALUE < 0) OR (VALUE > 10)))", false), None);
+        assert_eq!(TypeBounds::from_check("CHECK ((NOT (VALUE > 10)))", false), None);
+    }
+
+    #[test]
+    fn test_hstore_type() {
+        assert_eq!(map_sql_type_to_datatype("USER-DEFINED", "hstore", "test", "tags"), DataType::Hstore);
+        assert_eq!(unmapped_sql_type("USER-DEFINED", "hstore"), None);
+    }
+
+    #[test]
+    fn test_money() {
+        assert_eq!(map_sql_type_to_datatype("money", "money", "test", "price"), DataType::Float);
+        let money = TypeBounds::for_pg_type("money", None, None).unwrap();
+        assert!(money.max < 2f64.powi(63) / 100.0);
+
+        assert_eq!(parse_currency("$1,234.56"), Some(1234.56));
+        assert_eq!(parse_currency("-$3.00"), Some(-3.0));
+        assert_eq!(parse_currency("($4.50)"), Some(-4.5));
+        assert_eq!(parse_currency("12.00 USD"), Some(12.0));
+        assert_eq!(parse_currency("€-7"), Some(-7.0));
+        assert_eq!(parse_currency("1.2.3"), None);
+        assert_eq!(parse_currency("$"), None);
+        assert_eq!(parse_currency("n/a"), None);
+    }
+
+    #[test]
+    fn test_bytea_length() {
+        assert_eq!(bytea_length("\\x"), 0);
+        assert_eq!(bytea_length("\\xdeadbeef"), 4);
+        assert_eq!(bytea_length("ab\\\\c\\000"), 5);
+        assert_eq!(map_sql_type_to_datatype("bytea", "bytea", "test", "data"), DataType::Bytea);
+        assert_eq!(map_sql_type_to_datatype("interval", "interval", "test", "ttl"), DataType::Interval);
+    }
+
+    #[test]
+    fn test_type_mapping_unknown_fallback() {
+        let dt = map_sql_type_to_datatype("exotic_type", "custom", "test", "col");
+        assert_eq!(dt, DataType::Text);
+
+        assert_eq!(unmapped_sql_type("exotic_type", "custom").as_deref(), Some("exotic_type"));
+        assert_eq!(unmapped_sql_type("USER-DEFINED", "tsvector").as_deref(), Some("tsvector"));
+        assert_eq!(unmapped_sql_type("ARRAY", "_int4"), None);
+        assert_eq!(unmapped_sql_type("text", "text"), None);
+    }
+}
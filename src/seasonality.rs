@@ -0,0 +1,261 @@
+//! Calendar shape of timestamp columns: hour-of-day and day-of-week counts, plus a
+//! monthly trend for columns spanning more than a couple of months.
+//!
+//! A flat histogram over epoch seconds keeps the range but loses the daily and weekly
+//! rhythm of event tables. Generation composes the three profiles instead: a month by
+//! trend weight, a day in it by weekday weight, then an hour by hour-of-day weight.
+
+use std::collections::BTreeMap;
+use chrono::{DateTime, Datelike, NaiveDate};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Fewest non-null values a seasonality profile is built from.
+pub const MIN_SEASONALITY_VALUES: u64 = 100;
+
+/// Spans shorter than this get no monthly trend.
+const MIN_TREND_DAYS: f64 = 60.0;
+
+/// Spans with more months than this (e.g. sentinel dates in year 1 or 9999) get no trend.
+const MAX_TREND_MONTHS: usize = 1_200;
+
+/// Draws rejected (on weekday weight, or for leaving the range) before settling for the last one.
+const MAX_DAY_ATTEMPTS: usize = 32;
+
+const SECONDS_PER_DAY: i64 = 86_400;
+const SECONDS_PER_HOUR: i64 = 3_600;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Seasonality {
+    /// Values per UTC hour, 0-23.
+    pub hour_of_day: Vec<u64>,
+
+    /// Values per weekday, Monday first.
+    pub day_of_week: Vec<u64>,
+
+    /// Values per calendar month, keyed `YYYY-MM`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub monthly: BTreeMap<String, u64>,
+}
+
+/// Streaming counts for one column, turned into a [`Seasonality`] once the scan ends.
+#[derive(Debug, Clone, Default)]
+pub struct SeasonalityProfile {
+    hour_of_day: [u64; 24],
+    day_of_week: [u64; 7],
+    /// (year, month) -> count.
+    months: BTreeMap<(i32, u32), u64>,
+    count: u64,
+}
+
+impl SeasonalityProfile {
+    pub fn add(&mut self, epoch_seconds: f64) {
+        let Some(timestamp) = DateTime::from_timestamp(epoch_seconds.floor() as i64, 0) else {
+            return;
+        };
+
+        self.count += 1;
+        self.hour_of_day[timestamp.timestamp().rem_euclid(SECONDS_PER_DAY) as usize / SECONDS_PER_HOUR as usize] += 1;
+        self.day_of_week[timestamp.weekday().num_days_from_monday() as usize] += 1;
+        *self.months.entry((timestamp.year(), timestamp.month())).or_insert(0) += 1;
+    }
+
+    /// The profile, or `None` with too few values. `min` and `max` bound the column
+    /// in epoch seconds and decide whether a monthly trend is kept.
+    pub fn finish(self, min: f64, max: f64) -> Option<Seasonality> {
+        if self.count < MIN_SEASONALITY_VALUES {
+            return None;
+        }
+
+        let span_days = (max - min) / SECONDS_PER_DAY as f64;
+        let monthly = if span_days >= MIN_TREND_DAYS && self.months.len() <= MAX_TREND_MONTHS {
+            self.months
+                .into_iter()
+                .map(|((year, month), count)| (format!("{:04}-{:02}", year, month), count))
+                .collect()
+        } else {
+            BTreeMap::new()
+        };
+
+        Some(Seasonality {
+            hour_of_day: self.hour_of_day.to_vec(),
+            day_of_week: self.day_of_week.to_vec(),
+            monthly,
+        })
+    }
+}
+
+impl Seasonality {
+    /// Draws epoch seconds within [`min`, `max`] following the profiled calendar shape.
+    pub fn sample<R: Rng + ?Sized>(&self, min: f64, max: f64, rng: &mut R) -> f64 {
+        let (mut low, mut high) = (min.ceil() as i64, max.floor() as i64);
+        if low > high {
+            return min;
+        }
+
+        if let Some((start, end)) = self.pick_month(rng)
+            && start.max(low) < end.min(high + 1)
+        {
+            low = start.max(low);
+            high = end.min(high + 1) - 1;
+        }
+
+        // The first and last day are partial; redraw times that fall outside the range
+        let mut seconds = low;
+        for _ in 0..MAX_DAY_ATTEMPTS {
+            let day = self.pick_day(low.div_euclid(SECONDS_PER_DAY), high.div_euclid(SECONDS_PER_DAY), rng);
+            let hour = weighted_index(&self.hour_of_day, rng).unwrap_or(0) as i64;
+            seconds = day * SECONDS_PER_DAY + hour * SECONDS_PER_HOUR + rng.gen_range(0..SECONDS_PER_HOUR);
+            if (low..=high).contains(&seconds) {
+                break;
+            }
+        }
+
+        seconds.clamp(low, high) as f64
+    }
+
+    /// `epoch_seconds` on the same UTC day with the time of day redrawn by hour-of-day
+    /// weight, for values whose date was picked elsewhere (e.g. by a copula). Unchanged
+    /// if the new time would leave [`min`, `max`].
+    pub fn resample_time_of_day<R: Rng + ?Sized>(&self, epoch_seconds: f64, min: f64, max: f64, rng: &mut R) -> f64 {
+        let Some(hour) = weighted_index(&self.hour_of_day, rng) else {
+            return epoch_seconds;
+        };
+        let day = (epoch_seconds.floor() as i64).div_euclid(SECONDS_PER_DAY);
+        let seconds = (day * SECONDS_PER_DAY + hour as i64 * SECONDS_PER_HOUR + rng.gen_range(0..SECONDS_PER_HOUR)) as f64;
+        if (min..=max).contains(&seconds) { seconds } else { epoch_seconds }
+    }
+
+    /// Start and end (exclusive) of a month drawn by trend weight.
+    fn pick_month<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<(i64, i64)> {
+        let counts: Vec<u64> = self.monthly.values().copied().collect();
+        let key = self.monthly.keys().nth(weighted_index(&counts, rng)?)?;
+
+        let (year, month) = key.split_once('-')?;
+        let (year, month): (i32, u32) = (year.parse().ok()?, month.parse().ok()?);
+        let start = NaiveDate::from_ymd_opt(year, month, 1)?;
+        let end = if month == 12 {
+            NaiveDate::from_ymd_opt(year + 1, 1, 1)?
+        } else {
+            NaiveDate::from_ymd_opt(year, month + 1, 1)?
+        };
+
+        let epoch = |date: NaiveDate| date.and_hms_opt(0, 0, 0).map(|dt| dt.and_utc().timestamp());
+        Some((epoch(start)?, epoch(end)?))
+    }
+
+    /// Day number (days since the epoch) in [`first`, `last`], accepted with
+    /// probability proportional to its weekday's count.
+    fn pick_day<R: Rng + ?Sized>(&self, first: i64, last: i64, rng: &mut R) -> i64 {
+        let peak = self.day_of_week.iter().copied().max().unwrap_or(0);
+        let mut day = first;
+        for _ in 0..MAX_DAY_ATTEMPTS {
+            day = rng.gen_range(first..=last);
+            if peak == 0 {
+                break;
+            }
+            // 1970-01-01 was a Thursday (index 3 from Monday)
+            let weekday = (day + 3).rem_euclid(7) as usize;
+            if rng.gen_range(0..peak) < self.day_of_week[weekday] {
+                break;
+            }
+        }
+        day
+    }
+}
+
+fn weighted_index<R: Rng + ?Sized>(weights: &[u64], rng: &mut R) -> Option<usize> {
+    let total: u64 = weights.iter().sum();
+    if total == 0 {
+        return None;
+    }
+
+    let mut target = rng.gen_range(0..total);
+    for (index, &weight) in weights.iter().enumerate() {
+        if target < weight {
+            return Some(index);
+        }
+        target -= weight;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    /// Monday 2024-01-01 00:00 UTC.
+    const MONDAY: i64 = 1_704_067_200;
+
+    fn business_hours() -> (Seasonality, f64, f64) {
+        let mut profile = SeasonalityProfile::default();
+        // Weekdays only, 09:00-17:59, over twelve weeks
+        for week in 0..12 {
+            for weekday in 0..5 {
+                for hour in 9..18 {
+                    let day = MONDAY + (week * 7 + weekday) * SECONDS_PER_DAY;
+                    profile.add((day + hour * SECONDS_PER_HOUR + 600) as f64);
+                }
+            }
+        }
+        // Friday of the last week, 17:10
+        let max = (MONDAY + (11 * 7 + 4) * SECONDS_PER_DAY + 17 * SECONDS_PER_HOUR + 600) as f64;
+        (profile.finish(MONDAY as f64, max).expect("profile"), MONDAY as f64, max)
+    }
+
+    #[test]
+    fn test_profile_counts_calendar_fields() {
+        let (seasonality, _, _) = business_hours();
+
+        assert_eq!(seasonality.hour_of_day[3], 0);
+        assert_eq!(seasonality.hour_of_day[9], 60);
+        assert_eq!(&seasonality.day_of_week[5..], &[0, 0]);
+        assert_eq!(seasonality.monthly.keys().next().map(String::as_str), Some("2024-01"));
+    }
+
+    #[test]
+    fn test_samples_follow_profile() {
+        let (seasonality, min, max) = business_hours();
+        let mut rng = StdRng::seed_from_u64(5);
+
+        for _ in 0..500 {
+            let value = seasonality.sample(min, max, &mut rng);
+            assert!((min..=max).contains(&value));
+
+            let ts = DateTime::from_timestamp(value as i64, 0).unwrap();
+            assert!(ts.weekday().num_days_from_monday() < 5, "weekend sample {}", ts);
+            let hour = (value as i64).rem_euclid(SECONDS_PER_DAY) / SECONDS_PER_HOUR;
+            assert!((9..18).contains(&hour), "off-hours sample {}", ts);
+        }
+    }
+
+    #[test]
+    fn test_resampled_time_keeps_day() {
+        let (seasonality, min, max) = business_hours();
+        let mut rng = StdRng::seed_from_u64(5);
+
+        // 03:00 on the Wednesday of the second week
+        let night = (MONDAY + 9 * SECONDS_PER_DAY + 3 * SECONDS_PER_HOUR) as f64;
+        for _ in 0..100 {
+            let value = seasonality.resample_time_of_day(night, min, max, &mut rng);
+            assert_eq!((value as i64).div_euclid(SECONDS_PER_DAY), (night as i64).div_euclid(SECONDS_PER_DAY));
+            let hour = (value as i64).rem_euclid(SECONDS_PER_DAY) / SECONDS_PER_HOUR;
+            assert!((9..18).contains(&hour), "off-hours time {}", value);
+        }
+
+        // Times that would leave the range are kept
+        assert_eq!(seasonality.resample_time_of_day(min, min, min, &mut rng), min);
+    }
+
+    #[test]
+    fn test_short_span_has_no_trend() {
+        let mut profile = SeasonalityProfile::default();
+        for i in 0..200 {
+            profile.add((MONDAY + i * 600) as f64);
+        }
+        let seasonality = profile.finish(MONDAY as f64, (MONDAY + 200 * 600) as f64).unwrap();
+        assert!(seasonality.monthly.is_empty());
+    }
+}
@@ -0,0 +1,306 @@
+//! Semantic column types (names, emails, phone numbers, ...) and the heuristics that
+//! detect them from column names and sampled values.
+
+use std::fmt;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SemanticType {
+    PersonName,
+    Email,
+    Phone,
+    Ssn,
+    CreditCard,
+    Address,
+    Company,
+}
+
+impl SemanticType {
+    /// Whether values of this type identify a real person and must not be stored.
+    pub fn is_pii(&self) -> bool {
+        matches!(
+            self,
+            SemanticType::PersonName
+                | SemanticType::Email
+                | SemanticType::Phone
+                | SemanticType::Ssn
+                | SemanticType::CreditCard
+                | SemanticType::Address
+        )
+    }
+}
+
+impl fmt::Display for SemanticType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SemanticType::PersonName => write!(f, "person_name"),
+            SemanticType::Email => write!(f, "email"),
+            SemanticType::Phone => write!(f, "phone"),
+            SemanticType::Ssn => write!(f, "ssn"),
+            SemanticType::CreditCard => write!(f, "credit_card"),
+            SemanticType::Address => write!(f, "address"),
+            SemanticType::Company => write!(f, "company"),
+        }
+    }
+}
+
+impl FromStr for SemanticType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "person_name" | "name" => Ok(SemanticType::PersonName),
+            "email" => Ok(SemanticType::Email),
+            "phone" => Ok(SemanticType::Phone),
+            "ssn" => Ok(SemanticType::Ssn),
+            "credit_card" => Ok(SemanticType::CreditCard),
+            "address" => Ok(SemanticType::Address),
+            "company" => Ok(SemanticType::Company),
+            other => anyhow::bail!(
+                "Unknown semantic type '{}' (expected person_name, email, phone, ssn, credit_card, address or company)",
+                other
+            ),
+        }
+    }
+}
+
+/// Share of sampled values that must match a pattern when the column name gives no hint.
+const VALUE_MATCH_THRESHOLD: f64 = 0.8;
+
+/// Share of sampled values that must match when the column name already suggests the type.
+const NAMED_VALUE_MATCH_THRESHOLD: f64 = 0.5;
+
+/// Classifies a text column as PII from its name and a sample of its values.
+pub fn classify_pii(column_name: &str, samples: &[String]) -> Option<SemanticType> {
+    let by_name = classify_by_name(column_name).filter(SemanticType::is_pii);
+
+    // Names have no recognizable value pattern; trust the column name alone
+    if by_name == Some(SemanticType::PersonName) {
+        return by_name;
+    }
+
+    let non_empty: Vec<&str> = samples
+        .iter()
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    if let Some(kind) = by_name
+        && (non_empty.is_empty() || match_ratio(&non_empty, kind) >= NAMED_VALUE_MATCH_THRESHOLD)
+    {
+        return Some(kind);
+    }
+
+    if non_empty.is_empty() {
+        return None;
+    }
+
+    [
+        SemanticType::Ssn,
+        SemanticType::CreditCard,
+        SemanticType::Email,
+        SemanticType::Phone,
+    ]
+        .into_iter()
+        .find(|&kind| match_ratio(&non_empty, kind) >= VALUE_MATCH_THRESHOLD)
+}
+
+/// Column-name heuristics only.
+pub fn classify_by_name(column_name: &str) -> Option<SemanticType> {
+    let name = column_name.to_lowercase();
+    let tokens: Vec<&str> = name.split(|c: char| !c.is_ascii_alphanumeric()).collect();
+    let has = |t: &str| tokens.contains(&t);
+
+    if name.contains("email") || name.contains("e_mail") {
+        return Some(SemanticType::Email);
+    }
+    if name.contains("address") || has("addr") || has("street") {
+        return Some(SemanticType::Address);
+    }
+    if has("ssn") || name.contains("social_security") {
+        return Some(SemanticType::Ssn);
+    }
+    if name.contains("credit_card")
+        || name.contains("card_number")
+        || name.contains("card_num")
+        || name.contains("cc_num")
+    {
+        return Some(SemanticType::CreditCard);
+    }
+    if name.contains("phone") || has("mobile") || has("tel") || has("telephone") || has("cell") || has("fax") {
+        return Some(SemanticType::Phone);
+    }
+
+    if has("company") || has("employer") || has("organization") || has("organisation") || name.contains("business_name") {
+        return Some(SemanticType::Company);
+    }
+
+    let person_prefixes = [
+        "first", "last", "full", "given", "family", "middle", "customer", "contact", "person",
+        "employee", "owner", "author", "user", "patient", "member",
+    ];
+    if matches!(name.as_str(), "firstname" | "lastname" | "fullname" | "surname")
+        || (has("name") && person_prefixes.iter().any(|p| has(p)))
+    {
+        return Some(SemanticType::PersonName);
+    }
+
+    None
+}
+
+fn match_ratio(values: &[&str], kind: SemanticType) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let matches = values.iter().filter(|v| matches_pattern(v, kind)).count();
+    matches as f64 / values.len() as f64
+}
+
+fn matches_pattern(value: &str, kind: SemanticType) -> bool {
+    match kind {
+        SemanticType::Email => looks_like_email(value),
+        SemanticType::Phone => looks_like_phone(value),
+        SemanticType::Ssn => looks_like_ssn(value),
+        SemanticType::CreditCard => looks_like_credit_card(value),
+        SemanticType::Address => looks_like_address(value),
+        SemanticType::PersonName | SemanticType::Company => false,
+    }
+}
+
+fn looks_like_email(value: &str) -> bool {
+    let Some((local, domain)) = value.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && !value.contains(char::is_whitespace)
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+}
+
+/// Street number followed by at least two words, e.g. `221 Baker Street`.
+fn looks_like_address(value: &str) -> bool {
+    let mut words = value.split_whitespace();
+    let Some(number) = words.next() else {
+        return false;
+    };
+    number.chars().next().is_some_and(|c| c.is_ascii_digit())
+        && number.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        && words.filter(|w| w.chars().any(char::is_alphabetic)).count() >= 2
+}
+
+fn looks_like_phone(value: &str) -> bool {
+    if !value.chars().all(|c| c.is_ascii_digit() || " +-().".contains(c)) {
+        return false;
+    }
+    let digits = value.chars().filter(|c| c.is_ascii_digit()).count();
+    // Bare digit strings are more likely ids than phone numbers
+    let formatted = value.starts_with('+') || value.chars().any(|c| " -().".contains(c));
+    (7..=15).contains(&digits) && formatted
+}
+
+fn looks_like_ssn(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    bytes.len() == 11
+        && bytes[3] == b'-'
+        && bytes[6] == b'-'
+        && bytes
+            .iter()
+            .enumerate()
+            .all(|(i, b)| i == 3 || i == 6 || b.is_ascii_digit())
+}
+
+fn looks_like_credit_card(value: &str) -> bool {
+    if !value.chars().all(|c| c.is_ascii_digit() || c == ' ' || c == '-') {
+        return false;
+    }
+    let digits: Vec<u32> = value.chars().filter_map(|c| c.to_digit(10)).collect();
+    (13..=19).contains(&digits.len()) && luhn_valid(&digits)
+}
+
+/// Luhn checksum over a digit sequence (check digit last).
+pub fn luhn_valid(digits: &[u32]) -> bool {
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| {
+            if i % 2 == 1 {
+                let doubled = d * 2;
+                if doubled > 9 { doubled - 9 } else { doubled }
+            } else {
+                d
+            }
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_classify_by_name() {
+        assert_eq!(classify_by_name("email"), Some(SemanticType::Email));
+        assert_eq!(classify_by_name("contact_phone"), Some(SemanticType::Phone));
+        assert_eq!(classify_by_name("first_name"), Some(SemanticType::PersonName));
+        assert_eq!(classify_by_name("customer_ssn"), Some(SemanticType::Ssn));
+        assert_eq!(classify_by_name("product_name"), None);
+        assert_eq!(classify_by_name("hotel"), None);
+        assert_eq!(classify_by_name("billing_address"), Some(SemanticType::Address));
+        assert_eq!(classify_by_name("company_name"), Some(SemanticType::Company));
+    }
+
+    #[test]
+    fn test_company_is_not_pii() {
+        let companies = strings(&["Acme Corp", "Globex LLC"]);
+        assert_eq!(classify_pii("company", &companies), None);
+
+        let addresses = strings(&["221 Baker Street", "12 Grimmauld Place, London"]);
+        assert_eq!(classify_pii("home_address", &addresses), Some(SemanticType::Address));
+    }
+
+    #[test]
+    fn test_parse_semantic_type() {
+        assert_eq!("credit_card".parse::<SemanticType>().ok(), Some(SemanticType::CreditCard));
+        assert_eq!("Company".parse::<SemanticType>().ok(), Some(SemanticType::Company));
+        assert!("zip".parse::<SemanticType>().is_err());
+    }
+
+    #[test]
+    fn test_classify_by_values() {
+        let emails = strings(&["a@example.com", "b.c@test.org", "x@y.io"]);
+        assert_eq!(classify_pii("contact", &emails), Some(SemanticType::Email));
+
+        let ssns = strings(&["123-45-6789", "987-65-4321"]);
+        assert_eq!(classify_pii("identifier", &ssns), Some(SemanticType::Ssn));
+
+        let cards = strings(&["4111 1111 1111 1111", "5500-0000-0000-0004"]);
+        assert_eq!(classify_pii("pan", &cards), Some(SemanticType::CreditCard));
+
+        let phones = strings(&["+1 415 555 0100", "(212) 555-0199"]);
+        assert_eq!(classify_pii("contact", &phones), Some(SemanticType::Phone));
+
+        let statuses = strings(&["active", "closed"]);
+        assert_eq!(classify_pii("status", &statuses), None);
+    }
+
+    #[test]
+    fn test_name_hint_contradicted_by_values() {
+        let flags = strings(&["yes", "no", "yes"]);
+        assert_eq!(classify_pii("email_verified", &flags), None);
+    }
+
+    #[test]
+    fn test_luhn() {
+        assert!(luhn_valid(&[4, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1]));
+        assert!(!luhn_valid(&[4, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 2]));
+    }
+}
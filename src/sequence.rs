@@ -0,0 +1,171 @@
+//! Detection of increasing, sequence-like columns (`invoice_number`, `event_seq`,
+//! insertion-ordered `created_at`) and generation of new sequences from their gaps.
+//!
+//! Order is taken from the scan stream, which follows the table's physical order. A
+//! column counts as a sequence when nearly every step between consecutive non-null
+//! values goes up; generation then starts at the source minimum and adds gaps drawn
+//! from the observed gap distribution.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::math::{Reservoir, DEFAULT_RESERVOIR_CAPACITY};
+
+/// Fewest non-null values before a column can be called a sequence.
+pub const MIN_SEQUENCE_VALUES: u64 = 100;
+
+/// Share of steps that must not go down.
+pub const MIN_INCREASING_RATIO: f64 = 0.99;
+
+/// Gap quantiles stored in the genome (0%, 5%, ..., 100%).
+const GAP_QUANTILES: usize = 21;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SequenceModel {
+    /// First generated value.
+    pub start: f64,
+
+    /// Evenly spaced quantiles of the non-negative gaps between consecutive values.
+    pub gap_quantiles: Vec<f64>,
+
+    /// Round generated values to whole numbers.
+    pub integer: bool,
+}
+
+impl SequenceModel {
+    /// Gap to the next value, by inverse transform over the stored quantiles.
+    pub fn sample_gap<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        let Some(last) = self.gap_quantiles.len().checked_sub(1).filter(|&l| l > 0) else {
+            return self.gap_quantiles.first().copied().unwrap_or(1.0);
+        };
+
+        let position = rng.gen_range(0.0..1.0) * last as f64;
+        let index = (position.floor() as usize).min(last - 1);
+        let (low, high) = (self.gap_quantiles[index], self.gap_quantiles[index + 1]);
+        let gap = low + (high - low) * (position - index as f64);
+
+        if self.integer { gap.round() } else { gap }
+    }
+
+    /// The value following `previous`, or the start value for the first row.
+    pub fn next<R: Rng + ?Sized>(&self, previous: Option<f64>, rng: &mut R) -> f64 {
+        match previous {
+            Some(previous) => previous + self.sample_gap(rng),
+            None => self.start,
+        }
+    }
+}
+
+/// Follows one column through the scan stream.
+pub struct SequenceTracker {
+    first: Option<f64>,
+    previous: Option<f64>,
+    values: u64,
+    decreasing_steps: u64,
+    gaps: Reservoir<f64>,
+}
+
+impl Default for SequenceTracker {
+    fn default() -> Self {
+        Self {
+            first: None,
+            previous: None,
+            values: 0,
+            decreasing_steps: 0,
+            gaps: Reservoir::new(DEFAULT_RESERVOIR_CAPACITY),
+        }
+    }
+}
+
+impl SequenceTracker {
+    pub fn add(&mut self, value: f64) {
+        self.values += 1;
+        if let Some(previous) = self.previous {
+            if value < previous {
+                self.decreasing_steps += 1;
+            } else {
+                self.gaps.add(value - previous);
+            }
+        } else {
+            self.first = Some(value);
+        }
+        self.previous = Some(value);
+    }
+
+    /// The sequence model, or `None` when the column is not (nearly) increasing.
+    pub fn finish(self, integer: bool) -> Option<SequenceModel> {
+        if self.values < MIN_SEQUENCE_VALUES {
+            return None;
+        }
+
+        let steps = self.values - 1;
+        if (steps - self.decreasing_steps) as f64 / (steps as f64) < MIN_INCREASING_RATIO {
+            return None;
+        }
+
+        let mut gaps = self.gaps.into_sample();
+        gaps.sort_by(|a, b| a.total_cmp(b));
+        // A constant column "increases" by zero every step; that is not a sequence
+        if gaps.last().is_none_or(|&largest| largest <= 0.0) {
+            return None;
+        }
+
+        let gap_quantiles = (0..GAP_QUANTILES)
+            .map(|i| {
+                let index = i * (gaps.len() - 1) / (GAP_QUANTILES - 1);
+                gaps[index]
+            })
+            .collect();
+
+        Some(SequenceModel {
+            start: self.first?,
+            gap_quantiles,
+            integer,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_detects_increasing_column() {
+        let mut tracker = SequenceTracker::default();
+        for i in 0..500 {
+            // Mostly +1, every tenth number skipped
+            tracker.add((1000 + i + i / 10) as f64);
+        }
+
+        let model = tracker.finish(true).expect("sequence");
+        assert_eq!(model.start, 1000.0);
+        assert_eq!(model.gap_quantiles.first(), Some(&1.0));
+        assert_eq!(model.gap_quantiles.last(), Some(&2.0));
+    }
+
+    #[test]
+    fn test_shuffled_column_is_not_a_sequence() {
+        let mut tracker = SequenceTracker::default();
+        for i in 0..500u64 {
+            tracker.add(((i * 7919) % 500) as f64);
+        }
+        assert!(tracker.finish(true).is_none());
+    }
+
+    #[test]
+    fn test_generated_values_increase() {
+        let model = SequenceModel { start: 10.0, gap_quantiles: vec![1.0, 1.0, 3.0], integer: true };
+        let mut rng = StdRng::seed_from_u64(1);
+
+        let mut previous = None;
+        for _ in 0..100 {
+            let value = model.next(previous, &mut rng);
+            if let Some(p) = previous {
+                assert!(value > p && value - p <= 3.0);
+                assert_eq!(value.fract(), 0.0);
+            }
+            previous = Some(value);
+        }
+    }
+}
@@ -0,0 +1,462 @@
+//! HTTP server mode.
+//!
+//! `replica_db serve` keeps uploaded genomes in memory and generates from them on
+//! request, streaming the output back, so CI jobs and web UIs can use synthesis
+//! without installing the CLI:
+//!
+//! - `POST /genomes` with a genome file (JSON or binary) as the body stores it and
+//!   returns its id.
+//! - `GET /genomes/{id}` summarizes a stored genome; `DELETE` removes it.
+//! - `POST /genomes/{id}/generate` with `{"rows": 1000, "seed": 42}` streams COPY SQL
+//!   for every table; `{"rows": 1000, "format": "csv", "table": "users"}` streams one
+//!   table as CSV.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use anyhow::{Context, Result};
+use axum::body::{Body, Bytes};
+use axum::extract::{DefaultBodyLimit, Path, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+use crate::genome::DatabaseGenome;
+use crate::output::{write_copy_sql, write_table_csv};
+use crate::synth::{SynthesisConfig, Synthesizer};
+
+/// Bytes of output collected before a chunk is sent to the client.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Chunks buffered between the generating thread and the response body.
+const CHANNEL_CHUNKS: usize = 16;
+
+#[derive(Debug, Clone)]
+pub struct ServeOptions {
+    /// Largest genome upload accepted, in bytes.
+    pub max_genome_bytes: usize,
+
+    /// Largest `rows` a generate request may ask for, per table.
+    pub max_rows: usize,
+
+    /// Tables generated in parallel per request.
+    pub jobs: usize,
+}
+
+impl Default for ServeOptions {
+    fn default() -> Self {
+        Self {
+            max_genome_bytes: 64 * 1024 * 1024,
+            max_rows: 1_000_000,
+            jobs: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// `COPY ... FROM stdin` blocks for every table, in load order.
+    #[default]
+    Copy,
+    /// One table as CSV with a header row.
+    Csv,
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputFormat::Copy => write!(f, "copy"),
+            OutputFormat::Csv => write!(f, "csv"),
+        }
+    }
+}
+
+/// Body of `POST /genomes/{id}/generate`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GenerateRequest {
+    /// Rows per table.
+    pub rows: usize,
+
+    #[serde(default)]
+    pub seed: Option<u64>,
+
+    #[serde(default)]
+    pub format: OutputFormat,
+
+    /// Table to return; required for CSV, which holds a single table.
+    #[serde(default)]
+    pub table: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GenomeSummary {
+    pub id: String,
+    pub tables: Vec<String>,
+    pub columns: usize,
+}
+
+impl GenomeSummary {
+    fn new(id: &str, genome: &DatabaseGenome) -> Self {
+        Self {
+            id: id.to_string(),
+            tables: genome.tables.iter().map(|t| t.name.clone()).collect(),
+            columns: genome.total_columns(),
+        }
+    }
+}
+
+/// Uploaded genomes by id, shared by the HTTP and gRPC servers.
+#[derive(Default)]
+pub struct GenomeStore {
+    genomes: RwLock<HashMap<String, Arc<DatabaseGenome>>>,
+}
+
+impl GenomeStore {
+    /// Parses a genome file (JSON or binary) and stores it under a new id.
+    pub fn insert_bytes(&self, bytes: &[u8]) -> Result<GenomeSummary> {
+        let genome = DatabaseGenome::from_bytes(bytes)?;
+        let id = uuid::Uuid::new_v4().to_string();
+        let summary = GenomeSummary::new(&id, &genome);
+        self.genomes
+            .write()
+            .map_err(|_| anyhow::anyhow!("Genome store is poisoned"))?
+            .insert(id.clone(), Arc::new(genome));
+        info!(id = %id, tables = summary.tables.len(), "Stored uploaded genome");
+        Ok(summary)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<DatabaseGenome>> {
+        self.genomes.read().ok()?.get(id).cloned()
+    }
+
+    /// Returns whether a genome was stored under `id`.
+    pub fn remove(&self, id: &str) -> bool {
+        self.genomes.write().is_ok_and(|mut genomes| genomes.remove(id).is_some())
+    }
+}
+
+struct ServerState {
+    store: Arc<GenomeStore>,
+    options: ServeOptions,
+}
+
+impl ServerState {
+    fn genome(&self, id: &str) -> Result<Arc<DatabaseGenome>, ApiError> {
+        self.store
+            .get(id)
+            .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("No genome with id '{}'", id)))
+    }
+}
+
+/// An error returned to the client as `{"error": "..."}`.
+#[derive(Debug)]
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self { status, message: message.into() }
+    }
+
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+
+    fn internal(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, message)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(serde_json::json!({ "error": self.message }))).into_response()
+    }
+}
+
+/// The HTTP routes over `store`.
+pub fn router(store: Arc<GenomeStore>, options: ServeOptions) -> Router {
+    let state = Arc::new(ServerState {
+        store,
+        options: options.clone(),
+    });
+
+    Router::new()
+        .route("/health", get(|| async { "ok" }))
+        .route("/genomes", post(upload_genome))
+        .route("/genomes/{id}", get(describe_genome).delete(delete_genome))
+        .route("/genomes/{id}/generate", post(generate))
+        .layer(DefaultBodyLimit::max(options.max_genome_bytes))
+        .with_state(state)
+}
+
+/// Serves [`router`] on `listen` until the process is stopped.
+pub async fn serve(listen: SocketAddr, store: Arc<GenomeStore>, options: ServeOptions) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(listen)
+        .await
+        .context(format!("Failed to listen on {}", listen))?;
+    info!(address = %listen, "Serving replica_db HTTP API");
+    axum::serve(listener, router(store, options)).await.context("HTTP server failed")?;
+    Ok(())
+}
+
+async fn upload_genome(
+    State(state): State<Arc<ServerState>>,
+    body: Bytes,
+) -> Result<(StatusCode, Json<GenomeSummary>), ApiError> {
+    let summary = state
+        .store
+        .insert_bytes(&body)
+        .map_err(|e| ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, format!("{:#}", e)))?;
+    Ok((StatusCode::CREATED, Json(summary)))
+}
+
+async fn describe_genome(
+    State(state): State<Arc<ServerState>>,
+    Path(id): Path<String>,
+) -> Result<Json<GenomeSummary>, ApiError> {
+    let genome = state.genome(&id)?;
+    Ok(Json(GenomeSummary::new(&id, &genome)))
+}
+
+async fn delete_genome(State(state): State<Arc<ServerState>>, Path(id): Path<String>) -> Result<StatusCode, ApiError> {
+    if state.store.remove(&id) {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::new(StatusCode::NOT_FOUND, format!("No genome with id '{}'", id)))
+    }
+}
+
+/// Generates in a blocking task and streams the written output as it is produced.
+/// Errors found before the first byte (bad request, generation failure) get a proper
+/// status; a failure while writing can only cut the stream short.
+async fn generate(
+    State(state): State<Arc<ServerState>>,
+    Path(id): Path<String>,
+    Json(request): Json<GenerateRequest>,
+) -> Result<Response, ApiError> {
+    let genome = state.genome(&id)?;
+    validate_request(&request, &genome, &state.options)?;
+
+    let config = SynthesisConfig {
+        rows_per_table: request.rows,
+        seed: request.seed,
+        jobs: state.options.jobs,
+        ..SynthesisConfig::default()
+    };
+    let (synthesizer, result) = tokio::task::spawn_blocking(move || -> Result<_> {
+        let synthesizer = Synthesizer::new((*genome).clone(), config)?;
+        let result = synthesizer.generate()?;
+        Ok((synthesizer, result))
+    })
+    .await
+    .map_err(|e| ApiError::internal(format!("Generation task failed: {}", e)))?
+    .map_err(|e| ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, format!("{:#}", e)))?;
+
+    info!(id = %id, rows = result.total_rows(), format = %request.format, "Streaming generated data");
+
+    let (sender, receiver) = mpsc::channel(CHANNEL_CHUNKS);
+    let format = request.format;
+    let table = request.table;
+    tokio::task::spawn_blocking(move || {
+        let mut writer = ChannelWriter::new(sender);
+        let written = match (format, table) {
+            (OutputFormat::Csv, Some(table)) => write_table_csv(&mut writer, &synthesizer, &result, &table),
+            _ => write_copy_sql(&mut writer, &synthesizer, &result),
+        };
+        if let Err(e) = written {
+            warn!(error = %e, "Stopped streaming generated data");
+        }
+    });
+
+    let stream = futures::stream::unfold(receiver, |mut receiver| async move {
+        receiver
+            .recv()
+            .await
+            .map(|chunk| (Ok::<_, io::Error>(chunk), receiver))
+    });
+    let content_type = match format {
+        OutputFormat::Copy => "application/sql",
+        OutputFormat::Csv => "text/csv",
+    };
+    Ok(([(header::CONTENT_TYPE, content_type)], Body::from_stream(stream)).into_response())
+}
+
+fn validate_request(request: &GenerateRequest, genome: &DatabaseGenome, options: &ServeOptions) -> Result<(), ApiError> {
+    if request.rows > options.max_rows {
+        return Err(ApiError::bad_request(format!(
+            "rows is {} but this server generates at most {} rows per table",
+            request.rows, options.max_rows
+        )));
+    }
+    match (request.format, &request.table) {
+        (OutputFormat::Csv, None) => Err(ApiError::bad_request("format csv needs a table")),
+        (OutputFormat::Copy, Some(_)) => Err(ApiError::bad_request("table is only used with format csv")),
+        (OutputFormat::Csv, Some(table)) if genome.get_table(table).is_none() => {
+            Err(ApiError::bad_request(format!("Table '{}' not found in genome", table)))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Sends what is written to it to the response body in [`CHUNK_SIZE`] pieces. Fails
+/// with `BrokenPipe` once the client has gone away, which stops the writer.
+struct ChannelWriter {
+    sender: mpsc::Sender<Bytes>,
+    buffer: Vec<u8>,
+}
+
+impl ChannelWriter {
+    fn new(sender: mpsc::Sender<Bytes>) -> Self {
+        Self {
+            sender,
+            buffer: Vec::with_capacity(CHUNK_SIZE),
+        }
+    }
+
+    fn send_buffer(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let chunk = Bytes::from(std::mem::replace(&mut self.buffer, Vec::with_capacity(CHUNK_SIZE)));
+        self.sender
+            .blocking_send(chunk)
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "Client disconnected"))
+    }
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let taken = data.len().min(CHUNK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&data[..taken]);
+        if self.buffer.len() == CHUNK_SIZE {
+            self.send_buffer()?;
+        }
+        Ok(taken)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send_buffer()
+    }
+}
+
+impl Drop for ChannelWriter {
+    fn drop(&mut self) {
+        let _ = self.send_buffer();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use axum::http::Request;
+    use tower::ServiceExt;
+    use crate::math::{Distribution, Histogram};
+    use crate::schema::{Column, DataType, Table};
+
+    fn genome_json() -> Vec<u8> {
+        let tables = vec![Table::new(
+            "users".to_string(),
+            vec![
+                Column::new("id".to_string(), DataType::Integer, false, true),
+                Column::new("name".to_string(), DataType::Text, false, false),
+            ],
+            vec![],
+        )];
+        let mut distributions = HashMap::new();
+        distributions.insert(
+            DatabaseGenome::make_key("users", "name"),
+            Distribution::new(None, None, 0, 10, 1, Histogram::Categorical {
+                frequencies: [("Alice".to_string(), 10)].into_iter().collect(),
+                truncated: false,
+                long_tail: None,
+            }),
+        );
+        serde_json::to_vec(&DatabaseGenome::new(tables, distributions)).unwrap()
+    }
+
+    async fn send(app: &Router, method: &str, uri: &str, body: Vec<u8>) -> (StatusCode, String) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_upload_and_generate() {
+        let app = router(Arc::default(), ServeOptions::default());
+
+        let (status, body) = send(&app, "POST", "/genomes", genome_json()).await;
+        assert_eq!(status, StatusCode::CREATED, "{}", body);
+        let summary: GenomeSummary = serde_json::from_str(&body).unwrap();
+        assert_eq!(summary.tables, vec!["users"]);
+
+        let uri = format!("/genomes/{}/generate", summary.id);
+        let (status, body) = send(&app, "POST", &uri, br#"{"rows": 2}"#.to_vec()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "COPY users (id, name) FROM stdin;\n1\tAlice\n2\tAlice\n\\.\n\n");
+
+        let request = br#"{"rows": 2, "format": "csv", "table": "users"}"#.to_vec();
+        let (status, body) = send(&app, "POST", &uri, request).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "id,name\n1,Alice\n2,Alice\n");
+
+        let (status, _) = send(&app, "DELETE", &format!("/genomes/{}", summary.id), vec![]).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _) = send(&app, "POST", &uri, br#"{"rows": 2}"#.to_vec()).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_bad_requests() {
+        let app = router(Arc::default(), ServeOptions { max_rows: 10, ..ServeOptions::default() });
+
+        let (status, body) = send(&app, "POST", "/genomes", b"{\"tables\": 1}".to_vec()).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body.contains("error"));
+
+        let (_, body) = send(&app, "POST", "/genomes", genome_json()).await;
+        let summary: GenomeSummary = serde_json::from_str(&body).unwrap();
+        let uri = format!("/genomes/{}/generate", summary.id);
+
+        for request in [
+            r#"{"rows": 11}"#,
+            r#"{"rows": 1, "format": "csv"}"#,
+            r#"{"rows": 1, "format": "csv", "table": "missing"}"#,
+            r#"{"rows": 1, "table": "users"}"#,
+        ] {
+            let (status, _) = send(&app, "POST", &uri, request.as_bytes().to_vec()).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", request);
+        }
+    }
+
+    #[test]
+    fn test_channel_writer_chunks() {
+        let (sender, mut receiver) = mpsc::channel(CHANNEL_CHUNKS);
+        let mut writer = ChannelWriter::new(sender);
+        writer.write_all(&vec![b'x'; CHUNK_SIZE + 10]).unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+
+        let mut sizes = Vec::new();
+        while let Ok(chunk) = receiver.try_recv() {
+            sizes.push(chunk.len());
+        }
+        assert_eq!(sizes, vec![CHUNK_SIZE, 10]);
+    }
+}
@@ -0,0 +1,287 @@
+//! Referential subsetting: generating a slice of the database around a number of
+//! root entities instead of a fixed number of rows per table.
+//!
+//! The scan records, for every foreign key, how many parents have 0, 1, 2, ...
+//! children (a [`FanOut`]). Given root row counts, a [`SubsetLayout`] walks the
+//! tables in generation order. Each table below a root draws a child count for every
+//! parent row from its fan-out, so the slice holds every dependent row at the source's
+//! density, and child row `i` references the parent whose range holds `i`.
+
+use std::collections::HashMap;
+use std::ops::Range;
+use anyhow::{bail, Result};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+use crate::genome::DatabaseGenome;
+use crate::schema::{ForeignKey, Table};
+
+/// Child counts above this are rounded to two significant digits, which keeps the
+/// stored values few even for parents with millions of children.
+const EXACT_FANOUT_LIMIT: u64 = 100;
+
+/// How many parents have each number of children through one foreign key.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FanOut {
+    /// Distinct child counts, ascending.
+    pub children: Vec<u64>,
+
+    /// Parents with each child count.
+    pub parents: Vec<u64>,
+}
+
+impl FanOut {
+    /// Builds the fan-out from `(children, parents)` pairs, rounding large child
+    /// counts (see [`EXACT_FANOUT_LIMIT`]).
+    pub fn from_counts(counts: impl IntoIterator<Item = (u64, u64)>) -> Self {
+        let mut merged: std::collections::BTreeMap<u64, u64> = Default::default();
+        for (children, parents) in counts {
+            *merged.entry(round_children(children)).or_default() += parents;
+        }
+        merged.retain(|_, parents| *parents > 0);
+        let (children, parents) = merged.into_iter().unzip();
+        Self { children, parents }
+    }
+
+    /// Children per parent on average.
+    pub fn mean(&self) -> f64 {
+        let parents: u64 = self.parents.iter().sum();
+        if parents == 0 {
+            return 0.0;
+        }
+        let children: u64 = self.children.iter().zip(&self.parents).map(|(c, p)| c * p).sum();
+        children as f64 / parents as f64
+    }
+
+    /// Running parent totals, for [`FanOut::draw`].
+    fn cumulative(&self) -> Vec<u64> {
+        self.parents
+            .iter()
+            .scan(0, |total, &parents| {
+                *total += parents;
+                Some(*total)
+            })
+            .collect()
+    }
+
+    /// A child count drawn with its share of parents.
+    fn draw<R: Rng + ?Sized>(&self, cumulative: &[u64], rng: &mut R) -> u64 {
+        let Some(&total) = cumulative.last().filter(|&&total| total > 0) else {
+            return 0;
+        };
+        let target = rng.gen_range(0..total);
+        self.children[cumulative.partition_point(|&c| c <= target)]
+    }
+}
+
+fn round_children(children: u64) -> u64 {
+    if children <= EXACT_FANOUT_LIMIT {
+        return children;
+    }
+    let scale = 10u64.pow(children.ilog10() - 1);
+    (children + scale / 2) / scale * scale
+}
+
+/// Row counts of a slice, and for each table below a root, the foreign key its rows
+/// are grouped by and the child rows of every parent row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubsetLayout {
+    rows: HashMap<String, usize>,
+
+    /// Child table -> (foreign key column, parent `p` owns `bounds[p]..bounds[p + 1]`).
+    groups: HashMap<String, (String, Vec<usize>)>,
+}
+
+impl SubsetLayout {
+    /// Lays out the slice below `roots` (table -> rows) over `order`, the tables in
+    /// generation order. A table joins the slice through its foreign key to the table
+    /// furthest below a root already in it, the first such on ties; self-references
+    /// don't count. Foreign keys without a
+    /// stored fan-out use the ratio of the tables' estimated sizes, with each parent
+    /// getting the rounded mean. Tables outside the slice keep `rows_for`.
+    pub fn new<R: Rng + ?Sized>(
+        genome: &DatabaseGenome,
+        order: &[String],
+        roots: &HashMap<String, usize>,
+        rows_for: impl Fn(&str) -> usize,
+        rng: &mut R,
+    ) -> Result<Self> {
+        for root in roots.keys() {
+            if genome.get_table(root).is_none() {
+                bail!("Root table '{}' not found in genome", root);
+            }
+        }
+
+        let mut rows = HashMap::new();
+        let mut groups = HashMap::new();
+        // Steps below a root, for picking the nearest owner of a table
+        let mut depths: HashMap<&str, usize> = HashMap::new();
+        for name in order {
+            let Some(table) = genome.get_table(name) else {
+                continue;
+            };
+            if let Some(&count) = roots.get(name) {
+                rows.insert(name.clone(), count);
+                depths.insert(name, 0);
+                continue;
+            }
+
+            let Some(fk) = table
+                .foreign_keys
+                .iter()
+                .filter(|fk| fk.target_table != table.name && depths.contains_key(fk.target_table.as_str()))
+                .rev()
+                .max_by_key(|fk| depths[fk.target_table.as_str()])
+            else {
+                rows.insert(name.clone(), rows_for(name));
+                continue;
+            };
+
+            let parents = rows[&fk.target_table];
+            let bounds = child_bounds(genome, table, fk, parents, rng);
+            debug!(table = %name, parent = %fk.target_table, parents, rows = bounds[parents], "Sized table from its fan-out");
+            rows.insert(name.clone(), bounds[parents]);
+            groups.insert(name.clone(), (fk.source_col.clone(), bounds));
+            depths.insert(name, depths[fk.target_table.as_str()] + 1);
+        }
+
+        Ok(Self { rows, groups })
+    }
+
+    /// Rows to generate for `table`.
+    pub fn rows_for(&self, table: &str) -> Option<usize> {
+        self.rows.get(table).copied()
+    }
+
+    /// Parent row that row `row` of `table` references through foreign key `column`,
+    /// when the table is grouped by it.
+    pub fn parent_of(&self, table: &str, column: &str, row: usize) -> Option<usize> {
+        let (grouped_by, bounds) = self.groups.get(table)?;
+        if grouped_by != column {
+            return None;
+        }
+        let parent = bounds.partition_point(|&start| start <= row).checked_sub(1)?;
+        (parent + 1 < bounds.len()).then_some(parent)
+    }
+
+    /// Child rows of `table` referencing parent row `parent`.
+    pub fn children(&self, table: &str, parent: usize) -> Option<Range<usize>> {
+        let (_, bounds) = self.groups.get(table)?;
+        Some(*bounds.get(parent)?..*bounds.get(parent + 1)?)
+    }
+}
+
+/// Running child row bounds for `parents` parent rows of `fk`.
+fn child_bounds<R: Rng + ?Sized>(genome: &DatabaseGenome, table: &Table, fk: &ForeignKey, parents: usize, rng: &mut R) -> Vec<usize> {
+    let mut bounds = Vec::with_capacity(parents + 1);
+    bounds.push(0);
+
+    if let Some(fanout) = genome.fanouts.get(&DatabaseGenome::make_key(&table.name, &fk.source_col)) {
+        let cumulative = fanout.cumulative();
+        for _ in 0..parents {
+            let children = fanout.draw(&cumulative, rng) as usize;
+            bounds.push(bounds[bounds.len() - 1] + children);
+        }
+        return bounds;
+    }
+
+    let estimated = |name: &str| genome.get_table(name).and_then(|t| t.estimate?.rows).filter(|&rows| rows > 0);
+    let mean = match (estimated(&table.name), estimated(&fk.target_table)) {
+        (Some(children), Some(parents)) => children as f64 / parents as f64,
+        _ => {
+            warn!(table = %table.name, parent = %fk.target_table, "No fan-out or size estimates, one child per parent");
+            1.0
+        }
+    };
+    // Whole children per parent, with the fraction spread so the total keeps the mean
+    for parent in 0..parents {
+        let due = ((parent + 1) as f64 * mean).round() as usize;
+        bounds.push(due.max(bounds[parent]));
+    }
+    bounds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Column, DataType, TableEstimate};
+
+    #[test]
+    fn test_fanout_rounds_large_counts() {
+        let fanout = FanOut::from_counts([(0, 5), (3, 10), (1234, 1), (1180, 1), (7, 0)]);
+        assert_eq!(fanout.children, [0, 3, 1200]);
+        assert_eq!(fanout.parents, [5, 10, 2]);
+        assert!((fanout.mean() - (30.0 + 2400.0) / 17.0).abs() < 1e-9);
+
+        let cumulative = fanout.cumulative();
+        let mut rng = rand::thread_rng();
+        let draws: Vec<u64> = (0..1000).map(|_| fanout.draw(&cumulative, &mut rng)).collect();
+        assert!(draws.iter().all(|d| [0, 3, 1200].contains(d)));
+        assert!(draws.contains(&0) && draws.contains(&3));
+    }
+
+    fn genome() -> DatabaseGenome {
+        let id = || Column::new("id".to_string(), DataType::Integer, false, true);
+        let column = |name: &str| Column::new(name.to_string(), DataType::Integer, false, false);
+        let fk = |source: &str, target: &str| ForeignKey::new(source.to_string(), target.to_string(), "id".to_string());
+        let mut products = Table::new("products".to_string(), vec![id()], vec![]);
+        products.estimate = Some(TableEstimate { rows: Some(50), bytes: 0 });
+        let tables = vec![
+            products,
+            Table::new("users".to_string(), vec![id()], vec![]),
+            Table::new("orders".to_string(), vec![id(), column("user_id")], vec![fk("user_id", "users")]),
+            Table::new(
+                "items".to_string(),
+                vec![id(), column("product_id"), column("order_id")],
+                vec![fk("product_id", "products"), fk("order_id", "orders")],
+            ),
+        ];
+        let mut genome = DatabaseGenome::new(tables, HashMap::new());
+        genome.fanouts.insert("orders.user_id".to_string(), FanOut::from_counts([(0, 1), (2, 1), (4, 2)]));
+        genome.fanouts.insert("items.order_id".to_string(), FanOut::from_counts([(3, 1)]));
+        genome
+    }
+
+    #[test]
+    fn test_layout_follows_fanout_below_roots() -> Result<()> {
+        let genome = genome();
+        let order: Vec<String> = ["products", "users", "orders", "items"].map(String::from).to_vec();
+        let roots = HashMap::from([("users".to_string(), 100)]);
+        let layout = SubsetLayout::new(&genome, &order, &roots, |_| 7, &mut rand::thread_rng())?;
+
+        assert_eq!(layout.rows_for("users"), Some(100));
+        assert_eq!(layout.rows_for("products"), Some(7));
+        let orders = layout.rows_for("orders").unwrap();
+        assert!((200..=400).contains(&orders), "{} orders", orders);
+        // Items join through orders, not products
+        assert_eq!(layout.rows_for("items"), Some(3 * orders));
+        assert_eq!(layout.parent_of("items", "order_id", 5), Some(1));
+        assert_eq!(layout.parent_of("items", "product_id", 5), None);
+        assert_eq!(layout.children("items", 1), Some(3..6));
+        assert_eq!(layout.parent_of("items", "order_id", 3 * orders), None);
+
+        for user in 0..100 {
+            let children = layout.children("orders", user).unwrap().len();
+            assert!([0, 2, 4].contains(&children));
+        }
+        assert!(SubsetLayout::new(&genome, &order, &HashMap::from([("missing".to_string(), 1)]), |_| 7, &mut rand::thread_rng()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_estimates_stand_in_for_missing_fanout() {
+        let mut genome = genome();
+        genome.fanouts.clear();
+        for table in &mut genome.tables {
+            let rows = match table.name.as_str() {
+                "users" => 1000,
+                "orders" => 2500,
+                _ => continue,
+            };
+            table.estimate = Some(TableEstimate { rows: Some(rows), bytes: 0 });
+        }
+        let table = genome.get_table("orders").unwrap();
+        let bounds = child_bounds(&genome, table, &table.foreign_keys[0], 10, &mut rand::thread_rng());
+        assert_eq!(bounds, [0, 3, 5, 8, 10, 13, 15, 18, 20, 23, 25]);
+    }
+}
@@ -0,0 +1,125 @@
+//! Constant-time weighted sampling with Walker's alias method.
+//!
+//! Categorical values, numeric bins and discrete integers are drawn by weight for
+//! every generated cell. A linear scan over the frequencies costs O(K) per draw, which
+//! dominates wide tables with high-cardinality columns. The [`Synthesizer`](super::Synthesizer)
+//! builds an [`AliasSampler`] per distribution once, and each draw is then one uniform
+//! index plus one coin flip.
+
+use rand::Rng;
+use crate::math::{Distribution, Histogram};
+
+/// Walker alias table over `K` weights: slot `i` is kept with `probability[i]` and
+/// otherwise hands over to `alias[i]`.
+#[derive(Debug, Clone)]
+pub struct AliasTable {
+    probability: Vec<f64>,
+    alias: Vec<usize>,
+}
+
+impl AliasTable {
+    /// `None` when there is nothing to draw: no weights, or all of them zero.
+    pub fn new(weights: &[u64]) -> Option<Self> {
+        let total: u64 = weights.iter().sum();
+        if total == 0 {
+            return None;
+        }
+
+        let k = weights.len();
+        let mut probability: Vec<f64> = weights
+            .iter()
+            .map(|&w| w as f64 * k as f64 / total as f64)
+            .collect();
+        let mut alias: Vec<usize> = (0..k).collect();
+
+        let (mut small, mut large): (Vec<usize>, Vec<usize>) = (0..k).partition(|&i| probability[i] < 1.0);
+        while let (Some(&s), Some(&l)) = (small.last(), large.last()) {
+            small.pop();
+            alias[s] = l;
+            probability[l] -= 1.0 - probability[s];
+            if probability[l] < 1.0 {
+                large.pop();
+                small.push(l);
+            }
+        }
+        // Whatever is left is 1 up to rounding
+        for i in small.into_iter().chain(large) {
+            probability[i] = 1.0;
+        }
+
+        Some(Self { probability, alias })
+    }
+
+    /// Index of a weight, drawn in proportion to it.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> usize {
+        let slot = rng.gen_range(0..self.probability.len());
+        if rng.gen_range(0.0..1.0) < self.probability[slot] {
+            slot
+        } else {
+            self.alias[slot]
+        }
+    }
+}
+
+/// Precomputed draw for one distribution's histogram.
+#[derive(Debug, Clone)]
+pub enum AliasSampler {
+    /// Tracked categorical values, in the histogram's order.
+    Categorical { values: Vec<String>, table: AliasTable },
+    /// Numeric bins or discrete values, by index.
+    Indexed(AliasTable),
+}
+
+impl AliasSampler {
+    /// `None` for histograms that aren't drawn by weight, or have no weight to draw.
+    pub fn for_distribution(dist: &Distribution) -> Option<Self> {
+        match &dist.histogram {
+            Histogram::Categorical { frequencies, .. } => {
+                let weights: Vec<u64> = frequencies.values().copied().collect();
+                Some(AliasSampler::Categorical {
+                    values: frequencies.keys().cloned().collect(),
+                    table: AliasTable::new(&weights)?,
+                })
+            }
+            Histogram::Numeric { frequencies, .. } | Histogram::Discrete { frequencies, .. } => {
+                AliasTable::new(frequencies).map(AliasSampler::Indexed)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_alias_table_follows_weights() {
+        let weights = [5, 0, 1, 30, 4];
+        let table = AliasTable::new(&weights).unwrap();
+        let mut rng = StdRng::seed_from_u64(3);
+
+        let draws = 200_000;
+        let mut counts = [0usize; 5];
+        for _ in 0..draws {
+            counts[table.sample(&mut rng)] += 1;
+        }
+
+        assert_eq!(counts[1], 0);
+        for (count, weight) in counts.iter().zip(weights) {
+            let expected = weight as f64 / 40.0;
+            assert!((*count as f64 / draws as f64 - expected).abs() < 0.005, "{:?}", counts);
+        }
+    }
+
+    #[test]
+    fn test_alias_table_needs_weight() {
+        assert!(AliasTable::new(&[]).is_none());
+        assert!(AliasTable::new(&[0, 0]).is_none());
+
+        let single = AliasTable::new(&[7]).unwrap();
+        assert_eq!(single.sample(&mut StdRng::seed_from_u64(0)), 0);
+    }
+}
@@ -0,0 +1,199 @@
+//! Injected duplicate rows, for testing deduplication against the twin.
+//!
+//! A duplicate copies every column of an earlier row of the same shard except the
+//! primary key, which stays unique so the rows still load. A near-duplicate also
+//! changes one value slightly: an integer by one, a float by up to 1%, a timestamp by
+//! up to a minute, or text by one typo.
+
+use std::borrow::Cow;
+use anyhow::{bail, Result};
+use chrono::DateTime;
+use rand::Rng;
+use serde::Deserialize;
+use crate::schema::DataType;
+use crate::synth::strategy::{write_value, Value};
+
+/// Shares of a table's rows generated as copies of earlier rows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DuplicateRates {
+    /// Rows identical to an earlier one apart from the primary key.
+    #[serde(default)]
+    pub exact: f64,
+
+    /// Rows with one value of an earlier row slightly changed.
+    #[serde(default)]
+    pub near: f64,
+}
+
+/// How a generated row copies an earlier one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Duplicate {
+    Exact,
+    Near,
+}
+
+impl DuplicateRates {
+    pub fn is_off(&self) -> bool {
+        self.exact == 0.0 && self.near == 0.0
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if !(0.0..=1.0).contains(&self.exact) || !(0.0..=1.0).contains(&self.near) || self.exact + self.near > 1.0 {
+            bail!("Duplicate rates must be between 0 and 1 and add up to at most 1, got {} and {}", self.exact, self.near);
+        }
+        Ok(())
+    }
+
+    /// Whether the next row copies an earlier one, and how.
+    pub fn draw<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<Duplicate> {
+        let u: f64 = rng.r#gen();
+        if u < self.exact {
+            Some(Duplicate::Exact)
+        } else if u < self.exact + self.near {
+            Some(Duplicate::Near)
+        } else {
+            None
+        }
+    }
+}
+
+/// Largest relative change of a float.
+const FLOAT_JITTER: f64 = 0.01;
+
+/// Largest shift of a timestamp, in seconds.
+const TIMESTAMP_JITTER: f64 = 60.0;
+
+/// A slightly changed copy of `field`, a non-NULL COPY text value, or `None` when
+/// values of `data_type` aren't changed. Text holding escapes is left alone, since a
+/// typo could split one.
+pub fn perturb<R: Rng + ?Sized>(field: &str, data_type: &DataType, rng: &mut R) -> Option<Value<'static>> {
+    if field == "\\N" {
+        return None;
+    }
+    match data_type {
+        DataType::Integer => {
+            let value: i64 = field.parse().ok()?;
+            let step = if rng.gen_bool(0.5) { 1 } else { -1 };
+            Some(Value::Number(value.saturating_add(step) as f64))
+        }
+        DataType::Float => {
+            let value: f64 = field.parse().ok()?;
+            let decimals = field.split_once('.').map_or(0, |(_, fraction)| fraction.len()) as i32;
+            let scale = 10f64.powi(decimals);
+            let jittered = value * (1.0 + rng.gen_range(-FLOAT_JITTER..=FLOAT_JITTER));
+            // Keep the original precision, but always change the value
+            let rounded = (jittered * scale).round() / scale;
+            let step = if rng.gen_bool(0.5) { 1.0 } else { -1.0 } / scale;
+            Some(Value::Number(if rounded == value { value + step } else { rounded }))
+        }
+        DataType::Timestamp => {
+            let ts = DateTime::parse_from_str(field, "%Y-%m-%d %H:%M:%S%.f%:z").ok()?;
+            let epoch = ts.timestamp_micros() as f64 / 1e6;
+            let shift = rng.gen_range(1.0..=TIMESTAMP_JITTER).round();
+            Some(Value::Number(epoch + if rng.gen_bool(0.5) { shift } else { -shift }))
+        }
+        DataType::Text if !field.contains('\\') => typo(field, rng).map(|text| Value::Text(Cow::Owned(text))),
+        _ => None,
+    }
+}
+
+/// Changes one of `fields`, a row split into COPY text values, trying the
+/// `candidates` (field index and type) in random order. Returns whether a value
+/// changed; a row with nothing to change stays an exact copy.
+pub fn perturb_row<R: Rng + ?Sized>(fields: &mut [String], candidates: &[(usize, &DataType)], rng: &mut R) -> bool {
+    if candidates.is_empty() {
+        return false;
+    }
+    let offset = rng.gen_range(0..candidates.len());
+    for &(index, data_type) in candidates.iter().cycle().skip(offset).take(candidates.len()) {
+        if let Some(value) = perturb(&fields[index], data_type, rng) {
+            let mut out = Vec::new();
+            write_value(&mut out, &value, data_type);
+            fields[index] = String::from_utf8_lossy(&out).into_owned();
+            return true;
+        }
+    }
+    false
+}
+
+/// Drops, repeats or swaps a character, or flips the case of a letter.
+fn typo<R: Rng + ?Sized>(text: &str, rng: &mut R) -> Option<String> {
+    let mut chars: Vec<char> = text.chars().collect();
+    if chars.is_empty() {
+        return None;
+    }
+    let at = rng.gen_range(0..chars.len());
+    match rng.gen_range(0..4) {
+        0 if chars.len() > 1 => {
+            chars.remove(at);
+        }
+        1 if at + 1 < chars.len() && chars[at] != chars[at + 1] => chars.swap(at, at + 1),
+        2 if chars[at].is_alphabetic() && chars[at].is_lowercase() => {
+            chars.splice(at..=at, chars[at].to_uppercase().collect::<Vec<_>>());
+        }
+        2 if chars[at].is_alphabetic() && chars[at].is_uppercase() => {
+            chars.splice(at..=at, chars[at].to_lowercase().collect::<Vec<_>>());
+        }
+        _ => chars.insert(at, chars[at]),
+    }
+    Some(chars.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn written(value: &Value, data_type: &DataType) -> String {
+        let mut out = Vec::new();
+        write_value(&mut out, value, data_type);
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_perturb_changes_values_slightly() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let Some(Value::Number(n)) = perturb("41", &DataType::Integer, &mut rng) else { panic!() };
+            assert!(n == 40.0 || n == 42.0);
+
+            let Some(Value::Number(x)) = perturb("19.99", &DataType::Float, &mut rng) else { panic!() };
+            assert!(x != 19.99 && (x - 19.99).abs() <= 0.21, "{}", x);
+            assert!(written(&Value::Number(x), &DataType::Float).split('.').nth(1).is_none_or(|f| f.len() <= 2));
+
+            let ts = perturb("2024-03-01 12:00:00+00:00", &DataType::Timestamp, &mut rng).unwrap();
+            let ts = written(&ts, &DataType::Timestamp);
+            assert!(ts.starts_with("2024-03-01 1") && ts != "2024-03-01 12:00:00+00:00", "{}", ts);
+
+            let Some(Value::Text(text)) = perturb("Alice", &DataType::Text, &mut rng) else { panic!() };
+            assert_ne!(text, "Alice");
+            assert!(text.len().abs_diff(5) <= 1);
+        }
+
+        assert!(perturb("\\N", &DataType::Integer, &mut rng).is_none());
+        assert!(perturb("line\\nbreak", &DataType::Text, &mut rng).is_none());
+        assert!(perturb("t", &DataType::Boolean, &mut rng).is_none());
+
+        let mut row = vec!["7".to_string(), "t".to_string(), "\\N".to_string(), "Bob".to_string()];
+        let candidates = [(1, &DataType::Boolean), (2, &DataType::Float), (3, &DataType::Text)];
+        assert!(perturb_row(&mut row, &candidates, &mut rng));
+        assert_eq!(&row[..3], ["7", "t", "\\N"]);
+        assert_ne!(row[3], "Bob");
+        assert!(!perturb_row(&mut row, &candidates[..2], &mut rng));
+    }
+
+    #[test]
+    fn test_rates_validate_and_draw() {
+        assert!(DuplicateRates { exact: 0.6, near: 0.6 }.validate().is_err());
+        assert!(DuplicateRates { exact: -0.1, near: 0.0 }.validate().is_err());
+        assert!(DuplicateRates::default().is_off());
+
+        let mut rng = rand::thread_rng();
+        let rates = DuplicateRates { exact: 0.2, near: 0.3 };
+        let draws: Vec<_> = (0..10_000).map(|_| rates.draw(&mut rng)).collect();
+        let exact = draws.iter().filter(|d| **d == Some(Duplicate::Exact)).count();
+        let near = draws.iter().filter(|d| **d == Some(Duplicate::Near)).count();
+        assert!((1700..2300).contains(&exact), "{}", exact);
+        assert!((2600..3400).contains(&near), "{}", near);
+    }
+}
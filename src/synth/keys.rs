@@ -0,0 +1,291 @@
+//! Typed primary key values kept in the [`KeyStore`](super::KeyStore).
+//!
+//! Keys keep the type they were generated with, so a foreign key is written in the
+//! same form as the primary key it points at, and mismatched column types are caught
+//! before any rows are generated.
+//!
+//! A table's keys are held in a [`KeyPool`]: gapless integer keys as a range, anything
+//! else as a list that can be cut down to a uniform sample to bound memory.
+
+use std::borrow::Cow;
+use std::fmt;
+use rand::Rng;
+use rand::seq::index;
+use uuid::Uuid;
+use crate::schema::DataType;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum KeyValue {
+    Int(i64),
+    Uuid(Uuid),
+    Text(String),
+}
+
+/// The kind of [`KeyValue`] generated for a primary key column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyKind {
+    Int,
+    Uuid,
+    Text,
+}
+
+impl KeyKind {
+    /// Integer-like and unrecognised key types are numbered.
+    pub fn for_column(data_type: &DataType) -> Self {
+        match data_type {
+            DataType::Uuid => KeyKind::Uuid,
+            DataType::Text => KeyKind::Text,
+            _ => KeyKind::Int,
+        }
+    }
+
+    /// Whether a foreign key column of type `data_type` can hold keys of this kind.
+    pub fn fits(self, data_type: &DataType) -> bool {
+        match self {
+            KeyKind::Int => matches!(data_type, DataType::Integer | DataType::Float | DataType::Text),
+            KeyKind::Uuid => matches!(data_type, DataType::Uuid | DataType::Text),
+            KeyKind::Text => matches!(data_type, DataType::Text),
+        }
+    }
+}
+
+impl KeyValue {
+    pub fn kind(&self) -> KeyKind {
+        match self {
+            KeyValue::Int(_) => KeyKind::Int,
+            KeyValue::Uuid(_) => KeyKind::Uuid,
+            KeyValue::Text(_) => KeyKind::Text,
+        }
+    }
+
+    /// The value as a SQL literal, for INSERT-style output.
+    pub fn to_sql_literal(&self) -> String {
+        match self {
+            KeyValue::Int(value) => value.to_string(),
+            KeyValue::Uuid(value) => format!("'{}'", value),
+            KeyValue::Text(value) => format!("'{}'", value.replace('\'', "''")),
+        }
+    }
+
+    /// Appends the COPY text form to `out`.
+    pub fn write_copy(&self, out: &mut Vec<u8>) {
+        match self {
+            KeyValue::Int(value) => out.extend_from_slice(itoa::Buffer::new().format(*value).as_bytes()),
+            KeyValue::Uuid(value) => {
+                out.extend_from_slice(value.hyphenated().encode_lower(&mut Uuid::encode_buffer()).as_bytes())
+            }
+            KeyValue::Text(_) => out.extend_from_slice(self.to_string().as_bytes()),
+        }
+    }
+}
+
+/// COPY text format.
+impl fmt::Display for KeyValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyValue::Int(value) => write!(f, "{}", value),
+            KeyValue::Uuid(value) => write!(f, "{}", value.hyphenated()),
+            KeyValue::Text(value) => {
+                for c in value.chars() {
+                    match c {
+                        '\\' => f.write_str("\\\\")?,
+                        '\t' => f.write_str("\\t")?,
+                        '\n' => f.write_str("\\n")?,
+                        '\r' => f.write_str("\\r")?,
+                        c => write!(f, "{}", c)?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Primary keys of one generated table, as drawn from by child foreign keys.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyPool {
+    /// Consecutive integers from `start`, kept as bounds whatever the row count.
+    Range { start: i64, len: usize },
+    /// Every key, or a uniform sample of them after [`KeyPool::sample`].
+    Listed(Vec<KeyValue>),
+}
+
+impl KeyPool {
+    /// Stores consecutive integer keys as a range, anything else as a list.
+    pub fn new(keys: Vec<KeyValue>) -> Self {
+        if let Some(&KeyValue::Int(start)) = keys.first()
+            && keys.iter().zip(start..).all(|(key, expected)| *key == KeyValue::Int(expected))
+        {
+            return KeyPool::Range { start, len: keys.len() };
+        }
+        KeyPool::Listed(keys)
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            KeyPool::Range { len, .. } => *len,
+            KeyPool::Listed(keys) => keys.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<Cow<'_, KeyValue>> {
+        match self {
+            KeyPool::Range { start, len } => {
+                (index < *len).then(|| Cow::Owned(KeyValue::Int(start + index as i64)))
+            }
+            KeyPool::Listed(keys) => keys.get(index).map(Cow::Borrowed),
+        }
+    }
+
+    pub fn choose<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<Cow<'_, KeyValue>> {
+        if self.is_empty() {
+            return None;
+        }
+        self.get(rng.gen_range(0..self.len()))
+    }
+
+    /// Like [`KeyPool::choose`], but a range also draws from the `existing` integer
+    /// keys just below it, which an appended-to dataset already holds. Lists ignore
+    /// `existing`.
+    pub fn choose_with_existing<R: Rng + ?Sized>(&self, existing: i64, rng: &mut R) -> Option<Cow<'_, KeyValue>> {
+        match self {
+            KeyPool::Range { start, len } if existing > 0 => {
+                Some(Cow::Owned(KeyValue::Int(rng.gen_range(start.saturating_sub(existing)..start + *len as i64))))
+            }
+            _ => self.choose(rng),
+        }
+    }
+
+    /// A key of the pool's kind that the pool doesn't hold, for deliberately broken
+    /// foreign keys: integers past the largest key (spread over as many values as the
+    /// pool has keys), random UUIDs, or text outside the numbered keys. `None` for an
+    /// empty list, whose kind is unknown.
+    pub fn orphan<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<KeyValue> {
+        let past = |last: i64, len: usize, rng: &mut R| last.saturating_add(1 + rng.gen_range(0..len.max(1)) as i64);
+        match self {
+            KeyPool::Range { start, len } => Some(KeyValue::Int(past(start + *len as i64 - 1, *len, rng))),
+            KeyPool::Listed(keys) => match keys.first()?.kind() {
+                KeyKind::Int => {
+                    let last = keys.iter().filter_map(|key| match key {
+                        KeyValue::Int(value) => Some(*value),
+                        _ => None,
+                    });
+                    Some(KeyValue::Int(past(last.max()?, keys.len(), rng)))
+                }
+                KeyKind::Uuid => loop {
+                    let key = KeyValue::Uuid(uuid::Builder::from_random_bytes(rng.r#gen()).into_uuid());
+                    if !keys.contains(&key) {
+                        break Some(key);
+                    }
+                },
+                KeyKind::Text => Some(KeyValue::Text(format!("orphan-{:08x}", rng.r#gen::<u32>()))),
+            },
+        }
+    }
+
+    /// Cuts a list longer than `cap` down to a uniform sample of `cap` keys, kept in
+    /// their original order. Returns the kept positions so data aligned with the keys
+    /// can follow, or `None` when nothing was dropped. Ranges are already compact and
+    /// are never sampled.
+    pub fn sample<R: Rng + ?Sized>(&mut self, cap: usize, rng: &mut R) -> Option<Vec<usize>> {
+        let KeyPool::Listed(keys) = self else {
+            return None;
+        };
+        if keys.len() <= cap {
+            return None;
+        }
+
+        let mut kept = index::sample(rng, keys.len(), cap).into_vec();
+        kept.sort_unstable();
+        *keys = kept.iter().map(|&i| std::mem::replace(&mut keys[i], KeyValue::Int(0))).collect();
+        Some(kept)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formats_per_dialect() {
+        let uuid = Uuid::from_u128(0x0123_4567_89ab_cdef_0123_4567_89ab_cdef);
+        assert_eq!(KeyValue::Uuid(uuid).to_string(), "01234567-89ab-cdef-0123-456789abcdef");
+        assert_eq!(KeyValue::Uuid(uuid).to_sql_literal(), "'01234567-89ab-cdef-0123-456789abcdef'");
+
+        let text = KeyValue::Text("o'neil\tx".to_string());
+        assert_eq!(text.to_string(), "o'neil\\tx");
+        assert_eq!(text.to_sql_literal(), "'o''neil\tx'");
+
+        assert_eq!(KeyValue::Int(42).to_sql_literal(), "42");
+
+        let mut out = Vec::new();
+        for key in [KeyValue::Int(-7), KeyValue::Uuid(uuid), text] {
+            key.write_copy(&mut out);
+            assert_eq!(out, key.to_string().into_bytes());
+            out.clear();
+        }
+    }
+
+    #[test]
+    fn test_key_pool_compacts_and_samples() {
+        let range = KeyPool::new((5..1005).map(KeyValue::Int).collect());
+        assert_eq!(range, KeyPool::Range { start: 5, len: 1000 });
+        assert_eq!(range.get(999).as_deref(), Some(&KeyValue::Int(1004)));
+        assert!(range.get(1000).is_none());
+
+        let mut listed = KeyPool::new((0..100).map(|i| KeyValue::Text(format!("k{}", i))).collect());
+        let mut rng = rand::thread_rng();
+        assert!(listed.sample(200, &mut rng).is_none());
+
+        let kept = listed.sample(10, &mut rng).unwrap();
+        assert_eq!(listed.len(), 10);
+        assert!(kept.windows(2).all(|w| w[0] < w[1]));
+        for (position, &index) in kept.iter().enumerate() {
+            assert_eq!(listed.get(position).as_deref(), Some(&KeyValue::Text(format!("k{}", index))));
+        }
+    }
+
+    #[test]
+    fn test_existing_keys_extend_ranges() {
+        let mut rng = rand::thread_rng();
+        let appended = KeyPool::Range { start: 101, len: 50 };
+        let draws: Vec<Cow<KeyValue>> = (0..2000).map(|_| appended.choose_with_existing(100, &mut rng).unwrap()).collect();
+        assert!(draws.iter().all(|key| matches!(**key, KeyValue::Int(1..=150))));
+        assert!(draws.iter().any(|key| matches!(**key, KeyValue::Int(1..=100))));
+
+        let listed = KeyPool::new(vec![KeyValue::Text("a".to_string())]);
+        assert_eq!(listed.choose_with_existing(100, &mut rng).as_deref(), Some(&KeyValue::Text("a".to_string())));
+    }
+
+    #[test]
+    fn test_orphans_miss_the_pool() {
+        let mut rng = rand::thread_rng();
+        let range = KeyPool::Range { start: 1, len: 100 };
+        let listed = KeyPool::new([3, 9, 4].map(KeyValue::Int).to_vec());
+        let uuids = KeyPool::new((0..5).map(|i| KeyValue::Uuid(Uuid::from_u128(i))).collect());
+        let text = KeyPool::new((1..=5).map(|i| KeyValue::Text(i.to_string())).collect());
+
+        for _ in 0..100 {
+            assert!(matches!(range.orphan(&mut rng), Some(KeyValue::Int(101..=200))));
+            assert!(matches!(listed.orphan(&mut rng), Some(KeyValue::Int(10..=12))));
+            for pool in [&uuids, &text] {
+                let orphan = pool.orphan(&mut rng).unwrap();
+                assert_eq!(orphan.kind(), pool.get(0).unwrap().kind());
+                assert!((0..pool.len()).all(|i| *pool.get(i).unwrap() != orphan));
+            }
+        }
+        assert!(KeyPool::Listed(Vec::new()).orphan(&mut rng).is_none());
+    }
+
+    #[test]
+    fn test_key_kind_compatibility() {
+        assert!(KeyKind::Int.fits(&DataType::Integer));
+        assert!(KeyKind::Uuid.fits(&DataType::Text));
+        assert!(!KeyKind::Uuid.fits(&DataType::Integer));
+        assert!(!KeyKind::Int.fits(&DataType::Uuid));
+    }
+}
@@ -1,26 +1,264 @@
+mod alias;
+mod duplicates;
+mod faker;
+mod generators;
+mod keys;
+mod noise;
+mod plan;
+mod progress;
+mod simulate;
 mod strategy;
 
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
 use crate::genome::DatabaseGenome;
 use anyhow::{bail, Context, Result};
 use rand::rngs::StdRng;
-use rand::{thread_rng, SeedableRng};
+use rand::{Rng, SeedableRng};
 use tracing::{debug, info, warn};
-use crate::copula::GaussianCopula;
-use crate::order::calculate_execution_order;
-use crate::schema::{ForeignKey, Table};
+use crate::anonymize::{self, AnonymizeStage, FormatPreserving};
+use crate::conditional::ConditionalDistribution;
+use crate::copula::TableCopula;
+use crate::order::{calculate_execution_order, execution_levels};
+use crate::math::Distribution;
+use crate::schema::{Column, DataType, ForeignKey, Table};
+use crate::semantic::SemanticType;
+use crate::sequence::SequenceModel;
+use crate::synth::alias::AliasSampler;
+use crate::synth::duplicates::Duplicate;
+use crate::synth::noise::ColumnNoise;
+use crate::synth::simulate::{EventClock, Window};
+use crate::subset::SubsetLayout;
+use crate::tenant::TenantLayout;
 use crate::synth::strategy::synthesize_primary_key;
 
-pub type KeyStore = HashMap<String, Vec<PrimaryKeyValue>>;
+pub use duplicates::DuplicateRates;
+pub use generators::{GeneratorRegistry, ValueGenerator};
+pub use keys::{KeyKind, KeyPool, KeyValue};
+pub use plan::{ColumnSource, GenerationPlan, PlannedColumn, PlannedTable};
+#[cfg(feature = "progress")]
+pub use progress::ProgressBars;
+pub use progress::{GenerationProgress, PROGRESS_INTERVAL};
+pub use strategy::{write_value, Value};
+
+pub type KeyStore = HashMap<String, KeyPool>;
+
+/// Rows per independently seeded shard of a table.
+pub const DEFAULT_ROWS_PER_SHARD: usize = 100_000;
+
+/// Generated values of parent attributes used for conditioning, keyed `table.column` and
+/// aligned with the table's entry in the [`KeyStore`].
+pub type AttributeStore = HashMap<String, Vec<String>>;
+
+/// Output of one table: COPY rows, primary keys, and the attribute columns children
+/// condition on.
+struct GeneratedTable {
+    copy_data: String,
+    primary_keys: Vec<KeyValue>,
+    attributes: AttributeStore,
+}
+
+/// What the row loop needs about one table, resolved once and shared by its shards.
+struct TablePlan<'a> {
+    table: &'a Table,
+    /// Output columns, in COPY order.
+    columns: Vec<&'a Column>,
+    /// Per output column: its distribution, its alias table, and its position among the
+    /// copula's columns.
+    distributions: Vec<Option<&'a Distribution>>,
+    samplers: Vec<Option<&'a AliasSampler>>,
+    copula_indices: Vec<Option<usize>>,
+    fk_map: HashMap<&'a str, &'a ForeignKey>,
+    semantic_types: HashMap<&'a str, SemanticType>,
+    generators: HashMap<&'a str, &'a dyn ValueGenerator>,
+    /// Generation-stage format-preserving transforms.
+    anonymized: HashMap<&'a str, &'a FormatPreserving>,
+    conditionals: HashMap<&'a str, (&'a ConditionalDistribution, usize)>,
+    /// Conditioned foreign keys with their parent's row count, sorted.
+    conditioned_fks: Vec<(&'a str, usize)>,
+    attribute_columns: Vec<&'a str>,
+    copula: Option<&'a TableCopula>,
+    /// Primary key columns numbered by the counter, per row.
+    counted_keys: usize,
+    sequences: Vec<(&'a str, &'a Distribution, &'a SequenceModel)>,
+    /// Noise per column, all `None` unless [`SynthesisConfig::noise`] is set.
+    noise: Vec<Option<ColumnNoise<'a>>>,
+    /// Tenant column written from the tenant layout, when it references no parent.
+    tenant_column: Option<usize>,
+    /// Table seed every row's own streams derive from, under
+    /// [`SynthesisConfig::stable_entities`].
+    entity_seed: Option<u64>,
+    /// Counted keys already present, which generated keys continue after.
+    key_offset: i64,
+    /// Event column and its clock, under [`SynthesisConfig::simulate_days`].
+    clock: Option<(usize, &'a EventClock)>,
+}
+
+impl TablePlan<'_> {
+    /// Last value of every sequence column before each shard, found by replaying the
+    /// sequence streams of the shards before it. Gap draws are cheap next to full rows.
+    fn sequence_starts(&self, shards: &[Range<usize>], base_seed: u64) -> Vec<Vec<Option<f64>>> {
+        let mut previous: Vec<Option<f64>> = vec![None; self.sequences.len()];
+        let mut starts = Vec::with_capacity(shards.len());
+
+        for (index, rows) in shards.iter().enumerate() {
+            starts.push(previous.clone());
+            if index + 1 == shards.len() {
+                break;
+            }
+            let seed = shard_seed(base_seed, index);
+            for ((name, dist, sequence), last) in self.sequences.iter().zip(previous.iter_mut()) {
+                let mut stream = StdRng::seed_from_u64(table_seed(seed, name));
+                for _ in rows.clone() {
+                    strategy::advance_sequence(dist, sequence, last, &mut stream);
+                }
+            }
+        }
 
-pub type PrimaryKeyValue = String;
+        starts
+    }
+}
+
+/// One slice of a table's rows.
+struct Shard<'a> {
+    rows: Range<usize>,
+    seed: u64,
+    /// Value preceding the shard for each of the plan's sequence columns.
+    sequence_starts: &'a [Option<f64>],
+}
 
 #[derive(Debug, Clone)]
 pub struct SynthesisConfig {
     pub rows_per_table: usize,
     pub seed: Option<u64>,
     pub strict_fk_enforcement: bool,
+
+    /// Faker generators forced onto specific columns, keyed `table.column`.
+    pub semantic_columns: HashMap<String, SemanticType>,
+
+    /// Pick faker generators for text columns from their names (e.g. `email`, `address`).
+    pub infer_semantic_types: bool,
+
+    /// Named generators from `generators` forced onto specific text columns, keyed
+    /// `table.column`. They take precedence over faker generators.
+    pub column_generators: HashMap<String, String>,
+
+    /// Generators that `column_generators` can name; the built-ins by default.
+    pub generators: GeneratorRegistry,
+
+    /// Format-preserving anonymization keyed `table.column`. Generation-stage entries
+    /// transform every generated value; scan-stage entries were applied by the scan.
+    pub anonymized_columns: HashMap<String, FormatPreserving>,
+
+    /// Draw child columns from the genome's cross-table conditionals, using the
+    /// attribute of the parent row picked for the foreign key.
+    pub fk_conditioning: bool,
+
+    /// Leave columns with a database default (or identity) out of the output so the
+    /// target fills them. Primary keys referenced by foreign keys are always generated.
+    pub use_db_defaults: bool,
+
+    /// Worker threads: tables of one dependency level, or shards of one table, run
+    /// concurrently. Output does not depend on it.
+    pub jobs: usize,
+
+    /// Rows per shard. Each shard has its own random stream, so output under a seed
+    /// depends on this value.
+    pub rows_per_shard: usize,
+
+    /// Most primary keys kept per table for children to reference. Gapless integer
+    /// keys are stored as a range and never capped; other keys over the cap are cut
+    /// down to a uniform sample. `None` keeps every key.
+    pub max_parent_keys: Option<usize>,
+
+    /// Row counts for specific tables, overriding `rows_per_table`.
+    pub table_rows: HashMap<String, usize>,
+
+    /// Generate values beyond the clipping bounds of winsorized columns, at the rate
+    /// the scan clipped them. Off, generated values stay within the bounds.
+    pub emit_outliers: bool,
+
+    /// Split each table's correlation matrix into blocks of columns linked by
+    /// coefficients of at least this magnitude, with one copula per block (see
+    /// [`CovarianceMatrix::blocks`](crate::copula::CovarianceMatrix::blocks)). `None`
+    /// fits one copula over the whole matrix.
+    pub copula_block_threshold: Option<f64>,
+
+    /// Share of foreign key values, between 0 and 1, replaced by keys their parent
+    /// table never generated (see [`KeyPool::orphan`]), to test
+    /// how consumers cope with broken references. Needs `strict_fk_enforcement` off.
+    /// Under `max_parent_keys`, listed integer orphans are only past the kept keys.
+    pub orphan_rate: f64,
+
+    /// Shares of every table's rows generated as exact or near copies of an earlier
+    /// row of the same shard, with a fresh primary key, to test deduplication.
+    pub duplicates: DuplicateRates,
+
+    /// Duplicate rates for specific tables, overriding `duplicates`.
+    pub table_duplicates: HashMap<String, DuplicateRates>,
+
+    /// Privacy noise between 0 (values follow the genome exactly) and 1: numbers
+    /// jitter within their histogram bin, timestamps within a day, and rare categories
+    /// swap among each other (see [`noise`]). Sequence columns and generated text are
+    /// left alone.
+    pub noise: f64,
+
+    /// Tenants to generate from the genome's [`TenantProfile`](crate::tenant::TenantProfile). Each tenant table's
+    /// rows are split among them by the source's per-tenant skew, and foreign keys
+    /// between tenant tables stay within a tenant. A table the tenant column
+    /// references gets one row per tenant. Needs `max_parent_keys` unset.
+    pub tenants: Option<usize>,
+
+    /// Rows of root tables to generate a referential slice around. Tables below a
+    /// root are sized by the genome's fan-out and reference parents in proportion to
+    /// it (see [`SubsetLayout`]); the rest keep their row counts. Needs `tenants` and
+    /// `max_parent_keys` unset.
+    pub roots: HashMap<String, usize>,
+
+    /// Draw every row from streams seeded by the seed, table and row index instead of
+    /// one stream per shard, so row `i` keeps its values when the row count changes.
+    /// Only foreign keys, and columns conditioned on the parent they pick, follow
+    /// the parent tables. Needs `seed`.
+    pub stable_entities: bool,
+
+    /// Counted (integer and text) primary keys already present in every table, for
+    /// appending to an existing dataset: generated keys start after them, and foreign
+    /// keys to integer keys may also pick the keys from 1 to the offset. Overridden
+    /// per table by `table_key_offsets`.
+    pub key_offset: i64,
+
+    /// Per-table `key_offset`, e.g. each table's largest key on the target.
+    pub table_key_offsets: HashMap<String, i64>,
+
+    /// Spread the rows of event tables over this many days after the source's latest
+    /// event, their event times advancing with the row index and following the daily
+    /// and weekly profile (see [`simulate`]).
+    pub simulate_days: Option<u32>,
+
+    /// With `simulate_days`, size event tables by the source's events per day over the
+    /// window instead of their row counts. Needs `roots` unset.
+    pub events_per_day_from_genome: bool,
+}
+
+impl SynthesisConfig {
+    /// Rows to generate for `table`.
+    pub fn rows_for(&self, table: &str) -> usize {
+        self.table_rows.get(table).copied().unwrap_or(self.rows_per_table)
+    }
+
+    /// Keys already present in `table`.
+    pub fn key_offset_for(&self, table: &str) -> i64 {
+        self.table_key_offsets.get(table).copied().unwrap_or(self.key_offset)
+    }
+
+    /// Duplicate rates for `table`.
+    pub fn duplicates_for(&self, table: &str) -> DuplicateRates {
+        self.table_duplicates.get(table).copied().unwrap_or(self.duplicates)
+    }
 }
 
 impl Default for SynthesisConfig {
@@ -29,6 +267,30 @@ impl Default for SynthesisConfig {
             rows_per_table: 1000,
             seed: None,
             strict_fk_enforcement: true,
+            semantic_columns: HashMap::new(),
+            infer_semantic_types: true,
+            column_generators: HashMap::new(),
+            generators: GeneratorRegistry::default(),
+            anonymized_columns: HashMap::new(),
+            fk_conditioning: false,
+            use_db_defaults: false,
+            jobs: 1,
+            rows_per_shard: DEFAULT_ROWS_PER_SHARD,
+            max_parent_keys: None,
+            table_rows: HashMap::new(),
+            emit_outliers: false,
+            copula_block_threshold: None,
+            orphan_rate: 0.0,
+            duplicates: DuplicateRates::default(),
+            table_duplicates: HashMap::new(),
+            noise: 0.0,
+            tenants: None,
+            roots: HashMap::new(),
+            stable_entities: false,
+            key_offset: 0,
+            table_key_offsets: HashMap::new(),
+            simulate_days: None,
+            events_per_day_from_genome: false,
         }
     }
 }
@@ -36,61 +298,94 @@ impl Default for SynthesisConfig {
 pub struct Synthesizer {
     genome: Arc<DatabaseGenome>,
     execution_order: Vec<String>,
+    execution_levels: Vec<Vec<String>>,
     config: SynthesisConfig,
-    copulas: HashMap<String, Arc<GaussianCopula>>,
+    copulas: HashMap<String, Arc<TableCopula>>,
+    tenants: Option<TenantLayout>,
+    subset: Option<SubsetLayout>,
+    /// Event column and clock per event table, when simulating a time window.
+    clocks: HashMap<String, (String, EventClock)>,
+    /// Alias tables for the genome's distributions, keyed `table.column`.
+    samplers: HashMap<String, AliasSampler>,
+    progress: Option<Arc<dyn GenerationProgress>>,
 }
 
 impl Synthesizer {
 
-    pub fn new(genome: DatabaseGenome, config: SynthesisConfig) -> Result<Self> {
+    pub fn new(genome: DatabaseGenome, mut config: SynthesisConfig) -> Result<Self> {
         info!("Initializing Synthesizer");
 
         let execution_order = calculate_execution_order(&genome.tables)
             .context("Failed to calculate topological execution order")?;
 
+        validate_key_types(&genome.tables, config.strict_fk_enforcement)?;
+        validate_orphan_rate(&config)?;
+        validate_duplicates(&genome, &config)?;
+        if !(0.0..=1.0).contains(&config.noise) {
+            bail!("Noise must be between 0 and 1, got {}", config.noise);
+        }
+        if config.stable_entities && config.seed.is_none() {
+            bail!("Stable entities need a seed");
+        }
+        if config.key_offset < 0 || config.table_key_offsets.values().any(|&offset| offset < 0) {
+            bail!("Primary key offsets must not be negative");
+        }
+        validate_column_generators(&genome, &config)?;
+        anonymize::validate_columns(
+            &genome,
+            config
+                .anonymized_columns
+                .iter()
+                .filter(|(_, transform)| transform.stage == AnonymizeStage::Generate)
+                .map(|(key, _)| key),
+        )?;
+        let execution_levels = execution_levels(&genome.tables, &execution_order);
+
         info!(
             tables = execution_order.len(),
             "Synthesizer initialized with execution order: {:?}",
             execution_order
         );
 
-        let mut copulas = HashMap::new();
-
-        for (table_name, cov_matrix) in &genome.correlations {
-            match GaussianCopula::new(cov_matrix) {
-                Ok(copula ) => {
-                    debug!(
-                        table = %table_name,
-                        dimensions = copula.dimension(),
-                        "Initialized gausian copula for correlated sampling"
-                    );
-                    copulas.insert(table_name.clone(), Arc::new(copula));
-                }
-                Err(e) => {
-                    warn!(
-                        table = %table_name,
-                        error = %e,
-                        "Singular matrix or invalid correlation for table, falling back to independent sampling"
-                    );
-                }
-            }
-        }
-
-        if !copulas.is_empty() {
-            info!(
-                copulas = copulas.len(),
-                "Initialized {} gaussian copulas for multivariate synthesis",
-                copulas.len()
-            );
-        }
+        let window = match config.simulate_days {
+            Some(days) => Some(simulation_window(&genome, &mut config, days)?),
+            None => None,
+        };
+        let tenants = match config.tenants {
+            Some(count) => Some(tenant_layout(&genome, &mut config, count)?),
+            None => None,
+        };
+        let subset = if config.roots.is_empty() {
+            None
+        } else {
+            Some(subset_layout(&genome, &execution_order, &mut config)?)
+        };
+        let clocks = match window {
+            Some(window) => event_clocks(&genome, &config, &window),
+            None => HashMap::new(),
+        };
+        let copulas = build_copulas(&genome, config.copula_block_threshold);
+        let samplers = build_samplers(&genome);
         Ok(Self {
             genome: Arc::new(genome),
+            execution_levels,
             execution_order,
             config,
             copulas,
+            tenants,
+            subset,
+            clocks,
+            samplers,
+            progress: None,
         })
     }
 
+    /// Reports per-table progress to `progress` during [`Synthesizer::generate`].
+    pub fn with_progress(mut self, progress: Arc<dyn GenerationProgress>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
     pub fn execution_order(&self) -> &[String] {
         &self.execution_order
     }
@@ -103,25 +398,27 @@ impl Synthesizer {
         info!("Starting data generation for {} tables", self.execution_order.len());
 
         let mut key_store: KeyStore = HashMap::new();
+        let mut attribute_store: AttributeStore = HashMap::new();
         let mut table_data: HashMap<String, TableData> = HashMap::new();
 
-        for table_name in &self.execution_order {
-            let table = self.genome.get_table(table_name)
-                .ok_or_else(|| anyhow::anyhow!("Table '{}' not found in genome", table_name))?;
-
-            debug!(table = %table_name, "Generating data for table");
+        for (depth, level) in self.execution_levels.iter().enumerate() {
+            debug!(level = depth, tables = level.len(), "Generating dependency level");
 
-            let (copy_data, pk_values) = self.generate_table_data(table, &key_store)?;
+            for (table_name, generated) in self.generate_level(level, &key_store, &attribute_store)? {
+                // Cache primary keys for FK resolution
+                let mut attributes = generated.attributes;
+                if !generated.primary_keys.is_empty() {
+                    let keys = self.key_pool(&table_name, generated.primary_keys, &mut attributes);
+                    key_store.insert(table_name.clone(), keys);
+                }
+                attribute_store.extend(attributes);
 
-            // Cache primary keys for FK resolution
-            if !pk_values.is_empty() {
-                key_store.insert(table_name.clone(), pk_values);
+                let row_count = self.config.rows_for(&table_name);
+                table_data.insert(table_name, TableData {
+                    copy_format: generated.copy_data,
+                    row_count,
+                });
             }
-
-            table_data.insert(table_name.clone(), TableData {
-                copy_format: copy_data,
-                row_count: self.config.rows_per_table,
-            });
         }
 
         let total_rows: usize = table_data.values().map(|t| t.row_count).sum();
@@ -134,61 +431,342 @@ impl Synthesizer {
         Ok(GenerationResult { table_data })
     }
 
+    /// Compacts a table's keys for the [`KeyStore`], sampling them down to
+    /// `max_parent_keys` when set. Attribute values are sampled with their keys.
+    fn key_pool(&self, table_name: &str, keys: Vec<KeyValue>, attributes: &mut AttributeStore) -> KeyPool {
+        let mut pool = KeyPool::new(keys);
+        let Some(cap) = self.config.max_parent_keys else {
+            return pool;
+        };
+
+        let mut rng = match self.config.seed {
+            Some(seed) => StdRng::seed_from_u64(table_seed(table_seed(seed, table_name), "primary_keys")),
+            None => StdRng::from_entropy(),
+        };
+        let total = pool.len();
+        if let Some(kept) = pool.sample(cap, &mut rng) {
+            for values in attributes.values_mut() {
+                if values.len() == total {
+                    *values = kept.iter().map(|&i| std::mem::take(&mut values[i])).collect();
+                }
+            }
+            info!(
+                table = %table_name,
+                keys = total,
+                kept = cap,
+                "Primary keys over --max-parent-keys, children reference a uniform sample"
+            );
+        }
+        pool
+    }
+
+    /// Generates the tables of one dependency level on up to `jobs` threads. They only
+    /// read keys and attributes of earlier levels, so the stores are shared by reference
+    /// and extended once the whole level is done.
+    fn generate_level(
+        &self,
+        level: &[String],
+        key_store: &KeyStore,
+        attribute_store: &AttributeStore,
+    ) -> Result<Vec<(String, GeneratedTable)>> {
+        run_parallel(self.config.jobs, level.len(), |index| {
+            let name = &level[index];
+            Ok((name.clone(), self.generate_named_table(name, key_store, attribute_store)?))
+        })
+        .into_iter()
+        .collect()
+    }
+
+    fn generate_named_table(
+        &self,
+        table_name: &str,
+        key_store: &KeyStore,
+        attribute_store: &AttributeStore,
+    ) -> Result<GeneratedTable> {
+        let table = self.genome.get_table(table_name)
+            .ok_or_else(|| anyhow::anyhow!("Table '{}' not found in genome", table_name))?;
+
+        debug!(table = %table_name, "Generating data for table");
+        self.generate_table_data(table, key_store, attribute_store)
+    }
+
+    /// Generates `table` in shards of `rows_per_shard` rows, each drawing from its own
+    /// stream seeded with the table seed plus the shard index, and joins them in order.
     fn generate_table_data(
         &self,
         table: &Table,
         key_store: &KeyStore,
-    ) -> Result<(String, Vec<PrimaryKeyValue>)> {
+        attribute_store: &AttributeStore,
+    ) -> Result<GeneratedTable> {
         // Validate FK dependencies first
         self.validate_foreign_key_dependencies(table, key_store)?;
 
-        let mut rng: Box<dyn rand::RngCore> = if let Some(seed) = self.config.seed {
-            Box::new(StdRng::seed_from_u64(seed))
-        } else {
-            Box::new(thread_rng())
+        let base_seed = match self.config.seed {
+            Some(seed) => table_seed(seed, &table.name),
+            None => rand::random(),
         };
 
-        let mut primary_key_counter: i64 = 0;
-        let mut primary_key_values: Vec<PrimaryKeyValue> = Vec::new();
-
-        // Pre-allocate string buffer (estimate: 100 bytes per row)
-        let estimated_size = self.config.rows_per_table * 100;
-        let mut copy_data = String::with_capacity(estimated_size);
+        let conditionals = self.resolve_conditionals(table, key_store, attribute_store);
 
-        // Build FK lookup map for fast access
-        let fk_map: HashMap<&str, &ForeignKey> = table
-            .foreign_keys
-            .iter()
-            .map(|fk| (fk.source_col.as_str(), fk))
+        // Sorted so the per-row draws happen in a fixed order under a seed
+        let mut conditioned_fks: Vec<(&str, usize)> = conditionals
+            .values()
+            .map(|(conditional, parent_len)| (conditional.foreign_key.as_str(), *parent_len))
             .collect();
+        conditioned_fks.sort_unstable();
+        conditioned_fks.dedup();
 
-        let copula = self.copulas.get(&table.name);
-        if copula.is_some() {
-            debug!(
+        let copula = self.copulas.get(&table.name).map(Arc::as_ref);
+        if let Some(cop) = copula {
+            info!(
                 table = %table.name,
-                "Using Gaussian copula for correlated column generation"
+                columns = ?cop.columns(),
+                kind = %cop.kind(),
+                blocks = cop.block_count(),
+                "Using copula for correlated column generation"
             );
         }
 
-        // Generate rows
-        for _ in 0..self.config.rows_per_table {
+        let columns = self.output_columns(table);
+        let sequences: Vec<(&str, &Distribution, &SequenceModel)> = columns
+            .iter()
+            .filter(|c| !c.is_primary_key && !table.foreign_keys.iter().any(|fk| fk.source_col == c.name))
+            .filter_map(|c| {
+                let dist = self.genome.get_distribution(&table.name, &c.name)?;
+                Some((c.name.as_str(), dist, dist.sequence.as_ref()?))
+            })
+            .collect();
 
-            let correlated_quantities: Option<HashMap<String, f64>> = if let Some(cop) = copula {
-                let uniforms = cop.generate_correlated_uniforms(&mut thread_rng());
-                Some(cop.columns().iter().cloned().zip(uniforms).collect())
-            } else {
+        let distributions = columns
+            .iter()
+            .map(|c| self.genome.get_distribution(&table.name, &c.name))
+            .collect();
+        let samplers = columns
+            .iter()
+            .map(|c| self.samplers.get(&DatabaseGenome::make_key(&table.name, &c.name)))
+            .collect();
+        let copula_indices = columns
+            .iter()
+            .map(|c| copula.and_then(|cop| cop.columns().iter().position(|name| *name == c.name)))
+            .collect();
+        let tenant_column = self
+            .tenants
+            .as_ref()
+            .filter(|layout| layout.is_scoped(&table.name))
+            .and_then(|layout| {
+                columns
+                    .iter()
+                    .position(|c| c.name == layout.column() && !c.is_primary_key)
+                    .filter(|_| !table.foreign_keys.iter().any(|fk| fk.source_col == layout.column()))
+            });
+        let clock = self.clocks.get(&table.name).and_then(|(column, clock)| {
+            Some((columns.iter().position(|c| &c.name == column)?, clock))
+        });
+        let noise = columns
+            .iter()
+            .map(|c| {
+                let dist = self.genome.get_distribution(&table.name, &c.name)?;
+                ColumnNoise::for_column(c, dist).filter(|_| self.config.noise > 0.0 && dist.sequence.is_none())
+            })
+            .collect();
+
+        let plan = TablePlan {
+            table,
+            fk_map: table.foreign_keys.iter().map(|fk| (fk.source_col.as_str(), fk)).collect(),
+            semantic_types: self.resolve_semantic_types(table),
+            generators: self.resolve_generators(table),
+            anonymized: self.resolve_anonymized(table),
+            conditionals,
+            conditioned_fks,
+            attribute_columns: self.attribute_columns(&table.name),
+            copula,
+            counted_keys: columns
+                .iter()
+                .filter(|c| c.is_primary_key && KeyKind::for_column(&c.data_type) != KeyKind::Uuid)
+                .count(),
+            columns,
+            distributions,
+            samplers,
+            copula_indices,
+            sequences,
+            noise,
+            tenant_column,
+            entity_seed: self.config.stable_entities.then_some(base_seed),
+            key_offset: self.config.key_offset_for(&table.name),
+            clock,
+        };
+
+        if let Some(progress) = &self.progress {
+            progress.table_started(&table.name, self.config.rows_for(&table.name));
+        }
+
+        let shards = shard_ranges(self.config.rows_for(&table.name), self.config.rows_per_shard);
+        let sequence_starts = plan.sequence_starts(&shards, base_seed);
+        if shards.len() > 1 {
+            debug!(table = %table.name, shards = shards.len(), "Generating rows in shards");
+        }
+
+        let generated = run_parallel(self.config.jobs, shards.len(), |index| {
+            let shard = Shard {
+                rows: shards[index].clone(),
+                seed: shard_seed(base_seed, index),
+                sequence_starts: &sequence_starts[index],
+            };
+            self.generate_shard(&plan, &shard, key_store, attribute_store)
+        });
+
+        let mut merged = GeneratedTable {
+            copy_data: String::new(),
+            primary_keys: Vec::new(),
+            attributes: AttributeStore::new(),
+        };
+        for shard in generated {
+            let shard = shard?;
+            merged.copy_data.push_str(&shard.copy_data);
+            merged.primary_keys.extend(shard.primary_keys);
+            for (key, values) in shard.attributes {
+                merged.attributes.entry(key).or_default().extend(values);
+            }
+        }
+
+        if let Some(progress) = &self.progress {
+            progress.table_finished(&table.name);
+        }
+        Ok(merged)
+    }
+
+    fn generate_shard(
+        &self,
+        plan: &TablePlan,
+        shard: &Shard,
+        key_store: &KeyStore,
+        attribute_store: &AttributeStore,
+    ) -> Result<GeneratedTable> {
+        let table = plan.table;
+
+        // One generator drives every draw in the shard so a seed reproduces it exactly
+        let mut rng = StdRng::seed_from_u64(shard.seed);
+
+        // Integer keys continue the numbering of the shards before this one
+        let mut primary_key_counter = plan.key_offset + (shard.rows.start * plan.counted_keys) as i64;
+        let mut primary_key_values: Vec<KeyValue> = Vec::new();
+
+        // Pre-allocate the row buffer (estimate: 100 bytes per row)
+        let estimated_size = shard.rows.len() * 100;
+        let mut copy_data: Vec<u8> = Vec::with_capacity(estimated_size);
+
+        let mut attributes: AttributeStore = plan
+            .attribute_columns
+            .iter()
+            .map(|column| (DatabaseGenome::make_key(&table.name, column), Vec::new()))
+            .collect();
+        let attribute_keys: Vec<Option<String>> = plan
+            .columns
+            .iter()
+            .map(|c| {
+                let key = DatabaseGenome::make_key(&table.name, &c.name);
+                attributes.contains_key(&key).then_some(key)
+            })
+            .collect();
+
+        // Sequence columns draw from their own streams so shards can be stitched together;
+        // each entry holds the stream and the last value generated
+        let mut sequence_values: HashMap<&str, (StdRng, Option<f64>)> = plan
+            .sequences
+            .iter()
+            .zip(shard.sequence_starts)
+            .map(|(&(name, _, _), &previous)| (name, (StdRng::seed_from_u64(table_seed(shard.seed, name)), previous)))
+            .collect();
+
+        // Row offsets kept for duplicates to copy from, and the values near-duplicates may change
+        let duplicates = self.config.duplicates_for(&table.name);
+        let mut row_starts: Vec<usize> = Vec::new();
+        let perturbable: Vec<(usize, &DataType)> = plan
+            .columns
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| !c.is_primary_key && !plan.fk_map.contains_key(c.name.as_str()))
+            .map(|(index, c)| (index, &c.data_type))
+            .collect();
+
+        // Generate rows, writing each value straight into the buffer
+        for (done, _) in shard.rows.clone().enumerate() {
+            if let Some(progress) = &self.progress
+                && done > 0
+                && done.is_multiple_of(PROGRESS_INTERVAL)
+            {
+                progress.rows_generated(&table.name, PROGRESS_INTERVAL);
+            }
+
+            // Under stable entities the row's values and its parents draw from streams of
+            // their own, so the keys picked from a larger parent shift nothing else
+            let mut key_rng = None;
+            if let Some(seed) = plan.entity_seed {
+                let seed = entity_seed(seed, shard.rows.start + done);
+                rng = StdRng::seed_from_u64(seed);
+                key_rng = Some(StdRng::seed_from_u64(table_seed(seed, "keys")));
+            }
+
+            // Values of an earlier row this one copies, drawn only when enabled so seeded
+            // output without duplicates is unchanged
+            let copied = if duplicates.is_off() {
                 None
+            } else {
+                let duplicate = (!row_starts.is_empty()).then(|| duplicates.draw(&mut rng)).flatten();
+                let copied = duplicate.map(|duplicate| {
+                    let source = row_starts[rng.gen_range(0..row_starts.len())];
+                    let row = &copy_data[source..];
+                    let row = &row[..row.iter().position(|&b| b == b'\n').unwrap_or(row.len())];
+                    let mut fields: Vec<String> = String::from_utf8_lossy(row).split('\t').map(str::to_string).collect();
+                    if duplicate == Duplicate::Near {
+                        duplicates::perturb_row(&mut fields, &perturbable, &mut rng);
+                    }
+                    fields
+                });
+                row_starts.push(copy_data.len());
+                copied
             };
 
-            let mut row_values: Vec<String> = Vec::with_capacity(table.columns.len());
+            let uniforms = plan
+                .copula
+                .filter(|_| copied.is_none())
+                .map(|cop| cop.generate_correlated_uniforms(&mut rng));
+
+            // Parents of a tenant's rows are picked among the same tenant's rows
+            let tenant = self.tenants.as_ref().and_then(|layout| layout.tenant_of(&table.name, shard.rows.start + done));
+            let tenant_row = |parent: &str, rng: &mut StdRng| {
+                let rows = self.tenants.as_ref()?.rows(parent, tenant?)?;
+                (!rows.is_empty()).then(|| rng.gen_range(rows))
+            };
+            // Rows of a slice table reference the parent they were laid out under
+            let slice_row = |fk: &str| self.subset.as_ref()?.parent_of(&table.name, fk, shard.rows.start + done);
+
+            // Parent row picked for each conditioned foreign key
+            let mut parent_rows: HashMap<&str, usize> = HashMap::new();
+            for &(fk, parent_len) in plan.conditioned_fks.iter().filter(|_| copied.is_none()) {
+                let keys = key_rng.as_mut().unwrap_or(&mut rng);
+                let row = plan.fk_map.get(fk).and_then(|fk| tenant_row(&fk.target_table, keys));
+                parent_rows.insert(fk, row.or_else(|| slice_row(fk)).unwrap_or_else(|| keys.gen_range(0..parent_len)));
+            }
 
-            for column in &table.columns {
-                let value = if column.is_primary_key {
+            for (index, column) in plan.columns.iter().copied().enumerate() {
+                if index > 0 {
+                    copy_data.push(b'\t');
+                }
+                let start = copy_data.len();
+
+                if column.is_primary_key {
                     // Primary Key: Auto-increment or UUID
-                    let pk = synthesize_primary_key(&column.data_type, &mut primary_key_counter);
-                    primary_key_values.push(pk.clone());
-                    pk
-                } else if let Some(fk) = fk_map.get(column.name.as_str()) {
+                    let pk = synthesize_primary_key(&column.data_type, &mut primary_key_counter, &mut rng);
+                    pk.write_copy(&mut copy_data);
+                    primary_key_values.push(pk);
+                } else if let Some(fields) = &copied {
+                    copy_data.extend_from_slice(fields[index].as_bytes());
+                } else if plan.tenant_column == Some(index)
+                    && let Some(id) = tenant.and_then(|tenant| self.tenants.as_ref()?.id(tenant))
+                {
+                    id.write_copy(&mut copy_data);
+                } else if let Some(fk) = plan.fk_map.get(column.name.as_str()) {
                     // Foreign Key: Sample from parent KeyStore
                     let parent_keys = key_store.get(&fk.target_table)
                         .context(format!(
@@ -197,41 +775,299 @@ impl Synthesizer {
                             column.name
                         ))?;
 
-                    strategy::synthesize_foreign_key(parent_keys, &mut thread_rng())
-                        .context(format!(
-                            "Failed to generate FK '{}' from parent '{}'",
-                            column.name,
-                            fk.target_table
-                        ))?
+                    // Drawn only when enabled so seeded output without orphans is unchanged
+                    let keys = key_rng.as_mut().unwrap_or(&mut rng);
+                    let existing = self.config.key_offset_for(&fk.target_table);
+                    let orphaned = self.config.orphan_rate > 0.0 && keys.gen_bool(self.config.orphan_rate);
+                    let key = match parent_rows.get(column.name.as_str()) {
+                        _ if orphaned => Cow::Owned(parent_keys.orphan(keys)
+                            .context(format!("Parent '{}' has no keys to miss for FK '{}'", fk.target_table, column.name))?),
+                        Some(&row) => parent_keys.get(row)
+                            .context(format!("Parent row {} out of range for FK '{}'", row, column.name))?,
+                        None if let Some(row) = tenant_row(&fk.target_table, keys) => parent_keys.get(row)
+                            .context(format!("Tenant parent row {} out of range for FK '{}'", row, column.name))?,
+                        None if let Some(row) = slice_row(&column.name) => parent_keys.get(row)
+                            .context(format!("Slice parent row {} out of range for FK '{}'", row, column.name))?,
+                        None if existing > 0 => parent_keys.choose_with_existing(existing, keys)
+                            .context(format!("Parent '{}' has no keys for FK '{}'", fk.target_table, column.name))?,
+                        None => strategy::synthesize_foreign_key(parent_keys, keys)
+                            .context(format!(
+                                "Failed to generate FK '{}' from parent '{}'",
+                                column.name,
+                                fk.target_table
+                            ))?,
+                    };
+                    key.write_copy(&mut copy_data);
                 } else {
                     // Regular Column: Sample from Distribution
-                    let distribution = self.genome.get_distribution(&table.name, &column.name)
-                        .context(format!(
+                    let distribution = plan.distributions[index]
+                        .with_context(|| format!(
                             "Distribution not found for column '{}.{}'",
                             table.name,
                             column.name
                         ))?;
 
-                    let quantile = correlated_quantities.as_ref()
-                        .and_then(|q_map| q_map.get(&column.name).copied());
+                    let value = if let Some((_, clock)) = plan.clock.filter(|&(event_column, _)| event_column == index) {
+                        // Event times advance with the row instead of being drawn from the range
+                        let null_share = distribution.null_count as f64 / distribution.total_count.max(1) as f64;
+                        if null_share > 0.0 && rng.gen_bool(null_share.min(1.0)) {
+                            Value::Null
+                        } else {
+                            let row = shard.rows.start + done;
+                            let time = clock
+                                .timestamp(row, rng.r#gen())
+                                .context(format!("Row {} is past the event clock of '{}'", row, table.name))?;
+                            Value::Number(time)
+                        }
+                    } else if let Some(sequence) = &distribution.sequence
+                        && let Some((stream, previous)) = sequence_values.get_mut(column.name.as_str())
+                    {
+                        strategy::synthesize_sequence(distribution, sequence, previous, stream)
+                    } else {
+                        // Per-parent distributions have no alias table and scan their frequencies
+                        let (distribution, sampler) = match plan
+                            .conditionals
+                            .get(column.name.as_str())
+                            .and_then(|(conditional, _)| {
+                                let row = parent_rows.get(conditional.foreign_key.as_str())?;
+                                let parent_key = DatabaseGenome::make_key(&conditional.parent_table, &conditional.parent_column);
+                                let value = attribute_store.get(&parent_key)?.get(*row)?;
+                                conditional.for_parent_value(value)
+                            }) {
+                            Some(conditioned) => (conditioned, None),
+                            None => (distribution, plan.samplers[index]),
+                        };
+
+                        let quantile = plan.copula_indices[index]
+                            .zip(uniforms.as_ref())
+                            .map(|(i, uniforms)| uniforms[i]);
+
+                        if let Some(generator) = plan.generators.get(column.name.as_str()) {
+                            strategy::synthesize_custom(distribution, *generator, &mut rng)
+                        } else if let Some(kind) = plan.semantic_types.get(column.name.as_str()) {
+                            strategy::synthesize_semantic(distribution, *kind, &mut rng)
+                        } else if self.config.emit_outliers
+                            && let Some(outliers) = &distribution.outliers
+                            && let Some(outlier) = strategy::synthesize_outlier(distribution, outliers, &mut rng, quantile)
+                        {
+                            Value::Number(outlier)
+                        } else {
+                            let value = strategy::synthesize_value(distribution, sampler, &mut rng, quantile)
+                                .context(format!(
+                                    "Failed to synthesize value for column '{}.{}'",
+                                    table.name,
+                                    column.name
+                                ))?;
+                            match &plan.noise[index] {
+                                Some(noise) => noise.apply(value, self.config.noise, &mut rng),
+                                None => value,
+                            }
+                        }
+                    };
+                    let value = strategy::clamp_to_domain(value, distribution, column);
+                    let value = match column.data_type {
+                        DataType::Bytea => strategy::synthesize_bytes(value, &mut rng),
+                        DataType::Inet | DataType::Cidr | DataType::MacAddr => {
+                            strategy::synthesize_address(value, &column.data_type, &mut rng).context(format!(
+                                "Failed to synthesize address for column '{}.{}'",
+                                table.name,
+                                column.name
+                            ))?
+                        }
+                        DataType::Hstore => {
+                            strategy::synthesize_hstore(value, distribution, plan.samplers[index], &mut rng).context(format!(
+                                "Failed to synthesize hstore for column '{}.{}'",
+                                table.name,
+                                column.name
+                            ))?
+                        }
+                        DataType::Geometry | DataType::Geography => {
+                            strategy::synthesize_point(value, &mut rng).context(format!(
+                                "Failed to synthesize point for column '{}.{}'",
+                                table.name,
+                                column.name
+                            ))?
+                        }
+                        _ => value,
+                    };
+                    let value = match (plan.anonymized.get(column.name.as_str()), value) {
+                        (Some(transform), Value::Text(text)) => transform.transform(&text, &mut rng).into(),
+                        (_, value) => value,
+                    };
+                    strategy::write_value(&mut copy_data, &value, &column.data_type);
+                }
 
-                    strategy::synthesize_value(distribution, &mut thread_rng(), quantile)
-                        .context(format!(
-                            "Failed to synthesize value for column '{}.{}'",
-                            table.name,
-                            column.name
-                        ))?
-                };
+                if let Some(key) = &attribute_keys[index]
+                    && let Some(values) = attributes.get_mut(key)
+                {
+                    values.push(String::from_utf8_lossy(&copy_data[start..]).into_owned());
+                }
+            }
+
+            copy_data.push(b'\n');
+        }
+
+        if let Some(progress) = &self.progress
+            && !shard.rows.is_empty()
+        {
+            // Rows since the last full interval
+            progress.rows_generated(&table.name, (shard.rows.len() - 1) % PROGRESS_INTERVAL + 1);
+        }
+
+        Ok(GeneratedTable {
+            copy_data: String::from_utf8(copy_data).context("Generated COPY data is not valid UTF-8")?,
+            primary_keys: primary_key_values,
+            attributes,
+        })
+    }
+
+    /// Columns written for `table`, in COPY order.
+    pub fn output_columns<'a>(&self, table: &'a Table) -> Vec<&'a Column> {
+        if !self.config.use_db_defaults {
+            return table.columns.iter().collect();
+        }
+
+        let referenced = |column: &Column| {
+            self.genome.tables.iter().any(|t| {
+                t.foreign_keys
+                    .iter()
+                    .any(|fk| fk.target_table == table.name && fk.target_col == column.name)
+            })
+        };
+
+        let (kept, omitted): (Vec<&Column>, Vec<&Column>) = table
+            .columns
+            .iter()
+            .partition(|c| !c.has_default || (c.is_primary_key && referenced(c)));
+
+        if !omitted.is_empty() {
+            debug!(
+                table = %table.name,
+                columns = ?omitted.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(),
+                "Leaving columns to their database defaults"
+            );
+        }
+        kept
+    }
 
-                row_values.push(value);
+    /// Columns of `table` that some child is conditioned on.
+    fn attribute_columns(&self, table: &str) -> Vec<&str> {
+        if !self.config.fk_conditioning {
+            return Vec::new();
+        }
+        let mut columns: Vec<&str> = self
+            .genome
+            .conditionals
+            .values()
+            .filter(|c| c.parent_table == table)
+            .map(|c| c.parent_column.as_str())
+            .collect();
+        columns.sort_unstable();
+        columns.dedup();
+        columns
+    }
+
+    /// Conditionals usable for `table`'s columns, with the parent's row count. A parent
+    /// whose keys and attributes don't line up row for row (composite keys) is skipped.
+    fn resolve_conditionals<'a>(
+        &'a self,
+        table: &'a Table,
+        key_store: &KeyStore,
+        attribute_store: &AttributeStore,
+    ) -> HashMap<&'a str, (&'a ConditionalDistribution, usize)> {
+        let mut resolved = HashMap::new();
+        if !self.config.fk_conditioning {
+            return resolved;
+        }
+
+        for column in &table.columns {
+            let Some(conditional) = self.genome.conditionals.get(&DatabaseGenome::make_key(&table.name, &column.name)) else {
+                continue;
+            };
+            let parent_keys = key_store.get(&conditional.parent_table).map_or(0, KeyPool::len);
+            let parent_attributes = attribute_store
+                .get(&DatabaseGenome::make_key(&conditional.parent_table, &conditional.parent_column))
+                .map_or(0, Vec::len);
+
+            if parent_keys == 0 || parent_keys != parent_attributes {
+                warn!(
+                    table = %table.name,
+                    column = %column.name,
+                    parent = %conditional.parent_table,
+                    "Parent keys and attributes don't align, sampling column from its marginal"
+                );
+                continue;
             }
 
-            // Join columns with TAB and append newline
-            copy_data.push_str(&row_values.join("\t"));
-            copy_data.push('\n');
+            debug!(
+                table = %table.name,
+                column = %column.name,
+                parent_column = %conditional.parent_column,
+                "Conditioning column on parent attribute"
+            );
+            resolved.insert(column.name.as_str(), (conditional, parent_keys));
+        }
+
+        resolved
+    }
+
+    /// Columns of `table` that are filled by faker generators instead of their histogram.
+    fn resolve_semantic_types<'a>(&self, table: &'a Table) -> HashMap<&'a str, SemanticType> {
+        let mut resolved = HashMap::new();
+
+        for column in &table.columns {
+            if column.is_primary_key || table.foreign_keys.iter().any(|fk| fk.source_col == column.name) {
+                continue;
+            }
+            let Some(dist) = self.genome.get_distribution(&table.name, &column.name) else {
+                continue;
+            };
+            if let Some(kind) = strategy::resolve_semantic_type(
+                &table.name,
+                column,
+                dist,
+                &self.config.semantic_columns,
+                self.config.infer_semantic_types,
+            ) {
+                debug!(
+                    table = %table.name,
+                    column = %column.name,
+                    semantic_type = %kind,
+                    "Using faker generator for column"
+                );
+                resolved.insert(column.name.as_str(), kind);
+            }
         }
 
-        Ok((copy_data, primary_key_values))
+        resolved
+    }
+
+    /// Columns of `table` filled by a named generator from the config.
+    fn resolve_generators<'a>(&'a self, table: &'a Table) -> HashMap<&'a str, &'a dyn ValueGenerator> {
+        table
+            .columns
+            .iter()
+            .filter_map(|column| {
+                let name = self.config.column_generators.get(&DatabaseGenome::make_key(&table.name, &column.name))?;
+                debug!(table = %table.name, column = %column.name, generator = %name, "Using named generator for column");
+                Some((column.name.as_str(), self.config.generators.get(name)?))
+            })
+            .collect()
+    }
+
+    /// Columns of `table` anonymized as they are generated.
+    fn resolve_anonymized<'a>(&'a self, table: &'a Table) -> HashMap<&'a str, &'a FormatPreserving> {
+        table
+            .columns
+            .iter()
+            .filter_map(|column| {
+                let transform = self
+                    .config
+                    .anonymized_columns
+                    .get(&DatabaseGenome::make_key(&table.name, &column.name))?;
+                (transform.stage == AnonymizeStage::Generate).then_some((column.name.as_str(), transform))
+            })
+            .collect()
     }
 
     fn validate_foreign_key_dependencies(
@@ -276,6 +1112,345 @@ impl Synthesizer {
     }
 }
 
+/// Copulas for the tables with a correlation matrix: empirical where the genome holds
+/// pseudo-observations, otherwise Gaussian, split into blocks with `block_threshold`.
+/// Tables whose matrix can't be factored are sampled column by column, as are the
+/// columns of failed blocks.
+#[cfg(feature = "copula")]
+fn build_copulas(genome: &DatabaseGenome, block_threshold: Option<f64>) -> HashMap<String, Arc<TableCopula>> {
+    let mut copulas = HashMap::new();
+
+    for (table_name, cov_matrix) in &genome.correlations {
+        match TableCopula::new(cov_matrix, block_threshold) {
+            Ok(copula) if copula.block_count() == 0 => {
+                debug!(table = %table_name, "No correlated column blocks, sampling columns independently");
+            }
+            Ok(copula) => {
+                debug!(
+                    table = %table_name,
+                    dimensions = copula.dimension(),
+                    kind = %copula.kind(),
+                    blocks = copula.block_count(),
+                    "Initialized copula for correlated sampling"
+                );
+                copulas.insert(table_name.clone(), Arc::new(copula));
+            }
+            Err(e) => {
+                warn!(
+                    table = %table_name,
+                    error = %e,
+                    "Singular matrix or invalid correlation for table, falling back to independent sampling"
+                );
+            }
+        }
+    }
+
+    if !copulas.is_empty() {
+        info!(
+            copulas = copulas.len(),
+            "Initialized {} copulas for multivariate synthesis",
+            copulas.len()
+        );
+    }
+    copulas
+}
+
+#[cfg(not(feature = "copula"))]
+fn build_copulas(genome: &DatabaseGenome, _block_threshold: Option<f64>) -> HashMap<String, Arc<TableCopula>> {
+    if !genome.correlations.is_empty() {
+        warn!(
+            tables = genome.correlations.len(),
+            "Built without the copula feature, sampling correlated columns independently"
+        );
+    }
+    HashMap::new()
+}
+
+/// Alias tables for every distribution drawn by weight, so each categorical value, bin
+/// or discrete integer is picked in constant time.
+fn build_samplers(genome: &DatabaseGenome) -> HashMap<String, AliasSampler> {
+    let samplers: HashMap<String, AliasSampler> = genome
+        .distributions
+        .iter()
+        .filter_map(|(key, dist)| Some((key.clone(), AliasSampler::for_distribution(dist)?)))
+        .collect();
+    debug!(samplers = samplers.len(), "Precomputed alias tables for weighted sampling");
+    samplers
+}
+
+/// Checks that every foreign key column can hold the keys generated for its target.
+/// Mismatches fail under strict enforcement and are logged otherwise.
+fn validate_key_types(tables: &[Table], strict: bool) -> Result<()> {
+    for table in tables {
+        for fk in &table.foreign_keys {
+            let child = table.columns.iter().find(|c| c.name == fk.source_col);
+            let parent = tables
+                .iter()
+                .find(|t| t.name == fk.target_table)
+                .and_then(|t| t.columns.iter().find(|c| c.name == fk.target_col));
+            let (Some(child), Some(parent)) = (child, parent) else {
+                continue;
+            };
+
+            let kind = KeyKind::for_column(&parent.data_type);
+            if kind.fits(&child.data_type) {
+                continue;
+            }
+
+            if strict {
+                bail!(
+                    "Foreign key '{}.{}' ({}) cannot hold keys of '{}.{}' ({})",
+                    table.name,
+                    child.name,
+                    child.data_type,
+                    fk.target_table,
+                    parent.name,
+                    parent.data_type
+                );
+            }
+            warn!(
+                table = %table.name,
+                column = %child.name,
+                child_type = %child.data_type,
+                parent_type = %parent.data_type,
+                "Foreign key type differs from its target; generated values may not load"
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Orphaned foreign keys break the references strict enforcement guarantees.
+fn validate_orphan_rate(config: &SynthesisConfig) -> Result<()> {
+    if !(0.0..=1.0).contains(&config.orphan_rate) {
+        bail!("Orphan rate must be between 0 and 1, got {}", config.orphan_rate);
+    }
+    if config.orphan_rate > 0.0 && config.strict_fk_enforcement {
+        bail!("Orphaned foreign keys need strict_fk_enforcement turned off");
+    }
+    Ok(())
+}
+
+fn validate_duplicates(genome: &DatabaseGenome, config: &SynthesisConfig) -> Result<()> {
+    config.duplicates.validate()?;
+    for (table, rates) in &config.table_duplicates {
+        if genome.get_table(table).is_none() {
+            bail!("Duplicate rates given for unknown table '{}'", table);
+        }
+        rates.validate().context(format!("Invalid duplicate rates for table '{}'", table))?;
+    }
+    Ok(())
+}
+
+/// Checks that every `table.column` given a generator names a registered generator
+/// and a text column that isn't a key.
+fn validate_column_generators(genome: &DatabaseGenome, config: &SynthesisConfig) -> Result<()> {
+    let mut assignments: Vec<(&String, &String)> = config.column_generators.iter().collect();
+    assignments.sort();
+
+    for (key, name) in assignments {
+        if config.generators.get(name).is_none() {
+            bail!(
+                "Unknown generator '{}' for '{}' (available: {})",
+                name,
+                key,
+                config.generators.names().join(", ")
+            );
+        }
+        let (table_name, column_name) = key
+            .split_once('.')
+            .context(format!("Generator column '{}' must be table.column", key))?;
+        let table = genome
+            .get_table(table_name)
+            .context(format!("Generator column '{}': table not found in genome", key))?;
+        let column = table
+            .columns
+            .iter()
+            .find(|c| c.name == column_name)
+            .context(format!("Generator column '{}': column not found in genome", key))?;
+        if column.is_primary_key || table.foreign_keys.iter().any(|fk| fk.source_col == column.name) {
+            bail!("Generator column '{}' is a key column", key);
+        }
+        if column.data_type != DataType::Text {
+            bail!("Generator column '{}' is {}, generators only fill text columns", key, column.data_type);
+        }
+    }
+    Ok(())
+}
+
+/// Lays out `count` tenants over the genome's tenant tables. The table the tenant
+/// column references, if any, is given one row per tenant in `config`.
+fn tenant_layout(genome: &DatabaseGenome, config: &mut SynthesisConfig, count: usize) -> Result<TenantLayout> {
+    let profile = genome
+        .tenants
+        .as_ref()
+        .context("Tenants were requested, but the genome has no tenant profile (scan with a [tenant] config)")?;
+    if config.max_parent_keys.is_some() {
+        bail!("Tenants can't be combined with max_parent_keys, which loses the order of parent keys");
+    }
+
+    let scoped: Vec<&Table> = profile.tables.iter().filter_map(|name| genome.get_table(name)).collect();
+    let root = scoped
+        .iter()
+        .flat_map(|t| &t.foreign_keys)
+        .find(|fk| fk.source_col == profile.column)
+        .map(|fk| fk.target_table.as_str())
+        .filter(|root| genome.get_table(root).is_some());
+    let id_type = scoped
+        .iter()
+        .flat_map(|t| &t.columns)
+        .find(|c| c.name == profile.column)
+        .map_or(DataType::Integer, |c| c.data_type.clone());
+
+    if let Some(root) = root {
+        if config.table_rows.get(root).is_some_and(|&rows| rows != count) {
+            warn!(table = %root, tenants = count, "Tenant table gets one row per tenant, overriding its row count");
+        }
+        config.table_rows.insert(root.to_string(), count);
+    }
+    let mut rng = match config.seed {
+        Some(seed) => StdRng::seed_from_u64(table_seed(seed, "tenants")),
+        None => StdRng::from_entropy(),
+    };
+    info!(tenants = count, source_tenants = profile.source_tenants, root = ?root, "Laying out tenants");
+    TenantLayout::new(profile, count, |table| config.rows_for(table), root, &id_type, &mut rng)
+}
+
+/// Lays out the slice below `config.roots` and sets every table's row count in
+/// `config` to its size in the slice.
+fn subset_layout(genome: &DatabaseGenome, execution_order: &[String], config: &mut SynthesisConfig) -> Result<SubsetLayout> {
+    if config.tenants.is_some() {
+        bail!("Root entities can't be combined with tenants");
+    }
+    if config.max_parent_keys.is_some() {
+        bail!("Root entities can't be combined with max_parent_keys, which loses the order of parent keys");
+    }
+    if genome.fanouts.is_empty() {
+        warn!("The genome has no fan-out (scan with --fanout), slice sizes follow table size estimates");
+    }
+
+    let mut rng = match config.seed {
+        Some(seed) => StdRng::seed_from_u64(table_seed(seed, "roots")),
+        None => StdRng::from_entropy(),
+    };
+    let layout = SubsetLayout::new(genome, execution_order, &config.roots, |table| config.rows_for(table), &mut rng)?;
+    for table in execution_order {
+        if let Some(rows) = layout.rows_for(table) {
+            config.table_rows.insert(table.clone(), rows);
+        }
+    }
+    info!(roots = ?config.roots, "Laying out referential slice");
+    Ok(layout)
+}
+
+/// The simulated window of `days` days after the latest event of the genome's event
+/// tables. With `events_per_day_from_genome`, each event table's row count in
+/// `config` becomes its expected events over the window.
+fn simulation_window(genome: &DatabaseGenome, config: &mut SynthesisConfig, days: u32) -> Result<Window> {
+    if days == 0 {
+        bail!("At least one day must be simulated");
+    }
+    if config.events_per_day_from_genome && !config.roots.is_empty() {
+        bail!("Event tables can't be sized from the genome when generating around root entities");
+    }
+
+    let events: Vec<(&Table, &Distribution)> = genome
+        .tables
+        .iter()
+        .filter_map(|table| Some((table, simulate::event_column(genome, table)?.1)))
+        .collect();
+    let latest = events
+        .iter()
+        .filter_map(|(_, dist)| dist.max)
+        .reduce(f64::max)
+        .context("No table has a timestamp column with a seasonality profile to simulate")?;
+    let window = Window::after(latest, days);
+
+    if config.events_per_day_from_genome {
+        for (table, dist) in &events {
+            let rows = window.daily_volume(dist).iter().sum::<f64>().round() as usize;
+            config.table_rows.insert(table.name.clone(), rows);
+        }
+    }
+    info!(days, start_day = window.start_day, event_tables = events.len(), "Simulating a time window");
+    Ok(window)
+}
+
+/// A clock for every event table, over its final row count.
+fn event_clocks(genome: &DatabaseGenome, config: &SynthesisConfig, window: &Window) -> HashMap<String, (String, EventClock)> {
+    genome
+        .tables
+        .iter()
+        .filter_map(|table| {
+            let (column, dist) = simulate::event_column(genome, table)?;
+            let clock = EventClock::new(window, &window.daily_volume(dist), dist, config.rows_for(&table.name));
+            Some((table.name.clone(), (column.name.clone(), clock)))
+        })
+        .collect()
+}
+
+/// Per-table seed, so tables draw independent streams from one `--seed`.
+fn table_seed(seed: u64, table: &str) -> u64 {
+    // FNV-1a: stable across releases, unlike the std hasher
+    table.bytes().fold(seed ^ 0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Seed of row `index` of a table under stable entities: the index hashed into the
+/// table seed, so neighbouring rows get unrelated streams.
+fn entity_seed(table_seed: u64, index: usize) -> u64 {
+    index.to_le_bytes().iter().fold(table_seed, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Seed of shard `index` of a table: the table seed plus the shard index.
+fn shard_seed(table_seed: u64, index: usize) -> u64 {
+    table_seed.wrapping_add(index as u64)
+}
+
+/// Splits `rows` into consecutive ranges of at most `rows_per_shard` rows.
+fn shard_ranges(rows: usize, rows_per_shard: usize) -> Vec<Range<usize>> {
+    let size = rows_per_shard.max(1);
+    (0..rows.div_ceil(size))
+        .map(|i| i * size..((i + 1) * size).min(rows))
+        .collect()
+}
+
+/// Runs `task(0..count)` on up to `jobs` threads and returns the results in index order.
+fn run_parallel<T, F>(jobs: usize, count: usize, task: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(usize) -> T + Sync,
+{
+    let workers = jobs.clamp(1, count.max(1));
+    if workers == 1 {
+        return (0..count).map(task).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let finished = Mutex::new(Vec::with_capacity(count));
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    if index >= count {
+                        break;
+                    }
+                    let result = task(index);
+                    finished.lock().unwrap_or_else(|e| e.into_inner()).push((index, result));
+                }
+            });
+        }
+    });
+
+    let mut finished = finished.into_inner().unwrap_or_else(|e| e.into_inner());
+    finished.sort_unstable_by_key(|(index, _)| *index);
+    finished.into_iter().map(|(_, result)| result).collect()
+}
+
 #[derive(Debug)]
 pub struct GenerationResult {
     pub table_data: HashMap<String, TableData>,
@@ -317,7 +1492,12 @@ impl TableData {
 mod tests {
     use super::*;
     use crate::schema::{Column, DataType, ForeignKey};
-    use crate::math::{Distribution, Histogram};
+    use crate::copula::{CorrelationMethod, CovarianceMatrix};
+    use crate::math::{BinScale, Distribution, Histogram};
+    use crate::sequence::SequenceModel;
+    use crate::subset::FanOut;
+    use crate::tenant::TenantProfile;
+    use std::collections::HashSet;
 
     fn create_test_genome() -> DatabaseGenome {
         let tables = vec![
@@ -363,6 +1543,7 @@ mod tests {
                         ("Charlie".to_string(), 30),
                     ].iter().cloned().collect(),
                     truncated: false,
+                    long_tail: None,
                 },
             ),
         );
@@ -442,4 +1623,833 @@ mod tests {
         assert!(result.get_table_data("users").is_some());
         assert!(result.get_copy_data("users").is_some());
     }
+
+    fn correlated_genome(rho: f64) -> DatabaseGenome {
+        let tables = vec![Table::new(
+            "metrics".to_string(),
+            vec![
+                Column::new("id".to_string(), DataType::Integer, false, true),
+                Column::new("a".to_string(), DataType::Float, false, false),
+                Column::new("b".to_string(), DataType::Float, false, false),
+            ],
+            vec![],
+        )];
+
+        let uniform = || Distribution::new(Some(0.0), Some(100.0), 0, 1000, 1000, Histogram::Numeric {
+            bins: (0..=10).map(|i| i as f64 * 10.0).collect(),
+            frequencies: vec![100; 10],
+            scale: BinScale::Linear,
+        });
+        let mut distributions = HashMap::new();
+        distributions.insert(DatabaseGenome::make_key("metrics", "a"), uniform());
+        distributions.insert(DatabaseGenome::make_key("metrics", "b"), uniform());
+
+        let mut correlations = HashMap::new();
+        correlations.insert("metrics".to_string(), CovarianceMatrix {
+            columns: vec!["a".to_string(), "b".to_string()],
+            matrix_data: vec![1.0, rho, rho, 1.0],
+            dimension: 2,
+            method: CorrelationMethod::Pearson,
+            pseudo_observations: None,
+        });
+
+        DatabaseGenome::with_correlations(tables, distributions, correlations, None)
+    }
+
+    #[cfg(feature = "copula")]
+    fn pearson(xs: &[f64], ys: &[f64]) -> f64 {
+        let n = xs.len() as f64;
+        let (mx, my) = (xs.iter().sum::<f64>() / n, ys.iter().sum::<f64>() / n);
+        let cov: f64 = xs.iter().zip(ys).map(|(x, y)| (x - mx) * (y - my)).sum();
+        let vx: f64 = xs.iter().map(|x| (x - mx).powi(2)).sum();
+        let vy: f64 = ys.iter().map(|y| (y - my).powi(2)).sum();
+        cov / (vx * vy).sqrt()
+    }
+
+    #[test]
+    #[cfg(feature = "copula")]
+    fn test_copula_correlates_generated_columns() -> Result<()> {
+        let config = SynthesisConfig { rows_per_table: 2_000, seed: Some(11), ..Default::default() };
+        let result = Synthesizer::new(correlated_genome(0.9), config)?.generate()?;
+
+        let (mut a, mut b) = (Vec::new(), Vec::new());
+        for line in result.get_copy_data("metrics").unwrap().lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            a.push(fields[1].parse::<f64>()?);
+            b.push(fields[2].parse::<f64>()?);
+        }
+
+        let r = pearson(&a, &b);
+        assert!(r > 0.8, "generated correlation {}", r);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "copula")]
+    fn test_copula_blocks_drop_weak_links() -> Result<()> {
+        let correlation = |threshold| -> Result<f64> {
+            let config = SynthesisConfig {
+                rows_per_table: 2_000,
+                seed: Some(11),
+                copula_block_threshold: Some(threshold),
+                ..Default::default()
+            };
+            let result = Synthesizer::new(correlated_genome(0.9), config)?.generate()?;
+            let (mut a, mut b) = (Vec::new(), Vec::new());
+            for line in result.get_copy_data("metrics").unwrap().lines() {
+                let fields: Vec<&str> = line.split('\t').collect();
+                a.push(fields[1].parse::<f64>()?);
+                b.push(fields[2].parse::<f64>()?);
+            }
+            Ok(pearson(&a, &b))
+        };
+
+        let linked = correlation(0.5)?;
+        assert!(linked > 0.8, "generated correlation {}", linked);
+        // Below the threshold the columns form no block and are sampled independently
+        let unlinked = correlation(0.95)?;
+        assert!(unlinked.abs() < 0.1, "generated correlation {}", unlinked);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "copula")]
+    fn test_empirical_copula_follows_pseudo_observations() -> Result<()> {
+        // The stored matrix says uncorrelated, the joint sample says perfectly opposed
+        let mut genome = correlated_genome(0.0);
+        let samples: Vec<Vec<Option<f64>>> = (0..100).map(|i| vec![Some(i as f64), Some(-(i as f64))]).collect();
+        let matrix = genome.correlations.remove("metrics").unwrap();
+        genome.correlations.insert("metrics".to_string(), matrix.with_pseudo_observations(&samples));
+
+        let config = SynthesisConfig { rows_per_table: 2_000, seed: Some(11), ..Default::default() };
+        let result = Synthesizer::new(genome, config)?.generate()?;
+        let (mut a, mut b) = (Vec::new(), Vec::new());
+        for line in result.get_copy_data("metrics").unwrap().lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            a.push(fields[1].parse::<f64>()?);
+            b.push(fields[2].parse::<f64>()?);
+        }
+
+        let r = pearson(&a, &b);
+        assert!(r < -0.9, "generated correlation {}", r);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "copula")]
+    fn test_copula_correlates_timestamps() -> Result<()> {
+        use crate::seasonality::Seasonality;
+
+        let tables = vec![Table::new(
+            "users".to_string(),
+            vec![
+                Column::new("id".to_string(), DataType::Integer, false, true),
+                Column::new("signed_up_at".to_string(), DataType::Timestamp, false, false),
+                Column::new("first_order_at".to_string(), DataType::Timestamp, false, false),
+            ],
+            vec![],
+        )];
+
+        // 2024, spread evenly, with every value between 09:00 and 18:00 UTC
+        let (start, end) = (1_704_067_200.0, 1_735_689_600.0);
+        let year = || {
+            let mut distribution = Distribution::new(Some(start), Some(end), 0, 1000, 1000, Histogram::Numeric {
+                bins: (0..=12).map(|i| start + (end - start) * i as f64 / 12.0).collect(),
+                frequencies: vec![100; 12],
+                scale: BinScale::Linear,
+            });
+            distribution.seasonality = Some(Seasonality {
+                hour_of_day: (0..24).map(|hour| u64::from((9..18).contains(&hour))).collect(),
+                day_of_week: vec![1; 7],
+                monthly: Default::default(),
+            });
+            distribution
+        };
+        let mut distributions = HashMap::new();
+        distributions.insert(DatabaseGenome::make_key("users", "signed_up_at"), year());
+        distributions.insert(DatabaseGenome::make_key("users", "first_order_at"), year());
+
+        let mut correlations = HashMap::new();
+        correlations.insert("users".to_string(), CovarianceMatrix {
+            columns: vec!["signed_up_at".to_string(), "first_order_at".to_string()],
+            matrix_data: vec![1.0, 0.95, 0.95, 1.0],
+            dimension: 2,
+            method: CorrelationMethod::Pearson,
+            pseudo_observations: None,
+        });
+        let genome = DatabaseGenome::with_correlations(tables, distributions, correlations, None);
+
+        let config = SynthesisConfig { rows_per_table: 1_000, seed: Some(7), ..Default::default() };
+        let result = Synthesizer::new(genome, config)?.generate()?;
+
+        let (mut signups, mut orders) = (Vec::new(), Vec::new());
+        for line in result.get_copy_data("users").unwrap().lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            for (field, epochs) in fields[1..].iter().zip([&mut signups, &mut orders]) {
+                let ts = chrono::DateTime::parse_from_str(field, "%Y-%m-%d %H:%M:%S%.f%:z")?;
+                assert!((9..18).contains(&chrono::Timelike::hour(&ts)), "off-hours timestamp {}", field);
+                epochs.push(ts.timestamp() as f64);
+            }
+        }
+
+        let r = pearson(&signups, &orders);
+        assert!(r > 0.85, "generated correlation {}", r);
+        Ok(())
+    }
+
+    #[test]
+    fn test_child_conditioned_on_parent_attribute() -> Result<()> {
+        let tables = vec![
+            Table::new(
+                "customers".to_string(),
+                vec![
+                    Column::new("id".to_string(), DataType::Integer, false, true),
+                    Column::new("tier".to_string(), DataType::Text, false, false),
+                ],
+                vec![],
+            ),
+            Table::new(
+                "orders".to_string(),
+                vec![
+                    Column::new("id".to_string(), DataType::Integer, false, true),
+                    Column::new("customer_id".to_string(), DataType::Integer, false, false),
+                    Column::new("amount".to_string(), DataType::Float, false, false),
+                ],
+                vec![ForeignKey::new("customer_id".to_string(), "customers".to_string(), "id".to_string())],
+            ),
+        ];
+
+        let amounts = |low: f64, high: f64| Distribution::new(Some(low), Some(high), 0, 100, 100, Histogram::Numeric {
+            bins: vec![low, high],
+            frequencies: vec![100],
+            scale: BinScale::Linear,
+        });
+
+        let mut distributions = HashMap::new();
+        distributions.insert("customers.tier".to_string(), Distribution::new(None, None, 0, 100, 2, Histogram::Categorical {
+            frequencies: [("gold".to_string(), 50), ("basic".to_string(), 50)].into_iter().collect(),
+            truncated: false,
+            long_tail: None,
+        }));
+        distributions.insert("orders.amount".to_string(), amounts(0.0, 1000.0));
+
+        let mut genome = DatabaseGenome::new(tables, distributions);
+        genome.conditionals.insert("orders.amount".to_string(), ConditionalDistribution {
+            foreign_key: "customer_id".to_string(),
+            parent_table: "customers".to_string(),
+            parent_column: "tier".to_string(),
+            by_parent_value: [
+                ("gold".to_string(), amounts(900.0, 1000.0)),
+                ("basic".to_string(), amounts(0.0, 100.0)),
+            ].into_iter().collect(),
+        });
+
+        let config = SynthesisConfig { rows_per_table: 200, seed: Some(3), fk_conditioning: true, ..Default::default() };
+        let result = Synthesizer::new(genome, config)?.generate()?;
+
+        let tiers: HashMap<&str, &str> = result.get_copy_data("customers").unwrap()
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .collect();
+
+        for line in result.get_copy_data("orders").unwrap().lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let amount: f64 = fields[2].parse()?;
+            match tiers[fields[1]] {
+                "gold" => assert!(amount >= 900.0, "gold order with amount {}", amount),
+                _ => assert!(amount <= 100.0, "basic order with amount {}", amount),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_sequence_column_increases() -> Result<()> {
+        let tables = vec![Table::new(
+            "invoices".to_string(),
+            vec![Column::new("invoice_number".to_string(), DataType::Integer, false, false)],
+            vec![],
+        )];
+        let mut dist = Distribution::new(Some(5000.0), Some(6000.0), 0, 1000, 1000, Histogram::Numeric {
+            bins: vec![5000.0, 6000.0],
+            frequencies: vec![1000],
+            scale: BinScale::Linear,
+        });
+        dist.sequence = Some(SequenceModel { start: 5000.0, gap_quantiles: vec![1.0, 1.0, 2.0], integer: true });
+
+        let mut distributions = HashMap::new();
+        distributions.insert("invoices.invoice_number".to_string(), dist);
+
+        let config = SynthesisConfig { rows_per_table: 100, ..Default::default() };
+        let result = Synthesizer::new(DatabaseGenome::new(tables, distributions), config)?.generate()?;
+
+        let values: Vec<i64> = result.get_copy_data("invoices").unwrap()
+            .lines()
+            .map(|line| line.parse())
+            .collect::<Result<_, _>>()?;
+        assert_eq!(values[0], 5000);
+        assert!(values.windows(2).all(|w| w[1] > w[0]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sharded_rows_join_seamlessly() -> Result<()> {
+        let tables = vec![Table::new(
+            "invoices".to_string(),
+            vec![
+                Column::new("id".to_string(), DataType::Integer, false, true),
+                Column::new("invoice_number".to_string(), DataType::Integer, false, false),
+            ],
+            vec![],
+        )];
+        let mut dist = Distribution::new(Some(1.0), Some(2.0), 0, 1000, 1000, Histogram::Numeric {
+            bins: vec![1.0, 2.0],
+            frequencies: vec![1000],
+            scale: BinScale::Linear,
+        });
+        dist.sequence = Some(SequenceModel { start: 1.0, gap_quantiles: vec![1.0, 5.0], integer: true });
+        let genome = || {
+            let mut distributions = HashMap::new();
+            distributions.insert("invoices.invoice_number".to_string(), dist.clone());
+            DatabaseGenome::new(tables.clone(), distributions)
+        };
+
+        let config = SynthesisConfig { rows_per_table: 1000, seed: Some(5), rows_per_shard: 64, ..Default::default() };
+        let serial = Synthesizer::new(genome(), config.clone())?.generate()?;
+        let parallel = Synthesizer::new(genome(), SynthesisConfig { jobs: 4, ..config })?.generate()?;
+        assert_eq!(serial.get_copy_data("invoices"), parallel.get_copy_data("invoices"));
+
+        let rows: Vec<(i64, i64)> = serial.get_copy_data("invoices").unwrap()
+            .lines()
+            .map(|line| {
+                let (id, number) = line.split_once('\t').unwrap();
+                (id.parse().unwrap(), number.parse().unwrap())
+            })
+            .collect();
+        assert_eq!(rows.len(), 1000);
+        assert!(rows.iter().enumerate().all(|(i, (id, _))| *id == i as i64 + 1));
+        assert!(rows.windows(2).all(|w| w[1].1 > w[0].1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_table_rows_override() -> Result<()> {
+        let config = SynthesisConfig {
+            rows_per_table: 5,
+            seed: Some(3),
+            table_rows: [("orders".to_string(), 40)].into_iter().collect(),
+            ..Default::default()
+        };
+        let result = Synthesizer::new(create_test_genome(), config)?.generate()?;
+
+        assert_eq!(result.get_table_data("users").unwrap().row_count, 5);
+        assert_eq!(result.get_table_data("orders").unwrap().row_count, 40);
+        let orders = result.get_copy_data("orders").unwrap();
+        assert_eq!(orders.lines().count(), 40);
+        for line in orders.lines() {
+            let user_id: i64 = line.split('\t').nth(1).unwrap().parse()?;
+            assert!((1..=5).contains(&user_id));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_offsets_append_after_existing_rows() -> Result<()> {
+        let config = SynthesisConfig {
+            rows_per_table: 400,
+            seed: Some(6),
+            rows_per_shard: 64,
+            key_offset: 1000,
+            table_key_offsets: [("orders".to_string(), 250)].into_iter().collect(),
+            ..Default::default()
+        };
+        let result = Synthesizer::new(create_test_genome(), config)?.generate()?;
+
+        let ids = |table: &str| -> Vec<i64> {
+            result.get_copy_data(table).unwrap().lines().map(|line| line.split('\t').next().unwrap().parse().unwrap()).collect()
+        };
+        assert_eq!(ids("users"), (1001..=1400).collect::<Vec<_>>());
+        assert_eq!(ids("orders"), (251..=650).collect::<Vec<_>>());
+
+        // Orders pick among the 1000 existing users as well as the new ones
+        let user_ids: Vec<i64> = result.get_copy_data("orders").unwrap()
+            .lines()
+            .map(|line| line.split('\t').nth(1).unwrap().parse().unwrap())
+            .collect();
+        assert!(user_ids.iter().all(|id| (1..=1400).contains(id)));
+        assert!(user_ids.iter().any(|&id| id <= 1000));
+
+        let negative = SynthesisConfig { key_offset: -1, ..Default::default() };
+        assert!(Synthesizer::new(create_test_genome(), negative).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_simulated_events_advance_over_the_window() -> Result<()> {
+        use crate::seasonality::Seasonality;
+
+        let tables = vec![Table::new(
+            "events".to_string(),
+            vec![
+                Column::new("id".to_string(), DataType::Integer, false, true),
+                Column::new("happened_at".to_string(), DataType::Timestamp, false, false),
+            ],
+            vec![],
+        )];
+        // 3660 events over 2024, ten a day between 09:00 and 18:00 UTC
+        let (start, end) = (1_704_067_200.0, 1_735_689_600.0);
+        let mut distribution = Distribution::new(Some(start), Some(end), 0, 3660, 3660, Histogram::Numeric {
+            bins: vec![start, end],
+            frequencies: vec![3660],
+            scale: BinScale::Linear,
+        });
+        distribution.seasonality = Some(Seasonality {
+            hour_of_day: (0..24).map(|hour| u64::from((9..18).contains(&hour))).collect(),
+            day_of_week: vec![1; 7],
+            monthly: Default::default(),
+        });
+        let genome = DatabaseGenome::new(tables, HashMap::from([(DatabaseGenome::make_key("events", "happened_at"), distribution)]));
+
+        let config = SynthesisConfig {
+            rows_per_table: 50,
+            seed: Some(8),
+            rows_per_shard: 64,
+            simulate_days: Some(30),
+            events_per_day_from_genome: true,
+            ..Default::default()
+        };
+        let result = Synthesizer::new(genome, config)?.generate()?;
+        let times: Vec<String> = result.get_copy_data("events").unwrap()
+            .lines()
+            .map(|line| line.split('\t').nth(1).unwrap().to_string())
+            .collect();
+
+        assert!((290..=310).contains(&times.len()), "{} events", times.len());
+        assert!(times.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(times[0].as_str() >= "2025-01-02 09:00:00" && times[times.len() - 1].as_str() < "2025-01-31 18:00:00");
+        assert!(times.iter().all(|time| ("09".."18").contains(&&time[11..13])), "{:?}", times);
+
+        let nothing_to_simulate = SynthesisConfig { simulate_days: Some(30), ..Default::default() };
+        assert!(Synthesizer::new(create_test_genome(), nothing_to_simulate).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_shard_ranges() {
+        assert_eq!(shard_ranges(10, 4), vec![0..4, 4..8, 8..10]);
+        assert_eq!(shard_ranges(0, 4), Vec::<Range<usize>>::new());
+    }
+
+    #[test]
+    fn test_parallel_generation_matches_serial() -> Result<()> {
+        let serial = SynthesisConfig { rows_per_table: 30, seed: Some(11), ..Default::default() };
+        let parallel = SynthesisConfig { jobs: 4, ..serial.clone() };
+
+        let a = Synthesizer::new(create_test_genome(), serial)?.generate()?;
+        let b = Synthesizer::new(create_test_genome(), parallel)?.generate()?;
+
+        for table in ["users", "orders"] {
+            assert_eq!(a.get_copy_data(table), b.get_copy_data(table));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_seed_reproduces_output() -> Result<()> {
+        let config = SynthesisConfig { rows_per_table: 50, seed: Some(7), ..Default::default() };
+        let first = Synthesizer::new(correlated_genome(0.5), config.clone())?.generate()?;
+        let second = Synthesizer::new(correlated_genome(0.5), config)?.generate()?;
+
+        assert_eq!(first.get_copy_data("metrics"), second.get_copy_data("metrics"));
+        Ok(())
+    }
+
+    fn uuid_genome(child_type: DataType) -> DatabaseGenome {
+        let tables = vec![
+            Table::new(
+                "accounts".to_string(),
+                vec![Column::new("id".to_string(), DataType::Uuid, false, true)],
+                vec![],
+            ),
+            Table::new(
+                "sessions".to_string(),
+                vec![
+                    Column::new("id".to_string(), DataType::Integer, false, true),
+                    Column::new("account_id".to_string(), child_type, false, false),
+                ],
+                vec![ForeignKey::new("account_id".to_string(), "accounts".to_string(), "id".to_string())],
+            ),
+        ];
+        DatabaseGenome::new(tables, HashMap::new())
+    }
+
+    #[test]
+    fn test_uuid_foreign_keys_reference_parent() -> Result<()> {
+        let config = SynthesisConfig { rows_per_table: 20, seed: Some(1), ..Default::default() };
+        let result = Synthesizer::new(uuid_genome(DataType::Uuid), config)?.generate()?;
+
+        let parents: Vec<&str> = result.get_copy_data("accounts").unwrap().lines().collect();
+        for line in result.get_copy_data("sessions").unwrap().lines() {
+            let account = line.split('\t').nth(1).unwrap();
+            assert!(uuid::Uuid::parse_str(account).is_ok());
+            assert!(parents.contains(&account));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_parent_keys_samples_listed_keys() -> Result<()> {
+        let config = SynthesisConfig {
+            rows_per_table: 200,
+            seed: Some(4),
+            max_parent_keys: Some(5),
+            ..Default::default()
+        };
+        let result = Synthesizer::new(uuid_genome(DataType::Uuid), config)?.generate()?;
+
+        let parents: HashSet<&str> = result.get_copy_data("accounts").unwrap().lines().collect();
+        let referenced: HashSet<&str> = result.get_copy_data("sessions").unwrap()
+            .lines()
+            .map(|line| line.split('\t').nth(1).unwrap())
+            .collect();
+        assert_eq!(parents.len(), 200);
+        assert_eq!(referenced.len(), 5);
+        assert!(referenced.is_subset(&parents));
+        Ok(())
+    }
+
+    #[derive(Default)]
+    struct RecordedProgress {
+        events: Mutex<Vec<(String, &'static str, usize)>>,
+    }
+
+    impl GenerationProgress for RecordedProgress {
+        fn table_started(&self, table: &str, rows: usize) {
+            self.events.lock().unwrap().push((table.to_string(), "started", rows));
+        }
+
+        fn rows_generated(&self, table: &str, rows: usize) {
+            self.events.lock().unwrap().push((table.to_string(), "rows", rows));
+        }
+
+        fn table_finished(&self, table: &str) {
+            self.events.lock().unwrap().push((table.to_string(), "finished", 0));
+        }
+    }
+
+    #[test]
+    fn test_progress_reports_every_row() -> Result<()> {
+        let config = SynthesisConfig {
+            rows_per_table: 25_000,
+            rows_per_shard: 12_000,
+            seed: Some(2),
+            jobs: 2,
+            ..Default::default()
+        };
+        let progress = Arc::new(RecordedProgress::default());
+        Synthesizer::new(uuid_genome(DataType::Uuid), config)?
+            .with_progress(progress.clone())
+            .generate()?;
+
+        let events = progress.events.lock().unwrap();
+        for table in ["accounts", "sessions"] {
+            let of_table: Vec<_> = events.iter().filter(|(t, _, _)| t == table).collect();
+            assert_eq!(of_table.first().map(|e| (e.1, e.2)), Some(("started", 25_000)));
+            assert_eq!(of_table.last().map(|e| e.1), Some("finished"));
+            let rows: usize = of_table.iter().filter(|e| e.1 == "rows").map(|e| e.2).sum();
+            assert_eq!(rows, 25_000);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_column_generator_overrides_histogram() -> Result<()> {
+        let generators = |key: &str, name: &str| SynthesisConfig {
+            rows_per_table: 20,
+            seed: Some(5),
+            column_generators: [(key.to_string(), name.to_string())].into_iter().collect(),
+            ..Default::default()
+        };
+
+        let result = Synthesizer::new(create_test_genome(), generators("users.name", "ipv4"))?.generate()?;
+        for line in result.get_copy_data("users").unwrap().lines() {
+            let ip: std::net::Ipv4Addr = line.split('\t').nth(1).unwrap().parse()?;
+            assert!(ip.is_private());
+        }
+
+        assert!(Synthesizer::new(create_test_genome(), generators("users.name", "iban")).is_err());
+        assert!(Synthesizer::new(create_test_genome(), generators("users.nickname", "email")).is_err());
+        assert!(Synthesizer::new(create_test_genome(), generators("orders.user_id", "email")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_generation_stage_anonymization() -> Result<()> {
+        let anonymized = |key: &str, stage: AnonymizeStage| SynthesisConfig {
+            rows_per_table: 50,
+            seed: Some(9),
+            anonymized_columns: [(key.to_string(), FormatPreserving { keep_prefix: 1, stage })]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+
+        let result = Synthesizer::new(create_test_genome(), anonymized("users.name", AnonymizeStage::Generate))?
+            .generate()?;
+        let names: Vec<String> = result
+            .get_copy_data("users")
+            .unwrap()
+            .lines()
+            .map(|line| line.split('\t').nth(1).unwrap().to_string())
+            .collect();
+        assert!(names.iter().all(|name| !["Alice", "Bob", "Charlie"].contains(&name.as_str())));
+        assert!(names.iter().all(|name| {
+            let mut chars = name.chars();
+            matches!(chars.next(), Some('A' | 'B' | 'C')) && chars.all(|c| c.is_ascii_lowercase())
+        }));
+
+        // Scan-stage entries were applied by the scan and leave generation alone
+        let result = Synthesizer::new(create_test_genome(), anonymized("users.name", AnonymizeStage::Scan))?
+            .generate()?;
+        assert!(result.get_copy_data("users").unwrap().lines().any(|line| line.ends_with("\tBob")));
+
+        assert!(Synthesizer::new(create_test_genome(), anonymized("orders.user_id", AnonymizeStage::Generate)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_mismatched_key_types() {
+        assert!(Synthesizer::new(uuid_genome(DataType::Integer), SynthesisConfig::default()).is_err());
+
+        let lenient = SynthesisConfig { strict_fk_enforcement: false, ..Default::default() };
+        assert!(Synthesizer::new(uuid_genome(DataType::Integer), lenient).is_ok());
+    }
+
+    #[test]
+    fn test_duplicate_rows_keep_unique_keys() -> Result<()> {
+        let config = SynthesisConfig {
+            rows_per_table: 2000,
+            seed: Some(8),
+            table_duplicates: HashMap::from([("metrics".to_string(), DuplicateRates { exact: 0.1, near: 0.1 })]),
+            ..Default::default()
+        };
+        let result = Synthesizer::new(correlated_genome(0.0), config)?.generate()?;
+
+        let rows: Vec<Vec<&str>> = result.get_copy_data("metrics").unwrap()
+            .lines()
+            .map(|line| line.split('\t').collect())
+            .collect();
+        let ids: HashSet<&str> = rows.iter().map(|row| row[0]).collect();
+        assert_eq!(ids.len(), 2000);
+
+        let mut pairs: HashMap<(&str, &str), usize> = HashMap::new();
+        let (mut a_counts, mut b_counts): (HashMap<&str, usize>, HashMap<&str, usize>) = Default::default();
+        for row in &rows {
+            *pairs.entry((row[1], row[2])).or_default() += 1;
+            *a_counts.entry(row[1]).or_default() += 1;
+            *b_counts.entry(row[2]).or_default() += 1;
+        }
+        let exact = rows.len() - pairs.len();
+        // A near duplicate and its source each share one value with the other
+        let near = pairs
+            .iter()
+            .filter(|((a, b), count)| **count == 1 && (a_counts[a] > 1 || b_counts[b] > 1))
+            .count();
+        assert!((140..=260).contains(&exact), "{} exact duplicates", exact);
+        assert!((280..=520).contains(&near), "{} rows in near duplicate pairs", near);
+
+        let unknown = SynthesisConfig {
+            table_duplicates: HashMap::from([("missing".to_string(), DuplicateRates { exact: 0.1, near: 0.0 })]),
+            ..Default::default()
+        };
+        assert!(Synthesizer::new(correlated_genome(0.0), unknown).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_noise_keeps_the_distribution() -> Result<()> {
+        let generate = |noise: f64| -> Result<Vec<f64>> {
+            let config = SynthesisConfig { rows_per_table: 2000, seed: Some(3), noise, ..Default::default() };
+            let result = Synthesizer::new(correlated_genome(0.0), config)?.generate()?;
+            Ok(result.get_copy_data("metrics").unwrap()
+                .lines()
+                .map(|line| line.split('\t').nth(1).unwrap().parse().unwrap())
+                .collect())
+        };
+
+        let exact = generate(0.0)?;
+        let noisy = generate(0.5)?;
+        assert_ne!(exact, noisy);
+        let mean = noisy.iter().sum::<f64>() / noisy.len() as f64;
+        assert!((45.0..55.0).contains(&mean), "mean {}", mean);
+        assert!(noisy.iter().all(|&v| (-2.5..=102.5).contains(&v)));
+
+        let invalid = SynthesisConfig { noise: 1.5, ..Default::default() };
+        assert!(Synthesizer::new(correlated_genome(0.0), invalid).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_stable_entities_survive_row_count_changes() -> Result<()> {
+        let mut genome = correlated_genome(0.5);
+        genome.tables.push(Table::new(
+            "sites".to_string(),
+            vec![Column::new("id".to_string(), DataType::Integer, false, true)],
+            vec![],
+        ));
+        genome.tables[0].columns.push(Column::new("site_id".to_string(), DataType::Integer, false, false));
+        genome.tables[0].foreign_keys.push(ForeignKey::new("site_id".to_string(), "sites".to_string(), "id".to_string()));
+
+        let generate = |rows: usize, sites: usize, rows_per_shard: usize, stable_entities: bool| -> Result<Vec<String>> {
+            let config = SynthesisConfig {
+                rows_per_table: rows,
+                table_rows: HashMap::from([("sites".to_string(), sites)]),
+                rows_per_shard,
+                seed: Some(42),
+                stable_entities,
+                ..Default::default()
+            };
+            let result = Synthesizer::new(genome.clone(), config)?.generate()?;
+            // Everything but the foreign key, which follows the number of sites
+            Ok(result.get_copy_data("metrics").unwrap()
+                .lines()
+                .map(|line| line.split('\t').take(3).collect::<Vec<_>>().join("\t"))
+                .collect())
+        };
+
+        let small = generate(100, 5, 16, true)?;
+        let large = generate(500, 300, 1000, true)?;
+        assert_eq!(small[..], large[..100]);
+        assert_ne!(generate(100, 5, 16, false)?[..], generate(500, 300, 1000, false)?[..100]);
+
+        let unseeded = SynthesisConfig { stable_entities: true, ..Default::default() };
+        assert!(Synthesizer::new(genome, unseeded).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_roots_generate_a_referential_slice() -> Result<()> {
+        let mut genome = tenant_genome();
+        genome.tenants = None;
+        genome.fanouts.insert("users.tenant_id".to_string(), FanOut::from_counts([(2, 1), (5, 1)]));
+        genome.fanouts.insert("orders.user_id".to_string(), FanOut::from_counts([(0, 1), (3, 1)]));
+        let roots = HashMap::from([("tenants".to_string(), 20)]);
+        let config = SynthesisConfig { rows_per_table: 5000, seed: Some(4), roots, ..Default::default() };
+        let result = Synthesizer::new(genome, config)?.generate()?;
+
+        let rows = |table: &str| -> Vec<Vec<String>> {
+            result.get_copy_data(table).unwrap()
+                .lines()
+                .map(|line| line.split('\t').map(str::to_string).collect())
+                .collect()
+        };
+        assert_eq!(rows("tenants").len(), 20);
+        let users = rows("users");
+        assert!((40..=100).contains(&users.len()), "{} users", users.len());
+        let mut users_per_tenant: HashMap<&str, usize> = HashMap::new();
+        for user in &users {
+            *users_per_tenant.entry(user[1].as_str()).or_default() += 1;
+        }
+        assert!(users_per_tenant.values().all(|&n| n == 2 || n == 5));
+
+        // Orders join the slice under users, the deeper of their two parents
+        let orders = rows("orders");
+        let mut orders_per_user: HashMap<&str, usize> = HashMap::new();
+        for order in &orders {
+            *orders_per_user.entry(order[2].as_str()).or_default() += 1;
+        }
+        assert!(orders_per_user.values().all(|&n| n == 3));
+        assert!(orders_per_user.len() < users.len());
+
+        let conflicting = SynthesisConfig { tenants: Some(2), roots: HashMap::from([("tenants".to_string(), 1)]), ..Default::default() };
+        assert!(Synthesizer::new(tenant_genome(), conflicting).is_err());
+        Ok(())
+    }
+
+    fn tenant_genome() -> DatabaseGenome {
+        let column = |name: &str, pk: bool| Column::new(name.to_string(), DataType::Integer, false, pk);
+        let fk = |source: &str, target: &str| ForeignKey::new(source.to_string(), target.to_string(), "id".to_string());
+        let tables = vec![
+            Table::new("tenants".to_string(), vec![column("id", true)], vec![]),
+            Table::new(
+                "users".to_string(),
+                vec![column("id", true), column("tenant_id", false)],
+                vec![fk("tenant_id", "tenants")],
+            ),
+            Table::new(
+                "orders".to_string(),
+                vec![column("id", true), column("tenant_id", false), column("user_id", false)],
+                vec![fk("tenant_id", "tenants"), fk("user_id", "users")],
+            ),
+        ];
+        let mut genome = DatabaseGenome::new(tables, HashMap::new());
+        let counts = |big: u64, small: u64| HashMap::from([("big".to_string(), big), ("small".to_string(), small)]);
+        genome.tenants = Some(TenantProfile::from_counts(
+            "tenant_id",
+            vec![("users".to_string(), counts(90, 10)), ("orders".to_string(), counts(900, 100))],
+            &mut rand::thread_rng(),
+        ));
+        genome
+    }
+
+    #[test]
+    fn test_tenant_foreign_keys_stay_within_tenant() -> Result<()> {
+        let config = SynthesisConfig { rows_per_table: 2000, seed: Some(12), tenants: Some(8), ..Default::default() };
+        let result = Synthesizer::new(tenant_genome(), config)?.generate()?;
+
+        let rows = |table: &str| -> Vec<Vec<String>> {
+            result.get_copy_data(table).unwrap()
+                .lines()
+                .map(|line| line.split('\t').map(str::to_string).collect())
+                .collect()
+        };
+        assert_eq!(rows("tenants").len(), 8);
+        let user_tenants: HashMap<String, String> = rows("users").into_iter().map(|row| (row[0].clone(), row[1].clone())).collect();
+        let orders = rows("orders");
+        assert_eq!(orders.len(), 2000);
+        for order in &orders {
+            assert_eq!(user_tenants[&order[2]], order[1]);
+        }
+
+        // Both tables are split alike, as their source counts are
+        let mut per_tenant: HashMap<&str, (usize, usize)> = HashMap::new();
+        for tenant in user_tenants.values() {
+            per_tenant.entry(tenant.as_str()).or_default().0 += 1;
+        }
+        for order in &orders {
+            per_tenant.entry(order[1].as_str()).or_default().1 += 1;
+        }
+        assert_eq!(per_tenant.len(), 8);
+        for (users, orders) in per_tenant.values() {
+            assert!(orders.abs_diff(*users) <= 1, "{} users, {} orders", users, orders);
+        }
+
+        assert!(Synthesizer::new(create_test_genome(), SynthesisConfig { tenants: Some(3), ..Default::default() }).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_orphan_rate_breaks_some_references() -> Result<()> {
+        let config = SynthesisConfig {
+            rows_per_table: 2000,
+            seed: Some(6),
+            strict_fk_enforcement: false,
+            orphan_rate: 0.1,
+            ..Default::default()
+        };
+        let result = Synthesizer::new(create_test_genome(), config)?.generate()?;
+
+        let orphans = result.get_copy_data("orders").unwrap()
+            .lines()
+            .map(|line| line.split('\t').nth(1).unwrap().parse::<i64>().unwrap())
+            .filter(|user_id| !(1..=2000).contains(user_id))
+            .count();
+        assert!((120..=280).contains(&orphans), "{} orphans", orphans);
+
+        let strict = SynthesisConfig { orphan_rate: 0.1, ..Default::default() };
+        assert!(Synthesizer::new(create_test_genome(), strict).is_err());
+        let invalid = SynthesisConfig { strict_fk_enforcement: false, orphan_rate: 1.5, ..Default::default() };
+        assert!(Synthesizer::new(create_test_genome(), invalid).is_err());
+        Ok(())
+    }
 }
@@ -0,0 +1,161 @@
+//! Privacy noise dialled in by [`SynthesisConfig::noise`](super::SynthesisConfig::noise),
+//! trading how closely the twin mirrors the source for how little it reveals.
+//!
+//! At noise `n` between 0 and 1, numbers move by up to `n` times half the width of
+//! the histogram bin they fall in, timestamps by up to `n` days, and with probability
+//! `n` a rare category is swapped for another rare one. Noise 0 leaves values untouched.
+
+use std::borrow::Cow;
+use rand::Rng;
+use crate::math::{Distribution, Histogram, NUMERIC_HISTOGRAM_BINS};
+use crate::schema::{Column, DataType};
+use crate::synth::strategy::Value;
+
+/// Largest timestamp shift at full noise, in seconds.
+pub const TIMESTAMP_WINDOW: f64 = 86_400.0;
+
+/// Categories holding less than this share of a column's rows are rare.
+pub const RARE_SHARE: f64 = 0.01;
+
+/// How noise changes one column's values, prepared once per table.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnNoise<'a> {
+    /// Numbers jittered by the width of the bin holding them.
+    Binned(&'a [f64]),
+    /// Numbers jittered by a fixed width, for columns without bins.
+    Spread(f64),
+    /// Integers of a few-valued column moved to a neighbouring observed value.
+    Discrete(&'a [i64]),
+    Timestamp,
+    /// Rare categories, swapped among each other.
+    Categories(Vec<&'a str>),
+}
+
+impl<'a> ColumnNoise<'a> {
+    /// Noise for `column`, or `None` when its values are left alone: other types,
+    /// generated text, and categorical columns with fewer than two rare categories.
+    pub fn for_column(column: &Column, dist: &'a Distribution) -> Option<Self> {
+        match (&column.data_type, &dist.histogram) {
+            (DataType::Timestamp, _) => Some(ColumnNoise::Timestamp),
+            (DataType::Integer | DataType::Float, Histogram::Numeric { bins, .. }) if bins.len() > 1 => {
+                Some(ColumnNoise::Binned(bins))
+            }
+            (DataType::Integer | DataType::Float, Histogram::Discrete { values, .. }) if values.len() > 1 => {
+                Some(ColumnNoise::Discrete(values))
+            }
+            (DataType::Integer | DataType::Float, _) => {
+                let width = (dist.max? - dist.min?) / NUMERIC_HISTOGRAM_BINS as f64;
+                (width > 0.0).then_some(ColumnNoise::Spread(width))
+            }
+            (_, Histogram::Categorical { frequencies, .. }) => {
+                let total: u64 = frequencies.values().sum();
+                let rare: Vec<&str> = frequencies
+                    .iter()
+                    .filter(|&(_, &count)| (count as f64) < RARE_SHARE * total as f64)
+                    .map(|(value, _)| value.as_str())
+                    .collect();
+                (rare.len() > 1).then_some(ColumnNoise::Categories(rare))
+            }
+            _ => None,
+        }
+    }
+
+    /// `value` with noise of level `noise` added. NULLs and values outside the
+    /// column's kind pass through.
+    pub fn apply<R: Rng + ?Sized>(&self, value: Value<'a>, noise: f64, rng: &mut R) -> Value<'a> {
+        match (self, value) {
+            (ColumnNoise::Binned(bins), Value::Number(number)) => {
+                let bin = bins.partition_point(|&edge| edge <= number).clamp(1, bins.len() - 1);
+                Value::Number(number + jitter(bins[bin] - bins[bin - 1], noise, rng))
+            }
+            (ColumnNoise::Spread(width), Value::Number(number)) => Value::Number(number + jitter(*width, noise, rng)),
+            (ColumnNoise::Discrete(values), Value::Number(number)) if rng.gen_bool(noise) => {
+                let at = values.partition_point(|&v| (v as f64) < number).min(values.len() - 1);
+                let neighbour = match at {
+                    0 => 1,
+                    at if at + 1 == values.len() || rng.gen_bool(0.5) => at - 1,
+                    at => at + 1,
+                };
+                Value::Number(values[neighbour] as f64)
+            }
+            (ColumnNoise::Timestamp, Value::Number(epoch)) => {
+                Value::Number(epoch + jitter(2.0 * TIMESTAMP_WINDOW, noise, rng).round())
+            }
+            (ColumnNoise::Categories(rare), Value::Text(text)) if rare.contains(&text.as_ref()) && rng.gen_bool(noise) => {
+                Value::Text(Cow::Borrowed(rare[rng.gen_range(0..rare.len())]))
+            }
+            (_, value) => value,
+        }
+    }
+}
+
+/// Uniform shift of at most half of `width` times `noise` either way.
+fn jitter<R: Rng + ?Sized>(width: f64, noise: f64, rng: &mut R) -> f64 {
+    let half = 0.5 * width * noise;
+    if half > 0.0 { rng.gen_range(-half..=half) } else { 0.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use crate::math::BinScale;
+
+    fn distribution(histogram: Histogram) -> Distribution {
+        Distribution::new(Some(0.0), Some(100.0), 0, 1000, 10, histogram)
+    }
+
+    #[test]
+    fn test_numbers_stay_near_their_bin() {
+        let dist = distribution(Histogram::Numeric {
+            bins: vec![0.0, 10.0, 100.0],
+            frequencies: vec![500, 500],
+            scale: BinScale::Linear,
+        });
+        let column = Column::new("amount".to_string(), DataType::Float, false, false);
+        let noise = ColumnNoise::for_column(&column, &dist).unwrap();
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let Value::Number(small) = noise.apply(Value::Number(5.0), 1.0, &mut rng) else { panic!() };
+            assert!((0.0..=10.0).contains(&small), "{}", small);
+            let Value::Number(large) = noise.apply(Value::Number(50.0), 0.5, &mut rng) else { panic!() };
+            assert!((27.5..=72.5).contains(&large), "{}", large);
+        }
+        assert_eq!(noise.apply(Value::Number(5.0), 0.0, &mut rng), Value::Number(5.0));
+        assert_eq!(noise.apply(Value::Null, 1.0, &mut rng), Value::Null);
+    }
+
+    #[test]
+    fn test_discrete_values_move_to_a_neighbour() {
+        let dist = distribution(Histogram::Discrete { values: vec![1, 2, 5], frequencies: vec![1, 1, 1] });
+        let column = Column::new("rating".to_string(), DataType::Integer, false, false);
+        let noise = ColumnNoise::for_column(&column, &dist).unwrap();
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            assert_eq!(noise.apply(Value::Number(1.0), 1.0, &mut rng), Value::Number(2.0));
+            assert_eq!(noise.apply(Value::Number(5.0), 1.0, &mut rng), Value::Number(2.0));
+            assert!(matches!(noise.apply(Value::Number(2.0), 1.0, &mut rng), Value::Number(1.0 | 5.0)));
+        }
+    }
+
+    #[test]
+    fn test_only_rare_categories_swap() {
+        let frequencies: BTreeMap<String, u64> =
+            [("common", 9_000), ("usual", 980), ("rare_a", 10), ("rare_b", 5), ("rare_c", 5)]
+                .into_iter()
+                .map(|(value, count)| (value.to_string(), count))
+                .collect();
+        let dist = distribution(Histogram::Categorical { frequencies, truncated: false, long_tail: None });
+        let column = Column::new("city".to_string(), DataType::Text, false, false);
+        let noise = ColumnNoise::for_column(&column, &dist).unwrap();
+        assert_eq!(noise, ColumnNoise::Categories(vec!["rare_a", "rare_b", "rare_c"]));
+
+        let mut rng = rand::thread_rng();
+        let swapped: Vec<Value> = (0..300).map(|_| noise.apply(Value::Text("rare_a".into()), 1.0, &mut rng)).collect();
+        assert!(swapped.iter().any(|v| *v == Value::Text("rare_b".into())));
+        assert!(swapped.iter().all(|v| matches!(v, Value::Text(t) if t.starts_with("rare_"))));
+        assert_eq!(noise.apply(Value::Text("common".into()), 1.0, &mut rng), Value::Text("common".into()));
+    }
+}
@@ -0,0 +1,404 @@
+//! What a [`Synthesizer`] would generate, without generating it: `gen --dry-run`.
+//!
+//! The plan lists tables in execution order with their row counts and, per output
+//! column, where its values come from. Sizes are estimates from the genome alone:
+//! each cell's COPY width is guessed from the column's range, lengths or categories,
+//! and memory is the COPY text held until output plus the primary keys kept for
+//! foreign keys.
+
+use std::fmt;
+use crate::fit::ParametricFamily;
+use crate::genome::DatabaseGenome;
+use crate::math::{Distribution, Histogram};
+use crate::schema::{Column, DataType, Table};
+use crate::semantic::SemanticType;
+use super::keys::{KeyKind, KeyValue};
+use super::Synthesizer;
+
+/// Width assumed for text without recorded lengths or categories.
+const DEFAULT_TEXT_WIDTH: f64 = 16.0;
+
+/// Where a column's values come from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnSource {
+    PrimaryKey,
+    /// References the named table.
+    ForeignKey(String),
+    /// A named generator from the config.
+    Generator(String),
+    Semantic(SemanticType),
+    /// Conditioned on the `table.column` of the parent row.
+    Conditional(String),
+    Sequence,
+    /// A fitted closed form, by family name.
+    Fit(&'static str),
+    /// The histogram, by kind.
+    Histogram(&'static str),
+    /// A default for the column's type, as the source table was empty.
+    TypeDefault,
+    /// No distribution in the genome; generation would fail.
+    Missing,
+}
+
+impl fmt::Display for ColumnSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColumnSource::PrimaryKey => write!(f, "primary key"),
+            ColumnSource::ForeignKey(table) => write!(f, "foreign key -> {}", table),
+            ColumnSource::Generator(name) => write!(f, "generator {}", name),
+            ColumnSource::Semantic(kind) => write!(f, "faker {}", kind),
+            ColumnSource::Conditional(parent) => write!(f, "conditioned on {}", parent),
+            ColumnSource::Sequence => write!(f, "sequence"),
+            ColumnSource::Fit(family) => write!(f, "{} fit", family),
+            ColumnSource::Histogram(kind) => write!(f, "{} histogram", kind),
+            ColumnSource::TypeDefault => write!(f, "type default (empty source table)"),
+            ColumnSource::Missing => write!(f, "MISSING distribution"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedColumn {
+    pub name: String,
+    pub source: ColumnSource,
+    /// Estimated average bytes per COPY cell, separator included.
+    pub width: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedTable {
+    pub name: String,
+    /// Dependency level; tables of one level are generated together.
+    pub level: usize,
+    pub rows: usize,
+    pub columns: Vec<PlannedColumn>,
+    /// Estimated COPY text.
+    pub copy_bytes: u64,
+    /// Estimated memory of the primary keys kept for children.
+    pub key_bytes: u64,
+}
+
+/// Tables in execution order, as [`Synthesizer::plan`] resolves them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenerationPlan {
+    pub tables: Vec<PlannedTable>,
+}
+
+impl GenerationPlan {
+    /// `table.column` of every output column without a distribution.
+    pub fn missing_columns(&self) -> Vec<String> {
+        self.tables
+            .iter()
+            .flat_map(|t| {
+                t.columns
+                    .iter()
+                    .filter(|c| c.source == ColumnSource::Missing)
+                    .map(|c| DatabaseGenome::make_key(&t.name, &c.name))
+            })
+            .collect()
+    }
+
+    pub fn total_rows(&self) -> usize {
+        self.tables.iter().map(|t| t.rows).sum()
+    }
+
+    pub fn copy_bytes(&self) -> u64 {
+        self.tables.iter().map(|t| t.copy_bytes).sum()
+    }
+
+    /// Peak memory estimate: all COPY text is held until output, along with every
+    /// table's primary keys.
+    pub fn memory_bytes(&self) -> u64 {
+        self.tables.iter().map(|t| t.copy_bytes + t.key_bytes).sum()
+    }
+}
+
+impl Synthesizer {
+    /// Resolves what [`Synthesizer::generate`] would do under this config.
+    pub fn plan(&self) -> GenerationPlan {
+        let mut tables = Vec::with_capacity(self.execution_order.len());
+
+        for (level, names) in self.execution_levels.iter().enumerate() {
+            for name in names {
+                let Some(table) = self.genome.get_table(name) else {
+                    continue;
+                };
+                tables.push(self.plan_table(table, level));
+            }
+        }
+
+        GenerationPlan { tables }
+    }
+
+    fn plan_table(&self, table: &Table, level: usize) -> PlannedTable {
+        let rows = self.config.rows_for(&table.name);
+        let semantic_types = self.resolve_semantic_types(table);
+
+        let columns: Vec<PlannedColumn> = self
+            .output_columns(table)
+            .into_iter()
+            .map(|column| {
+                let key = DatabaseGenome::make_key(&table.name, &column.name);
+                let dist = self.genome.distributions.get(&key);
+                let fk = table.foreign_keys.iter().find(|fk| fk.source_col == column.name);
+
+                let source = if column.is_primary_key {
+                    ColumnSource::PrimaryKey
+                } else if let Some(fk) = fk {
+                    ColumnSource::ForeignKey(fk.target_table.clone())
+                } else if let Some(dist) = dist {
+                    if dist.sequence.is_some() {
+                        ColumnSource::Sequence
+                    } else if let Some(name) = self.config.column_generators.get(&key) {
+                        ColumnSource::Generator(name.clone())
+                    } else if let Some(kind) = semantic_types.get(column.name.as_str()) {
+                        ColumnSource::Semantic(*kind)
+                    } else {
+                        match self.genome.conditionals.get(&key) {
+                            Some(conditional) if self.config.fk_conditioning => ColumnSource::Conditional(
+                                DatabaseGenome::make_key(&conditional.parent_table, &conditional.parent_column),
+                            ),
+                            _ => distribution_source(dist),
+                        }
+                    }
+                } else {
+                    ColumnSource::Missing
+                };
+
+                let width = match fk.and_then(|fk| self.genome.get_table(&fk.target_table)) {
+                    Some(parent) => key_width(parent, self.config.rows_for(&parent.name)),
+                    None if column.is_primary_key => key_width(table, rows),
+                    None => cell_width(column, dist),
+                };
+
+                PlannedColumn { name: column.name.clone(), source, width: width + 1.0 }
+            })
+            .collect();
+
+        let copy_bytes = (columns.iter().map(|c| c.width).sum::<f64>() * rows as f64) as u64;
+        PlannedTable {
+            name: table.name.clone(),
+            level,
+            rows,
+            columns,
+            copy_bytes,
+            key_bytes: self.key_bytes(table, rows),
+        }
+    }
+
+    /// Gapless integer keys are kept as a range; anything else one value per kept row.
+    fn key_bytes(&self, table: &Table, rows: usize) -> u64 {
+        let primary_keys = table.primary_keys();
+        match primary_keys.as_slice() {
+            [] => 0,
+            [pk] if KeyKind::for_column(&pk.data_type) == KeyKind::Int => 0,
+            keys => {
+                let kept = self.config.max_parent_keys.map_or(rows, |cap| cap.min(rows));
+                let per_key = std::mem::size_of::<KeyValue>() as f64 + key_width(table, rows);
+                (kept as f64 * keys.len() as f64 * per_key) as u64
+            }
+        }
+    }
+}
+
+/// Average characters of a generated key of `table`.
+fn key_width(table: &Table, rows: usize) -> f64 {
+    match table.primary_keys().first().map(|pk| KeyKind::for_column(&pk.data_type)) {
+        Some(KeyKind::Uuid) => 36.0,
+        _ => digits(rows as f64),
+    }
+}
+
+fn distribution_source(dist: &Distribution) -> ColumnSource {
+    if dist.synthetic_default {
+        return ColumnSource::TypeDefault;
+    }
+    if let Some(fit) = &dist.fit {
+        return ColumnSource::Fit(match fit.family {
+            ParametricFamily::Normal { .. } => "normal",
+            ParametricFamily::LogNormal { .. } => "lognormal",
+            ParametricFamily::Exponential { .. } => "exponential",
+            ParametricFamily::Uniform { .. } => "uniform",
+        });
+    }
+    ColumnSource::Histogram(match dist.histogram {
+        Histogram::Numeric { .. } => "numeric",
+        Histogram::Categorical { .. } => "categorical",
+        Histogram::Semantic { .. } => "semantic",
+        Histogram::Pattern { .. } => "pattern",
+        Histogram::Markov { .. } => "markov",
+        Histogram::Quantile { .. } => "quantile",
+        Histogram::Boolean { .. } => "boolean",
+        Histogram::Discrete { .. } => "discrete",
+    })
+}
+
+/// Estimated average characters of a non-key cell, nulls (`\N`) included.
+fn cell_width(column: &Column, dist: Option<&Distribution>) -> f64 {
+    let Some(dist) = dist else {
+        return DEFAULT_TEXT_WIDTH;
+    };
+
+    let value = match column.data_type {
+        DataType::Boolean => 1.0,
+        DataType::Uuid => 36.0,
+        // `YYYY-MM-DD HH:MM:SS` plus fractional seconds and `+00:00`
+        DataType::Timestamp => 29.0,
+        DataType::Integer | DataType::Float => {
+            let magnitude = dist.min.unwrap_or(0.0).abs().max(dist.max.unwrap_or(0.0).abs());
+            let sign = if dist.min.is_some_and(|min| min < 0.0) { 0.5 } else { 0.0 };
+            // Up to six decimals, trailing zeros dropped
+            let fraction = if column.data_type == DataType::Float { 6.0 } else { 0.0 };
+            digits(magnitude) + sign + fraction
+        }
+        DataType::Text => text_width(dist),
+        // `\\x` and two hex digits per byte of the mid-range length
+        DataType::Bytea => 3.0 + dist.min.unwrap_or(0.0) + dist.max.unwrap_or(0.0),
+        // `D days HH:MM:SS`, fractions aside
+        DataType::Interval => 16.0,
+        DataType::Inet => 13.0,
+        DataType::Cidr => 14.0,
+        DataType::MacAddr => 17.0,
+        // Mean pair length times mean pairs per row, with `, ` between pairs
+        DataType::Hstore => {
+            let pair = text_width(dist);
+            let pairs = dist.pair_counts.as_ref().and_then(|counts| counts.mean()).unwrap_or(1.0);
+            (pair + 2.0) * pairs
+        }
+        // `SRID=4326;POINT(x y)` with full-precision coordinates
+        DataType::Geometry | DataType::Geography => 50.0,
+    };
+
+    let null_rate = dist.null_rate();
+    null_rate * 2.0 + (1.0 - null_rate) * value
+}
+
+fn text_width(dist: &Distribution) -> f64 {
+    if let Some(mean) = dist.lengths.as_ref().and_then(|lengths| lengths.mean()) {
+        return mean;
+    }
+    if let Histogram::Categorical { frequencies, .. } = &dist.histogram {
+        let total: u64 = frequencies.values().sum();
+        if total > 0 {
+            return frequencies.iter().map(|(value, &n)| value.len() as f64 * n as f64).sum::<f64>() / total as f64;
+        }
+    }
+    DEFAULT_TEXT_WIDTH
+}
+
+/// Decimal digits in the integer part of `value`.
+fn digits(value: f64) -> f64 {
+    value.abs().max(1.0).log10().floor() + 1.0
+}
+
+const MIB: f64 = (1 << 20) as f64;
+
+impl fmt::Display for GenerationPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for table in &self.tables {
+            writeln!(
+                f,
+                "level {} {}: {} rows, ~{:.1} MiB",
+                table.level,
+                table.name,
+                table.rows,
+                table.copy_bytes as f64 / MIB
+            )?;
+            for column in &table.columns {
+                writeln!(f, "    {}: {} (~{:.0} B/cell)", column.name, column.source, column.width)?;
+            }
+        }
+
+        writeln!(
+            f,
+            "total: {} tables, {} rows, ~{:.1} MiB of COPY data, ~{:.1} MiB peak memory",
+            self.tables.len(),
+            self.total_rows(),
+            self.copy_bytes() as f64 / MIB,
+            self.memory_bytes() as f64 / MIB
+        )?;
+
+        let missing = self.missing_columns();
+        if !missing.is_empty() {
+            writeln!(f, "missing distributions: {}", missing.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+    use super::*;
+    use crate::math::BinScale;
+    use crate::schema::ForeignKey;
+    use crate::synth::SynthesisConfig;
+
+    fn genome() -> DatabaseGenome {
+        let users = Table::new(
+            "users".to_string(),
+            vec![
+                Column::new("id".to_string(), DataType::Uuid, false, true),
+                Column::new("tier".to_string(), DataType::Text, false, false),
+            ],
+            vec![],
+        );
+        let orders = Table::new(
+            "orders".to_string(),
+            vec![
+                Column::new("id".to_string(), DataType::Integer, false, true),
+                Column::new("user_id".to_string(), DataType::Uuid, false, false),
+                Column::new("amount".to_string(), DataType::Float, true, false),
+                Column::new("note".to_string(), DataType::Text, true, false),
+            ],
+            vec![ForeignKey::new("user_id".to_string(), "users".to_string(), "id".to_string())],
+        );
+
+        let tier = Distribution::new(None, None, 0, 10, 2, Histogram::Categorical {
+            frequencies: BTreeMap::from([("gold".to_string(), 5), ("bronze".to_string(), 5)]),
+            truncated: false,
+            long_tail: None,
+        });
+        let amount = Distribution::new(Some(0.0), Some(250.0), 5, 10, 5, Histogram::Numeric {
+            bins: vec![0.0, 250.0],
+            frequencies: vec![5],
+            scale: BinScale::Linear,
+        });
+        let distributions = HashMap::from([
+            ("users.tier".to_string(), tier),
+            ("orders.amount".to_string(), amount),
+        ]);
+        DatabaseGenome::new(vec![orders, users], distributions)
+    }
+
+    #[test]
+    fn test_plan_lists_tables_in_order() {
+        let config = SynthesisConfig { rows_per_table: 1000, ..SynthesisConfig::default() };
+        let plan = Synthesizer::new(genome(), config).unwrap().plan();
+
+        let names: Vec<(&str, usize)> = plan.tables.iter().map(|t| (t.name.as_str(), t.level)).collect();
+        assert_eq!(names, [("users", 0), ("orders", 1)]);
+
+        let orders = &plan.tables[1];
+        let sources: Vec<&ColumnSource> = orders.columns.iter().map(|c| &c.source).collect();
+        assert_eq!(sources, [
+            &ColumnSource::PrimaryKey,
+            &ColumnSource::ForeignKey("users".to_string()),
+            &ColumnSource::Histogram("numeric"),
+            &ColumnSource::Missing,
+        ]);
+        assert_eq!(plan.missing_columns(), ["orders.note"]);
+
+        // Integer ids up to 1000, uuids, half-null amounts up to 250 and 5 char tiers
+        assert_eq!(orders.columns[0].width, 5.0);
+        assert_eq!(orders.columns[1].width, 37.0);
+        assert_eq!(orders.columns[2].width, 0.5 * 2.0 + 0.5 * 9.0 + 1.0);
+        assert_eq!(plan.tables[0].columns[1].width, 6.0);
+
+        // Only the uuid keys take memory
+        assert_eq!(orders.key_bytes, 0);
+        assert!(plan.tables[0].key_bytes > 36 * 1000);
+        assert_eq!(plan.memory_bytes(), plan.copy_bytes() + plan.tables[0].key_bytes);
+        assert!(plan.to_string().contains("missing distributions: orders.note"));
+    }
+}
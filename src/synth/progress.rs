@@ -0,0 +1,127 @@
+//! Progress reporting during generation.
+//!
+//! The [`Synthesizer`](super::Synthesizer) reports each table's start, rows as they
+//! are produced, and completion to a [`GenerationProgress`]. `ProgressBars` draws
+//! them as indicatif bars with the `progress` feature; library users can plug in their
+//! own reporting.
+
+#[cfg(feature = "progress")]
+use std::collections::HashMap;
+#[cfg(feature = "progress")]
+use std::sync::Mutex;
+use std::sync::Arc;
+#[cfg(feature = "progress")]
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+/// Rows generated between two [`GenerationProgress::rows_generated`] calls for a shard.
+pub const PROGRESS_INTERVAL: usize = 10_000;
+
+/// Receives generation progress. Tables and shards run on worker threads, so calls
+/// for different tables may interleave.
+pub trait GenerationProgress: Send + Sync {
+    /// `table` is about to generate `rows` rows.
+    fn table_started(&self, _table: &str, _rows: usize) {}
+
+    /// Another `rows` rows of `table` are done.
+    fn rows_generated(&self, _table: &str, _rows: usize) {}
+
+    /// Every row of `table` is done.
+    fn table_finished(&self, _table: &str) {}
+}
+
+/// Forwards every event to each reporter in turn, e.g. progress bars and metrics.
+impl GenerationProgress for Vec<Arc<dyn GenerationProgress>> {
+    fn table_started(&self, table: &str, rows: usize) {
+        self.iter().for_each(|p| p.table_started(table, rows));
+    }
+
+    fn rows_generated(&self, table: &str, rows: usize) {
+        self.iter().for_each(|p| p.rows_generated(table, rows));
+    }
+
+    fn table_finished(&self, table: &str) {
+        self.iter().for_each(|p| p.table_finished(table));
+    }
+}
+
+/// One indicatif bar per table with row count, throughput and ETA.
+#[cfg(feature = "progress")]
+pub struct ProgressBars {
+    multi: MultiProgress,
+    bars: Mutex<HashMap<String, ProgressBar>>,
+}
+
+#[cfg(feature = "progress")]
+impl ProgressBars {
+    /// Bars drawn on stderr; hidden when stderr is not a terminal.
+    pub fn new() -> Self {
+        Self::with_draw_target(ProgressDrawTarget::stderr())
+    }
+
+    pub fn with_draw_target(target: ProgressDrawTarget) -> Self {
+        Self {
+            multi: MultiProgress::with_draw_target(target),
+            bars: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn bar(&self, table: &str) -> Option<ProgressBar> {
+        self.bars.lock().ok()?.get(table).cloned()
+    }
+}
+
+#[cfg(feature = "progress")]
+impl Default for ProgressBars {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "progress")]
+impl GenerationProgress for ProgressBars {
+    fn table_started(&self, table: &str, rows: usize) {
+        let bar = self.multi.add(ProgressBar::new(rows as u64));
+        bar.set_style(
+            ProgressStyle::default_bar()
+                .template("{prefix:>20} [{bar:30.cyan/blue}] {pos}/{len} rows {per_sec} ETA {eta}")
+                .unwrap_or_else(|_| ProgressStyle::default_bar())
+                .progress_chars("=> "),
+        );
+        bar.set_prefix(table.to_string());
+        if let Ok(mut bars) = self.bars.lock() {
+            bars.insert(table.to_string(), bar);
+        }
+    }
+
+    fn rows_generated(&self, table: &str, rows: usize) {
+        if let Some(bar) = self.bar(table) {
+            bar.inc(rows as u64);
+        }
+    }
+
+    fn table_finished(&self, table: &str) {
+        if let Some(bar) = self.bar(table) {
+            bar.finish();
+        }
+    }
+}
+
+#[cfg(all(test, feature = "progress"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bars_track_rows() {
+        let progress = ProgressBars::with_draw_target(ProgressDrawTarget::hidden());
+        progress.table_started("users", 25_000);
+        progress.rows_generated("users", PROGRESS_INTERVAL);
+        progress.rows_generated("users", 15_000);
+        // Unknown tables are ignored
+        progress.rows_generated("orders", 1);
+        progress.table_finished("users");
+
+        let bar = progress.bar("users").unwrap();
+        assert_eq!(bar.position(), 25_000);
+        assert!(bar.is_finished());
+    }
+}
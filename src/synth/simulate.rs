@@ -0,0 +1,210 @@
+//! Time-window simulation: event tables generated as a run of days rather than as
+//! independent draws from the source's range.
+//!
+//! An event table is one with a timestamp column carrying a seasonality profile (its
+//! event column). Each day of the window gets the source's events per day, scaled by
+//! the day's weekday weight, and a table's rows are split among the days in that
+//! proportion. Within a day, row `j` of `n` is placed at the `(j + u) / n` quantile of
+//! the hour-of-day profile for a uniform `u`, so event times advance with the row
+//! index while following the daily rhythm, and every row is placed without looking at
+//! the others.
+
+use crate::genome::DatabaseGenome;
+use crate::math::Distribution;
+use crate::schema::{Column, DataType, Table};
+
+const SECONDS_PER_DAY: i64 = 86_400;
+const SECONDS_PER_HOUR: f64 = 3_600.0;
+
+/// The days events are spread over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    /// First day, in days since the epoch.
+    pub start_day: i64,
+    pub days: u32,
+}
+
+impl Window {
+    /// The `days` days following the day of `latest`, in epoch seconds.
+    pub fn after(latest: f64, days: u32) -> Self {
+        Self { start_day: (latest.floor() as i64).div_euclid(SECONDS_PER_DAY) + 1, days }
+    }
+
+    /// Expected events on each day of the window for an event column: the source's
+    /// non-null values per day over its range (at least one day), scaled by the
+    /// weekday's share of the profile. The monthly trend isn't extrapolated.
+    pub fn daily_volume(&self, dist: &Distribution) -> Vec<f64> {
+        let events = dist.total_count.saturating_sub(dist.null_count) as f64;
+        let span_days = match (dist.min, dist.max) {
+            (Some(min), Some(max)) => ((max - min) / SECONDS_PER_DAY as f64).max(1.0),
+            _ => 1.0,
+        };
+        let weekdays = dist.seasonality.as_ref().map(|s| s.day_of_week.as_slice()).unwrap_or_default();
+        let mean = weekdays.iter().sum::<u64>() as f64 / 7.0;
+
+        (0..self.days as i64)
+            .map(|offset| {
+                // 1970-01-01 was a Thursday (index 3 from Monday)
+                let weekday = (self.start_day + offset + 3).rem_euclid(7) as usize;
+                let factor = match weekdays.get(weekday) {
+                    Some(&count) if mean > 0.0 => count as f64 / mean,
+                    _ => 1.0,
+                };
+                events / span_days * factor
+            })
+            .collect()
+    }
+}
+
+/// Event times of one table's rows over a [`Window`], increasing with the row index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventClock {
+    /// Epoch seconds of the window's first midnight.
+    start: i64,
+
+    /// Day `d` holds rows `bounds[d]..bounds[d + 1]`.
+    bounds: Vec<usize>,
+
+    /// Running share of events by the end of each UTC hour, ending at 1.
+    hours: Vec<f64>,
+}
+
+impl EventClock {
+    /// Splits `rows` among the window's days by `volume` (one weight per day, as from
+    /// [`Window::daily_volume`]) and takes the time of day from `dist`'s hour-of-day
+    /// profile, or spreads it evenly without one.
+    pub fn new(window: &Window, volume: &[f64], dist: &Distribution, rows: usize) -> Self {
+        let total: f64 = volume.iter().sum();
+        let mut bounds = Vec::with_capacity(volume.len() + 1);
+        bounds.push(0);
+        let mut running = 0.0;
+        for (day, weight) in volume.iter().enumerate() {
+            running += weight;
+            let end = if total > 0.0 {
+                (rows as f64 * running / total).round() as usize
+            } else {
+                rows * (day + 1) / volume.len()
+            };
+            bounds.push(end.min(rows));
+        }
+        if let Some(last) = bounds.last_mut() {
+            *last = rows;
+        }
+
+        let hour_counts = dist.seasonality.as_ref().map(|s| s.hour_of_day.as_slice()).unwrap_or_default();
+        let hour_total: u64 = hour_counts.iter().sum();
+        let hours = match hour_total {
+            0 => (1..=24).map(|hour| hour as f64 / 24.0).collect(),
+            _ => hour_counts
+                .iter()
+                .scan(0u64, |running, &count| {
+                    *running += count;
+                    Some(*running as f64 / hour_total as f64)
+                })
+                .collect(),
+        };
+
+        Self { start: window.start_day * SECONDS_PER_DAY, bounds, hours }
+    }
+
+    /// Event time of row `row` in epoch seconds, with `u` in `[0, 1)` placing it among
+    /// the day's other rows. `None` past the last row.
+    pub fn timestamp(&self, row: usize, u: f64) -> Option<f64> {
+        let day = self.bounds.partition_point(|&start| start <= row).checked_sub(1)?;
+        let end = *self.bounds.get(day + 1)?;
+        let start = self.bounds[day];
+
+        let quantile = ((row - start) as f64 + u) / (end - start) as f64;
+        let hour = self.hours.partition_point(|&share| share <= quantile).min(self.hours.len() - 1);
+        let before = if hour == 0 { 0.0 } else { self.hours[hour - 1] };
+        let within = match self.hours[hour] - before {
+            width if width > 0.0 => ((quantile - before) / width).clamp(0.0, 1.0),
+            _ => 0.0,
+        };
+        let seconds = (hour as f64 + within) * SECONDS_PER_HOUR;
+        Some((self.start + day as i64 * SECONDS_PER_DAY) as f64 + seconds.floor().min(SECONDS_PER_DAY as f64 - 1.0))
+    }
+}
+
+/// The event column of `table`: its timestamp column with a seasonality profile and
+/// the most non-null values, the first on ties. Keys are left out.
+pub fn event_column<'a>(genome: &'a DatabaseGenome, table: &'a Table) -> Option<(&'a Column, &'a Distribution)> {
+    let mut best: Option<(&Column, &Distribution)> = None;
+    for column in &table.columns {
+        if column.data_type != DataType::Timestamp
+            || column.is_primary_key
+            || table.foreign_keys.iter().any(|fk| fk.source_col == column.name)
+        {
+            continue;
+        }
+        let Some(dist) = genome.distributions.get(&DatabaseGenome::make_key(&table.name, &column.name)) else {
+            continue;
+        };
+        let events = |dist: &Distribution| dist.total_count.saturating_sub(dist.null_count);
+        if dist.seasonality.is_some() && best.is_none_or(|(_, best)| events(dist) > events(best)) {
+            best = Some((column, dist));
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{BinScale, Histogram};
+    use crate::seasonality::Seasonality;
+
+    /// Monday 2024-01-01 00:00 UTC.
+    const MONDAY: f64 = 1_704_067_200.0;
+
+    /// 700 events over 7 days, weekdays only, all between 09:00 and 11:00.
+    fn office_events() -> Distribution {
+        let histogram = Histogram::Numeric {
+            bins: vec![MONDAY, MONDAY + 7.0 * 86_400.0],
+            frequencies: vec![700],
+            scale: BinScale::Linear,
+        };
+        let mut dist = Distribution::new(Some(MONDAY), Some(MONDAY + 7.0 * 86_400.0), 0, 700, 700, histogram);
+        let mut hour_of_day = vec![0; 24];
+        hour_of_day[9] = 350;
+        hour_of_day[10] = 350;
+        dist.seasonality = Some(Seasonality {
+            hour_of_day,
+            day_of_week: vec![140, 140, 140, 140, 140, 0, 0],
+            monthly: Default::default(),
+        });
+        dist
+    }
+
+    #[test]
+    fn test_daily_volume_follows_weekdays() {
+        let dist = office_events();
+        // Starts on the Monday after the source's last day
+        let window = Window::after(MONDAY + 6.5 * 86_400.0, 14);
+        assert_eq!(window.start_day, (MONDAY as i64 + 7 * 86_400) / 86_400);
+
+        let volume = window.daily_volume(&dist);
+        assert_eq!(volume.len(), 14);
+        assert!((volume[0] - 140.0).abs() < 1e-9);
+        assert_eq!(volume[5], 0.0);
+        assert!((volume.iter().sum::<f64>() - 1400.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_clock_advances_within_business_hours() {
+        let dist = office_events();
+        let window = Window::after(MONDAY + 6.5 * 86_400.0, 7);
+        let clock = EventClock::new(&window, &window.daily_volume(&dist), &dist, 50);
+
+        let mut rng = rand::thread_rng();
+        let times: Vec<f64> = (0..50).map(|row| clock.timestamp(row, rand::Rng::r#gen(&mut rng)).unwrap()).collect();
+        assert!(times.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", times);
+        let start = window.start_day as f64 * 86_400.0;
+        for time in &times {
+            let day = ((time - start) / 86_400.0).floor();
+            let hour = (time - start - day * 86_400.0) / 3_600.0;
+            assert!((0.0..5.0).contains(&day) && (9.0..11.0).contains(&hour), "day {} hour {}", day, hour);
+        }
+        assert_eq!(clock.timestamp(50, 0.5), None);
+    }
+}
@@ -1,27 +1,375 @@
-use std::collections::HashMap;
-use crate::math::{Distribution, Histogram};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Write;
+use chrono::{Datelike, Timelike};
+use crate::genome::DatabaseGenome;
+use crate::interval::write_interval;
+use crate::math::{discrete_quantile, locate_in_mixture, BinScale, Distribution, Histogram, LongTail, MixtureDraw, Outliers};
+use crate::schema::{Column, DataType};
+use crate::semantic::{classify_by_name, SemanticType};
+use crate::sequence::SequenceModel;
+use crate::synth::alias::AliasSampler;
+use crate::synth::generators::ValueGenerator;
+use crate::synth::keys::{KeyKind, KeyPool, KeyValue};
 use rand::prelude::*;
 use rand::Rng;
 use anyhow::{Context, Result};
 
-pub fn synthesize_value(dist: &Distribution, rng: &mut ThreadRng, quantile: Option<f64>) -> Result<String> {
+/// One generated cell. Categorical values borrow from the genome; [`write_value`]
+/// formats it straight into the COPY buffer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value<'a> {
+    Null,
+    Number(f64),
+    Text(Cow<'a, str>),
+}
+
+impl From<String> for Value<'_> {
+    fn from(text: String) -> Self {
+        Value::Text(Cow::Owned(text))
+    }
+}
+
+/// Draws one cell from `dist`. `sampler` is the distribution's precomputed alias
+/// table, used for weighted draws when no copula `quantile` is given; without it the
+/// frequencies are scanned.
+pub fn synthesize_value<'a, R: Rng + ?Sized>(
+    dist: &'a Distribution,
+    sampler: Option<&'a AliasSampler>,
+    rng: &mut R,
+    quantile: Option<f64>,
+) -> Result<Value<'a>> {
 
     if should_generate_null(dist, rng) {
-        return Ok("\\N".to_string()); 
+        return Ok(Value::Null);
+    }
+
+    // Point masses and the continuous part form one mixture, so a copula quantile
+    // still orders values across both
+    let mut quantile = quantile;
+    if !dist.point_masses.is_empty() {
+        let q = quantile.unwrap_or_else(|| rng.gen_range(0.0..1.0));
+        let cdf = |x: f64| match &dist.fit {
+            Some(fit) => fit.family.cdf(x),
+            None => dist.histogram_cdf(x).unwrap_or(0.0),
+        };
+        match locate_in_mixture(&dist.point_masses, q, cdf) {
+            MixtureDraw::PointMass(value) => return Ok(Value::Number(value)),
+            MixtureDraw::Continuous(q) => quantile = Some(q),
+        }
+    }
+
+    // Timestamps keep their calendar shape. A copula quantile dictates the day, and the
+    // time of day still follows the profile
+    if let (Some(seasonality), Some(min), Some(max)) = (&dist.seasonality, dist.min, dist.max) {
+        return match quantile {
+            None => Ok(Value::Number(seasonality.sample(min, max, rng))),
+            Some(_) => match synthesize_from_shape(dist, sampler, rng, quantile)? {
+                Value::Number(epoch_seconds) => {
+                    Ok(Value::Number(seasonality.resample_time_of_day(epoch_seconds, min, max, rng)))
+                }
+                other => Ok(other),
+            },
+        };
+    }
+
+    synthesize_from_shape(dist, sampler, rng, quantile)
+}
+
+/// Draws a non-null value from the fitted family or the histogram of `dist`.
+fn synthesize_from_shape<'a, R: Rng + ?Sized>(
+    dist: &'a Distribution,
+    sampler: Option<&'a AliasSampler>,
+    rng: &mut R,
+    quantile: Option<f64>,
+) -> Result<Value<'a>> {
+    // A fitted closed form takes precedence over the binned histogram
+    if let Some(fit) = &dist.fit {
+        let q = quantile.unwrap_or_else(|| rng.gen_range(0.0..1.0));
+        return Ok(Value::Number(fit.family.inverse_cdf(q)));
     }
 
     //Generate non-null value based on histogram type
     match &dist.histogram {
+        Histogram::Categorical { frequencies, long_tail: Some(tail), .. } => {
+            let tracked: u64 = frequencies.values().sum();
+            if tail.count > 0 && rng.gen_range(0..tracked + tail.count) >= tracked {
+                Ok(generate_with_length(dist, rng, |rng| synthesize_tail_value(tail, rng)).into())
+            } else {
+                Ok(Value::Text(Cow::Borrowed(synthesize_categorical(frequencies, sampler, rng)?)))
+            }
+        }
         Histogram::Categorical { frequencies, .. } => {
-            synthesize_categorical(frequencies, rng)
+            Ok(Value::Text(Cow::Borrowed(synthesize_categorical(frequencies, sampler, rng)?)))
+        }
+        Histogram::Numeric { bins, frequencies, scale } => {
+            if quantile.is_none()
+                && let Some(AliasSampler::Indexed(table)) = sampler
+                && bins.len() == frequencies.len() + 1
+            {
+                return Ok(Value::Number(sample_in_bin(bins, table.sample(rng), *scale, rng)));
+            }
+            Ok(Value::Number(synthesize_numeric(bins, frequencies, *scale, rng, quantile)?))
+        }
+        Histogram::Semantic { semantic_type } => {
+            Ok(generate_with_length(dist, rng, |rng| super::faker::generate(*semantic_type, rng)).into())
+        }
+        Histogram::Pattern { pattern } => {
+            Ok(generate_with_length(dist, rng, |rng| pattern.generate(rng)).into())
+        }
+        Histogram::Markov { model } => {
+            Ok(generate_with_length(dist, rng, |rng| model.generate(rng)).into())
+        }
+        Histogram::Quantile { digest } => {
+            let q = quantile.unwrap_or_else(|| rng.gen_range(0.0..1.0));
+            Ok(Value::Number(digest.quantile(q).unwrap_or(0.0)))
+        }
+        Histogram::Boolean { bernoulli } => {
+            Ok(Value::Text(Cow::Borrowed(if bernoulli.sample(rng) { "t" } else { "f" })))
+        }
+        Histogram::Discrete { values, frequencies } => {
+            if quantile.is_none()
+                && let Some(AliasSampler::Indexed(table)) = sampler
+                && let Some(&value) = values.get(table.sample(rng))
+            {
+                return Ok(Value::Number(value as f64));
+            }
+            let q = quantile.unwrap_or_else(|| rng.gen_range(0.0..1.0));
+            let value = discrete_quantile(values, frequencies, q).context("Discrete histogram has no values")?;
+            Ok(Value::Number(value as f64))
+        }
+    }
+}
+
+/// A value beyond the clipping bounds of a winsorized column, drawn with the share of
+/// non-null values the scan clipped on each side; `None` for the rest. Outliers are
+/// spread uniformly between a bound and the true min or max. A copula `quantile` picks
+/// the tail, so outliers stay correlated with the other columns.
+pub fn synthesize_outlier<R: Rng + ?Sized>(
+    dist: &Distribution,
+    outliers: &Outliers,
+    rng: &mut R,
+    quantile: Option<f64>,
+) -> Option<f64> {
+    let (Some(min), Some(max)) = (dist.min, dist.max) else {
+        return None;
+    };
+    let non_null = 1.0 - dist.null_rate();
+    let below = outliers.below_share * non_null;
+    let above = outliers.above_share * non_null;
+
+    let q = quantile.unwrap_or_else(|| rng.gen_range(0.0..1.0));
+    if q < below {
+        Some(min + q / below * (outliers.lower - min))
+    } else if q >= 1.0 - above {
+        Some(outliers.upper + (q - (1.0 - above)) / above * (max - outliers.upper))
+    } else {
+        None
+    }
+}
+
+/// Keeps a generated number inside `column`'s type bounds, at zero or above when no
+/// sampled value was negative, whole for integer columns and in cents for money ones.
+pub fn clamp_to_domain<'a>(value: Value<'a>, dist: &Distribution, column: &Column) -> Value<'a> {
+    let Value::Number(mut number) = value else {
+        return value;
+    };
+    if column.data_type == DataType::Integer {
+        number = number.round();
+    }
+    if column.money {
+        number = (number * 100.0).round() / 100.0;
+    }
+    if dist.non_negative {
+        number = number.max(0.0);
+    }
+    if let Some(bounds) = &column.bounds {
+        number = bounds.clamp(number);
+    }
+    Value::Number(number)
+}
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// Random bytes for a bytea column, as many as the sampled `length`, in COPY text
+/// form (`\\x` and hex digits, the backslash escaped for COPY).
+pub fn synthesize_bytes<'a, R: Rng + ?Sized>(length: Value<'a>, rng: &mut R) -> Value<'a> {
+    let Value::Number(length) = length else {
+        return length;
+    };
+    let length = length.round().max(0.0) as usize;
+    let mut text = String::with_capacity(3 + 2 * length);
+    text.push_str("\\\\x");
+    for _ in 0..length {
+        let byte: u8 = rng.r#gen();
+        text.push(HEX_DIGITS[(byte >> 4) as usize] as char);
+        text.push(HEX_DIGITS[(byte & 0xf) as usize] as char);
+    }
+    text.into()
+}
+
+/// An address within the prefix drawn for a network column; see
+/// [`address_in_prefix`](crate::network::address_in_prefix).
+pub fn synthesize_address<'a, R: Rng + ?Sized>(prefix: Value<'a>, data_type: &DataType, rng: &mut R) -> Result<Value<'a>> {
+    match prefix {
+        Value::Text(prefix) => Ok(crate::network::address_in_prefix(data_type, &prefix, rng)?.into()),
+        other => Ok(other),
+    }
+}
+
+/// A point within the bounding box drawn for a geometry column; see
+/// [`point_in_box`](crate::geometry::point_in_box).
+pub fn synthesize_point<'a, R: Rng + ?Sized>(bounds: Value<'a>, rng: &mut R) -> Result<Value<'a>> {
+    match bounds {
+        Value::Text(bounds) => Ok(crate::geometry::point_in_box(&bounds, rng)?.into()),
+        other => Ok(other),
+    }
+}
+
+/// Draws per pair of an hstore value before settling for fewer pairs.
+const PAIR_DRAW_ATTEMPTS: usize = 4;
+
+/// An hstore value for a column whose categories are single pairs: as many pairs as a
+/// count drawn from the profiled ones, `first` among them, with distinct keys. Written
+/// in COPY text form.
+pub fn synthesize_hstore<'a, R: Rng + ?Sized>(
+    first: Value<'a>,
+    dist: &'a Distribution,
+    sampler: Option<&'a AliasSampler>,
+    rng: &mut R,
+) -> Result<Value<'a>> {
+    let Value::Text(first) = first else {
+        return Ok(first);
+    };
+    let count = dist.pair_counts.as_ref().and_then(|counts| counts.sample(rng)).unwrap_or(1);
+
+    let mut keys = HashSet::new();
+    let mut pairs = Vec::with_capacity(count);
+    let mut next = Some(first);
+    // Repeated keys are redrawn, a bounded number of times
+    for _ in 0..count * PAIR_DRAW_ATTEMPTS {
+        if pairs.len() == count {
+            break;
         }
-        Histogram::Numeric { bins, frequencies } => {
-            synthesize_numeric(bins, frequencies, rng, quantile)
+        let pair = match next.take() {
+            Some(pair) => pair,
+            None => match synthesize_value(dist, sampler, rng, None)? {
+                Value::Text(pair) => pair,
+                _ => continue,
+            },
+        };
+        if let Some(key) = crate::hstore::pair_key(&pair)
+            && keys.insert(key)
+        {
+            pairs.push(pair);
         }
     }
+    Ok(crate::output::escape_copy_text(&pairs.join(", ")).into_owned().into())
 }
 
-fn should_generate_null(dist: &Distribution, rng: &mut ThreadRng) -> bool {
+/// Next value of a sequence column: `previous` plus a sampled gap. NULLs don't advance
+/// the sequence.
+pub fn synthesize_sequence<R: Rng + ?Sized>(
+    dist: &Distribution,
+    sequence: &SequenceModel,
+    previous: &mut Option<f64>,
+    rng: &mut R,
+) -> Value<'static> {
+    advance_sequence(dist, sequence, previous, rng).map_or(Value::Null, Value::Number)
+}
+
+/// [`synthesize_sequence`] as a plain number; `None` for a NULL.
+pub fn advance_sequence<R: Rng + ?Sized>(
+    dist: &Distribution,
+    sequence: &SequenceModel,
+    previous: &mut Option<f64>,
+    rng: &mut R,
+) -> Option<f64> {
+    if should_generate_null(dist, rng) {
+        return None;
+    }
+    let value = sequence.next(*previous, rng);
+    *previous = Some(value);
+    Some(value)
+}
+
+/// Picks the faker generator for a column, if any: an explicit entry in
+/// `overrides` (keyed `table.column`) wins, then a column-name heuristic for
+/// text columns whose histogram would otherwise replay source strings.
+pub fn resolve_semantic_type(
+    table: &str,
+    column: &Column,
+    dist: &Distribution,
+    overrides: &HashMap<String, SemanticType>,
+    infer: bool,
+) -> Option<SemanticType> {
+    if let Some(kind) = overrides.get(&DatabaseGenome::make_key(table, &column.name)) {
+        return Some(*kind);
+    }
+
+    let replays_text = column.data_type == DataType::Text
+        && matches!(dist.histogram, Histogram::Categorical { .. });
+
+    if infer && replays_text {
+        return classify_by_name(&column.name);
+    }
+
+    None
+}
+
+/// Like [`synthesize_value`] but draws non-null values from a faker generator.
+pub fn synthesize_semantic<R: Rng + ?Sized>(
+    dist: &Distribution,
+    semantic_type: SemanticType,
+    rng: &mut R,
+) -> Value<'static> {
+    if should_generate_null(dist, rng) {
+        return Value::Null;
+    }
+    generate_with_length(dist, rng, |rng| super::faker::generate(semantic_type, rng)).into()
+}
+
+/// Like [`synthesize_value`] but draws non-null values from a registered generator.
+pub fn synthesize_custom<R: Rng>(
+    dist: &Distribution,
+    generator: &dyn ValueGenerator,
+    rng: &mut R,
+) -> Value<'static> {
+    if should_generate_null(dist, rng) {
+        return Value::Null;
+    }
+    generate_with_length(dist, rng, |rng| generator.generate(rng)).into()
+}
+
+/// Candidates drawn per value when steering text generators towards a profiled length.
+const LENGTH_MATCH_ATTEMPTS: usize = 8;
+
+/// Runs a text generator, keeping the candidate whose length is closest to one drawn
+/// from the column's profiled length distribution.
+fn generate_with_length<R: Rng + ?Sized>(
+    dist: &Distribution,
+    rng: &mut R,
+    mut generate: impl FnMut(&mut R) -> String,
+) -> String {
+    let Some(target) = dist.lengths.as_ref().and_then(|l| l.sample(rng)) else {
+        return generate(rng);
+    };
+
+    let mut best = generate(rng);
+    for _ in 1..LENGTH_MATCH_ATTEMPTS {
+        let best_distance = best.chars().count().abs_diff(target);
+        if best_distance == 0 {
+            break;
+        }
+        let candidate = generate(rng);
+        if candidate.chars().count().abs_diff(target) < best_distance {
+            best = candidate;
+        }
+    }
+    best
+}
+
+fn should_generate_null<R: Rng + ?Sized>(dist: &Distribution, rng: &mut R) -> bool {
     if dist.total_count == 0 {
         return false;
     }
@@ -32,12 +380,17 @@ fn should_generate_null(dist: &Distribution, rng: &mut ThreadRng) -> bool {
     rng.gen_bool(null_probability)
 }
 
-fn synthesize_categorical(
-    frequencies: &HashMap<String, u64>,
-    rng: &mut ThreadRng,
-) -> Result<String> {
+fn synthesize_categorical<'a, R: Rng + ?Sized>(
+    frequencies: &'a BTreeMap<String, u64>,
+    sampler: Option<&'a AliasSampler>,
+    rng: &mut R,
+) -> Result<&'a str> {
     if frequencies.is_empty() {
-        return Ok("unknown".to_string());
+        return Ok("unknown");
+    }
+
+    if let Some(AliasSampler::Categorical { values, table }) = sampler {
+        return Ok(&values[table.sample(rng)]);
     }
 
     // Calculate total weight
@@ -47,8 +400,7 @@ fn synthesize_categorical(
         // Fallback: uniform selection if all frequencies are 0
         let keys: Vec<_> = frequencies.keys().collect();
         return Ok(keys.choose(rng)
-            .map(|s| (*s).clone())
-            .unwrap_or_else(|| "unknown".to_string()));
+            .map_or("unknown", |s| s.as_str()));
     }
 
     // Weighted random selection
@@ -56,25 +408,42 @@ fn synthesize_categorical(
 
     for (value, &weight) in frequencies {
         if random_weight < weight {
-            return Ok(value.clone());
+            return Ok(value);
         }
         random_weight -= weight;
     }
 
     // Fallback (shouldn't reach here due to mathematics, but handle gracefully)
     Ok(frequencies.keys().next()
-        .map(|s| s.clone())
-        .unwrap_or_else(|| "unknown".to_string()))
+        .map_or("unknown", String::as_str))
 }
 
-fn synthesize_numeric(
+/// Length of tail tokens when the tail has no inferred pattern.
+const TAIL_TOKEN_LENGTH: usize = 8;
+
+/// A value outside the heavy hitters: from the tail's faker generator or pattern when
+/// known, otherwise a random lowercase token.
+fn synthesize_tail_value<R: Rng + ?Sized>(tail: &LongTail, rng: &mut R) -> String {
+    if let Some(kind) = tail.semantic_type {
+        return super::faker::generate(kind, rng);
+    }
+    match &tail.pattern {
+        Some(pattern) => pattern.generate(rng),
+        None => (0..TAIL_TOKEN_LENGTH)
+            .map(|_| rng.gen_range(b'a'..=b'z') as char)
+            .collect(),
+    }
+}
+
+fn synthesize_numeric<R: Rng + ?Sized>(
     bins: &[f64],
     frequencies: &[u64],
-    rng: &mut ThreadRng,
+    scale: BinScale,
+    rng: &mut R,
     quantile: Option<f64>,
-) -> Result<String> {
+) -> Result<f64> {
     if bins.len() < 2 || frequencies.is_empty() {
-        return Ok("0".to_string());
+        return Ok(0.0);
     }
 
     // Step 1: Select bin via weighted sampling
@@ -84,21 +453,27 @@ fn synthesize_numeric(
         // No samples - return midpoint of first bin
         if bins.len() >= 2 {
             let midpoint = (bins[0] + bins[1]) / 2.0;
-            return Ok(format_numeric(midpoint));
+            return Ok(midpoint);
         }
-        return Ok("0".to_string());
+        return Ok(0.0);
     }
 
     let value = if let Some(q) = quantile {
-        inverse_transform_sample(bins, frequencies, q, total_weight)?
+        inverse_transform_sample(bins, frequencies, scale, q, total_weight)?
     } else {
-        weighted_random_sample(bins, frequencies, rng, total_weight)
+        weighted_random_sample(bins, frequencies, scale, rng, total_weight)
     };
 
-    Ok(format_numeric(value))
+    Ok(value)
 }
 
-fn inverse_transform_sample(bins: &[f64], frequencies: &[u64], quantile: f64, total_weight: u64) -> Result<f64> {
+fn inverse_transform_sample(
+    bins: &[f64],
+    frequencies: &[u64],
+    scale: BinScale,
+    quantile: f64,
+    total_weight: u64,
+) -> Result<f64> {
     let target_cumulative = quantile * total_weight as f64;
     let mut cumulative = 0.0;
 
@@ -115,7 +490,7 @@ fn inverse_transform_sample(bins: &[f64], frequencies: &[u64], quantile: f64, to
             }
 
             let position_in_bin = (target_cumulative - prev_cumulative) / frequency as f64;
-            let value = bin_min + position_in_bin * (bin_max - bin_min);
+            let value = scale.interpolate(bin_min, bin_max, position_in_bin, bins[0]);
 
             return Ok(value.clamp(bin_min, bin_max));
         }
@@ -124,10 +499,11 @@ fn inverse_transform_sample(bins: &[f64], frequencies: &[u64], quantile: f64, to
     Ok(bins[bins.len() - 1])
 }
 
-fn weighted_random_sample(
+fn weighted_random_sample<R: Rng + ?Sized>(
     bins: &[f64],
     frequencies: &[u64],
-    rng: &mut ThreadRng,
+    scale: BinScale,
+    rng: &mut R,
     total_weight: u64,
 ) -> f64 {
     let mut random_weight = rng.gen_range(0..total_weight);
@@ -141,57 +517,159 @@ fn weighted_random_sample(
         random_weight -= weight;
     }
 
-    let bin_min = bins[selected_bin_idx];
-    let bin_max = bins[selected_bin_idx + 1];
+    sample_in_bin(bins, selected_bin_idx, scale, rng)
+}
+
+/// A value spread within bin `index` the way `scale` spaces the edges.
+fn sample_in_bin<R: Rng + ?Sized>(bins: &[f64], index: usize, scale: BinScale, rng: &mut R) -> f64 {
+    let bin_min = bins[index];
+    let bin_max = bins[index + 1];
 
-    rng.gen_range(bin_min..bin_max)
+    match scale {
+        BinScale::Linear => rng.gen_range(bin_min..bin_max),
+        BinScale::Log => scale.interpolate(bin_min, bin_max, rng.gen_range(0.0..1.0), bins[0]),
+    }
 }
 
-fn format_numeric(value: f64) -> String {
-    // Check if value is effectively an integer
-    if value.fract().abs() < 1e-9 && value.abs() < i64::MAX as f64 {
-        format!("{}", value as i64)
-    } else {
-        // Format with reasonable precision
-        format!("{:.6}", value).trim_end_matches('0').trim_end_matches('.').to_string()
+/// Appends `value` to a COPY text buffer. Timestamp columns are profiled as epoch
+/// seconds and written as UTC timestamp literals, interval columns as seconds and
+/// written as interval literals.
+pub fn write_value(out: &mut Vec<u8>, value: &Value, data_type: &DataType) {
+    let timestamp = *data_type == DataType::Timestamp;
+    let interval = *data_type == DataType::Interval;
+    match value {
+        Value::Null => out.extend_from_slice(b"\\N"),
+        Value::Number(number) if timestamp => write_timestamp(out, *number),
+        Value::Number(number) if interval => write_interval(out, *number),
+        Value::Number(number) => write_numeric(out, *number),
+        Value::Text(text) => match text.parse::<f64>() {
+            Ok(epoch) if timestamp => write_timestamp(out, epoch),
+            Ok(seconds) if interval => write_interval(out, seconds),
+            _ => out.extend_from_slice(text.as_bytes()),
+        },
     }
 }
 
-pub fn synthesize_primary_key(
-    data_type: &crate::schema::DataType,
-    counter: &mut i64,
-) -> String {
-    use crate::schema::DataType;
+/// Values within this of a whole number are written as integers.
+const INTEGER_TOLERANCE: f64 = 1e-9;
 
-    match data_type {
-        DataType::Integer => {
-            *counter += 1;
-            counter.to_string()
+/// Magnitudes below this are written with up to six decimals; larger ones (and
+/// non-finite values) in shortest round-trip form.
+const FIXED_POINT_LIMIT: f64 = 1e12;
+
+const MICROS: f64 = 1e6;
+
+fn is_whole(value: f64) -> bool {
+    value.fract().abs() < INTEGER_TOLERANCE && value.abs() < i64::MAX as f64
+}
+
+fn write_numeric(out: &mut Vec<u8>, value: f64) {
+    if is_whole(value) {
+        out.extend_from_slice(itoa::Buffer::new().format(value as i64).as_bytes());
+        return;
+    }
+    if !value.is_finite() || value.abs() >= FIXED_POINT_LIMIT {
+        out.extend_from_slice(ryu::Buffer::new().format(value).as_bytes());
+        return;
+    }
+
+    let micros = (value.abs() * MICROS).round() as u64;
+    if value < 0.0 && micros > 0 {
+        out.push(b'-');
+    }
+    out.extend_from_slice(itoa::Buffer::new().format(micros / MICROS as u64).as_bytes());
+    let fraction = (micros % MICROS as u64) as u32;
+    if fraction > 0 {
+        out.push(b'.');
+        write_padded(out, fraction, 6);
+        while out.last() == Some(&b'0') {
+            out.pop();
         }
-        DataType::Uuid => {
-            uuid::Uuid::new_v4().to_string()
+    }
+}
+
+/// Writes epoch seconds as `YYYY-MM-DD HH:MM:SS[.fff]+00:00`, to microsecond
+/// precision; epochs outside chrono's range are written as numbers.
+fn write_timestamp(out: &mut Vec<u8>, epoch: f64) {
+    let micros = if is_whole(epoch) { epoch.trunc() * MICROS } else { (epoch * MICROS).round() };
+    let Some(ts) = chrono::DateTime::from_timestamp_micros(micros as i64) else {
+        return write_numeric(out, epoch);
+    };
+
+    let (date, time) = (ts.date_naive(), ts.time());
+    if !(0..=9999).contains(&date.year()) {
+        // chrono signs years outside four digits
+        let _ = write!(out, "{}", ts.format("%Y-%m-%d %H:%M:%S%.f+00:00"));
+        return;
+    }
+
+    write_padded(out, date.year() as u32, 4);
+    out.push(b'-');
+    write_padded(out, date.month(), 2);
+    out.push(b'-');
+    write_padded(out, date.day(), 2);
+    out.push(b' ');
+    write_padded(out, time.hour(), 2);
+    out.push(b':');
+    write_padded(out, time.minute(), 2);
+    out.push(b':');
+    write_padded(out, time.second(), 2);
+
+    // Same digits as chrono's `%.f`: none, milliseconds or microseconds
+    let micros = time.nanosecond() / 1_000;
+    if micros != 0 {
+        out.push(b'.');
+        if micros % 1_000 == 0 {
+            write_padded(out, micros / 1_000, 3);
+        } else {
+            write_padded(out, micros, 6);
+        }
+    }
+    out.extend_from_slice(b"+00:00");
+}
+
+fn write_padded(out: &mut Vec<u8>, value: u32, width: usize) {
+    let mut buffer = itoa::Buffer::new();
+    let digits = buffer.format(value);
+    out.extend(std::iter::repeat_n(b'0', width.saturating_sub(digits.len())));
+    out.extend_from_slice(digits.as_bytes());
+}
+
+pub fn synthesize_primary_key<R: Rng + ?Sized>(
+    data_type: &DataType,
+    counter: &mut i64,
+    rng: &mut R,
+) -> KeyValue {
+    match KeyKind::for_column(data_type) {
+        KeyKind::Uuid => {
+            let mut bytes = [0u8; 16];
+            rng.fill_bytes(&mut bytes);
+            KeyValue::Uuid(uuid::Builder::from_random_bytes(bytes).into_uuid())
+        }
+        KeyKind::Text => {
+            *counter += 1;
+            KeyValue::Text(counter.to_string())
         }
-        _ => {
-            // Fallback: treat as integer
+        KeyKind::Int => {
             *counter += 1;
-            counter.to_string()
+            KeyValue::Int(*counter)
         }
     }
 }
 
-pub fn synthesize_foreign_key(
-    parent_keys: &[String],
-    rng: &mut ThreadRng,
-) -> Result<String> {
+pub fn synthesize_foreign_key<'a, R: Rng + ?Sized>(
+    parent_keys: &'a KeyPool,
+    rng: &mut R,
+) -> Result<Cow<'a, KeyValue>> {
     parent_keys.choose(rng)
-        .map(|s| s.clone())
         .context("Parent key list is empty (should have been validated earlier)")
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::math::{Distribution, Histogram};
+    use crate::math::{Distribution, Histogram, PointMass};
+    use crate::schema::TypeBounds;
 
     #[test]
     fn test_inverse_transform_sampling() {
@@ -201,20 +679,20 @@ mod tests {
         let total = 100;
 
         // q=0.0 → should be near 0
-        let v1 = inverse_transform_sample(&bins, &frequencies, 0.0, total).unwrap();
-        assert!(v1 >= 0.0 && v1 < 50.0);
+        let v1 = inverse_transform_sample(&bins, &frequencies, BinScale::Linear, 0.0, total).unwrap();
+        assert!((0.0..50.0).contains(&v1));
 
         // q=0.25 → exactly at boundary (25/100)
-        let v2 = inverse_transform_sample(&bins, &frequencies, 0.25, total).unwrap();
+        let v2 = inverse_transform_sample(&bins, &frequencies, BinScale::Linear, 0.25, total).unwrap();
         assert!((v2 - 50.0).abs() < 0.1);
 
         // q=0.5 → middle of second bin
-        let v3 = inverse_transform_sample(&bins, &frequencies, 0.5, total).unwrap();
-        assert!(v3 >= 50.0 && v3 < 100.0);
+        let v3 = inverse_transform_sample(&bins, &frequencies, BinScale::Linear, 0.5, total).unwrap();
+        assert!((50.0..100.0).contains(&v3));
 
         // q=1.0 → near max
-        let v4 = inverse_transform_sample(&bins, &frequencies, 1.0, total).unwrap();
-        assert!(v4 >= 50.0 && v4 <= 100.0);
+        let v4 = inverse_transform_sample(&bins, &frequencies, BinScale::Linear, 1.0, total).unwrap();
+        assert!((50.0..=100.0).contains(&v4));
     }
 
     #[test]
@@ -230,30 +708,271 @@ mod tests {
             Histogram::Numeric {
                 bins: vec![0.0, 50.0, 100.0],
                 frequencies: vec![50, 50],
+                scale: BinScale::Linear,
             },
         );
 
         // With quantile=0.5, should be in upper half
-        let value = synthesize_value(&dist, &mut rng, Some(0.5)).unwrap();
-        let parsed: f64 = value.parse().unwrap();
-        assert!(parsed >= 25.0); // Should be around midpoint
+        let Value::Number(value) = synthesize_value(&dist, None, &mut rng, Some(0.5)).unwrap() else {
+            panic!("expected a number");
+        };
+        assert!(value >= 25.0); // Should be around midpoint
 
         // With quantile=1.0, should be near max
-        let value = synthesize_value(&dist, &mut rng, Some(1.0)).unwrap();
-        let parsed: f64 = value.parse().unwrap();
-        assert!(parsed >= 75.0);
+        let Value::Number(value) = synthesize_value(&dist, None, &mut rng, Some(1.0)).unwrap() else {
+            panic!("expected a number");
+        };
+        assert!(value >= 75.0);
     }
 
     #[test]
-    fn test_format_numeric_integer() {
-        assert_eq!(format_numeric(42.0), "42");
-        assert_eq!(format_numeric(100.0), "100");
+    fn test_outliers_emitted_at_clipped_rate() {
+        let mut dist = Distribution::new(Some(-100.0), Some(1e6), 0, 1000, 1000, Histogram::Numeric {
+            bins: vec![0.0, 100.0],
+            frequencies: vec![1000],
+            scale: BinScale::Linear,
+        });
+        let outliers = Outliers { lower: 0.0, upper: 100.0, below_share: 0.01, above_share: 0.02 };
+        dist.outliers = Some(outliers);
+        let mut rng = StdRng::seed_from_u64(3);
+
+        assert_eq!(synthesize_outlier(&dist, &outliers, &mut rng, Some(0.005)), Some(-50.0));
+        assert_eq!(synthesize_outlier(&dist, &outliers, &mut rng, Some(0.5)), None);
+        let high = synthesize_outlier(&dist, &outliers, &mut rng, Some(0.99)).unwrap();
+        assert!((100.0..=1e6).contains(&high));
+
+        let drawn: Vec<f64> = (0..20_000)
+            .filter_map(|_| synthesize_outlier(&dist, &outliers, &mut rng, None))
+            .collect();
+        let low = drawn.iter().filter(|&&v| v < 0.0).count() as f64 / 20_000.0;
+        let high = drawn.iter().filter(|&&v| v > 100.0).count() as f64 / 20_000.0;
+        assert!((low - 0.01).abs() < 0.004, "{}", low);
+        assert!((high - 0.02).abs() < 0.005, "{}", high);
+    }
+
+    #[test]
+    fn test_discrete_generates_observed_integers() {
+        let dist = Distribution::new(Some(1.0), Some(5.0), 0, 100, 3, Histogram::Discrete {
+            values: vec![1, 3, 5],
+            frequencies: vec![20, 30, 50],
+        });
+        let mut rng = StdRng::seed_from_u64(9);
+
+        let mut counts = BTreeMap::new();
+        for _ in 0..10_000 {
+            let Value::Number(value) = synthesize_value(&dist, None, &mut rng, None).unwrap() else {
+                panic!("expected a number");
+            };
+            *counts.entry(value as i64).or_insert(0) += 1;
+            assert_eq!(value.fract(), 0.0);
+        }
+        assert_eq!(counts.keys().copied().collect::<Vec<_>>(), vec![1, 3, 5]);
+        assert!((counts[&5] as f64 / 10_000.0 - 0.5).abs() < 0.02);
+
+        let Value::Number(value) = synthesize_value(&dist, None, &mut rng, Some(0.3)).unwrap() else {
+            panic!("expected a number");
+        };
+        assert_eq!(value, 3.0);
     }
 
     #[test]
-    fn test_format_numeric_float() {
-        let result = format_numeric(std::f64::consts::PI);
-        assert!(result.contains("3.14"));
+    fn test_point_masses_generated_at_their_share() {
+        let mut dist = Distribution::new(Some(0.0), Some(20.0), 0, 1000, 100, Histogram::Numeric {
+            bins: vec![10.0, 20.0],
+            frequencies: vec![100],
+            scale: BinScale::Linear,
+        });
+        dist.point_masses = vec![PointMass { value: 0.0, share: 0.9 }];
+        let mut rng = StdRng::seed_from_u64(2);
+
+        let values: Vec<f64> = (0..10_000)
+            .map(|_| match synthesize_value(&dist, None, &mut rng, None).unwrap() {
+                Value::Number(value) => value,
+                other => panic!("expected a number, got {:?}", other),
+            })
+            .collect();
+        let zeros = values.iter().filter(|&&v| v == 0.0).count() as f64 / 10_000.0;
+        assert!((zeros - 0.9).abs() < 0.01, "{}", zeros);
+        assert!(values.iter().all(|&v| v == 0.0 || (10.0..=20.0).contains(&v)));
+
+        // Copula quantiles keep their order across the mixture
+        let at = |q: f64| match synthesize_value(&dist, None, &mut StdRng::seed_from_u64(0), Some(q)).unwrap() {
+            Value::Number(value) => value,
+            other => panic!("expected a number, got {:?}", other),
+        };
+        assert_eq!(at(0.5), 0.0);
+        assert!((at(0.95) - 15.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_alias_sampler_matches_frequencies() {
+        let frequencies: BTreeMap<String, u64> = [("a", 1), ("b", 0), ("c", 3)]
+            .iter()
+            .map(|&(k, v)| (k.to_string(), v))
+            .collect();
+        let dist = Distribution::new(None, None, 0, 4, 3, Histogram::Categorical {
+            frequencies,
+            truncated: false,
+            long_tail: None,
+        });
+        let sampler = AliasSampler::for_distribution(&dist);
+        let mut rng = StdRng::seed_from_u64(4);
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for _ in 0..20_000 {
+            let Value::Text(text) = synthesize_value(&dist, sampler.as_ref(), &mut rng, None).unwrap() else {
+                panic!("expected text");
+            };
+            *counts.entry(text.into_owned()).or_default() += 1;
+        }
+        assert!(!counts.contains_key("b"));
+        assert!((counts["c"] as f64 / 20_000.0 - 0.75).abs() < 0.02, "{:?}", counts);
+
+        let dist = Distribution::new(Some(0.0), Some(10.0), 0, 4, 4, Histogram::Numeric {
+            bins: vec![0.0, 5.0, 10.0],
+            frequencies: vec![0, 4],
+            scale: BinScale::Linear,
+        });
+        let sampler = AliasSampler::for_distribution(&dist);
+        for _ in 0..100 {
+            let Value::Number(value) = synthesize_value(&dist, sampler.as_ref(), &mut rng, None).unwrap() else {
+                panic!("expected a number");
+            };
+            assert!((5.0..10.0).contains(&value));
+        }
+    }
+
+    #[test]
+    fn test_synthesize_bytes() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let value = render(synthesize_bytes(Value::Number(3.4), &mut rng), DataType::Bytea);
+        assert_eq!(value.len(), 3 + 6);
+        assert!(value.starts_with("\\\\x"));
+        assert!(value[3..].bytes().all(|b| b.is_ascii_hexdigit()));
+        assert_eq!(crate::schema::bytea_length(&crate::output::unescape_copy_text(&value)), 3);
+
+        assert_eq!(synthesize_bytes(Value::Null, &mut rng), Value::Null);
+    }
+
+    #[test]
+    fn test_write_interval_values() {
+        assert_eq!(render(Value::Number(273_600.0), DataType::Interval), "3 days 04:00:00");
+        assert_eq!(render(Value::Text("-1.5".into()), DataType::Interval), "-00:00:01.5");
+        assert_eq!(render(Value::Null, DataType::Interval), "\\N");
+    }
+
+    #[test]
+    fn test_synthesize_hstore() {
+        let frequencies = [(r#""a"=>"1""#, 5), (r#""b"=>"x\\y""#, 5)]
+            .into_iter()
+            .map(|(pair, count)| (pair.to_string(), count))
+            .collect();
+        let mut dist = Distribution::new(None, None, 0, 10, 2, Histogram::Categorical {
+            frequencies,
+            truncated: false,
+            long_tail: None,
+        });
+        let mut counts = crate::math::LengthDistribution::default();
+        counts.add(2);
+        dist.pair_counts = Some(counts);
+        let mut rng = StdRng::seed_from_u64(9);
+
+        let mut complete = 0;
+        for _ in 0..20 {
+            let first = synthesize_value(&dist, None, &mut rng, None).unwrap();
+            let Value::Text(text) = synthesize_hstore(first, &dist, None, &mut rng).unwrap() else {
+                panic!("expected text");
+            };
+            let pairs = crate::hstore::parse_hstore(&crate::output::unescape_copy_text(&text)).unwrap();
+            let mut keys: Vec<_> = pairs.iter().map(|(key, _)| key.as_str()).collect();
+            keys.sort();
+            keys.dedup();
+            assert_eq!(keys.len(), pairs.len(), "{}", text);
+            complete += (keys == ["a", "b"]) as usize;
+        }
+        assert!(complete >= 15, "{} of 20 values had both keys", complete);
+        assert_eq!(synthesize_hstore(Value::Null, &dist, None, &mut rng).unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn test_clamp_to_domain() {
+        let mut dist = Distribution::new(Some(0.0), Some(10.0), 0, 10, 10, Histogram::Numeric {
+            bins: vec![0.0, 10.0],
+            frequencies: vec![10],
+            scale: BinScale::Linear,
+        });
+        let mut quantity = Column::new("quantity".to_string(), DataType::Integer, false, false);
+        quantity.bounds = TypeBounds::for_pg_type("int2", None, None);
+
+        assert_eq!(clamp_to_domain(Value::Number(3.6), &dist, &quantity), Value::Number(4.0));
+        assert_eq!(clamp_to_domain(Value::Number(1e9), &dist, &quantity), Value::Number(32767.0));
+        assert_eq!(clamp_to_domain(Value::Number(-2.0), &dist, &quantity), Value::Number(-2.0));
+        dist.non_negative = true;
+        assert_eq!(clamp_to_domain(Value::Number(-2.0), &dist, &quantity), Value::Number(0.0));
+        assert_eq!(clamp_to_domain(Value::Null, &dist, &quantity), Value::Null);
+
+        let mut price = Column::new("price".to_string(), DataType::Float, false, false);
+        assert_eq!(clamp_to_domain(Value::Number(3.6), &dist, &price), Value::Number(3.6));
+        price.money = true;
+        assert_eq!(clamp_to_domain(Value::Number(3.456), &dist, &price), Value::Number(3.46));
+    }
+
+    #[test]
+    fn test_boolean_written_as_copy_literals() {
+        let bernoulli = crate::math::Bernoulli { true_count: 1, false_count: 1 };
+        let dist = Distribution::new(None, None, 0, 2, 2, Histogram::Boolean { bernoulli });
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..50 {
+            let value = render(synthesize_value(&dist, None, &mut rng, None).unwrap(), DataType::Boolean);
+            assert!(value == "t" || value == "f", "value {}", value);
+        }
+    }
+
+    #[test]
+    fn test_resolve_semantic_type() {
+        let dist = Distribution::new(None, None, 0, 2, 2, Histogram::Categorical {
+            frequencies: BTreeMap::from([("1 Main Street".to_string(), 2)]),
+            truncated: false,
+            long_tail: None,
+        });
+        let address = Column::new("address".to_string(), DataType::Text, true, false);
+        let notes = Column::new("notes".to_string(), DataType::Text, true, false);
+        let mut overrides = HashMap::new();
+
+        assert_eq!(resolve_semantic_type("users", &address, &dist, &overrides, true), Some(SemanticType::Address));
+        assert_eq!(resolve_semantic_type("users", &address, &dist, &overrides, false), None);
+        assert_eq!(resolve_semantic_type("users", &notes, &dist, &overrides, true), None);
+
+        overrides.insert("users.notes".to_string(), SemanticType::Company);
+        assert_eq!(resolve_semantic_type("users", &notes, &dist, &overrides, false), Some(SemanticType::Company));
+    }
+
+    fn render(value: Value, data_type: DataType) -> String {
+        let mut out = Vec::new();
+        write_value(&mut out, &value, &data_type);
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_write_numeric() {
+        assert_eq!(render(Value::Number(42.0), DataType::Integer), "42");
+        assert_eq!(render(Value::Number(-100.0), DataType::Integer), "-100");
+        assert_eq!(render(Value::Number(std::f64::consts::PI), DataType::Float), "3.141593");
+        assert_eq!(render(Value::Number(-0.25), DataType::Float), "-0.25");
+        assert_eq!(render(Value::Number(0.9999999), DataType::Float), "1");
+        assert_eq!(render(Value::Number(2.5e13), DataType::Float), "25000000000000");
+        assert_eq!(render(Value::Number(1.5e13 + 0.5), DataType::Float), "15000000000000.5");
+        assert_eq!(render(Value::Null, DataType::Float), "\\N");
+    }
+
+    #[test]
+    fn test_write_timestamp() {
+        assert_eq!(render(Value::Number(1704067200.0), DataType::Timestamp), "2024-01-01 00:00:00+00:00");
+        assert_eq!(render(Value::Number(86400.5), DataType::Timestamp), "1970-01-02 00:00:00.500+00:00");
+        assert_eq!(render(Value::Number(1.25e-4), DataType::Timestamp), "1970-01-01 00:00:00.000125+00:00");
+        assert_eq!(render(Value::from("1704067200".to_string()), DataType::Timestamp), "2024-01-01 00:00:00+00:00");
+        assert_eq!(render(Value::Null, DataType::Timestamp), "\\N");
     }
 
     #[test]
@@ -261,11 +980,11 @@ mod tests {
         use crate::schema::DataType;
         let mut counter = 0;
 
-        let pk1 = synthesize_primary_key(&DataType::Integer, &mut counter);
-        let pk2 = synthesize_primary_key(&DataType::Integer, &mut counter);
+        let pk1 = synthesize_primary_key(&DataType::Integer, &mut counter, &mut rand::thread_rng());
+        let pk2 = synthesize_primary_key(&DataType::Integer, &mut counter, &mut rand::thread_rng());
 
-        assert_eq!(pk1, "1");
-        assert_eq!(pk2, "2");
+        assert_eq!(pk1, KeyValue::Int(1));
+        assert_eq!(pk2, KeyValue::Int(2));
     }
 
     #[test]
@@ -273,19 +992,41 @@ mod tests {
         use crate::schema::DataType;
         let mut counter = 0;
 
-        let pk = synthesize_primary_key(&DataType::Uuid, &mut counter);
+        let pk = synthesize_primary_key(&DataType::Uuid, &mut counter, &mut rand::thread_rng());
 
         // Verify it's a valid UUID format
-        assert!(uuid::Uuid::parse_str(&pk).is_ok());
+        assert_eq!(pk.kind(), KeyKind::Uuid);
+        assert!(uuid::Uuid::parse_str(&pk.to_string()).is_ok());
     }
 
     #[test]
     fn test_synthesize_foreign_key() {
         let mut rng = rand::thread_rng();
-        let parent_keys = vec!["1".to_string(), "2".to_string(), "3".to_string()];
+        let keys = vec![KeyValue::Int(1), KeyValue::Int(2), KeyValue::Int(3)];
+        let parent_keys = KeyPool::new(keys.clone());
 
         let fk = synthesize_foreign_key(&parent_keys, &mut rng).unwrap();
 
-        assert!(parent_keys.contains(&fk));
+        assert!(keys.contains(&fk));
+    }
+
+    #[test]
+    fn test_generate_with_length_prefers_profiled_length() {
+        let mut rng = rand::thread_rng();
+        let mut dist = Distribution::new(None, None, 0, 10, 10, Histogram::Categorical {
+            frequencies: BTreeMap::new(),
+            truncated: false,
+            long_tail: None,
+        });
+        dist.lengths = Some(crate::math::LengthDistribution::from_values(&["abcd".to_string()]));
+
+        let mut calls = 0;
+        let value = generate_with_length(&dist, &mut rng, |_| {
+            calls += 1;
+            "x".repeat(calls)
+        });
+
+        assert_eq!(value, "xxxx");
+        assert_eq!(calls, 4);
     }
-}
\ No newline at end of file
+}
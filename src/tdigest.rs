@@ -0,0 +1,238 @@
+//! Merging t-digest (Dunning & Ertl) for streaming quantile estimation.
+//!
+//! Centroids near the tails stay small, so extreme quantiles of heavy-tailed columns
+//! (revenue, latencies) are kept far more accurately than by equal-width bins.
+
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_COMPRESSION: f64 = 200.0;
+
+/// Values buffered before they are merged into the centroid list.
+const BUFFER_SIZE: usize = 1_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Centroid {
+    pub mean: f64,
+    pub weight: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TDigest {
+    pub compression: f64,
+    pub centroids: Vec<Centroid>,
+    pub count: u64,
+    pub min: f64,
+    pub max: f64,
+
+    #[serde(skip)]
+    buffer: Vec<f64>,
+}
+
+impl Default for TDigest {
+    fn default() -> Self {
+        Self::new(DEFAULT_COMPRESSION)
+    }
+}
+
+impl TDigest {
+    pub fn new(compression: f64) -> Self {
+        Self {
+            compression,
+            centroids: Vec::new(),
+            count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            buffer: Vec::with_capacity(BUFFER_SIZE),
+        }
+    }
+
+    pub fn add(&mut self, value: f64) {
+        if !value.is_finite() {
+            return;
+        }
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.buffer.push(value);
+        if self.buffer.len() >= BUFFER_SIZE {
+            self.compress();
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Merges buffered values into the centroids. Call before reading or serializing.
+    pub fn compress(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let mut items: Vec<Centroid> = self.centroids.drain(..).collect();
+        items.extend(self.buffer.drain(..).map(|mean| Centroid { mean, weight: 1.0 }));
+        items.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+
+        let total: f64 = items.iter().map(|c| c.weight).sum();
+        let mut merged = Vec::with_capacity(items.len().min(self.compression as usize * 2));
+        let mut current = items[0];
+        let mut weight_so_far = 0.0;
+        let mut k_lower = self.scale(0.0);
+
+        for item in items.into_iter().skip(1) {
+            let q = (weight_so_far + current.weight + item.weight) / total;
+            if self.scale(q) - k_lower <= 1.0 {
+                let weight = current.weight + item.weight;
+                current.mean += (item.mean - current.mean) * item.weight / weight;
+                current.weight = weight;
+            } else {
+                weight_so_far += current.weight;
+                merged.push(current);
+                k_lower = self.scale(weight_so_far / total);
+                current = item;
+            }
+        }
+        merged.push(current);
+
+        self.centroids = merged;
+    }
+
+    /// k1 scale function: δ / 2π · asin(2q − 1).
+    fn scale(&self, q: f64) -> f64 {
+        self.compression / (2.0 * std::f64::consts::PI) * (2.0 * q.clamp(0.0, 1.0) - 1.0).asin()
+    }
+
+    fn total_weight(&self) -> f64 {
+        self.centroids.iter().map(|c| c.weight).sum()
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        let total = self.total_weight();
+        if total == 0.0 {
+            return None;
+        }
+        Some(self.centroids.iter().map(|c| c.mean * c.weight).sum::<f64>() / total)
+    }
+
+    /// Estimated q-quantile, interpolating between centroid centers and the exact min/max.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        let total = self.total_weight();
+        if total == 0.0 {
+            return None;
+        }
+
+        let target = q.clamp(0.0, 1.0) * total;
+        let (mut prev_position, mut prev_value) = (0.0, self.min);
+        let mut cumulative = 0.0;
+
+        for centroid in &self.centroids {
+            let position = cumulative + centroid.weight / 2.0;
+            if target <= position {
+                return Some(interpolate(target, prev_position, position, prev_value, centroid.mean));
+            }
+            prev_position = position;
+            prev_value = centroid.mean;
+            cumulative += centroid.weight;
+        }
+
+        Some(interpolate(target, prev_position, total, prev_value, self.max))
+    }
+
+    /// Estimated CDF at `x`; the inverse of [`TDigest::quantile`].
+    pub fn cdf(&self, x: f64) -> Option<f64> {
+        let total = self.total_weight();
+        if total == 0.0 {
+            return None;
+        }
+        if x <= self.min {
+            return Some(0.0);
+        }
+        if x >= self.max {
+            return Some(1.0);
+        }
+
+        let (mut prev_position, mut prev_value) = (0.0, self.min);
+        let mut cumulative = 0.0;
+
+        for centroid in &self.centroids {
+            let position = cumulative + centroid.weight / 2.0;
+            if x <= centroid.mean {
+                return Some(interpolate(x, prev_value, centroid.mean, prev_position, position) / total);
+            }
+            prev_position = position;
+            prev_value = centroid.mean;
+            cumulative += centroid.weight;
+        }
+
+        Some(interpolate(x, prev_value, self.max, prev_position, total) / total)
+    }
+}
+
+/// Maps `x` from [x0, x1] onto [y0, y1].
+fn interpolate(x: f64, x0: f64, x1: f64, y0: f64, y1: f64) -> f64 {
+    if x1 <= x0 {
+        return y1;
+    }
+    y0 + (y1 - y0) * ((x - x0) / (x1 - x0)).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_uniform_quantiles() {
+        let mut digest = TDigest::default();
+        for i in 0..=10_000 {
+            digest.add(i as f64);
+        }
+        digest.compress();
+
+        for q in [0.01, 0.25, 0.5, 0.75, 0.99] {
+            let estimate = digest.quantile(q).unwrap();
+            assert!((estimate - q * 10_000.0).abs() < 50.0, "q={} estimate={}", q, estimate);
+        }
+        assert_eq!(digest.quantile(0.0), Some(0.0));
+        assert_eq!(digest.quantile(1.0), Some(10_000.0));
+        assert!(digest.centroids.len() < 200);
+    }
+
+    #[test]
+    fn test_heavy_tail_p99() {
+        let mut rng = StdRng::seed_from_u64(9);
+        let mut values: Vec<f64> = (0..50_000)
+            .map(|_| {
+                let u: f64 = rng.gen_range(0.0..1.0);
+                // Pareto with alpha = 1.5
+                (1.0 - u).powf(-1.0 / 1.5)
+            })
+            .collect();
+
+        let mut digest = TDigest::default();
+        for &v in &values {
+            digest.add(v);
+        }
+        digest.compress();
+
+        values.sort_by(|a, b| a.total_cmp(b));
+        let exact = values[(0.99 * values.len() as f64) as usize];
+        let estimate = digest.quantile(0.99).unwrap();
+        assert!((estimate - exact).abs() / exact < 0.05, "exact={} estimate={}", exact, estimate);
+    }
+
+    #[test]
+    fn test_cdf_inverts_quantile() {
+        let mut digest = TDigest::default();
+        for i in 0..5_000 {
+            digest.add((i as f64).sqrt());
+        }
+        digest.compress();
+
+        for q in [0.1, 0.5, 0.9] {
+            let x = digest.quantile(q).unwrap();
+            assert!((digest.cdf(x).unwrap() - q).abs() < 0.01);
+        }
+    }
+}
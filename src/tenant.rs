@@ -0,0 +1,263 @@
+//! Multi-tenant schemas, where most tables carry a tenant column such as `tenant_id`.
+//!
+//! The scan counts every tenant's rows in each table holding the column and keeps
+//! those counts, without the tenant values, as a [`TenantProfile`]. Generation then
+//! draws as many source tenants as it is asked to generate and splits each table's
+//! rows among them in proportion to the drawn tenants' counts, so big tenants are big
+//! in every table. A [`TenantLayout`] records which rows each tenant owns, and foreign
+//! keys between tenant tables pick parent rows of the same tenant.
+
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use anyhow::{bail, Result};
+use rand::seq::index;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::schema::DataType;
+use crate::synth::KeyValue;
+
+/// Most source tenants whose counts are kept; larger sources keep a uniform sample.
+pub const MAX_TENANT_PROFILES: usize = 10_000;
+
+/// Per-tenant row counts of the tables holding the tenant column.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TenantProfile {
+    /// Column naming the tenant, the same in every tenant table.
+    pub column: String,
+
+    /// Tables holding the column, in the order of each tenant's counts.
+    pub tables: Vec<String>,
+
+    /// Rows in each of `tables` for every kept source tenant.
+    pub row_counts: Vec<Vec<u64>>,
+
+    /// Distinct tenants in the source, kept or not.
+    pub source_tenants: usize,
+}
+
+impl TenantProfile {
+    /// Builds the profile from each table's rows per tenant value. The values only
+    /// line tenants up across tables and are not kept. Tenants are ordered largest
+    /// first by their total rows.
+    pub fn from_counts<R: Rng + ?Sized>(
+        column: &str,
+        counts: Vec<(String, HashMap<String, u64>)>,
+        rng: &mut R,
+    ) -> Self {
+        let mut tenants: BTreeMap<&str, Vec<u64>> = BTreeMap::new();
+        for (i, (_, per_tenant)) in counts.iter().enumerate() {
+            for (tenant, &rows) in per_tenant {
+                tenants.entry(tenant).or_insert_with(|| vec![0; counts.len()])[i] = rows;
+            }
+        }
+
+        let source_tenants = tenants.len();
+        let mut row_counts: Vec<Vec<u64>> = tenants.into_values().collect();
+        if row_counts.len() > MAX_TENANT_PROFILES {
+            let kept = index::sample(rng, row_counts.len(), MAX_TENANT_PROFILES);
+            row_counts = kept.iter().map(|i| std::mem::take(&mut row_counts[i])).collect();
+        }
+        row_counts.sort_by_key(|rows| std::cmp::Reverse(rows.iter().sum::<u64>()));
+
+        Self {
+            column: column.to_string(),
+            tables: counts.into_iter().map(|(table, _)| table).collect(),
+            row_counts,
+            source_tenants,
+        }
+    }
+
+    /// Forgets `table`, e.g. once its tenant column is dropped.
+    pub fn remove_table(&mut self, table: &str) {
+        if let Some(i) = self.tables.iter().position(|t| t == table) {
+            self.tables.remove(i);
+            for rows in &mut self.row_counts {
+                rows.remove(i);
+            }
+        }
+    }
+}
+
+/// Rows each generated tenant owns in every tenant table, and its tenant column value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TenantLayout {
+    column: String,
+
+    /// Tenant column value of each generated tenant, when it isn't a parent's key.
+    ids: Vec<KeyValue>,
+
+    /// Tenant `k` owns rows `bounds[k]..bounds[k + 1]` of the table.
+    bounds: HashMap<String, Vec<usize>>,
+}
+
+impl TenantLayout {
+    /// Draws `tenants` source tenants from `profile` and splits each tenant table's
+    /// `rows_for` rows among them by the drawn tenants' counts. A tenant with rows in
+    /// the source gets at least one while rows are left to give. `root` is a table
+    /// referenced by the tenant column; its row `k` is tenant `k`. Without one, the
+    /// tenants are numbered, or named `tenant-<n>` for text and given random UUIDs for
+    /// uuid columns of type `id_type`.
+    pub fn new<R: Rng + ?Sized>(
+        profile: &TenantProfile,
+        tenants: usize,
+        rows_for: impl Fn(&str) -> usize,
+        root: Option<&str>,
+        id_type: &DataType,
+        rng: &mut R,
+    ) -> Result<Self> {
+        if profile.row_counts.is_empty() {
+            bail!("The genome's tenant profile holds no tenants");
+        }
+        if tenants == 0 {
+            bail!("At least one tenant must be generated");
+        }
+
+        let drawn: Vec<&Vec<u64>> = (0..tenants)
+            .map(|_| &profile.row_counts[rng.gen_range(0..profile.row_counts.len())])
+            .collect();
+        let mut bounds: HashMap<String, Vec<usize>> = profile
+            .tables
+            .iter()
+            .enumerate()
+            .map(|(i, table)| {
+                let weights: Vec<u64> = drawn.iter().map(|rows| rows[i]).collect();
+                (table.clone(), allocate(rows_for(table), &weights))
+            })
+            .collect();
+        if let Some(root) = root {
+            bounds.insert(root.to_string(), (0..=tenants).collect());
+        }
+
+        let ids = (0..tenants)
+            .map(|k| match id_type {
+                DataType::Uuid => KeyValue::Uuid(uuid::Builder::from_random_bytes(rng.r#gen()).into_uuid()),
+                DataType::Text => KeyValue::Text(format!("tenant-{}", k + 1)),
+                _ => KeyValue::Int(k as i64 + 1),
+            })
+            .collect();
+
+        Ok(Self { column: profile.column.clone(), ids, bounds })
+    }
+
+    pub fn column(&self) -> &str {
+        &self.column
+    }
+
+    pub fn tenants(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Whether rows of `table` belong to tenants.
+    pub fn is_scoped(&self, table: &str) -> bool {
+        self.bounds.contains_key(table)
+    }
+
+    /// Generated tenant owning row `row` of `table`.
+    pub fn tenant_of(&self, table: &str, row: usize) -> Option<usize> {
+        let bounds = self.bounds.get(table)?;
+        let tenant = bounds.partition_point(|&start| start <= row).checked_sub(1)?;
+        (tenant < self.ids.len()).then_some(tenant)
+    }
+
+    /// Rows of `table` owned by `tenant`.
+    pub fn rows(&self, table: &str, tenant: usize) -> Option<Range<usize>> {
+        let bounds = self.bounds.get(table)?;
+        Some(*bounds.get(tenant)?..*bounds.get(tenant + 1)?)
+    }
+
+    /// Tenant column value of `tenant`, for tables whose tenant column references no
+    /// parent.
+    pub fn id(&self, tenant: usize) -> Option<&KeyValue> {
+        self.ids.get(tenant)
+    }
+}
+
+/// Splits `total` rows by `weights` (largest remainder), giving every positive
+/// weight at least one row while there are enough, and returns the running bounds.
+/// All-zero weights split evenly.
+fn allocate(total: usize, weights: &[u64]) -> Vec<usize> {
+    let weights: Vec<f64> = match weights.iter().sum::<u64>() {
+        0 => vec![1.0; weights.len()],
+        _ => weights.iter().map(|&w| w as f64).collect(),
+    };
+    let positive = weights.iter().filter(|&&w| w > 0.0).count();
+    let floor = usize::from(total >= positive);
+    let spare = total - floor * positive;
+    let sum: f64 = weights.iter().sum();
+
+    let shares: Vec<f64> = weights.iter().map(|w| spare as f64 * w / sum).collect();
+    let mut rows: Vec<usize> = shares
+        .iter()
+        .zip(&weights)
+        .map(|(share, &w)| share.floor() as usize + if w > 0.0 { floor } else { 0 })
+        .collect();
+    let mut order: Vec<usize> = (0..rows.len()).collect();
+    order.sort_by(|&a, &b| (shares[b] - shares[b].floor()).total_cmp(&(shares[a] - shares[a].floor())));
+    let given: usize = rows.iter().sum();
+    for &i in order.iter().take(total - given) {
+        rows[i] += 1;
+    }
+
+    let mut bounds = Vec::with_capacity(rows.len() + 1);
+    bounds.push(0);
+    for count in rows {
+        bounds.push(bounds.last().copied().unwrap_or(0) + count);
+    }
+    bounds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile() -> TenantProfile {
+        let counts = vec![
+            ("users".to_string(), HashMap::from([("acme".to_string(), 90), ("tiny".to_string(), 10)])),
+            ("orders".to_string(), HashMap::from([("acme".to_string(), 900), ("tiny".to_string(), 100)])),
+        ];
+        TenantProfile::from_counts("tenant_id", counts, &mut rand::thread_rng())
+    }
+
+    #[test]
+    fn test_profile_drops_tenant_values() {
+        let profile = profile();
+        assert_eq!(profile.tables, ["users", "orders"]);
+        assert_eq!(profile.row_counts, [vec![90, 900], vec![10, 100]]);
+        assert_eq!(profile.source_tenants, 2);
+
+        let mut without = profile.clone();
+        without.remove_table("users");
+        assert_eq!(without.row_counts, [vec![900], vec![100]]);
+    }
+
+    #[test]
+    fn test_allocate_follows_weights() {
+        assert_eq!(allocate(100, &[3, 1]), [0, 75, 100]);
+        assert_eq!(allocate(10, &[0, 0]), [0, 5, 10]);
+        // Small tenants keep a row while there are enough
+        assert_eq!(allocate(3, &[1000, 1, 0]), [0, 2, 3, 3]);
+        assert_eq!(allocate(1, &[5, 5]), [0, 1, 1]);
+    }
+
+    #[test]
+    fn test_layout_keeps_tenant_sizes_consistent() -> Result<()> {
+        let profile = profile();
+        let rows_for = |table: &str| if table == "users" { 1000 } else { 10_000 };
+        let layout = TenantLayout::new(&profile, 20, rows_for, Some("tenants"), &DataType::Integer, &mut rand::thread_rng())?;
+
+        assert_eq!(layout.tenants(), 20);
+        assert!(layout.is_scoped("users") && layout.is_scoped("tenants") && !layout.is_scoped("audit"));
+        for tenant in 0..20 {
+            let users = layout.rows("users", tenant).unwrap().len();
+            let orders = layout.rows("orders", tenant).unwrap().len();
+            assert!(users > 0 && orders.abs_diff(users * 10) <= 20, "{} users, {} orders", users, orders);
+            assert_eq!(layout.rows("tenants", tenant), Some(tenant..tenant + 1));
+        }
+        assert_eq!(layout.rows("users", 19).unwrap().end, 1000);
+        assert_eq!(layout.tenant_of("orders", 0), Some(0));
+        assert_eq!(layout.tenant_of("orders", 9_999), Some(19));
+        assert_eq!(layout.tenant_of("orders", 10_000), None);
+        assert_eq!(layout.id(2), Some(&KeyValue::Int(3)));
+        Ok(())
+    }
+}
@@ -0,0 +1,581 @@
+//! Full-screen progress dashboard for `--tui`.
+//!
+//! A [`Dashboard`] receives scan and generation progress for every table, along with
+//! the warnings collected by a [`WarningLayer`]. [`Dashboard::show`] draws it on
+//! stderr with ratatui, so generated SQL can still be piped from stdout, until the
+//! returned [`TuiSession`] is finished. Finishing restores the terminal and prints a
+//! summary of the run.
+
+use std::collections::HashMap;
+use std::fmt::{self, Write as _};
+use std::io::{self, IsTerminal, Stderr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use anyhow::{bail, Context, Result};
+use ratatui::backend::{Backend, CrosstermBackend};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Block, Cell, Gauge, List, ListItem, Row, Table};
+use ratatui::{Frame, Terminal};
+use tracing::field::{Field, Visit};
+use tracing::{Event as TracingEvent, Level, Subscriber};
+use tracing_subscriber::layer::{Context as LayerContext, Layer};
+use crate::metrics::Phase;
+#[cfg(feature = "postgres")]
+use crate::metrics::TableMetrics;
+#[cfg(feature = "postgres")]
+use crate::scanner::ScanProgress;
+use crate::synth::GenerationProgress;
+
+/// Time between two redraws.
+const REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Warnings shown in the summary; the dashboard shows as many as fit.
+const SUMMARY_WARNINGS: usize = 20;
+
+/// Slowest tables listed in the summary.
+const SUMMARY_SLOWEST: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TableStatus {
+    Running,
+    Waiting,
+    Failed,
+    Done,
+}
+
+impl fmt::Display for TableStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableStatus::Running => f.pad("running"),
+            TableStatus::Waiting => f.pad("waiting"),
+            TableStatus::Failed => f.pad("failed"),
+            TableStatus::Done => f.pad("done"),
+        }
+    }
+}
+
+/// One table of one phase, as the dashboard shows it.
+#[derive(Debug, Clone)]
+pub struct TableProgress {
+    pub phase: Phase,
+    pub name: String,
+    pub status: TableStatus,
+    pub rows: u64,
+    /// Rows to generate, or the planner's estimate for scans.
+    pub expected_rows: Option<u64>,
+    /// Mean reservoir fill of a profiled table.
+    pub reservoir_fill: Option<f64>,
+    pub error: Option<String>,
+    started: Option<Instant>,
+    /// Fixed once the table is done or failed.
+    duration: Option<Duration>,
+}
+
+impl TableProgress {
+    fn new(phase: Phase, name: &str) -> Self {
+        Self {
+            phase,
+            name: name.to_string(),
+            status: TableStatus::Waiting,
+            rows: 0,
+            expected_rows: None,
+            reservoir_fill: None,
+            error: None,
+            started: None,
+            duration: None,
+        }
+    }
+
+    fn start(&mut self) {
+        self.status = TableStatus::Running;
+        self.started = Some(Instant::now());
+    }
+
+    fn stop(&mut self, status: TableStatus) {
+        self.status = status;
+        self.duration = self.started.map(|started| started.elapsed());
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.duration
+            .or_else(|| self.started.map(|started| started.elapsed()))
+            .unwrap_or_default()
+    }
+
+    pub fn rows_per_second(&self) -> Option<f64> {
+        let seconds = self.elapsed().as_secs_f64();
+        (seconds > 0.0).then(|| self.rows as f64 / seconds)
+    }
+
+    /// Share of the expected rows done, in [0, 1].
+    pub fn ratio(&self) -> Option<f64> {
+        match self.status {
+            TableStatus::Done => Some(1.0),
+            _ => self
+                .expected_rows
+                .filter(|&expected| expected > 0)
+                .map(|expected| (self.rows as f64 / expected as f64).min(1.0)),
+        }
+    }
+}
+
+/// Everything the dashboard shows, in the order tables were first reported.
+#[derive(Debug, Clone, Default)]
+pub struct DashboardState {
+    pub tables: Vec<TableProgress>,
+    pub warnings: Vec<String>,
+}
+
+impl DashboardState {
+    /// Tables and warnings first reported after `mark`, a count of each taken earlier.
+    fn since(mut self, (tables, warnings): (usize, usize)) -> Self {
+        self.tables.drain(..tables.min(self.tables.len()));
+        self.warnings.drain(..warnings.min(self.warnings.len()));
+        self
+    }
+
+    /// `(done, total, rows, elapsed)` of `phase`, or `None` if no table of it was reported.
+    fn phase_totals(&self, phase: Phase) -> Option<(usize, usize, u64, Duration)> {
+        let tables: Vec<&TableProgress> = self.tables.iter().filter(|t| t.phase == phase).collect();
+        if tables.is_empty() {
+            return None;
+        }
+        let done = tables.iter().filter(|t| t.status == TableStatus::Done).count();
+        let rows = tables.iter().map(|t| t.rows).sum();
+        let started = tables.iter().filter_map(|t| t.started).min();
+        let finished = tables
+            .iter()
+            .filter_map(|t| Some(t.started? + t.duration?))
+            .max();
+        let elapsed = match (started, finished) {
+            (Some(started), Some(finished)) if done == tables.len() => finished - started,
+            (Some(started), _) => started.elapsed(),
+            _ => Duration::ZERO,
+        };
+        Some((done, tables.len(), rows, elapsed))
+    }
+}
+
+/// Post-run summary: totals per phase, the slowest tables, failures and warnings.
+impl fmt::Display for DashboardState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for phase in [Phase::Scan, Phase::Gen] {
+            let Some((done, total, rows, elapsed)) = self.phase_totals(phase) else {
+                continue;
+            };
+            let seconds = elapsed.as_secs_f64();
+            let rate = if seconds > 0.0 { rows as f64 / seconds } else { 0.0 };
+            writeln!(
+                f,
+                "{}: {}/{} tables, {} rows in {:.1}s ({:.0} rows/s)",
+                phase, done, total, rows, seconds, rate
+            )?;
+
+            let mut slowest: Vec<&TableProgress> = self
+                .tables
+                .iter()
+                .filter(|t| t.phase == phase && t.status == TableStatus::Done)
+                .collect();
+            slowest.sort_by_key(|t| std::cmp::Reverse(t.elapsed()));
+            for table in slowest.iter().take(SUMMARY_SLOWEST) {
+                writeln!(f, "    {}: {} rows in {:.1}s", table.name, table.rows, table.elapsed().as_secs_f64())?;
+            }
+        }
+
+        for table in self.tables.iter().filter(|t| t.status == TableStatus::Failed) {
+            writeln!(f, "{} {} failed: {}", table.phase, table.name, table.error.as_deref().unwrap_or("unknown error"))?;
+        }
+
+        if !self.warnings.is_empty() {
+            writeln!(f, "{} warnings:", self.warnings.len())?;
+            for warning in self.warnings.iter().take(SUMMARY_WARNINGS) {
+                writeln!(f, "    {}", warning)?;
+            }
+            if self.warnings.len() > SUMMARY_WARNINGS {
+                writeln!(f, "    ... and {} more", self.warnings.len() - SUMMARY_WARNINGS)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Collects progress from scan and generation tasks; shared with the drawing thread.
+#[derive(Debug, Default)]
+pub struct Dashboard {
+    state: Mutex<DashboardState>,
+    /// Position of each `(phase, table)` in `state.tables`.
+    index: Mutex<HashMap<(Phase, String), usize>>,
+}
+
+impl Dashboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A copy of the current state.
+    pub fn snapshot(&self) -> DashboardState {
+        self.state.lock().map(|state| state.clone()).unwrap_or_default()
+    }
+
+    pub fn warn(&self, message: String) {
+        if let Ok(mut state) = self.state.lock() {
+            state.warnings.push(message);
+        }
+    }
+
+    /// Applies `update` to the table, adding it first if it wasn't reported yet.
+    fn update(&self, phase: Phase, table: &str, update: impl FnOnce(&mut TableProgress)) {
+        let (Ok(mut state), Ok(mut index)) = (self.state.lock(), self.index.lock()) else {
+            return;
+        };
+        let position = *index.entry((phase, table.to_string())).or_insert_with(|| {
+            state.tables.push(TableProgress::new(phase, table));
+            state.tables.len() - 1
+        });
+        update(&mut state.tables[position]);
+    }
+
+    /// Takes over the terminal and redraws the dashboard until the session is finished.
+    /// Ctrl-C or `q` restores the terminal and exits, as raw mode swallows the signal.
+    pub fn show(self: &Arc<Self>) -> Result<TuiSession> {
+        if !io::stderr().is_terminal() {
+            bail!("--tui needs a terminal on stderr");
+        }
+
+        enable_raw_mode().context("Failed to enable raw terminal mode")?;
+        execute!(io::stderr(), EnterAlternateScreen).context("Failed to enter the alternate screen")?;
+        let terminal = match Terminal::new(CrosstermBackend::new(io::stderr())) {
+            Ok(terminal) => terminal,
+            Err(e) => {
+                restore_terminal();
+                return Err(e).context("Failed to set up the terminal");
+            }
+        };
+
+        let state = self.snapshot();
+        let mark = (state.tables.len(), state.warnings.len());
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = std::thread::spawn({
+            let dashboard = Arc::clone(self);
+            let stop = Arc::clone(&stop);
+            move || draw_loop(terminal, &dashboard, &stop)
+        });
+
+        Ok(TuiSession {
+            dashboard: Arc::clone(self),
+            mark,
+            stop,
+            handle: Some(handle),
+        })
+    }
+}
+
+impl GenerationProgress for Dashboard {
+    fn table_started(&self, table: &str, rows: usize) {
+        self.update(Phase::Gen, table, |t| {
+            t.expected_rows = Some(rows as u64);
+            t.start();
+        });
+    }
+
+    fn rows_generated(&self, table: &str, rows: usize) {
+        self.update(Phase::Gen, table, |t| t.rows += rows as u64);
+    }
+
+    fn table_finished(&self, table: &str) {
+        self.update(Phase::Gen, table, |t| t.stop(TableStatus::Done));
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl ScanProgress for Dashboard {
+    fn table_queued(&self, table: &str, estimated_rows: Option<u64>) {
+        self.update(Phase::Scan, table, |t| t.expected_rows = estimated_rows);
+    }
+
+    fn table_started(&self, table: &str) {
+        self.update(Phase::Scan, table, |t| {
+            t.rows = 0;
+            t.start();
+        });
+    }
+
+    fn rows_scanned(&self, table: &str, rows: u64) {
+        self.update(Phase::Scan, table, |t| t.rows = rows);
+    }
+
+    fn table_finished(&self, table: &str, metrics: &TableMetrics) {
+        self.update(Phase::Scan, table, |t| {
+            t.rows = metrics.rows;
+            t.reservoir_fill = metrics.reservoir_fill;
+            t.stop(TableStatus::Done);
+        });
+    }
+
+    fn table_failed(&self, table: &str, error: &str) {
+        self.update(Phase::Scan, table, |t| {
+            t.error = Some(error.to_string());
+            t.stop(TableStatus::Failed);
+        });
+    }
+}
+
+/// The dashboard on screen. Dropping it restores the terminal without a summary.
+pub struct TuiSession {
+    dashboard: Arc<Dashboard>,
+    /// Tables and warnings reported before the session, left out of its summary.
+    mark: (usize, usize),
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<Result<()>>>,
+}
+
+impl TuiSession {
+    /// Restores the terminal and prints a summary of the session on stderr.
+    pub fn finish(mut self) -> Result<()> {
+        self.close()?;
+        eprint!("{}", self.dashboard.snapshot().since(self.mark));
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.stop.store(true, Ordering::Relaxed);
+        match self.handle.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => bail!("Dashboard thread panicked"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for TuiSession {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+fn draw_loop(mut terminal: Terminal<CrosstermBackend<Stderr>>, dashboard: &Dashboard, stop: &AtomicBool) -> Result<()> {
+    let started = Instant::now();
+    let result = (|| {
+        while !stop.load(Ordering::Relaxed) {
+            draw(&mut terminal, &dashboard.snapshot(), started.elapsed())?;
+            if event::poll(REFRESH_INTERVAL)?
+                && let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+                && (key.code == KeyCode::Char('q')
+                    || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)))
+            {
+                restore_terminal();
+                eprint!("{}", dashboard.snapshot());
+                eprintln!("Interrupted");
+                std::process::exit(130);
+            }
+        }
+        // Last frame with the final counts
+        draw(&mut terminal, &dashboard.snapshot(), started.elapsed())
+    })();
+    restore_terminal();
+    result
+}
+
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(io::stderr(), LeaveAlternateScreen);
+}
+
+fn draw<B: Backend>(terminal: &mut Terminal<B>, state: &DashboardState, elapsed: Duration) -> Result<()> {
+    terminal
+        .draw(|frame| render(frame, state, elapsed))
+        .context("Failed to draw the dashboard")?;
+    Ok(())
+}
+
+/// Overall gauge on top, the tables (running first) in the middle and the latest
+/// warnings at the bottom.
+fn render(frame: &mut Frame, state: &DashboardState, elapsed: Duration) {
+    let [header, body, footer] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(5),
+        Constraint::Length(8),
+    ])
+    .areas(frame.area());
+
+    let done = state.tables.iter().filter(|t| t.status == TableStatus::Done).count();
+    let rows: u64 = state.tables.iter().map(|t| t.rows).sum();
+    let ratio = if state.tables.is_empty() { 0.0 } else { done as f64 / state.tables.len() as f64 };
+    let gauge = Gauge::default()
+        .block(Block::bordered().title(" replica_db (q to quit) "))
+        .gauge_style(Style::default().fg(Color::Cyan))
+        .ratio(ratio)
+        .label(format!(
+            "{}/{} tables, {} rows, {}s",
+            done,
+            state.tables.len(),
+            rows,
+            elapsed.as_secs()
+        ));
+    frame.render_widget(gauge, header);
+
+    let mut tables: Vec<&TableProgress> = state.tables.iter().collect();
+    tables.sort_by_key(|t| t.status);
+    let table_rows = tables.into_iter().map(|t| {
+        let style = match t.status {
+            TableStatus::Running => Style::default().fg(Color::Yellow),
+            TableStatus::Failed => Style::default().fg(Color::Red),
+            TableStatus::Done => Style::default().fg(Color::Green),
+            TableStatus::Waiting => Style::default().fg(Color::DarkGray),
+        };
+        Row::new(vec![
+            Cell::from(t.phase.to_string()),
+            Cell::from(t.name.clone()),
+            Cell::from(t.status.to_string()),
+            Cell::from(t.rows.to_string()),
+            Cell::from(t.ratio().map(|r| format!("{:.0}%", r * 100.0)).unwrap_or_default()),
+            Cell::from(t.rows_per_second().map(|r| format!("{:.0}", r)).unwrap_or_default()),
+            Cell::from(t.reservoir_fill.map(|f| format!("{:.0}%", f * 100.0)).unwrap_or_default()),
+            Cell::from(format!("{:.1}s", t.elapsed().as_secs_f64())),
+        ])
+        .style(style)
+    });
+    let widths = [
+        Constraint::Length(5),
+        Constraint::Min(20),
+        Constraint::Length(8),
+        Constraint::Length(12),
+        Constraint::Length(5),
+        Constraint::Length(10),
+        Constraint::Length(9),
+        Constraint::Length(8),
+    ];
+    let table = Table::new(table_rows, widths)
+        .header(
+            Row::new(["phase", "table", "status", "rows", "done", "rows/s", "reservoir", "time"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().title(" tables "));
+    frame.render_widget(table, body);
+
+    let shown = footer.height.saturating_sub(2) as usize;
+    let warnings: Vec<ListItem> = state
+        .warnings
+        .iter()
+        .rev()
+        .take(shown)
+        .rev()
+        .map(|w| ListItem::new(w.as_str()))
+        .collect();
+    let list = List::new(warnings)
+        .style(Style::default().fg(Color::Yellow))
+        .block(Block::bordered().title(format!(" warnings ({}) ", state.warnings.len())));
+    frame.render_widget(list, footer);
+}
+
+/// Tracing layer that hands each WARN and ERROR event, as one line, to a sink such as
+/// [`Dashboard::warn`] or [`RunMetrics::record_warning`](crate::metrics::RunMetrics::record_warning).
+/// With the dashboard on screen it replaces the log output, since log lines written
+/// to stderr would tear through it.
+pub struct WarningLayer {
+    sink: Box<dyn Fn(String) + Send + Sync>,
+}
+
+impl WarningLayer {
+    pub fn new(sink: impl Fn(String) + Send + Sync + 'static) -> Self {
+        Self { sink: Box::new(sink) }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for WarningLayer {
+    fn on_event(&self, event: &TracingEvent<'_>, _ctx: LayerContext<'_, S>) {
+        if *event.metadata().level() > Level::WARN {
+            return;
+        }
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        (self.sink)(visitor.finish());
+    }
+}
+
+/// `message (field=value, ...)` of an event.
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: Vec<String>,
+}
+
+impl MessageVisitor {
+    fn finish(self) -> String {
+        if self.fields.is_empty() {
+            return self.message;
+        }
+        let mut line = self.message;
+        let _ = write!(line, " ({})", self.fields.join(", "));
+        line
+    }
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => self.message = value.to_string(),
+            name => self.fields.push(format!("{}={}", name, value)),
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => self.message = format!("{:?}", value),
+            name => self.fields.push(format!("{}={:?}", name, value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+
+    #[test]
+    fn test_dashboard_tracks_generation() {
+        let dashboard = Dashboard::new();
+        GenerationProgress::table_started(&dashboard, "users", 20_000);
+        dashboard.rows_generated("users", 10_000);
+        GenerationProgress::table_started(&dashboard, "orders", 40_000);
+        dashboard.rows_generated("users", 10_000);
+        GenerationProgress::table_finished(&dashboard, "users");
+        dashboard.warn("Something odd (table=orders)".to_string());
+
+        let state = dashboard.snapshot();
+        let users = &state.tables[0];
+        assert_eq!((users.name.as_str(), users.rows, users.status), ("users", 20_000, TableStatus::Done));
+        assert_eq!(users.ratio(), Some(1.0));
+        assert_eq!(state.tables[1].status, TableStatus::Running);
+        assert_eq!(state.tables[1].ratio(), Some(0.0));
+
+        let summary = state.to_string();
+        assert!(summary.starts_with("gen: 1/2 tables, 20000 rows"));
+        assert!(summary.contains("1 warnings:\n    Something odd (table=orders)"));
+    }
+
+    #[test]
+    fn test_render_lists_tables_and_warnings() -> Result<()> {
+        let dashboard = Dashboard::new();
+        GenerationProgress::table_started(&dashboard, "invoices", 1_000);
+        dashboard.warn("Table is empty".to_string());
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 20))?;
+        draw(&mut terminal, &dashboard.snapshot(), Duration::from_secs(3))?;
+
+        let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+        assert!(screen.contains("0/1 tables, 0 rows, 3s"));
+        assert!(screen.contains("invoices"));
+        assert!(screen.contains("running"));
+        assert!(screen.contains("warnings (1)"));
+        assert!(screen.contains("Table is empty"));
+        Ok(())
+    }
+}